@@ -0,0 +1,227 @@
+//! Benchmarks comparing the crate's ray query APIs on the same rays: one
+//! `intersect` call per ray, packets of 4, 8 and 16 rays, and AoS and SoA
+//! ray streams. The scenes are generated by the `testing` module's
+//! deterministic generator as stand-ins for standard test scenes: a dense
+//! scene of many small objects, similar in triangle count to Crytek Sponza,
+//! and the same kind of scene built from instances.
+//!
+//! The rays are primary rays from a pinhole camera over the scene, traced
+//! in tiles as a renderer would. Each sample traces a fresh copy of the
+//! rays, the copy isn't timed. Results can be tracked over releases with
+//! criterion's baselines, e.g. `cargo bench -- --save-baseline 0.3.8`
+//! before a release and `cargo bench -- --baseline 0.3.8` to compare the
+//! next one against it.
+
+#[macro_use]
+extern crate criterion;
+extern crate cgmath;
+extern crate embree;
+
+use std::mem;
+
+use cgmath::{InnerSpace, Vector3};
+use criterion::{BatchSize, Criterion, Throughput};
+use embree::sys::{RTCRayHit16, RTCRayHit8};
+use embree::testing::{self, SceneConfig};
+use embree::{
+    CommittedScene, Device, IntersectContext, Ray, Ray4, RayHit, RayHit4, RayHitN, RayN, Tile,
+};
+
+const TILE_SIZE: u32 = 64;
+const IMAGE_SIZE: f32 = 512.0;
+
+/// A pinhole camera looking at the center of the generated scenes
+fn camera(x: f32, y: f32) -> (Vector3<f32>, Vector3<f32>) {
+    let origin = Vector3::new(0.0, 0.0, 30.0);
+    let u = 2.0 * x / IMAGE_SIZE - 1.0;
+    let v = 1.0 - 2.0 * y / IMAGE_SIZE;
+    let dir = Vector3::new(0.6 * u, 0.6 * v, -1.0).normalize();
+    (origin, dir)
+}
+
+/// Get the rays of the tiles covering the center of the image, in the
+/// order `RayN::fill_primary` places them
+fn primary_rays(tiles: &[Tile]) -> Vec<Ray> {
+    tiles
+        .iter()
+        .flat_map(|t| t.pixels())
+        .map(|(i, j)| {
+            let (o, d) = camera(i as f32 + 0.5, j as f32 + 0.5);
+            Ray::new(o, d)
+        })
+        .collect()
+}
+
+macro_rules! wide_packets {
+    ($name:ident, $packet:ty, $width:expr) => {
+        fn $name(rays: &[Ray]) -> Vec<$packet> {
+            rays.chunks($width)
+                .map(|chunk| {
+                    let mut p: $packet = unsafe { mem::zeroed() };
+                    for (i, r) in chunk.iter().enumerate() {
+                        p.ray.org_x[i] = r.org_x;
+                        p.ray.org_y[i] = r.org_y;
+                        p.ray.org_z[i] = r.org_z;
+                        p.ray.dir_x[i] = r.dir_x;
+                        p.ray.dir_y[i] = r.dir_y;
+                        p.ray.dir_z[i] = r.dir_z;
+                        p.ray.tnear[i] = r.tnear;
+                        p.ray.tfar[i] = r.tfar;
+                        p.ray.mask[i] = r.mask;
+                    }
+                    p.hit.geomID = [u32::MAX; $width];
+                    p.hit.instID[0] = [u32::MAX; $width];
+                    p
+                })
+                .collect()
+        }
+    };
+}
+
+wide_packets!(packets8, RTCRayHit8, 8);
+wide_packets!(packets16, RTCRayHit16, 16);
+
+fn packets4(rays: &[Ray]) -> Vec<RayHit4> {
+    rays.chunks(4)
+        .map(|c| {
+            RayHit4::new(Ray4::new(
+                [c[0].origin(), c[1].origin(), c[2].origin(), c[3].origin()],
+                [c[0].dir(), c[1].dir(), c[2].dir(), c[3].dir()],
+            ))
+        })
+        .collect()
+}
+
+fn bench_queries(c: &mut Criterion, name: &str, scene: &CommittedScene) {
+    let tiles: Vec<Tile> = (0..4)
+        .map(|t| {
+            let x = 128 + (t % 2) * TILE_SIZE;
+            let y = 128 + (t / 2) * TILE_SIZE;
+            Tile::new(x, y, TILE_SIZE, TILE_SIZE)
+        })
+        .collect();
+    let rays = primary_rays(&tiles);
+    let tile_rays = (TILE_SIZE * TILE_SIZE) as usize;
+
+    let mut group = c.benchmark_group(name);
+    group.throughput(Throughput::Elements(rays.len() as u64));
+    group.bench_function("intersect1", |b| {
+        let ray_hits: Vec<RayHit> = rays.iter().map(|r| RayHit::new(*r)).collect();
+        b.iter_batched_ref(
+            || ray_hits.clone(),
+            |ray_hits| {
+                let mut ctx = IntersectContext::coherent();
+                for r in ray_hits.iter_mut() {
+                    scene.intersect(&mut ctx, r);
+                }
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.bench_function("packet4", |b| {
+        let packets = packets4(&rays);
+        b.iter_batched_ref(
+            || packets.clone(),
+            |packets| {
+                let mut ctx = IntersectContext::coherent();
+                for p in packets.iter_mut() {
+                    scene.intersect4(&mut ctx, p, &[-1; 4]);
+                }
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.bench_function("packet8", |b| {
+        let packets = packets8(&rays);
+        b.iter_batched_ref(
+            || packets.clone(),
+            |packets| {
+                let mut ctx = IntersectContext::coherent();
+                for p in packets.iter_mut() {
+                    scene.intersect8(&mut ctx, p, &[-1; 8]);
+                }
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.bench_function("packet16", |b| {
+        let packets = packets16(&rays);
+        b.iter_batched_ref(
+            || packets.clone(),
+            |packets| {
+                let mut ctx = IntersectContext::coherent();
+                for p in packets.iter_mut() {
+                    scene.intersect16(&mut ctx, p, &[-1; 16]);
+                }
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.bench_function("stream_aos", |b| {
+        let streams: Vec<Vec<RayHit>> = rays
+            .chunks(tile_rays)
+            .map(|tile| tile.iter().map(|r| RayHit::new(*r)).collect())
+            .collect();
+        b.iter_batched_ref(
+            || streams.clone(),
+            |streams| {
+                let mut ctx = IntersectContext::coherent();
+                for s in streams.iter_mut() {
+                    scene.intersect_stream_aos(&mut ctx, s);
+                }
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.bench_function("stream_soa", |b| {
+        b.iter_batched_ref(
+            || {
+                tiles
+                    .iter()
+                    .map(|t| {
+                        let mut s = RayHitN::new(RayN::new(tile_rays));
+                        s.fill_primary(camera, t, 0.0, u32::MAX);
+                        s
+                    })
+                    .collect::<Vec<_>>()
+            },
+            |streams| {
+                let mut ctx = IntersectContext::coherent();
+                for s in streams.iter_mut() {
+                    scene.intersect_stream_soa(&mut ctx, s);
+                }
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+fn dense_scene(c: &mut Criterion) {
+    let device = Device::new();
+    // 262k triangles, about as many as Crytek Sponza
+    let config = SceneConfig::new()
+        .spheres(192)
+        .meshes(64)
+        .resolution(16)
+        .seed(1);
+    let scene = testing::generate_scene(&device, &config, None);
+    bench_queries(c, "dense", &scene.commit());
+}
+
+fn instanced_scene(c: &mut Criterion) {
+    let device = Device::new();
+    let prototype = testing::prototype_scene(&device, 32);
+    let prototype = prototype.commit();
+    let config = SceneConfig::new()
+        .spheres(0)
+        .meshes(16)
+        .instances(512)
+        .resolution(16)
+        .seed(2);
+    let scene = testing::generate_scene(&device, &config, Some(&prototype));
+    bench_queries(c, "instanced", &scene.commit());
+}
+
+criterion_group!(benches, dense_scene, instanced_scene);
+criterion_main!(benches);
@@ -0,0 +1,147 @@
+#![allow(dead_code)]
+
+extern crate cgmath;
+extern crate embree;
+extern crate image;
+extern crate support;
+extern crate tobj;
+
+use std::path::Path;
+
+use cgmath::{InnerSpace, Vector3};
+use embree::{Bounds, Device, Geometry, IntersectContext, Ray, RayHit, Scene, TriangleMesh};
+use image::RgbImage;
+use support::{Camera, AABB};
+
+/// Draw the line between the pixels a and b into the image
+fn draw_line(image: &mut RgbImage, a: (f32, f32), b: (f32, f32), color: [u8; 3]) {
+    let steps = (b.0 - a.0).abs().max((b.1 - a.1).abs()).ceil() as usize;
+    // Skip lines which are far off screen
+    if steps > 8 * image.width() as usize {
+        return;
+    }
+    for s in 0..steps + 1 {
+        let t = if steps > 0 {
+            s as f32 / steps as f32
+        } else {
+            0.0
+        };
+        let x = a.0 + (b.0 - a.0) * t;
+        let y = a.1 + (b.1 - a.1) * t;
+        if x >= 0.0 && y >= 0.0 && (x as u32) < image.width() && (y as u32) < image.height() {
+            image.get_pixel_mut(x as u32, y as u32).0 = color;
+        }
+    }
+}
+
+/// Draw the wireframe of the box into the image
+fn draw_box(image: &mut RgbImage, camera: &Camera, b: &Bounds, color: [u8; 3]) {
+    let corner = |i: usize| {
+        Vector3::new(
+            if i & 1 == 0 { b.lower_x } else { b.upper_x },
+            if i & 2 == 0 { b.lower_y } else { b.upper_y },
+            if i & 4 == 0 { b.lower_z } else { b.upper_z },
+        )
+    };
+    for i in 0..8 {
+        for axis in &[1, 2, 4] {
+            // Each edge connects corners differing in one axis
+            if i & axis == 0 {
+                let a = camera.project(corner(i));
+                let b = camera.project(corner(i | axis));
+                if let (Some(a), Some(b)) = (a, b) {
+                    draw_line(image, a, b, color);
+                }
+            }
+        }
+    }
+}
+
+fn main() {
+    let mut display = support::Display::new(512, 512, "BVH Viewer");
+    let device = Device::new();
+
+    // Expect <obj_path> [max_depth]
+    let args: Vec<_> = std::env::args().collect();
+    let max_depth = args
+        .get(2)
+        .map(|d| d.parse::<usize>().expect("max depth must be an integer"))
+        .unwrap_or(8);
+
+    let (models, _) = tobj::load_obj(&Path::new(&args[1])).unwrap();
+    let mut scene = Scene::new(&device);
+    let mut aabb = AABB::default();
+    for m in models.iter() {
+        let mesh = &m.mesh;
+        let mut tris =
+            TriangleMesh::unanimated(&device, mesh.indices.len() / 3, mesh.positions.len() / 3);
+        {
+            let mut verts = tris.vertex_buffer.map();
+            let mut tris = tris.index_buffer.map();
+            for i in 0..mesh.positions.len() / 3 {
+                let p = Vector3::new(
+                    mesh.positions[i * 3],
+                    mesh.positions[i * 3 + 1],
+                    mesh.positions[i * 3 + 2],
+                );
+                aabb = aabb.union_vec(&p);
+                verts[i] = p.extend(0.0);
+            }
+            for i in 0..mesh.indices.len() / 3 {
+                tris[i] = Vector3::new(
+                    mesh.indices[i * 3],
+                    mesh.indices[i * 3 + 1],
+                    mesh.indices[i * 3 + 2],
+                );
+            }
+        }
+        let mut tri_geom = Geometry::Triangle(tris);
+        tri_geom.commit();
+        scene.attach_geometry(tri_geom);
+    }
+    display = display.aabb(aabb);
+    let rtscene = scene.commit();
+
+    let levels = embree::bvh_levels(&device, &rtscene, Some(max_depth));
+    for (i, l) in levels.iter().enumerate() {
+        println!("BVH level {} has {} nodes", i, l.len());
+    }
+
+    let mut intersection_ctx = IntersectContext::coherent();
+    display.run(|image, camera_pose, time| {
+        let img_dims = image.dimensions();
+        let camera = Camera::look_dir(
+            camera_pose.pos,
+            camera_pose.dir,
+            camera_pose.up,
+            55.0,
+            img_dims,
+        );
+        // Render the scene with simple eye-light shading
+        for j in 0..img_dims.1 {
+            for i in 0..img_dims.0 {
+                let dir = camera.ray_dir((i as f32 + 0.5, j as f32 + 0.5));
+                let mut ray_hit = RayHit::new(Ray::new(camera.pos, dir));
+                rtscene.intersect(&mut intersection_ctx, &mut ray_hit);
+                let p = image.get_pixel_mut(i, j);
+                if ray_hit.hit.hit() {
+                    let n = Vector3::new(ray_hit.hit.Ng_x, ray_hit.hit.Ng_y, ray_hit.hit.Ng_z);
+                    let c = (n.normalize().dot(dir).abs() * 200.0) as u8;
+                    p.0 = [c, c, c];
+                } else {
+                    p.0 = [0, 0, 0];
+                }
+            }
+        }
+
+        // Overlay the boxes of one level of the BVH, stepping
+        // down through the levels every second
+        if !levels.is_empty() {
+            let level = time as usize % levels.len();
+            let color = [255, (255 * level / levels.len()) as u8, 0];
+            for b in levels[level].iter() {
+                draw_box(image, &camera, b, color);
+            }
+        }
+    });
+}
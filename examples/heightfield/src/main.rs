@@ -0,0 +1,77 @@
+extern crate cgmath;
+extern crate embree;
+extern crate support;
+
+use cgmath::{InnerSpace, Vector2, Vector3};
+use embree::{Device, Geometry, GridMesh, IntersectContext, Ray, RayHit, Scene};
+use support::Camera;
+
+/// Sample a field of rolling hills, large enough to be split into
+/// several grids
+fn make_heights(width: usize, height: usize) -> Vec<f32> {
+    let mut heights = Vec::with_capacity(width * height);
+    for y in 0..height {
+        for x in 0..width {
+            let (fx, fy) = (x as f32 * 0.05, y as f32 * 0.05);
+            heights.push(fx.sin() * fy.cos() + 0.25 * (fx * 3.0 + fy * 2.0).sin());
+        }
+    }
+    heights
+}
+
+fn main() {
+    let mut display = support::Display::new(512, 512, "heightfield");
+    let device = Device::new();
+
+    let (width, height) = (400, 400);
+    let heights = make_heights(width, height);
+    let terrain = Geometry::Grid(GridMesh::from_heightfield(
+        &device,
+        &heights,
+        width,
+        height,
+        Vector3::new(-20.0, -3.0, -20.0),
+        Vector2::new(0.1, 0.1),
+    ));
+
+    let mut scene = Scene::new(&device);
+    scene.attach_geometry(terrain);
+    let rtscene = scene.commit();
+
+    let mut intersection_ctx = IntersectContext::coherent();
+    let light_dir = Vector3::new(1.0, 1.0, 0.5).normalize();
+
+    display.run(|image, camera_pose, _| {
+        for p in image.iter_mut() {
+            *p = 0;
+        }
+        let img_dims = image.dimensions();
+        let camera = Camera::look_dir(
+            camera_pose.pos,
+            camera_pose.dir,
+            camera_pose.up,
+            75.0,
+            img_dims,
+        );
+        for j in 0..img_dims.1 {
+            for i in 0..img_dims.0 {
+                let dir = camera.ray_dir((i as f32 + 0.5, j as f32 + 0.5));
+                let ray = Ray::new(camera.pos, dir);
+                let mut ray_hit = RayHit::new(ray);
+                rtscene.intersect(&mut intersection_ctx, &mut ray_hit);
+                if ray_hit.hit.hit() {
+                    let h = &ray_hit.hit;
+                    let mut n = Vector3::new(h.Ng_x, h.Ng_y, h.Ng_z).normalize();
+                    if n.dot(dir) > 0.0 {
+                        n = -n;
+                    }
+                    let shade = 0.1 + 0.9 * n.dot(light_dir).max(0.0);
+                    let mut p = image.get_pixel_mut(i, j);
+                    p[0] = (shade * 0.45 * 255.0) as u8;
+                    p[1] = (shade * 0.75 * 255.0) as u8;
+                    p[2] = (shade * 0.35 * 255.0) as u8;
+                }
+            }
+        }
+    });
+}
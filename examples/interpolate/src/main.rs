@@ -0,0 +1,123 @@
+//! Compares the time to interpolate a vertex attribute at ray hits on a
+//! triangle mesh with `TriangleMesh::interpolate_attribute_cpu` against
+//! calling `rtcInterpolate` for each hit.
+
+extern crate cgmath;
+extern crate embree;
+
+use std::ptr;
+use std::time::Instant;
+
+use cgmath::{Vector2, Vector3, Vector4};
+use embree::{sys, Device, Geometry, Hit, Ray, Scene, TriangleMesh};
+
+/// The number of quads along each side of the grid mesh
+const GRID_SIZE: usize = 256;
+const NUM_HITS: usize = 1 << 20;
+
+/// Build a grid mesh on [0, 1]^2 with a texture coordinate attribute
+fn make_grid(device: &Device) -> TriangleMesh<'_> {
+    let n = GRID_SIZE + 1;
+    let mut mesh = TriangleMesh::unanimated(device, 2 * GRID_SIZE * GRID_SIZE, n * n);
+    let uv = mesh.add_vertex_attribute();
+    {
+        let mut verts = mesh.vertex_buffer.map();
+        let mut uvs = mesh.vertex_attribute_buffers[uv as usize].map();
+        for j in 0..n {
+            for i in 0..n {
+                let x = i as f32 / GRID_SIZE as f32;
+                let y = j as f32 / GRID_SIZE as f32;
+                verts[j * n + i] = Vector4::new(x, y, 0.0, 0.0);
+                uvs[j * n + i] = Vector4::new(x, 1.0 - y, 0.0, 0.0);
+            }
+        }
+        let mut tris = mesh.index_buffer.map();
+        for j in 0..GRID_SIZE {
+            for i in 0..GRID_SIZE {
+                let v0 = (j * n + i) as u32;
+                let n = n as u32;
+                let q = 2 * (j * GRID_SIZE + i);
+                tris[q] = Vector3::new(v0, v0 + 1, v0 + n + 1);
+                tris[q + 1] = Vector3::new(v0, v0 + n + 1, v0 + n);
+            }
+        }
+    }
+    mesh
+}
+
+fn main() {
+    let device = Device::new();
+    let mut geom = Geometry::Triangle(make_grid(&device));
+    geom.commit();
+    let handle = geom.handle();
+    let mut scene = Scene::new(&device);
+    let id = scene.attach(geom);
+    let rtscene = scene.commit();
+
+    // Trace rays in a jittered grid to collect the hits to interpolate at
+    let side = (NUM_HITS as f32).sqrt() as usize;
+    let mut hits: Vec<Hit> = Vec::with_capacity(side * side);
+    for j in 0..side {
+        for i in 0..side {
+            let x = (i as f32 + 0.37) / side as f32;
+            let y = (j as f32 + 0.61) / side as f32;
+            let ray = Ray::new(Vector3::new(x, y, 1.0), Vector3::new(0.0, 0.0, -1.0));
+            if let Some(rh) = rtscene.intersect_ray(&ray) {
+                hits.push(rh.hit);
+            }
+        }
+    }
+    println!("Interpolating at {} hits", hits.len());
+
+    let mesh = match *scene.geometry(id).unwrap() {
+        Geometry::Triangle(ref m) => m,
+        _ => unreachable!(),
+    };
+
+    let start = Instant::now();
+    let mut cpu_sum = Vector2::new(0.0, 0.0);
+    for h in &hits {
+        cpu_sum += mesh.interpolate_attribute_cpu::<Vector2<f32>>(h, 0).unwrap();
+    }
+    let cpu_time = start.elapsed();
+
+    let start = Instant::now();
+    let mut embree_sum = Vector2::new(0.0, 0.0);
+    for h in &hits {
+        let mut uv = [0.0f32; 2];
+        let args = sys::RTCInterpolateArguments {
+            geometry: handle,
+            primID: h.primID,
+            u: h.u,
+            v: h.v,
+            bufferType: sys::RTCBufferType::VERTEX_ATTRIBUTE,
+            bufferSlot: 0,
+            P: uv.as_mut_ptr(),
+            dPdu: ptr::null_mut(),
+            dPdv: ptr::null_mut(),
+            ddPdudu: ptr::null_mut(),
+            ddPdvdv: ptr::null_mut(),
+            ddPdudv: ptr::null_mut(),
+            valueCount: 2,
+        };
+        unsafe {
+            sys::rtcInterpolate(&args);
+        }
+        embree_sum += Vector2::new(uv[0], uv[1]);
+    }
+    let embree_time = start.elapsed();
+
+    println!(
+        "interpolate_attribute_cpu: {:?} ({:.2} ns/hit)",
+        cpu_time,
+        cpu_time.as_secs_f64() * 1e9 / hits.len() as f64
+    );
+    println!(
+        "rtcInterpolate: {:?} ({:.2} ns/hit)",
+        embree_time,
+        embree_time.as_secs_f64() * 1e9 / hits.len() as f64
+    );
+    // Print the sums so the interpolation isn't optimized away, and as a
+    // check that both give the same result
+    println!("Sums: {:?} vs. {:?}", cpu_sum, embree_sum);
+}
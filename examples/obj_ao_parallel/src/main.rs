@@ -2,75 +2,17 @@
 
 extern crate cgmath;
 extern crate embree;
-extern crate rand;
 extern crate rayon;
 extern crate support;
 extern crate tobj;
 use std::path::Path;
 
-use cgmath::{InnerSpace, Matrix3, Point2, Vector2, Vector3, Vector4};
+use cgmath::{InnerSpace, Point2, Vector3, Vector4};
 use embree::{Device, Geometry, IntersectContext, Ray, RayHit, Scene, TriangleMesh};
-use rand::prelude::*;
 use rayon::prelude::*;
+use support::sampling::{self, cosine_sample_hemisphere, Frame, SeedMode};
 use support::{Camera, AABB};
 
-/// Function to sample a point inside a 2D disk
-pub fn concentric_sample_disk(u: Point2<f32>) -> Point2<f32> {
-    // map uniform random numbers to $[-1,1]^2$
-    let u_offset: Point2<f32> = u * 2.0 as f32 - Vector2 { x: 1.0, y: 1.0 };
-    // handle degeneracy at the origin
-    if u_offset.x == 0.0 as f32 && u_offset.y == 0.0 as f32 {
-        return Point2 { x: 0.0, y: 0.0 };
-    }
-    // apply concentric mapping to point
-    let theta: f32;
-    let r: f32;
-    if u_offset.x.abs() > u_offset.y.abs() {
-        r = u_offset.x;
-        theta = std::f32::consts::FRAC_PI_4 * (u_offset.y / u_offset.x);
-    } else {
-        r = u_offset.y;
-        theta =
-            std::f32::consts::FRAC_PI_2 - std::f32::consts::FRAC_PI_4 * (u_offset.x / u_offset.y);
-    }
-    Point2 {
-        x: theta.cos(),
-        y: theta.sin(),
-    } * r
-}
-/// Function to sample cosine-weighted hemisphere
-pub fn cosine_sample_hemisphere(u: Point2<f32>) -> Vector3<f32> {
-    let d: Point2<f32> = concentric_sample_disk(u);
-    let z: f32 = (0.0 as f32).max(1.0 as f32 - d.x * d.x - d.y * d.y).sqrt();
-    Vector3 { x: d.x, y: d.y, z }
-}
-
-// "Building an Orthonormal Basis, Revisited" by Duff et al., JCGT, 2017
-// http://jcgt.org/published/0006/01/01/
-pub struct Frame(Matrix3<f32>);
-impl Frame {
-    pub fn new(n: Vector3<f32>) -> Frame {
-        let sign = n.z.signum();
-        let a = -1.0 / (sign + n.z);
-        let b = n.x * n.y * a;
-        Frame {
-            0: Matrix3 {
-                x: Vector3::new(1.0 + sign * n.x * n.x * a, sign * b, -sign * n.x),
-                y: Vector3::new(b, sign + n.y * n.y * a, -n.y),
-                z: n,
-            },
-        }
-    }
-
-    pub fn to_world(&self, v: Vector3<f32>) -> Vector3<f32> {
-        self.0.x * v.x + self.0.y * v.y + self.0.z * v.z
-    }
-
-    pub fn to_local(&self, v: Vector3<f32>) -> Vector3<f32> {
-        Vector3::new(v.dot(self.0.x), v.dot(self.0.y), v.dot(self.0.z))
-    }
-}
-
 // It is an example of a custom structure
 // that encapsulate the embree commited scene
 pub struct AOIntegrator<'embree> {
@@ -100,8 +42,9 @@ impl<'embree> AOIntegrator<'embree> {
         }
     }
 
-    // Simple AO computation method
-    pub fn render(&self, i: u32, j: u32, u: Point2<f32>) -> f32 {
+    // Trace the primary ray through the pixel center and compute the
+    // shading normal at the hit point, facing the camera
+    fn primary_hit(&self, i: u32, j: u32) -> Option<(RayHit, Vector3<f32>)> {
         let dir = self.camera.ray_dir((i as f32 + 0.5, j as f32 + 0.5));
         let ray = Ray::new(self.camera.pos, dir);
         let mut ray_hit = RayHit::new(ray);
@@ -157,7 +100,25 @@ impl<'embree> AOIntegrator<'embree> {
             if n.dot(dir) > 0.0 {
                 n *= -1.0;
             }
+            Some((ray_hit, n))
+        } else {
+            None
+        }
+    }
 
+    // Normal AOV for the denoiser, as the primary rays always go through the
+    // pixel center this only needs to be computed when the camera changes
+    pub fn render_normal(&self, i: u32, j: u32) -> Vector3<f32> {
+        match self.primary_hit(i, j) {
+            Some((_, n)) => n,
+            None => Vector3::new(0.0, 0.0, 0.0),
+        }
+    }
+
+    // Simple AO computation method
+    pub fn render(&self, i: u32, j: u32, u: Point2<f32>) -> f32 {
+        if let Some((ray_hit, n)) = self.primary_hit(i, j) {
+            let dir = Vector3::new(ray_hit.ray.dir_x, ray_hit.ray.dir_y, ray_hit.ray.dir_z);
             // Create local frame
             let frame = Frame::new(n);
             let p = self.camera.pos + dir * ray_hit.ray.tfar;
@@ -219,6 +180,13 @@ fn main() {
         ),
     };
 
+    // Seed each pixel's samples from its coordinates if AO_SEED is set,
+    // so the accumulated image is the same on every run
+    let seeds = match std::env::var("AO_SEED") {
+        Ok(seed) => SeedMode::PerPixel(seed.parse().expect("AO_SEED must be an integer")),
+        Err(_) => SeedMode::PerThread,
+    };
+
     // Load the obj
     let (models, _) = tobj::load_obj(&Path::new(&args[1])).unwrap();
     let mut tri_geoms = Vec::new();
@@ -292,6 +260,18 @@ fn main() {
     let mut spp = 0;
     let mut img = Vec::new();
 
+    // AOV buffers and scratch space for denoising the accumulated AO. The
+    // AO is rendered as a white material, so the albedo is 1 where the
+    // primary ray hit something and 0 otherwise.
+    #[cfg(feature = "denoise")]
+    let mut denoiser = support::denoise::Denoiser::new();
+    #[cfg(feature = "denoise")]
+    let mut albedo = Vec::new();
+    #[cfg(feature = "denoise")]
+    let mut normals = Vec::new();
+    #[cfg(feature = "denoise")]
+    let mut color = Vec::new();
+
     println!("Rendering launched ... ");
     display.run(|image, camera_pose, _| {
         for p in image.iter_mut() {
@@ -313,28 +293,66 @@ fn main() {
             for i in &mut img {
                 (*i) = 0.0;
             }
+
+            #[cfg(feature = "denoise")]
+            {
+                normals.resize(img.len() * 3, 0.0);
+                normals
+                    .par_chunks_mut(image.width() as usize * 3)
+                    .enumerate()
+                    .for_each(|(y, row)| {
+                        for (x, p) in row.chunks_mut(3).enumerate() {
+                            let n = scene.render_normal(x as u32, y as u32);
+                            p.copy_from_slice(&[n.x, n.y, n.z]);
+                        }
+                    });
+                albedo.clear();
+                albedo.extend(normals.chunks(3).flat_map(|n| {
+                    let a = if n.iter().any(|x| *x != 0.0) {
+                        1.0
+                    } else {
+                        0.0
+                    };
+                    std::iter::repeat(a).take(3)
+                }));
+            }
         }
 
         // Render the scene with Rayon. Here each pixel compute 1 spp AO
         img.par_chunks_mut(image.width() as usize)
             .enumerate()
             .for_each(|(y, row)| {
-                let mut rng = rand::thread_rng();
                 for (x, p) in row.iter_mut().enumerate() {
-                    let u = Point2::new(rng.gen(), rng.gen());
+                    let (x, y) = (x as u32, y as u32);
+                    let u = seeds.with_pixel_rng(x, y, spp, sampling::sample_2d);
                     // Weighting average
-                    (*p) =
-                        (*p * spp as f32 + scene.render(x as u32, y as u32, u)) / (spp + 1) as f32;
+                    (*p) = (*p * spp as f32 + scene.render(x, y, u)) / (spp + 1) as f32;
                 }
             });
         spp += 1;
 
-        // Copy the accumulated result inside the image buffer
-        let raw_out = image.as_mut();
-        raw_out.chunks_mut(3).zip(img.iter()).for_each(|(p, v)| {
-            p[0] = (v * 255.0) as u8;
-            p[1] = (v * 255.0) as u8;
-            p[2] = (v * 255.0) as u8;
-        });
+        // Denoise the accumulated result and write it to the image buffer
+        #[cfg(feature = "denoise")]
+        {
+            support::denoise::gray_to_rgb(&img, &mut color);
+            let denoised = denoiser.denoise(
+                img_dims.0 as usize,
+                img_dims.1 as usize,
+                &color,
+                Some((&albedo, &normals)),
+            );
+            support::denoise::write_rgb_image(denoised, image);
+        }
+
+        // Otherwise copy the accumulated result inside the image buffer
+        #[cfg(not(feature = "denoise"))]
+        {
+            let raw_out = image.as_mut();
+            raw_out.chunks_mut(3).zip(img.iter()).for_each(|(p, v)| {
+                p[0] = (v * 255.0) as u8;
+                p[1] = (v * 255.0) as u8;
+                p[2] = (v * 255.0) as u8;
+            });
+        }
     });
 }
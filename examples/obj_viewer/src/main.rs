@@ -9,7 +9,7 @@ use std::path::Path;
 
 use cgmath::{InnerSpace, Vector3, Vector4};
 use embree::{Device, Geometry, IntersectContext, Ray, RayHit, Scene, TriangleMesh};
-use support::{Camera, AABB};
+use support::{Camera, Channel, TileScheduler, TiledImage, AABB};
 
 fn main() {
     let mut display = support::Display::new(512, 512, "OBJ Viewer");
@@ -69,12 +69,35 @@ fn main() {
     }
     let rtscene = scene.commit();
 
-    let mut intersection_ctx = IntersectContext::coherent();
+    // Expect <obj_path> [channel], where channel is one of the AOVs
+    // written by the renderer to display for debugging
+    let display_channel = match args.get(2) {
+        Some(name) => Channel::from_name(name).unwrap_or_else(|| {
+            panic!(
+                "Unknown channel '{}', expected color, normal, depth or geomID",
+                name
+            )
+        }),
+        None => Channel::Color,
+    };
+    display = display
+        .metadata("Scene", &args[1])
+        .metadata("Shading", display_channel.name());
+    let mut framebuffer = TiledImage::new(
+        512,
+        512,
+        64,
+        &[
+            Channel::Color,
+            Channel::Normal,
+            Channel::Depth,
+            Channel::GeomId,
+        ],
+    );
+
+    let mut scheduler = TileScheduler::new();
 
     display.run(|image, camera_pose, _| {
-        for p in image.iter_mut() {
-            *p = 0;
-        }
         let img_dims = image.dimensions();
         let camera = Camera::look_dir(
             camera_pose.pos,
@@ -83,17 +106,19 @@ fn main() {
             55.0,
             img_dims,
         );
-        // Render the scene
-        for j in 0..img_dims.1 {
-            for i in 0..img_dims.0 {
+        // Render the scene, writing all the AOVs for each pixel in one pass
+        framebuffer.clear();
+        scheduler.run(framebuffer.tiles_mut(), |tile| {
+            let mut intersection_ctx = IntersectContext::coherent();
+            let pixels: Vec<_> = tile.pixels().collect();
+            for (i, j) in pixels {
                 let dir = camera.ray_dir((i as f32 + 0.5, j as f32 + 0.5));
                 let ray = Ray::new(camera.pos, dir);
                 let mut ray_hit = RayHit::new(ray);
                 rtscene.intersect(&mut intersection_ctx, &mut ray_hit);
                 if ray_hit.hit.hit() {
-                    let p = image.get_pixel_mut(i, j);
                     let mesh = &models[mesh_ids[ray_hit.hit.geomID as usize] as usize].mesh;
-                    if !mesh.normals.is_empty() {
+                    let n = if !mesh.normals.is_empty() {
                         let prim = ray_hit.hit.primID as usize;
                         let tri = [
                             mesh.indices[prim * 3] as usize,
@@ -120,19 +145,21 @@ fn main() {
                         );
 
                         let w = 1.0 - ray_hit.hit.u - ray_hit.hit.v;
-                        let mut n = (na * w + nb * ray_hit.hit.u + nc * ray_hit.hit.v).normalize();
-                        n = (n + Vector3::new(1.0, 1.0, 1.0)) * 0.5;
-
-                        p[0] = (n.x * 255.0) as u8;
-                        p[1] = (n.y * 255.0) as u8;
-                        p[2] = (n.z * 255.0) as u8;
+                        let n = (na * w + nb * ray_hit.hit.u + nc * ray_hit.hit.v).normalize();
+                        let c = (n + Vector3::new(1.0, 1.0, 1.0)) * 0.5;
+                        tile.set(i, j, Channel::Color, &[c.x, c.y, c.z]);
+                        n
                     } else {
-                        p[0] = (ray_hit.hit.u * 255.0) as u8;
-                        p[1] = (ray_hit.hit.v * 255.0) as u8;
-                        p[2] = 0;
-                    }
+                        tile.set(i, j, Channel::Color, &[ray_hit.hit.u, ray_hit.hit.v, 0.0]);
+                        Vector3::new(ray_hit.hit.Ng_x, ray_hit.hit.Ng_y, ray_hit.hit.Ng_z)
+                            .normalize()
+                    };
+                    tile.set(i, j, Channel::Normal, &[n.x, n.y, n.z]);
+                    tile.set(i, j, Channel::Depth, &[ray_hit.ray.tfar]);
+                    tile.set_geom_id(i, j, ray_hit.hit.geomID);
                 }
             }
-        }
+        });
+        framebuffer.write_to_image(display_channel, image);
     });
 }
@@ -0,0 +1,148 @@
+//! Loads an OBJ file and builds an Embree scene from it as quickly as
+//! possible, filling and committing the meshes' geometry in parallel with
+//! rayon, then prints a breakdown of the time spent in each stage and the
+//! memory Embree allocated. This is a reference for loading large scenes:
+//! the mesh buffers are filled directly from the loaded data on worker
+//! threads, and the scene's BVH build reports its progress as it runs.
+//!
+//! Usage: parallel_build <file.obj>
+
+extern crate cgmath;
+extern crate embree;
+extern crate rayon;
+extern crate tobj;
+
+use std::path::Path;
+use std::sync::atomic::{AtomicIsize, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use cgmath::{Vector3, Vector4};
+use embree::{BuildQuality, Device, Geometry, Scene, TriangleMesh};
+use rayon::prelude::*;
+
+/// Create a triangle mesh for the model and fill its buffers
+fn build_mesh<'a>(device: &'a Device, mesh: &tobj::Mesh) -> Geometry<'a> {
+    let num_verts = mesh.positions.len() / 3;
+    let num_tris = mesh.indices.len() / 3;
+    let mut tris = TriangleMesh::unanimated(device, num_tris, num_verts);
+    {
+        let mut verts = tris.vertex_buffer.map();
+        for (v, p) in verts
+            .as_mut_slice()
+            .iter_mut()
+            .zip(mesh.positions.chunks(3))
+        {
+            *v = Vector4::new(p[0], p[1], p[2], 0.0);
+        }
+        let mut indices = tris.index_buffer.map();
+        for (t, i) in indices
+            .as_mut_slice()
+            .iter_mut()
+            .zip(mesh.indices.chunks(3))
+        {
+            *t = Vector3::new(i[0], i[1], i[2]);
+        }
+    }
+    Geometry::Triangle(tris)
+}
+
+fn print_time(stage: &str, time: Duration, total: Duration) {
+    println!(
+        "{:>16}: {:8.2}ms ({:5.1}%)",
+        stage,
+        time.as_secs_f64() * 1000.0,
+        100.0 * time.as_secs_f64() / total.as_secs_f64()
+    );
+}
+
+fn main() {
+    let args: Vec<_> = std::env::args().collect();
+    if args.len() < 2 {
+        println!("Usage: parallel_build <file.obj>");
+        return;
+    }
+
+    // Track the memory Embree allocates for the buffers and BVH
+    let mut device = Device::new();
+    let allocated = Arc::new(AtomicIsize::new(0));
+    let peak = Arc::new(AtomicIsize::new(0));
+    {
+        let allocated = allocated.clone();
+        let peak = peak.clone();
+        device.set_memory_monitor_function(move |bytes, post| {
+            if !post {
+                let now = allocated.fetch_add(bytes, Ordering::Relaxed) + bytes;
+                peak.fetch_max(now, Ordering::Relaxed);
+            }
+            true
+        });
+    }
+
+    let start = Instant::now();
+    let (models, _) = tobj::load_obj(Path::new(&args[1])).unwrap();
+    let io_time = start.elapsed();
+    let num_tris: usize = models.iter().map(|m| m.mesh.indices.len() / 3).sum();
+    println!("Loaded {} meshes with {} triangles", models.len(), num_tris);
+
+    let start = Instant::now();
+    let mut geometry: Vec<Geometry> = models
+        .par_iter()
+        .map(|m| build_mesh(&device, &m.mesh))
+        .collect();
+    let fill_time = start.elapsed();
+
+    let start = Instant::now();
+    geometry.par_iter_mut().for_each(|g| g.commit());
+    let geometry_commit_time = start.elapsed();
+
+    let start = Instant::now();
+    let mut scene = Scene::new(&device);
+    scene.set_build_quality(BuildQuality::HIGH);
+    for g in geometry.drain(..) {
+        scene.attach_geometry(g);
+    }
+    let attach_time = start.elapsed();
+
+    // Print the progress of the BVH build in 10% steps. Embree calls the
+    // monitor from its build threads, so the last step printed is tracked
+    // atomically.
+    let last_step = Arc::new(AtomicUsize::new(0));
+    {
+        let last_step = last_step.clone();
+        scene.set_progress_monitor_function(move |progress| {
+            let step = (progress * 10.0) as usize;
+            if last_step.fetch_max(step, Ordering::Relaxed) < step {
+                println!("Building BVH: {}%", step * 10);
+            }
+            true
+        });
+    }
+    let start = Instant::now();
+    let rtscene = scene.commit();
+    let scene_commit_time = start.elapsed();
+
+    let total = io_time + fill_time + geometry_commit_time + attach_time + scene_commit_time;
+    println!("Timing breakdown:");
+    print_time("OBJ loading", io_time, total);
+    print_time("buffer fill", fill_time, total);
+    print_time("geometry commit", geometry_commit_time, total);
+    print_time("attach", attach_time, total);
+    print_time("scene commit", scene_commit_time, total);
+    print_time("total", total, total);
+    println!(
+        "Embree memory: {:.2}MB allocated, {:.2}MB peak",
+        allocated.load(Ordering::Relaxed) as f64 / 1e6,
+        peak.load(Ordering::Relaxed) as f64 / 1e6
+    );
+    let bounds = rtscene.bounds();
+    println!(
+        "Scene bounds: [{}, {}, {}] - [{}, {}, {}]",
+        bounds.lower_x,
+        bounds.lower_y,
+        bounds.lower_z,
+        bounds.upper_x,
+        bounds.upper_y,
+        bounds.upper_z
+    );
+}
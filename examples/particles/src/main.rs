@@ -0,0 +1,79 @@
+extern crate cgmath;
+extern crate embree;
+extern crate support;
+
+use cgmath::{InnerSpace, Vector3};
+use embree::{Device, Geometry, IntersectContext, PointGeometry, Ray, RayHit, Scene};
+use support::Camera;
+
+/// A cheap hash mapping an index to a pseudo-random float in [0, 1)
+fn hash(i: u32) -> f32 {
+    let mut x = i.wrapping_mul(0x9e37_79b9) ^ 0x85eb_ca6b;
+    x ^= x >> 16;
+    x = x.wrapping_mul(0x7feb_352d);
+    x ^= x >> 15;
+    (x >> 8) as f32 / (1 << 24) as f32
+}
+
+/// Scatter particles along the arms of a spiral, as [x, y, z, radius]
+fn make_particles(n: u32) -> Vec<[f32; 4]> {
+    (0..n)
+        .map(|i| {
+            let arm = (i % 3) as f32 * 2.0 * std::f32::consts::PI / 3.0;
+            let r = 0.5 + 6.0 * hash(3 * i);
+            let theta = arm + r * 0.6 + 0.4 * hash(3 * i + 1);
+            let y = 0.3 * (hash(3 * i + 2) - 0.5) * (7.0 - r);
+            [
+                r * theta.cos(),
+                y,
+                r * theta.sin(),
+                0.02 + 0.05 * hash(i + n),
+            ]
+        })
+        .collect()
+}
+
+fn main() {
+    let mut display = support::Display::new(512, 512, "particles");
+    let device = Device::new();
+
+    let particles = Geometry::Point(PointGeometry::spheres(&device, &make_particles(20_000)));
+    let mut scene = Scene::new(&device);
+    scene.attach_geometry(particles);
+    let rtscene = scene.commit();
+
+    let mut intersection_ctx = IntersectContext::coherent();
+
+    display.run(|image, camera_pose, _| {
+        for p in image.iter_mut() {
+            *p = 0;
+        }
+        let img_dims = image.dimensions();
+        let camera = Camera::look_dir(
+            camera_pose.pos,
+            camera_pose.dir,
+            camera_pose.up,
+            75.0,
+            img_dims,
+        );
+        for j in 0..img_dims.1 {
+            for i in 0..img_dims.0 {
+                let dir = camera.ray_dir((i as f32 + 0.5, j as f32 + 0.5));
+                let ray = Ray::new(camera.pos, dir);
+                let mut ray_hit = RayHit::new(ray);
+                rtscene.intersect(&mut intersection_ctx, &mut ray_hit);
+                if ray_hit.hit.hit() {
+                    let h = &ray_hit.hit;
+                    let n = Vector3::new(h.Ng_x, h.Ng_y, h.Ng_z).normalize();
+                    let shade = 0.2 + 0.8 * n.dot(-dir).max(0.0);
+                    // Color each particle by its ID
+                    let tint = hash(h.primID);
+                    let mut p = image.get_pixel_mut(i, j);
+                    p[0] = (shade * (0.6 + 0.4 * tint) * 255.0) as u8;
+                    p[1] = (shade * 0.7 * 255.0) as u8;
+                    p[2] = (shade * (1.0 - 0.4 * tint) * 255.0) as u8;
+                }
+            }
+        }
+    });
+}
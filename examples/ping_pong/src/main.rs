@@ -0,0 +1,169 @@
+//! Measures the benefit of overlapping tracing and shading with
+//! `PingPongStreams`. An image of a bumpy terrain is rendered in tiles,
+//! with each tile's primary rays traced as a ray stream and the hits
+//! shaded with ambient occlusion. The image is rendered twice: once
+//! tracing and shading each tile in turn, and once shading each tile while
+//! the next one is traced with `rayon::join`.
+
+extern crate cgmath;
+extern crate embree;
+extern crate rayon;
+
+use std::time::{Duration, Instant};
+
+use cgmath::{InnerSpace, Vector3, Vector4};
+use embree::{
+    CommittedScene, Device, Geometry, IntersectContext, PingPongStreams, Ray, RayHitN, RayN, Scene,
+    SoAHit, SoARay, Tile, TriangleMesh,
+};
+
+const WIDTH: u32 = 1024;
+const HEIGHT: u32 = 1024;
+const TILE_SIZE: u32 = 64;
+/// The number of quads along each side of the terrain
+const GRID_SIZE: usize = 512;
+const AO_SAMPLES: usize = 8;
+
+fn height(x: f32, y: f32) -> f32 {
+    0.05 * (x * 40.0).sin() * (y * 37.0).cos() + 0.1 * (x * 7.0 + y * 3.0).sin()
+}
+
+/// Build a terrain on [0, 1]^2 displaced by `height`
+fn make_terrain(device: &Device) -> TriangleMesh<'_> {
+    let n = GRID_SIZE + 1;
+    let mut mesh = TriangleMesh::unanimated(device, 2 * GRID_SIZE * GRID_SIZE, n * n);
+    {
+        let mut verts = mesh.vertex_buffer.map();
+        for j in 0..n {
+            for i in 0..n {
+                let x = i as f32 / GRID_SIZE as f32;
+                let y = j as f32 / GRID_SIZE as f32;
+                verts[j * n + i] = Vector4::new(x, y, height(x, y), 0.0);
+            }
+        }
+        let mut tris = mesh.index_buffer.map();
+        for j in 0..GRID_SIZE {
+            for i in 0..GRID_SIZE {
+                let v0 = (j * n + i) as u32;
+                let n = n as u32;
+                let q = 2 * (j * GRID_SIZE + i);
+                tris[q] = Vector3::new(v0, v0 + 1, v0 + n + 1);
+                tris[q + 1] = Vector3::new(v0, v0 + n + 1, v0 + n);
+            }
+        }
+    }
+    mesh
+}
+
+fn camera(px: f32, py: f32) -> (Vector3<f32>, Vector3<f32>) {
+    let origin = Vector3::new(0.5, -0.6, 0.8);
+    let target = Vector3::new(
+        px / WIDTH as f32,
+        0.2 + 0.8 * (1.0 - py / HEIGHT as f32),
+        0.0,
+    );
+    (origin, (target - origin).normalize())
+}
+
+/// Shade the hits of the stream with ambient occlusion, returning the sum
+/// of the occlusion over the tile
+fn shade(scene: &CommittedScene, stream: &RayHitN) -> f32 {
+    let mut sum = 0.0;
+    for i in 0..stream.len() {
+        if !stream.hit.hit(i) {
+            continue;
+        }
+        let p = stream.ray.org(i) + stream.ray.dir(i) * stream.ray.tfar(i);
+        let n = stream.hit.normal(i).normalize();
+        let n = if n.dot(stream.ray.dir(i)) > 0.0 {
+            -n
+        } else {
+            n
+        };
+        let mut visible = 0;
+        for s in 0..AO_SAMPLES {
+            // Directions on a fixed spiral over the hemisphere around n
+            let phi = 2.4 * s as f32 + i as f32;
+            let z = (s as f32 + 0.5) / AO_SAMPLES as f32;
+            let r = (1.0 - z * z).sqrt();
+            let d = Vector3::new(r * phi.cos(), r * phi.sin(), z);
+            let d = if d.dot(n) < 0.0 { -d } else { d };
+            let ray = Ray::segment(p + n * 1e-4, d, 0.0, 0.2);
+            if !scene.is_occluded(&ray) {
+                visible += 1;
+            }
+        }
+        sum += visible as f32 / AO_SAMPLES as f32;
+    }
+    sum
+}
+
+fn tiles() -> Vec<Tile> {
+    let mut tiles = Vec::new();
+    for y in (0..HEIGHT).step_by(TILE_SIZE as usize) {
+        for x in (0..WIDTH).step_by(TILE_SIZE as usize) {
+            tiles.push(Tile::new(x, y, TILE_SIZE, TILE_SIZE));
+        }
+    }
+    tiles
+}
+
+/// Trace and shade each tile in turn
+fn render_serial(scene: &CommittedScene) -> (f32, Duration) {
+    let start = Instant::now();
+    let mut stream = RayHitN::new(RayN::new((TILE_SIZE * TILE_SIZE) as usize));
+    let mut ctx = IntersectContext::coherent();
+    let mut sum = 0.0;
+    for tile in tiles() {
+        stream.fill_primary(camera, &tile, 0.0, u32::MAX);
+        scene.intersect_stream_soa(&mut ctx, &mut stream);
+        sum += shade(scene, &stream);
+    }
+    (sum, start.elapsed())
+}
+
+/// Shade each tile while tracing the next one
+fn render_overlapped(scene: &CommittedScene) -> (f32, Duration) {
+    let start = Instant::now();
+    let mut streams = PingPongStreams::new((TILE_SIZE * TILE_SIZE) as usize);
+    let mut sum = 0.0;
+    for tile in tiles() {
+        streams
+            .back_mut()
+            .fill_primary(camera, &tile, 0.0, u32::MAX);
+        let (front, back) = streams.split_mut();
+        let (_, shaded) = rayon::join(
+            || scene.intersect_stream_soa(&mut IntersectContext::coherent(), back),
+            || shade(scene, front),
+        );
+        sum += shaded;
+        streams.swap();
+    }
+    sum += shade(scene, streams.front());
+    (sum, start.elapsed())
+}
+
+fn main() {
+    let device = Device::new();
+    let mut geom = Geometry::Triangle(make_terrain(&device));
+    geom.commit();
+    let mut scene = Scene::new(&device);
+    scene.attach_geometry(geom);
+    let rtscene = scene.commit();
+
+    let (serial_sum, serial) = render_serial(&rtscene);
+    let (overlapped_sum, overlapped) = render_overlapped(&rtscene);
+    println!("Serial: {:?}", serial);
+    println!("Overlapped: {:?}", overlapped);
+    println!(
+        "Speedup: {:.2}x",
+        serial.as_secs_f64() / overlapped.as_secs_f64()
+    );
+    // The renders must match, as only the order of the work changed
+    let pixels = (WIDTH * HEIGHT) as f32;
+    println!(
+        "Mean AO: {:.4} vs. {:.4}",
+        serial_sum / pixels,
+        overlapped_sum / pixels
+    );
+}
@@ -0,0 +1,101 @@
+extern crate cgmath;
+extern crate embree;
+extern crate support;
+
+use cgmath::{InnerSpace, Vector3};
+use embree::{
+    Device, Geometry, IntersectContext, PointGeometry, Ray, RayHit, RayMask, Scene, TriangleMesh,
+};
+use support::Camera;
+
+/// The mask bit of rays from the camera
+const CAMERA: u32 = 0;
+/// The mask bit of shadow rays
+const SHADOW: u32 = 1;
+
+fn main() {
+    let mut display = support::Display::new(512, 512, "ray mask");
+    let device = Device::new();
+    if !device.supports_ray_masks() {
+        println!("Embree was built without EMBREE_RAY_MASK, all geometry will be visible");
+    }
+    let (camera_mask, shadow_mask) = (RayMask::bit(CAMERA), RayMask::bit(SHADOW));
+
+    let mut ground = Geometry::Triangle(
+        TriangleMesh::try_from_slices(
+            &device,
+            &[
+                [-10.0, -2.0, -10.0],
+                [10.0, -2.0, -10.0],
+                [10.0, -2.0, 10.0],
+                [-10.0, -2.0, 10.0],
+            ],
+            &[[0, 1, 2], [0, 2, 3]],
+        )
+        .unwrap(),
+    );
+    ground.set_ray_mask(camera_mask | shadow_mask);
+    ground.commit();
+
+    // The spheres cast shadows on the ground but are hidden from the camera
+    let mut spheres = Geometry::Point(PointGeometry::spheres(
+        &device,
+        &[
+            [-2.0, 0.0, 0.0, 1.0],
+            [0.0, 0.5, -1.0, 0.75],
+            [2.0, 0.0, 0.5, 1.0],
+        ],
+    ));
+    spheres.set_ray_mask(shadow_mask);
+    spheres.commit();
+
+    let mut scene = Scene::new(&device);
+    scene.attach_geometry(ground);
+    scene.attach_geometry(spheres);
+    let rtscene = scene.commit();
+
+    let mut intersection_ctx = IntersectContext::coherent();
+    let light_dir = Vector3::new(0.5, 1.0, 0.3).normalize();
+
+    display.run(|image, camera_pose, _| {
+        for p in image.iter_mut() {
+            *p = 0;
+        }
+        let img_dims = image.dimensions();
+        let camera = Camera::look_dir(
+            camera_pose.pos,
+            camera_pose.dir,
+            camera_pose.up,
+            75.0,
+            img_dims,
+        );
+        for j in 0..img_dims.1 {
+            for i in 0..img_dims.0 {
+                let dir = camera.ray_dir((i as f32 + 0.5, j as f32 + 0.5));
+                let ray = Ray::new(camera.pos, dir).with_mask(camera_mask);
+                let mut ray_hit = RayHit::new(ray);
+                rtscene.intersect(&mut intersection_ctx, &mut ray_hit);
+                if ray_hit.hit.hit() {
+                    let h = &ray_hit.hit;
+                    let mut n = Vector3::new(h.Ng_x, h.Ng_y, h.Ng_z).normalize();
+                    if n.dot(dir) > 0.0 {
+                        n = -n;
+                    }
+                    let pos = camera.pos + dir * ray_hit.ray.tfar;
+                    let shadow =
+                        Ray::segment(pos, light_dir, 1e-3, f32::INFINITY).with_mask(shadow_mask);
+                    let lit = if rtscene.is_occluded(&shadow) {
+                        0.0
+                    } else {
+                        n.dot(light_dir).max(0.0)
+                    };
+                    let shade = 0.15 + 0.85 * lit;
+                    let mut p = image.get_pixel_mut(i, j);
+                    p[0] = (shade * 255.0) as u8;
+                    p[1] = (shade * 255.0) as u8;
+                    p[2] = (shade * 255.0) as u8;
+                }
+            }
+        }
+    });
+}
@@ -0,0 +1,58 @@
+//! Compares the time to build a procedurally generated scene with the
+//! time to load it from a scene cache. The first run builds the scene and
+//! writes `scene_cache.bin`, later runs load it from the cache. Pass a
+//! different seed to change the scene, which invalidates the cache.
+
+extern crate embree;
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::time::Instant;
+
+use embree::testing::{generate_scene, SceneConfig};
+use embree::{BuildQuality, Device, SceneCache};
+
+fn main() {
+    let seed = std::env::args()
+        .nth(1)
+        .map(|s| s.parse::<u64>().expect("seed must be an integer"))
+        .unwrap_or(0);
+    let config = SceneConfig::new()
+        .spheres(256)
+        .meshes(64)
+        .resolution(64)
+        .seed(seed);
+
+    // The configuration determines the scene, so its hash is the key
+    let mut hasher = DefaultHasher::new();
+    format!("{:?}", config).hash(&mut hasher);
+    let key = hasher.finish();
+
+    let device = Device::new();
+    let cache = SceneCache::new("scene_cache.bin");
+    let start = Instant::now();
+    let (scene, loaded) = cache
+        .load_or_build(&device, key, |device| {
+            let mut scene = generate_scene(device, &config, None);
+            scene.set_build_quality(BuildQuality::HIGH);
+            scene
+        })
+        .expect("failed to access the scene cache");
+    let load_time = start.elapsed();
+
+    let start = Instant::now();
+    let _rtscene = scene.commit();
+    let commit_time = start.elapsed();
+
+    println!(
+        "{} {} triangles in {:.1}ms, committed in {:.1}ms",
+        if loaded {
+            "Loaded cached"
+        } else {
+            "Built and cached"
+        },
+        config.triangle_count(),
+        load_time.as_secs_f64() * 1000.0,
+        commit_time.as_secs_f64() * 1000.0
+    );
+}
@@ -0,0 +1,84 @@
+//! Compares the build and trace time of a scene of long, thin, diagonal
+//! triangles with and without spatial splits. Such triangles have large,
+//! mostly empty bounding boxes which overlap heavily, the case where
+//! spatial splits give the biggest improvement in BVH quality.
+
+extern crate cgmath;
+extern crate embree;
+
+use std::time::Instant;
+
+use cgmath::{InnerSpace, Vector3};
+use embree::testing::Pcg32;
+use embree::{Device, Geometry, IntersectContext, Ray, RayHit, Scene, TriangleMesh};
+
+const NUM_TRIS: usize = 200_000;
+const IMAGE_SIZE: u32 = 512;
+
+/// Build a mesh of thin triangles spanning the [-1, 1]^3 cube diagonally
+fn make_slivers(device: &Device) -> Geometry<'_> {
+    let mut rng = Pcg32::new(1);
+    let mut mesh = TriangleMesh::unanimated(device, NUM_TRIS, 3 * NUM_TRIS);
+    {
+        let mut verts = mesh.vertex_buffer.map();
+        let mut tris = mesh.index_buffer.map();
+        for i in 0..NUM_TRIS {
+            let a = rng.point_in_cube(1.0);
+            let b = -a + rng.point_in_cube(0.1);
+            let width = rng.point_in_cube(0.005);
+            verts[3 * i] = a.extend(0.0);
+            verts[3 * i + 1] = b.extend(0.0);
+            verts[3 * i + 2] = (a + width).extend(0.0);
+            let v = 3 * i as u32;
+            tris[i] = Vector3::new(v, v + 1, v + 2);
+        }
+    }
+    let mut geom = Geometry::Triangle(mesh);
+    geom.commit();
+    geom
+}
+
+fn run(device: &Device, spatial_splits: bool) {
+    let mut scene = Scene::new(device);
+    scene.attach_geometry(make_slivers(device));
+    scene.set_spatial_splits(spatial_splits);
+
+    let start = Instant::now();
+    let rtscene = scene.commit();
+    let build_time = start.elapsed();
+
+    // Trace an orthographic view through the cube
+    let mut ctx = IntersectContext::coherent();
+    let mut hits = 0;
+    let start = Instant::now();
+    for j in 0..IMAGE_SIZE {
+        for i in 0..IMAGE_SIZE {
+            let x = 2.0 * (i as f32 + 0.5) / IMAGE_SIZE as f32 - 1.0;
+            let y = 2.0 * (j as f32 + 0.5) / IMAGE_SIZE as f32 - 1.0;
+            let org = Vector3::new(x, y, -2.0);
+            let dir = Vector3::new(0.1, 0.05, 1.0).normalize();
+            let mut ray_hit = RayHit::new(Ray::new(org, dir));
+            rtscene.intersect(&mut ctx, &mut ray_hit);
+            if ray_hit.hit.hit() {
+                hits += 1;
+            }
+        }
+    }
+    let trace_time = start.elapsed();
+    let num_rays = IMAGE_SIZE * IMAGE_SIZE;
+    println!(
+        "spatial splits {}: build {:.1}ms, trace {:.1}ms ({:.2} Mrays/s, {} hits)",
+        if spatial_splits { "on " } else { "off" },
+        build_time.as_secs_f64() * 1000.0,
+        trace_time.as_secs_f64() * 1000.0,
+        num_rays as f64 / trace_time.as_secs_f64() / 1e6,
+        hits
+    );
+}
+
+fn main() {
+    let device = Device::new();
+    println!("{} diagonal sliver triangles", NUM_TRIS);
+    run(&device, false);
+    run(&device, true);
+}
@@ -43,4 +43,19 @@ impl Camera {
             + px.1 / (self.img.1 as f32) * self.screen_dv)
             .normalize()
     }
+    /// Project the world space point into the image, returning its pixel
+    /// coordinates. Returns None if the point is behind the camera.
+    pub fn project(&self, p: Vector3) -> Option<(f32, f32)> {
+        let dz = self.dir_top_left + 0.5 * self.screen_du + 0.5 * self.screen_dv;
+        let d = p - self.pos;
+        let depth = d.dot(dz);
+        if depth <= 0.0 {
+            return None;
+        }
+        let q = d / depth - self.dir_top_left;
+        Some((
+            q.dot(self.screen_du) / self.screen_du.magnitude2() * self.img.0 as f32,
+            q.dot(self.screen_dv) / self.screen_dv.magnitude2() * self.img.1 as f32,
+        ))
+    }
 }
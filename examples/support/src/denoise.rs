@@ -0,0 +1,62 @@
+use std::iter;
+
+use image::RgbImage;
+use oidn;
+
+/// Denoiser for the progressively accumulated images rendered by the
+/// examples, using Open Image Denoise. The color, albedo and normal
+/// buffers are all 3 channel f32 images in row-major order, the same
+/// layout as the RgbImage the Display renders into.
+pub struct Denoiser {
+    device: oidn::Device,
+    output: Vec<f32>,
+}
+
+impl Denoiser {
+    pub fn new() -> Denoiser {
+        Denoiser {
+            device: oidn::Device::new(),
+            output: Vec::new(),
+        }
+    }
+
+    /// Denoise the `width` x `height` color image passed, optionally guided
+    /// by the first-hit albedo and normal AOVs. Returns the denoised image,
+    /// which is valid until the next call to `denoise`.
+    pub fn denoise(
+        &mut self,
+        width: usize,
+        height: usize,
+        color: &[f32],
+        albedo_normal: Option<(&[f32], &[f32])>,
+    ) -> &[f32] {
+        self.output.resize(color.len(), 0.0);
+        let mut filter = oidn::RayTracing::new(&self.device);
+        filter.srgb(true).image_dimensions(width, height);
+        if let Some((albedo, normal)) = albedo_normal {
+            filter.albedo_normal(albedo, normal);
+        }
+        if let Err(e) = filter.filter(color, &mut self.output) {
+            eprintln!("Denoising failed: {:?}", e);
+            self.output.copy_from_slice(color);
+        }
+        if let Err(e) = self.device.get_error() {
+            eprintln!("OIDN device error: {:?}", e);
+        }
+        &self.output
+    }
+}
+
+/// Expand a single channel image (e.g. the AO accumulation buffer) to the
+/// 3 channel layout expected by the denoiser
+pub fn gray_to_rgb(gray: &[f32], rgb: &mut Vec<f32>) {
+    rgb.clear();
+    rgb.extend(gray.iter().flat_map(|v| iter::repeat(*v).take(3)));
+}
+
+/// Write a 3 channel f32 image with values in [0, 1] to the display image
+pub fn write_rgb_image(rgb: &[f32], image: &mut RgbImage) {
+    for (p, v) in image.as_mut().iter_mut().zip(rgb.iter()) {
+        *p = (v.max(0.0).min(1.0) * 255.0) as u8;
+    }
+}
@@ -1,3 +1,9 @@
+use std::collections::VecDeque;
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
 use arcball::ArcballCamera;
 use cgmath::InnerSpace;
 use cgmath::{Matrix4, SquareMatrix, Vector2, Vector3, Vector4};
@@ -8,18 +14,31 @@ use glium::glutin::{
 use glium::texture::RawImage2d;
 use glium::Texture2d;
 use glium::{self, glutin, Surface};
-use image::RgbImage;
+use image::{Rgb, RgbImage};
+use png;
 use AABB;
 
 /// Manager to display the rendered image in an interactive window.
+///
+/// Along with the camera controls, the window has hotkeys for capturing
+/// what's rendered and checking performance:
+///
+/// - F12 saves a screenshot to `screenshot-<time>.png`
+/// - F10 starts or stops recording each frame to `capture-<time>/`
+/// - F3 shows or hides a graph of the recent frame times
+///
+/// The PNGs are saved with the window title, camera pose and any metadata
+/// set with `metadata` as text chunks, so the view can be reproduced.
 pub struct Display {
     window_dims: (u32, u32),
     event_loop: glutin::EventsLoop,
     display: glium::Display,
     aabb: Option<AABB>,
+    title: String,
+    metadata: Vec<(String, String)>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Copy, Clone)]
 pub struct CameraPose {
     pub pos: Vector3<f32>,
     pub dir: Vector3<f32>,
@@ -44,6 +63,8 @@ impl Display {
             event_loop,
             display,
             aabb: None,
+            title: title.to_owned(),
+            metadata: Vec::new(),
         }
     }
 
@@ -52,6 +73,13 @@ impl Display {
         self
     }
 
+    /// Add metadata saved with screenshots and captured frames, e.g. the
+    /// scene file and shading mode
+    pub fn metadata(mut self, key: &str, value: &str) -> Display {
+        self.metadata.push((key.to_owned(), value.to_owned()));
+        self
+    }
+
     /// The function passed should render and update the image to be displayed in the window,
     /// optionally using the camera pose information passed.
     pub fn run<F>(&mut self, mut render: F)
@@ -90,14 +118,26 @@ impl Display {
 
         let mut mouse_pressed = [false, false];
         let mut prev_mouse = None;
+        let mut frame_graph = FrameGraph::new();
+        let mut show_frame_graph = false;
+        let mut recording: Option<(PathBuf, usize)> = None;
         let t_start = clock_ticks::precise_time_s();
+        let mut t_frame = t_start;
         loop {
             let mut should_quit = false;
+            let mut screenshot = false;
+            let mut toggle_recording = false;
             self.event_loop.poll_events(|e| match e {
                 Event::WindowEvent { event, .. } => match event {
                     WindowEvent::CloseRequested => should_quit = true,
                     WindowEvent::KeyboardInput { input, .. } => match input.virtual_keycode {
                         Some(VirtualKeyCode::Escape) => should_quit = true,
+                        Some(key) if input.state == ElementState::Pressed => match key {
+                            VirtualKeyCode::F12 => screenshot = true,
+                            VirtualKeyCode::F10 => toggle_recording = true,
+                            VirtualKeyCode::F3 => show_frame_graph = !show_frame_graph,
+                            _ => {}
+                        },
                         _ => {}
                     },
                     WindowEvent::CursorMoved { position, .. } if prev_mouse.is_none() => {
@@ -151,8 +191,65 @@ impl Display {
                 cam_pose,
                 (clock_ticks::precise_time_s() - t_start) as f32,
             );
-            let img =
-                RawImage2d::from_raw_rgb_reversed(embree_target.get(..).unwrap(), self.window_dims);
+
+            if toggle_recording {
+                recording = match recording {
+                    Some((dir, frames)) => {
+                        println!("Captured {} frames to {}", frames, dir.display());
+                        None
+                    }
+                    None => {
+                        let dir = PathBuf::from(format!("capture-{}", unix_time()));
+                        match fs::create_dir_all(&dir) {
+                            Ok(_) => Some((dir, 0)),
+                            Err(e) => {
+                                eprintln!("Failed to create {}: {}", dir.display(), e);
+                                None
+                            }
+                        }
+                    }
+                };
+            }
+            let mut capture_failed = false;
+            if let Some((ref dir, ref mut frames)) = recording {
+                let path = dir.join(format!("frame-{:05}.png", frames));
+                match self.save_png(&embree_target, &cam_pose, &path) {
+                    Ok(_) => *frames += 1,
+                    Err(e) => {
+                        eprintln!(
+                            "Stopped recording, failed to save {}: {}",
+                            path.display(),
+                            e
+                        );
+                        capture_failed = true;
+                    }
+                }
+            }
+            if capture_failed {
+                recording = None;
+            }
+            if screenshot {
+                let path = PathBuf::from(format!("screenshot-{}.png", unix_time()));
+                match self.save_png(&embree_target, &cam_pose, &path) {
+                    Ok(_) => println!("Saved screenshot to {}", path.display()),
+                    Err(e) => eprintln!("Failed to save {}: {}", path.display(), e),
+                }
+            }
+
+            // The graph is drawn over a copy, so it isn't captured and
+            // doesn't overwrite the render's image
+            let t_now = clock_ticks::precise_time_s();
+            frame_graph.push(((t_now - t_frame) * 1000.0) as f32);
+            t_frame = t_now;
+            let mut overlay = None;
+            if show_frame_graph {
+                let mut img = embree_target.clone();
+                frame_graph.draw(&mut img);
+                overlay = Some(img);
+            }
+            let shown = overlay.as_ref().unwrap_or(&embree_target);
+
+            let img = RawImage2d::from_raw_rgb_reversed(shown.get(..).unwrap(), self.window_dims);
             let opengl_texture = Texture2d::new(&self.display, img).unwrap();
 
             // Upload and blit the rendered image to display it
@@ -163,4 +260,105 @@ impl Display {
             target.finish().unwrap();
         }
     }
+
+    /// Save the image as a PNG with the metadata and camera pose
+    fn save_png(
+        &self,
+        img: &RgbImage,
+        camera_pose: &CameraPose,
+        path: &Path,
+    ) -> Result<(), png::EncodingError> {
+        let mut metadata = vec![
+            ("Title".to_owned(), self.title.clone()),
+            ("Camera".to_owned(), format!("{:?}", camera_pose)),
+        ];
+        metadata.extend(self.metadata.iter().cloned());
+        write_png(img, &metadata, path)
+    }
+}
+
+/// Write the image to a PNG with the metadata as text chunks
+fn write_png(
+    img: &RgbImage,
+    metadata: &[(String, String)],
+    path: &Path,
+) -> Result<(), png::EncodingError> {
+    let file = BufWriter::new(File::create(path)?);
+    let mut encoder = png::Encoder::new(file, img.width(), img.height());
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    for &(ref key, ref value) in metadata {
+        encoder.add_text_chunk(key.clone(), value.clone())?;
+    }
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(img.as_raw())
+}
+
+/// Seconds since the Unix epoch, to give captures unique names
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |t| t.as_secs())
+}
+
+/// The times of the most recent frames, drawn as a bar graph in the
+/// corner of the image. Each bar is a frame, colored green if it ran at
+/// 60 FPS or faster, yellow for 30 FPS or faster and red if slower. The
+/// graph is cut off at 50ms.
+struct FrameGraph {
+    /// The frame times in milliseconds, the most recent last
+    times: VecDeque<f32>,
+}
+
+impl FrameGraph {
+    const FRAMES: usize = 128;
+    const HEIGHT: u32 = 64;
+    const MAX_MS: f32 = 50.0;
+
+    fn new() -> FrameGraph {
+        FrameGraph {
+            times: VecDeque::with_capacity(FrameGraph::FRAMES),
+        }
+    }
+
+    fn push(&mut self, ms: f32) {
+        if self.times.len() == FrameGraph::FRAMES {
+            self.times.pop_front();
+        }
+        self.times.push_back(ms);
+    }
+
+    fn draw(&self, img: &mut RgbImage) {
+        let width = (FrameGraph::FRAMES as u32).min(img.width());
+        let height = FrameGraph::HEIGHT.min(img.height());
+        let ms_to_px = height as f32 / FrameGraph::MAX_MS;
+        let target_60fps = height - (1000.0 / 60.0 * ms_to_px) as u32;
+        // Darken the background so the graph is readable over the image
+        for y in 0..height {
+            for x in 0..width {
+                let p = img.get_pixel_mut(x, y);
+                *p = Rgb([p[0] / 4, p[1] / 4, p[2] / 4]);
+            }
+            img.put_pixel(width - 1, y, Rgb([128, 128, 128]));
+        }
+        // Right align the bars so the latest frame is always at the edge
+        let skip = self.times.len().saturating_sub(width as usize);
+        let x0 = width as usize - (self.times.len() - skip);
+        for (i, ms) in self.times.iter().skip(skip).enumerate() {
+            let color = if *ms <= 1000.0 / 60.0 {
+                Rgb([0, 220, 0])
+            } else if *ms <= 1000.0 / 30.0 {
+                Rgb([230, 200, 0])
+            } else {
+                Rgb([230, 0, 0])
+            };
+            let bar = ((ms * ms_to_px) as u32).min(height);
+            for y in height - bar..height {
+                img.put_pixel((x0 + i) as u32, y, color);
+            }
+        }
+        for x in 0..width {
+            img.put_pixel(x, target_60fps, Rgb([255, 255, 255]));
+        }
+    }
 }
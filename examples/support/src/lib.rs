@@ -1,8 +1,12 @@
 extern crate arcball;
 extern crate cgmath;
 extern crate clock_ticks;
+extern crate embree;
 extern crate glium;
 extern crate image;
+#[cfg(feature = "denoise")]
+extern crate oidn;
+extern crate png;
 
 type Mat4 = cgmath::Matrix4<f32>;
 type CgPoint = cgmath::Point3<f32>;
@@ -13,11 +17,18 @@ type Vector4 = cgmath::Vector4<f32>;
 
 pub mod aabb;
 pub mod camera;
+#[cfg(feature = "denoise")]
+pub mod denoise;
 pub mod display;
+pub mod sampling;
+pub mod tile_scheduler;
+pub mod tiled_image;
 
 pub use aabb::AABB;
 pub use camera::Camera;
 pub use display::Display;
+pub use tile_scheduler::TileScheduler;
+pub use tiled_image::{Channel, Tile, TiledImage};
 
 /// Clamp `x` to be between `min` and `max`
 pub fn clamp<T: PartialOrd>(x: T, min: T, max: T) -> T {
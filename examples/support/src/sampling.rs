@@ -0,0 +1,346 @@
+//! Sampling routines shared by the Monte Carlo examples: warping uniform
+//! samples to disks and hemispheres, stratified and low-discrepancy
+//! sample patterns, and per-thread or per-pixel random number generators.
+//!
+//! # Reproducible Renders
+//!
+//! With a random number generator per thread, which samples a pixel gets
+//! depends on which thread renders it and what that thread rendered
+//! before, which changes from run to run as the threads race for work.
+//! Accumulated images then differ slightly between runs, which gets in
+//! the way of comparing renders in tests. `SeedMode::PerPixel` instead
+//! seeds a generator for each pixel from its coordinates, the frame index
+//! and a fixed seed, so a pixel gets the same samples no matter which
+//! thread renders it or in what order.
+//!
+//! Combined with the `TileScheduler`, renders are bit-stable as long as
+//! each pixel's samples are drawn and accumulated in a fixed order within
+//! the tile that owns it. The scheduler hands each tile to one thread per
+//! frame, so only the order tiles run in varies, which doesn't affect a
+//! pixel's result:
+//!
+//! ```ignore
+//! let seeds = SeedMode::PerPixel(7);
+//! scheduler.run(framebuffer.tiles_mut(), |tile| {
+//!     let pixels: Vec<_> = tile.pixels().collect();
+//!     for (x, y) in pixels {
+//!         let color = seeds.with_pixel_rng(x, y, frame, |rng| render(x, y, rng));
+//!         tile.set(x, y, Channel::Color, &color);
+//!     }
+//! });
+//! ```
+
+use std::cell::RefCell;
+use std::collections::hash_map::DefaultHasher;
+use std::f32;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread;
+
+use cgmath::{InnerSpace, Matrix3, Point2, Vector2, Vector3};
+use embree::testing::Pcg32;
+
+/// Map a uniform sample in [0, 1)^2 to a point in the unit disk, using
+/// Shirley and Chiu's concentric mapping which preserves stratification
+pub fn concentric_sample_disk(u: Point2<f32>) -> Point2<f32> {
+    // map uniform random numbers to $[-1,1]^2$
+    let u_offset: Point2<f32> = u * 2.0 - Vector2::new(1.0, 1.0);
+    // handle degeneracy at the origin
+    if u_offset.x == 0.0 && u_offset.y == 0.0 {
+        return Point2::new(0.0, 0.0);
+    }
+    // apply concentric mapping to point
+    let (r, theta) = if u_offset.x.abs() > u_offset.y.abs() {
+        (
+            u_offset.x,
+            f32::consts::FRAC_PI_4 * (u_offset.y / u_offset.x),
+        )
+    } else {
+        (
+            u_offset.y,
+            f32::consts::FRAC_PI_2 - f32::consts::FRAC_PI_4 * (u_offset.x / u_offset.y),
+        )
+    };
+    Point2::new(r * theta.cos(), r * theta.sin())
+}
+
+/// Sample a cosine weighted direction on the hemisphere about +Z
+pub fn cosine_sample_hemisphere(u: Point2<f32>) -> Vector3<f32> {
+    let d = concentric_sample_disk(u);
+    let z = (1.0 - d.x * d.x - d.y * d.y).max(0.0).sqrt();
+    Vector3::new(d.x, d.y, z)
+}
+
+/// The PDF of sampling `dir` with `cosine_sample_hemisphere`
+pub fn cosine_hemisphere_pdf(dir: Vector3<f32>) -> f32 {
+    dir.z.max(0.0) * f32::consts::FRAC_1_PI
+}
+
+/// Sample a uniformly distributed direction on the hemisphere about +Z
+pub fn uniform_sample_hemisphere(u: Point2<f32>) -> Vector3<f32> {
+    let z = u.x;
+    let r = (1.0 - z * z).max(0.0).sqrt();
+    let phi = 2.0 * f32::consts::PI * u.y;
+    Vector3::new(r * phi.cos(), r * phi.sin(), z)
+}
+
+/// Sample a uniformly distributed direction on the unit sphere
+pub fn uniform_sample_sphere(u: Point2<f32>) -> Vector3<f32> {
+    let z = 1.0 - 2.0 * u.x;
+    let r = (1.0 - z * z).max(0.0).sqrt();
+    let phi = 2.0 * f32::consts::PI * u.y;
+    Vector3::new(r * phi.cos(), r * phi.sin(), z)
+}
+
+/// Generate `nx * ny` jittered samples in [0, 1)^2, one in each cell of an
+/// `nx` by `ny` grid
+pub fn stratified_2d(rng: &mut Pcg32, nx: u32, ny: u32) -> Vec<Point2<f32>> {
+    let mut samples = Vec::with_capacity((nx * ny) as usize);
+    for j in 0..ny {
+        for i in 0..nx {
+            samples.push(Point2::new(
+                ((i as f32 + rng.next_f32()) / nx as f32).min(ONE_MINUS_EPSILON),
+                ((j as f32 + rng.next_f32()) / ny as f32).min(ONE_MINUS_EPSILON),
+            ));
+        }
+    }
+    samples
+}
+
+/// The largest f32 below 1, used to keep samples in [0, 1)
+pub const ONE_MINUS_EPSILON: f32 = 1.0 - f32::EPSILON / 2.0;
+
+/// Compute the radical inverse of `i` in the base passed, i.e. mirror its
+/// digits about the decimal point
+pub fn radical_inverse(base: u32, mut i: u64) -> f32 {
+    let inv_base = 1.0 / base as f64;
+    let mut inv_base_n = 1.0;
+    let mut reversed = 0u64;
+    while i > 0 {
+        let next = i / base as u64;
+        reversed = reversed * base as u64 + (i - next * base as u64);
+        inv_base_n *= inv_base;
+        i = next;
+    }
+    ((reversed as f64 * inv_base_n) as f32).min(ONE_MINUS_EPSILON)
+}
+
+/// Get the i'th point of the `n` point Hammersley set in [0, 1)^2. The
+/// set is well distributed for a known sample count.
+pub fn hammersley(i: u32, n: u32) -> Point2<f32> {
+    Point2::new(i as f32 / n as f32, radical_inverse(2, i as u64))
+}
+
+/// Get the i'th point of the Halton sequence in bases 2 and 3, which is
+/// well distributed for any prefix, e.g. for progressive rendering
+pub fn halton(i: u64) -> Point2<f32> {
+    Point2::new(radical_inverse(2, i), radical_inverse(3, i))
+}
+
+/// Apply a Cranley-Patterson rotation by `offset` to the sample, to
+/// decorrelate low-discrepancy samples shared between pixels
+pub fn rotate_sample(u: Point2<f32>, offset: Point2<f32>) -> Point2<f32> {
+    let wrap = |x: f32| {
+        let x = x.fract();
+        if x < 0.0 {
+            (x + 1.0).min(ONE_MINUS_EPSILON)
+        } else {
+            x
+        }
+    };
+    Point2::new(wrap(u.x + offset.x), wrap(u.y + offset.y))
+}
+
+static NEXT_THREAD_SEED: AtomicU64 = AtomicU64::new(0);
+
+thread_local! {
+    static THREAD_RNG: RefCell<Pcg32> = RefCell::new(Pcg32::new(thread_seed()));
+}
+
+/// Seed each thread differently, from its ID and the order threads first
+/// requested a random number generator
+fn thread_seed() -> u64 {
+    let mut hasher = DefaultHasher::new();
+    thread::current().id().hash(&mut hasher);
+    NEXT_THREAD_SEED
+        .fetch_add(1, Ordering::Relaxed)
+        .hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Run `f` with the calling thread's random number generator, e.g. from
+/// the worker threads rendering different parts of the image
+pub fn with_thread_rng<R, F>(f: F) -> R
+where
+    F: FnOnce(&mut Pcg32) -> R,
+{
+    THREAD_RNG.with(|rng| f(&mut rng.borrow_mut()))
+}
+
+/// Mix the bits of `x` with the SplitMix64 finalizer. Unlike the standard
+/// library's hashers the result is specified, so it's the same on every
+/// platform and Rust version.
+fn mix64(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+/// Get the seed of the random number generator for the pixel in a frame,
+/// derived only from the pixel's coordinates, the frame index and `seed`
+pub fn pixel_seed(x: u32, y: u32, frame: u32, seed: u64) -> u64 {
+    let pixel = (x as u64) | ((y as u64) << 32);
+    mix64(mix64(mix64(seed) ^ pixel) ^ frame as u64)
+}
+
+/// Get a random number generator for the pixel in a frame, which gives the
+/// same sequence regardless of the thread rendering the pixel
+pub fn pixel_rng(x: u32, y: u32, frame: u32, seed: u64) -> Pcg32 {
+    Pcg32::new(pixel_seed(x, y, frame, seed))
+}
+
+/// How the random number generators used to render pixels are seeded
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SeedMode {
+    /// Use the rendering thread's generator, see `with_thread_rng`. This
+    /// is the fastest, but results depend on the order pixels are rendered
+    PerThread,
+    /// Seed a generator for each pixel from its coordinates, the frame
+    /// index and the seed, see `pixel_rng`, for reproducible renders
+    PerPixel(u64),
+}
+
+impl SeedMode {
+    /// Run `f` with the random number generator for the pixel in a frame
+    pub fn with_pixel_rng<R, F>(&self, x: u32, y: u32, frame: u32, f: F) -> R
+    where
+        F: FnOnce(&mut Pcg32) -> R,
+    {
+        match *self {
+            SeedMode::PerThread => with_thread_rng(f),
+            SeedMode::PerPixel(seed) => f(&mut pixel_rng(x, y, frame, seed)),
+        }
+    }
+}
+
+/// Get a random sample in [0, 1)^2 from the random number generator
+pub fn sample_2d(rng: &mut Pcg32) -> Point2<f32> {
+    Point2::new(rng.next_f32(), rng.next_f32())
+}
+
+/// An orthonormal basis about a normal, for transforming sampled directions
+/// between the local space about +Z and world space. Built with "Building
+/// an Orthonormal Basis, Revisited" by Duff et al., JCGT, 2017
+/// http://jcgt.org/published/0006/01/01/
+pub struct Frame(Matrix3<f32>);
+
+impl Frame {
+    pub fn new(n: Vector3<f32>) -> Frame {
+        let sign = 1.0f32.copysign(n.z);
+        let a = -1.0 / (sign + n.z);
+        let b = n.x * n.y * a;
+        Frame(Matrix3 {
+            x: Vector3::new(1.0 + sign * n.x * n.x * a, sign * b, -sign * n.x),
+            y: Vector3::new(b, sign + n.y * n.y * a, -n.y),
+            z: n,
+        })
+    }
+    pub fn to_world(&self, v: Vector3<f32>) -> Vector3<f32> {
+        self.0.x * v.x + self.0.y * v.y + self.0.z * v.z
+    }
+    pub fn to_local(&self, v: Vector3<f32>) -> Vector3<f32> {
+        Vector3::new(v.dot(self.0.x), v.dot(self.0.y), v.dot(self.0.z))
+    }
+}
+
+#[test]
+fn test_disk_and_hemisphere_samples() {
+    let mut rng = Pcg32::new(7);
+    for _ in 0..1000 {
+        let u = sample_2d(&mut rng);
+        let d = concentric_sample_disk(u);
+        assert!(d.x * d.x + d.y * d.y <= 1.0 + 1e-5);
+        for dir in &[
+            cosine_sample_hemisphere(u),
+            uniform_sample_hemisphere(u),
+            uniform_sample_sphere(u),
+        ] {
+            assert!((dir.magnitude() - 1.0).abs() < 1e-4);
+        }
+        assert!(cosine_sample_hemisphere(u).z >= 0.0);
+        assert!(uniform_sample_hemisphere(u).z >= 0.0);
+    }
+    assert_eq!(
+        concentric_sample_disk(Point2::new(0.5, 0.5)),
+        Point2::new(0.0, 0.0)
+    );
+}
+
+#[test]
+fn test_cosine_hemisphere_mean() {
+    // The mean of cos(theta) under the cosine weighted PDF is 2/3
+    let n = 64;
+    let mut rng = Pcg32::new(3);
+    let samples = stratified_2d(&mut rng, n, n);
+    let mean: f32 = samples
+        .iter()
+        .map(|u| cosine_sample_hemisphere(*u).z)
+        .sum::<f32>()
+        / samples.len() as f32;
+    assert!((mean - 2.0 / 3.0).abs() < 1e-3);
+}
+
+#[test]
+fn test_low_discrepancy() {
+    assert_eq!(radical_inverse(2, 0), 0.0);
+    assert_eq!(radical_inverse(2, 1), 0.5);
+    assert_eq!(radical_inverse(2, 6), 0.375);
+    assert!((radical_inverse(3, 5) - 7.0 / 9.0).abs() < 1e-6);
+    assert_eq!(hammersley(2, 4), Point2::new(0.5, 0.25));
+    assert_eq!(halton(1), Point2::new(0.5, 1.0 / 3.0));
+
+    // Each quarter of the unit square gets the same number of points
+    let mut quadrants = [0; 4];
+    for i in 0..64 {
+        let p = hammersley(i, 64);
+        let q = (p.x >= 0.5) as usize + 2 * (p.y >= 0.5) as usize;
+        quadrants[q] += 1;
+    }
+    assert_eq!(quadrants, [16; 4]);
+
+    let r = rotate_sample(Point2::new(0.75, 0.25), Point2::new(0.5, 0.5));
+    assert_eq!(r, Point2::new(0.25, 0.75));
+}
+
+#[test]
+fn test_frame_round_trip() {
+    let mut rng = Pcg32::new(11);
+    for _ in 0..100 {
+        let n = uniform_sample_sphere(sample_2d(&mut rng));
+        let frame = Frame::new(n);
+        let v = uniform_sample_sphere(sample_2d(&mut rng));
+        let back = frame.to_local(frame.to_world(v));
+        assert!((back - v).magnitude() < 1e-4);
+        assert!((frame.to_world(Vector3::new(0.0, 0.0, 1.0)) - n).magnitude() < 1e-5);
+    }
+}
+
+#[test]
+fn test_pixel_rng() {
+    let draw = |x, y, frame, seed| {
+        let mut rng = pixel_rng(x, y, frame, seed);
+        (rng.next_u32(), rng.next_u32())
+    };
+    assert_eq!(draw(3, 5, 1, 7), draw(3, 5, 1, 7));
+    assert_ne!(draw(3, 5, 1, 7), draw(5, 3, 1, 7));
+    assert_ne!(draw(3, 5, 1, 7), draw(3, 5, 2, 7));
+    assert_ne!(draw(3, 5, 1, 7), draw(3, 5, 1, 8));
+
+    // The samples don't depend on the thread drawing them
+    let mode = SeedMode::PerPixel(7);
+    let on_thread = thread::spawn(move || mode.with_pixel_rng(3, 5, 1, |rng| rng.next_u32()))
+        .join()
+        .unwrap();
+    assert_eq!(on_thread, draw(3, 5, 1, 7).0);
+}
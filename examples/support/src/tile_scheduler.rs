@@ -0,0 +1,128 @@
+//! A work stealing scheduler for rendering the tiles of an image in
+//! parallel. Splitting the tiles into a fixed partition per thread leaves
+//! threads idle while others finish their expensive tiles, e.g. tiles
+//! covering geometry with heavy filter functions. Instead, each thread
+//! takes the next tile to render from a shared atomic index until none
+//! are left, so the threads stay busy until the last tiles.
+//!
+//! The scheduler also records how long each tile took to render. With
+//! cost ordering enabled, the default, the next frame hands out the tiles
+//! which took longest first, so an expensive tile isn't started last and
+//! left running on one thread after the others are done. In an interactive
+//! viewer the cost of a tile changes little between frames, making the
+//! previous frame's times a good prediction.
+//!
+//! Which thread renders a tile, and when, changes between runs. For
+//! renders which must be the same on every run, e.g. images compared in
+//! tests, seed the random numbers of each pixel with
+//! `sampling::SeedMode::PerPixel` instead of using a generator per thread,
+//! see the `sampling` module.
+
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub struct TileScheduler {
+    threads: usize,
+    cost_ordering: bool,
+    /// The time each tile took to render in the last frame, in nanoseconds
+    tile_times: Vec<u64>,
+}
+
+impl TileScheduler {
+    /// Create a scheduler using a thread per available core
+    pub fn new() -> TileScheduler {
+        TileScheduler {
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+            cost_ordering: true,
+            tile_times: Vec::new(),
+        }
+    }
+    /// Set the number of threads to render with
+    pub fn threads(mut self, threads: usize) -> TileScheduler {
+        assert!(threads > 0, "At least one thread is needed to render");
+        self.threads = threads;
+        self
+    }
+    /// Enable or disable handing out the tiles which took longest in the
+    /// previous frame first
+    pub fn cost_ordering(mut self, enabled: bool) -> TileScheduler {
+        self.cost_ordering = enabled;
+        self
+    }
+    /// Get the time each tile took to render in the last frame
+    pub fn tile_times(&self) -> Vec<Duration> {
+        self.tile_times
+            .iter()
+            .map(|t| Duration::from_nanos(*t))
+            .collect()
+    }
+    /// Get the order to hand out the tiles in, the most expensive first
+    /// when ordering by cost. Ties and tiles without a time from the last
+    /// frame keep their order in the image.
+    fn order(&self, tiles: usize) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..tiles).collect();
+        if self.cost_ordering && self.tile_times.len() == tiles {
+            order.sort_by(|a, b| self.tile_times[*b].cmp(&self.tile_times[*a]));
+        }
+        order
+    }
+    /// Render the tiles in parallel by calling `render` on each one,
+    /// recording the time each tile took
+    pub fn run<T, F>(&mut self, tiles: &mut [T], render: F)
+    where
+        T: Send,
+        F: Fn(&mut T) + Sync,
+    {
+        let order = self.order(tiles.len());
+        // Each tile is only taken by one thread, so its lock is never
+        // contended
+        let tiles: Vec<Mutex<&mut T>> = tiles.iter_mut().map(Mutex::new).collect();
+        let times: Vec<AtomicU64> = tiles.iter().map(|_| AtomicU64::new(0)).collect();
+        let next = AtomicUsize::new(0);
+        thread::scope(|s| {
+            for _ in 0..self.threads.min(tiles.len()) {
+                s.spawn(|| loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    if i >= order.len() {
+                        break;
+                    }
+                    let t = order[i];
+                    let start = Instant::now();
+                    render(&mut tiles[t].lock().unwrap());
+                    times[t].store(start.elapsed().as_nanos() as u64, Ordering::Relaxed);
+                });
+            }
+        });
+        self.tile_times = times.into_iter().map(|t| t.into_inner()).collect();
+    }
+}
+
+impl Default for TileScheduler {
+    fn default() -> TileScheduler {
+        TileScheduler::new()
+    }
+}
+
+#[test]
+fn test_run_each_tile_once() {
+    let mut tiles = vec![0u32; 37];
+    let mut scheduler = TileScheduler::new().threads(4);
+    for frame in 1..3 {
+        scheduler.run(&mut tiles, |t| *t += 1);
+        assert!(tiles.iter().all(|t| *t == frame));
+        assert_eq!(scheduler.tile_times().len(), tiles.len());
+    }
+}
+
+#[test]
+fn test_cost_ordering() {
+    let mut scheduler = TileScheduler::new();
+    scheduler.tile_times = vec![5, 20, 5, 10];
+    assert_eq!(scheduler.order(4), vec![1, 3, 0, 2]);
+    // Times from a frame with a different number of tiles are ignored
+    assert_eq!(scheduler.order(3), vec![0, 1, 2]);
+    let scheduler = scheduler.cost_ordering(false);
+    assert_eq!(scheduler.order(4), vec![0, 1, 2, 3]);
+}
@@ -0,0 +1,249 @@
+use std::f32;
+
+use image::RgbImage;
+
+/// The arbitrary output variables (AOVs) which can be written for each
+/// pixel of a `TiledImage`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Channel {
+    /// RGB color, with values in [0, 1]
+    Color,
+    /// The world space shading normal
+    Normal,
+    /// The distance along the primary ray to the hit point
+    Depth,
+    /// The ID of the geometry hit, or `u32::MAX` if nothing was hit. The ID
+    /// is stored bit-cast to an f32 so it is represented exactly.
+    GeomId,
+}
+
+impl Channel {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Channel::Color => "color",
+            Channel::Normal => "normal",
+            Channel::Depth => "depth",
+            Channel::GeomId => "geomID",
+        }
+    }
+    /// Number of f32 components stored per pixel for the channel
+    pub fn components(&self) -> usize {
+        match *self {
+            Channel::Color | Channel::Normal => 3,
+            Channel::Depth | Channel::GeomId => 1,
+        }
+    }
+    /// Find the channel with the name passed
+    pub fn from_name(name: &str) -> Option<Channel> {
+        [
+            Channel::Color,
+            Channel::Normal,
+            Channel::Depth,
+            Channel::GeomId,
+        ]
+        .iter()
+        .find(|c| c.name() == name)
+        .cloned()
+    }
+    /// The value written for pixels where nothing is hit
+    fn clear_value(&self) -> f32 {
+        match *self {
+            Channel::Depth => f32::INFINITY,
+            Channel::GeomId => f32::from_bits(u32::max_value()),
+            _ => 0.0,
+        }
+    }
+}
+
+/// A rectangular region of a `TiledImage` which holds the data for each
+/// channel of the image over the pixels it covers. Tiles are independent,
+/// so they can be rendered in parallel and written back to the image
+/// in a single pass.
+pub struct Tile {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    channels: Vec<(Channel, Vec<f32>)>,
+}
+
+impl Tile {
+    fn new(x: u32, y: u32, width: u32, height: u32, channels: &[Channel]) -> Tile {
+        let n = (width * height) as usize;
+        Tile {
+            x,
+            y,
+            width,
+            height,
+            channels: channels
+                .iter()
+                .map(|c| (*c, vec![c.clear_value(); n * c.components()]))
+                .collect(),
+        }
+    }
+    /// Iterate over the image space coordinates of the pixels in the tile
+    pub fn pixels(&self) -> impl Iterator<Item = (u32, u32)> {
+        let (x, y, w, h) = (self.x, self.y, self.width, self.height);
+        (y..y + h).flat_map(move |j| (x..x + w).map(move |i| (i, j)))
+    }
+    /// Check if the tile stores the channel passed
+    pub fn has_channel(&self, channel: Channel) -> bool {
+        self.channels.iter().any(|c| c.0 == channel)
+    }
+    /// Write the value of `channel` for the pixel at image coordinates (i, j).
+    /// Writes to channels not stored in the image are ignored, so render
+    /// functions can write all the AOVs they compute unconditionally.
+    pub fn set(&mut self, i: u32, j: u32, channel: Channel, value: &[f32]) {
+        let px = ((j - self.y) * self.width + i - self.x) as usize;
+        if let Some(c) = self.channels.iter_mut().find(|c| c.0 == channel) {
+            let n = channel.components();
+            c.1[px * n..(px + 1) * n].copy_from_slice(&value[..n]);
+        }
+    }
+    /// Write the geometry ID for the pixel at image coordinates (i, j)
+    pub fn set_geom_id(&mut self, i: u32, j: u32, id: u32) {
+        self.set(i, j, Channel::GeomId, &[f32::from_bits(id)]);
+    }
+    /// Reset all channels in the tile to their clear values
+    pub fn clear(&mut self) {
+        for c in self.channels.iter_mut() {
+            let v = c.0.clear_value();
+            for x in c.1.iter_mut() {
+                *x = v;
+            }
+        }
+    }
+}
+
+/// An image split into tiles for parallel rendering, storing multiple named
+/// channels per pixel so e.g. the color, normal, depth and geometry ID can
+/// all be written in one render pass and displayed for debugging or passed
+/// on to a denoiser.
+pub struct TiledImage {
+    pub width: u32,
+    pub height: u32,
+    pub tile_size: u32,
+    channels: Vec<Channel>,
+    tiles: Vec<Tile>,
+}
+
+impl TiledImage {
+    pub fn new(width: u32, height: u32, tile_size: u32, channels: &[Channel]) -> TiledImage {
+        let mut tiles = Vec::new();
+        for y in (0..height).step_by(tile_size as usize) {
+            for x in (0..width).step_by(tile_size as usize) {
+                tiles.push(Tile::new(
+                    x,
+                    y,
+                    tile_size.min(width - x),
+                    tile_size.min(height - y),
+                    channels,
+                ));
+            }
+        }
+        TiledImage {
+            width,
+            height,
+            tile_size,
+            channels: channels.to_vec(),
+            tiles,
+        }
+    }
+    pub fn channels(&self) -> &[Channel] {
+        &self.channels
+    }
+    pub fn tiles(&self) -> &[Tile] {
+        &self.tiles
+    }
+    /// Get mutable access to the tiles to render them, e.g. with
+    /// rayon's `par_iter_mut`
+    pub fn tiles_mut(&mut self) -> &mut [Tile] {
+        &mut self.tiles
+    }
+    pub fn clear(&mut self) {
+        for t in self.tiles.iter_mut() {
+            t.clear();
+        }
+    }
+    /// Copy the channel into a flat row-major f32 buffer with the channel's
+    /// number of components per pixel, e.g. for use as a denoiser input.
+    /// Returns false if the image doesn't store the channel.
+    pub fn write_to_f32_buffer(&self, channel: Channel, out: &mut [f32]) -> bool {
+        if !self.channels.contains(&channel) {
+            return false;
+        }
+        let n = channel.components();
+        for t in self.tiles.iter() {
+            let data = &t.channels.iter().find(|c| c.0 == channel).unwrap().1;
+            for (row, src) in data.chunks(t.width as usize * n).enumerate() {
+                let start = (((t.y + row as u32) * self.width + t.x) as usize) * n;
+                out[start..start + src.len()].copy_from_slice(src);
+            }
+        }
+        true
+    }
+    /// Write the channel to the flat RGB8 buffer passed for display, mapping
+    /// the channel's values to colors: colors are clamped, normals are
+    /// remapped from [-1, 1], depth is normalized by the max depth in the
+    /// image and geometry IDs are given a unique random color. Returns false
+    /// if the image doesn't store the channel.
+    pub fn write_to_flat_buffer(&self, channel: Channel, out: &mut [u8]) -> bool {
+        let n = channel.components();
+        let mut data = vec![0.0; (self.width * self.height) as usize * n];
+        if !self.write_to_f32_buffer(channel, &mut data) {
+            return false;
+        }
+        let max_depth = if channel == Channel::Depth {
+            data.iter()
+                .filter(|d| d.is_finite())
+                .fold(0.0f32, |m, d| m.max(*d))
+        } else {
+            1.0
+        };
+        let to_u8 = |x: f32| (x.max(0.0).min(1.0) * 255.0) as u8;
+        for (p, v) in out.chunks_mut(3).zip(data.chunks(n)) {
+            match channel {
+                Channel::Color => {
+                    for c in 0..3 {
+                        p[c] = to_u8(v[c]);
+                    }
+                }
+                Channel::Normal => {
+                    for c in 0..3 {
+                        p[c] = to_u8(v[c] * 0.5 + 0.5);
+                    }
+                }
+                Channel::Depth => {
+                    let d = if v[0].is_finite() && max_depth > 0.0 {
+                        to_u8(1.0 - v[0] / max_depth)
+                    } else {
+                        0
+                    };
+                    p.copy_from_slice(&[d, d, d]);
+                }
+                Channel::GeomId => {
+                    let id = v[0].to_bits();
+                    if id == u32::max_value() {
+                        p.copy_from_slice(&[0, 0, 0]);
+                    } else {
+                        let h = hash_u32(id);
+                        p.copy_from_slice(&[h as u8, (h >> 8) as u8, (h >> 16) as u8]);
+                    }
+                }
+            }
+        }
+        true
+    }
+    /// Write the channel into the image to display it
+    pub fn write_to_image(&self, channel: Channel, image: &mut RgbImage) -> bool {
+        self.write_to_flat_buffer(channel, image.as_mut())
+    }
+}
+
+/// Hash an integer to get a random looking color for it
+fn hash_u32(x: u32) -> u32 {
+    let mut x = x.wrapping_add(0x9e37_79b9);
+    x = (x ^ (x >> 16)).wrapping_mul(0x85eb_ca6b);
+    x = (x ^ (x >> 13)).wrapping_mul(0xc2b2_ae35);
+    x ^ (x >> 16)
+}
@@ -5,7 +5,7 @@ extern crate embree;
 extern crate support;
 
 use cgmath::{Vector3, Vector4};
-use embree::{Device, Geometry, IntersectContext, RayHitN, RayN, Scene, TriangleMesh};
+use embree::{Device, Geometry, IntersectContext, RayHitN, RayN, Scene, Tile, TriangleMesh};
 
 fn main() {
     let mut display = support::Display::new(512, 512, "triangle");
@@ -34,19 +34,24 @@ fn main() {
     display.run(|image, _, _| {
         let img_dims = image.dimensions();
         // Render the scene
+        let mut ray_hit = RayHitN::new(RayN::new(img_dims.0 as usize));
         for j in 0..img_dims.1 {
-            let y = -(j as f32 + 0.5) / img_dims.1 as f32 + 0.5;
-
             // Try out streams of scanlines across x
-            let mut rays = RayN::new(img_dims.0 as usize);
-            for (i, mut ray) in rays.iter_mut().enumerate() {
-                let x = (i as f32 + 0.5) / img_dims.0 as f32 - 0.5;
-                let dir_len = f32::sqrt(x * x + y * y + 1.0);
-                ray.set_origin(Vector3::new(0.0, 0.5, 2.0));
-                ray.set_dir(Vector3::new(x / dir_len, y / dir_len, -1.0 / dir_len));
-            }
-
-            let mut ray_hit = RayHitN::new(rays);
+            let scanline = Tile::new(0, j, img_dims.0, 1);
+            ray_hit.fill_primary(
+                |px, py| {
+                    let x = px / img_dims.0 as f32 - 0.5;
+                    let y = -py / img_dims.1 as f32 + 0.5;
+                    let dir_len = f32::sqrt(x * x + y * y + 1.0);
+                    (
+                        Vector3::new(0.0, 0.5, 2.0),
+                        Vector3::new(x / dir_len, y / dir_len, -1.0 / dir_len),
+                    )
+                },
+                &scanline,
+                0.0,
+                u32::max_value(),
+            );
             rtscene.intersect_stream_soa(&mut intersection_ctx, &mut ray_hit);
             for (i, hit) in ray_hit.hit.iter().enumerate().filter(|(_i, h)| h.hit()) {
                 let p = image.get_pixel_mut(i as u32, j);
@@ -0,0 +1,82 @@
+//! A visibility query service over HTTP. Each request to `POST /visibility`
+//! sends a batch of point pairs as JSON and gets back whether each pair
+//! can see the other in a procedurally generated scene:
+//!
+//! ```text
+//! curl -X POST localhost:3000/visibility -H 'content-type: application/json' \
+//!     -d '{"pairs": [[[0, 0, 30], [0, 0, -30]], [[20, 20, 30], [20, 20, 25]]]}'
+//! {"visible":[false,true]}
+//! ```
+//!
+//! The scene is committed and queried on tokio's blocking thread pool
+//! through `AsyncScene`, so tracing a large batch doesn't stall the
+//! executor serving the other requests. Pass a seed to change the scene.
+
+use std::net::SocketAddr;
+
+use axum::extract::State;
+use axum::routing::post;
+use axum::{Json, Router};
+use cgmath::{InnerSpace, Vector3};
+use embree::testing::{generate_scene, SceneConfig};
+use embree::{AsyncScene, Device, Ray};
+use serde::{Deserialize, Serialize};
+
+/// How far the shadow ray between two points stops short of them, so it
+/// doesn't hit the surfaces they were sampled on
+const EPSILON: f32 = 1e-4;
+
+#[derive(Deserialize)]
+struct VisibilityRequest {
+    pairs: Vec<[[f32; 3]; 2]>,
+}
+
+#[derive(Serialize)]
+struct VisibilityResponse {
+    visible: Vec<bool>,
+}
+
+async fn visibility(
+    State(scene): State<AsyncScene>,
+    Json(request): Json<VisibilityRequest>,
+) -> Json<VisibilityResponse> {
+    let rays = request
+        .pairs
+        .iter()
+        .map(|&[a, b]| {
+            let a = Vector3::from(a);
+            let d = Vector3::from(b) - a;
+            let len = d.magnitude();
+            Ray::segment(a, d / len, EPSILON, len - EPSILON)
+        })
+        .collect();
+    let occluded = scene.occluded_async(rays).await;
+    Json(VisibilityResponse {
+        visible: occluded.into_iter().map(|o| !o).collect(),
+    })
+}
+
+#[tokio::main]
+async fn main() {
+    let seed = std::env::args()
+        .nth(1)
+        .map(|s| s.parse::<u64>().expect("seed must be an integer"))
+        .unwrap_or(0);
+
+    // The device serves the scene for as long as the service runs
+    let device: &'static Device = Box::leak(Box::new(Device::new()));
+    let config = SceneConfig::new()
+        .spheres(256)
+        .meshes(64)
+        .resolution(32)
+        .seed(seed);
+    let scene = generate_scene(device, &config, None).commit_async().await;
+
+    let app = Router::new()
+        .route("/visibility", post(visibility))
+        .with_state(scene);
+    let addr = SocketAddr::from(([127, 0, 0, 1], 3000));
+    let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
+    println!("Serving visibility queries on http://{}/visibility", addr);
+    axum::serve(listener, app).await.unwrap();
+}
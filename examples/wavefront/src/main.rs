@@ -0,0 +1,305 @@
+//! A wavefront path tracer built on the ray stream APIs. Instead of
+//! tracing each path to completion, the paths of a tile advance together
+//! one bounce at a time through three queues:
+//!
+//! - the ray queue, the extension rays of the live paths, which is traced
+//!   with a single stream intersection per bounce,
+//! - the hit queue, the results of the ray queue partitioned by the
+//!   geometry hit so each material is shaded as a batch,
+//! - the shadow queue, the light sampling rays spawned while shading,
+//!   which is traced with a single stream occlusion query per bounce.
+//!
+//! Paths which escape the scene or are terminated by Russian roulette are
+//! compacted out of the ray queue, so the stream shrinks as the bounces
+//! go on, with the per-path state compacted along with it. Renders a
+//! procedurally generated scene lit by a sun and sky to `wavefront.png`.
+
+extern crate cgmath;
+extern crate embree;
+extern crate image;
+extern crate support;
+
+use std::f32;
+use std::time::Instant;
+
+use cgmath::{ElementWise, InnerSpace, Vector3};
+use embree::testing::{generate_scene, Pcg32, SceneConfig};
+use embree::{
+    CommittedScene, Device, HitPartition, IntersectContext, RayHitN, RayN, RayStateVec, SoAHit,
+    SoARay, Tile,
+};
+use support::sampling::{cosine_sample_hemisphere, sample_2d, Frame};
+use support::Camera;
+
+const WIDTH: u32 = 512;
+const HEIGHT: u32 = 512;
+const TILE_SIZE: u32 = 64;
+const SAMPLES: u32 = 16;
+const MAX_DEPTH: u32 = 8;
+/// Paths are terminated by Russian roulette after this many bounces
+const ROULETTE_DEPTH: u32 = 2;
+const SHADOW_EPSILON: f32 = 1e-3;
+
+/// The per-path state carried along with each ray in the ray queue
+#[derive(Copy, Clone)]
+struct Path {
+    pixel: usize,
+    throughput: Vector3<f32>,
+}
+
+/// The queues of the wavefront, allocated once for the largest tile and
+/// reused for every tile, sample and bounce
+struct Queues {
+    rays: RayHitN,
+    paths: RayStateVec<Path>,
+    hits: HitPartition,
+    shadow: RayN,
+    /// The pixel and unoccluded contribution of each shadow ray
+    shadow_contrib: Vec<(usize, Vector3<f32>)>,
+    /// The ray queue indices of the paths which continue to the next bounce
+    live: Vec<u32>,
+}
+
+impl Queues {
+    fn new(n: usize) -> Queues {
+        Queues {
+            rays: RayHitN::new(RayN::new(n)),
+            paths: RayStateVec::with_capacity(n),
+            hits: HitPartition::with_capacity(n),
+            shadow: RayN::new(n),
+            shadow_contrib: Vec::with_capacity(n),
+            live: Vec::with_capacity(n),
+        }
+    }
+}
+
+/// Counts of the rays traced, to see how the queues shrink with depth
+#[derive(Default)]
+struct Stats {
+    rays: Vec<usize>,
+    shadow_rays: usize,
+}
+
+struct Lights {
+    sun_dir: Vector3<f32>,
+    sun_irradiance: Vector3<f32>,
+}
+
+impl Lights {
+    fn sky(&self, dir: Vector3<f32>) -> Vector3<f32> {
+        let t = 0.5 * (dir.y + 1.0);
+        Vector3::new(1.0, 1.0, 1.0) * (1.0 - t) + Vector3::new(0.5, 0.7, 1.0) * t
+    }
+}
+
+/// A diffuse albedo for each geometry, hashed from its ID
+fn albedo(geom_id: u32) -> Vector3<f32> {
+    let mut rng = Pcg32::new(geom_id as u64);
+    Vector3::new(
+        rng.range(0.2, 0.9),
+        rng.range(0.2, 0.9),
+        rng.range(0.2, 0.9),
+    )
+}
+
+fn max_component(v: Vector3<f32>) -> f32 {
+    v.x.max(v.y).max(v.z)
+}
+
+/// Shade the hit queue, filling the shadow queue with a sun sample for
+/// each hit and writing the bounce rays of the surviving paths in place
+/// over their current rays in the ray queue
+fn shade_hits(q: &mut Queues, lights: &Lights, depth: u32, rng: &mut Pcg32) {
+    let Queues {
+        ref mut rays,
+        ref mut paths,
+        ref hits,
+        ref mut shadow,
+        ref mut shadow_contrib,
+        ref mut live,
+    } = *q;
+    shadow.resize(shadow.capacity());
+    shadow_contrib.clear();
+    live.clear();
+    for (geom_id, group) in hits.groups() {
+        let albedo = albedo(geom_id);
+        for i in group.iter().map(|i| *i as usize) {
+            let dir = rays.ray.dir(i);
+            let p = rays.ray.org(i) + dir * rays.ray.tfar(i);
+            let mut n = rays.hit.normal(i).normalize();
+            if n.dot(dir) > 0.0 {
+                n = -n;
+            }
+            let path = &mut paths[i];
+            let f = albedo / f32::consts::PI;
+
+            let cos_sun = n.dot(lights.sun_dir);
+            if cos_sun > 0.0 {
+                let s = shadow_contrib.len();
+                shadow.set_org(s, p + n * SHADOW_EPSILON);
+                shadow.set_dir(s, lights.sun_dir);
+                shadow.set_tnear(s, 0.0);
+                shadow.set_tfar(s, f32::INFINITY);
+                let contrib = path
+                    .throughput
+                    .mul_element_wise(f)
+                    .mul_element_wise(lights.sun_irradiance)
+                    * cos_sun;
+                shadow_contrib.push((path.pixel, contrib));
+            }
+
+            // Cosine sampling the bounce cancels the cosine and pi of the
+            // diffuse BRDF, leaving just the albedo
+            path.throughput = path.throughput.mul_element_wise(albedo);
+            if depth >= ROULETTE_DEPTH {
+                let survive = max_component(path.throughput).min(0.95);
+                if rng.next_f32() >= survive {
+                    continue;
+                }
+                path.throughput /= survive;
+            }
+            let w = Frame::new(n).to_world(cosine_sample_hemisphere(sample_2d(rng)));
+            rays.ray.set_org(i, p + n * SHADOW_EPSILON);
+            rays.ray.set_dir(i, w);
+            rays.ray.set_tnear(i, 0.0);
+            rays.ray.set_tfar(i, f32::INFINITY);
+            live.push(i as u32);
+        }
+    }
+    shadow.resize(shadow_contrib.len());
+    // The groups visit the rays out of order, but compaction needs the
+    // indices sorted
+    live.sort_unstable();
+}
+
+/// The scene and lights being rendered and the image the paths
+/// contributions are accumulated into
+struct Renderer<'a> {
+    scene: &'a CommittedScene<'a>,
+    camera: Camera,
+    lights: Lights,
+    rng: Pcg32,
+    image: Vec<Vector3<f32>>,
+    stats: Stats,
+}
+
+impl<'a> Renderer<'a> {
+    fn render_tile(&mut self, tile: &Tile, q: &mut Queues) {
+        let mut ctx = IntersectContext::incoherent();
+        for _ in 0..SAMPLES {
+            // Jitter the primary rays of the tile within their pixels
+            let jitter = sample_2d(&mut self.rng);
+            let camera = &self.camera;
+            q.rays.resize(tile.pixel_count());
+            q.rays.fill_primary(
+                |x, y| {
+                    let px = (x - 0.5 + jitter.x, y - 0.5 + jitter.y);
+                    (camera.pos, camera.ray_dir(px))
+                },
+                tile,
+                0.0,
+                u32::MAX,
+            );
+            // The primary rays are in row-major order over the tile
+            q.paths.fill_with(tile.pixel_count(), |r| {
+                let (i, j) = (r as u32 % tile.width, r as u32 / tile.width);
+                Path {
+                    pixel: ((tile.y + j) * WIDTH + tile.x + i) as usize,
+                    throughput: Vector3::new(1.0, 1.0, 1.0),
+                }
+            });
+
+            for depth in 0..MAX_DEPTH {
+                if q.rays.len() == 0 {
+                    break;
+                }
+                if self.stats.rays.len() <= depth as usize {
+                    self.stats.rays.push(0);
+                }
+                self.stats.rays[depth as usize] += q.rays.len();
+
+                self.scene.intersect_stream_soa(&mut ctx, &mut q.rays);
+                q.hits.partition_by_geom_id(&q.rays.hit);
+                for i in q.hits.misses().iter().map(|i| *i as usize) {
+                    let path = &q.paths[i];
+                    let sky = self.lights.sky(q.rays.ray.dir(i));
+                    self.image[path.pixel] += path.throughput.mul_element_wise(sky);
+                }
+
+                shade_hits(q, &self.lights, depth, &mut self.rng);
+                self.stats.shadow_rays += q.shadow.len();
+                self.scene.occluded_stream_soa(&mut ctx, &mut q.shadow);
+                for (s, &(pixel, contrib)) in q.shadow_contrib.iter().enumerate() {
+                    // Embree sets tfar to -inf for occluded rays
+                    if q.shadow.tfar(s) >= 0.0 {
+                        self.image[pixel] += contrib;
+                    }
+                }
+
+                q.paths.compact_with(&mut q.rays, &q.live);
+                q.rays.hit.reset();
+            }
+        }
+    }
+}
+
+fn main() {
+    let device = Device::new();
+    let config = SceneConfig::new().spheres(48).meshes(6).seed(3);
+    let scene = generate_scene(&device, &config, None);
+    let rtscene = scene.commit();
+
+    let camera = Camera::look_at(
+        Vector3::new(0.0, 6.0, 28.0),
+        Vector3::new(0.0, 0.0, 0.0),
+        Vector3::new(0.0, 1.0, 0.0),
+        50.0,
+        (WIDTH, HEIGHT),
+    );
+    let lights = Lights {
+        sun_dir: Vector3::new(0.4, 1.0, 0.3).normalize(),
+        sun_irradiance: Vector3::new(3.0, 2.8, 2.5),
+    };
+
+    let mut renderer = Renderer {
+        scene: &rtscene,
+        camera,
+        lights,
+        rng: Pcg32::new(0),
+        image: vec![Vector3::new(0.0, 0.0, 0.0); (WIDTH * HEIGHT) as usize],
+        stats: Stats::default(),
+    };
+    let mut queues = Queues::new((TILE_SIZE * TILE_SIZE) as usize);
+    let start = Instant::now();
+    for y in (0..HEIGHT).step_by(TILE_SIZE as usize) {
+        for x in (0..WIDTH).step_by(TILE_SIZE as usize) {
+            let tile = Tile::new(x, y, TILE_SIZE.min(WIDTH - x), TILE_SIZE.min(HEIGHT - y));
+            renderer.render_tile(&tile, &mut queues);
+        }
+    }
+    let elapsed = start.elapsed();
+
+    let stats = &renderer.stats;
+    let total: usize = stats.rays.iter().sum::<usize>() + stats.shadow_rays;
+    println!(
+        "Rendered {}x{} at {} spp in {:.2}s ({:.2} Mrays/s)",
+        WIDTH,
+        HEIGHT,
+        SAMPLES,
+        elapsed.as_secs_f64(),
+        total as f64 / elapsed.as_secs_f64() / 1e6
+    );
+    for (depth, n) in stats.rays.iter().enumerate() {
+        println!("bounce {}: {} rays", depth, n);
+    }
+    println!("shadow rays: {}", stats.shadow_rays);
+
+    let mut out = image::RgbImage::new(WIDTH, HEIGHT);
+    for (p, c) in out.pixels_mut().zip(renderer.image.iter()) {
+        let c = *c / SAMPLES as f32;
+        let to_srgb = |x: f32| (x.clamp(0.0, 1.0).powf(1.0 / 2.2) * 255.0) as u8;
+        p.0 = [to_srgb(c.x), to_srgb(c.y), to_srgb(c.z)];
+    }
+    out.save("wavefront.png")
+        .expect("failed to save wavefront.png");
+}
@@ -0,0 +1,154 @@
+#ifndef EMBREE_RS_H
+#define EMBREE_RS_H
+
+/* Generated with cbindgen from src/capi.rs, don't edit by hand */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/*
+ The ID returned in place of a geometry, primitive or instance ID when
+ there's none
+ */
+#define EMBREE_RS_INVALID_ID UINT32_MAX
+
+/*
+ An Embree device
+ */
+typedef struct EmbreeRsDevice EmbreeRsDevice;
+
+/*
+ A scene and the geometry attached to it
+ */
+typedef struct EmbreeRsScene EmbreeRsScene;
+
+/*
+ A ray to query a scene with
+ */
+typedef struct EmbreeRsRay {
+  float org[3];
+  float dir[3];
+  /*
+   The start of the ray's interval
+   */
+  float tnear;
+  /*
+   The end of the ray's interval, e.g. `INFINITY`
+   */
+  float tfar;
+} EmbreeRsRay;
+
+/*
+ The closest hit found by a query
+ */
+typedef struct EmbreeRsHit {
+  /*
+   The distance along the ray to the hit
+   */
+  float t;
+  /*
+   The parametric coordinates of the hit on the primitive
+   */
+  float u;
+  float v;
+  /*
+   The unnormalized geometric normal of the hit
+   */
+  float ng[3];
+  /*
+   The ID of the geometry hit, as returned when adding it
+   */
+  uint32_t geom_id;
+  uint32_t prim_id;
+  /*
+   The ID of the instance the hit was found through, if any
+   */
+  uint32_t inst_id;
+} EmbreeRsHit;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/*
+ Create a device with the default configuration. Returns null if the
+ device can't be created.
+ */
+EmbreeRsDevice *embree_rs_device_new(void);
+
+/*
+ Free a device, null is ignored.
+
+ # Safety
+ `device` must be null or returned by `embree_rs_device_new`, and the
+ scenes created on it must have been freed.
+ */
+void embree_rs_device_free(EmbreeRsDevice *device);
+
+/*
+ Create an empty scene on the device. Returns null if `device` is null.
+
+ # Safety
+ `device` must be null or a live device, and must outlive the scene.
+ */
+EmbreeRsScene *embree_rs_scene_new(const EmbreeRsDevice *device);
+
+/*
+ Free a scene and its geometry, null is ignored.
+
+ # Safety
+ `scene` must be null or returned by `embree_rs_scene_new`, and mustn't
+ be in use by other threads.
+ */
+void embree_rs_scene_free(EmbreeRsScene *scene);
+
+/*
+ Add a triangle mesh to the scene, copying `num_vertices` positions of
+ three floats each and `num_triangles` triangles of three vertex indices
+ each. Returns the ID of the mesh in the scene, or
+ `EMBREE_RS_INVALID_ID` if a pointer is null, an index is out of bounds
+ or the mesh can't be created.
+
+ # Safety
+ `scene` must be a live scene not in use by other threads, and
+ `positions` and `indices` must point to arrays of at least
+ `3 * num_vertices` floats and `3 * num_triangles` indices.
+ */
+uint32_t embree_rs_scene_add_triangle_mesh(EmbreeRsScene *scene,
+                                           const float *positions,
+                                           size_t num_vertices,
+                                           const uint32_t *indices,
+                                           size_t num_triangles);
+
+/*
+ Commit the scene, building its BVH so it can be queried. Returns false
+ if `scene` is null or the commit failed, in which case queries on the
+ scene miss until it's committed again.
+
+ # Safety
+ `scene` must be null or a live scene not in use by other threads.
+ */
+bool embree_rs_scene_commit(EmbreeRsScene *scene);
+
+/*
+ Find the closest hit along the ray, writing it to `hit` and returning
+ true if there is one. Returns false if the ray misses, a pointer is
+ null, the scene changed since it was last committed or the query
+ failed.
+
+ # Safety
+ `scene` must be a live scene, and `ray` and `hit` must point to a valid
+ ray and hit to write.
+ */
+bool embree_rs_scene_intersect1(const EmbreeRsScene *scene,
+                                const EmbreeRsRay *ray,
+                                EmbreeRsHit *hit);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+#endif /* EMBREE_RS_H */
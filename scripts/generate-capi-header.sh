@@ -0,0 +1,4 @@
+#!/bin/bash
+
+# Generate the header for the C API enabled by the capi feature
+cbindgen --config cbindgen.toml --crate embree --output include/embree_rs.h
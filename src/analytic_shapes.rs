@@ -0,0 +1,372 @@
+//! Cones and capsules for user geometries, the proxy shapes commonly used
+//! for collision and visibility queries, intersected exactly instead of
+//! being tessellated. Put a set of shapes in a scene with
+//! `UserGeometry::new`:
+//!
+//! ```no_run
+//! # extern crate cgmath;
+//! # extern crate embree;
+//! # use cgmath::Vector3;
+//! # use embree::{Capsule, Device, Geometry, Scene, UserGeometry};
+//! # let device = Device::new();
+//! let limbs = vec![
+//!     Capsule::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 1.0, 0.0), 0.2),
+//!     Capsule::new(Vector3::new(0.0, 1.0, 0.0), Vector3::new(0.5, 1.5, 0.0), 0.1),
+//! ];
+//! let mut geom = Geometry::User(UserGeometry::new(&device, limbs));
+//! geom.commit();
+//! let mut scene = Scene::new(&device);
+//! scene.attach_geometry(geom);
+//! ```
+
+use std::f32;
+
+use cgmath::{InnerSpace, Vector3};
+
+use user_geometry::{AnalyticShape, ShapeHit};
+use Bounds;
+
+/// A cone with its tip cut off, capped by flat disks at both ends. The
+/// radius varies linearly from `r0` at `p0` to `r1` at `p1`, a radius of
+/// 0 gives a pointed cone and equal radii give a capped cylinder.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Cone {
+    pub p0: Vector3<f32>,
+    pub r0: f32,
+    pub p1: Vector3<f32>,
+    pub r1: f32,
+}
+
+/// A cylinder with hemispherical caps, i.e. the points within `radius` of
+/// the segment from `p0` to `p1`
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Capsule {
+    pub p0: Vector3<f32>,
+    pub p1: Vector3<f32>,
+    pub radius: f32,
+}
+
+impl Cone {
+    /// Create a cone from `p0` with radius `r0` to `p1` with radius `r1`.
+    ///
+    /// Panics if the radii are negative or the ends are the same point.
+    pub fn new(p0: Vector3<f32>, r0: f32, p1: Vector3<f32>, r1: f32) -> Cone {
+        assert!(r0 >= 0.0 && r1 >= 0.0, "Cone radii must not be negative");
+        assert!(p0 != p1, "A cone's ends must be different points");
+        Cone { p0, r0, p1, r1 }
+    }
+}
+
+impl Capsule {
+    /// Create a capsule around the segment from `p0` to `p1`. The ends can
+    /// be the same point, giving a sphere.
+    ///
+    /// Panics if the radius is negative.
+    pub fn new(p0: Vector3<f32>, p1: Vector3<f32>, radius: f32) -> Capsule {
+        assert!(radius >= 0.0, "A capsule's radius must not be negative");
+        Capsule { p0, p1, radius }
+    }
+}
+
+/// Get the bounds of the disk of `radius` around `center` with `axis` as
+/// its normal, which extends by `radius * sqrt(1 - axis_i^2)` along axis i
+fn disk_bounds(center: Vector3<f32>, axis: Vector3<f32>, radius: f32) -> Bounds {
+    let e = Vector3::new(
+        radius * (1.0 - axis.x * axis.x).max(0.0).sqrt(),
+        radius * (1.0 - axis.y * axis.y).max(0.0).sqrt(),
+        radius * (1.0 - axis.z * axis.z).max(0.0).sqrt(),
+    );
+    Bounds {
+        lower_x: center.x - e.x,
+        lower_y: center.y - e.y,
+        lower_z: center.z - e.z,
+        align0: 0.0,
+        upper_x: center.x + e.x,
+        upper_y: center.y + e.y,
+        upper_z: center.z + e.z,
+        align1: 0.0,
+    }
+}
+
+fn union(a: Bounds, b: Bounds) -> Bounds {
+    Bounds {
+        lower_x: a.lower_x.min(b.lower_x),
+        lower_y: a.lower_y.min(b.lower_y),
+        lower_z: a.lower_z.min(b.lower_z),
+        align0: 0.0,
+        upper_x: a.upper_x.max(b.upper_x),
+        upper_y: a.upper_y.max(b.upper_y),
+        upper_z: a.upper_z.max(b.upper_z),
+        align1: 0.0,
+    }
+}
+
+/// Find the roots of `a t^2 + b t + c`, smallest first
+fn solve_quadratic(a: f32, b: f32, c: f32) -> Option<(f32, f32)> {
+    if a.abs() < 1e-12 {
+        if b.abs() < 1e-12 {
+            return None;
+        }
+        let t = -c / b;
+        return Some((t, t));
+    }
+    let discrim = b * b - 4.0 * a * c;
+    if discrim < 0.0 {
+        return None;
+    }
+    // Avoid cancellation by computing the root with the larger magnitude
+    // first and finding the other from the product of the roots
+    let q = -0.5 * (b + b.signum() * discrim.sqrt());
+    let (t0, t1) = if q == 0.0 { (0.0, 0.0) } else { (q / a, c / q) };
+    Some((t0.min(t1), t0.max(t1)))
+}
+
+/// Intersect the line with the sphere, returning the entry and exit points
+fn intersect_sphere(
+    center: Vector3<f32>,
+    radius: f32,
+    org: Vector3<f32>,
+    dir: Vector3<f32>,
+) -> Option<[ShapeHit; 2]> {
+    let oc = org - center;
+    let (t0, t1) = solve_quadratic(
+        dir.dot(dir),
+        2.0 * oc.dot(dir),
+        oc.dot(oc) - radius * radius,
+    )?;
+    Some([
+        ShapeHit {
+            t: t0,
+            normal: (oc + dir * t0).normalize(),
+        },
+        ShapeHit {
+            t: t1,
+            normal: (oc + dir * t1).normalize(),
+        },
+    ])
+}
+
+/// Merge the intersections of the line with two convex shapes which
+/// overlap, giving the intersection with their union
+fn merge(a: Option<[ShapeHit; 2]>, b: Option<[ShapeHit; 2]>) -> Option<[ShapeHit; 2]> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some([
+            if b[0].t < a[0].t { b[0] } else { a[0] },
+            if b[1].t > a[1].t { b[1] } else { a[1] },
+        ]),
+        (a, None) => a,
+        (None, b) => b,
+    }
+}
+
+impl AnalyticShape for Cone {
+    fn bounds(&self) -> Bounds {
+        let axis = (self.p1 - self.p0).normalize();
+        union(
+            disk_bounds(self.p0, axis, self.r0),
+            disk_bounds(self.p1, axis, self.r1),
+        )
+    }
+    fn intersect_line(&self, org: Vector3<f32>, dir: Vector3<f32>) -> Option<[ShapeHit; 2]> {
+        let height = (self.p1 - self.p0).magnitude();
+        let axis = (self.p1 - self.p0) / height;
+        // The change in radius per unit along the axis
+        let k = (self.r1 - self.r0) / height;
+        let oc = org - self.p0;
+        let ho = oc.dot(axis);
+        let hd = dir.dot(axis);
+
+        // Points inside the infinite cone have a squared distance to the
+        // axis at most the squared radius at their height h along it,
+        // |p|^2 - h^2 - (r0 + k h)^2 <= 0, a quadratic along the line
+        let rk = self.r0 + k * ho;
+        let a = dir.dot(dir) - hd * hd - k * k * hd * hd;
+        let b = 2.0 * (oc.dot(dir) - ho * hd) - 2.0 * rk * k * hd;
+        let c = oc.dot(oc) - ho * ho - rk * rk;
+        let f = |t: f32| (a * t + b) * t + c;
+
+        // The part of the line between the planes of the caps
+        let (slab0, slab1) = if hd.abs() < 1e-12 {
+            if ho < 0.0 || ho > height {
+                return None;
+            }
+            (f32::NEG_INFINITY, f32::INFINITY)
+        } else {
+            let t0 = -ho / hd;
+            let t1 = (height - ho) / hd;
+            (t0.min(t1), t0.max(t1))
+        };
+
+        // Within the slab the cone is convex, so the line enters and
+        // leaves it at the nearest and farthest of the candidate points
+        // which lie on its surface: the points on the caps' planes and the
+        // roots of the quadratic between them
+        let scale = self.r0.max(self.r1).max(height);
+        let eps = 1e-4 * scale * scale;
+        let mut candidates = Vec::with_capacity(4);
+        for &t in &[slab0, slab1] {
+            if t.is_finite() && f(t) <= eps {
+                candidates.push((t, true));
+            }
+        }
+        if let Some((t0, t1)) = solve_quadratic(a, b, c) {
+            for &t in &[t0, t1] {
+                if t >= slab0 && t <= slab1 {
+                    candidates.push((t, false));
+                }
+            }
+        }
+        let enter = candidates
+            .iter()
+            .cloned()
+            .fold(None, |m: Option<(f32, bool)>, c| match m {
+                Some(m) if m.0 <= c.0 => Some(m),
+                _ => Some(c),
+            })?;
+        let exit = candidates
+            .iter()
+            .cloned()
+            .fold(None, |m: Option<(f32, bool)>, c| match m {
+                Some(m) if m.0 >= c.0 => Some(m),
+                _ => Some(c),
+            })?;
+        let normal = |(t, on_cap): (f32, bool)| {
+            let h = ho + t * hd;
+            if on_cap {
+                if h < 0.5 * height {
+                    -axis
+                } else {
+                    axis
+                }
+            } else {
+                // The gradient of the quadratic, which points outwards
+                let q = oc + dir * t;
+                let radial = q - axis * h;
+                (radial - axis * ((self.r0 + k * h) * k)).normalize()
+            }
+        };
+        Some([
+            ShapeHit {
+                t: enter.0,
+                normal: normal(enter),
+            },
+            ShapeHit {
+                t: exit.0,
+                normal: normal(exit),
+            },
+        ])
+    }
+}
+
+impl AnalyticShape for Capsule {
+    fn bounds(&self) -> Bounds {
+        let r = self.radius;
+        let lower = Vector3::new(
+            self.p0.x.min(self.p1.x),
+            self.p0.y.min(self.p1.y),
+            self.p0.z.min(self.p1.z),
+        );
+        let upper = Vector3::new(
+            self.p0.x.max(self.p1.x),
+            self.p0.y.max(self.p1.y),
+            self.p0.z.max(self.p1.z),
+        );
+        Bounds {
+            lower_x: lower.x - r,
+            lower_y: lower.y - r,
+            lower_z: lower.z - r,
+            align0: 0.0,
+            upper_x: upper.x + r,
+            upper_y: upper.y + r,
+            upper_z: upper.z + r,
+            align1: 0.0,
+        }
+    }
+    fn intersect_line(&self, org: Vector3<f32>, dir: Vector3<f32>) -> Option<[ShapeHit; 2]> {
+        // The capsule is the union of the spheres at its ends and the
+        // cylinder between them, whose caps lie inside the spheres
+        let spheres = merge(
+            intersect_sphere(self.p0, self.radius, org, dir),
+            intersect_sphere(self.p1, self.radius, org, dir),
+        );
+        if self.p0 == self.p1 {
+            return spheres;
+        }
+        let cylinder = Cone::new(self.p0, self.radius, self.p1, self.radius);
+        merge(spheres, cylinder.intersect_line(org, dir))
+    }
+}
+
+#[test]
+fn test_cone_intersect() {
+    let cone = Cone::new(
+        Vector3::new(0.0, 0.0, 0.0),
+        1.0,
+        Vector3::new(0.0, 0.0, 2.0),
+        0.0,
+    );
+    // Along the axis, entering through the base and leaving at the tip
+    let hits = cone
+        .intersect_line(Vector3::new(0.0, 0.0, -1.0), Vector3::new(0.0, 0.0, 1.0))
+        .unwrap();
+    assert!((hits[0].t - 1.0).abs() < 1e-5);
+    assert_eq!(hits[0].normal, Vector3::new(0.0, 0.0, -1.0));
+    assert!((hits[1].t - 3.0).abs() < 1e-4);
+    // Across the side at half height, where the radius is 0.5
+    let hits = cone
+        .intersect_line(Vector3::new(-2.0, 0.0, 1.0), Vector3::new(1.0, 0.0, 0.0))
+        .unwrap();
+    assert!((hits[0].t - 1.5).abs() < 1e-5);
+    assert!((hits[1].t - 2.5).abs() < 1e-5);
+    // The side slopes at 45 degrees, so its normal is tilted up the axis
+    let n = hits[0].normal;
+    assert!((n - Vector3::new(-1.0, 0.0, 0.5).normalize()).magnitude() < 1e-5);
+    // Passing above the tip
+    assert!(cone
+        .intersect_line(Vector3::new(-2.0, 0.0, 2.5), Vector3::new(1.0, 0.0, 0.0))
+        .is_none());
+    // Passing beside the base, within the slab but outside the cone
+    assert!(cone
+        .intersect_line(Vector3::new(-2.0, 1.5, 0.5), Vector3::new(1.0, 0.0, 0.0))
+        .is_none());
+
+    let b = cone.bounds();
+    assert_eq!((b.lower_x, b.lower_y, b.lower_z), (-1.0, -1.0, 0.0));
+    assert_eq!((b.upper_x, b.upper_y, b.upper_z), (1.0, 1.0, 2.0));
+}
+
+#[test]
+fn test_capsule_intersect() {
+    let capsule = Capsule::new(
+        Vector3::new(0.0, 0.0, 0.0),
+        Vector3::new(2.0, 0.0, 0.0),
+        0.5,
+    );
+    // Along the axis through both hemispheres
+    let hits = capsule
+        .intersect_line(Vector3::new(-1.0, 0.0, 0.0), Vector3::new(1.0, 0.0, 0.0))
+        .unwrap();
+    assert!((hits[0].t - 0.5).abs() < 1e-5);
+    assert!((hits[0].normal - Vector3::new(-1.0, 0.0, 0.0)).magnitude() < 1e-5);
+    assert!((hits[1].t - 3.5).abs() < 1e-5);
+    assert!((hits[1].normal - Vector3::new(1.0, 0.0, 0.0)).magnitude() < 1e-5);
+    // Across the cylinder
+    let hits = capsule
+        .intersect_line(Vector3::new(1.0, -2.0, 0.0), Vector3::new(0.0, 1.0, 0.0))
+        .unwrap();
+    assert!((hits[0].t - 1.5).abs() < 1e-5);
+    assert!((hits[0].normal - Vector3::new(0.0, -1.0, 0.0)).magnitude() < 1e-5);
+    assert!((hits[1].t - 2.5).abs() < 1e-5);
+    // Across a hemisphere, off the cylinder
+    let hits = capsule
+        .intersect_line(Vector3::new(2.3, -2.0, 0.0), Vector3::new(0.0, 1.0, 0.0))
+        .unwrap();
+    assert!((hits[0].t - 1.6).abs() < 1e-5);
+    assert!(capsule
+        .intersect_line(Vector3::new(2.6, -2.0, 0.0), Vector3::new(0.0, 1.0, 0.0))
+        .is_none());
+
+    let b = capsule.bounds();
+    assert_eq!((b.lower_x, b.lower_y, b.lower_z), (-0.5, -0.5, -0.5));
+    assert_eq!((b.upper_x, b.upper_y, b.upper_z), (2.5, 0.5, 0.5));
+}
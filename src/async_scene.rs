@@ -0,0 +1,143 @@
+//! Commit and query helpers for async services, enabled with the `async`
+//! feature. Building a BVH or tracing a batch of rays can take long enough
+//! to stall an async executor, so the helpers run the work on tokio's
+//! blocking thread pool with `spawn_blocking` and await its result.
+//!
+//! Work sent to the blocking pool must be `'static`, so the scene must be
+//! a `Scene<'static>`, i.e. its device must live for the rest of the
+//! program. A service typically creates one device at startup and leaks
+//! it. `Scene::commit_async` moves the scene into an `AsyncScene`, a
+//! cheaply cloned handle to the committed scene which can be shared
+//! between request handlers:
+//!
+//! ```ignore
+//! let device: &'static Device = Box::leak(Box::new(Device::new()));
+//! let mut scene = Scene::new(device);
+//! // ... attach geometry
+//! let scene = scene.commit_async().await;
+//! let hits = scene.intersect_async(rays).await;
+//! ```
+//!
+//! To change the scene, take it back with `AsyncScene::into_scene` once
+//! the other handles are dropped, and commit it again.
+//!
+//! The helpers return a `Blocking` future of the work's result, which
+//! resumes the panic on the awaiting task if the work panicked.
+
+use std::future::Future;
+use std::panic;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use tokio::task::{self, JoinHandle};
+
+use ray::{IntersectContext, Ray, RayHit};
+use scene::{CommitToken, CommittedScene, Scene};
+
+/// Work running on tokio's blocking thread pool, resolving to its result.
+/// Dropping the future doesn't cancel the work.
+pub struct Blocking<T> {
+    handle: JoinHandle<T>,
+}
+
+impl<T: Send + 'static> Blocking<T> {
+    /// Run `f` on tokio's blocking thread pool.
+    ///
+    /// Panics if called outside of a tokio runtime.
+    fn spawn<F: FnOnce() -> T + Send + 'static>(f: F) -> Blocking<T> {
+        Blocking {
+            handle: task::spawn_blocking(f),
+        }
+    }
+}
+
+impl<T> Future for Blocking<T> {
+    type Output = T;
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<T> {
+        match Pin::new(&mut self.handle).poll(cx) {
+            Poll::Ready(Ok(value)) => Poll::Ready(value),
+            Poll::Ready(Err(e)) => match e.try_into_panic() {
+                Ok(payload) => panic::resume_unwind(payload),
+                Err(e) => panic!("Blocking work failed: {}", e),
+            },
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+/// A committed scene whose queries run on tokio's blocking thread pool,
+/// returned by `Scene::commit_async`. Clones share the same scene.
+#[derive(Clone)]
+pub struct AsyncScene {
+    scene: Arc<Scene<'static>>,
+    token: CommitToken,
+}
+
+impl Scene<'static> {
+    /// Commit the scene on tokio's blocking thread pool, see `commit`.
+    ///
+    /// Panics if called outside of a tokio runtime.
+    pub fn commit_async(self) -> Blocking<AsyncScene> {
+        Blocking::spawn(move || {
+            let token = self.commit().token();
+            AsyncScene {
+                scene: Arc::new(self),
+                token,
+            }
+        })
+    }
+}
+
+impl AsyncScene {
+    /// Get the token of the commit the queries run against
+    pub fn token(&self) -> CommitToken {
+        self.token
+    }
+    pub fn scene(&self) -> &Scene<'static> {
+        &self.scene
+    }
+    /// Run `f` with the committed scene on tokio's blocking thread pool,
+    /// e.g. to run queries not covered by the other helpers.
+    ///
+    /// Panics if called outside of a tokio runtime.
+    pub fn with_committed<F, R>(&self, f: F) -> Blocking<R>
+    where
+        F: FnOnce(&CommittedScene) -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let scene = self.scene.clone();
+        let token = self.token;
+        Blocking::spawn(move || f(&CommittedScene::at_commit(&scene, token)))
+    }
+    /// Find the closest hit of each ray on tokio's blocking thread pool,
+    /// returning the rays with their hits in the same order
+    pub fn intersect_async(&self, rays: Vec<Ray>) -> Blocking<Vec<RayHit>> {
+        self.with_committed(move |scene| {
+            let mut ctx = IntersectContext::incoherent();
+            rays.into_iter()
+                .map(|r| {
+                    let mut ray_hit = RayHit::new(r);
+                    scene.intersect(&mut ctx, &mut ray_hit);
+                    ray_hit
+                })
+                .collect::<Vec<_>>()
+        })
+    }
+    /// Test whether each ray is occluded on tokio's blocking thread pool,
+    /// e.g. for batches of visibility queries
+    pub fn occluded_async(&self, rays: Vec<Ray>) -> Blocking<Vec<bool>> {
+        self.with_committed(move |scene| {
+            rays.iter()
+                .map(|r| scene.is_occluded(r))
+                .collect::<Vec<_>>()
+        })
+    }
+    /// Take back the scene to change it, returning the handle again if
+    /// other clones of it are still alive. The scene must be committed
+    /// again after changing it.
+    pub fn into_scene(self) -> Result<Scene<'static>, AsyncScene> {
+        let token = self.token;
+        Arc::try_unwrap(self.scene).map_err(|scene| AsyncScene { scene, token })
+    }
+}
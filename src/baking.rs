@@ -0,0 +1,359 @@
+//! Baking ambient occlusion into a texture over the UV layout of a mesh,
+//! e.g. to precompute the soft shadowing of static geometry for a
+//! real-time renderer. The triangles of the mesh are rasterized in UV
+//! space to find a sample point on the surface for each texel they cover,
+//! then cosine-weighted hemisphere rays are traced from each point with
+//! occlusion rays in batches through the ray stream API.
+//!
+//! The texture's first row is at the top, where v is 1, following the
+//! convention of image files and OpenGL textures. Texels whose centers
+//! aren't covered by a triangle in UV space are left unbaked and flagged
+//! in the texture's `coverage`, so they can be filled by dilating the
+//! baked texels before the texture is filtered.
+//!
+//! ```no_run
+//! # extern crate embree;
+//! # use embree::{AoBaker, Device, Scene};
+//! # let device = Device::new();
+//! # let scene = Scene::new(&device);
+//! # let (mesh_id, uv_slot) = (0, 0);
+//! let rtscene = scene.commit();
+//! let ao = AoBaker::new(512, 512)
+//!     .samples(64)
+//!     .max_distance(2.0)
+//!     .bake(&rtscene, mesh_id, uv_slot)
+//!     .unwrap();
+//! ```
+//!
+//! Sample points for other surfaces, or laid out differently over the
+//! texture, can be baked with `bake_samples`.
+
+use std::f32;
+use std::iter;
+
+use cgmath::{InnerSpace, Vector2, Vector3};
+
+use geometry::Geometry;
+use ray::IntersectContext;
+use scene::CommittedScene;
+use testing::Pcg32;
+use triangle_mesh::TriangleMesh;
+
+/// Number of occlusion rays traced together in a single stream
+const BATCH_SIZE: usize = 1024;
+
+/// A point on a surface to bake the occlusion of into a texel
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TexelSample {
+    /// The index of the texel in the texture, `x + y * width`
+    pub texel: usize,
+    pub position: Vector3<f32>,
+    /// The unit normal of the front face of the surface, which rays are
+    /// traced from
+    pub normal: Vector3<f32>,
+}
+
+/// An ambient occlusion texture baked by an `AoBaker`
+#[derive(Debug, Clone, PartialEq)]
+pub struct AoTexture {
+    pub width: u32,
+    pub height: u32,
+    /// The fraction of cosine-weighted directions which aren't occluded
+    /// for each texel, from 0 for fully occluded to 1 for unoccluded.
+    /// Unbaked texels are 1.
+    pub texels: Vec<f32>,
+    /// Whether each texel was baked
+    pub coverage: Vec<bool>,
+}
+
+impl AoTexture {
+    /// Get the occlusion baked into the texel, if it was baked
+    pub fn get(&self, x: u32, y: u32) -> Option<f32> {
+        let i = (x + y * self.width) as usize;
+        if self.coverage[i] {
+            Some(self.texels[i])
+        } else {
+            None
+        }
+    }
+}
+
+/// Bakes ambient occlusion over the surface of a mesh into a texture
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct AoBaker {
+    width: u32,
+    height: u32,
+    samples: usize,
+    max_distance: f32,
+    seed: u64,
+}
+
+impl AoBaker {
+    /// Create a baker for a `width` x `height` texture, tracing 32 rays
+    /// with unlimited distance for each texel
+    pub fn new(width: u32, height: u32) -> AoBaker {
+        assert!(width > 0 && height > 0, "The texture must not be empty");
+        AoBaker {
+            width,
+            height,
+            samples: 32,
+            max_distance: f32::INFINITY,
+            seed: 0,
+        }
+    }
+    /// Set the number of rays traced for each texel
+    pub fn samples(mut self, samples: usize) -> AoBaker {
+        assert!(samples > 0, "At least one ray must be traced per texel");
+        self.samples = samples;
+        self
+    }
+    /// Set the distance beyond which geometry doesn't occlude the surface,
+    /// e.g. to bake only local occlusion in an enclosed scene
+    pub fn max_distance(mut self, distance: f32) -> AoBaker {
+        assert!(distance > 0.0, "The occlusion distance must be positive");
+        self.max_distance = distance;
+        self
+    }
+    /// Set the seed of the random directions, the same seed gives the same
+    /// texture
+    pub fn seed(mut self, seed: u64) -> AoBaker {
+        self.seed = seed;
+        self
+    }
+    /// Find the sample point of each texel covered by the mesh, by
+    /// rasterizing its triangles at the texel centers in UV space. The
+    /// UVs are read from the first two components of the vertex attribute
+    /// in `uv_slot`. Where triangles overlap in UV space the first one
+    /// covering a texel is sampled.
+    pub fn texel_samples(&self, mesh: &TriangleMesh, uv_slot: u32) -> Vec<TexelSample> {
+        let verts = mesh.vertex_buffer.as_slice();
+        let uvs = mesh.vertex_attribute_buffers[uv_slot as usize].as_slice();
+        let triangles: Vec<_> = mesh
+            .index_buffer
+            .as_slice()
+            .iter()
+            .map(|t| {
+                let i = [t.x as usize, t.y as usize, t.z as usize];
+                (
+                    [
+                        verts[i[0]].truncate(),
+                        verts[i[1]].truncate(),
+                        verts[i[2]].truncate(),
+                    ],
+                    [
+                        uvs[i[0]].truncate().truncate(),
+                        uvs[i[1]].truncate().truncate(),
+                        uvs[i[2]].truncate().truncate(),
+                    ],
+                )
+            })
+            .collect();
+        rasterize(&triangles, self.width, self.height)
+    }
+    /// Bake the occlusion of the triangle mesh with the ID `geom_id` in the
+    /// scene, with its UVs in the vertex attribute `uv_slot`. Returns
+    /// `None` if the geometry isn't a triangle mesh in the scene.
+    ///
+    /// Panics if the mesh has no vertex attribute in the slot.
+    pub fn bake(&self, scene: &CommittedScene, geom_id: u32, uv_slot: u32) -> Option<AoTexture> {
+        let samples = match *scene.scene.geometry_by_raw_id(geom_id)? {
+            Geometry::Triangle(ref m) => self.texel_samples(m, uv_slot),
+            _ => return None,
+        };
+        Some(self.bake_samples(scene, &samples))
+    }
+    /// Bake the occlusion at the sample points into their texels. If more
+    /// than one point is given for a texel their occlusion is averaged.
+    ///
+    /// Panics if a sample's texel is outside the texture.
+    pub fn bake_samples(&self, scene: &CommittedScene, samples: &[TexelSample]) -> AoTexture {
+        let texel_count = (self.width * self.height) as usize;
+        let mut unoccluded = vec![0usize; texel_count];
+        let mut points = vec![0usize; texel_count];
+        for s in samples {
+            assert!(s.texel < texel_count, "Texel is outside the texture");
+            points[s.texel] += 1;
+        }
+
+        let settings = scene.traversal_settings();
+        let mut rng = Pcg32::new(self.seed);
+        let mut ctx = IntersectContext::incoherent();
+        let mut rays = Vec::with_capacity(BATCH_SIZE);
+        let mut texels = Vec::with_capacity(BATCH_SIZE);
+        let mut pending = samples
+            .iter()
+            .flat_map(|s| iter::repeat_n(s, self.samples))
+            .peekable();
+        while pending.peek().is_some() {
+            rays.clear();
+            texels.clear();
+            for s in pending.by_ref().take(BATCH_SIZE) {
+                let dir = cosine_hemisphere(s.normal, rng.next_f32(), rng.next_f32());
+                let mut ray = settings.spawn_ray(s.position, dir);
+                ray.tfar = self.max_distance;
+                rays.push(ray);
+                texels.push(s.texel);
+            }
+            #[cfg(feature = "streams")]
+            scene.occluded_stream_aos(&mut ctx, &mut rays);
+            #[cfg(not(feature = "streams"))]
+            for r in rays.iter_mut() {
+                scene.occluded(&mut ctx, r);
+            }
+
+            for (r, t) in rays.iter().zip(texels.iter()) {
+                // Occluded rays have their tfar set to -inf
+                if r.tfar >= 0.0 {
+                    unoccluded[*t] += 1;
+                }
+            }
+        }
+
+        let texels = unoccluded
+            .iter()
+            .zip(points.iter())
+            .map(|(u, p)| {
+                if *p > 0 {
+                    *u as f32 / (*p * self.samples) as f32
+                } else {
+                    1.0
+                }
+            })
+            .collect();
+        AoTexture {
+            width: self.width,
+            height: self.height,
+            texels,
+            coverage: points.iter().map(|p| *p > 0).collect(),
+        }
+    }
+}
+
+/// The positions and UVs of a triangle's vertices
+type UvTriangle = ([Vector3<f32>; 3], [Vector2<f32>; 3]);
+
+/// Find the sample points of the texels whose centers are covered by the
+/// triangles, given as their positions and UVs. The normal of each
+/// triangle is the side its vertices wind counter-clockwise around.
+fn rasterize(triangles: &[UvTriangle], width: u32, height: u32) -> Vec<TexelSample> {
+    let mut covered = vec![false; (width * height) as usize];
+    let mut samples = Vec::new();
+    let size = Vector2::new(width as f32, height as f32);
+    for (p, uv) in triangles {
+        // Work in texel space, with y down the rows of the texture
+        let t: Vec<Vector2<f32>> = uv
+            .iter()
+            .map(|uv| Vector2::new(uv.x * size.x, (1.0 - uv.y) * size.y))
+            .collect();
+        let area = edge(t[0], t[1], t[2]);
+        let normal = (p[1] - p[0]).cross(p[2] - p[0]);
+        if area == 0.0 || normal.magnitude2() == 0.0 {
+            continue;
+        }
+        let normal = normal.normalize();
+        let lo = t[0].zip(t[1].zip(t[2], f32::min), f32::min);
+        let hi = t[0].zip(t[1].zip(t[2], f32::max), f32::max);
+        let x_range = texel_range(lo.x, hi.x, width);
+        let y_range = texel_range(lo.y, hi.y, height);
+        for y in y_range {
+            for x in x_range.clone() {
+                let c = Vector2::new(x as f32 + 0.5, y as f32 + 0.5);
+                let b = [
+                    edge(t[1], t[2], c) / area,
+                    edge(t[2], t[0], c) / area,
+                    edge(t[0], t[1], c) / area,
+                ];
+                let i = (x + y * width) as usize;
+                if b.iter().all(|b| *b >= 0.0) && !covered[i] {
+                    covered[i] = true;
+                    samples.push(TexelSample {
+                        texel: i,
+                        position: p[0] * b[0] + p[1] * b[1] + p[2] * b[2],
+                        normal,
+                    });
+                }
+            }
+        }
+    }
+    samples
+}
+
+/// Twice the signed area of the triangle `a`, `b`, `c`
+fn edge(a: Vector2<f32>, b: Vector2<f32>, c: Vector2<f32>) -> f32 {
+    (b.x - a.x) * (c.y - a.y) - (b.y - a.y) * (c.x - a.x)
+}
+
+/// The range of texels whose centers may lie between `lo` and `hi`
+fn texel_range(lo: f32, hi: f32, texels: u32) -> ::std::ops::Range<u32> {
+    let start = (lo - 0.5).ceil().max(0.0) as u32;
+    let end = ((hi - 0.5).floor() + 1.0).max(0.0).min(texels as f32) as u32;
+    start..end.max(start)
+}
+
+/// Sample a cosine-weighted direction in the hemisphere around the unit
+/// normal `n` using the two random numbers in [0, 1) passed
+fn cosine_hemisphere(n: Vector3<f32>, u: f32, v: f32) -> Vector3<f32> {
+    let r = u.sqrt();
+    let phi = 2.0 * f32::consts::PI * v;
+    let z = (1.0 - u).max(0.0).sqrt();
+    // Build a basis around the normal without branching on its direction,
+    // from Duff et al. 2017
+    let sign = 1.0f32.copysign(n.z);
+    let a = -1.0 / (sign + n.z);
+    let b = n.x * n.y * a;
+    let t = Vector3::new(1.0 + sign * n.x * n.x * a, sign * b, -sign * n.x);
+    let s = Vector3::new(b, sign + n.y * n.y * a, -n.y);
+    t * (r * phi.cos()) + s * (r * phi.sin()) + n * z
+}
+
+#[test]
+fn test_rasterize() {
+    // A unit square in the xy plane mapped to the left half of a 4x2
+    // texture, split along its diagonal
+    let p = [
+        Vector3::new(0.0, 0.0, 0.0),
+        Vector3::new(1.0, 0.0, 0.0),
+        Vector3::new(1.0, 1.0, 0.0),
+        Vector3::new(0.0, 1.0, 0.0),
+    ];
+    let uv = [
+        Vector2::new(0.0, 0.0),
+        Vector2::new(0.5, 0.0),
+        Vector2::new(0.5, 1.0),
+        Vector2::new(0.0, 1.0),
+    ];
+    let triangles = [
+        ([p[0], p[1], p[2]], [uv[0], uv[1], uv[2]]),
+        ([p[0], p[2], p[3]], [uv[0], uv[2], uv[3]]),
+    ];
+    let mut samples = rasterize(&triangles, 4, 2);
+    samples.sort_by_key(|s| s.texel);
+    let texels: Vec<_> = samples.iter().map(|s| s.texel).collect();
+    assert_eq!(texels, vec![0, 1, 4, 5]);
+    for s in &samples {
+        assert_eq!(s.normal, Vector3::new(0.0, 0.0, 1.0));
+    }
+    // The top left texel center is at u = 0.125, v = 0.75
+    assert!((samples[0].position - Vector3::new(0.25, 0.75, 0.0)).magnitude() < 1e-6);
+    assert!((samples[3].position - Vector3::new(0.75, 0.25, 0.0)).magnitude() < 1e-6);
+}
+
+#[test]
+fn test_cosine_hemisphere() {
+    let mut rng = Pcg32::new(3);
+    for n in &[
+        Vector3::new(0.0, 0.0, 1.0),
+        Vector3::new(0.0, 0.0, -1.0),
+        Vector3::new(1.0, 2.0, -3.0).normalize(),
+    ] {
+        let mut mean_cos = 0.0;
+        for _ in 0..4096 {
+            let d = cosine_hemisphere(*n, rng.next_f32(), rng.next_f32());
+            assert!((d.magnitude() - 1.0).abs() < 1e-4);
+            assert!(d.dot(*n) >= 0.0);
+            mean_cos += d.dot(*n) / 4096.0;
+        }
+        // The mean cosine of a cosine-weighted hemisphere is 2/3
+        assert!((mean_cos - 2.0 / 3.0).abs() < 0.02);
+    }
+}
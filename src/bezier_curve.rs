@@ -2,19 +2,24 @@ use cgmath::{Vector2, Vector3, Vector4};
 
 use buffer::Buffer;
 use device::Device;
-use geometry::Geometry;
+use geometry::{self, Geometry};
 use sys::*;
 use {BufferType, CurveType, Format, GeometryType};
 
 pub struct BezierCurve<'a> {
     device: &'a Device,
     pub(crate) handle: RTCGeometry,
+    pub(crate) curve_type: CurveType,
     pub vertex_buffer: Buffer<'a, Vector4<f32>>,
     pub index_buffer: Buffer<'a, u32>,
     pub normal_buffer: Option<Buffer<'a, Vector3<f32>>>,
 }
 
 impl<'a> BezierCurve<'a> {
+    /// The buffers which must be set before committing the geometry
+    pub const REQUIRED: &'static [BufferType] = &[BufferType::VERTEX, BufferType::INDEX];
+    /// The buffers the geometry can optionally use
+    pub const OPTIONAL: &'static [BufferType] = &[BufferType::NORMAL, BufferType::VERTEX_ATTRIBUTE];
     pub fn flat(
         device: &'a Device,
         num_segments: usize,
@@ -68,13 +73,13 @@ impl<'a> BezierCurve<'a> {
         match curve_type {
             CurveType::NormalOriented => {
                 h = unsafe {
-                    rtcNewGeometry(device.handle, GeometryType::NORMAL_ORIENTED_BEZIER_CURVE)
+                    geometry::new_handle(device, GeometryType::NORMAL_ORIENTED_BEZIER_CURVE)
                 }
             }
             CurveType::Round => {
-                h = unsafe { rtcNewGeometry(device.handle, GeometryType::ROUND_BEZIER_CURVE) }
+                h = unsafe { geometry::new_handle(device, GeometryType::ROUND_BEZIER_CURVE) }
             }
-            _ => h = unsafe { rtcNewGeometry(device.handle, GeometryType::FLAT_BEZIER_CURVE) },
+            _ => h = unsafe { geometry::new_handle(device, GeometryType::FLAT_BEZIER_CURVE) },
         };
         let mut vertex_buffer = Buffer::new(device, num_verts);
         let mut index_buffer = Buffer::new(device, num_segments);
@@ -124,6 +129,7 @@ impl<'a> BezierCurve<'a> {
         BezierCurve {
             device: device,
             handle: h,
+            curve_type,
             vertex_buffer: vertex_buffer,
             index_buffer: index_buffer,
             normal_buffer: normal_buffer,
@@ -132,3 +138,4 @@ impl<'a> BezierCurve<'a> {
 }
 
 unsafe impl<'a> Sync for BezierCurve<'a> {}
+unsafe impl<'a> Send for BezierCurve<'a> {}
@@ -0,0 +1,153 @@
+//! Experimental budgeted intersection queries for interactive use, e.g.
+//! picking in an editor which must never hitch. Embree doesn't expose
+//! traversal of its BVH, so a query can't be stopped after visiting some
+//! number of nodes. What makes a closest hit query expensive in practice
+//! is filter functions rejecting many candidate hits, e.g. alpha tested
+//! foliage or all-hit collection, as each rejected candidate lets the
+//! traversal continue past it. A budgeted query counts the candidate hits
+//! passed to the geometry's filter functions, and once the budget is
+//! spent it stops calling the filters and accepts each candidate found,
+//! so the remaining traversal is bounded by the nearest candidate like a
+//! query without filters. The hit returned may then be one a filter would
+//! have rejected, so it's flagged as approximate.
+//!
+//! Only filters set with `Geometry::set_intersect_filter_function` are
+//! budgeted, raw filter functions are called as usual.
+
+use std::cell::Cell;
+use std::time::{Duration, Instant};
+
+use ray::{IntersectContext, RayHit};
+use sys;
+
+/// The budget of a query, in candidate hits passed to filter functions
+/// and time spent in the query. The budget is spent when either limit is
+/// reached.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct QueryBudget {
+    pub max_candidates: Option<u32>,
+    pub max_time: Option<Duration>,
+}
+
+impl QueryBudget {
+    /// Create an unlimited budget
+    pub fn new() -> QueryBudget {
+        QueryBudget::default()
+    }
+    pub fn candidates(mut self, max: u32) -> QueryBudget {
+        self.max_candidates = Some(max);
+        self
+    }
+    /// Limit the time spent in the query. The time is checked before each
+    /// candidate hit is filtered, so time spent traversing the BVH between
+    /// candidates can overrun the limit.
+    pub fn time(mut self, max: Duration) -> QueryBudget {
+        self.max_time = Some(max);
+        self
+    }
+}
+
+/// The result of a budgeted query
+#[derive(Debug, Copy, Clone)]
+pub struct BudgetedHit {
+    pub ray_hit: Option<RayHit>,
+    /// Whether the budget was spent, in which case the hit may not be the
+    /// closest one the filters would accept
+    pub approximate: bool,
+    /// The number of candidate hits filtered during the query
+    pub candidates: u32,
+}
+
+/// An intersection context carrying the state of a budgeted query, which
+/// is found from the context Embree passes to filter functions
+#[repr(C)]
+pub(crate) struct BudgetContext {
+    pub ctx: IntersectContext,
+    budget: QueryBudget,
+    deadline: Option<Instant>,
+    candidates: Cell<u32>,
+    spent: Cell<bool>,
+}
+
+/// Marks an intersection context as a `BudgetContext`. It's only called
+/// by Embree in scenes built with `SceneFlags::CONTEXT_FILTER_FUNCTION`,
+/// where it accepts every hit.
+unsafe extern "C" fn budget_marker(_: *const sys::RTCFilterFunctionNArguments) {}
+
+impl BudgetContext {
+    pub fn new(budget: QueryBudget) -> BudgetContext {
+        let mut ctx = IntersectContext::incoherent();
+        ctx.filter = Some(budget_marker);
+        BudgetContext {
+            ctx,
+            budget,
+            deadline: budget.max_time.map(|t| Instant::now() + t),
+            candidates: Cell::new(0),
+            spent: Cell::new(false),
+        }
+    }
+    /// Get the budget of the query the context is for, if it's a budgeted
+    /// query
+    pub unsafe fn from_context<'c>(ctx: *const IntersectContext) -> Option<&'c BudgetContext> {
+        match (*ctx).filter {
+            Some(f) if f as *const () == budget_marker as *const () => {
+                Some(&*(ctx as *const BudgetContext))
+            }
+            _ => None,
+        }
+    }
+    /// Charge a candidate hit to the budget, returning false if the budget
+    /// is spent and the candidate should be accepted without filtering
+    pub fn charge(&self) -> bool {
+        if self.spent.get() {
+            return false;
+        }
+        let n = self.candidates.get();
+        let over_count = self.budget.max_candidates.is_some_and(|max| n >= max);
+        let over_time = self.deadline.is_some_and(|d| Instant::now() >= d);
+        if over_count || over_time {
+            self.spent.set(true);
+            return false;
+        }
+        self.candidates.set(n + 1);
+        true
+    }
+    pub fn result(&self, ray_hit: RayHit) -> BudgetedHit {
+        BudgetedHit {
+            ray_hit: if ray_hit.hit.hit() {
+                Some(ray_hit)
+            } else {
+                None
+            },
+            approximate: self.spent.get(),
+            candidates: self.candidates.get(),
+        }
+    }
+}
+
+#[test]
+fn test_charge() {
+    use cgmath::Vector3;
+    use ray::Ray;
+
+    let ctx = BudgetContext::new(QueryBudget::new().candidates(2));
+    unsafe {
+        let found = BudgetContext::from_context(&ctx.ctx).unwrap();
+        assert!(std::ptr::eq(found, &ctx));
+        assert!(BudgetContext::from_context(&IntersectContext::incoherent()).is_none());
+    }
+    assert!(ctx.charge());
+    assert!(ctx.charge());
+    assert!(!ctx.charge());
+    assert!(!ctx.charge());
+    let ray = Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 1.0));
+    let result = ctx.result(RayHit::new(ray));
+    assert!(result.ray_hit.is_none());
+    assert!(result.approximate);
+    assert_eq!(result.candidates, 2);
+
+    let ctx = BudgetContext::new(QueryBudget::new());
+    for _ in 0..100 {
+        assert!(ctx.charge());
+    }
+}
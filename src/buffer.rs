@@ -1,16 +1,23 @@
 use std::marker::PhantomData;
 use std::ops::{Index, IndexMut};
-use std::{mem, ptr};
+use std::{mem, ptr, slice};
+
+use cgmath::{Vector3, Vector4};
 
 use device::Device;
+use geometry::{self, Geometry};
+use leak_check::{self, ObjectKind};
 use sys::*;
-use BufferType;
+use {BufferType, Format};
 
 #[derive(Copy, Clone)]
 struct BufferAttachment {
     geom: RTCGeometry,
     buf_type: BufferType,
     slot: u32,
+    /// Whether the buffer holds a reference to the geometry, for buffers
+    /// which aren't owned by the geometry they're attached to
+    retained: bool,
 }
 
 impl BufferAttachment {
@@ -19,6 +26,7 @@ impl BufferAttachment {
             geom: ptr::null_mut(),
             buf_type: BufferType::VERTEX,
             slot: std::u32::MAX,
+            retained: false,
         }
     }
     fn is_attached(&self) -> bool {
@@ -26,6 +34,116 @@ impl BufferAttachment {
     }
 }
 
+/// Get the number of elements of type `T` which must follow the last
+/// vertex of a vertex buffer shared with Embree, which reads it with a
+/// 16 byte load
+pub fn vertex_padding<T>() -> usize {
+    let size = mem::size_of::<T>();
+    if size >= 16 {
+        0
+    } else {
+        (16 - size).div_ceil(size)
+    }
+}
+
+/// Get the size in bytes to allocate for a buffer holding `bytes` of
+/// elements of `elem_size` bytes: room for a 16 byte read starting at the
+/// last element, rounded up to a multiple of 16 bytes
+pub fn padded_bytes(bytes: usize, elem_size: usize) -> usize {
+    let bytes = bytes + 16usize.saturating_sub(elem_size);
+    bytes.div_ceil(16) * 16
+}
+
+/// The layout of the `FLOAT3` vertices of a vertex or vertex attribute
+/// buffer. Embree reads vertices with 16 byte SSE loads, so the 4 bytes
+/// after each vertex are read too: with `Padded16` they're the unused
+/// fourth component of the vertex, while with `Packed12` they belong to
+/// the next vertex, and the buffer must be padded after its last vertex
+/// so the read stays inside the allocation. Forgetting the padding reads
+/// past the end of the buffer, which usually works and occasionally
+/// crashes when the buffer ends at a page boundary.
+///
+/// Buffers made by `Buffer::new` and the geometry constructors are always
+/// padded, the layout only matters for how the vertices are stored:
+/// `Padded16` keeps each vertex 16 byte aligned, `Packed12` saves a
+/// quarter of the memory.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum VertexLayout {
+    /// Vertices of 3 floats stored back to back with a 12 byte stride
+    Packed12,
+    /// Vertices of 3 floats followed by an unused float, with a 16 byte
+    /// stride
+    Padded16,
+}
+
+impl VertexLayout {
+    /// Get the stride in bytes between vertices
+    pub fn stride(&self) -> usize {
+        match *self {
+            VertexLayout::Packed12 => 12,
+            VertexLayout::Padded16 => 16,
+        }
+    }
+    /// Get the number of bytes which must follow the last vertex for
+    /// Embree's 16 byte read of it
+    pub fn padding(&self) -> usize {
+        16 - self.stride()
+    }
+    /// Get the size in bytes of a buffer holding `count` vertices and the
+    /// padding after the last one
+    pub fn buffer_bytes(&self, count: usize) -> usize {
+        count * self.stride() + self.padding()
+    }
+}
+
+/// A vertex type which can be stored in a `FLOAT3` vertex buffer, which
+/// determines the buffer's `VertexLayout`
+pub trait VertexElement: Copy {
+    const LAYOUT: VertexLayout;
+    fn from_position(p: [f32; 3]) -> Self;
+    fn position(&self) -> [f32; 3];
+}
+
+impl VertexElement for [f32; 3] {
+    const LAYOUT: VertexLayout = VertexLayout::Packed12;
+    fn from_position(p: [f32; 3]) -> Self {
+        p
+    }
+    fn position(&self) -> [f32; 3] {
+        *self
+    }
+}
+
+impl VertexElement for Vector3<f32> {
+    const LAYOUT: VertexLayout = VertexLayout::Packed12;
+    fn from_position(p: [f32; 3]) -> Self {
+        Vector3::from(p)
+    }
+    fn position(&self) -> [f32; 3] {
+        (*self).into()
+    }
+}
+
+impl VertexElement for [f32; 4] {
+    const LAYOUT: VertexLayout = VertexLayout::Padded16;
+    fn from_position(p: [f32; 3]) -> Self {
+        [p[0], p[1], p[2], 0.0]
+    }
+    fn position(&self) -> [f32; 3] {
+        [self[0], self[1], self[2]]
+    }
+}
+
+impl VertexElement for Vector4<f32> {
+    const LAYOUT: VertexLayout = VertexLayout::Padded16;
+    fn from_position(p: [f32; 3]) -> Self {
+        Vector4::new(p[0], p[1], p[2], 0.0)
+    }
+    fn position(&self) -> [f32; 3] {
+        [self.x, self.y, self.z]
+    }
+}
+
 // TODO: To handle this nicely for sharing/re-using/changing buffer views
 // we basically need an API/struct for making buffer views of existing
 // larger buffers.
@@ -35,54 +153,97 @@ pub struct Buffer<'a, T> {
     // TODO: We need a list of RTCGeometry handles
     // that we're attached to to mark buffers as updated on
     // the geometries.
-    bytes: usize,
+    len: usize,
     attachment: BufferAttachment,
     marker: PhantomData<T>,
 }
 
 impl<'a, T> Buffer<'a, T> {
-    /// Allocate a buffer with some raw capacity in bytes
+    /// Allocate a buffer with some raw capacity in bytes. The allocation
+    /// is padded for a 16 byte read of the last element, see
+    /// `padded_bytes`.
     pub fn raw(device: &'a Device, bytes: usize) -> Buffer<'a, T> {
-        // Pad to a multiple of 16 bytes
-        let bytes = if bytes % 16 == 0 {
-            bytes
-        } else {
-            bytes + bytes / 16
-        };
+        let len = bytes / mem::size_of::<T>();
+        let bytes = padded_bytes(bytes, mem::size_of::<T>());
+        leak_check::created(ObjectKind::Buffer);
         Buffer {
             device: device,
             handle: unsafe { rtcNewBuffer(device.handle, bytes) },
-            bytes: bytes,
+            len,
             attachment: BufferAttachment::none(),
             marker: PhantomData,
         }
     }
+    /// Allocate a buffer of `len` elements. The allocation is padded for
+    /// a 16 byte read of the last element, see `padded_bytes`.
+    /// Panics if the size in bytes overflows `usize`.
     pub fn new(device: &'a Device, len: usize) -> Buffer<'a, T> {
-        let mut bytes = len * mem::size_of::<T>();
-        // Pad to a multiple of 16 bytes
-        bytes = if bytes % 16 == 0 {
-            bytes
-        } else {
-            bytes + bytes / 16
-        };
+        let bytes = len
+            .checked_mul(mem::size_of::<T>())
+            .expect("Buffer size in bytes overflows usize");
+        let bytes = padded_bytes(bytes, mem::size_of::<T>());
+        leak_check::created(ObjectKind::Buffer);
         Buffer {
             device: device,
             handle: unsafe { rtcNewBuffer(device.handle, bytes) },
-            bytes: bytes,
+            len,
             attachment: BufferAttachment::none(),
             marker: PhantomData,
         }
     }
+    /// Get the number of elements of type `T` in the buffer
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
     pub fn map(&mut self) -> MappedBuffer<'a, T> {
-        let len = self.bytes / mem::size_of::<T>();
+        let len = self.len;
         let slice = unsafe { rtcGetBufferData(self.handle) as *mut T };
         MappedBuffer {
             buffer: PhantomData,
             attachment: self.attachment,
             slice: slice,
-            len: len,
+            len,
         }
     }
+    /// Map the buffer and run `f` with exclusive access to its contents as
+    /// a slice, then mark the buffer as updated on the geometry it is
+    /// attached to. Returns the result of `f`.
+    pub fn mapped_scope<R, F>(&mut self, f: F) -> R
+    where
+        T: 'a,
+        F: FnOnce(&mut [T]) -> R,
+    {
+        let mut mapped = self.map();
+        f(mapped.as_mut_slice())
+    }
+    /// Read-only view of the buffer contents, used internally to
+    /// inspect geometry data without mapping the buffer.
+    pub(crate) fn as_slice(&self) -> &[T] {
+        unsafe { slice::from_raw_parts(rtcGetBufferData(self.handle) as *const T, self.len) }
+    }
+    /// Release the reference to the geometry the buffer was attached to
+    /// by `attach_vertices`, if any
+    unsafe fn release_attachment(&mut self) {
+        if self.attachment.retained {
+            rtcReleaseGeometry(self.attachment.geom);
+            self.attachment = BufferAttachment::none();
+        }
+    }
+    /// Check the buffer is still the one Embree uses for the geometry slot
+    /// it was attached to. It isn't once shared data is bound to the slot
+    /// in its place, e.g. with `Geometry::set_shared_buffer_from_slice`,
+    /// after which the buffer no longer holds the geometry's data.
+    pub(crate) fn is_bound(&self) -> bool {
+        let a = &self.attachment;
+        a.is_attached()
+            && unsafe {
+                rtcGetGeometryBufferData(a.geom, a.buf_type, a.slot)
+                    == rtcGetBufferData(self.handle)
+            }
+    }
     pub(crate) fn set_attachment(&mut self, geom: RTCGeometry, buf_type: BufferType, slot: u32) {
         self.attachment.geom = geom;
         self.attachment.buf_type = buf_type;
@@ -90,15 +251,114 @@ impl<'a, T> Buffer<'a, T> {
     }
 }
 
+impl<'a, T: VertexElement + 'a> Buffer<'a, T> {
+    /// Allocate a vertex buffer holding the positions, stored in the
+    /// layout of `T`, e.g. `[f32; 3]` for `Packed12` or `Vector4<f32>` for
+    /// `Padded16`. The buffer is padded for Embree's read of the last
+    /// vertex.
+    pub fn vertices(device: &'a Device, positions: &[[f32; 3]]) -> Buffer<'a, T> {
+        let mut buffer = Buffer::new(device, positions.len());
+        {
+            let mut mapped = buffer.map();
+            for (v, p) in mapped.as_mut_slice().iter_mut().zip(positions.iter()) {
+                *v = T::from_position(*p);
+            }
+        }
+        buffer
+    }
+    /// Get the layout of the vertices in the buffer
+    pub fn layout(&self) -> VertexLayout {
+        T::LAYOUT
+    }
+    /// Get the positions of the vertices in the buffer
+    pub fn positions(&self) -> Vec<[f32; 3]> {
+        self.as_slice().iter().map(|v| v.position()).collect()
+    }
+    /// Attach the buffer to the geometry as its `FLOAT3` buffer of type
+    /// `buf_type` in `slot`, with the stride of the buffer's layout. Embree
+    /// keeps a reference to the buffer, so it stays in use by the geometry
+    /// after it's dropped, and mapping the buffer marks it as updated on
+    /// the geometry. For vertex attribute buffers the geometry's vertex
+    /// attribute count must include the slot. The geometry must be
+    /// committed for the change to take effect.
+    ///
+    /// The buffer stays mutably borrowed for as long as the geometry, and
+    /// any scene it's attached to, lives, so it can't be mapped while
+    /// Embree may be reading it during a build or a query. The buffer
+    /// keeps a reference to the geometry while it's attached, so it can be
+    /// mapped again after the `Geometry` is dropped.
+    ///
+    /// ```compile_fail
+    /// # extern crate embree;
+    /// # use embree::{Buffer, BufferType, Device, Geometry, Scene, TriangleMesh};
+    /// # let device = Device::new();
+    /// let positions = [[0.0f32, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]];
+    /// let mut vertices = Buffer::<[f32; 3]>::vertices(&device, &positions);
+    /// let mut geom = Geometry::Triangle(TriangleMesh::unanimated(&device, 1, 0));
+    /// vertices.attach_vertices(&mut geom, BufferType::VERTEX, 0);
+    /// let mut scene = Scene::new(&device);
+    /// scene.attach_geometry(geom);
+    /// let rtscene = scene.commit();
+    /// vertices.mapped_scope(|v| v[0] = [0.5, 0.0, 0.0]);
+    /// drop(rtscene);
+    /// ```
+    ///
+    /// Panics if the geometry's wrapper owns a non-empty buffer for the
+    /// slot, as mapping that buffer would no longer change the geometry.
+    /// Create the wrapper with no vertices to take them from this buffer,
+    /// e.g. `TriangleMesh::unanimated(&device, num_tris, 0)`. The wrapper
+    /// can't read the attached vertices back, so like geometry with shared
+    /// buffers, see `Geometry::set_shared_buffer_from_slice`, the helpers
+    /// reading the geometry's buffers skip it or return an error for it.
+    pub fn attach_vertices<'g>(
+        &'g mut self,
+        geometry: &mut Geometry<'g>,
+        buf_type: BufferType,
+        slot: u32,
+    ) where
+        'a: 'g,
+    {
+        assert_eq!(
+            geometry.owned_buffer_len(buf_type, slot),
+            0,
+            "Geometry already owns a {:?} buffer in slot {}",
+            buf_type,
+            slot
+        );
+        let layout = T::LAYOUT;
+        unsafe {
+            self.release_attachment();
+            rtcRetainGeometry(geometry.handle());
+            rtcSetGeometryBuffer(
+                geometry.handle(),
+                buf_type,
+                slot,
+                Format::FLOAT3,
+                self.handle,
+                0,
+                layout.stride(),
+                self.len,
+            );
+        }
+        self.set_attachment(geometry.handle(), buf_type, slot);
+        self.attachment.retained = true;
+        geometry::set_shared_count(geometry.handle(), buf_type, slot, self.len);
+        geometry::mark_dirty(geometry.handle());
+    }
+}
+
 impl<'a, T> Drop for Buffer<'a, T> {
     fn drop(&mut self) {
         unsafe {
+            self.release_attachment();
             rtcReleaseBuffer(self.handle);
         }
+        leak_check::released(ObjectKind::Buffer);
     }
 }
 
 unsafe impl<'a, T> Sync for Buffer<'a, T> {}
+unsafe impl<'a, T: Send> Send for Buffer<'a, T> {}
 
 pub struct MappedBuffer<'a, T: 'a> {
     buffer: PhantomData<&'a mut Buffer<'a, T>>,
@@ -111,6 +371,12 @@ impl<'a, T: 'a> MappedBuffer<'a, T> {
     pub fn len(&self) -> usize {
         self.len
     }
+    pub fn as_slice(&self) -> &[T] {
+        unsafe { slice::from_raw_parts(self.slice, self.len) }
+    }
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        unsafe { slice::from_raw_parts_mut(self.slice, self.len) }
+    }
 }
 
 impl<'a, T: 'a> Drop for MappedBuffer<'a, T> {
@@ -124,6 +390,7 @@ impl<'a, T: 'a> Drop for MappedBuffer<'a, T> {
                     self.attachment.slot,
                 );
             }
+            geometry::mark_dirty(self.attachment.geom);
         }
     }
 }
@@ -148,3 +415,29 @@ impl<'a, T: 'a> IndexMut<usize> for MappedBuffer<'a, T> {
         unsafe { &mut *self.slice.offset(index as isize) }
     }
 }
+
+#[test]
+fn test_vertex_padding() {
+    assert_eq!(vertex_padding::<[f32; 4]>(), 0);
+    assert_eq!(vertex_padding::<[f32; 3]>(), 1);
+    assert_eq!(vertex_padding::<[f32; 2]>(), 1);
+    assert_eq!(vertex_padding::<f32>(), 3);
+    assert_eq!(vertex_padding::<[f32; 8]>(), 0);
+}
+
+#[test]
+fn test_padded_bytes() {
+    // The 16 byte read of the last of four packed vertices ends at byte 52
+    assert_eq!(padded_bytes(48, 12), 64);
+    assert_eq!(padded_bytes(12, 12), 16);
+    assert_eq!(padded_bytes(64, 16), 64);
+    assert_eq!(padded_bytes(12, 4), 32);
+    assert_eq!(padded_bytes(128, 64), 128);
+    for count in 1..64 {
+        for layout in [VertexLayout::Packed12, VertexLayout::Padded16] {
+            let bytes = padded_bytes(count * layout.stride(), layout.stride());
+            assert!(bytes >= layout.buffer_bytes(count));
+            assert!((count - 1) * layout.stride() + 16 <= bytes);
+        }
+    }
+}
@@ -0,0 +1,713 @@
+//! A safe interface to Embree's BVH builder (`rtcBuildBVH`) and
+//! introspection of a BVH built over the primitives of a scene.
+//!
+//! `Bvh::build` builds a hierarchy over the bounds of arbitrary
+//! primitives, e.g. for a renderer with its own traversal kernels, and
+//! returns it as a tree of Rust values. The data stored in inner nodes and
+//! leaves is chosen through the `BvhNode` and `BvhLeaf` traits, the
+//! callbacks Embree calls during the build and its thread local allocator
+//! are handled internally:
+//!
+//! ```no_run
+//! # extern crate embree;
+//! # use embree::{Bounds, Bvh, BuildPrimitive, BuildSettings, Device};
+//! # fn bounds(i: u32) -> Bounds { unimplemented!() }
+//! let device = Device::new();
+//! let prims: Vec<BuildPrimitive> = (0..1000).map(|i| BuildPrimitive::new(&bounds(i), 0, i)).collect();
+//! let settings = BuildSettings::new().branching_factor(4).leaf_size(1, 8);
+//! // Inner nodes store nothing extra and leaves a copy of their primitives
+//! let bvh: Bvh<(), Vec<BuildPrimitive>> = Bvh::build(&device, &prims, &settings);
+//! let mut found = 0;
+//! bvh.traverse(|b| b.lower_x <= 0.0 && 0.0 <= b.upper_x, |leaf| found += leaf.len());
+//! ```
+//!
+//! Embree doesn't expose the nodes of the BVH it builds internally for a
+//! scene, so `bvh_levels` instead builds a BVH over the bounds of the
+//! scene's primitives with settings similar to a scene BVH, and extracts
+//! the node bounds of the resulting hierarchy level by level for
+//! visualizing the hierarchy when teaching or debugging. The boxes are
+//! representative of, but not identical to, the ones Embree traverses when
+//! tracing rays against the scene.
+
+use std::any::Any;
+use std::os::raw;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Mutex;
+use std::{f32, mem, ptr, slice};
+
+use cgmath::Vector4;
+
+use device::Device;
+use geometry::Geometry;
+use scene::CommittedScene;
+use sys::*;
+use {Bounds, BuildFlags, BuildQuality};
+
+/// The most children an inner node of a `Bvh` can have
+pub const MAX_BRANCHING_FACTOR: usize = 8;
+
+/// A primitive to build a `Bvh` over: its bounds and the geometry and
+/// primitive IDs identifying it
+pub type BuildPrimitive = RTCBuildPrimitive;
+
+impl BuildPrimitive {
+    pub fn new(bounds: &Bounds, geom_id: u32, prim_id: u32) -> BuildPrimitive {
+        build_primitive(geom_id, prim_id, *bounds)
+    }
+    pub fn bounds(&self) -> Bounds {
+        Bounds {
+            lower_x: self.lower_x,
+            lower_y: self.lower_y,
+            lower_z: self.lower_z,
+            align0: 0.0,
+            upper_x: self.upper_x,
+            upper_y: self.upper_y,
+            upper_z: self.upper_z,
+            align1: 0.0,
+        }
+    }
+}
+
+/// The data stored in the inner nodes of a `Bvh`, created once the build
+/// is finished from the bounds of the node's children
+pub trait BvhNode: Sized {
+    fn create(child_bounds: &[Bounds]) -> Self;
+}
+
+/// The data stored in the leaves of a `Bvh`, created during the build
+/// from the primitives in the leaf. Leaves are created on Embree's build
+/// threads, so they must be `Send`.
+pub trait BvhLeaf: Sized + Send {
+    fn create(prims: &[BuildPrimitive]) -> Self;
+}
+
+impl BvhNode for () {
+    fn create(_: &[Bounds]) {}
+}
+
+impl BvhLeaf for () {
+    fn create(_: &[BuildPrimitive]) {}
+}
+
+impl BvhLeaf for Vec<BuildPrimitive> {
+    fn create(prims: &[BuildPrimitive]) -> Vec<BuildPrimitive> {
+        prims.to_vec()
+    }
+}
+
+/// The settings `Bvh::build` passes to Embree's builder
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct BuildSettings {
+    quality: BuildQuality,
+    branching_factor: u32,
+    max_depth: u32,
+    sah_block_size: u32,
+    min_leaf_size: u32,
+    max_leaf_size: u32,
+    traversal_cost: f32,
+    intersection_cost: f32,
+}
+
+impl BuildSettings {
+    /// Settings for a binary BVH of medium quality with leaves of up to
+    /// 32 primitives
+    pub fn new() -> BuildSettings {
+        BuildSettings {
+            quality: BuildQuality::MEDIUM,
+            branching_factor: 2,
+            max_depth: 32,
+            sah_block_size: 1,
+            min_leaf_size: 1,
+            max_leaf_size: RTCBuildConstants_RTC_BUILD_MAX_PRIMITIVES_PER_LEAF,
+            traversal_cost: 1.0,
+            intersection_cost: 1.0,
+        }
+    }
+    pub fn quality(mut self, quality: BuildQuality) -> BuildSettings {
+        self.quality = quality;
+        self
+    }
+    /// Set the most children of each inner node.
+    ///
+    /// Panics if it isn't between 2 and `MAX_BRANCHING_FACTOR`.
+    pub fn branching_factor(mut self, n: usize) -> BuildSettings {
+        assert!(
+            (2..=MAX_BRANCHING_FACTOR).contains(&n),
+            "The branching factor must be between 2 and {}",
+            MAX_BRANCHING_FACTOR
+        );
+        self.branching_factor = n as u32;
+        self
+    }
+    /// Set the depth of the tree past which the builder makes leaves
+    pub fn max_depth(mut self, depth: u32) -> BuildSettings {
+        self.max_depth = depth;
+        self
+    }
+    /// Set the number of primitives the SAH is computed in blocks of, e.g.
+    /// the SIMD width leaves are intersected with
+    pub fn sah_block_size(mut self, n: u32) -> BuildSettings {
+        self.sah_block_size = n;
+        self
+    }
+    /// Set the fewest and most primitives in each leaf.
+    ///
+    /// Panics if `min` is zero or greater than `max`, or `max` is over
+    /// the 32 primitives per leaf Embree supports.
+    pub fn leaf_size(mut self, min: u32, max: u32) -> BuildSettings {
+        assert!(
+            0 < min && min <= max && max <= RTCBuildConstants_RTC_BUILD_MAX_PRIMITIVES_PER_LEAF,
+            "Leaves must hold between 1 and {} primitives",
+            RTCBuildConstants_RTC_BUILD_MAX_PRIMITIVES_PER_LEAF
+        );
+        self.min_leaf_size = min;
+        self.max_leaf_size = max;
+        self
+    }
+    /// Set the estimated costs of traversing a node and intersecting a
+    /// primitive used by the SAH
+    pub fn costs(mut self, traversal: f32, intersection: f32) -> BuildSettings {
+        self.traversal_cost = traversal;
+        self.intersection_cost = intersection;
+        self
+    }
+}
+
+impl Default for BuildSettings {
+    fn default() -> BuildSettings {
+        BuildSettings::new()
+    }
+}
+
+/// A node of a `Bvh`
+#[derive(Debug, Clone)]
+pub enum BvhTree<N, L> {
+    /// An inner node and its children with their bounds
+    Node {
+        node: N,
+        children: Vec<(Bounds, BvhTree<N, L>)>,
+    },
+    Leaf(L),
+}
+
+impl<N, L> BvhTree<N, L> {
+    /// Get the number of levels of the tree below and including this node
+    pub fn depth(&self) -> usize {
+        match *self {
+            BvhTree::Node { ref children, .. } => {
+                1 + children.iter().map(|c| c.1.depth()).max().unwrap_or(0)
+            }
+            BvhTree::Leaf(_) => 1,
+        }
+    }
+}
+
+/// A BVH built by Embree's builder, see the `bvh` module
+#[derive(Debug, Clone)]
+pub struct Bvh<N, L> {
+    root: Option<BvhTree<N, L>>,
+    bounds: Bounds,
+}
+
+impl<N: BvhNode, L: BvhLeaf> Bvh<N, L> {
+    /// Build a BVH over the primitives with the settings. The BVH is empty
+    /// if there are no primitives or the build fails.
+    ///
+    /// Panics if creating a leaf or a node panics, once the build has
+    /// finished.
+    pub fn build(device: &Device, prims: &[BuildPrimitive], settings: &BuildSettings) -> Bvh<N, L> {
+        let bounds = prims
+            .iter()
+            .fold(empty_bounds(), |b, p| union_bounds(&b, &p.bounds()));
+        if prims.is_empty() {
+            return Bvh { root: None, bounds };
+        }
+        // The builder reorders the primitives, so it works on a copy
+        let mut prims = prims.to_vec();
+        let mut state = BuildState::<L> {
+            leaves: Mutex::new(Vec::new()),
+            panic: Mutex::new(None),
+        };
+        let root = unsafe {
+            let bvh = rtcNewBVH(device.handle);
+            let args = RTCBuildArguments {
+                byteSize: mem::size_of::<RTCBuildArguments>(),
+                buildQuality: settings.quality,
+                buildFlags: BuildFlags::NONE,
+                maxBranchingFactor: settings.branching_factor,
+                maxDepth: settings.max_depth,
+                sahBlockSize: settings.sah_block_size,
+                minLeafSize: settings.min_leaf_size,
+                maxLeafSize: settings.max_leaf_size,
+                traversalCost: settings.traversal_cost,
+                intersectionCost: settings.intersection_cost,
+                bvh,
+                primitives: prims.as_mut_ptr(),
+                primitiveCount: prims.len(),
+                primitiveArrayCapacity: prims.len(),
+                createNode: Some(create_node::<L>),
+                setNodeChildren: Some(set_node_children::<L>),
+                setNodeBounds: Some(set_node_bounds::<L>),
+                createLeaf: Some(create_leaf::<L>),
+                splitPrimitive: None,
+                buildProgress: None,
+                userPtr: &mut state as *mut BuildState<L> as *mut raw::c_void,
+            };
+            let root = rtcBuildBVH(&args) as *const RawNode;
+            if let Some(p) = state.panic.get_mut().unwrap().take() {
+                rtcReleaseBVH(bvh);
+                panic::resume_unwind(p);
+            }
+            // The nodes are freed with the BVH, so the tree is copied out
+            // before releasing it
+            let leaves = state.leaves.get_mut().unwrap();
+            let root = if root.is_null() {
+                None
+            } else {
+                Some(convert_node(root, leaves))
+            };
+            rtcReleaseBVH(bvh);
+            root
+        };
+        Bvh { root, bounds }
+    }
+}
+
+impl<N, L> Bvh<N, L> {
+    /// Get the root of the tree, `None` if the BVH is empty
+    pub fn root(&self) -> Option<&BvhTree<N, L>> {
+        self.root.as_ref()
+    }
+    /// Get the bounds of all the primitives in the BVH
+    pub fn bounds(&self) -> Bounds {
+        self.bounds
+    }
+    /// Get the number of levels of the tree, 0 if it's empty
+    pub fn depth(&self) -> usize {
+        self.root.as_ref().map_or(0, |r| r.depth())
+    }
+    /// Traverse the tree depth first, descending into the nodes whose
+    /// bounds `visit` returns true for and calling `leaf` on each leaf
+    /// reached
+    pub fn traverse<V, F>(&self, mut visit: V, mut leaf: F)
+    where
+        V: FnMut(&Bounds) -> bool,
+        F: FnMut(&L),
+    {
+        let root = match self.root {
+            Some(ref r) if visit(&self.bounds) => r,
+            _ => return,
+        };
+        let mut stack = vec![root];
+        while let Some(node) = stack.pop() {
+            match *node {
+                BvhTree::Node { ref children, .. } => {
+                    for (b, c) in children.iter().rev() {
+                        if visit(b) {
+                            stack.push(c);
+                        }
+                    }
+                }
+                BvhTree::Leaf(ref l) => leaf(l),
+            }
+        }
+    }
+}
+
+/// The leaves created during a build, shared between Embree's build
+/// threads through the user pointer, and the first panic raised by a
+/// callback of the build to resume once the build is done
+struct BuildState<L> {
+    leaves: Mutex<Vec<Option<L>>>,
+    panic: Mutex<Option<Box<dyn Any + Send>>>,
+}
+
+impl<L> BuildState<L> {
+    /// Run a callback of the build, holding a panic in it until the build
+    /// returns as unwinding into Embree would abort. Returns `None` if the
+    /// callback panicked.
+    fn hold_panic<R, F: FnOnce() -> R>(&self, f: F) -> Option<R> {
+        match panic::catch_unwind(AssertUnwindSafe(f)) {
+            Ok(r) => Some(r),
+            Err(p) => {
+                let mut held = self.panic.lock().unwrap_or_else(|e| e.into_inner());
+                held.get_or_insert(p);
+                None
+            }
+        }
+    }
+}
+
+/// Node of the BVH, allocated with Embree's thread local allocator
+/// during the build and freed when the BVH is released. Leaves store the
+/// index of their data in the build state.
+#[repr(C)]
+struct RawNode {
+    leaf: bool,
+    leaf_index: usize,
+    child_count: usize,
+    children: [*mut RawNode; MAX_BRANCHING_FACTOR],
+    child_bounds: [Bounds; MAX_BRANCHING_FACTOR],
+}
+
+pub(crate) fn empty_bounds() -> Bounds {
+    Bounds {
+        lower_x: f32::INFINITY,
+        lower_y: f32::INFINITY,
+        lower_z: f32::INFINITY,
+        align0: 0.0,
+        upper_x: f32::NEG_INFINITY,
+        upper_y: f32::NEG_INFINITY,
+        upper_z: f32::NEG_INFINITY,
+        align1: 0.0,
+    }
+}
+
+pub(crate) fn union_bounds(a: &Bounds, b: &Bounds) -> Bounds {
+    Bounds {
+        lower_x: a.lower_x.min(b.lower_x),
+        lower_y: a.lower_y.min(b.lower_y),
+        lower_z: a.lower_z.min(b.lower_z),
+        align0: 0.0,
+        upper_x: a.upper_x.max(b.upper_x),
+        upper_y: a.upper_y.max(b.upper_y),
+        upper_z: a.upper_z.max(b.upper_z),
+        align1: 0.0,
+    }
+}
+
+/// Compute the bounds of the points, padded by the radius stored in w
+pub(crate) fn point_bounds<'p, I: Iterator<Item = &'p Vector4<f32>>>(points: I) -> Bounds {
+    let mut b = empty_bounds();
+    for p in points {
+        b.lower_x = b.lower_x.min(p.x - p.w);
+        b.lower_y = b.lower_y.min(p.y - p.w);
+        b.lower_z = b.lower_z.min(p.z - p.w);
+        b.upper_x = b.upper_x.max(p.x + p.w);
+        b.upper_y = b.upper_y.max(p.y + p.w);
+        b.upper_z = b.upper_z.max(p.z + p.w);
+    }
+    b
+}
+
+fn build_primitive(geom_id: u32, prim_id: u32, b: Bounds) -> RTCBuildPrimitive {
+    RTCBuildPrimitive {
+        lower_x: b.lower_x,
+        lower_y: b.lower_y,
+        lower_z: b.lower_z,
+        geomID: geom_id,
+        upper_x: b.upper_x,
+        upper_y: b.upper_y,
+        upper_z: b.upper_z,
+        primID: prim_id,
+    }
+}
+
+/// Collect the bounds of each primitive in the geometry. Meshes, linear
+/// curves and Bezier and B-spline curves are supported, for which the
+/// bounds of the control points contain the curve. The vertex w component
+/// of meshes is zero, so it doesn't pad their bounds. Geometry with
+/// shared data bound in place of its buffers can't be read and is skipped.
+fn collect_primitives(geom_id: u32, geom: &Geometry, prims: &mut Vec<RTCBuildPrimitive>) {
+    if !geom.owns_bound_buffers() {
+        return;
+    }
+    #[cfg(feature = "curves")]
+    let mut push_curves = |verts: &[Vector4<f32>], indices: &[u32], n: usize| {
+        for (i, start) in indices.iter().enumerate() {
+            let s = *start as usize;
+            let b = point_bounds(verts[s..s + n].iter());
+            prims.push(build_primitive(geom_id, i as u32, b));
+        }
+    };
+    match *geom {
+        Geometry::Triangle(ref m) => {
+            let verts = m.vertex_buffer.as_slice();
+            let indices = m.index_buffer.as_slice();
+            for (i, t) in indices.iter().enumerate() {
+                let v = [t.x, t.y, t.z];
+                let b = point_bounds(v.iter().map(|j| &verts[*j as usize]));
+                prims.push(build_primitive(geom_id, i as u32, b));
+            }
+        }
+        Geometry::Quad(ref m) => {
+            let verts = m.vertex_buffer.as_slice();
+            let indices = m.index_buffer.as_slice();
+            for (i, q) in indices.iter().enumerate() {
+                let v = [q.x, q.y, q.z, q.w];
+                let b = point_bounds(v.iter().map(|j| &verts[*j as usize]));
+                prims.push(build_primitive(geom_id, i as u32, b));
+            }
+        }
+        #[cfg(feature = "curves")]
+        Geometry::LinearCurve(ref c) => {
+            push_curves(c.vertex_buffer.as_slice(), c.index_buffer.as_slice(), 2)
+        }
+        #[cfg(feature = "curves")]
+        Geometry::BezierCurve(ref c) => {
+            push_curves(c.vertex_buffer.as_slice(), c.index_buffer.as_slice(), 4)
+        }
+        #[cfg(feature = "curves")]
+        Geometry::BsplineCurve(ref c) => {
+            push_curves(c.vertex_buffer.as_slice(), c.index_buffer.as_slice(), 4)
+        }
+        Geometry::User(ref u) => {
+            for i in 0..u.len() as u32 {
+                prims.push(build_primitive(geom_id, i, u.bounds(i)));
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Copy the node built by Embree and its children into a tree
+unsafe fn convert_node<N: BvhNode, L>(
+    node: *const RawNode,
+    leaves: &mut [Option<L>],
+) -> BvhTree<N, L> {
+    let node = &*node;
+    if node.leaf {
+        let leaf = leaves[node.leaf_index]
+            .take()
+            .expect("Each leaf is referenced once");
+        return BvhTree::Leaf(leaf);
+    }
+    let bounds = &node.child_bounds[..node.child_count];
+    let children = bounds
+        .iter()
+        .zip(node.children.iter())
+        .map(|(b, c)| (*b, convert_node(*c, leaves)))
+        .collect();
+    BvhTree::Node {
+        node: N::create(bounds),
+        children,
+    }
+}
+
+unsafe fn alloc_node(alloc: RTCThreadLocalAllocator) -> *mut RawNode {
+    let node = rtcThreadLocalAlloc(alloc, mem::size_of::<RawNode>(), mem::align_of::<RawNode>())
+        as *mut RawNode;
+    ptr::write(
+        node,
+        RawNode {
+            leaf: false,
+            leaf_index: 0,
+            child_count: 0,
+            children: [ptr::null_mut(); MAX_BRANCHING_FACTOR],
+            child_bounds: [empty_bounds(); MAX_BRANCHING_FACTOR],
+        },
+    );
+    node
+}
+
+/// Allocate a node, which is still returned if its child count is out of
+/// range as Embree sets its children next, the panic raised for it is
+/// resumed once the build returns
+unsafe extern "C" fn create_node<L>(
+    alloc: RTCThreadLocalAllocator,
+    child_count: raw::c_uint,
+    user_ptr: *mut raw::c_void,
+) -> *mut raw::c_void {
+    let state = &*(user_ptr as *const BuildState<L>);
+    state.hold_panic(|| {
+        assert!(
+            child_count as usize <= MAX_BRANCHING_FACTOR,
+            "Node has {} children, more than the maximum of {}",
+            child_count,
+            MAX_BRANCHING_FACTOR
+        )
+    });
+    alloc_node(alloc) as *mut raw::c_void
+}
+
+unsafe extern "C" fn set_node_children<L>(
+    node: *mut raw::c_void,
+    children: *mut *mut raw::c_void,
+    child_count: raw::c_uint,
+    user_ptr: *mut raw::c_void,
+) {
+    let state = &*(user_ptr as *const BuildState<L>);
+    let node = &mut *(node as *mut RawNode);
+    let children = slice::from_raw_parts(children, child_count as usize);
+    state.hold_panic(|| {
+        node.child_count = children.len();
+        for (i, c) in children.iter().enumerate() {
+            node.children[i] = *c as *mut RawNode;
+        }
+    });
+}
+
+unsafe extern "C" fn set_node_bounds<L>(
+    node: *mut raw::c_void,
+    bounds: *mut *const RTCBounds,
+    child_count: raw::c_uint,
+    user_ptr: *mut raw::c_void,
+) {
+    let state = &*(user_ptr as *const BuildState<L>);
+    let node = &mut *(node as *mut RawNode);
+    let bounds = slice::from_raw_parts(bounds, child_count as usize);
+    state.hold_panic(|| {
+        for (i, b) in bounds.iter().enumerate() {
+            node.child_bounds[i] = **b;
+        }
+    });
+}
+
+unsafe extern "C" fn create_leaf<L: BvhLeaf>(
+    alloc: RTCThreadLocalAllocator,
+    prims: *const RTCBuildPrimitive,
+    prim_count: usize,
+    user_ptr: *mut raw::c_void,
+) -> *mut raw::c_void {
+    let state = &*(user_ptr as *const BuildState<L>);
+    let prims = slice::from_raw_parts(prims, prim_count);
+    let index = match state.hold_panic(|| L::create(prims)) {
+        Some(leaf) => {
+            let mut leaves = state.leaves.lock().unwrap();
+            leaves.push(Some(leaf));
+            leaves.len() - 1
+        }
+        None => usize::MAX,
+    };
+    let node = alloc_node(alloc);
+    (*node).leaf = true;
+    (*node).leaf_index = index;
+    node as *mut raw::c_void
+}
+
+/// Build a binary BVH over the primitives in the scene and return the
+/// bounds of its nodes grouped by level, where `levels[0]` holds the
+/// bounds of the root and `levels[d]` the bounds of the nodes at depth
+/// `d`. Leaves are included at the level they occur. If `max_depth` is
+/// set the levels below it are not returned. Primitives of instances,
+/// Catmull-Rom and Hermite curves and geometry with buffers shared from
+/// user memory are not included in the build.
+pub fn bvh_levels(
+    device: &Device,
+    scene: &CommittedScene,
+    max_depth: Option<usize>,
+) -> Vec<Vec<Bounds>> {
+    let mut prims = Vec::new();
+    for (id, geom) in scene.scene.iter_ordered() {
+        collect_primitives(id, geom, &mut prims);
+    }
+    let bvh: Bvh<(), ()> = Bvh::build(device, &prims, &BuildSettings::new());
+    let root = match bvh.root() {
+        Some(r) => r,
+        None => return Vec::new(),
+    };
+
+    let max_levels = max_depth.map(|d| d + 1).unwrap_or(usize::MAX);
+    let mut levels: Vec<Vec<Bounds>> = Vec::new();
+    let mut current = vec![(root, bvh.bounds())];
+    while !current.is_empty() && levels.len() < max_levels {
+        let mut next = Vec::new();
+        for &(node, _) in current.iter() {
+            if let BvhTree::Node { ref children, .. } = *node {
+                next.extend(children.iter().map(|&(b, ref c)| (c, b)));
+            }
+        }
+        levels.push(current.iter().map(|n| n.1).collect());
+        current = next;
+    }
+    levels
+}
+
+#[cfg(test)]
+fn test_bounds(lower: f32, upper: f32) -> Bounds {
+    Bounds {
+        lower_x: lower,
+        lower_y: 0.0,
+        lower_z: 0.0,
+        align0: 0.0,
+        upper_x: upper,
+        upper_y: 1.0,
+        upper_z: 1.0,
+        align1: 0.0,
+    }
+}
+
+#[test]
+fn test_convert_node() {
+    let raw_node = |leaf, leaf_index, child_count| RawNode {
+        leaf,
+        leaf_index,
+        child_count,
+        children: [ptr::null_mut(); MAX_BRANCHING_FACTOR],
+        child_bounds: [empty_bounds(); MAX_BRANCHING_FACTOR],
+    };
+    let mut leaves: Vec<RawNode> = (0..3).map(|i| raw_node(true, i, 0)).collect();
+    let mut root = raw_node(false, 0, 3);
+    for (i, l) in leaves.iter_mut().enumerate() {
+        root.children[i] = l;
+        root.child_bounds[i] = test_bounds(i as f32, i as f32 + 1.0);
+    }
+    let mut data = vec![Some('a'), Some('b'), Some('c')];
+    let tree: BvhTree<usize, char> = unsafe { convert_node(&root, &mut data) };
+    assert_eq!(tree.depth(), 2);
+    if let BvhTree::Node { ref node, .. } = &tree {
+        assert_eq!(*node, 3);
+    }
+    assert!(data.iter().all(|d| d.is_none()));
+
+    // Only descend into the children overlapping x = 2.5
+    let bvh = Bvh {
+        root: Some(tree),
+        bounds: test_bounds(0.0, 3.0),
+    };
+    let mut found = Vec::new();
+    bvh.traverse(|b| b.lower_x <= 2.5 && 2.5 <= b.upper_x, |l| found.push(*l));
+    assert_eq!(found, vec!['c']);
+    let mut all = Vec::new();
+    bvh.traverse(|_| true, |l| all.push(*l));
+    assert_eq!(all, vec!['a', 'b', 'c']);
+}
+
+#[cfg(test)]
+impl BvhNode for usize {
+    fn create(child_bounds: &[Bounds]) -> usize {
+        child_bounds.len()
+    }
+}
+
+#[test]
+#[should_panic]
+fn test_branching_factor_limit() {
+    let _ = BuildSettings::new().branching_factor(MAX_BRANCHING_FACTOR + 1);
+}
+
+#[test]
+fn test_node_panic_is_held() {
+    let state = BuildState::<()> {
+        leaves: Mutex::new(Vec::new()),
+        panic: Mutex::new(None),
+    };
+    let user_ptr = &state as *const BuildState<()> as *mut raw::c_void;
+    let mut node = RawNode {
+        leaf: false,
+        leaf_index: 0,
+        child_count: 0,
+        children: [ptr::null_mut(); MAX_BRANCHING_FACTOR],
+        child_bounds: [empty_bounds(); MAX_BRANCHING_FACTOR],
+    };
+    let node_ptr = &mut node as *mut RawNode as *mut raw::c_void;
+    // More children than a node holds
+    let mut children = [ptr::null_mut(); MAX_BRANCHING_FACTOR + 1];
+    let b = empty_bounds();
+    let mut bounds = [&b as *const RTCBounds; MAX_BRANCHING_FACTOR + 1];
+    unsafe {
+        set_node_children::<()>(
+            node_ptr,
+            children.as_mut_ptr(),
+            children.len() as u32,
+            user_ptr,
+        );
+        set_node_bounds::<()>(node_ptr, bounds.as_mut_ptr(), bounds.len() as u32, user_ptr);
+    }
+    assert!(state.panic.lock().unwrap().take().is_some());
+    unsafe {
+        set_node_children::<()>(node_ptr, children.as_mut_ptr(), 2, user_ptr);
+    }
+    assert!(state.panic.lock().unwrap().is_none());
+    assert_eq!(node.child_count, 2);
+}
@@ -0,0 +1,278 @@
+//! A minimal C API over the safe wrapper, for embedding scenes managed by
+//! the crate in C and C++ applications. It's built with the `capi`
+//! feature, and the header declaring it is in `include/embree_rs.h`,
+//! generated with `scripts/generate-capi-header.sh`. To link against it,
+//! build the crate as a static or dynamic library, e.g. with
+//! `cargo rustc --release --features capi --crate-type staticlib`, and
+//! link Embree as well.
+//!
+//! Devices and scenes are opaque handles created and freed through the
+//! API. A device must outlive the scenes created on it, and a scene can't
+//! be changed while other threads are querying it. Queries can be run
+//! from multiple threads at once. A scene must be committed after adding
+//! geometry before it can be queried, queries on a scene which changed
+//! since its last commit miss.
+//!
+//! Unwinding out of the API would abort the process, so a panic in a call
+//! is caught and reported like other failures, e.g. by returning null,
+//! `EMBREE_RS_INVALID_ID` or false.
+//!
+//! ```c
+//! EmbreeRsDevice *device = embree_rs_device_new();
+//! EmbreeRsScene *scene = embree_rs_scene_new(device);
+//! float positions[] = {-1, -1, 0, 1, -1, 0, 0, 1, 0};
+//! uint32_t indices[] = {0, 1, 2};
+//! embree_rs_scene_add_triangle_mesh(scene, positions, 3, indices, 1);
+//! if (!embree_rs_scene_commit(scene)) {
+//!     fprintf(stderr, "commit failed\n");
+//! }
+//!
+//! EmbreeRsRay ray = {{0, 0, 1}, {0, 0, -1}, 0, INFINITY};
+//! EmbreeRsHit hit;
+//! if (embree_rs_scene_intersect1(scene, &ray, &hit)) {
+//!     printf("hit primitive %u at t = %f\n", hit.prim_id, hit.t);
+//! }
+//! embree_rs_scene_free(scene);
+//! embree_rs_device_free(device);
+//! ```
+
+use std::panic::{self, AssertUnwindSafe};
+use std::{ptr, slice};
+
+use cgmath::Vector3;
+
+use device::Device;
+use geometry::Geometry;
+use ray::Ray;
+use scene::Scene;
+use triangle_mesh::TriangleMesh;
+
+/// The ID returned in place of a geometry, primitive or instance ID when
+/// there's none
+pub const EMBREE_RS_INVALID_ID: u32 = u32::MAX;
+
+/// Run the body of an API function, returning `error` if it panics
+fn guard<R, F: FnOnce() -> R>(error: R, f: F) -> R {
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or(error)
+}
+
+/// An Embree device
+pub struct EmbreeRsDevice {
+    device: Device,
+}
+
+/// A scene and the geometry attached to it
+pub struct EmbreeRsScene {
+    /// The device the scene was created on, which the caller keeps alive
+    device: &'static Device,
+    scene: Scene<'static>,
+    /// Whether the scene was committed since it was last changed
+    committed: bool,
+}
+
+/// A ray to query a scene with
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct EmbreeRsRay {
+    pub org: [f32; 3],
+    pub dir: [f32; 3],
+    /// The start of the ray's interval
+    pub tnear: f32,
+    /// The end of the ray's interval, e.g. `INFINITY`
+    pub tfar: f32,
+}
+
+/// The closest hit found by a query
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct EmbreeRsHit {
+    /// The distance along the ray to the hit
+    pub t: f32,
+    /// The parametric coordinates of the hit on the primitive
+    pub u: f32,
+    pub v: f32,
+    /// The unnormalized geometric normal of the hit
+    pub ng: [f32; 3],
+    /// The ID of the geometry hit, as returned when adding it
+    pub geom_id: u32,
+    pub prim_id: u32,
+    /// The ID of the instance the hit was found through, if any
+    pub inst_id: u32,
+}
+
+/// Create a device with the default configuration. Returns null if the
+/// device can't be created.
+#[no_mangle]
+pub extern "C" fn embree_rs_device_new() -> *mut EmbreeRsDevice {
+    guard(ptr::null_mut(), || {
+        let device = Device::new();
+        if device.handle.is_null() {
+            return ptr::null_mut();
+        }
+        Box::into_raw(Box::new(EmbreeRsDevice { device }))
+    })
+}
+
+/// Free a device, null is ignored.
+///
+/// # Safety
+/// `device` must be null or returned by `embree_rs_device_new`, and the
+/// scenes created on it must have been freed.
+#[no_mangle]
+pub unsafe extern "C" fn embree_rs_device_free(device: *mut EmbreeRsDevice) {
+    guard((), || {
+        if !device.is_null() {
+            drop(Box::from_raw(device));
+        }
+    })
+}
+
+/// Create an empty scene on the device. Returns null if `device` is null.
+///
+/// # Safety
+/// `device` must be null or a live device, and must outlive the scene.
+#[no_mangle]
+pub unsafe extern "C" fn embree_rs_scene_new(device: *const EmbreeRsDevice) -> *mut EmbreeRsScene {
+    guard(ptr::null_mut(), || {
+        if device.is_null() {
+            return ptr::null_mut();
+        }
+        let device: &'static Device = &(*device).device;
+        Box::into_raw(Box::new(EmbreeRsScene {
+            device,
+            scene: Scene::new(device),
+            committed: false,
+        }))
+    })
+}
+
+/// Free a scene and its geometry, null is ignored.
+///
+/// # Safety
+/// `scene` must be null or returned by `embree_rs_scene_new`, and mustn't
+/// be in use by other threads.
+#[no_mangle]
+pub unsafe extern "C" fn embree_rs_scene_free(scene: *mut EmbreeRsScene) {
+    guard((), || {
+        if !scene.is_null() {
+            drop(Box::from_raw(scene));
+        }
+    })
+}
+
+/// Add a triangle mesh to the scene, copying `num_vertices` positions of
+/// three floats each and `num_triangles` triangles of three vertex indices
+/// each. Returns the ID of the mesh in the scene, or
+/// `EMBREE_RS_INVALID_ID` if a pointer is null, an index is out of bounds
+/// or the mesh can't be created.
+///
+/// # Safety
+/// `scene` must be a live scene not in use by other threads, and
+/// `positions` and `indices` must point to arrays of at least
+/// `3 * num_vertices` floats and `3 * num_triangles` indices.
+#[no_mangle]
+pub unsafe extern "C" fn embree_rs_scene_add_triangle_mesh(
+    scene: *mut EmbreeRsScene,
+    positions: *const f32,
+    num_vertices: usize,
+    indices: *const u32,
+    num_triangles: usize,
+) -> u32 {
+    guard(EMBREE_RS_INVALID_ID, || {
+        if scene.is_null() || positions.is_null() || indices.is_null() {
+            return EMBREE_RS_INVALID_ID;
+        }
+        let scene = &mut *scene;
+        let positions = slice::from_raw_parts(positions as *const [f32; 3], num_vertices);
+        let indices = slice::from_raw_parts(indices as *const [u32; 3], num_triangles);
+        match TriangleMesh::try_from_slices(scene.device, positions, indices) {
+            Ok(mesh) => {
+                scene.committed = false;
+                scene.scene.attach_geometry(Geometry::Triangle(mesh))
+            }
+            Err(_) => EMBREE_RS_INVALID_ID,
+        }
+    })
+}
+
+/// Commit the scene, building its BVH so it can be queried. Returns false
+/// if `scene` is null or the commit failed, in which case queries on the
+/// scene miss until it's committed again.
+///
+/// # Safety
+/// `scene` must be null or a live scene not in use by other threads.
+#[no_mangle]
+pub unsafe extern "C" fn embree_rs_scene_commit(scene: *mut EmbreeRsScene) -> bool {
+    guard(false, || {
+        if scene.is_null() {
+            return false;
+        }
+        let scene: &'static mut EmbreeRsScene = &mut *scene;
+        scene.committed = false;
+        scene.scene.commit();
+        scene.committed = true;
+        true
+    })
+}
+
+/// Find the closest hit along the ray, writing it to `hit` and returning
+/// true if there is one. Returns false if the ray misses, a pointer is
+/// null, the scene changed since it was last committed or the query
+/// failed.
+///
+/// # Safety
+/// `scene` must be a live scene, and `ray` and `hit` must point to a valid
+/// ray and hit to write.
+#[no_mangle]
+pub unsafe extern "C" fn embree_rs_scene_intersect1(
+    scene: *const EmbreeRsScene,
+    ray: *const EmbreeRsRay,
+    hit: *mut EmbreeRsHit,
+) -> bool {
+    guard(false, || {
+        if scene.is_null() || ray.is_null() || hit.is_null() {
+            return false;
+        }
+        let scene: &'static EmbreeRsScene = &*scene;
+        if !scene.committed {
+            return false;
+        }
+        let committed = match scene.scene.last_commit() {
+            Some(c) => c,
+            None => return false,
+        };
+        let r = &*ray;
+        let ray = Ray::segment(
+            Vector3::new(r.org[0], r.org[1], r.org[2]),
+            Vector3::new(r.dir[0], r.dir[1], r.dir[2]),
+            r.tnear,
+            r.tfar,
+        );
+        match committed.intersect_ray(&ray) {
+            Some(ray_hit) => {
+                let h = &ray_hit.hit;
+                *hit = EmbreeRsHit {
+                    t: ray_hit.ray.tfar,
+                    u: h.u,
+                    v: h.v,
+                    ng: [h.Ng_x, h.Ng_y, h.Ng_z],
+                    geom_id: h.geomID,
+                    prim_id: h.primID,
+                    inst_id: h.instID[0],
+                };
+                true
+            }
+            None => false,
+        }
+    })
+}
+
+#[test]
+fn test_capi_layout() {
+    use std::mem;
+
+    // The layouts declared in include/embree_rs.h
+    assert_eq!(mem::size_of::<EmbreeRsRay>(), 32);
+    assert_eq!(mem::size_of::<EmbreeRsHit>(), 36);
+    assert_eq!(mem::align_of::<EmbreeRsHit>(), 4);
+}
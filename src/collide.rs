@@ -0,0 +1,104 @@
+use std::any::Any;
+use std::os::raw;
+use std::panic::{self, AssertUnwindSafe};
+use std::slice;
+use std::sync::Mutex;
+
+use sys;
+
+/// A pair of potentially colliding primitives found by
+/// `CommittedScene::collide`. The `geomID0` and `primID0` members refer
+/// to the primitive in the first scene, `geomID1` and `primID1` to the
+/// primitive in the second.
+pub type Collision = sys::RTCCollision;
+
+/// The closure of a collision query and the first panic it raised, which
+/// is held until the query returns as unwinding into Embree would abort
+struct CollideState<'f, F: 'f> {
+    callback: &'f F,
+    panic: Mutex<Option<Box<dyn Any + Send>>>,
+}
+
+/// Call the Rust closure passed through the user pointer with the
+/// collisions, skipping it once it has panicked
+unsafe extern "C" fn collide_callback<F>(
+    user_ptr: *mut raw::c_void,
+    collisions: *mut sys::RTCCollision,
+    num_collisions: raw::c_uint,
+) where
+    F: Fn(&[Collision]) + Sync,
+{
+    let state = &*(user_ptr as *const CollideState<F>);
+    if num_collisions == 0 {
+        return;
+    }
+    let collisions = slice::from_raw_parts(collisions, num_collisions as usize);
+    let mut held = state.panic.lock().unwrap_or_else(|e| e.into_inner());
+    if held.is_some() {
+        return;
+    }
+    // The lock isn't held while calling the closure, which may be called
+    // concurrently from Embree's threads
+    drop(held);
+    if let Err(p) = panic::catch_unwind(AssertUnwindSafe(|| (state.callback)(collisions))) {
+        held = state.panic.lock().unwrap_or_else(|e| e.into_inner());
+        if held.is_none() {
+            *held = Some(p);
+        }
+    }
+}
+
+/// Find the colliding primitives of the scenes with `rtcCollide`, calling
+/// the closure with them and resuming a panic it raised once the query
+/// returns
+pub(crate) unsafe fn collide<F>(scene0: sys::RTCScene, scene1: sys::RTCScene, callback: &F)
+where
+    F: Fn(&[Collision]) + Sync,
+{
+    let state = CollideState {
+        callback,
+        panic: Mutex::new(None),
+    };
+    sys::rtcCollide(
+        scene0,
+        scene1,
+        Some(collide_callback::<F>),
+        &state as *const CollideState<F> as *mut raw::c_void,
+    );
+    let held = state.panic.lock().unwrap_or_else(|e| e.into_inner()).take();
+    if let Some(p) = held {
+        panic::resume_unwind(p);
+    }
+}
+
+#[test]
+fn test_collide_callback_panic() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    let calls = AtomicUsize::new(0);
+    let callback = |c: &[Collision]| {
+        calls.fetch_add(1, Ordering::Relaxed);
+        assert!(c[0].primID0 == 0, "callback panicked");
+    };
+    let state = CollideState {
+        callback: &callback,
+        panic: Mutex::new(None),
+    };
+    unsafe fn call<F: Fn(&[Collision]) + Sync>(state: &CollideState<F>, c: &mut [Collision]) {
+        let ptr = state as *const CollideState<F> as *mut raw::c_void;
+        collide_callback::<F>(ptr, c.as_mut_ptr(), c.len() as u32);
+    }
+    let mut collisions = [Collision {
+        geomID0: 0,
+        primID0: 1,
+        geomID1: 0,
+        primID1: 2,
+    }];
+    unsafe {
+        call(&state, &mut collisions);
+        // The closure isn't called again once it has panicked
+        call(&state, &mut collisions);
+    }
+    assert_eq!(calls.load(Ordering::Relaxed), 1);
+    let p = state.panic.lock().unwrap().take().unwrap();
+    assert_eq!(p.downcast_ref::<&str>(), Some(&"callback panicked"));
+}
@@ -1,6 +1,192 @@
+use bezier_curve::BezierCurve;
+use bspline_curve::BsplineCurve;
+use catmull_rom_curve::CatmullRomCurve;
+use device::Device;
+use geometry::Geometry;
+use hermite_curve::HermiteCurve;
+use linear_curve::LinearCurve;
+use {BufferType, Error, GeometryKind};
+
+/// How a curve is shaped around its center line
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum CurveType {
+    /// A ribbon facing the ray
     Flat,
+    /// A ribbon facing along the normals given in the normal buffer
     NormalOriented,
+    /// A tube with round ends
     Round,
+    /// A tube with flat ends, only supported by linear curves
     Cone,
 }
+
+/// The basis the vertices of a curve segment are interpreted in, picking
+/// which curve wrapper `Geometry::curve` creates
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum CurveBasis {
+    /// Straight segments between two vertices, a `LinearCurve`
+    Linear,
+    /// Cubic Bézier segments of four control points, a `BezierCurve`
+    Bezier,
+    /// Cubic B-spline segments of four control points, a `BsplineCurve`
+    Bspline,
+    /// Cubic Hermite segments between two vertices with tangents, a
+    /// `HermiteCurve`
+    Hermite,
+    /// Cubic Catmull-Rom segments of four control points, a
+    /// `CatmullRomCurve`
+    CatmullRom,
+}
+
+impl CurveBasis {
+    /// The kind of geometry holding curves of the basis
+    pub fn kind(&self) -> GeometryKind {
+        match *self {
+            CurveBasis::Linear => GeometryKind::LinearCurve,
+            CurveBasis::Bezier => GeometryKind::BezierCurve,
+            CurveBasis::Bspline => GeometryKind::BsplineCurve,
+            CurveBasis::Hermite => GeometryKind::HermiteCurve,
+            CurveBasis::CatmullRom => GeometryKind::CatmullRomCurve,
+        }
+    }
+    /// Whether Embree has curves of the basis with the type: linear curves
+    /// can't be normal oriented and only linear curves can be cones
+    pub fn supports(&self, curve_type: CurveType) -> bool {
+        match (*self, curve_type) {
+            (CurveBasis::Linear, CurveType::NormalOriented) => false,
+            (CurveBasis::Linear, _) => true,
+            (_, CurveType::Cone) => false,
+            _ => true,
+        }
+    }
+    /// Get the buffers which must be set before committing curves of the
+    /// basis and type. Normal oriented curves need normals, and normal
+    /// oriented Hermite curves their derivatives as well.
+    pub fn required_buffers(&self, curve_type: CurveType) -> &'static [BufferType] {
+        match (*self, curve_type) {
+            (CurveBasis::Hermite, CurveType::NormalOriented) => &[
+                BufferType::VERTEX,
+                BufferType::INDEX,
+                BufferType::TANGENT,
+                BufferType::NORMAL,
+                BufferType::NORMAL_DERIVATIVE,
+            ],
+            (CurveBasis::Hermite, _) => {
+                &[BufferType::VERTEX, BufferType::INDEX, BufferType::TANGENT]
+            }
+            (_, CurveType::NormalOriented) => {
+                &[BufferType::VERTEX, BufferType::INDEX, BufferType::NORMAL]
+            }
+            _ => &[BufferType::VERTEX, BufferType::INDEX],
+        }
+    }
+}
+
+impl<'a> Geometry<'a> {
+    /// Create curves of the basis and type with `num_segments` segments
+    /// over `num_verts` vertices, e.g. normal oriented B-splines with
+    /// `Geometry::curve(&device, CurveBasis::Bspline, CurveType::NormalOriented, n, 4 * n)`.
+    /// The buffers required by the type are allocated, see
+    /// `CurveBasis::required_buffers`, and are checked by `try_commit`
+    /// once filled. To use normals with flat or round curves create them
+    /// through the curve wrappers instead.
+    ///
+    /// Returns `Error::INVALID_ARGUMENT` if the basis doesn't support the
+    /// type, see `CurveBasis::supports`.
+    pub fn curve(
+        device: &'a Device,
+        basis: CurveBasis,
+        curve_type: CurveType,
+        num_segments: usize,
+        num_verts: usize,
+    ) -> Result<Geometry<'a>, Error> {
+        if !basis.supports(curve_type) {
+            return Err(Error::INVALID_ARGUMENT);
+        }
+        let (s, v) = (num_segments, num_verts);
+        let geom = match (basis, curve_type) {
+            (CurveBasis::Linear, CurveType::Flat) => {
+                Geometry::LinearCurve(LinearCurve::flat(device, s, v, false))
+            }
+            (CurveBasis::Linear, CurveType::Round) => {
+                Geometry::LinearCurve(LinearCurve::round(device, s, v, false))
+            }
+            (CurveBasis::Linear, _) => {
+                Geometry::LinearCurve(LinearCurve::cone(device, s, v, false))
+            }
+            (CurveBasis::Bezier, CurveType::Flat) => {
+                Geometry::BezierCurve(BezierCurve::flat(device, s, v, false))
+            }
+            (CurveBasis::Bezier, CurveType::Round) => {
+                Geometry::BezierCurve(BezierCurve::round(device, s, v, false))
+            }
+            (CurveBasis::Bezier, _) => {
+                Geometry::BezierCurve(BezierCurve::normal_oriented(device, s, v))
+            }
+            (CurveBasis::Bspline, CurveType::Flat) => {
+                Geometry::BsplineCurve(BsplineCurve::flat(device, s, v, false))
+            }
+            (CurveBasis::Bspline, CurveType::Round) => {
+                Geometry::BsplineCurve(BsplineCurve::round(device, s, v, false))
+            }
+            (CurveBasis::Bspline, _) => {
+                Geometry::BsplineCurve(BsplineCurve::normal_oriented(device, s, v))
+            }
+            (CurveBasis::Hermite, CurveType::Flat) => {
+                Geometry::HermiteCurve(HermiteCurve::flat(device, s, v, false))
+            }
+            (CurveBasis::Hermite, CurveType::Round) => {
+                Geometry::HermiteCurve(HermiteCurve::round(device, s, v, false))
+            }
+            (CurveBasis::Hermite, _) => {
+                Geometry::HermiteCurve(HermiteCurve::normal_oriented(device, s, v))
+            }
+            (CurveBasis::CatmullRom, CurveType::Flat) => {
+                Geometry::CatmullRomCurve(CatmullRomCurve::flat(device, s, v, false))
+            }
+            (CurveBasis::CatmullRom, CurveType::Round) => {
+                Geometry::CatmullRomCurve(CatmullRomCurve::round(device, s, v, false))
+            }
+            (CurveBasis::CatmullRom, _) => {
+                Geometry::CatmullRomCurve(CatmullRomCurve::normal_oriented(device, s, v))
+            }
+        };
+        Ok(geom)
+    }
+    /// Get the basis of the curves, or `None` if the geometry isn't a curve
+    pub fn curve_basis(&self) -> Option<CurveBasis> {
+        match *self {
+            Geometry::LinearCurve(_) => Some(CurveBasis::Linear),
+            Geometry::BezierCurve(_) => Some(CurveBasis::Bezier),
+            Geometry::BsplineCurve(_) => Some(CurveBasis::Bspline),
+            Geometry::HermiteCurve(_) => Some(CurveBasis::Hermite),
+            Geometry::CatmullRomCurve(_) => Some(CurveBasis::CatmullRom),
+            _ => None,
+        }
+    }
+    /// Get the type of the curves, or `None` if the geometry isn't a curve
+    pub fn curve_type(&self) -> Option<CurveType> {
+        match *self {
+            Geometry::LinearCurve(ref c) => Some(c.curve_type),
+            Geometry::BezierCurve(ref c) => Some(c.curve_type),
+            Geometry::BsplineCurve(ref c) => Some(c.curve_type),
+            Geometry::HermiteCurve(ref c) => Some(c.curve_type),
+            Geometry::CatmullRomCurve(ref c) => Some(c.curve_type),
+            _ => None,
+        }
+    }
+}
+
+#[test]
+fn test_curve_basis_support() {
+    assert!(CurveBasis::Linear.supports(CurveType::Cone));
+    assert!(!CurveBasis::Linear.supports(CurveType::NormalOriented));
+    assert!(!CurveBasis::Bspline.supports(CurveType::Cone));
+    assert!(CurveBasis::Hermite.supports(CurveType::NormalOriented));
+    assert!(CurveBasis::Hermite
+        .required_buffers(CurveType::NormalOriented)
+        .contains(&BufferType::NORMAL_DERIVATIVE));
+    assert!(!CurveBasis::Bezier
+        .required_buffers(CurveType::Round)
+        .contains(&BufferType::NORMAL));
+}
@@ -0,0 +1,325 @@
+//! Debugging aids for inspecting the rays traced by a renderer. Rays are
+//! written as line segments to OBJ or PLY files, which can be opened in
+//! MeshLab or Blender to check their distribution when diagnosing sampling
+//! or masking bugs. Each segment starts at the ray's origin and ends at:
+//!
+//! - The hit point, for rays with a hit.
+//! - `origin + dir * tfar` for rays which missed with a finite `tfar`,
+//!   e.g. shadow rays towards a light.
+//! - `origin + dir * miss_length` for rays which missed with an infinite
+//!   `tfar` and for occluded rays, whose `tfar` Embree sets to `-inf`.
+//!
+//! OBJ files put the hit, occluded and missed rays in the groups `hits`,
+//! `occluded` and `misses`, while PLY files color them green, yellow and
+//! red.
+//!
+//! Rays can be captured from the stream queries of a scene by setting a
+//! `RayCapture` with `Scene::set_ray_capture`, which records every Nth
+//! ray traced by the queries along with its hit:
+//!
+//! ```ignore
+//! let capture = Arc::new(RayCapture::every(64));
+//! scene.set_ray_capture(Some(capture.clone()));
+//! // ... render with intersect_stream_soa
+//! capture.dump("rays.ply")?;
+//! ```
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+#[cfg(feature = "streams")]
+use std::sync::atomic::{AtomicUsize, Ordering};
+#[cfg(feature = "streams")]
+use std::sync::Mutex;
+
+use cgmath::Vector3;
+
+#[cfg(feature = "streams")]
+use ray::RayHit;
+use ray::{Hit, Ray};
+#[cfg(feature = "streams")]
+use ray_stream::{HitN, RayHitN, RayN};
+#[cfg(feature = "streams")]
+use soa_ray::{SoAHit, SoARay};
+
+/// The length of the segments written for rays without an end point, in
+/// units of the ray direction's length
+pub const DEFAULT_MISS_LENGTH: f32 = 1.0;
+
+/// The file format to write rays in
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LineFormat {
+    Obj,
+    Ply,
+}
+
+impl LineFormat {
+    /// Pick the format from the extension of the path, PLY for `.ply` and
+    /// OBJ otherwise
+    pub fn from_path<P: AsRef<Path>>(path: P) -> LineFormat {
+        match path.as_ref().extension().and_then(|e| e.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("ply") => LineFormat::Ply,
+            _ => LineFormat::Obj,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum SegmentKind {
+    Hit,
+    Occluded,
+    Miss,
+}
+
+const KINDS: [(SegmentKind, &str, [u8; 3]); 3] = [
+    (SegmentKind::Hit, "hits", [0, 255, 0]),
+    (SegmentKind::Occluded, "occluded", [255, 255, 0]),
+    (SegmentKind::Miss, "misses", [255, 0, 0]),
+];
+
+/// Get the end point of the segment drawn for the ray and what it shows
+fn segment_end(ray: &Ray, hit: Option<&Hit>, miss_length: f32) -> (Vector3<f32>, SegmentKind) {
+    let (t, kind) = if hit.is_some_and(|h| h.hit()) {
+        (ray.tfar, SegmentKind::Hit)
+    } else if ray.tfar == f32::NEG_INFINITY {
+        (miss_length, SegmentKind::Occluded)
+    } else if ray.tfar.is_finite() {
+        (ray.tfar, SegmentKind::Miss)
+    } else {
+        (miss_length, SegmentKind::Miss)
+    };
+    (ray.origin() + ray.dir() * t, kind)
+}
+
+/// Write the rays as line segments in the format, see the module
+/// documentation. `hits` holds the hit of each ray, or is empty for rays
+/// traced by occlusion queries.
+///
+/// Panics if `hits` isn't empty and has a different length than `rays`.
+pub fn write_rays_as_lines<W: Write>(
+    rays: &[Ray],
+    hits: &[Hit],
+    miss_length: f32,
+    format: LineFormat,
+    mut out: W,
+) -> io::Result<()> {
+    assert!(
+        hits.is_empty() || hits.len() == rays.len(),
+        "Each ray must have a hit if hits are passed"
+    );
+    let segments: Vec<_> = rays
+        .iter()
+        .enumerate()
+        .map(|(i, r)| {
+            let (end, kind) = segment_end(r, hits.get(i), miss_length);
+            (r.origin(), end, kind)
+        })
+        .collect();
+    match format {
+        LineFormat::Obj => {
+            writeln!(out, "# {} rays", rays.len())?;
+            for &(start, end, _) in segments.iter() {
+                writeln!(out, "v {} {} {}", start.x, start.y, start.z)?;
+                writeln!(out, "v {} {} {}", end.x, end.y, end.z)?;
+            }
+            for &(kind, group, _) in KINDS.iter() {
+                writeln!(out, "g {}", group)?;
+                for (i, s) in segments.iter().enumerate() {
+                    if s.2 == kind {
+                        writeln!(out, "l {} {}", 2 * i + 1, 2 * i + 2)?;
+                    }
+                }
+            }
+        }
+        LineFormat::Ply => {
+            writeln!(out, "ply\nformat ascii 1.0")?;
+            writeln!(out, "element vertex {}", 2 * segments.len())?;
+            writeln!(out, "property float x\nproperty float y\nproperty float z")?;
+            writeln!(
+                out,
+                "property uchar red\nproperty uchar green\nproperty uchar blue"
+            )?;
+            writeln!(out, "element edge {}", segments.len())?;
+            writeln!(
+                out,
+                "property int vertex1\nproperty int vertex2\nend_header"
+            )?;
+            for &(start, end, kind) in segments.iter() {
+                let c = KINDS.iter().find(|k| k.0 == kind).unwrap().2;
+                for p in &[start, end] {
+                    writeln!(out, "{} {} {} {} {} {}", p.x, p.y, p.z, c[0], c[1], c[2])?;
+                }
+            }
+            for i in 0..segments.len() {
+                writeln!(out, "{} {}", 2 * i, 2 * i + 1)?;
+            }
+        }
+    }
+    out.flush()
+}
+
+/// Write the rays as line segments to the file, in PLY format if its
+/// extension is `.ply` and OBJ otherwise, see `write_rays_as_lines`.
+pub fn dump_rays_as_lines<P: AsRef<Path>>(rays: &[Ray], hits: &[Hit], path: P) -> io::Result<()> {
+    let format = LineFormat::from_path(&path);
+    let file = BufWriter::new(File::create(path)?);
+    write_rays_as_lines(rays, hits, DEFAULT_MISS_LENGTH, format, file)
+}
+
+/// Records every Nth ray traced by the stream queries of a scene it's set
+/// on with `Scene::set_ray_capture`, along with its hit. The count runs
+/// across all the queries, so rays are sampled evenly however the work is
+/// split into streams. Rays traced by occlusion queries are recorded
+/// without a hit.
+#[cfg(feature = "streams")]
+pub struct RayCapture {
+    every: usize,
+    count: AtomicUsize,
+    captured: Mutex<Vec<(Ray, Hit)>>,
+}
+
+#[cfg(feature = "streams")]
+impl RayCapture {
+    /// Create a capture recording every `n`th ray, starting from the first.
+    ///
+    /// Panics if `n` is 0.
+    pub fn every(n: usize) -> RayCapture {
+        assert!(n > 0, "Can't capture every 0th ray");
+        RayCapture {
+            every: n,
+            count: AtomicUsize::new(0),
+            captured: Mutex::new(Vec::new()),
+        }
+    }
+    /// Get the number of rays captured
+    pub fn len(&self) -> usize {
+        self.captured.lock().unwrap().len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// Take the rays captured so far and their hits
+    pub fn take(&self) -> (Vec<Ray>, Vec<Hit>) {
+        let captured = std::mem::take(&mut *self.captured.lock().unwrap());
+        captured.into_iter().unzip()
+    }
+    /// Write the rays captured so far to the file, see
+    /// `dump_rays_as_lines`
+    pub fn dump<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let (rays, hits): (Vec<Ray>, Vec<Hit>) =
+            self.captured.lock().unwrap().iter().cloned().unzip();
+        dump_rays_as_lines(&rays, &hits, path)
+    }
+    /// Record the sampled rays of a stream of `n` rays, calling `get` for
+    /// the ray and hit at each index sampled
+    fn record<F: Fn(usize) -> (Ray, Hit)>(&self, n: usize, get: F) {
+        let base = self.count.fetch_add(n, Ordering::Relaxed);
+        let first = (self.every - base % self.every) % self.every;
+        if first >= n {
+            return;
+        }
+        let mut captured = self.captured.lock().unwrap();
+        for i in (first..n).step_by(self.every) {
+            captured.push(get(i));
+        }
+    }
+    pub(crate) fn record_aos(&self, rays: &[RayHit]) {
+        self.record(rays.len(), |i| (rays[i].ray, rays[i].hit));
+    }
+    pub(crate) fn record_aos_occluded(&self, rays: &[Ray]) {
+        self.record(rays.len(), |i| (rays[i], Hit::new()));
+    }
+    pub(crate) fn record_soa(&self, rays: &RayHitN) {
+        self.record(rays.len(), |i| {
+            (soa_ray(&rays.ray, i), soa_hit(&rays.hit, i))
+        });
+    }
+    pub(crate) fn record_soa_occluded(&self, rays: &RayN) {
+        self.record(rays.len(), |i| (soa_ray(rays, i), Hit::new()));
+    }
+}
+
+#[cfg(feature = "streams")]
+fn soa_ray(rays: &RayN, i: usize) -> Ray {
+    let mut ray = Ray::segment(rays.org(i), rays.dir(i), rays.tnear(i), rays.tfar(i));
+    ray.time = rays.time(i);
+    ray.mask = rays.mask(i);
+    ray.id = rays.id(i);
+    ray.flags = rays.flags(i);
+    ray
+}
+
+#[cfg(feature = "streams")]
+fn soa_hit(hits: &HitN, i: usize) -> Hit {
+    let mut hit = Hit::new();
+    let n = hits.normal(i);
+    hit.Ng_x = n.x;
+    hit.Ng_y = n.y;
+    hit.Ng_z = n.z;
+    let (u, v) = hits.uv(i);
+    hit.u = u;
+    hit.v = v;
+    hit.primID = hits.prim_id(i);
+    hit.geomID = hits.geom_id(i);
+    hit.instID[0] = hits.inst_id(i);
+    hit
+}
+
+#[test]
+fn test_write_rays_as_lines() {
+    let mut hit_ray = Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 1.0));
+    hit_ray.tfar = 2.0;
+    let mut hit = Hit::new();
+    hit.geomID = 0;
+    let miss_ray = Ray::new(Vector3::new(1.0, 0.0, 0.0), Vector3::new(0.0, 2.0, 0.0));
+    let mut occluded_ray = Ray::new(Vector3::new(0.0, 1.0, 0.0), Vector3::new(1.0, 0.0, 0.0));
+    occluded_ray.tfar = f32::NEG_INFINITY;
+    let rays = [hit_ray, miss_ray, occluded_ray];
+    let hits = [hit, Hit::new(), Hit::new()];
+
+    let mut obj = Vec::new();
+    write_rays_as_lines(&rays, &hits, 1.0, LineFormat::Obj, &mut obj).unwrap();
+    let obj = String::from_utf8(obj).unwrap();
+    let expected = "# 3 rays\n\
+                    v 0 0 0\nv 0 0 2\n\
+                    v 1 0 0\nv 1 2 0\n\
+                    v 0 1 0\nv 1 1 0\n\
+                    g hits\nl 1 2\n\
+                    g occluded\nl 5 6\n\
+                    g misses\nl 3 4\n";
+    assert_eq!(obj, expected);
+
+    let mut ply = Vec::new();
+    write_rays_as_lines(&rays, &[], 0.5, LineFormat::Ply, &mut ply).unwrap();
+    let ply = String::from_utf8(ply).unwrap();
+    let body: Vec<&str> = ply.split("end_header\n").nth(1).unwrap().lines().collect();
+    // Without hits, the first ray is drawn to its tfar as a miss
+    assert_eq!(body[1], "0 0 2 255 0 0");
+    assert_eq!(body[3], "1 1 0 255 0 0");
+    assert_eq!(body[5], "0.5 1 0 255 255 0");
+    assert_eq!(&body[6..], &["0 1", "2 3", "4 5"]);
+
+    assert_eq!(LineFormat::from_path("rays.PLY"), LineFormat::Ply);
+    assert_eq!(LineFormat::from_path("rays.obj"), LineFormat::Obj);
+}
+
+#[test]
+#[cfg(feature = "streams")]
+fn test_ray_capture_sampling() {
+    let capture = RayCapture::every(3);
+    let rays: Vec<Ray> = (0..5)
+        .map(|i| {
+            let mut r = Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 1.0));
+            r.id = i;
+            r
+        })
+        .collect();
+    // The count carries over between streams
+    capture.record_aos_occluded(&rays);
+    capture.record_aos_occluded(&rays);
+    let (captured, hits) = capture.take();
+    let ids: Vec<u32> = captured.iter().map(|r| r.id).collect();
+    assert_eq!(ids, vec![0, 3, 1, 4]);
+    assert!(hits.iter().all(|h| !h.hit()));
+    assert!(capture.is_empty());
+}
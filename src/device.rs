@@ -1,44 +1,536 @@
-#[cfg(x86_64)]
-use std::arch::x86_64;
+use std::any::Any;
 use std::ffi::CString;
+use std::fmt::Write;
+use std::os::raw;
+use std::panic::{self, AssertUnwindSafe};
 use std::ptr;
+use std::sync::atomic::{AtomicIsize, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
 
+use leak_check::{self, ObjectKind};
 use sys::*;
+use DeviceProperty;
+
+/// The SIMD instruction sets Embree can select between for its kernels
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Isa {
+    Neon,
+    Sse2,
+    Sse42,
+    Avx,
+    Avx2,
+    Avx512,
+}
+
+impl Isa {
+    /// The name of the ISA used in Embree's configuration string
+    pub fn config_name(&self) -> &'static str {
+        match *self {
+            Isa::Neon => "neon",
+            Isa::Sse2 => "sse2",
+            Isa::Sse42 => "sse4.2",
+            Isa::Avx => "avx",
+            Isa::Avx2 => "avx2",
+            Isa::Avx512 => "avx512",
+        }
+    }
+    /// Detect the best ISA supported by the host CPU
+    pub fn host() -> Isa {
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+        {
+            if is_x86_feature_detected!("avx512f")
+                && is_x86_feature_detected!("avx512dq")
+                && is_x86_feature_detected!("avx512cd")
+                && is_x86_feature_detected!("avx512bw")
+                && is_x86_feature_detected!("avx512vl")
+            {
+                Isa::Avx512
+            } else if is_x86_feature_detected!("avx2") {
+                Isa::Avx2
+            } else if is_x86_feature_detected!("avx") {
+                Isa::Avx
+            } else if is_x86_feature_detected!("sse4.2") {
+                Isa::Sse42
+            } else {
+                Isa::Sse2
+            }
+        }
+        #[cfg(not(any(target_arch = "x86", target_arch = "x86_64")))]
+        {
+            Isa::Neon
+        }
+    }
+}
+
+/// The CPU frequency level Embree should try to keep the application at.
+/// Some CPUs reduce their clock rate when executing wide AVX or AVX512
+/// instructions, setting a lower level makes Embree avoid kernels which
+/// would trigger the downclocking, at the cost of narrower SIMD.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum FrequencyLevel {
+    /// Run at the highest frequency, only using 128-bit wide SIMD
+    Simd128,
+    /// Allow the AVX2 frequency level
+    Simd256,
+    /// Allow the heavy AVX512 frequency level
+    Simd512,
+}
+
+impl FrequencyLevel {
+    pub fn config_name(&self) -> &'static str {
+        match *self {
+            FrequencyLevel::Simd128 => "simd128",
+            FrequencyLevel::Simd256 => "simd256",
+            FrequencyLevel::Simd512 => "simd512",
+        }
+    }
+    /// The widest ISA which can be used without dropping below this level
+    fn max_isa(&self) -> Isa {
+        match *self {
+            FrequencyLevel::Simd128 => Isa::Sse42,
+            FrequencyLevel::Simd256 => Isa::Avx2,
+            FrequencyLevel::Simd512 => Isa::Avx512,
+        }
+    }
+}
+
+/// Typed configuration for creating a `Device`, which is translated into
+/// the configuration string passed to `rtcNewDevice`. Options left unset
+/// use Embree's defaults.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DeviceConfig {
+    threads: Option<u32>,
+    set_affinity: Option<bool>,
+    start_threads: Option<bool>,
+    isa: Option<Isa>,
+    max_isa: Option<Isa>,
+    frequency_level: Option<FrequencyLevel>,
+    hugepages: Option<bool>,
+    max_spatial_split_replications: Option<f32>,
+    verbose: Option<u32>,
+}
+
+impl DeviceConfig {
+    pub fn new() -> DeviceConfig {
+        DeviceConfig::default()
+    }
+    /// Set the number of build threads Embree should use
+    pub fn threads(mut self, threads: u32) -> DeviceConfig {
+        self.threads = Some(threads);
+        self
+    }
+    /// Pin Embree's build threads to hardware threads
+    pub fn set_affinity(mut self, affinity: bool) -> DeviceConfig {
+        self.set_affinity = Some(affinity);
+        self
+    }
+    /// Start Embree's build threads when the device is created instead
+    /// of at the first commit
+    pub fn start_threads(mut self, start: bool) -> DeviceConfig {
+        self.start_threads = Some(start);
+        self
+    }
+    /// Force Embree to use the ISA passed
+    pub fn isa(mut self, isa: Isa) -> DeviceConfig {
+        self.isa = Some(isa);
+        self
+    }
+    /// Restrict Embree to ISAs no wider than the one passed
+    pub fn max_isa(mut self, isa: Isa) -> DeviceConfig {
+        self.max_isa = Some(isa);
+        self
+    }
+    /// Set the CPU frequency level Embree should try to stay at
+    pub fn frequency_level(mut self, level: FrequencyLevel) -> DeviceConfig {
+        self.frequency_level = Some(level);
+        self
+    }
+    /// Enable or disable the use of huge pages for allocations
+    pub fn hugepages(mut self, enabled: bool) -> DeviceConfig {
+        self.hugepages = Some(enabled);
+        self
+    }
+    /// Set how much the number of primitive references may grow by in
+    /// spatial split builds, relative to the number of primitives, e.g.
+    /// 1.2 allows 20% more references. Embree uses spatial splits for
+    /// triangle and quad geometry in scenes built with
+    /// `BuildQuality::HIGH`. Higher values give better BVHs for scenes
+    /// with long or thin primitives, at the cost of memory and build time.
+    pub fn max_spatial_split_replications(mut self, replications: f32) -> DeviceConfig {
+        self.max_spatial_split_replications = Some(replications);
+        self
+    }
+    /// Set Embree's verbosity level for diagnostic output
+    pub fn verbose(mut self, level: u32) -> DeviceConfig {
+        self.verbose = Some(level);
+        self
+    }
+    /// Get the configuration string passed to Embree for these settings
+    pub fn to_config_string(&self) -> String {
+        let mut cfg = String::new();
+        let mut push = |key: &str, val: &dyn std::fmt::Display| {
+            if !cfg.is_empty() {
+                cfg.push(',');
+            }
+            write!(cfg, "{}={}", key, val).unwrap();
+        };
+        if let Some(t) = self.threads {
+            push("threads", &t);
+        }
+        if let Some(a) = self.set_affinity {
+            push("set_affinity", &(a as u32));
+        }
+        if let Some(s) = self.start_threads {
+            push("start_threads", &(s as u32));
+        }
+        if let Some(isa) = self.isa {
+            push("isa", &isa.config_name());
+        }
+        if let Some(isa) = self.max_isa {
+            push("max_isa", &isa.config_name());
+        }
+        if let Some(f) = self.frequency_level {
+            push("frequency_level", &f.config_name());
+        }
+        if let Some(h) = self.hugepages {
+            push("hugepages", &(h as u32));
+        }
+        if let Some(r) = self.max_spatial_split_replications {
+            push("max_spatial_split_replications", &r);
+        }
+        if let Some(v) = self.verbose {
+            push("verbose", &v);
+        }
+        cfg
+    }
+}
+
+/// Closure called by Embree before and after it allocates or frees memory
+pub type MemoryMonitorFunction = dyn Fn(isize, bool) -> bool + Send + Sync;
+
+/// Tracks the memory Embree allocates on a device and refuses allocations
+/// which would take it past a limit, see `Device::set_memory_budget`
+#[derive(Debug)]
+pub struct MemoryBudget {
+    limit: usize,
+    used: AtomicIsize,
+    peak: AtomicIsize,
+    refused: AtomicUsize,
+}
+
+impl MemoryBudget {
+    pub fn new(limit: usize) -> MemoryBudget {
+        MemoryBudget {
+            limit,
+            used: AtomicIsize::new(0),
+            peak: AtomicIsize::new(0),
+            refused: AtomicUsize::new(0),
+        }
+    }
+    /// Get the most bytes Embree may have allocated at once
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+    /// Get the bytes Embree has allocated and not yet freed
+    pub fn used(&self) -> usize {
+        self.used.load(Ordering::Relaxed).max(0) as usize
+    }
+    /// Get the most bytes Embree had allocated at once
+    pub fn peak(&self) -> usize {
+        self.peak.load(Ordering::Relaxed).max(0) as usize
+    }
+    /// Get the number of allocations refused for exceeding the limit
+    pub fn refused(&self) -> usize {
+        self.refused.load(Ordering::Relaxed)
+    }
+    /// Account for the change in memory reported to the memory monitor,
+    /// returning false to refuse an allocation exceeding the limit. Frees
+    /// and allocations reported after the fact (`post`) are always
+    /// counted, as they can't be refused.
+    fn update(&self, bytes: isize, post: bool) -> bool {
+        let used = self.used.fetch_add(bytes, Ordering::Relaxed) + bytes;
+        if bytes > 0 && !post && used > self.limit as isize {
+            self.used.fetch_sub(bytes, Ordering::Relaxed);
+            self.refused.fetch_add(1, Ordering::Relaxed);
+            return false;
+        }
+        self.peak.fetch_max(used, Ordering::Relaxed);
+        true
+    }
+}
 
 pub struct Device {
     pub(crate) handle: RTCDevice,
+    config: DeviceConfig,
+    memory_monitor: Option<Box<MemoryMonitor>>,
+}
+
+/// The memory monitor closure set on a device and the first panic it
+/// raised, which is held as unwinding into Embree would abort
+struct MemoryMonitor {
+    monitor: Box<MemoryMonitorFunction>,
+    panic: Mutex<Option<Box<dyn Any + Send>>>,
+}
+
+/// Call the Rust closure passed through the user pointer with the
+/// allocation, refusing it if the closure panics
+unsafe extern "C" fn memory_monitor(ptr: *mut raw::c_void, bytes: ssize_t, post: bool) -> bool {
+    let state = &*(ptr as *const MemoryMonitor);
+    match panic::catch_unwind(AssertUnwindSafe(|| (state.monitor)(bytes, post))) {
+        Ok(allow) => allow,
+        Err(p) => {
+            let mut held = state.panic.lock().unwrap_or_else(|e| e.into_inner());
+            if held.is_none() {
+                *held = Some(p);
+            }
+            false
+        }
+    }
+}
+
+/// Make the calling thread flush denormal floats to zero, which Embree
+/// recommends as denormals are very slow to compute with. On x86 this sets
+/// the flush to zero and denormals are zero modes of the MXCSR register,
+/// on ARM the flush to zero bit of the FPCR register, which covers both
+/// denormal inputs and results. The mode is per thread, Embree sets it on
+/// its own worker threads, and this is called on the thread creating a
+/// `Device`. Applications tracing rays from their own threads, e.g. with a
+/// thread pool, should call it on each of them.
+#[allow(deprecated)]
+pub fn set_flush_denormals_to_zero() {
+    #[cfg(target_arch = "x86")]
+    use std::arch::x86 as arch;
+    #[cfg(target_arch = "x86_64")]
+    use std::arch::x86_64 as arch;
+
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    unsafe {
+        // The flush to zero (bit 15) and denormals are zero (bit 6) flags
+        arch::_mm_setcsr(arch::_mm_getcsr() | 0x8040);
+    }
+    #[cfg(target_arch = "aarch64")]
+    unsafe {
+        let fpcr: u64;
+        std::arch::asm!("mrs {}, fpcr", out(reg) fpcr);
+        std::arch::asm!("msr fpcr, {}", in(reg) fpcr | (1 << 24));
+    }
 }
 
 impl Device {
     pub fn new() -> Device {
+        Device::with_config(&DeviceConfig::default())
+    }
+    pub fn debug() -> Device {
+        Device::with_config(&DeviceConfig::new().verbose(4))
+    }
+    /// Create a device using the typed configuration passed
+    pub fn with_config(config: &DeviceConfig) -> Device {
         // Set the flush zero and denormals modes from Embrees's perf. recommendations
         // https://embree.github.io/api.html#performance-recommendations
-        // Though, in Rust I think we just call the below function to do both
-        #[cfg(x86_64)]
-        unsafe {
-            x86_64::_MM_SET_FLUSH_ZERO_MODE(x86_64::_MM_FLUSH_ZERO_ON);
-        }
+        set_flush_denormals_to_zero();
 
+        let cfg = config.to_config_string();
+        let handle = if cfg.is_empty() {
+            unsafe { rtcNewDevice(ptr::null()) }
+        } else {
+            let cfg = CString::new(cfg).unwrap();
+            unsafe { rtcNewDevice(cfg.as_ptr()) }
+        };
+        leak_check::created(ObjectKind::Device);
         Device {
-            handle: unsafe { rtcNewDevice(ptr::null()) },
+            handle,
+            config: config.clone(),
+            memory_monitor: None,
         }
     }
-    pub fn debug() -> Device {
-        let cfg = CString::new("verbose=4").unwrap();
-        Device {
-            handle: unsafe { rtcNewDevice(cfg.as_ptr()) },
+    /// Get the configuration the device was created with
+    pub fn config(&self) -> &DeviceConfig {
+        &self.config
+    }
+    /// Query a property of the device, e.g. whether Embree was built with
+    /// support for a feature such as `DeviceProperty::RAY_MASK_SUPPORTED`,
+    /// which is non-zero if supported
+    pub fn property(&self, prop: DeviceProperty) -> isize {
+        unsafe { rtcGetDeviceProperty(self.handle, prop) }
+    }
+    /// Whether Embree was built with native support for ray packets of the
+    /// width, one of 1, 4, 8 or 16. Packet queries of a width without
+    /// native support are emulated with narrower packets, so filter
+    /// functions may be passed packets narrower than the query.
+    pub fn supports_packet_width(&self, width: u32) -> bool {
+        let prop = match width {
+            1 => return true,
+            4 => DeviceProperty::NATIVE_RAY4_SUPPORTED,
+            8 => DeviceProperty::NATIVE_RAY8_SUPPORTED,
+            16 => DeviceProperty::NATIVE_RAY16_SUPPORTED,
+            _ => return false,
+        };
+        self.property(prop) != 0
+    }
+    /// Whether Embree was built with `EMBREE_RAY_MASK` enabled, without it
+    /// ray and geometry masks are ignored and all rays intersect all
+    /// geometry
+    pub fn supports_ray_masks(&self) -> bool {
+        self.property(DeviceProperty::RAY_MASK_SUPPORTED) != 0
+    }
+    /// Estimate which ISA Embree selects for its kernels on this device.
+    ///
+    /// This is an estimate and not a report of Embree's choice, which it
+    /// doesn't expose through the API. It's derived from the same inputs
+    /// Embree uses: the ISA supported by the host CPU, capped by the `isa`,
+    /// `max_isa` and `frequency_level` settings of the device
+    /// configuration. When no frequency level is set Embree only uses
+    /// AVX512 on CPUs which don't downclock significantly, which is assumed
+    /// to not be the case here. Embree picks a lower ISA than estimated if
+    /// the library wasn't compiled with support for this one.
+    pub fn expected_isa(&self) -> Isa {
+        let host = Isa::host();
+        if host == Isa::Neon {
+            return host;
+        }
+        let mut isa = match self.config.isa {
+            Some(isa) => isa.min(host),
+            None => host,
+        };
+        if let Some(max) = self.config.max_isa {
+            isa = isa.min(max);
+        }
+        let level = self
+            .config
+            .frequency_level
+            .unwrap_or(FrequencyLevel::Simd256);
+        isa.min(level.max_isa())
+    }
+    /// Set a closure to monitor the memory Embree allocates and frees on
+    /// the device, e.g. to track the memory used by BVHs and buffers. The
+    /// closure is passed the number of bytes, which is negative when memory
+    /// is freed, and whether the call is made after the operation has been
+    /// performed. Returning false before an allocation cancels it, causing
+    /// the operation needing the memory to fail with an out of memory
+    /// error. The closure may be called concurrently from Embree's threads.
+    ///
+    /// If the closure panics the allocation is refused, and the panic is
+    /// held for `take_memory_monitor_panic`, as it can't unwind through
+    /// Embree.
+    pub fn set_memory_monitor_function<F>(&mut self, monitor: F)
+    where
+        F: Fn(isize, bool) -> bool + Send + Sync + 'static,
+    {
+        let state = Box::new(MemoryMonitor {
+            monitor: Box::new(monitor),
+            panic: Mutex::new(None),
+        });
+        unsafe {
+            rtcSetDeviceMemoryMonitorFunction(
+                self.handle,
+                Some(memory_monitor),
+                &*state as *const MemoryMonitor as *mut raw::c_void,
+            );
         }
+        self.memory_monitor = Some(state);
+    }
+    /// Take the first panic raised by the memory monitor function since it
+    /// was set or the panic was last taken, e.g. to resume it with
+    /// `std::panic::resume_unwind` once the failed operation returns
+    pub fn take_memory_monitor_panic(&self) -> Option<Box<dyn Any + Send>> {
+        let state = self.memory_monitor.as_ref()?;
+        let mut held = state.panic.lock().unwrap_or_else(|e| e.into_inner());
+        held.take()
+    }
+    /// Remove the memory monitor function set on the device, if any
+    pub fn clear_memory_monitor_function(&mut self) {
+        unsafe {
+            rtcSetDeviceMemoryMonitorFunction(self.handle, None, ptr::null_mut());
+        }
+        self.memory_monitor = None;
+    }
+    /// Cap the memory Embree allocates on the device at `limit` bytes,
+    /// replacing any memory monitor function set. Allocations past the
+    /// limit are refused, so a commit needing more memory fails with an
+    /// out of memory error instead of exhausting the host's memory, e.g.
+    /// in a 32-bit process. The returned budget tracks the memory used and
+    /// counts the refused allocations, so failed builds can be detected.
+    /// Only memory allocated after the budget is set is counted, and
+    /// allocations Embree reports after making them can't be refused.
+    pub fn set_memory_budget(&mut self, limit: usize) -> Arc<MemoryBudget> {
+        let budget = Arc::new(MemoryBudget::new(limit));
+        let monitor = budget.clone();
+        self.set_memory_monitor_function(move |bytes, post| monitor.update(bytes, post));
+        budget
     }
-    // TODO: Setup the flush zero and denormals mode needed by Embree
-    // using the Rust SIMD when it's in core
 }
 
 impl Drop for Device {
     fn drop(&mut self) {
+        // The memory monitor is dropped after releasing the device, as
+        // Embree reports the memory freed while releasing it
         unsafe {
             rtcReleaseDevice(self.handle);
         }
+        leak_check::released(ObjectKind::Device);
     }
 }
 
 unsafe impl Sync for Device {}
+
+#[test]
+fn test_device_config_string() {
+    assert_eq!(DeviceConfig::new().to_config_string(), "");
+    let cfg = DeviceConfig::new()
+        .threads(8)
+        .max_isa(Isa::Avx2)
+        .frequency_level(FrequencyLevel::Simd128)
+        .verbose(1);
+    assert_eq!(
+        cfg.to_config_string(),
+        "threads=8,max_isa=avx2,frequency_level=simd128,verbose=1"
+    );
+    assert_eq!(
+        DeviceConfig::new()
+            .max_spatial_split_replications(1.5)
+            .to_config_string(),
+        "max_spatial_split_replications=1.5"
+    );
+}
+
+#[test]
+fn test_memory_budget() {
+    let budget = MemoryBudget::new(100);
+    assert!(budget.update(60, false));
+    assert!(!budget.update(50, false));
+    assert_eq!((budget.used(), budget.refused()), (60, 1));
+    assert!(budget.update(-40, true));
+    assert_eq!(budget.used(), 20);
+    assert!(budget.update(50, false));
+    assert_eq!((budget.used(), budget.peak()), (70, 70));
+    // Allocations reported after the fact can't be refused
+    assert!(budget.update(40, true));
+    assert_eq!(
+        (budget.used(), budget.peak(), budget.refused()),
+        (110, 110, 1)
+    );
+}
+
+#[test]
+fn test_memory_monitor_panic() {
+    let state = MemoryMonitor {
+        monitor: Box::new(|bytes, _| {
+            if bytes > 100 {
+                panic!("monitor panicked")
+            }
+            true
+        }),
+        panic: Mutex::new(None),
+    };
+    let ptr = &state as *const MemoryMonitor as *mut raw::c_void;
+    unsafe {
+        assert!(memory_monitor(ptr, 10, false));
+        assert!(!memory_monitor(ptr, 200, false));
+        assert!(!memory_monitor(ptr, 300, false));
+    }
+    let held = state.panic.lock().unwrap().take();
+    assert_eq!(
+        held.unwrap().downcast_ref::<&str>(),
+        Some(&"monitor panicked")
+    );
+}
@@ -0,0 +1,429 @@
+//! Intersection and occlusion filter functions set on a geometry, which
+//! are called for each candidate hit found on the geometry and can reject
+//! it to continue traversal, e.g. to implement alpha cutouts or to collect
+//! all the hits along a ray.
+//!
+//! # Thread Safety
+//!
+//! Embree calls filter functions from whichever threads are tracing rays
+//! against the scene, including concurrently when a `CommittedScene` is
+//! shared between threads. Filters must therefore be `Send + Sync`, and
+//! closures capturing data which isn't, like `Rc` or `RefCell`, are
+//! rejected at compile time:
+//!
+//! ```compile_fail
+//! # extern crate embree;
+//! # use std::rc::Rc;
+//! # use embree::{Device, Geometry, TriangleMesh};
+//! # let device = Device::new();
+//! let skipped = Rc::new(0);
+//! let mut geom = Geometry::Triangle(TriangleMesh::unanimated(&device, 1, 3));
+//! geom.set_intersect_filter_function(move |_, _| *skipped > 0);
+//! ```
+//!
+//! ```compile_fail
+//! # extern crate embree;
+//! # use std::cell::RefCell;
+//! # use embree::{Device, Geometry, TriangleMesh};
+//! # let device = Device::new();
+//! let hits = RefCell::new(Vec::new());
+//! let mut geom = Geometry::Triangle(TriangleMesh::unanimated(&device, 1, 3));
+//! geom.set_occluded_filter_function(|_, hit| {
+//!     hits.borrow_mut().push(hit.primID);
+//!     true
+//! });
+//! ```
+//!
+//! State shared between calls should use thread safe types instead, such
+//! as atomics or a `Mutex`. Output written for each ray, e.g. a list of
+//! the hits along it, should go in the ray's slot of a `PerRayOutput` to
+//! keep the output of different rays separate:
+//!
+//! ```no_run
+//! # extern crate embree;
+//! # use std::sync::atomic::{AtomicUsize, Ordering};
+//! # use embree::{Device, Geometry, TriangleMesh};
+//! # let device = Device::new();
+//! let calls = AtomicUsize::new(0);
+//! let mut geom = Geometry::Triangle(TriangleMesh::unanimated(&device, 1, 3));
+//! geom.set_intersect_filter_function(|_, _| {
+//!     calls.fetch_add(1, Ordering::Relaxed);
+//!     true
+//! });
+//! ```
+//!
+//! Filters written with SIMD intrinsics can instead be passed whole packets
+//! of a fixed width, see the `packet_filter` module.
+//!
+//! # Panics
+//!
+//! Unwinding into Embree would abort the process, so a panic in a filter
+//! is caught and held until the query which called it returns, then
+//! resumed on the thread which made the query. The hits the panicking
+//! filter was called with are rejected, and the remaining filter calls of
+//! the query are skipped, rejecting their hits too.
+//!
+//! # Raw Filter Functions
+//!
+//! The closure based filters unpack each ray and hit of the packet Embree
+//! passes before calling the closure. Hot filters which need to avoid this
+//! can instead be registered as an `extern "C"` function with
+//! `Geometry::set_intersect_filter_function_raw` and
+//! `Geometry::set_occluded_filter_function_raw`, which Embree calls directly
+//! with the packet. These are unsafe: the function must only access the
+//! `N` rays and hits of the packet and must follow the same thread safety
+//! rules as the closures, which the compiler can't check for it. The user
+//! data pointer passed when registering the filter is retrieved in the
+//! filter with `user_data`, as the geometry user pointer in the arguments
+//! refers to the wrapper's own data.
+//!
+//! ```no_run
+//! # extern crate embree;
+//! # use std::ptr;
+//! # use embree::{sys, Device, Geometry, TriangleMesh};
+//! /// Reject all hits on odd primitives
+//! unsafe extern "C" fn skip_odd(args: *const sys::RTCFilterFunctionNArguments) {
+//!     let args = &*args;
+//!     let n = args.N as usize;
+//!     let prim_ids = (args.hit as *const u32).add(5 * n);
+//!     for i in 0..n {
+//!         if *prim_ids.add(i) % 2 == 1 {
+//!             *args.valid.add(i) = 0;
+//!         }
+//!     }
+//! }
+//! # let device = Device::new();
+//! let mut geom = Geometry::Triangle(TriangleMesh::unanimated(&device, 2, 4));
+//! unsafe {
+//!     geom.set_intersect_filter_function_raw(Some(skip_odd), ptr::null_mut());
+//! }
+//! ```
+
+use std::any::Any;
+use std::cell::RefCell;
+use std::os::raw;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::sync::atomic::AtomicBool;
+use std::sync::Mutex;
+
+use budget::BudgetContext;
+use ray::{Hit, Ray};
+use sys;
+use user_geometry::ShapeSet;
+use BufferType;
+
+/// A filter function called with the ray and the candidate hit found for
+/// it, returning whether the hit should be accepted. Embree may call the
+/// filter concurrently from the threads tracing rays against the scene.
+pub type FilterFunction<'a> = dyn Fn(&Ray, &Hit) -> bool + Send + Sync + 'a;
+
+/// A width specific filter wrapped to be called with Embree's filter
+/// arguments for a packet of any width, see the `packet_filter` module
+pub(crate) type PacketDispatch<'a> = dyn Fn(&sys::RTCFilterFunctionNArguments) + Send + Sync + 'a;
+
+/// Which faces of a geometry rays can hit, see `Geometry::set_hit_face_mode`.
+/// The front face is the side the hit's geometric normal `Ng` points to,
+/// so a ray hits it when its direction is opposite the normal.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum HitFaceMode {
+    /// Hit both faces, the default
+    #[default]
+    Both,
+    /// Only hit front faces, e.g. to find where rays enter a closed mesh
+    /// whose normals face outwards
+    FrontOnly,
+    /// Only hit back faces, e.g. to find where rays exit a volume
+    BackOnly,
+}
+
+impl HitFaceMode {
+    /// Whether the mode accepts a hit with normal `ng` on a ray in
+    /// direction `dir`
+    pub fn accepts(&self, ng: [f32; 3], dir: [f32; 3]) -> bool {
+        let d = ng[0] * dir[0] + ng[1] * dir[1] + ng[2] * dir[2];
+        match *self {
+            HitFaceMode::Both => true,
+            HitFaceMode::FrontOnly => d < 0.0,
+            HitFaceMode::BackOnly => d >= 0.0,
+        }
+    }
+}
+
+/// Rust data attached to a geometry through Embree's geometry user pointer,
+/// owned by the `Geometry` and released when it's dropped.
+pub(crate) struct GeometryData<'a> {
+    pub intersect_filter: Option<Box<FilterFunction<'a>>>,
+    pub occluded_filter: Option<Box<FilterFunction<'a>>>,
+    /// Width specific filters, see the `packet_filter` module
+    pub intersect_packet_filter: Option<Box<PacketDispatch<'a>>>,
+    pub occluded_packet_filter: Option<Box<PacketDispatch<'a>>>,
+    /// The user data passed when registering a raw filter function
+    pub raw_user_data: *mut raw::c_void,
+    /// Whether raw filter functions are set for intersection and occlusion
+    /// queries, which can't be combined with culling faces
+    pub raw_intersect_filter: bool,
+    pub raw_occluded_filter: bool,
+    /// The faces of the geometry rays can hit, applied before the filters
+    pub face_mode: HitFaceMode,
+    /// The shapes of a user geometry, see the `user_geometry` module
+    pub user_shapes: Option<Box<dyn ShapeSet + 'a>>,
+    /// Whether the geometry has changed since it was last committed
+    pub dirty: AtomicBool,
+    /// Whether the geometry is enabled, Embree doesn't provide a getter
+    pub enabled: bool,
+    /// The mask of the geometry, Embree doesn't provide a getter
+    pub mask: u32,
+    /// The number of elements of the shared data bound to each buffer
+    /// type and slot in place of the wrapper's buffers, as Embree doesn't
+    /// provide a getter
+    pub shared_counts: Vec<(BufferType, u32, usize)>,
+    /// A panic in a callback Embree made while building a scene holding
+    /// the geometry, e.g. the bounds of a user geometry's primitive, held
+    /// until the commit returns
+    pub build_panic: Mutex<Option<Box<dyn Any + Send>>>,
+}
+
+impl<'a> Default for GeometryData<'a> {
+    fn default() -> GeometryData<'a> {
+        GeometryData {
+            intersect_filter: None,
+            occluded_filter: None,
+            intersect_packet_filter: None,
+            occluded_packet_filter: None,
+            raw_user_data: ptr::null_mut(),
+            raw_intersect_filter: false,
+            raw_occluded_filter: false,
+            face_mode: HitFaceMode::Both,
+            user_shapes: None,
+            dirty: AtomicBool::new(true),
+            enabled: true,
+            mask: u32::MAX,
+            shared_counts: Vec::new(),
+            build_panic: Mutex::new(None),
+        }
+    }
+}
+
+thread_local! {
+    /// A panic in a callback Embree made during a query on this thread,
+    /// held until the query returns as unwinding into Embree would abort
+    static HELD_PANIC: RefCell<Option<Box<dyn Any + Send>>> = const { RefCell::new(None) };
+}
+
+/// Run a callback Embree makes during a query on this thread, e.g. a
+/// filter function, catching a panic in it to be resumed by
+/// `resume_held_panic` after the query returns. Returns `None` if the
+/// callback panicked, or isn't run as a panic is already held.
+pub(crate) fn catch_panic<R, F: FnOnce() -> R>(f: F) -> Option<R> {
+    if HELD_PANIC.with(|p| p.borrow().is_some()) {
+        return None;
+    }
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(r) => Some(r),
+        Err(p) => {
+            HELD_PANIC.with(|held| *held.borrow_mut() = Some(p));
+            None
+        }
+    }
+}
+
+/// Resume a panic caught by `catch_panic` on this thread, called after
+/// each query Embree may make callbacks during
+pub(crate) fn resume_held_panic() {
+    if let Some(p) = HELD_PANIC.with(|p| p.borrow_mut().take()) {
+        panic::resume_unwind(p);
+    }
+}
+
+/// Get the user data pointer passed when registering the raw filter
+/// function being called with `args`. The intersection and occlusion raw
+/// filters of a geometry share the same user data, which is the pointer
+/// passed by the most recent registration.
+///
+/// # Safety
+/// `args` must be the arguments Embree passed to a raw filter function
+/// registered through `Geometry`.
+pub unsafe fn user_data(args: *const sys::RTCFilterFunctionNArguments) -> *mut raw::c_void {
+    (*((*args).geometryUserPtr as *const GeometryData)).raw_user_data
+}
+
+/// Read the i'th ray of the N wide SoA ray packet
+pub(crate) unsafe fn ray_n(ray: *const sys::RTCRayN, n: usize, i: usize) -> Ray {
+    let f = ray as *const f32;
+    let u = ray as *const u32;
+    sys::RTCRay {
+        org_x: *f.add(i),
+        org_y: *f.add(n + i),
+        org_z: *f.add(2 * n + i),
+        tnear: *f.add(3 * n + i),
+        dir_x: *f.add(4 * n + i),
+        dir_y: *f.add(5 * n + i),
+        dir_z: *f.add(6 * n + i),
+        time: *f.add(7 * n + i),
+        tfar: *f.add(8 * n + i),
+        mask: *u.add(9 * n + i),
+        id: *u.add(10 * n + i),
+        flags: *u.add(11 * n + i),
+    }
+}
+
+/// Read the i'th hit of the N wide SoA hit packet
+pub(crate) unsafe fn hit_n(hit: *const sys::RTCHitN, n: usize, i: usize) -> Hit {
+    let f = hit as *const f32;
+    let u = hit as *const u32;
+    sys::RTCHit {
+        Ng_x: *f.add(i),
+        Ng_y: *f.add(n + i),
+        Ng_z: *f.add(2 * n + i),
+        u: *f.add(3 * n + i),
+        v: *f.add(4 * n + i),
+        primID: *u.add(5 * n + i),
+        geomID: *u.add(6 * n + i),
+        instID: [*u.add(7 * n + i)],
+    }
+}
+
+/// Run the filter on each valid ray of the packet, marking the rays whose
+/// hit it rejects, or whose hit it panics on, as invalid
+unsafe fn run_filter(args: *const sys::RTCFilterFunctionNArguments, filter: &FilterFunction) {
+    let args = &*args;
+    let n = args.N as usize;
+    let budget = BudgetContext::from_context(args.context);
+    for i in 0..n {
+        let valid = args.valid.add(i);
+        if *valid == 0 {
+            continue;
+        }
+        // Once a budgeted query's budget is spent candidates are accepted
+        // without filtering them
+        if let Some(budget) = budget {
+            if !budget.charge() {
+                continue;
+            }
+        }
+        let ray = ray_n(args.ray, n, i);
+        let hit = hit_n(args.hit, n, i);
+        if catch_panic(|| filter(&ray, &hit)) != Some(true) {
+            *valid = 0;
+        }
+    }
+}
+
+/// Run the packet filter on the packet, rejecting all of its hits if the
+/// filter panics
+unsafe fn run_packet_filter(
+    args: *const sys::RTCFilterFunctionNArguments,
+    filter: &PacketDispatch,
+) {
+    let args = &*args;
+    if catch_panic(|| filter(args)).is_none() {
+        for i in 0..args.N as usize {
+            *args.valid.add(i) = 0;
+        }
+    }
+}
+
+/// Mark the hits on the faces the mode rejects as invalid
+unsafe fn cull_faces(args: *const sys::RTCFilterFunctionNArguments, mode: HitFaceMode) {
+    if mode == HitFaceMode::Both {
+        return;
+    }
+    let args = &*args;
+    let n = args.N as usize;
+    for i in 0..n {
+        let valid = args.valid.add(i);
+        if *valid == 0 {
+            continue;
+        }
+        let ray = ray_n(args.ray, n, i);
+        let hit = hit_n(args.hit, n, i);
+        let ng = [hit.Ng_x, hit.Ng_y, hit.Ng_z];
+        if !mode.accepts(ng, [ray.dir_x, ray.dir_y, ray.dir_z]) {
+            *valid = 0;
+        }
+    }
+}
+
+pub(crate) unsafe extern "C" fn intersect_filter(args: *const sys::RTCFilterFunctionNArguments) {
+    let data = &*((*args).geometryUserPtr as *const GeometryData);
+    cull_faces(args, data.face_mode);
+    if let Some(ref filter) = data.intersect_filter {
+        run_filter(args, filter.as_ref());
+    } else if let Some(ref filter) = data.intersect_packet_filter {
+        run_packet_filter(args, filter.as_ref());
+    }
+}
+
+pub(crate) unsafe extern "C" fn occluded_filter(args: *const sys::RTCFilterFunctionNArguments) {
+    let data = &*((*args).geometryUserPtr as *const GeometryData);
+    cull_faces(args, data.face_mode);
+    if let Some(ref filter) = data.occluded_filter {
+        run_filter(args, filter.as_ref());
+    } else if let Some(ref filter) = data.occluded_packet_filter {
+        run_packet_filter(args, filter.as_ref());
+    }
+}
+
+#[test]
+fn test_cull_faces() {
+    // A packet of 3 rays heading down -z with the third invalid, hitting
+    // faces whose normals point up, down and up
+    let n = 3;
+    let mut ray = vec![0.0f32; 12 * n];
+    ray[6 * n..7 * n].copy_from_slice(&[-1.0, -1.0, -1.0]);
+    let mut hit = vec![0.0f32; 8 * n];
+    hit[2 * n..3 * n].copy_from_slice(&[1.0, -1.0, 1.0]);
+    let cull = |mode| {
+        let mut valid = [-1, -1, 0];
+        let args = sys::RTCFilterFunctionNArguments {
+            valid: valid.as_mut_ptr(),
+            geometryUserPtr: ptr::null_mut(),
+            context: ptr::null_mut(),
+            ray: ray.as_ptr() as *mut sys::RTCRayN,
+            hit: hit.as_ptr() as *mut sys::RTCHitN,
+            N: n as u32,
+        };
+        unsafe {
+            cull_faces(&args, mode);
+        }
+        valid
+    };
+    assert_eq!(cull(HitFaceMode::Both), [-1, -1, 0]);
+    assert_eq!(cull(HitFaceMode::FrontOnly), [-1, 0, 0]);
+    assert_eq!(cull(HitFaceMode::BackOnly), [0, -1, 0]);
+}
+
+#[test]
+fn test_filter_panic_is_held() {
+    use ray::IntersectContext;
+    // A packet of 3 valid rays, where the filter panics on the second
+    let n = 3;
+    let ray = vec![0.0f32; 12 * n];
+    let mut hit = vec![0u32; 8 * n];
+    hit[5 * n..6 * n].copy_from_slice(&[0, 1, 2]);
+    let mut valid = [-1, -1, -1];
+    let mut ctx = IntersectContext::incoherent();
+    let args = sys::RTCFilterFunctionNArguments {
+        valid: valid.as_mut_ptr(),
+        geometryUserPtr: ptr::null_mut(),
+        context: &mut ctx,
+        ray: ray.as_ptr() as *mut sys::RTCRayN,
+        hit: hit.as_ptr() as *mut sys::RTCHitN,
+        N: n as u32,
+    };
+    let filter = |_: &Ray, hit: &Hit| {
+        assert!(hit.primID != 1, "filter panicked");
+        true
+    };
+    unsafe {
+        run_filter(&args, &filter);
+    }
+    // The hit the filter panicked on and those after it are rejected
+    assert_eq!(valid, [-1, 0, 0]);
+    let p = panic::catch_unwind(resume_held_panic).unwrap_err();
+    assert_eq!(p.downcast_ref::<&str>(), Some(&"filter panicked"));
+    // The panic was taken, so the next query runs its filters again
+    assert!(catch_panic(|| ()).is_some());
+    resume_held_panic();
+}
@@ -0,0 +1,203 @@
+//! Monte Carlo estimation of form factors (view factors) between two
+//! surfaces in a scene, e.g. for thermal or radiosity style radiation
+//! exchange computations. Points are sampled uniformly over the area of
+//! each surface and the visibility between them is resolved with batches
+//! of occlusion rays.
+
+use std::f32;
+
+use cgmath::{InnerSpace, Vector3};
+
+use geometry::Geometry;
+use ray::{IntersectContext, Ray};
+use scene::CommittedScene;
+use testing::Pcg32;
+
+/// Number of visibility rays traced together in a single stream
+const BATCH_SIZE: usize = 1024;
+
+/// Result of a form factor estimate
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct FormFactorEstimate {
+    /// The estimated form factor from the source to the target surface
+    pub value: f32,
+    /// The variance of the estimate
+    pub variance: f32,
+    /// The number of point pairs sampled
+    pub samples: usize,
+}
+
+impl FormFactorEstimate {
+    /// The standard error of the estimate
+    pub fn std_error(&self) -> f32 {
+        self.variance.sqrt()
+    }
+}
+
+/// A point sampled on a surface along with the surface normal
+#[derive(Debug, Copy, Clone)]
+struct SurfacePoint {
+    p: Vector3<f32>,
+    n: Vector3<f32>,
+}
+
+/// Area-weighted sampler over the triangles of a geometry. Quads are
+/// split into two triangles.
+struct SurfaceSampler {
+    triangles: Vec<[Vector3<f32>; 3]>,
+    /// Running sum of the triangle areas, used to pick a triangle
+    /// proportional to its area
+    cdf: Vec<f32>,
+    area: f32,
+}
+
+impl SurfaceSampler {
+    fn new(geom: &Geometry) -> Option<SurfaceSampler> {
+        let mut triangles = Vec::new();
+        match *geom {
+            Geometry::Triangle(ref m) => {
+                let verts = m.vertex_buffer.as_slice();
+                for t in m.index_buffer.as_slice().iter() {
+                    triangles.push([
+                        verts[t.x as usize].truncate(),
+                        verts[t.y as usize].truncate(),
+                        verts[t.z as usize].truncate(),
+                    ]);
+                }
+            }
+            Geometry::Quad(ref m) => {
+                let verts = m.vertex_buffer.as_slice();
+                for q in m.index_buffer.as_slice().iter() {
+                    let v = [
+                        verts[q.x as usize].truncate(),
+                        verts[q.y as usize].truncate(),
+                        verts[q.z as usize].truncate(),
+                        verts[q.w as usize].truncate(),
+                    ];
+                    triangles.push([v[0], v[1], v[3]]);
+                    triangles.push([v[2], v[3], v[1]]);
+                }
+            }
+            _ => return None,
+        }
+
+        let mut cdf = Vec::with_capacity(triangles.len());
+        let mut area = 0.0;
+        for t in triangles.iter() {
+            area += 0.5 * (t[1] - t[0]).cross(t[2] - t[0]).magnitude();
+            cdf.push(area);
+        }
+        if area > 0.0 {
+            Some(SurfaceSampler {
+                triangles,
+                cdf,
+                area,
+            })
+        } else {
+            None
+        }
+    }
+    /// Sample a point uniformly over the surface area using the three
+    /// random numbers in [0, 1) passed
+    fn sample(&self, u: f32, v: f32, w: f32) -> SurfacePoint {
+        let target = u * self.area;
+        let i = match self
+            .cdf
+            .binary_search_by(|c| c.partial_cmp(&target).unwrap())
+        {
+            Ok(i) => i,
+            Err(i) => i,
+        };
+        let t = &self.triangles[i.min(self.triangles.len() - 1)];
+        // Uniform barycentric coordinates via the square root warping
+        let su = v.sqrt();
+        let b0 = 1.0 - su;
+        let b1 = w * su;
+        let p = t[0] * b0 + t[1] * b1 + t[2] * (1.0 - b0 - b1);
+        let n = (t[1] - t[0]).cross(t[2] - t[0]).normalize();
+        SurfacePoint { p, n }
+    }
+}
+
+/// Estimate the form factor from the geometry `from` to the geometry `to`
+/// in the committed scene, i.e. the fraction of energy diffusely emitted
+/// by `from` which arrives at `to`. Both geometries must be triangle or
+/// quad meshes, and surfaces are treated as one-sided with the front face
+/// given by a counter-clockwise winding order.
+///
+/// `samples` pairs of points are drawn uniformly over the two surfaces and
+/// the visibility between them tested with occlusion rays traced in batches
+/// through the ray stream API. Returns `None` if either geometry is not in
+/// the scene, is not a mesh, or has zero surface area.
+pub fn estimate_form_factor(
+    scene: &CommittedScene,
+    from: u32,
+    to: u32,
+    samples: usize,
+    seed: u64,
+) -> Option<FormFactorEstimate> {
+    let src = SurfaceSampler::new(scene.scene.geometry_by_raw_id(from)?)?;
+    let dst = SurfaceSampler::new(scene.scene.geometry_by_raw_id(to)?)?;
+    if samples == 0 {
+        return None;
+    }
+
+    let mut rng = Pcg32::new(seed);
+    let mut ctx = IntersectContext::incoherent();
+    let mut rays = Vec::with_capacity(BATCH_SIZE);
+    let mut weights = Vec::with_capacity(BATCH_SIZE);
+    let mut sum = 0.0f64;
+    let mut sum_sqr = 0.0f64;
+
+    let mut remaining = samples;
+    while remaining > 0 {
+        let batch = remaining.min(BATCH_SIZE);
+        rays.clear();
+        weights.clear();
+        for _ in 0..batch {
+            let x = src.sample(rng.next_f32(), rng.next_f32(), rng.next_f32());
+            let y = dst.sample(rng.next_f32(), rng.next_f32(), rng.next_f32());
+            let d = y.p - x.p;
+            let dist2 = d.magnitude2();
+            let w = d / dist2.sqrt();
+            let cos_x = x.n.dot(w);
+            let cos_y = -y.n.dot(w);
+            let g = if dist2 > 0.0 && cos_x > 0.0 && cos_y > 0.0 {
+                cos_x * cos_y / (f32::consts::PI * dist2)
+            } else {
+                0.0
+            };
+            // The ray spans the segment between the points, with the
+            // end points trimmed to avoid hitting the surfaces sampled
+            rays.push(Ray::segment(x.p, d, 1e-4, 1.0 - 1e-4));
+            weights.push(g * dst.area);
+        }
+        #[cfg(feature = "streams")]
+        scene.occluded_stream_aos(&mut ctx, &mut rays);
+        #[cfg(not(feature = "streams"))]
+        for r in rays.iter_mut() {
+            scene.occluded(&mut ctx, r);
+        }
+
+        for (r, w) in rays.iter().zip(weights.iter()) {
+            // Occluded rays have their tfar set to -inf
+            let f = if r.tfar < 0.0 { 0.0 } else { *w as f64 };
+            sum += f;
+            sum_sqr += f * f;
+        }
+        remaining -= batch;
+    }
+
+    let n = samples as f64;
+    let mean = sum / n;
+    let sample_variance = if samples > 1 {
+        ((sum_sqr - n * mean * mean) / (n - 1.0)).max(0.0)
+    } else {
+        0.0
+    };
+    Some(FormFactorEstimate {
+        value: mean as f32,
+        variance: (sample_variance / n) as f32,
+        samples,
+    })
+}
@@ -0,0 +1,161 @@
+//! Lookups of the layout of Embree's buffer formats and the formats each
+//! geometry kind accepts for its buffers, for checking the layout of data
+//! before passing it to Embree, which otherwise only reports an invalid
+//! format as an error on the device when the buffer is set or the geometry
+//! committed. The formats accepted are those listed in the Embree
+//! documentation of each geometry type.
+
+use std::mem;
+
+use geometry::GeometryKind;
+use sys::RTCGrid;
+use {BufferType, Format};
+
+/// The formats of vertex attributes, which can have 1 to 16 floats
+const FLOAT_N: &[Format] = &[
+    Format::FLOAT,
+    Format::FLOAT2,
+    Format::FLOAT3,
+    Format::FLOAT4,
+    Format::FLOAT5,
+    Format::FLOAT6,
+    Format::FLOAT7,
+    Format::FLOAT8,
+    Format::FLOAT9,
+    Format::FLOAT10,
+    Format::FLOAT11,
+    Format::FLOAT12,
+    Format::FLOAT13,
+    Format::FLOAT14,
+    Format::FLOAT15,
+    Format::FLOAT16,
+];
+
+impl Format {
+    /// Get the size in bytes of an element in this format, returns `None`
+    /// for `UNDEFINED`
+    pub fn size_in_bytes(&self) -> Option<usize> {
+        if *self == Format::GRID {
+            return Some(mem::size_of::<RTCGrid>());
+        }
+        let scalar = match (*self as u32) >> 12 {
+            1 | 2 => 1,
+            3 | 4 => 2,
+            5 | 6 | 9 => 4,
+            7 | 8 => 8,
+            _ => return None,
+        };
+        self.component_count().map(|n| scalar * n)
+    }
+    #[deprecated(note = "renamed to size_in_bytes")]
+    pub fn byte_size(&self) -> Option<usize> {
+        self.size_in_bytes()
+    }
+    /// Get the number of components of an element in this format, e.g. 3
+    /// for `FLOAT3` or 12 for `FLOAT3X4_ROW_MAJOR`. A `GRID` is a single
+    /// component. Returns `None` for `UNDEFINED`.
+    pub fn component_count(&self) -> Option<usize> {
+        let v = *self as u32;
+        match v & 0xff00 {
+            _ if *self == Format::UNDEFINED => None,
+            _ if *self == Format::GRID => Some(1),
+            // Matrix formats encode their rows and columns in the low byte
+            0x9100 | 0x9200 => Some(((v >> 4) & 0xf) as usize * (v & 0xf) as usize),
+            _ => Some((v & 0xfff) as usize),
+        }
+    }
+    /// Whether the format can be used for buffers of the type on geometry
+    /// of the kind, see `valid_formats`
+    pub fn is_valid_for(&self, usage: BufferType, kind: GeometryKind) -> bool {
+        valid_formats(usage, kind).contains(self)
+    }
+}
+
+/// Get the formats geometry of the kind accepts for buffers of the type.
+/// The list is empty for buffer types the kind doesn't use.
+pub fn valid_formats(usage: BufferType, kind: GeometryKind) -> &'static [Format] {
+    match (kind, usage) {
+        (GeometryKind::Instance, _) | (GeometryKind::User, _) => &[],
+        (_, BufferType::VERTEX_ATTRIBUTE) => FLOAT_N,
+        (GeometryKind::Triangle, BufferType::INDEX) => &[Format::UINT3],
+        (GeometryKind::Quad, BufferType::INDEX) => &[Format::UINT4],
+        (GeometryKind::Triangle, BufferType::VERTEX) | (GeometryKind::Quad, BufferType::VERTEX) => {
+            &[Format::FLOAT3]
+        }
+        (GeometryKind::Grid, BufferType::VERTEX) => &[Format::FLOAT3],
+        (GeometryKind::Grid, BufferType::GRID) => &[Format::GRID],
+        (GeometryKind::Point, BufferType::VERTEX) => &[Format::FLOAT4],
+        (GeometryKind::Point, BufferType::NORMAL) => &[Format::FLOAT3],
+        (GeometryKind::Subdivision, usage) => match usage {
+            BufferType::VERTEX => &[Format::FLOAT3],
+            BufferType::INDEX
+            | BufferType::FACE
+            | BufferType::VERTEX_CREASE_INDEX
+            | BufferType::HOLE => &[Format::UINT],
+            BufferType::EDGE_CREASE_INDEX => &[Format::UINT2],
+            BufferType::EDGE_CREASE_WEIGHT
+            | BufferType::VERTEX_CREASE_WEIGHT
+            | BufferType::LEVEL => &[Format::FLOAT],
+            _ => &[],
+        },
+        (kind, usage) if kind.is_curve() => match usage {
+            BufferType::INDEX => &[Format::UINT],
+            BufferType::VERTEX => &[Format::FLOAT4],
+            BufferType::NORMAL => &[Format::FLOAT3],
+            BufferType::TANGENT if kind == GeometryKind::HermiteCurve => &[Format::FLOAT4],
+            BufferType::NORMAL_DERIVATIVE if kind == GeometryKind::HermiteCurve => {
+                &[Format::FLOAT3]
+            }
+            BufferType::FLAGS if kind == GeometryKind::LinearCurve => &[Format::UCHAR],
+            _ => &[],
+        },
+        _ => &[],
+    }
+}
+
+#[test]
+fn test_format_size_in_bytes() {
+    assert_eq!(Format::UNDEFINED.size_in_bytes(), None);
+    assert_eq!(Format::UCHAR3.size_in_bytes(), Some(3));
+    assert_eq!(Format::SHORT2.size_in_bytes(), Some(4));
+    assert_eq!(Format::UINT3.size_in_bytes(), Some(12));
+    assert_eq!(Format::LLONG.size_in_bytes(), Some(8));
+    assert_eq!(Format::FLOAT3.size_in_bytes(), Some(12));
+    assert_eq!(Format::FLOAT16.size_in_bytes(), Some(64));
+    assert_eq!(Format::FLOAT3X4_ROW_MAJOR.size_in_bytes(), Some(48));
+    assert_eq!(Format::FLOAT4X4_COLUMN_MAJOR.size_in_bytes(), Some(64));
+    assert_eq!(Format::GRID.size_in_bytes(), Some(12));
+}
+
+#[test]
+fn test_format_component_count() {
+    assert_eq!(Format::UNDEFINED.component_count(), None);
+    assert_eq!(Format::UCHAR.component_count(), Some(1));
+    assert_eq!(Format::ULLONG4.component_count(), Some(4));
+    assert_eq!(Format::FLOAT13.component_count(), Some(13));
+    assert_eq!(Format::FLOAT2X3_ROW_MAJOR.component_count(), Some(6));
+    assert_eq!(Format::FLOAT4X3_COLUMN_MAJOR.component_count(), Some(12));
+    assert_eq!(Format::GRID.component_count(), Some(1));
+}
+
+#[test]
+fn test_format_is_valid_for() {
+    use BufferType as B;
+    use GeometryKind as K;
+
+    assert!(Format::UINT3.is_valid_for(B::INDEX, K::Triangle));
+    assert!(!Format::UINT4.is_valid_for(B::INDEX, K::Triangle));
+    assert!(Format::UINT4.is_valid_for(B::INDEX, K::Quad));
+    assert!(Format::FLOAT3.is_valid_for(B::VERTEX, K::Quad));
+    assert!(!Format::FLOAT4.is_valid_for(B::VERTEX, K::Triangle));
+    assert!(Format::FLOAT4.is_valid_for(B::VERTEX, K::BezierCurve));
+    assert!(Format::FLOAT16.is_valid_for(B::VERTEX_ATTRIBUTE, K::Subdivision));
+    assert!(!Format::UINT.is_valid_for(B::VERTEX_ATTRIBUTE, K::Triangle));
+    assert!(Format::UINT2.is_valid_for(B::EDGE_CREASE_INDEX, K::Subdivision));
+    assert!(!Format::UINT2.is_valid_for(B::EDGE_CREASE_INDEX, K::Triangle));
+    assert!(Format::FLOAT4.is_valid_for(B::TANGENT, K::HermiteCurve));
+    assert!(!Format::FLOAT4.is_valid_for(B::TANGENT, K::BsplineCurve));
+    assert!(Format::UCHAR.is_valid_for(B::FLAGS, K::LinearCurve));
+    assert!(!Format::UCHAR.is_valid_for(B::FLAGS, K::CatmullRomCurve));
+    assert!(valid_formats(B::VERTEX, K::Instance).is_empty());
+}
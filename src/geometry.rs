@@ -1,51 +1,1010 @@
+use std::any::Any;
+use std::os::raw;
+use std::ptr;
+use std::sync::atomic::Ordering;
+use std::{error, fmt, mem};
+
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+use buffer::{self, Buffer, VertexElement};
+use bvh::{empty_bounds, union_bounds};
+use device::Device;
+use filter::{self, GeometryData, HitFaceMode};
+use grid_mesh;
+use interleaved::InterleavedBinding;
+use leak_check::{self, ObjectKind};
+use light_group;
+use linear_bounds::{self, LinearBounds};
+use ray::{Hit, Ray};
+use ray_mask::RayMask;
 use sys::*;
+use validation::{self, ValidationError};
+use {Bounds, BufferType, BuildQuality, Error, Format, GeometryType};
 
+#[cfg(feature = "curves")]
 use bezier_curve;
+#[cfg(feature = "curves")]
 use bspline_curve;
+#[cfg(feature = "curves")]
 use catmull_rom_curve;
+#[cfg(feature = "curves")]
+use curve::CurveBasis;
+#[cfg(feature = "curves")]
 use hermite_curve;
 use instance;
+#[cfg(feature = "curves")]
 use linear_curve;
+use point_geometry;
 use quad_mesh;
+#[cfg(feature = "subdivision")]
+use subdivision_mesh;
 use triangle_mesh;
+use user_geometry;
+
+/// Get the Rust data attached to the geometry handle when it was created
+/// by `new_handle`. The pointer is null once the `Geometry` holding the
+/// handle has been dropped, which buffers attached with
+/// `Buffer::attach_vertices` can outlive.
+pub(crate) unsafe fn data_ptr<'a>(h: RTCGeometry) -> *mut GeometryData<'a> {
+    rtcGetGeometryUserData(h) as *mut GeometryData<'a>
+}
+
+/// Mark the geometry as changed since it was last committed, see
+/// `Geometry::needs_commit`. Does nothing if the `Geometry` is gone.
+pub(crate) fn mark_dirty(h: RTCGeometry) {
+    unsafe {
+        let data = data_ptr(h);
+        if !data.is_null() {
+            (*data).dirty.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Record the number of elements of the shared data bound to the buffer
+/// slot of the geometry. Does nothing if the `Geometry` is gone.
+pub(crate) fn set_shared_count(h: RTCGeometry, buf_type: BufferType, slot: u32, count: usize) {
+    unsafe {
+        let data = data_ptr(h);
+        if !data.is_null() {
+            let counts = &mut (*data).shared_counts;
+            counts.retain(|&(t, s, _)| (t, s) != (buf_type, slot));
+            counts.push((buf_type, slot, count));
+        }
+    }
+}
+
+/// Take the panic held from building a scene holding the geometry, if any
+pub(crate) fn take_build_panic(h: RTCGeometry) -> Option<Box<dyn Any + Send>> {
+    unsafe {
+        let data = data_ptr(h);
+        if data.is_null() {
+            None
+        } else {
+            (*data).build_panic.lock().unwrap().take()
+        }
+    }
+}
+
+/// Commit the geometry and mark it as up to date
+pub(crate) fn commit_handle(h: RTCGeometry) {
+    unsafe {
+        rtcCommitGeometry(h);
+        let data = data_ptr(h);
+        if !data.is_null() {
+            (*data).dirty.store(false, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Create a new geometry handle of the type, which is released when the
+/// `Geometry` holding it is dropped. The handle's `GeometryData` is
+/// allocated here rather than on first use, as `Geometry` is `Sync` and
+/// threads reading a fresh geometry would race to allocate it.
+pub(crate) unsafe fn new_handle(device: &Device, geom_type: GeometryType) -> RTCGeometry {
+    leak_check::created(ObjectKind::Geometry);
+    let h = rtcNewGeometry(device.handle, geom_type);
+    let data: Box<GeometryData> = Box::default();
+    rtcSetGeometryUserData(h, Box::into_raw(data) as *mut raw::c_void);
+    h
+}
 
 pub enum Geometry<'a> {
     Triangle(triangle_mesh::TriangleMesh<'a>),
     Quad(quad_mesh::QuadMesh<'a>),
+    Grid(grid_mesh::GridMesh<'a>),
+    Point(point_geometry::PointGeometry<'a>),
     Instance(instance::Instance<'a>),
+    #[cfg(feature = "curves")]
     LinearCurve(linear_curve::LinearCurve<'a>),
+    #[cfg(feature = "curves")]
     BsplineCurve(bspline_curve::BsplineCurve<'a>),
+    #[cfg(feature = "curves")]
     BezierCurve(bezier_curve::BezierCurve<'a>),
+    #[cfg(feature = "curves")]
     HermiteCurve(hermite_curve::HermiteCurve<'a>),
+    #[cfg(feature = "curves")]
     CatmullRomCurve(catmull_rom_curve::CatmullRomCurve<'a>),
+    #[cfg(feature = "subdivision")]
+    Subdivision(subdivision_mesh::SubdivisionMesh<'a>),
+    User(user_geometry::UserGeometry<'a>),
+}
+
+/// The kind of a `Geometry`, i.e. which wrapper type it holds
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub enum GeometryKind {
+    Triangle,
+    Quad,
+    Grid,
+    Point,
+    Instance,
+    LinearCurve,
+    BsplineCurve,
+    BezierCurve,
+    HermiteCurve,
+    CatmullRomCurve,
+    Subdivision,
+    User,
+}
+
+impl GeometryKind {
+    /// Whether the kind is one of the curve types
+    pub fn is_curve(&self) -> bool {
+        matches!(
+            *self,
+            GeometryKind::LinearCurve
+                | GeometryKind::BsplineCurve
+                | GeometryKind::BezierCurve
+                | GeometryKind::HermiteCurve
+                | GeometryKind::CatmullRomCurve
+        )
+    }
+}
+
+/// A geometry wrapper type which can be recovered from the generic
+/// `Geometry` holding it, see `Geometry::as_kind`
+pub trait TypedGeometry<'a>: Sized {
+    const KIND: GeometryKind;
+    fn from_geometry<'g>(geometry: &'g Geometry<'a>) -> Option<&'g Self>;
+    fn from_geometry_mut<'g>(geometry: &'g mut Geometry<'a>) -> Option<&'g mut Self>;
 }
 
+macro_rules! typed_geometry {
+    ($variant:ident, $ty:ty) => {
+        impl<'a> TypedGeometry<'a> for $ty {
+            const KIND: GeometryKind = GeometryKind::$variant;
+            fn from_geometry<'g>(geometry: &'g Geometry<'a>) -> Option<&'g Self> {
+                match *geometry {
+                    Geometry::$variant(ref g) => Some(g),
+                    _ => None,
+                }
+            }
+            fn from_geometry_mut<'g>(geometry: &'g mut Geometry<'a>) -> Option<&'g mut Self> {
+                match *geometry {
+                    Geometry::$variant(ref mut g) => Some(g),
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+typed_geometry!(Triangle, triangle_mesh::TriangleMesh<'a>);
+typed_geometry!(Quad, quad_mesh::QuadMesh<'a>);
+typed_geometry!(Grid, grid_mesh::GridMesh<'a>);
+typed_geometry!(Point, point_geometry::PointGeometry<'a>);
+typed_geometry!(Instance, instance::Instance<'a>);
+#[cfg(feature = "curves")]
+typed_geometry!(LinearCurve, linear_curve::LinearCurve<'a>);
+#[cfg(feature = "curves")]
+typed_geometry!(BsplineCurve, bspline_curve::BsplineCurve<'a>);
+#[cfg(feature = "curves")]
+typed_geometry!(BezierCurve, bezier_curve::BezierCurve<'a>);
+#[cfg(feature = "curves")]
+typed_geometry!(HermiteCurve, hermite_curve::HermiteCurve<'a>);
+#[cfg(feature = "curves")]
+typed_geometry!(CatmullRomCurve, catmull_rom_curve::CatmullRomCurve<'a>);
+#[cfg(feature = "subdivision")]
+typed_geometry!(Subdivision, subdivision_mesh::SubdivisionMesh<'a>);
+typed_geometry!(User, user_geometry::UserGeometry<'a>);
+
 /// Geometry trait implemented by all Embree Geometry types
 impl<'a> Geometry<'a> {
     pub fn handle(&self) -> RTCGeometry {
         match self {
             &Geometry::Triangle(ref m) => m.handle,
             &Geometry::Quad(ref q) => q.handle,
+            &Geometry::Grid(ref g) => g.handle,
+            &Geometry::Point(ref p) => p.handle,
             &Geometry::Instance(ref i) => i.handle,
+            #[cfg(feature = "curves")]
             &Geometry::LinearCurve(ref lc) => lc.handle,
+            #[cfg(feature = "curves")]
             &Geometry::BsplineCurve(ref bsc) => bsc.handle,
+            #[cfg(feature = "curves")]
             &Geometry::BezierCurve(ref bzc) => bzc.handle,
+            #[cfg(feature = "curves")]
             &Geometry::HermiteCurve(ref hc) => hc.handle,
+            #[cfg(feature = "curves")]
             &Geometry::CatmullRomCurve(ref crc) => crc.handle,
+            #[cfg(feature = "subdivision")]
+            &Geometry::Subdivision(ref s) => s.handle,
+            &Geometry::User(ref u) => u.handle,
         }
     }
     pub fn commit(&mut self) {
+        commit_handle(self.handle());
+    }
+    /// Validate the geometry's buffers with `validate` and commit it if
+    /// they're consistent, leaving it uncommitted otherwise
+    pub fn try_commit(&mut self) -> Result<(), ValidationError> {
+        self.validate()?;
+        self.commit();
+        Ok(())
+    }
+    /// Enable or disable the geometry, disabled geometry is skipped by all
+    /// queries. Geometry is enabled when created. The scene holding the
+    /// geometry must be committed for the change to take effect, the
+    /// geometry itself doesn't need to be.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        unsafe {
+            if enabled {
+                rtcEnableGeometry(self.handle());
+            } else {
+                rtcDisableGeometry(self.handle());
+            }
+        }
+        self.data().enabled = enabled;
+    }
+    pub fn is_enabled(&self) -> bool {
+        unsafe { (*data_ptr(self.handle())).enabled }
+    }
+    /// Whether the geometry has changed since it was last committed, or
+    /// was never committed. Changes are tracked through the wrapper:
+    /// mapping one of the geometry's buffers or calling a setter which
+    /// must be committed to take effect marks it as changed. Writes to
+    /// shared buffers through their own slices aren't seen, so geometry
+    /// using them must still be committed by the application after
+    /// changing them.
+    pub fn needs_commit(&self) -> bool {
+        unsafe { (*data_ptr(self.handle())).dirty.load(Ordering::Relaxed) }
+    }
+    pub fn kind(&self) -> GeometryKind {
+        match *self {
+            Geometry::Triangle(_) => GeometryKind::Triangle,
+            Geometry::Quad(_) => GeometryKind::Quad,
+            Geometry::Grid(_) => GeometryKind::Grid,
+            Geometry::Point(_) => GeometryKind::Point,
+            Geometry::Instance(_) => GeometryKind::Instance,
+            #[cfg(feature = "curves")]
+            Geometry::LinearCurve(_) => GeometryKind::LinearCurve,
+            #[cfg(feature = "curves")]
+            Geometry::BsplineCurve(_) => GeometryKind::BsplineCurve,
+            #[cfg(feature = "curves")]
+            Geometry::BezierCurve(_) => GeometryKind::BezierCurve,
+            #[cfg(feature = "curves")]
+            Geometry::HermiteCurve(_) => GeometryKind::HermiteCurve,
+            #[cfg(feature = "curves")]
+            Geometry::CatmullRomCurve(_) => GeometryKind::CatmullRomCurve,
+            #[cfg(feature = "subdivision")]
+            Geometry::Subdivision(_) => GeometryKind::Subdivision,
+            Geometry::User(_) => GeometryKind::User,
+        }
+    }
+    /// Whether the geometry holds a `T`, e.g. `geom.is_kind::<TriangleMesh>()`
+    pub fn is_kind<T: TypedGeometry<'a>>(&self) -> bool {
+        self.kind() == T::KIND
+    }
+    /// Get the geometry as a `T` to call its kind specific methods, e.g.
+    /// to map the vertex buffer of a `TriangleMesh` found with
+    /// `Scene::get_geometry`. Returns `None` if the geometry holds
+    /// another kind.
+    pub fn as_kind<T: TypedGeometry<'a>>(&self) -> Option<&T> {
+        T::from_geometry(self)
+    }
+    /// Get the geometry as a mutable `T`, see `as_kind`
+    pub fn as_kind_mut<T: TypedGeometry<'a>>(&mut self) -> Option<&mut T> {
+        T::from_geometry_mut(self)
+    }
+    /// Get the geometry as a `T`, returning an error naming the kind it
+    /// holds if it isn't one. The `Geometry` keeps ownership of the Embree
+    /// geometry, which it releases when dropped, so the wrapper is only
+    /// borrowed.
+    pub fn try_into_kind<T: TypedGeometry<'a>>(&self) -> Result<&T, KindMismatch> {
+        T::from_geometry(self).ok_or(KindMismatch {
+            expected: T::KIND,
+            found: self.kind(),
+        })
+    }
+    /// Whether the geometry is tessellated when intersected, so its
+    /// quality can be traded for speed with `set_tessellation_rate`
+    pub fn is_tessellated(&self) -> bool {
+        matches!(
+            self.kind(),
+            GeometryKind::BsplineCurve
+                | GeometryKind::BezierCurve
+                | GeometryKind::HermiteCurve
+                | GeometryKind::CatmullRomCurve
+                | GeometryKind::Subdivision
+        )
+    }
+    /// Set the number of segments each curve segment or subdivision edge
+    /// is tessellated into, taking effect when the geometry is committed.
+    ///
+    /// Panics if the geometry isn't tessellated, see `is_tessellated`.
+    pub fn set_tessellation_rate(&mut self, rate: f32) {
+        if self.try_set_tessellation_rate(rate).is_err() {
+            panic!("Only cubic curves and subdivision meshes have a tessellation rate");
+        }
+    }
+    /// Set the tessellation rate of the geometry, see
+    /// `set_tessellation_rate`. Returns `Error::INVALID_OPERATION` and
+    /// leaves the geometry unchanged if it isn't tessellated.
+    pub fn try_set_tessellation_rate(&mut self, rate: f32) -> Result<(), Error> {
+        if !self.is_tessellated() {
+            return Err(Error::INVALID_OPERATION);
+        }
+        mark_dirty(self.handle());
+        unsafe {
+            rtcSetGeometryTessellationRate(self.handle(), rate);
+        }
+        Ok(())
+    }
+    /// Copy the vertex positions of each motion blur time step into the
+    /// geometry, reallocating its vertex buffers to match, see
+    /// `TriangleMesh::set_time_steps`. The geometry must be committed for
+    /// the change to take effect.
+    ///
+    /// Panics if the geometry isn't a triangle mesh, the only kind with
+    /// motion blur vertex buffers, if there are no time steps or if they
+    /// don't all have the same number of vertices, see
+    /// `try_set_motion_vertex_buffers`.
+    pub fn set_motion_vertex_buffers<V: VertexElement>(&mut self, steps: &[&[V]]) {
+        match self.try_set_motion_vertex_buffers(steps) {
+            Ok(()) => {}
+            Err(Error::INVALID_OPERATION) => {
+                panic!("Only triangle meshes have motion blur vertex buffers")
+            }
+            Err(_) => panic!("Motion blur time steps must all have the same number of vertices"),
+        }
+    }
+    /// Copy the vertex positions of each motion blur time step into the
+    /// geometry, see `set_motion_vertex_buffers`. Returns
+    /// `Error::INVALID_OPERATION` if the geometry isn't a triangle mesh, or
+    /// `Error::INVALID_ARGUMENT` if there are no time steps or they don't
+    /// all have the same number of vertices, leaving the geometry
+    /// unchanged. `TriangleMesh::set_time_steps` reports which time step
+    /// doesn't match.
+    pub fn try_set_motion_vertex_buffers<V: VertexElement>(
+        &mut self,
+        steps: &[&[V]],
+    ) -> Result<(), Error> {
+        match *self {
+            Geometry::Triangle(ref mut m) if !steps.is_empty() => {
+                m.set_time_steps(steps).map_err(|_| Error::INVALID_ARGUMENT)
+            }
+            Geometry::Triangle(_) => Err(Error::INVALID_ARGUMENT),
+            _ => Err(Error::INVALID_OPERATION),
+        }
+    }
+    /// Set the mask of the geometry, rays only intersect the geometry if
+    /// their mask shares a set bit with it. The default mask has all bits
+    /// set. The geometry must be committed for the change to take effect.
+    pub fn set_mask(&mut self, mask: u32) {
+        mark_dirty(self.handle());
         unsafe {
-            rtcCommitGeometry(self.handle());
+            rtcSetGeometryMask(self.handle(), mask);
         }
+        self.data().mask = mask;
+    }
+    /// Set the mask of the geometry, as `set_mask` with a typed mask. See
+    /// the `ray_mask` module.
+    pub fn set_ray_mask(&mut self, mask: RayMask) {
+        self.set_mask(mask.bits());
+    }
+    pub fn mask(&self) -> u32 {
+        unsafe { (*data_ptr(self.handle())).mask }
+    }
+    pub fn ray_mask(&self) -> RayMask {
+        RayMask(self.mask())
+    }
+    /// Set the quality of the geometry's BVH, taking effect when the
+    /// geometry is committed. `BuildQuality::REFIT` updates the previous
+    /// BVH to the geometry's new vertices instead of building a new one,
+    /// which is much faster for deforming meshes, e.g. skinned characters,
+    /// whose topology stays the same between frames. See the `skinning`
+    /// module.
+    pub fn set_build_quality(&mut self, quality: BuildQuality) {
+        mark_dirty(self.handle());
+        unsafe {
+            rtcSetGeometryBuildQuality(self.handle(), quality);
+        }
+    }
+    /// Put the geometry in a single light group, keeping it visible to rays
+    /// using the user mask bits. See the `light_group` module.
+    ///
+    /// Panics if the group is out of range.
+    pub fn set_light_group(&mut self, group: u8) {
+        self.set_light_groups(&[group]);
+    }
+    /// Put the geometry in each of the light groups, keeping it visible to
+    /// rays using the user mask bits. See the `light_group` module.
+    ///
+    /// Panics if any group is out of range.
+    pub fn set_light_groups(&mut self, groups: &[u8]) {
+        self.set_mask(light_group::USER_MASK_BITS | light_group::light_groups_mask(groups));
+    }
+    /// Get the linear bounds of the geometry over the shutter interval,
+    /// computed from its buffers as Embree doesn't provide the bounds of
+    /// individual geometries. Curve vertices are padded by their radius,
+    /// and all vertices in the buffers are included whether they're
+    /// referenced by a primitive or not. Hermite and Catmull-Rom curves,
+    /// which can extend outside their vertices, are bounded by the Bézier
+    /// control points of their segments. Instances are bounded by the
+    /// instanced scene's bounds transformed to world space, and user
+    /// geometries by the union of their primitives' bounds.
+    ///
+    /// Returns `None` if shared data was bound in place of any of the
+    /// geometry's buffers, e.g. with `set_shared_buffer_from_slice`, as the
+    /// bounds can't be computed without reading it. Committing the scene
+    /// and using `CommittedScene::linear_bounds` works for any geometry.
+    pub fn linear_bounds(&self) -> Option<LinearBounds> {
+        if !self.owns_bound_buffers() {
+            return None;
+        }
+        let verts = match *self {
+            Geometry::Triangle(ref m) => {
+                let mut steps = vec![m.vertex_buffer.as_slice()];
+                steps.extend(m.motion_vertex_buffers.iter().map(|b| b.as_slice()));
+                return Some(linear_bounds::fit_vertices(&steps));
+            }
+            Geometry::Quad(ref q) => q.vertex_buffer.as_slice(),
+            Geometry::Grid(ref g) => g.vertex_buffer.as_slice(),
+            Geometry::Point(ref p) => p.vertex_buffer.as_slice(),
+            #[cfg(feature = "curves")]
+            Geometry::LinearCurve(ref c) => c.vertex_buffer.as_slice(),
+            #[cfg(feature = "curves")]
+            Geometry::BsplineCurve(ref c) => c.vertex_buffer.as_slice(),
+            #[cfg(feature = "curves")]
+            Geometry::BezierCurve(ref c) => c.vertex_buffer.as_slice(),
+            #[cfg(feature = "curves")]
+            Geometry::HermiteCurve(ref c) => {
+                let points = linear_bounds::hermite_control_points(
+                    c.vertex_buffer.as_slice(),
+                    c.tangent_buffer.as_slice(),
+                    c.index_buffer.as_slice(),
+                );
+                return Some(linear_bounds::fit_vertices(&[&points]));
+            }
+            #[cfg(feature = "curves")]
+            Geometry::CatmullRomCurve(ref c) => {
+                let points = linear_bounds::catmull_rom_control_points(
+                    c.vertex_buffer.as_slice(),
+                    c.index_buffer.as_slice(),
+                );
+                return Some(linear_bounds::fit_vertices(&[&points]));
+            }
+            #[cfg(feature = "subdivision")]
+            Geometry::Subdivision(ref s) => s.vertex_buffer.as_slice(),
+            Geometry::Instance(ref i) => {
+                let lb = i.scene.linear_bounds();
+                return Some(LinearBounds::new(
+                    linear_bounds::transform_bounds(i.transform(), &lb.bounds0),
+                    linear_bounds::transform_bounds(i.transform(), &lb.bounds1),
+                ));
+            }
+            Geometry::User(ref u) => {
+                let b = (0..u.len() as u32)
+                    .map(|prim| u.bounds(prim))
+                    .fold(empty_bounds(), |a, b| union_bounds(&a, &b));
+                return Some(LinearBounds::new(b, b));
+            }
+        };
+        Some(linear_bounds::fit_vertices(&[verts]))
+    }
+    /// Get the bounds of the geometry over the whole shutter interval, see
+    /// `linear_bounds`. Instances are bounded in world space, e.g. to fit
+    /// a camera or clipping planes to the geometry. Returns `None` if
+    /// the bounds can't be computed, see `linear_bounds`.
+    pub fn bounds(&self) -> Option<Bounds> {
+        self.linear_bounds().map(|lb| lb.shutter_bounds())
+    }
+    /// Get the buffers which must be set for this kind of geometry. For
+    /// curves and points they depend on their type as well, see
+    /// `CurveBasis::required_buffers` and `PointType::required_buffers`.
+    pub fn required_buffers(&self) -> &'static [BufferType] {
+        match *self {
+            Geometry::Triangle(_) => triangle_mesh::TriangleMesh::REQUIRED,
+            Geometry::Quad(_) => quad_mesh::QuadMesh::REQUIRED,
+            Geometry::Grid(_) => grid_mesh::GridMesh::REQUIRED,
+            Geometry::Point(ref p) => p.point_type.required_buffers(),
+            Geometry::Instance(_) => instance::Instance::REQUIRED,
+            #[cfg(feature = "curves")]
+            Geometry::LinearCurve(ref c) => CurveBasis::Linear.required_buffers(c.curve_type),
+            #[cfg(feature = "curves")]
+            Geometry::BsplineCurve(ref c) => CurveBasis::Bspline.required_buffers(c.curve_type),
+            #[cfg(feature = "curves")]
+            Geometry::BezierCurve(ref c) => CurveBasis::Bezier.required_buffers(c.curve_type),
+            #[cfg(feature = "curves")]
+            Geometry::HermiteCurve(ref c) => CurveBasis::Hermite.required_buffers(c.curve_type),
+            #[cfg(feature = "curves")]
+            Geometry::CatmullRomCurve(ref c) => {
+                CurveBasis::CatmullRom.required_buffers(c.curve_type)
+            }
+            #[cfg(feature = "subdivision")]
+            Geometry::Subdivision(_) => subdivision_mesh::SubdivisionMesh::REQUIRED,
+            Geometry::User(_) => user_geometry::UserGeometry::REQUIRED,
+        }
+    }
+    /// Get the buffers this kind of geometry can optionally use
+    pub fn optional_buffers(&self) -> &'static [BufferType] {
+        match *self {
+            Geometry::Triangle(_) => triangle_mesh::TriangleMesh::OPTIONAL,
+            Geometry::Quad(_) => quad_mesh::QuadMesh::OPTIONAL,
+            Geometry::Grid(_) => grid_mesh::GridMesh::OPTIONAL,
+            Geometry::Point(_) => point_geometry::PointGeometry::OPTIONAL,
+            Geometry::Instance(_) => instance::Instance::OPTIONAL,
+            #[cfg(feature = "curves")]
+            Geometry::LinearCurve(_) => linear_curve::LinearCurve::OPTIONAL,
+            #[cfg(feature = "curves")]
+            Geometry::BsplineCurve(_) => bspline_curve::BsplineCurve::OPTIONAL,
+            #[cfg(feature = "curves")]
+            Geometry::BezierCurve(_) => bezier_curve::BezierCurve::OPTIONAL,
+            #[cfg(feature = "curves")]
+            Geometry::HermiteCurve(_) => hermite_curve::HermiteCurve::OPTIONAL,
+            #[cfg(feature = "curves")]
+            Geometry::CatmullRomCurve(_) => catmull_rom_curve::CatmullRomCurve::OPTIONAL,
+            #[cfg(feature = "subdivision")]
+            Geometry::Subdivision(_) => subdivision_mesh::SubdivisionMesh::OPTIONAL,
+            Geometry::User(_) => user_geometry::UserGeometry::OPTIONAL,
+        }
+    }
+    /// Check that the buffers required by the geometry are set and
+    /// consistent with each other before committing it: that motion blur
+    /// time steps and per vertex buffers have the same number of
+    /// vertices, and that the primitives only reference vertices in the
+    /// vertex buffer, and that there are no more primitives than Embree
+    /// can give IDs to. The contents of buffers shared from user memory
+    /// are not checked, as their size isn't known.
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        validation::validate(self)
+    }
+    /// Get the number of primitives of the geometry, which Embree gives
+    /// IDs to and builds the BVH over. An instance is a single primitive.
+    /// If shared data was bound in place of the buffer holding the
+    /// primitives, e.g. the index buffer, the number of elements shared
+    /// is used.
+    pub fn primitive_count(&self) -> usize {
+        match *self {
+            Geometry::Triangle(ref m) => self.bound_len(&m.index_buffer, BufferType::INDEX),
+            Geometry::Quad(ref m) => self.bound_len(&m.index_buffer, BufferType::INDEX),
+            Geometry::Grid(ref m) => self.bound_len(&m.grid_buffer, BufferType::GRID),
+            Geometry::Point(ref p) => self.bound_len(&p.vertex_buffer, BufferType::VERTEX),
+            Geometry::Instance(_) => 1,
+            #[cfg(feature = "curves")]
+            Geometry::LinearCurve(ref c) => self.bound_len(&c.index_buffer, BufferType::INDEX),
+            #[cfg(feature = "curves")]
+            Geometry::BsplineCurve(ref c) => self.bound_len(&c.index_buffer, BufferType::INDEX),
+            #[cfg(feature = "curves")]
+            Geometry::BezierCurve(ref c) => self.bound_len(&c.index_buffer, BufferType::INDEX),
+            #[cfg(feature = "curves")]
+            Geometry::HermiteCurve(ref c) => self.bound_len(&c.index_buffer, BufferType::INDEX),
+            #[cfg(feature = "curves")]
+            Geometry::CatmullRomCurve(ref c) => self.bound_len(&c.index_buffer, BufferType::INDEX),
+            #[cfg(feature = "subdivision")]
+            Geometry::Subdivision(ref m) => self.bound_len(&m.face_buffer, BufferType::FACE),
+            Geometry::User(ref u) => u.len(),
+        }
+    }
+    /// Get the number of elements bound to slot 0 of the buffer type, the
+    /// length of the wrapper's buffer unless shared data replaced it
+    fn bound_len<T>(&self, buf: &Buffer<T>, buf_type: BufferType) -> usize {
+        if buf.is_bound() {
+            return buf.len();
+        }
+        let counts = unsafe { &(*data_ptr(self.handle())).shared_counts };
+        counts
+            .iter()
+            .find(|&&(t, s, _)| t == buf_type && s == 0)
+            .map_or(0, |&(_, _, count)| count)
+    }
+    /// Check every buffer owned by the geometry's wrapper is still the one
+    /// Embree uses, see `Buffer::is_bound`. Code reading the geometry's
+    /// data from the wrapper's buffers must check this first, as they no
+    /// longer hold the geometry's data once shared data is bound in their
+    /// place.
+    pub(crate) fn owns_bound_buffers(&self) -> bool {
+        fn all<T>(buffers: &[Buffer<T>]) -> bool {
+            buffers.iter().all(|b| b.is_bound())
+        }
+        fn opt<T>(buffer: &Option<Buffer<T>>) -> bool {
+            buffer.as_ref().is_none_or(|b| b.is_bound())
+        }
+        match *self {
+            Geometry::Triangle(ref m) => {
+                m.vertex_buffer.is_bound()
+                    && m.index_buffer.is_bound()
+                    && all(&m.motion_vertex_buffers)
+                    && all(&m.vertex_attribute_buffers)
+            }
+            Geometry::Quad(ref m) => m.vertex_buffer.is_bound() && m.index_buffer.is_bound(),
+            Geometry::Grid(ref m) => m.vertex_buffer.is_bound() && m.grid_buffer.is_bound(),
+            Geometry::Point(ref p) => p.vertex_buffer.is_bound() && opt(&p.normal_buffer),
+            Geometry::Instance(_) | Geometry::User(_) => true,
+            #[cfg(feature = "curves")]
+            Geometry::LinearCurve(ref c) => {
+                c.vertex_buffer.is_bound()
+                    && c.index_buffer.is_bound()
+                    && c.flag_buffer.is_bound()
+                    && opt(&c.normal_buffer)
+            }
+            #[cfg(feature = "curves")]
+            Geometry::BsplineCurve(ref c) => {
+                c.vertex_buffer.is_bound() && c.index_buffer.is_bound() && opt(&c.normal_buffer)
+            }
+            #[cfg(feature = "curves")]
+            Geometry::BezierCurve(ref c) => {
+                c.vertex_buffer.is_bound() && c.index_buffer.is_bound() && opt(&c.normal_buffer)
+            }
+            #[cfg(feature = "curves")]
+            Geometry::HermiteCurve(ref c) => {
+                c.vertex_buffer.is_bound()
+                    && c.index_buffer.is_bound()
+                    && c.tangent_buffer.is_bound()
+                    && opt(&c.normal_derivative_buffer)
+                    && opt(&c.normal_buffer)
+            }
+            #[cfg(feature = "curves")]
+            Geometry::CatmullRomCurve(ref c) => {
+                c.vertex_buffer.is_bound() && c.index_buffer.is_bound() && opt(&c.normal_buffer)
+            }
+            #[cfg(feature = "subdivision")]
+            Geometry::Subdivision(ref m) => {
+                m.vertex_buffer.is_bound()
+                    && m.face_buffer.is_bound()
+                    && all(&m.index_buffers)
+                    && all(&m.vertex_attribute_buffers)
+            }
+        }
+    }
+    /// Get the number of elements in the buffer the geometry's wrapper
+    /// owns for `buf_type` and `slot`, or 0 if it doesn't own one
+    pub(crate) fn owned_buffer_len(&self, buf_type: BufferType, slot: u32) -> usize {
+        fn len_of<T>(buffers: &[Buffer<T>], slot: u32) -> usize {
+            buffers.get(slot as usize).map_or(0, |b| b.len())
+        }
+        fn opt_len<T>(buffer: &Option<Buffer<T>>) -> usize {
+            buffer.as_ref().map_or(0, |b| b.len())
+        }
+        match (self, buf_type) {
+            (Geometry::Triangle(m), BufferType::VERTEX) => match slot {
+                0 => m.vertex_buffer.len(),
+                _ => len_of(&m.motion_vertex_buffers, slot - 1),
+            },
+            (Geometry::Triangle(m), BufferType::VERTEX_ATTRIBUTE) => {
+                len_of(&m.vertex_attribute_buffers, slot)
+            }
+            (_, BufferType::VERTEX) if slot != 0 => 0,
+            (Geometry::Quad(m), BufferType::VERTEX) => m.vertex_buffer.len(),
+            (Geometry::Grid(m), BufferType::VERTEX) => m.vertex_buffer.len(),
+            (Geometry::Point(p), BufferType::VERTEX) => p.vertex_buffer.len(),
+            (Geometry::Point(p), BufferType::NORMAL) => opt_len(&p.normal_buffer),
+            #[cfg(feature = "curves")]
+            (Geometry::LinearCurve(c), BufferType::VERTEX) => c.vertex_buffer.len(),
+            #[cfg(feature = "curves")]
+            (Geometry::LinearCurve(c), BufferType::NORMAL) => opt_len(&c.normal_buffer),
+            #[cfg(feature = "curves")]
+            (Geometry::BsplineCurve(c), BufferType::VERTEX) => c.vertex_buffer.len(),
+            #[cfg(feature = "curves")]
+            (Geometry::BsplineCurve(c), BufferType::NORMAL) => opt_len(&c.normal_buffer),
+            #[cfg(feature = "curves")]
+            (Geometry::BezierCurve(c), BufferType::VERTEX) => c.vertex_buffer.len(),
+            #[cfg(feature = "curves")]
+            (Geometry::BezierCurve(c), BufferType::NORMAL) => opt_len(&c.normal_buffer),
+            #[cfg(feature = "curves")]
+            (Geometry::HermiteCurve(c), BufferType::VERTEX) => c.vertex_buffer.len(),
+            #[cfg(feature = "curves")]
+            (Geometry::HermiteCurve(c), BufferType::TANGENT) => c.tangent_buffer.len(),
+            #[cfg(feature = "curves")]
+            (Geometry::HermiteCurve(c), BufferType::NORMAL) => opt_len(&c.normal_buffer),
+            #[cfg(feature = "curves")]
+            (Geometry::HermiteCurve(c), BufferType::NORMAL_DERIVATIVE) => {
+                opt_len(&c.normal_derivative_buffer)
+            }
+            #[cfg(feature = "curves")]
+            (Geometry::CatmullRomCurve(c), BufferType::VERTEX) => c.vertex_buffer.len(),
+            #[cfg(feature = "curves")]
+            (Geometry::CatmullRomCurve(c), BufferType::NORMAL) => opt_len(&c.normal_buffer),
+            #[cfg(feature = "subdivision")]
+            (Geometry::Subdivision(m), BufferType::VERTEX) => m.vertex_buffer.len(),
+            #[cfg(feature = "subdivision")]
+            (Geometry::Subdivision(m), BufferType::VERTEX_ATTRIBUTE) => {
+                len_of(&m.vertex_attribute_buffers, slot)
+            }
+            _ => 0,
+        }
+    }
+    /// Share the first `count` elements of `data` with Embree as the
+    /// geometry's buffer in `slot`, without copying it. The slice stays
+    /// borrowed for as long as the geometry lives, so it can't be modified
+    /// or freed while Embree may read it. Any buffer owned by the geometry
+    /// for the slot is no longer used, and the geometry must be committed
+    /// for the change to take effect.
+    ///
+    /// The wrapper can't read the shared data back, so the helpers which
+    /// read a geometry's buffers, e.g. `linear_bounds`, the reference
+    /// intersector and the scene cache, return `None` or an error for the
+    /// geometry or skip it.
+    ///
+    /// Embree reads the last element of vertex and vertex attribute
+    /// buffers with 16 byte loads, so `data` must hold enough elements
+    /// after the first `count` to pad the read, e.g. one extra `[f32; 3]`.
+    /// The number required is given by `buffer::vertex_padding`, see
+    /// `VertexLayout` for how this applies to packed and padded vertices.
+    ///
+    /// Panics if `data` is too short or its elements are not 4 byte aligned.
+    pub fn set_shared_buffer_from_slice<T: Copy>(
+        &mut self,
+        buf_type: BufferType,
+        slot: u32,
+        format: Format,
+        data: &'a [T],
+        count: usize,
+    ) {
+        mark_dirty(self.handle());
+        let padding = match buf_type {
+            BufferType::VERTEX | BufferType::VERTEX_ATTRIBUTE => buffer::vertex_padding::<T>(),
+            _ => 0,
+        };
+        assert!(
+            count + padding <= data.len(),
+            "Shared buffer of {} elements is too short for {} elements and {} padding",
+            data.len(),
+            count,
+            padding
+        );
+        assert!(
+            mem::align_of::<T>() >= 4,
+            "Shared buffer elements must be 4 byte aligned"
+        );
+        unsafe {
+            rtcSetSharedGeometryBuffer(
+                self.handle(),
+                buf_type,
+                slot,
+                format,
+                data.as_ptr() as *const raw::c_void,
+                0,
+                mem::size_of::<T>(),
+                count,
+            );
+        }
+        set_shared_count(self.handle(), buf_type, slot, count);
+    }
+    /// Bind the first `count` elements of a slice of interleaved structs to
+    /// buffer slots of the geometry without copying it, with each member
+    /// bound by the returned builder. See the `interleaved` module. The
+    /// geometry must be committed for the change to take effect.
+    pub fn bind_interleaved<T: Copy>(
+        &mut self,
+        data: &'a [T],
+        count: usize,
+    ) -> InterleavedBinding<'_, 'a, T> {
+        InterleavedBinding::new(self.handle(), self.kind(), data, count)
+    }
+    /// Set a filter function called for each hit found on the geometry by
+    /// intersection queries, hits the filter returns false for are
+    /// ignored and traversal continues. The geometry must be committed for
+    /// the filter to take effect. See the `filter` module for the thread
+    /// safety requirements on the filter.
+    pub fn set_intersect_filter_function<F>(&mut self, filter: F)
+    where
+        F: Fn(&Ray, &Hit) -> bool + Send + Sync + 'a,
+    {
+        mark_dirty(self.handle());
+        let data = self.data();
+        data.intersect_filter = Some(Box::new(filter));
+        data.intersect_packet_filter = None;
+        data.raw_intersect_filter = false;
+        unsafe {
+            rtcSetGeometryIntersectFilterFunction(self.handle(), Some(filter::intersect_filter));
+        }
+    }
+    /// Set a filter function called for each hit found on the geometry by
+    /// occlusion queries, the ray is only marked occluded if the filter
+    /// accepts the hit. The geometry must be committed for the filter to
+    /// take effect. See the `filter` module for the thread safety
+    /// requirements on the filter.
+    ///
+    /// The filter may be called more than once for the same ray, e.g. when
+    /// it passes through an edge shared by two triangles and the scene is
+    /// built with `SceneFlags::ROBUST`, so filters should not assume each
+    /// call is for a distinct surface crossing.
+    pub fn set_occluded_filter_function<F>(&mut self, filter: F)
+    where
+        F: Fn(&Ray, &Hit) -> bool + Send + Sync + 'a,
+    {
+        mark_dirty(self.handle());
+        let data = self.data();
+        data.occluded_filter = Some(Box::new(filter));
+        data.occluded_packet_filter = None;
+        data.raw_occluded_filter = false;
+        unsafe {
+            rtcSetGeometryOccludedFilterFunction(self.handle(), Some(filter::occluded_filter));
+        }
+    }
+    /// Set a raw filter function called by Embree for each packet of hits
+    /// found on the geometry by intersection queries, replacing any filter
+    /// closure set. This avoids the overhead of unpacking each ray and hit
+    /// for hot filters, `user_data` can be retrieved in the filter with
+    /// `filter::user_data`. Passing `None` removes the filter. The geometry
+    /// must be committed for the filter to take effect.
+    ///
+    /// Faces are culled by the wrapper's filter functions, so this resets
+    /// the hit face mode to `HitFaceMode::Both` for both intersection and
+    /// occlusion queries, see `set_hit_face_mode`.
+    ///
+    /// # Safety
+    /// The filter must only access the rays and hits of the packet it's
+    /// passed and be safe to call concurrently from the threads tracing rays,
+    /// and `user_data` must remain valid for as long as the filter is set.
+    /// See the `filter` module for an example.
+    pub unsafe fn set_intersect_filter_function_raw(
+        &mut self,
+        filter: RTCFilterFunctionN,
+        user_data: *mut raw::c_void,
+    ) {
+        let handle = self.handle();
+        mark_dirty(handle);
+        let data = self.data();
+        data.intersect_filter = None;
+        data.intersect_packet_filter = None;
+        data.raw_user_data = user_data;
+        data.raw_intersect_filter = filter.is_some();
+        rtcSetGeometryIntersectFilterFunction(handle, filter);
+        if data.face_mode != HitFaceMode::Both {
+            data.face_mode = HitFaceMode::Both;
+            // The occlusion filter may only have been set to cull faces
+            if data.occluded_filter.is_none() && data.occluded_packet_filter.is_none() {
+                rtcSetGeometryOccludedFilterFunction(handle, None);
+            }
+        }
+    }
+    /// Set a raw filter function called by Embree for each packet of hits
+    /// found on the geometry by occlusion queries, replacing any filter
+    /// closure set. See `set_intersect_filter_function_raw`.
+    ///
+    /// # Safety
+    /// The same requirements as `set_intersect_filter_function_raw` apply.
+    pub unsafe fn set_occluded_filter_function_raw(
+        &mut self,
+        filter: RTCFilterFunctionN,
+        user_data: *mut raw::c_void,
+    ) {
+        let handle = self.handle();
+        mark_dirty(handle);
+        let data = self.data();
+        data.occluded_filter = None;
+        data.occluded_packet_filter = None;
+        data.raw_user_data = user_data;
+        data.raw_occluded_filter = filter.is_some();
+        rtcSetGeometryOccludedFilterFunction(handle, filter);
+        if data.face_mode != HitFaceMode::Both {
+            data.face_mode = HitFaceMode::Both;
+            if data.intersect_filter.is_none() && data.intersect_packet_filter.is_none() {
+                rtcSetGeometryIntersectFilterFunction(handle, None);
+            }
+        }
+    }
+    /// Remove the intersection and occlusion filter functions set on the
+    /// geometry. The hit face mode is kept. The geometry must be committed
+    /// for this to take effect.
+    pub fn clear_filter_functions(&mut self) {
+        mark_dirty(self.handle());
+        let data = self.data();
+        data.intersect_filter = None;
+        data.occluded_filter = None;
+        data.intersect_packet_filter = None;
+        data.occluded_packet_filter = None;
+        data.raw_user_data = ptr::null_mut();
+        data.raw_intersect_filter = false;
+        data.raw_occluded_filter = false;
+        let culled = data.face_mode != HitFaceMode::Both;
+        unsafe {
+            if culled {
+                rtcSetGeometryIntersectFilterFunction(
+                    self.handle(),
+                    Some(filter::intersect_filter),
+                );
+                rtcSetGeometryOccludedFilterFunction(self.handle(), Some(filter::occluded_filter));
+            } else {
+                rtcSetGeometryIntersectFilterFunction(self.handle(), None);
+                rtcSetGeometryOccludedFilterFunction(self.handle(), None);
+            }
+        }
+    }
+    /// Set which faces of the geometry intersection and occlusion queries
+    /// can hit, e.g. to only find where rays enter or exit closed meshes
+    /// for CSG or volume boundaries. Hits on the other faces are skipped
+    /// before calling the filter functions set on the geometry. The
+    /// geometry must be committed for this to take effect.
+    ///
+    /// The faces are culled by the wrapper's filter functions, so culling
+    /// and raw filter functions are mutually exclusive: setting a raw
+    /// filter function resets the mode to `HitFaceMode::Both`, and this
+    /// panics if a raw filter function is set and `mode` culls faces, see
+    /// `try_set_hit_face_mode`.
+    pub fn set_hit_face_mode(&mut self, mode: HitFaceMode) {
+        if self.try_set_hit_face_mode(mode).is_err() {
+            panic!("Faces can't be culled while a raw filter function is set");
+        }
+    }
+    /// Set which faces of the geometry queries can hit, see
+    /// `set_hit_face_mode`. Returns `Error::INVALID_OPERATION` and leaves
+    /// the geometry unchanged if `mode` culls faces and a raw filter
+    /// function is set. Raw filter functions are kept when setting
+    /// `HitFaceMode::Both`.
+    pub fn try_set_hit_face_mode(&mut self, mode: HitFaceMode) -> Result<(), Error> {
+        let handle = self.handle();
+        let data = self.data();
+        let culled = mode != HitFaceMode::Both;
+        let raw = data.raw_intersect_filter || data.raw_occluded_filter;
+        if culled && raw {
+            return Err(Error::INVALID_OPERATION);
+        }
+        mark_dirty(handle);
+        data.face_mode = mode;
+        let intersect =
+            culled || data.intersect_filter.is_some() || data.intersect_packet_filter.is_some();
+        let occluded =
+            culled || data.occluded_filter.is_some() || data.occluded_packet_filter.is_some();
+        unsafe {
+            if !data.raw_intersect_filter {
+                rtcSetGeometryIntersectFilterFunction(
+                    handle,
+                    if intersect {
+                        Some(filter::intersect_filter)
+                    } else {
+                        None
+                    },
+                );
+            }
+            if !data.raw_occluded_filter {
+                rtcSetGeometryOccludedFilterFunction(
+                    handle,
+                    if occluded {
+                        Some(filter::occluded_filter)
+                    } else {
+                        None
+                    },
+                );
+            }
+        }
+        Ok(())
+    }
+    pub fn hit_face_mode(&self) -> HitFaceMode {
+        unsafe { (*data_ptr(self.handle())).face_mode }
+    }
+    /// Get the Rust data attached to the geometry
+    pub(crate) fn data(&mut self) -> &mut GeometryData<'a> {
+        unsafe { &mut *data_ptr(self.handle()) }
     }
 }
 
 impl<'a> Drop for Geometry<'a> {
     fn drop(&mut self) {
         unsafe {
+            let data = rtcGetGeometryUserData(self.handle()) as *mut GeometryData<'a>;
+            if !data.is_null() {
+                // Buffers attached with `Buffer::attach_vertices` can keep
+                // the handle alive past the wrapper
+                rtcSetGeometryUserData(self.handle(), ptr::null_mut());
+                drop(Box::from_raw(data));
+            }
             rtcReleaseGeometry(self.handle());
         }
+        leak_check::released(ObjectKind::Geometry);
     }
 }
 
@@ -56,3 +1015,83 @@ impl<'a> PartialEq<Geometry<'a>> for Geometry<'a> {
 }
 
 impl<'a> Eq for Geometry<'a> {}
+
+/// Error returned when a geometry is accessed as a kind it doesn't hold
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct KindMismatch {
+    pub expected: GeometryKind,
+    pub found: GeometryKind,
+}
+
+impl fmt::Display for KindMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "expected a {:?} geometry but found a {:?} geometry",
+            self.expected, self.found
+        )
+    }
+}
+
+impl error::Error for KindMismatch {}
+
+/// Error returned when building a mesh from slices of user data
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MeshError {
+    /// A primitive references a vertex past the end of the vertex slice
+    IndexOutOfBounds {
+        primitive: usize,
+        index: u32,
+        num_verts: usize,
+    },
+}
+
+impl fmt::Display for MeshError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            MeshError::IndexOutOfBounds {
+                primitive,
+                index,
+                num_verts,
+            } => write!(
+                f,
+                "primitive {} references vertex {} but the mesh has {} vertices",
+                primitive, index, num_verts
+            ),
+        }
+    }
+}
+
+impl error::Error for MeshError {}
+
+/// Check that all the vertex indices of each primitive are in bounds
+pub(crate) fn validate_indices<I: AsRef<[u32]>>(
+    indices: &[I],
+    num_verts: usize,
+) -> Result<(), MeshError> {
+    for (primitive, prim) in indices.iter().enumerate() {
+        for &index in prim.as_ref() {
+            if index as usize >= num_verts {
+                return Err(MeshError::IndexOutOfBounds {
+                    primitive,
+                    index,
+                    num_verts,
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_validate_indices() {
+    assert_eq!(validate_indices(&[[0, 1, 2], [2, 1, 3]], 4), Ok(()));
+    assert_eq!(
+        validate_indices(&[[0, 1, 2], [2, 1, 4]], 4),
+        Err(MeshError::IndexOutOfBounds {
+            primitive: 1,
+            index: 4,
+            num_verts: 4,
+        })
+    );
+}
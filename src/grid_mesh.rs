@@ -0,0 +1,181 @@
+use cgmath::{Vector2, Vector3, Vector4};
+
+use buffer::Buffer;
+use device::Device;
+use geometry;
+use sys::*;
+use {BufferType, Format, GeometryType};
+
+/// A grid of vertices in the vertex buffer of a `GridMesh`, forming
+/// `(width - 1) * (height - 1)` quads. The vertex in column `x` and row
+/// `y` of the grid is `start_vertex_id + y * stride + x`.
+pub type Grid = RTCGrid;
+
+/// The most vertices along each side of the grids made by
+/// `GridMesh::from_heightfield`, larger heightfields are split into tiles
+/// which share their edge vertices
+const MAX_GRID_RES: usize = 256;
+
+impl Grid {
+    pub fn new(start_vertex_id: u32, stride: u32, width: u16, height: u16) -> Grid {
+        RTCGrid {
+            startVertexID: start_vertex_id,
+            stride,
+            width,
+            height,
+        }
+    }
+    /// Get the index of the vertex in column `x` and row `y` of the grid
+    pub fn vertex_id(&self, x: u32, y: u32) -> u32 {
+        self.startVertexID + y * self.stride + x
+    }
+    /// Get the index of the last vertex referenced by the grid
+    pub fn last_vertex_id(&self) -> u32 {
+        self.vertex_id(self.width as u32 - 1, self.height as u32 - 1)
+    }
+}
+
+/// A mesh of regular grids of vertices, a compact way to store
+/// heightfields and displaced surfaces. Each grid is a primitive, and the
+/// `u` and `v` of a hit are its coordinates across the grid it hit.
+pub struct GridMesh<'a> {
+    pub(crate) handle: RTCGeometry,
+    pub vertex_buffer: Buffer<'a, Vector4<f32>>,
+    pub grid_buffer: Buffer<'a, Grid>,
+}
+
+impl<'a> GridMesh<'a> {
+    /// The buffers which must be set before committing the geometry
+    pub const REQUIRED: &'static [BufferType] = &[BufferType::VERTEX, BufferType::GRID];
+    /// The buffers the geometry can optionally use
+    pub const OPTIONAL: &'static [BufferType] = &[BufferType::VERTEX_ATTRIBUTE];
+    pub fn unanimated(device: &'a Device, num_grids: usize, num_verts: usize) -> GridMesh<'a> {
+        let h = unsafe { geometry::new_handle(device, GeometryType::GRID) };
+        let mut vertex_buffer = Buffer::new(device, num_verts);
+        let mut grid_buffer = Buffer::new(device, num_grids);
+        unsafe {
+            rtcSetGeometryBuffer(
+                h,
+                BufferType::VERTEX,
+                0,
+                Format::FLOAT3,
+                vertex_buffer.handle,
+                0,
+                16,
+                num_verts,
+            );
+            vertex_buffer.set_attachment(h, BufferType::VERTEX, 0);
+
+            rtcSetGeometryBuffer(
+                h,
+                BufferType::GRID,
+                0,
+                Format::GRID,
+                grid_buffer.handle,
+                0,
+                ::std::mem::size_of::<Grid>(),
+                num_grids,
+            );
+            grid_buffer.set_attachment(h, BufferType::GRID, 0);
+        }
+        GridMesh {
+            handle: h,
+            vertex_buffer,
+            grid_buffer,
+        }
+    }
+    /// Create and commit a grid mesh for a heightfield of `width` by
+    /// `height` samples, given row by row in `heights`. The sample in
+    /// column `x` and row `y` is placed at `origin + (x * spacing.x,
+    /// heights[y * width + x], y * spacing.y)`, so the heightfield lies
+    /// in the xz plane with y up. Large heightfields are split into
+    /// several grids sharing their edge vertices.
+    ///
+    /// Panics if either side has fewer than two samples or `heights`
+    /// doesn't have `width * height` samples.
+    pub fn from_heightfield(
+        device: &'a Device,
+        heights: &[f32],
+        width: usize,
+        height: usize,
+        origin: Vector3<f32>,
+        spacing: Vector2<f32>,
+    ) -> GridMesh<'a> {
+        assert!(
+            width >= 2 && height >= 2,
+            "A heightfield needs at least 2x2 samples"
+        );
+        assert_eq!(
+            heights.len(),
+            width * height,
+            "The heightfield must have width * height samples"
+        );
+        let grids = heightfield_grids(width, height);
+        let mut mesh = GridMesh::unanimated(device, grids.len(), heights.len());
+        {
+            let mut verts = mesh.vertex_buffer.map();
+            for (i, h) in heights.iter().enumerate() {
+                let (x, y) = ((i % width) as f32, (i / width) as f32);
+                verts[i] = Vector4::new(
+                    origin.x + x * spacing.x,
+                    origin.y + h,
+                    origin.z + y * spacing.y,
+                    0.0,
+                );
+            }
+            let mut grid_buf = mesh.grid_buffer.map();
+            for (i, g) in grids.into_iter().enumerate() {
+                grid_buf[i] = g;
+            }
+        }
+        geometry::commit_handle(mesh.handle);
+        mesh
+    }
+    /// Get the number of quads formed by the grids in the grid buffer
+    pub fn quad_count(&self) -> usize {
+        self.grid_buffer
+            .as_slice()
+            .iter()
+            .map(|g| (g.width.max(1) as usize - 1) * (g.height.max(1) as usize - 1))
+            .sum()
+    }
+}
+
+/// Split a heightfield of `width` by `height` samples into grids of at
+/// most `MAX_GRID_RES` vertices per side, neighboring grids sharing the
+/// vertices on their common edge
+fn heightfield_grids(width: usize, height: usize) -> Vec<Grid> {
+    let mut grids = Vec::new();
+    let step = MAX_GRID_RES - 1;
+    for y in (0..height - 1).step_by(step) {
+        let h = (height - y).min(MAX_GRID_RES);
+        for x in (0..width - 1).step_by(step) {
+            let w = (width - x).min(MAX_GRID_RES);
+            grids.push(Grid::new(
+                (y * width + x) as u32,
+                width as u32,
+                w as u16,
+                h as u16,
+            ));
+        }
+    }
+    grids
+}
+
+unsafe impl<'a> Sync for GridMesh<'a> {}
+unsafe impl<'a> Send for GridMesh<'a> {}
+
+#[test]
+fn test_heightfield_grids() {
+    let grids = heightfield_grids(4, 3);
+    assert_eq!(grids.len(), 1);
+    assert_eq!((grids[0].width, grids[0].height), (4, 3));
+    assert_eq!(grids[0].last_vertex_id(), 11);
+
+    // 300 samples are split into grids of 256 and 45, sharing a column
+    let grids = heightfield_grids(300, 2);
+    assert_eq!(grids.len(), 2);
+    assert_eq!(grids[0].width, 256);
+    assert_eq!((grids[1].startVertexID, grids[1].width), (255, 45));
+    assert_eq!(grids[1].last_vertex_id(), 599);
+}
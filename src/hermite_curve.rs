@@ -2,13 +2,14 @@ use cgmath::{Vector2, Vector3, Vector4};
 
 use buffer::Buffer;
 use device::Device;
-use geometry::Geometry;
+use geometry::{self, Geometry};
 use sys::*;
 use {BufferType, CurveType, Format, GeometryType};
 
 pub struct HermiteCurve<'a> {
     device: &'a Device,
     pub(crate) handle: RTCGeometry,
+    pub(crate) curve_type: CurveType,
     pub vertex_buffer: Buffer<'a, Vector4<f32>>,
     pub index_buffer: Buffer<'a, u32>,
     pub tangent_buffer: Buffer<'a, Vector4<f32>>,
@@ -17,6 +18,15 @@ pub struct HermiteCurve<'a> {
 }
 
 impl<'a> HermiteCurve<'a> {
+    /// The buffers which must be set before committing the geometry
+    pub const REQUIRED: &'static [BufferType] =
+        &[BufferType::VERTEX, BufferType::INDEX, BufferType::TANGENT];
+    /// The buffers the geometry can optionally use
+    pub const OPTIONAL: &'static [BufferType] = &[
+        BufferType::NORMAL,
+        BufferType::NORMAL_DERIVATIVE,
+        BufferType::VERTEX_ATTRIBUTE,
+    ];
     pub fn flat(
         device: &'a Device,
         num_segments: usize,
@@ -70,13 +80,13 @@ impl<'a> HermiteCurve<'a> {
         match curve_type {
             CurveType::NormalOriented => {
                 h = unsafe {
-                    rtcNewGeometry(device.handle, GeometryType::NORMAL_ORIENTED_HERMITE_CURVE)
+                    geometry::new_handle(device, GeometryType::NORMAL_ORIENTED_HERMITE_CURVE)
                 }
             }
             CurveType::Round => {
-                h = unsafe { rtcNewGeometry(device.handle, GeometryType::ROUND_HERMITE_CURVE) }
+                h = unsafe { geometry::new_handle(device, GeometryType::ROUND_HERMITE_CURVE) }
             }
-            _ => h = unsafe { rtcNewGeometry(device.handle, GeometryType::FLAT_HERMITE_CURVE) },
+            _ => h = unsafe { geometry::new_handle(device, GeometryType::FLAT_HERMITE_CURVE) },
         };
         let mut vertex_buffer = Buffer::new(device, num_verts);
         let mut index_buffer = Buffer::new(device, num_segments);
@@ -154,6 +164,7 @@ impl<'a> HermiteCurve<'a> {
         HermiteCurve {
             device: device,
             handle: h,
+            curve_type,
             vertex_buffer: vertex_buffer,
             index_buffer: index_buffer,
             tangent_buffer: tangent_buffer,
@@ -164,3 +175,4 @@ impl<'a> HermiteCurve<'a> {
 }
 
 unsafe impl<'a> Sync for HermiteCurve<'a> {}
+unsafe impl<'a> Send for HermiteCurve<'a> {}
@@ -1,23 +1,142 @@
+//! Instances of a committed scene placed in another scene with a
+//! transform. A transform with a negative determinant mirrors the
+//! instanced scene, which reverses the winding of its triangles as seen in
+//! world space, so a normal computed from the transformed vertices points
+//! the opposite way from the object space normal Embree reports in the
+//! hit. `Instance::flips_orientation` detects this, and `transform_normal`
+//! and `Instance::normal_to_world` transform hit normals to world space so
+//! they stay on the same side of the surface as in object space, mirrored
+//! or not. A transform which isn't invertible, e.g. one scaling an axis to
+//! 0, flattens the instance and Embree can't transform rays into it, which
+//! `Instance::is_invertible` detects.
+//!
+//! Transforms can also be set as a `QuaternionDecomposition`, which Embree
+//! interpolates with a spherical rotation between motion blur time steps.
+
 use std::os::raw;
 
-use cgmath::Matrix4;
+use cgmath::{InnerSpace, Matrix4, Quaternion, SquareMatrix, Vector3};
 
 use device::Device;
-use geometry::Geometry;
+use geometry::{self, Geometry};
 use scene::{CommittedScene, Scene};
 use sys::*;
 use {BufferType, Format, GeometryType};
 
+/// A transform split into a scale, rotation and translation, applied in
+/// that order. The scale part can also skew and shift the object, e.g. to
+/// rotate it about a pivot point, see `set_shift`.
+pub type QuaternionDecomposition = RTCQuaternionDecomposition;
+
+impl QuaternionDecomposition {
+    /// Create the decomposition of scaling by `scale`, rotating by
+    /// `rotation` and translating by `translation`. The rotation is
+    /// normalized.
+    pub fn new(
+        scale: Vector3<f32>,
+        rotation: Quaternion<f32>,
+        translation: Vector3<f32>,
+    ) -> QuaternionDecomposition {
+        let q = rotation.normalize();
+        RTCQuaternionDecomposition {
+            scale_x: scale.x,
+            scale_y: scale.y,
+            scale_z: scale.z,
+            skew_xy: 0.0,
+            skew_xz: 0.0,
+            skew_yz: 0.0,
+            shift_x: 0.0,
+            shift_y: 0.0,
+            shift_z: 0.0,
+            quaternion_r: q.s,
+            quaternion_i: q.v.x,
+            quaternion_j: q.v.y,
+            quaternion_k: q.v.z,
+            translation_x: translation.x,
+            translation_y: translation.y,
+            translation_z: translation.z,
+        }
+    }
+    /// Set the shift applied after scaling and before rotating, e.g. the
+    /// negated pivot point, with the pivot added to the translation, to
+    /// rotate about the pivot
+    pub fn set_shift(&mut self, shift: Vector3<f32>) {
+        self.shift_x = shift.x;
+        self.shift_y = shift.y;
+        self.shift_z = shift.z;
+    }
+    pub fn rotation(&self) -> Quaternion<f32> {
+        Quaternion::new(
+            self.quaternion_r,
+            self.quaternion_i,
+            self.quaternion_j,
+            self.quaternion_k,
+        )
+    }
+    /// Get the transform as a matrix, the translation times the rotation
+    /// times the scale, skew and shift
+    pub fn to_matrix(&self) -> Matrix4<f32> {
+        let scale = Matrix4::new(
+            self.scale_x,
+            0.0,
+            0.0,
+            0.0,
+            self.skew_xy,
+            self.scale_y,
+            0.0,
+            0.0,
+            self.skew_xz,
+            self.skew_yz,
+            self.scale_z,
+            0.0,
+            self.shift_x,
+            self.shift_y,
+            self.shift_z,
+            1.0,
+        );
+        let translation = Matrix4::from_translation(Vector3::new(
+            self.translation_x,
+            self.translation_y,
+            self.translation_z,
+        ));
+        translation * Matrix4::from(self.rotation()) * scale
+    }
+}
+
 pub struct Instance<'a> {
     device: &'a Device,
     pub(crate) handle: RTCGeometry,
     /// The scene being instanced
-    scene: &'a CommittedScene<'a>,
+    pub(crate) scene: &'a CommittedScene<'a>,
+    transform: Matrix4<f32>,
+}
+
+/// Transform the object space normal `n` to world space by `transform`,
+/// using its inverse transpose so the normal stays on the same side of the
+/// surface when the transform mirrors it. The result isn't normalized.
+/// Transforms which aren't invertible are handled by using the cofactor
+/// matrix, though the normal of a flattened axis is lost.
+pub fn transform_normal(transform: &Matrix4<f32>, n: Vector3<f32>) -> Vector3<f32> {
+    let a = transform.x.truncate();
+    let b = transform.y.truncate();
+    let c = transform.z.truncate();
+    // The cofactor matrix is the inverse transpose scaled by the
+    // determinant, so undo the flip a negative determinant would cause
+    let cof = b.cross(c) * n.x + c.cross(a) * n.y + a.cross(b) * n.z;
+    if a.dot(b.cross(c)) < 0.0 {
+        -cof
+    } else {
+        cof
+    }
 }
 
 impl<'a> Instance<'a> {
+    /// The buffers which must be set before committing the geometry
+    pub const REQUIRED: &'static [BufferType] = &[];
+    /// The buffers the geometry can optionally use
+    pub const OPTIONAL: &'static [BufferType] = &[];
     pub fn unanimated(device: &'a Device, scene: &'a CommittedScene) -> Instance<'a> {
-        let h = unsafe { rtcNewGeometry(device.handle, GeometryType::INSTANCE) };
+        let h = unsafe { geometry::new_handle(device, GeometryType::INSTANCE) };
         unsafe {
             rtcSetGeometryInstancedScene(h, scene.scene.handle);
         }
@@ -25,9 +144,13 @@ impl<'a> Instance<'a> {
             device: device,
             handle: h,
             scene: scene,
+            transform: Matrix4::identity(),
         }
     }
+    /// Set the transform of the instance. Check `flips_orientation` and
+    /// `is_invertible` afterwards to find mirrored or flattened instances.
     pub fn set_transform(&mut self, transform: &Matrix4<f32>) {
+        self.transform = *transform;
         let mat: &[f32; 16] = transform.as_ref();
         // Will this be fine if we don't set the number of timesteps? Default should be 1?
         unsafe {
@@ -38,7 +161,87 @@ impl<'a> Instance<'a> {
                 mat.as_ptr() as *const raw::c_void,
             );
         }
+        geometry::mark_dirty(self.handle);
+    }
+    /// Set the transform of the instance from its decomposition, which
+    /// Embree interpolates with a spherical rotation for motion blur.
+    /// `transform` returns the decomposition as a matrix.
+    pub fn set_quaternion_decomposition(&mut self, qd: &QuaternionDecomposition) {
+        self.transform = qd.to_matrix();
+        unsafe {
+            rtcSetGeometryTransformQuaternion(self.handle, 0, qd as *const _);
+        }
+        geometry::mark_dirty(self.handle);
+    }
+    pub fn transform(&self) -> &Matrix4<f32> {
+        &self.transform
+    }
+    /// Whether the transform mirrors the instanced scene, reversing the
+    /// winding of its triangles in world space
+    pub fn flips_orientation(&self) -> bool {
+        self.transform.determinant() < 0.0
+    }
+    /// Whether the transform can be inverted, Embree can't transform rays
+    /// into an instance whose transform can't be and won't find hits on it
+    pub fn is_invertible(&self) -> bool {
+        self.transform.determinant().abs() > f32::EPSILON
+    }
+    /// Transform the object space normal of a hit on the instance, such as
+    /// `Hit::normal`, to a normalized world space normal, see
+    /// `transform_normal`
+    pub fn normal_to_world(&self, n: Vector3<f32>) -> Vector3<f32> {
+        transform_normal(&self.transform, n).normalize()
     }
 }
 
 unsafe impl<'a> Sync for Instance<'a> {}
+unsafe impl<'a> Send for Instance<'a> {}
+
+#[test]
+fn test_transform_normal() {
+    use cgmath::Rad;
+
+    let n = Vector3::new(0.0, 0.0, 1.0);
+    // Mirroring across the normal's plane keeps it on the same side of the
+    // surface, even though the transformed winding is reversed
+    let mirror = Matrix4::from_nonuniform_scale(-1.0, 1.0, 1.0);
+    assert_eq!(transform_normal(&mirror, n), n);
+    // Mirroring along the normal flips it with the surface
+    let mirror = Matrix4::from_nonuniform_scale(1.0, 1.0, -1.0);
+    assert_eq!(transform_normal(&mirror, n), -n);
+
+    // A non-uniform scale uses the inverse transpose
+    let scale = Matrix4::from_nonuniform_scale(1.0, 4.0, 1.0);
+    let t = transform_normal(&scale, Vector3::new(1.0, 1.0, 0.0)).normalize();
+    assert!((t - Vector3::new(4.0, 1.0, 0.0).normalize()).magnitude() < 1e-6);
+
+    let rot = Matrix4::from_angle_x(Rad(std::f32::consts::FRAC_PI_2));
+    let t = transform_normal(&rot, n);
+    assert!((t - Vector3::new(0.0, -1.0, 0.0)).magnitude() < 1e-6);
+
+    // Flattening along the normal keeps the surface and its normal, while
+    // flattening across it collapses the surface to a line
+    let flat = Matrix4::from_nonuniform_scale(1.0, 1.0, 0.0);
+    assert_eq!(transform_normal(&flat, n).normalize(), n);
+    let flat = Matrix4::from_nonuniform_scale(0.0, 1.0, 1.0);
+    assert_eq!(transform_normal(&flat, n), Vector3::new(0.0, 0.0, 0.0));
+}
+
+#[test]
+fn test_quaternion_decomposition() {
+    use cgmath::{Deg, Rotation3, Vector4};
+
+    let rot = Quaternion::from_angle_z(Deg(90.0));
+    let mut qd = QuaternionDecomposition::new(
+        Vector3::new(2.0, 1.0, 1.0),
+        rot,
+        Vector3::new(0.0, 0.0, 5.0),
+    );
+    let p = qd.to_matrix() * Vector4::new(1.0, 0.0, 0.0, 1.0);
+    assert!((p - Vector4::new(0.0, 2.0, 5.0, 1.0)).magnitude() < 1e-6);
+
+    // The shift moves the scaled object before it's rotated
+    qd.set_shift(Vector3::new(-1.0, 0.0, 0.0));
+    let p = qd.to_matrix() * Vector4::new(1.0, 0.0, 0.0, 1.0);
+    assert!((p - Vector4::new(0.0, 1.0, 5.0, 1.0)).magnitude() < 1e-6);
+}
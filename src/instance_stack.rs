@@ -0,0 +1,232 @@
+//! The stack of instance IDs a hit was found through, as stored in the
+//! `instID` arrays of hits, point queries and intersection contexts. Level
+//! 0 holds the ID of the outermost instance, attached to the scene being
+//! traced, and each following level the ID of the instance inside the
+//! previous one's scene, with the first `INVALID_ID` ending the stack. A
+//! hit on geometry attached directly to the scene has an empty stack.
+//!
+//! The IDs after the end of the stack aren't meaningful, so `InstanceStack`
+//! ignores them when comparing and hashing stacks, which makes it usable
+//! as part of a hit's identity, e.g. with the hit's `geomID` and `primID`
+//! as the key of a cache of shading results. `key` packs the stack into
+//! a `u64` for compact keys.
+//!
+//! # Seeding Contexts for Object Space Rays
+//!
+//! Embree fills in the instance stack of a context as traversal enters
+//! instances, and copies it to the hits found. A renderer shading in object
+//! space may spawn secondary rays from a hit directly against the scene of
+//! the innermost instance, with the ray in that scene's space, instead of
+//! transforming it back to world space and tracing the whole scene. The
+//! context for such a ray should start with the instance stack of the hit,
+//! so the hits found and the filters called see the same instance IDs as
+//! they would for a ray traced from the top level scene. The context is
+//! seeded with `IntersectContext::with_instance_stack`:
+//!
+//! ```ignore
+//! // A hit through an instance `outer` of a scene holding an instance
+//! // `inner` of the scene `leaf`, with the stack [outer, inner]
+//! let hit = world.intersect_ray(&ray).unwrap();
+//! let to_leaf = (outer_transform * inner_transform).invert().unwrap();
+//! let bounce = Ray::new(
+//!     (to_leaf * hit_point.extend(1.0)).truncate(),
+//!     (to_leaf * dir.extend(0.0)).truncate(),
+//! );
+//! let mut ctx = IntersectContext::with_instance_stack(&hit.hit);
+//! let mut bounce_hit = RayHit::new(bounce);
+//! leaf.intersect(&mut ctx, &mut bounce_hit);
+//! // The hit reports the stack [outer, inner], like hits from the world
+//! assert_eq!(bounce_hit.hit.instance_stack(), hit.hit.instance_stack());
+//! ```
+//!
+//! A seeded context must only be used to trace the scene of the innermost
+//! instance of its stack: tracing a scene which holds instances would push
+//! their IDs past the seeded ones, beyond the levels Embree supports when
+//! the stack is full.
+
+use std::hash::{Hash, Hasher};
+
+use ray::{Hit, IntersectContext};
+use sys;
+
+/// The number of instance levels Embree was built to support
+pub const MAX_INSTANCE_LEVELS: usize = sys::RTC_MAX_INSTANCE_LEVEL_COUNT as usize;
+/// The ID ending an instance stack, also used by Embree for "no geometry"
+pub const INVALID_ID: u32 = u32::MAX;
+
+/// A stack of instance IDs, see the module documentation
+#[derive(Debug, Copy, Clone)]
+#[repr(transparent)]
+pub struct InstanceStack(pub [u32; MAX_INSTANCE_LEVELS]);
+
+impl InstanceStack {
+    /// Create an empty stack, for hits which weren't found through an
+    /// instance
+    pub fn empty() -> InstanceStack {
+        InstanceStack([INVALID_ID; MAX_INSTANCE_LEVELS])
+    }
+    /// Create a stack of the instance IDs, from the outermost instance in
+    ///
+    /// Panics if there are more IDs than `MAX_INSTANCE_LEVELS` or one of
+    /// them is `INVALID_ID`.
+    pub fn from_ids(ids: &[u32]) -> InstanceStack {
+        assert!(
+            ids.len() <= MAX_INSTANCE_LEVELS,
+            "{} instance levels is more than the {} supported",
+            ids.len(),
+            MAX_INSTANCE_LEVELS
+        );
+        assert!(
+            !ids.contains(&INVALID_ID),
+            "Instance stacks can't hold INVALID_ID"
+        );
+        let mut stack = InstanceStack::empty();
+        stack.0[..ids.len()].copy_from_slice(ids);
+        stack
+    }
+    /// Get the instance stack of the hit
+    pub fn from_hit(hit: &Hit) -> InstanceStack {
+        InstanceStack(hit.instID)
+    }
+    /// Get the number of instances in the stack
+    pub fn depth(&self) -> usize {
+        self.0
+            .iter()
+            .position(|id| *id == INVALID_ID)
+            .unwrap_or(MAX_INSTANCE_LEVELS)
+    }
+    pub fn is_empty(&self) -> bool {
+        self.depth() == 0
+    }
+    /// Get the IDs in the stack, from the outermost instance in
+    pub fn ids(&self) -> &[u32] {
+        &self.0[..self.depth()]
+    }
+    /// Iterate over the IDs in the stack, from the outermost instance in
+    pub fn iter(&self) -> impl Iterator<Item = u32> + '_ {
+        self.ids().iter().cloned()
+    }
+    /// Get the ID of the innermost instance, the one whose scene holds the
+    /// hit geometry
+    pub fn innermost(&self) -> Option<u32> {
+        self.ids().last().cloned()
+    }
+    /// Pack the stack into a `u64`, e.g. to combine with a geometry and
+    /// primitive ID in a compact hit key. Stacks compare equal exactly when
+    /// their keys do if Embree supports up to two instance levels, deeper
+    /// stacks are hashed into the key so distinct stacks may collide.
+    pub fn key(&self) -> u64 {
+        if MAX_INSTANCE_LEVELS <= 2 {
+            let mut key = u64::MAX;
+            for (i, id) in self.iter().enumerate() {
+                key &= !(0xffff_ffffu64 << (32 * i));
+                key |= u64::from(id) << (32 * i);
+            }
+            key
+        } else {
+            // 64-bit FNV-1a over the IDs
+            let mut h = 0xcbf2_9ce4_8422_2325u64;
+            for id in self.iter() {
+                for b in id.to_le_bytes().iter() {
+                    h ^= u64::from(*b);
+                    h = h.wrapping_mul(0x0100_0000_01b3);
+                }
+            }
+            h
+        }
+    }
+}
+
+impl Default for InstanceStack {
+    fn default() -> InstanceStack {
+        InstanceStack::empty()
+    }
+}
+
+impl PartialEq for InstanceStack {
+    fn eq(&self, other: &InstanceStack) -> bool {
+        self.ids() == other.ids()
+    }
+}
+
+impl Eq for InstanceStack {}
+
+impl Hash for InstanceStack {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.ids().hash(state);
+    }
+}
+
+impl From<InstanceStack> for u64 {
+    fn from(stack: InstanceStack) -> u64 {
+        stack.key()
+    }
+}
+
+impl Hit {
+    /// Get the stack of instances the hit was found through
+    pub fn instance_stack(&self) -> InstanceStack {
+        InstanceStack::from_hit(self)
+    }
+}
+
+impl IntersectContext {
+    /// Create a context for an incoherent secondary ray spawned from the
+    /// hit, with its instance stack seeded from the hit's. See the module
+    /// documentation for the scene the ray must be traced against.
+    pub fn with_instance_stack(hit: &Hit) -> IntersectContext {
+        let mut ctx = IntersectContext::incoherent();
+        ctx.set_instance_stack(hit.instance_stack());
+        ctx
+    }
+    /// Set the instance stack the context starts traversal with
+    pub fn set_instance_stack(&mut self, stack: InstanceStack) {
+        self.instID = stack.0;
+    }
+    pub fn instance_stack(&self) -> InstanceStack {
+        InstanceStack(self.instID)
+    }
+}
+
+#[test]
+fn test_instance_stack() {
+    use std::collections::HashSet;
+
+    let empty = InstanceStack::empty();
+    assert!(empty.is_empty());
+    assert_eq!(empty.iter().count(), 0);
+    assert_eq!(empty.innermost(), None);
+    assert_eq!(empty.key(), u64::MAX);
+
+    let stack = InstanceStack::from_ids(&[3]);
+    assert_eq!(stack.depth(), 1);
+    assert_eq!(stack.ids(), &[3]);
+    assert_eq!(stack.innermost(), Some(3));
+    assert_ne!(stack, empty);
+    assert_ne!(stack.key(), empty.key());
+    assert_eq!(u64::from(stack), stack.key());
+
+    let mut hit = Hit::new();
+    assert_eq!(hit.instance_stack(), empty);
+    hit.instID[0] = 3;
+    assert_eq!(hit.instance_stack(), stack);
+
+    let set: HashSet<InstanceStack> = [stack, stack, empty].iter().cloned().collect();
+    assert_eq!(set.len(), 2);
+}
+
+#[test]
+fn test_seed_context() {
+    let mut hit = Hit::new();
+    hit.instID[0] = 5;
+    let ctx = IntersectContext::with_instance_stack(&hit);
+    assert_eq!(ctx.instance_stack(), InstanceStack::from_ids(&[5]));
+    assert_eq!(ctx.flags, sys::RTCIntersectContextFlags::INCOHERENT);
+    assert!(IntersectContext::coherent().instance_stack().is_empty());
+}
+
+#[test]
+#[should_panic]
+fn test_instance_stack_too_deep() {
+    InstanceStack::from_ids(&[0; MAX_INSTANCE_LEVELS + 1]);
+}
@@ -0,0 +1,137 @@
+//! Binding a slice of interleaved vertex structs, e.g.
+//! `struct Vertex { pos: [f32; 3], n: [f32; 3], uv: [f32; 2] }`, to several
+//! buffer slots of a geometry at once without copying it. Each member is
+//! bound by its offset in the struct, which can be found with
+//! `std::mem::offset_of!`, and the struct size is used as the stride:
+//!
+//! ```ignore
+//! geom.bind_interleaved(&vertices, count)
+//!     .bind(BufferType::VERTEX, 0, Format::FLOAT3, offset_of!(Vertex, pos))
+//!     .bind(BufferType::VERTEX_ATTRIBUTE, 0, Format::FLOAT3, offset_of!(Vertex, n))
+//!     .bind(BufferType::VERTEX_ATTRIBUTE, 1, Format::FLOAT2, offset_of!(Vertex, uv));
+//! ```
+
+use std::marker::PhantomData;
+use std::mem;
+use std::os::raw;
+
+use geometry::{self, Geometry, GeometryKind};
+use sys::*;
+use {BufferType, Error, Format};
+
+/// Builder binding the members of an interleaved slice of `T` to buffer
+/// slots of a geometry, returned by `Geometry::bind_interleaved`. The
+/// slice stays borrowed for as long as the geometry lives.
+pub struct InterleavedBinding<'g, 'a: 'g, T: 'a> {
+    geometry: PhantomData<&'g mut Geometry<'a>>,
+    handle: RTCGeometry,
+    kind: GeometryKind,
+    data: &'a [T],
+    count: usize,
+    attribute_count: u32,
+}
+
+impl<'g, 'a: 'g, T: Copy + 'a> InterleavedBinding<'g, 'a, T> {
+    pub(crate) fn new(
+        handle: RTCGeometry,
+        kind: GeometryKind,
+        data: &'a [T],
+        count: usize,
+    ) -> InterleavedBinding<'g, 'a, T> {
+        assert!(
+            count <= data.len(),
+            "Interleaved buffer of {} elements is too short for {} elements",
+            data.len(),
+            count
+        );
+        assert!(
+            mem::align_of::<T>() >= 4,
+            "Interleaved buffer elements must be 4 byte aligned"
+        );
+        InterleavedBinding {
+            geometry: PhantomData,
+            handle,
+            kind,
+            data,
+            count,
+            attribute_count: 0,
+        }
+    }
+    /// Bind the member at `offset` bytes into `T`, stored in `format`, to
+    /// the buffer `slot`. Binding a vertex attribute slot increases the
+    /// geometry's vertex attribute count to include it.
+    ///
+    /// Embree reads the last element of vertex and vertex attribute
+    /// buffers with 16 byte loads, members near the end of `T` need the
+    /// slice to hold a padding element after the first `count` for this.
+    ///
+    /// Panics if the format isn't valid for the buffer type of the
+    /// geometry's kind, see `format::valid_formats`, or if the member
+    /// doesn't fit in `T`, isn't 4 byte aligned, or the slice lacks the
+    /// padding required for the last element.
+    pub fn bind(self, buf_type: BufferType, slot: u32, format: Format, offset: usize) -> Self {
+        let kind = self.kind;
+        match self.try_bind(buf_type, slot, format, offset) {
+            Ok(binding) => binding,
+            Err(_) => panic!(
+                "{:?} isn't a valid format for the {:?} buffer of {:?} geometry",
+                format, buf_type, kind
+            ),
+        }
+    }
+    /// Bind the member at `offset` bytes into `T`, see `bind`. Returns
+    /// `Error::INVALID_OPERATION` without binding the member if the format
+    /// isn't valid for the buffer type of the geometry's kind, the other
+    /// checks of `bind` still panic as they're errors in the layout of `T`.
+    pub fn try_bind(
+        mut self,
+        buf_type: BufferType,
+        slot: u32,
+        format: Format,
+        offset: usize,
+    ) -> Result<Self, Error> {
+        if !format.is_valid_for(buf_type, self.kind) {
+            return Err(Error::INVALID_OPERATION);
+        }
+        let stride = mem::size_of::<T>();
+        let size = format
+            .size_in_bytes()
+            .expect("Interleaved members must have a defined format");
+        assert!(
+            offset + size <= stride,
+            "Member at offset {} of {} bytes doesn't fit in the {} byte element",
+            offset,
+            size,
+            stride
+        );
+        assert_eq!(offset % 4, 0, "Member offset must be 4 byte aligned");
+        let is_vertex = buf_type == BufferType::VERTEX || buf_type == BufferType::VERTEX_ATTRIBUTE;
+        if is_vertex && self.count > 0 {
+            let last_read = (self.count - 1) * stride + offset + size.max(16);
+            assert!(
+                last_read <= mem::size_of_val(self.data),
+                "Interleaved buffer needs a padding element for the 16 byte read of member at offset {}",
+                offset
+            );
+        }
+        unsafe {
+            if buf_type == BufferType::VERTEX_ATTRIBUTE && slot >= self.attribute_count {
+                self.attribute_count = slot + 1;
+                rtcSetGeometryVertexAttributeCount(self.handle, self.attribute_count);
+            }
+            rtcSetSharedGeometryBuffer(
+                self.handle,
+                buf_type,
+                slot,
+                format,
+                self.data.as_ptr() as *const raw::c_void,
+                offset,
+                stride,
+                self.count,
+            );
+        }
+        geometry::set_shared_count(self.handle, buf_type, slot, self.count);
+        geometry::mark_dirty(self.handle);
+        Ok(self)
+    }
+}
@@ -0,0 +1,427 @@
+//! Conversions between the ray and hit types and other math libraries.
+//!
+//! The `mint` feature adds conversions to and from the
+//! [mint](https://crates.io/crates/mint) math interop types. Any math
+//! library which converts to and from mint (e.g. glam, nalgebra or
+//! ultraviolet) can then be used to build rays and read back hits without
+//! this crate depending on it directly. The feature also enables cgmath's
+//! mint support, so the accessors which return cgmath vectors
+//! (e.g. `SoAHitRef::normal`) can be converted to mint types with `.into()`.
+//!
+//! The `interop-glam`, `interop-nalgebra` and `interop-cgmath` features
+//! instead implement the `InteropVec3`, `InteropMat4` and `InteropQuat`
+//! traits for the library's vector, point, matrix and quaternion types,
+//! so they can be passed to and read back from rays, bounds, instance
+//! transforms and quaternion decompositions directly, e.g.
+//! `Ray::from_interop(glam::Vec3::ZERO, glam::Vec3::Z)` or
+//! `instance.set_transform_from(glam::Mat4::from_scale(s))`. The cgmath
+//! types are supported with any of these features.
+
+use cgmath::{Matrix4, Point3, Quaternion, Vector3};
+#[cfg(feature = "mint")]
+use mint;
+
+use instance::{Instance, QuaternionDecomposition};
+use ray::{Hit, Ray};
+#[cfg(feature = "mint")]
+#[cfg(feature = "packets")]
+use ray_packet::Ray4;
+use Bounds;
+
+/// A 3 component `f32` vector or point of a math library
+pub trait InteropVec3: Copy {
+    fn to_cgmath(self) -> Vector3<f32>;
+    fn from_cgmath(v: Vector3<f32>) -> Self;
+}
+
+/// A 4x4 `f32` transform matrix of a math library
+pub trait InteropMat4: Copy {
+    fn to_cgmath(self) -> Matrix4<f32>;
+    fn from_cgmath(m: Matrix4<f32>) -> Self;
+}
+
+/// An `f32` rotation quaternion of a math library
+pub trait InteropQuat: Copy {
+    fn to_cgmath(self) -> Quaternion<f32>;
+    fn from_cgmath(q: Quaternion<f32>) -> Self;
+}
+
+impl InteropVec3 for Vector3<f32> {
+    fn to_cgmath(self) -> Vector3<f32> {
+        self
+    }
+    fn from_cgmath(v: Vector3<f32>) -> Self {
+        v
+    }
+}
+
+impl InteropVec3 for Point3<f32> {
+    fn to_cgmath(self) -> Vector3<f32> {
+        Vector3::new(self.x, self.y, self.z)
+    }
+    fn from_cgmath(v: Vector3<f32>) -> Self {
+        Point3::new(v.x, v.y, v.z)
+    }
+}
+
+impl InteropMat4 for Matrix4<f32> {
+    fn to_cgmath(self) -> Matrix4<f32> {
+        self
+    }
+    fn from_cgmath(m: Matrix4<f32>) -> Self {
+        m
+    }
+}
+
+impl InteropQuat for Quaternion<f32> {
+    fn to_cgmath(self) -> Quaternion<f32> {
+        self
+    }
+    fn from_cgmath(q: Quaternion<f32>) -> Self {
+        q
+    }
+}
+
+#[cfg(feature = "interop-glam")]
+mod glam_impls {
+    use cgmath::{Matrix4, Quaternion, Vector3};
+    use glam::{Mat4, Quat, Vec3, Vec3A};
+
+    use super::{InteropMat4, InteropQuat, InteropVec3};
+
+    impl InteropVec3 for Vec3 {
+        fn to_cgmath(self) -> Vector3<f32> {
+            Vector3::new(self.x, self.y, self.z)
+        }
+        fn from_cgmath(v: Vector3<f32>) -> Self {
+            Vec3::new(v.x, v.y, v.z)
+        }
+    }
+
+    impl InteropVec3 for Vec3A {
+        fn to_cgmath(self) -> Vector3<f32> {
+            Vector3::new(self.x, self.y, self.z)
+        }
+        fn from_cgmath(v: Vector3<f32>) -> Self {
+            Vec3A::new(v.x, v.y, v.z)
+        }
+    }
+
+    impl InteropMat4 for Mat4 {
+        fn to_cgmath(self) -> Matrix4<f32> {
+            Matrix4::from(self.to_cols_array_2d())
+        }
+        fn from_cgmath(m: Matrix4<f32>) -> Self {
+            Mat4::from_cols_array_2d(&m.into())
+        }
+    }
+
+    impl InteropQuat for Quat {
+        fn to_cgmath(self) -> Quaternion<f32> {
+            Quaternion::new(self.w, self.x, self.y, self.z)
+        }
+        fn from_cgmath(q: Quaternion<f32>) -> Self {
+            Quat::from_xyzw(q.v.x, q.v.y, q.v.z, q.s)
+        }
+    }
+}
+
+#[cfg(feature = "interop-nalgebra")]
+mod nalgebra_impls {
+    use cgmath::{Matrix4, Quaternion, Vector3};
+    use nalgebra;
+
+    use super::{InteropMat4, InteropQuat, InteropVec3};
+
+    impl InteropVec3 for nalgebra::Vector3<f32> {
+        fn to_cgmath(self) -> Vector3<f32> {
+            Vector3::new(self.x, self.y, self.z)
+        }
+        fn from_cgmath(v: Vector3<f32>) -> Self {
+            nalgebra::Vector3::new(v.x, v.y, v.z)
+        }
+    }
+
+    impl InteropVec3 for nalgebra::Point3<f32> {
+        fn to_cgmath(self) -> Vector3<f32> {
+            Vector3::new(self.x, self.y, self.z)
+        }
+        fn from_cgmath(v: Vector3<f32>) -> Self {
+            nalgebra::Point3::new(v.x, v.y, v.z)
+        }
+    }
+
+    impl InteropMat4 for nalgebra::Matrix4<f32> {
+        fn to_cgmath(self) -> Matrix4<f32> {
+            let mut cols = [[0.0; 4]; 4];
+            for (i, c) in self.column_iter().enumerate() {
+                cols[i] = [c[0], c[1], c[2], c[3]];
+            }
+            Matrix4::from(cols)
+        }
+        fn from_cgmath(m: Matrix4<f32>) -> Self {
+            // Both are stored column major
+            let cols: &[f32; 16] = m.as_ref();
+            nalgebra::Matrix4::from_column_slice(cols)
+        }
+    }
+
+    /// Unit quaternions are converted as is, and normalized when converted
+    /// back
+    impl InteropQuat for nalgebra::UnitQuaternion<f32> {
+        fn to_cgmath(self) -> Quaternion<f32> {
+            Quaternion::new(self.w, self.i, self.j, self.k)
+        }
+        fn from_cgmath(q: Quaternion<f32>) -> Self {
+            nalgebra::UnitQuaternion::from_quaternion(nalgebra::Quaternion::new(
+                q.s, q.v.x, q.v.y, q.v.z,
+            ))
+        }
+    }
+}
+
+impl Ray {
+    /// Create a new ray starting at `origin` and heading in direction `dir`
+    pub fn from_interop<O: InteropVec3, D: InteropVec3>(origin: O, dir: D) -> Ray {
+        Ray::new(origin.to_cgmath(), dir.to_cgmath())
+    }
+    /// Get the origin of the ray as a vector or point type of a math library
+    pub fn origin_as<V: InteropVec3>(&self) -> V {
+        V::from_cgmath(self.origin())
+    }
+    /// Get the direction of the ray as a vector type of a math library
+    pub fn dir_as<V: InteropVec3>(&self) -> V {
+        V::from_cgmath(self.dir())
+    }
+}
+
+impl Hit {
+    /// Get the unnormalized geometric normal of the hit as a vector type of
+    /// a math library
+    pub fn normal_as<V: InteropVec3>(&self) -> V {
+        V::from_cgmath(self.normal())
+    }
+}
+
+impl Bounds {
+    /// Create bounds from their lower and upper corners
+    pub fn from_interop<V: InteropVec3>(lower: V, upper: V) -> Bounds {
+        let (lower, upper) = (lower.to_cgmath(), upper.to_cgmath());
+        Bounds {
+            lower_x: lower.x,
+            lower_y: lower.y,
+            lower_z: lower.z,
+            align0: 0.0,
+            upper_x: upper.x,
+            upper_y: upper.y,
+            upper_z: upper.z,
+            align1: 0.0,
+        }
+    }
+    /// Get the lower corner of the bounds as a vector or point type of a
+    /// math library
+    pub fn lower_as<V: InteropVec3>(&self) -> V {
+        V::from_cgmath(Vector3::new(self.lower_x, self.lower_y, self.lower_z))
+    }
+    /// Get the upper corner of the bounds as a vector or point type of a
+    /// math library
+    pub fn upper_as<V: InteropVec3>(&self) -> V {
+        V::from_cgmath(Vector3::new(self.upper_x, self.upper_y, self.upper_z))
+    }
+}
+
+impl<'a> Instance<'a> {
+    /// Set the transform of the instance from a matrix type of a math
+    /// library, see `set_transform`
+    pub fn set_transform_from<M: InteropMat4>(&mut self, transform: M) {
+        self.set_transform(&transform.to_cgmath());
+    }
+    /// Get the transform of the instance as a matrix type of a math library
+    pub fn transform_as<M: InteropMat4>(&self) -> M {
+        M::from_cgmath(*self.transform())
+    }
+}
+
+impl QuaternionDecomposition {
+    /// Create the decomposition of scaling by `scale`, rotating by
+    /// `rotation` and translating by `translation`, given as types of a
+    /// math library, see `QuaternionDecomposition::new`
+    pub fn from_interop<V: InteropVec3, Q: InteropQuat>(
+        scale: V,
+        rotation: Q,
+        translation: V,
+    ) -> QuaternionDecomposition {
+        QuaternionDecomposition::new(
+            scale.to_cgmath(),
+            rotation.to_cgmath(),
+            translation.to_cgmath(),
+        )
+    }
+    /// Get the rotation as a quaternion type of a math library
+    pub fn rotation_as<Q: InteropQuat>(&self) -> Q {
+        Q::from_cgmath(self.rotation())
+    }
+}
+
+#[cfg(feature = "mint")]
+fn vec_from_point(p: mint::Point3<f32>) -> Vector3<f32> {
+    Vector3::new(p.x, p.y, p.z)
+}
+
+#[cfg(feature = "mint")]
+impl Ray {
+    /// Create a new ray starting at `origin` and heading in direction `dir`
+    pub fn from_mint<O, D>(origin: O, dir: D) -> Ray
+    where
+        O: Into<mint::Point3<f32>>,
+        D: Into<mint::Vector3<f32>>,
+    {
+        Ray::new(vec_from_point(origin.into()), dir.into().into())
+    }
+    /// Create a new ray segment spanning `[tnear, tfar]` along the ray
+    pub fn segment_from_mint<O, D>(origin: O, dir: D, tnear: f32, tfar: f32) -> Ray
+    where
+        O: Into<mint::Point3<f32>>,
+        D: Into<mint::Vector3<f32>>,
+    {
+        Ray::segment(
+            vec_from_point(origin.into()),
+            dir.into().into(),
+            tnear,
+            tfar,
+        )
+    }
+    /// Get the origin of the ray as a mint point
+    pub fn mint_origin(&self) -> mint::Point3<f32> {
+        mint::Point3 {
+            x: self.org_x,
+            y: self.org_y,
+            z: self.org_z,
+        }
+    }
+    /// Get the direction of the ray as a mint vector
+    pub fn mint_dir(&self) -> mint::Vector3<f32> {
+        self.dir().into()
+    }
+}
+
+#[cfg(feature = "mint")]
+#[cfg(feature = "packets")]
+impl Ray4 {
+    /// Create a new ray packet with the origins and directions passed
+    pub fn from_mint<O, D>(origin: [O; 4], dir: [D; 4]) -> Ray4
+    where
+        O: Into<mint::Point3<f32>> + Copy,
+        D: Into<mint::Vector3<f32>> + Copy,
+    {
+        let mut org = [Vector3::new(0.0, 0.0, 0.0); 4];
+        let mut d = [Vector3::new(0.0, 0.0, 0.0); 4];
+        for i in 0..4 {
+            org[i] = vec_from_point(origin[i].into());
+            d[i] = dir[i].into().into();
+        }
+        Ray4::new(org, d)
+    }
+}
+
+#[cfg(feature = "mint")]
+impl Hit {
+    /// Get the unnormalized geometric normal of the hit as a mint vector
+    pub fn mint_normal(&self) -> mint::Vector3<f32> {
+        self.normal().into()
+    }
+}
+
+#[cfg(feature = "mint")]
+#[test]
+fn test_mint_interop() {
+    let origin = mint::Point3::from([1.0, 2.0, 3.0]);
+    let dir = mint::Vector3::from([0.0, 0.0, -1.0]);
+    let ray = Ray::from_mint(origin, dir);
+    assert_eq!(ray.mint_origin(), origin);
+    assert_eq!(ray.mint_dir(), dir);
+    assert_eq!((ray.tnear, ray.tfar), (0.0, f32::INFINITY));
+
+    let ray = Ray::segment_from_mint([1.0, 2.0, 3.0], [0.0, 1.0, 0.0], 0.5, 2.0);
+    assert_eq!(ray.mint_origin(), origin);
+    assert_eq!(ray.mint_dir(), mint::Vector3::from([0.0, 1.0, 0.0]));
+    assert_eq!((ray.tnear, ray.tfar), (0.5, 2.0));
+
+    let mut hit = Hit::new();
+    hit.Ng_x = 1.0;
+    hit.Ng_y = -2.0;
+    hit.Ng_z = 0.5;
+    assert_eq!(hit.mint_normal(), mint::Vector3::from([1.0, -2.0, 0.5]));
+
+    #[cfg(feature = "packets")]
+    {
+        let origins = [
+            [0.0, 0.0, 0.0],
+            [1.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0],
+            [0.0, 0.0, 1.0],
+        ];
+        let dirs = [
+            [0.0, 0.0, 1.0],
+            [0.0, 1.0, 0.0],
+            [1.0, 0.0, 0.0],
+            [0.0, 0.0, -1.0],
+        ];
+        let packet = Ray4::from_mint(origins, dirs);
+        for i in 0..4 {
+            assert_eq!(
+                [packet.org_x[i], packet.org_y[i], packet.org_z[i]],
+                origins[i]
+            );
+            assert_eq!([packet.dir_x[i], packet.dir_y[i], packet.dir_z[i]], dirs[i]);
+        }
+        assert_eq!(packet.tnear, [0.0; 4]);
+        assert_eq!(packet.tfar, [f32::INFINITY; 4]);
+    }
+}
+
+#[cfg(feature = "interop-glam")]
+#[test]
+fn test_glam_interop() {
+    use glam::{Mat4, Quat, Vec3};
+
+    let ray = Ray::from_interop(Vec3::new(1.0, 2.0, 3.0), Vec3::Z);
+    assert_eq!(ray.origin_as::<Vec3>(), Vec3::new(1.0, 2.0, 3.0));
+    assert_eq!(ray.dir_as::<Vec3>(), Vec3::Z);
+
+    let m = Mat4::from_translation(Vec3::new(1.0, 2.0, 3.0));
+    let c = m.to_cgmath();
+    assert_eq!(c.w.truncate(), Vector3::new(1.0, 2.0, 3.0));
+    assert_eq!(Mat4::from_cgmath(c), m);
+
+    let q = Quat::from_rotation_y(0.5);
+    let qd = QuaternionDecomposition::from_interop(Vec3::ONE, q, Vec3::ZERO);
+    assert!(qd.rotation_as::<Quat>().abs_diff_eq(q, 1e-6));
+}
+
+#[cfg(feature = "interop-nalgebra")]
+#[test]
+fn test_nalgebra_interop() {
+    use nalgebra::{
+        Matrix4 as NaMatrix4, Point3 as NaPoint3, UnitQuaternion, Vector3 as NaVector3,
+    };
+
+    let b = Bounds::from_interop(
+        NaPoint3::new(-1.0, -2.0, -3.0),
+        NaPoint3::new(1.0, 2.0, 3.0),
+    );
+    assert_eq!(
+        b.lower_as::<NaPoint3<f32>>(),
+        NaPoint3::new(-1.0, -2.0, -3.0)
+    );
+    assert_eq!(b.upper_as::<Vector3<f32>>(), Vector3::new(1.0, 2.0, 3.0));
+
+    let m = NaMatrix4::new_translation(&NaVector3::new(1.0, 2.0, 3.0));
+    let c = m.to_cgmath();
+    assert_eq!(c.w.truncate(), Vector3::new(1.0, 2.0, 3.0));
+    assert_eq!(NaMatrix4::from_cgmath(c), m);
+
+    let q = UnitQuaternion::from_euler_angles(0.1, 0.2, 0.3);
+    let back: UnitQuaternion<f32> = InteropQuat::from_cgmath(q.to_cgmath());
+    assert!((back.angle_to(&q)).abs() < 1e-5);
+}
@@ -0,0 +1,78 @@
+//! Counts of the Embree objects created and released through the
+//! wrapper, for diagnosing leaks and double frees. Embree doesn't expose
+//! the reference counts of its objects, so with the `leak-check` feature
+//! enabled the wrapper counts the devices, scenes, geometries and buffers
+//! it creates and releases. `live_objects` returns the number of each
+//! which are still alive, e.g. to check that none are left once a test or
+//! frame's objects have been dropped. Releasing more objects of a kind
+//! than were created panics, as it points to a double free.
+//!
+//! Geometry is released when the `Geometry` wrapping it is dropped, so
+//! meshes and curves dropped without being wrapped in a `Geometry` show
+//! up as leaked geometry. Without the feature the counting compiles away.
+
+#[cfg(feature = "leak-check")]
+use std::sync::atomic::{AtomicIsize, Ordering};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum ObjectKind {
+    Device = 0,
+    Scene = 1,
+    Geometry = 2,
+    Buffer = 3,
+}
+
+/// The number of each kind of Embree object created through the wrapper
+/// which haven't been released yet
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct LiveObjects {
+    pub devices: isize,
+    pub scenes: isize,
+    pub geometries: isize,
+    pub buffers: isize,
+}
+
+#[cfg(feature = "leak-check")]
+static LIVE: [AtomicIsize; 4] = [
+    AtomicIsize::new(0),
+    AtomicIsize::new(0),
+    AtomicIsize::new(0),
+    AtomicIsize::new(0),
+];
+
+#[cfg(feature = "leak-check")]
+pub(crate) fn created(kind: ObjectKind) {
+    LIVE[kind as usize].fetch_add(1, Ordering::Relaxed);
+}
+
+#[cfg(feature = "leak-check")]
+pub(crate) fn released(kind: ObjectKind) {
+    let prev = LIVE[kind as usize].fetch_sub(1, Ordering::Relaxed);
+    assert!(
+        prev > 0,
+        "{:?} released more times than created, is it double freed?",
+        kind
+    );
+}
+
+#[cfg(not(feature = "leak-check"))]
+#[inline(always)]
+pub(crate) fn created(_: ObjectKind) {}
+
+#[cfg(not(feature = "leak-check"))]
+#[inline(always)]
+pub(crate) fn released(_: ObjectKind) {}
+
+/// Get the number of objects of each kind which are currently alive.
+/// The counts are shared by all threads, so objects created on other
+/// threads are included.
+#[cfg(feature = "leak-check")]
+pub fn live_objects() -> LiveObjects {
+    let count = |kind: ObjectKind| LIVE[kind as usize].load(Ordering::Relaxed);
+    LiveObjects {
+        devices: count(ObjectKind::Device),
+        scenes: count(ObjectKind::Scene),
+        geometries: count(ObjectKind::Geometry),
+        buffers: count(ObjectKind::Buffer),
+    }
+}
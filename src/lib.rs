@@ -10,56 +10,217 @@
 //! Embree documentation can be found [here](https://embree.github.io/api.html).
 //! See the [examples/](https://github.com/Twinklebear/embree-rs/tree/master/examples)
 //! for some example applications using the bindings.
+//!
+//! # Features
+//!
+//! The wrappers of the less commonly used parts of Embree can be left out
+//! of minimal builds by disabling default features:
+//!
+//! - `curves`: the curve geometry types and `shadow_proxy`.
+//! - `subdivision`: `SubdivisionMesh`.
+//! - `point-query`: `CommittedScene::point_query` and the `point_query`
+//!   module, which runs batches of queries with rayon.
+//! - `streams`: the AoS and SoA ray stream queries, the `ray_stream`,
+//!   `ping_pong`, `partition` and `ray_state` modules, and `RayCapture`.
+//!   `ping_pong` traces and shades with rayon.
+//! - `packets`: the 4, 8 and 16 wide packet queries and the
+//!   `packet_filter` functions.
+//!
+//! The `lod` module requires `curves` or `subdivision`. All of these are
+//! enabled by default. The optional `mint`, `interop-glam`,
+//! `interop-nalgebra`, `interop-cgmath`, `leak-check`, `simplify`, `capi`,
+//! `async`, `reference` and `serde` features are described in their
+//! modules.
 
 use std::{alloc, mem};
 
 extern crate cgmath;
+#[cfg(feature = "interop-glam")]
+extern crate glam;
+#[cfg(feature = "mint")]
+extern crate mint;
+#[cfg(feature = "interop-nalgebra")]
+extern crate nalgebra;
+#[cfg(any(feature = "point-query", feature = "streams"))]
+extern crate rayon;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(feature = "async")]
+extern crate tokio;
 
+pub mod analytic_shapes;
+#[cfg(feature = "async")]
+pub mod async_scene;
+pub mod baking;
+#[cfg(feature = "curves")]
 pub mod bezier_curve;
+#[cfg(feature = "curves")]
 pub mod bspline_curve;
+pub mod budget;
 pub mod buffer;
+pub mod bvh;
+#[cfg(feature = "capi")]
+pub mod capi;
+#[cfg(feature = "curves")]
 pub mod catmull_rom_curve;
+pub mod collide;
+#[cfg(feature = "curves")]
 pub mod curve;
+pub mod debug;
 pub mod device;
+pub mod filter;
+pub mod form_factor;
+pub mod format;
 pub mod geometry;
+pub mod grid_mesh;
+#[cfg(feature = "curves")]
 pub mod hermite_curve;
 pub mod instance;
+pub mod instance_stack;
+pub mod interleaved;
+#[cfg(any(
+    feature = "mint",
+    feature = "interop-glam",
+    feature = "interop-nalgebra",
+    feature = "interop-cgmath"
+))]
+pub mod interop;
+pub mod leak_check;
+pub mod light_group;
+pub mod linear_bounds;
+#[cfg(feature = "curves")]
 pub mod linear_curve;
+#[cfg(any(feature = "curves", feature = "subdivision"))]
+pub mod lod;
+pub mod medium;
+pub mod memory_estimate;
+#[cfg(feature = "simplify")]
+pub mod mesh_utils;
+#[cfg(feature = "packets")]
+pub mod packet_filter;
+#[cfg(feature = "streams")]
+pub mod partition;
+pub mod per_ray_output;
+#[cfg(feature = "streams")]
+pub mod ping_pong;
+pub mod point_geometry;
+#[cfg(feature = "point-query")]
+pub mod point_query;
 pub mod quad_mesh;
 pub mod ray;
+pub mod ray_layout;
+pub mod ray_mask;
+#[cfg(feature = "packets")]
 pub mod ray_packet;
+#[cfg(feature = "streams")]
+pub mod ray_state;
+#[cfg(feature = "streams")]
 pub mod ray_stream;
+#[cfg(feature = "reference")]
+pub mod reference;
 pub mod scene;
+pub mod scene_bundle;
+pub mod scene_cache;
+pub mod scene_diff;
+pub mod scene_query;
+pub mod shade_context;
+#[cfg(feature = "curves")]
+pub mod shadow_proxy;
+pub mod skinning;
+pub mod snapshot;
 pub mod soa_ray;
+pub mod statistics;
+#[cfg(feature = "subdivision")]
+pub mod subdivision_mesh;
 #[allow(non_upper_case_globals)]
 #[allow(non_camel_case_types)]
 #[allow(non_snake_case)]
 pub mod sys;
+pub mod testing;
+pub mod transform_hierarchy;
+pub mod traversal;
 pub mod triangle_mesh;
+pub mod user_geometry;
+pub mod validation;
 
+pub use analytic_shapes::{Capsule, Cone};
+#[cfg(feature = "async")]
+pub use async_scene::{AsyncScene, Blocking};
+pub use baking::{AoBaker, AoTexture, TexelSample};
+#[cfg(feature = "curves")]
 pub use bezier_curve::BezierCurve;
+#[cfg(feature = "curves")]
 pub use bspline_curve::BsplineCurve;
-pub use buffer::{Buffer, MappedBuffer};
+pub use budget::{BudgetedHit, QueryBudget};
+pub use buffer::{Buffer, MappedBuffer, VertexElement, VertexLayout};
+pub use bvh::{bvh_levels, BuildPrimitive, BuildSettings, Bvh, BvhLeaf, BvhNode, BvhTree};
+#[cfg(feature = "curves")]
 pub use catmull_rom_curve::CatmullRomCurve;
-pub use curve::CurveType;
-pub use device::Device;
-pub use geometry::Geometry;
+pub use collide::Collision;
+#[cfg(feature = "curves")]
+pub use curve::{CurveBasis, CurveType};
+#[cfg(feature = "streams")]
+pub use debug::RayCapture;
+pub use device::{Device, DeviceConfig, FrequencyLevel, Isa, MemoryBudget, MemoryMonitorFunction};
+pub use filter::{FilterFunction, HitFaceMode};
+pub use geometry::{Geometry, GeometryKind, KindMismatch, MeshError, TypedGeometry};
+pub use grid_mesh::{Grid, GridMesh};
+#[cfg(feature = "curves")]
 pub use hermite_curve::HermiteCurve;
-pub use instance::Instance;
+pub use instance::{transform_normal, Instance, QuaternionDecomposition};
+pub use instance_stack::InstanceStack;
+pub use interleaved::InterleavedBinding;
+pub use linear_bounds::LinearBounds;
+#[cfg(feature = "curves")]
 pub use linear_curve::LinearCurve;
+#[cfg(any(feature = "curves", feature = "subdivision"))]
+pub use lod::{LodController, LodLevel};
+pub use medium::{Crossing, MediumTracker};
+pub use memory_estimate::{MemoryEstimate, MemoryModel};
+#[cfg(feature = "packets")]
+pub use packet_filter::{FilterPacket16, FilterPacket4, FilterPacket8};
+#[cfg(feature = "streams")]
+pub use partition::HitPartition;
+pub use per_ray_output::PerRayOutput;
+#[cfg(feature = "streams")]
+pub use ping_pong::PingPongStreams;
+pub use point_geometry::{PointGeometry, PointType};
+#[cfg(feature = "point-query")]
+pub use point_query::{PointQuery, PointQueryContext, PointQueryPrimitive};
 pub use quad_mesh::QuadMesh;
-pub use ray::{Hit, IntersectContext, Ray, RayHit};
-pub use ray_packet::{Hit4, Ray4, RayHit4};
-pub use ray_stream::{HitN, RayHitN, RayN};
-pub use scene::{CommittedScene, Scene};
+pub use ray::{Hit, IntersectContext, Occlusion, Ray, RayHit};
+pub use ray_mask::RayMask;
+#[cfg(feature = "packets")]
+pub use ray_packet::{Hit16, Hit4, Hit8, Ray16, Ray4, Ray8, RayHit16, RayHit4, RayHit8};
+#[cfg(feature = "streams")]
+pub use ray_state::RayStateVec;
+#[cfg(feature = "streams")]
+pub use ray_stream::{Compact, HitN, RayHitN, RayN, Tile};
+#[cfg(feature = "reference")]
+pub use reference::ReferenceScene;
+pub use scene::{CommitToken, CommittedScene, GeomId, ProgressMonitorFunction, Scene, Stamped};
+pub use scene_bundle::{SceneBundle, SceneId};
+pub use scene_cache::SceneCache;
+pub use scene_diff::{MeshChange, MeshDescriptor, SceneChanges, SceneSync};
+pub use scene_query::SceneQuery;
+pub use shade_context::ShadeContext;
+pub use skinning::{Skin, VertexInfluence};
 pub use soa_ray::{
     SoAHit, SoAHitIter, SoAHitIterMut, SoAHitRef, SoARay, SoARayIter, SoARayIterMut, SoARayRef,
     SoARayRefMut,
 };
-pub use triangle_mesh::TriangleMesh;
+pub use statistics::{KindCount, RayCounts, SceneStatistics};
+#[cfg(feature = "subdivision")]
+pub use subdivision_mesh::{SubdivisionMesh, Topology, TopologyId};
+pub use transform_hierarchy::{NodeId, TransformHierarchy};
+pub use traversal::TraversalSettings;
+pub use triangle_mesh::{AttributeValue, TriangleMesh};
+pub use user_geometry::{AnalyticShape, PrimitiveHit, ShapeHit, UserGeometry, UserPrimitive};
+pub use validation::ValidationError;
 
 // Pull in some cleaned up enum and bitfield types directly,
 // with prettier aliases
+pub use sys::RTCBounds as Bounds;
 pub use sys::RTCBufferType as BufferType;
 pub use sys::RTCBuildQuality as BuildQuality;
 pub use sys::RTCDeviceProperty as DeviceProperty;
@@ -0,0 +1,65 @@
+//! Light linking through Embree's ray and geometry masks. A ray only
+//! intersects geometry whose mask shares a set bit with the ray's mask, so
+//! giving each light group a mask bit lets shadow rays towards a light
+//! only test the geometry linked to its group, e.g. to keep a character's
+//! rim light from being shadowed by the set.
+//!
+//! The mask bits are split into two ranges: the low `USER_MASK_BITS` are
+//! left for the application's own visibility flags, such as camera
+//! visibility, and the remaining high bits each hold a light group, giving
+//! at most `MAX_LIGHT_GROUPS` groups with Embree's 32 bit masks. Geometry
+//! put in light groups with `Geometry::set_light_group` keeps all the user
+//! bits set, so it stays visible to rays using them, e.g. camera rays
+//! with the mask `USER_MASK_BITS` or the default mask of all bits. Shadow
+//! rays made with `Ray::for_light_group` only have their group's bit set.
+//!
+//! Embree must be built with `EMBREE_RAY_MASK` enabled for masks to have an
+//! effect, otherwise all rays intersect all geometry. Whether it was can
+//! be checked with `Device::supports_ray_masks`.
+
+/// The mask bits left for the application's use
+pub const USER_MASK_BITS: u32 = 0xff;
+/// The first mask bit used for light groups
+pub const LIGHT_GROUP_SHIFT: u32 = 8;
+/// The number of light groups which fit in the bits above the user bits
+pub const MAX_LIGHT_GROUPS: u8 = 24;
+
+/// Get the mask bit of the light group.
+///
+/// Panics if the group is `MAX_LIGHT_GROUPS` or larger.
+pub fn light_group_mask(group: u8) -> u32 {
+    assert!(
+        group < MAX_LIGHT_GROUPS,
+        "Light group {} is out of range, there are at most {} groups",
+        group,
+        MAX_LIGHT_GROUPS
+    );
+    1 << (LIGHT_GROUP_SHIFT + group as u32)
+}
+
+/// Get the mask with the bits of each of the light groups set
+pub fn light_groups_mask(groups: &[u8]) -> u32 {
+    groups.iter().fold(0, |m, g| m | light_group_mask(*g))
+}
+
+/// Get the light groups whose bits are set in the mask
+pub fn light_groups(mask: u32) -> impl Iterator<Item = u8> {
+    (0..MAX_LIGHT_GROUPS).filter(move |g| mask & light_group_mask(*g) != 0)
+}
+
+#[test]
+fn test_light_group_masks() {
+    assert_eq!(light_group_mask(0), 0x100);
+    assert_eq!(light_group_mask(23), 0x8000_0000);
+    assert_eq!(light_group_mask(3) & USER_MASK_BITS, 0);
+    let mask = light_groups_mask(&[1, 5, 23]) | USER_MASK_BITS;
+    assert_eq!(light_groups(mask).collect::<Vec<_>>(), vec![1, 5, 23]);
+    assert_eq!(light_groups(USER_MASK_BITS).count(), 0);
+    assert_eq!(light_groups(u32::MAX).count(), MAX_LIGHT_GROUPS as usize);
+}
+
+#[test]
+#[should_panic]
+fn test_light_group_out_of_range() {
+    light_group_mask(MAX_LIGHT_GROUPS);
+}
@@ -0,0 +1,242 @@
+//! Linear bounds of motion blurred geometry and scenes over the shutter
+//! interval, e.g. for culling against a time varying region. The bounds
+//! at time t in [0, 1] are the linear interpolation of the bounds at
+//! shutter open and close, and conservatively contain the geometry at
+//! every time in the interval.
+
+use bvh::{empty_bounds, point_bounds, union_bounds};
+use cgmath::{Matrix4, Vector4};
+use sys;
+use Bounds;
+
+pub type LinearBounds = sys::RTCLinearBounds;
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a * (1.0 - t) + b * t
+}
+
+impl LinearBounds {
+    /// Create linear bounds from the bounds at shutter open and close
+    pub fn new(bounds0: Bounds, bounds1: Bounds) -> LinearBounds {
+        LinearBounds { bounds0, bounds1 }
+    }
+    /// Get the bounds at `time` in the shutter interval
+    pub fn at(&self, time: f32) -> Bounds {
+        let (a, b) = (&self.bounds0, &self.bounds1);
+        Bounds {
+            lower_x: lerp(a.lower_x, b.lower_x, time),
+            lower_y: lerp(a.lower_y, b.lower_y, time),
+            lower_z: lerp(a.lower_z, b.lower_z, time),
+            align0: 0.0,
+            upper_x: lerp(a.upper_x, b.upper_x, time),
+            upper_y: lerp(a.upper_y, b.upper_y, time),
+            upper_z: lerp(a.upper_z, b.upper_z, time),
+            align1: 0.0,
+        }
+    }
+    /// Get the bounds over the time range [t0, t1] of the shutter
+    /// interval, which is the union of the bounds at its ends
+    pub fn over(&self, t0: f32, t1: f32) -> Bounds {
+        union_bounds(&self.at(t0), &self.at(t1))
+    }
+    /// Get the bounds over the whole shutter interval
+    pub fn shutter_bounds(&self) -> Bounds {
+        union_bounds(&self.bounds0, &self.bounds1)
+    }
+    /// Fit linear bounds to the bounds of geometry at time steps spread
+    /// uniformly over the shutter interval, which contain the bounds of
+    /// every step at its time. Panics if there are no time steps.
+    pub fn from_time_steps(steps: &[Bounds]) -> LinearBounds {
+        assert!(
+            !steps.is_empty(),
+            "geometry must have at least one time step"
+        );
+        let mut lb = LinearBounds::new(steps[0], steps[steps.len() - 1]);
+        // Grow both ends by how far each step is outside the interpolated
+        // bounds at its time, growing never uncovers an earlier step
+        for (i, step) in steps.iter().enumerate() {
+            let t = i as f32 / (steps.len() - 1).max(1) as f32;
+            let b = lb.at(t);
+            let lower = [
+                (step.lower_x - b.lower_x).min(0.0),
+                (step.lower_y - b.lower_y).min(0.0),
+                (step.lower_z - b.lower_z).min(0.0),
+            ];
+            let upper = [
+                (step.upper_x - b.upper_x).max(0.0),
+                (step.upper_y - b.upper_y).max(0.0),
+                (step.upper_z - b.upper_z).max(0.0),
+            ];
+            for b in [&mut lb.bounds0, &mut lb.bounds1].iter_mut() {
+                b.lower_x += lower[0];
+                b.lower_y += lower[1];
+                b.lower_z += lower[2];
+                b.upper_x += upper[0];
+                b.upper_y += upper[1];
+                b.upper_z += upper[2];
+            }
+        }
+        lb
+    }
+}
+
+/// Fit linear bounds to the vertex positions of each time step, padded by
+/// the radius stored in w
+pub(crate) fn fit_vertices(steps: &[&[Vector4<f32>]]) -> LinearBounds {
+    if steps.iter().all(|verts| verts.is_empty()) {
+        return LinearBounds::new(empty_bounds(), empty_bounds());
+    }
+    let bounds: Vec<Bounds> = steps
+        .iter()
+        .map(|verts| point_bounds(verts.iter()))
+        .collect();
+    LinearBounds::from_time_steps(&bounds)
+}
+
+/// Get the Bézier control points of each Hermite segment starting at the
+/// vertices in `indices`, which bound the segment along with its radius.
+/// Segments indexing past the end of the buffers are skipped.
+#[cfg(feature = "curves")]
+pub(crate) fn hermite_control_points(
+    verts: &[Vector4<f32>],
+    tangents: &[Vector4<f32>],
+    indices: &[u32],
+) -> Vec<Vector4<f32>> {
+    let mut points = Vec::with_capacity(indices.len() * 4);
+    for &i in indices {
+        let i = i as usize;
+        if i + 1 >= verts.len() || i + 1 >= tangents.len() {
+            continue;
+        }
+        points.push(verts[i]);
+        points.push(verts[i] + tangents[i] / 3.0);
+        points.push(verts[i + 1] - tangents[i + 1] / 3.0);
+        points.push(verts[i + 1]);
+    }
+    points
+}
+
+/// Get the Bézier control points of each Catmull-Rom segment starting at
+/// the vertices in `indices`, see `hermite_control_points`
+#[cfg(feature = "curves")]
+pub(crate) fn catmull_rom_control_points(
+    verts: &[Vector4<f32>],
+    indices: &[u32],
+) -> Vec<Vector4<f32>> {
+    let mut points = Vec::with_capacity(indices.len() * 4);
+    for &i in indices {
+        let i = i as usize;
+        if i + 3 >= verts.len() {
+            continue;
+        }
+        let p = &verts[i..i + 4];
+        points.push(p[1]);
+        points.push(p[1] + (p[2] - p[0]) / 6.0);
+        points.push(p[2] - (p[3] - p[1]) / 6.0);
+        points.push(p[2]);
+    }
+    points
+}
+
+/// Get the bounds of the box transformed by the affine transform. Empty
+/// bounds stay empty.
+pub(crate) fn transform_bounds(transform: &Matrix4<f32>, b: &Bounds) -> Bounds {
+    if b.lower_x > b.upper_x || b.lower_y > b.upper_y || b.lower_z > b.upper_z {
+        return *b;
+    }
+    let corners: Vec<Vector4<f32>> = (0..8)
+        .map(|i| {
+            let corner = Vector4::new(
+                if i & 1 == 0 { b.lower_x } else { b.upper_x },
+                if i & 2 == 0 { b.lower_y } else { b.upper_y },
+                if i & 4 == 0 { b.lower_z } else { b.upper_z },
+                1.0,
+            );
+            // Clear w so the corners aren't padded as a radius
+            let mut p = transform * corner;
+            p.w = 0.0;
+            p
+        })
+        .collect();
+    point_bounds(corners.iter())
+}
+
+/// Get the union of the linear bounds, which contains each of them at
+/// every time
+pub(crate) fn union_linear_bounds(a: &LinearBounds, b: &LinearBounds) -> LinearBounds {
+    LinearBounds::new(
+        union_bounds(&a.bounds0, &b.bounds0),
+        union_bounds(&a.bounds1, &b.bounds1),
+    )
+}
+
+#[test]
+fn test_from_time_steps() {
+    let bounds = |lower: f32, upper: f32| Bounds {
+        lower_x: lower,
+        lower_y: 0.0,
+        lower_z: 0.0,
+        align0: 0.0,
+        upper_x: upper,
+        upper_y: 1.0,
+        upper_z: 1.0,
+        align1: 0.0,
+    };
+    let lb = LinearBounds::from_time_steps(&[bounds(0.0, 1.0)]);
+    assert_eq!(lb.at(0.5).upper_x, 1.0);
+
+    // A box moving out to x = 4 and back, the ends are pushed out so the
+    // interpolated bounds contain it at the middle step
+    let lb = LinearBounds::from_time_steps(&[bounds(0.0, 1.0), bounds(3.0, 4.0), bounds(0.0, 1.0)]);
+    let mid = lb.at(0.5);
+    assert!(mid.lower_x <= 3.0 && mid.upper_x >= 4.0);
+    assert_eq!(lb.bounds0.upper_x, 4.0);
+    assert_eq!(lb.bounds0.lower_x, 0.0);
+
+    let lb = LinearBounds::from_time_steps(&[bounds(0.0, 1.0), bounds(2.0, 3.0)]);
+    assert_eq!(lb.at(0.5).lower_x, 1.0);
+    assert_eq!(lb.over(0.25, 0.75).lower_x, 0.5);
+    assert_eq!(lb.shutter_bounds().upper_x, 3.0);
+}
+
+#[test]
+#[cfg(feature = "curves")]
+fn test_curve_control_points() {
+    let v = |x: f32| Vector4::new(x, 0.0, 0.0, 0.1);
+    let verts = [v(0.0), v(1.0), v(2.0), v(3.0)];
+    let tangents = [v(3.0), v(3.0), v(3.0), v(3.0)];
+    let points = hermite_control_points(&verts, &tangents, &[0, 3]);
+    // The second segment runs past the end of the buffers
+    assert_eq!(points.len(), 4);
+    assert_eq!(points[1].x, 1.0);
+    assert_eq!(points[2].x, 0.0);
+    // Evenly spaced Catmull-Rom vertices give a straight segment from the
+    // second to the third vertex
+    let points = catmull_rom_control_points(&verts, &[0, 1]);
+    assert_eq!(points.len(), 4);
+    assert_eq!(points[0].x, 1.0);
+    assert!((points[1].x - 4.0 / 3.0).abs() < 1e-6);
+    assert_eq!(points[3].x, 2.0);
+}
+
+#[test]
+fn test_transform_bounds() {
+    use cgmath::{Deg, Vector3};
+    let b = Bounds {
+        lower_x: 0.0,
+        lower_y: 0.0,
+        lower_z: 0.0,
+        align0: 0.0,
+        upper_x: 2.0,
+        upper_y: 1.0,
+        upper_z: 1.0,
+        align1: 0.0,
+    };
+    let m =
+        Matrix4::from_translation(Vector3::new(0.0, 5.0, 0.0)) * Matrix4::from_angle_z(Deg(90.0));
+    let t = transform_bounds(&m, &b);
+    assert!((t.lower_x + 1.0).abs() < 1e-5 && t.upper_x.abs() < 1e-5);
+    assert!((t.lower_y - 5.0).abs() < 1e-5 && (t.upper_y - 7.0).abs() < 1e-5);
+    assert_eq!((t.lower_z, t.upper_z), (0.0, 1.0));
+    assert!(transform_bounds(&m, &empty_bounds()).lower_x.is_infinite());
+}
@@ -2,13 +2,14 @@ use cgmath::{Vector2, Vector3, Vector4};
 
 use buffer::Buffer;
 use device::Device;
-use geometry::Geometry;
+use geometry::{self, Geometry};
 use sys::*;
 use {BufferType, CurveType, Format, GeometryType};
 
 pub struct LinearCurve<'a> {
     device: &'a Device,
     pub(crate) handle: RTCGeometry,
+    pub(crate) curve_type: CurveType,
     pub vertex_buffer: Buffer<'a, Vector4<f32>>,
     pub index_buffer: Buffer<'a, u32>,
     pub flag_buffer: Buffer<'a, u32>,
@@ -16,6 +17,14 @@ pub struct LinearCurve<'a> {
 }
 
 impl<'a> LinearCurve<'a> {
+    /// The buffers which must be set before committing the geometry
+    pub const REQUIRED: &'static [BufferType] = &[BufferType::VERTEX, BufferType::INDEX];
+    /// The buffers the geometry can optionally use
+    pub const OPTIONAL: &'static [BufferType] = &[
+        BufferType::FLAGS,
+        BufferType::NORMAL,
+        BufferType::VERTEX_ATTRIBUTE,
+    ];
     pub fn flat(
         device: &'a Device,
         num_segments: usize,
@@ -68,12 +77,12 @@ impl<'a> LinearCurve<'a> {
         let h: RTCGeometry;
         match curve_type {
             CurveType::Cone => {
-                h = unsafe { rtcNewGeometry(device.handle, GeometryType::CONE_LINEAR_CURVE) }
+                h = unsafe { geometry::new_handle(device, GeometryType::CONE_LINEAR_CURVE) }
             }
             CurveType::Round => {
-                h = unsafe { rtcNewGeometry(device.handle, GeometryType::ROUND_LINEAR_CURVE) }
+                h = unsafe { geometry::new_handle(device, GeometryType::ROUND_LINEAR_CURVE) }
             }
-            _ => h = unsafe { rtcNewGeometry(device.handle, GeometryType::FLAT_LINEAR_CURVE) },
+            _ => h = unsafe { geometry::new_handle(device, GeometryType::FLAT_LINEAR_CURVE) },
         };
         let mut vertex_buffer = Buffer::new(device, num_verts);
         let mut index_buffer = Buffer::new(device, num_segments);
@@ -137,6 +146,7 @@ impl<'a> LinearCurve<'a> {
         LinearCurve {
             device: device,
             handle: h,
+            curve_type,
             vertex_buffer: vertex_buffer,
             index_buffer: index_buffer,
             flag_buffer: flag_buffer,
@@ -146,3 +156,4 @@ impl<'a> LinearCurve<'a> {
 }
 
 unsafe impl<'a> Sync for LinearCurve<'a> {}
+unsafe impl<'a> Send for LinearCurve<'a> {}
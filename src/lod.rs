@@ -0,0 +1,185 @@
+//! Camera driven level of detail for curves and subdivision meshes. A
+//! `LodController` maps the distance from the camera to each geometry it
+//! tracks to a tessellation rate, so nearby hair and surfaces are
+//! tessellated finely while distant ones are traced with fewer, cheaper
+//! segments. Geometry is only recommitted when the camera moves it across
+//! a level's distance threshold.
+
+use cgmath::{InnerSpace, Vector3, Vector4};
+
+use geometry::Geometry;
+use scene::Scene;
+
+/// A level of detail, used for geometry up to `max_distance` from the
+/// camera which isn't within the distance of a finer level
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct LodLevel {
+    pub max_distance: f32,
+    pub tessellation_rate: f32,
+}
+
+struct Tracked {
+    id: u32,
+    center: Vector3<f32>,
+    radius: f32,
+    /// The level last applied to the geometry
+    level: Option<usize>,
+}
+
+/// Updates the tessellation rate of curves and subdivision meshes in a
+/// scene based on their distance to the camera.
+#[derive(Default)]
+pub struct LodController {
+    levels: Vec<LodLevel>,
+    tracked: Vec<Tracked>,
+}
+
+/// Compute a bounding sphere of the vertices, padded by the radius of
+/// curve vertices stored in w
+fn bounding_sphere(verts: &[Vector4<f32>]) -> (Vector3<f32>, f32) {
+    if verts.is_empty() {
+        return (Vector3::new(0.0, 0.0, 0.0), 0.0);
+    }
+    let mut lower = verts[0].truncate();
+    let mut upper = lower;
+    for v in verts {
+        lower = Vector3::new(lower.x.min(v.x), lower.y.min(v.y), lower.z.min(v.z));
+        upper = Vector3::new(upper.x.max(v.x), upper.y.max(v.y), upper.z.max(v.z));
+    }
+    let center = (lower + upper) * 0.5;
+    let radius = verts
+        .iter()
+        .map(|v| (v.truncate() - center).magnitude() + v.w.abs())
+        .fold(0.0, f32::max);
+    (center, radius)
+}
+
+impl LodController {
+    pub fn new() -> LodController {
+        LodController::default()
+    }
+    /// Add a level using `tessellation_rate` for geometry up to
+    /// `max_distance` from the camera. Geometry beyond the last level's
+    /// distance uses the last level.
+    pub fn level(mut self, max_distance: f32, tessellation_rate: f32) -> LodController {
+        self.levels.push(LodLevel {
+            max_distance,
+            tessellation_rate,
+        });
+        self.levels
+            .sort_by(|a, b| a.max_distance.partial_cmp(&b.max_distance).unwrap());
+        self
+    }
+    pub fn levels(&self) -> &[LodLevel] {
+        &self.levels
+    }
+    /// Get the index of the level used for geometry at `distance` from
+    /// the camera, or `None` if no levels have been added
+    pub fn level_for_distance(&self, distance: f32) -> Option<usize> {
+        if self.levels.is_empty() {
+            return None;
+        }
+        let i = self
+            .levels
+            .iter()
+            .position(|l| distance <= l.max_distance)
+            .unwrap_or(self.levels.len() - 1);
+        Some(i)
+    }
+    /// Track the level of detail of the geometry `id` in the scene, using
+    /// the bounds of its vertices to measure its distance to the camera.
+    /// The bounds are computed once, so if the geometry's vertices move
+    /// it should be tracked again to update them.
+    ///
+    /// Panics if the geometry isn't attached to the scene or isn't a cubic
+    /// curve or subdivision mesh.
+    pub fn track(&mut self, scene: &Scene, id: u32) {
+        let geom = scene
+            .geometry_by_raw_id(id)
+            .unwrap_or_else(|| panic!("No geometry {} is attached to the scene", id));
+        let verts = match *geom {
+            #[cfg(feature = "curves")]
+            Geometry::BsplineCurve(ref c) => c.vertex_buffer.as_slice(),
+            #[cfg(feature = "curves")]
+            Geometry::BezierCurve(ref c) => c.vertex_buffer.as_slice(),
+            #[cfg(feature = "curves")]
+            Geometry::HermiteCurve(ref c) => c.vertex_buffer.as_slice(),
+            #[cfg(feature = "curves")]
+            Geometry::CatmullRomCurve(ref c) => c.vertex_buffer.as_slice(),
+            #[cfg(feature = "subdivision")]
+            Geometry::Subdivision(ref s) => s.vertex_buffer.as_slice(),
+            _ => panic!("Geometry {} doesn't have a tessellation rate", id),
+        };
+        let (center, radius) = bounding_sphere(verts);
+        self.untrack(id);
+        self.tracked.push(Tracked {
+            id,
+            center,
+            radius,
+            level: None,
+        });
+    }
+    /// Stop tracking the geometry `id`, returning true if it was tracked
+    pub fn untrack(&mut self, id: u32) -> bool {
+        let n = self.tracked.len();
+        self.tracked.retain(|t| t.id != id);
+        self.tracked.len() != n
+    }
+    /// Get the level last applied to the geometry `id` by `update`
+    pub fn current_level(&self, id: u32) -> Option<usize> {
+        self.tracked
+            .iter()
+            .find(|t| t.id == id)
+            .and_then(|t| t.level)
+    }
+    /// Update the tessellation rate of the tracked geometry for a camera
+    /// at `camera`, setting the rate and recommitting only the geometry
+    /// whose level changed. The scene must be committed afterwards for the
+    /// new rates to be used. Returns the number of geometries updated.
+    ///
+    /// Panics if tracked geometry has been detached from the scene.
+    pub fn update(&mut self, scene: &mut Scene, camera: Vector3<f32>) -> usize {
+        let mut updated = 0;
+        for i in 0..self.tracked.len() {
+            let distance = {
+                let t = &self.tracked[i];
+                ((t.center - camera).magnitude() - t.radius).max(0.0)
+            };
+            let level = self.level_for_distance(distance);
+            let t = &mut self.tracked[i];
+            if level.is_none() || level == t.level {
+                continue;
+            }
+            let geom = scene
+                .geometry_by_raw_id_mut(t.id)
+                .unwrap_or_else(|| panic!("No geometry {} is attached to the scene", t.id));
+            geom.set_tessellation_rate(self.levels[level.unwrap()].tessellation_rate);
+            geom.commit();
+            t.level = level;
+            updated += 1;
+        }
+        updated
+    }
+}
+
+#[test]
+fn test_level_for_distance() {
+    let lod = LodController::new()
+        .level(50.0, 2.0)
+        .level(10.0, 8.0)
+        .level(200.0, 1.0);
+    assert_eq!(lod.levels()[0].tessellation_rate, 8.0);
+    assert_eq!(lod.level_for_distance(0.0), Some(0));
+    assert_eq!(lod.level_for_distance(10.0), Some(0));
+    assert_eq!(lod.level_for_distance(30.0), Some(1));
+    assert_eq!(lod.level_for_distance(150.0), Some(2));
+    assert_eq!(lod.level_for_distance(1000.0), Some(2));
+    assert_eq!(LodController::new().level_for_distance(1.0), None);
+
+    let (center, radius) = bounding_sphere(&[
+        Vector4::new(-1.0, 0.0, 0.0, 0.5),
+        Vector4::new(1.0, 2.0, 0.0, 0.5),
+    ]);
+    assert_eq!(center, Vector3::new(0.0, 1.0, 0.0));
+    assert!((radius - (2.0f32.sqrt() + 0.5)).abs() < 1e-6);
+}
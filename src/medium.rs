@@ -0,0 +1,223 @@
+//! Tracking the medium a ray travels through across the surfaces it
+//! crosses, for nested dielectrics such as ice in water in glass, or
+//! participating media bounded by closed meshes. Each geometry bounding a
+//! medium is tagged with the medium and a priority. As the hits along a
+//! ray are passed to a `MediumTracker` in order, it keeps the stack of
+//! media the ray is inside, and the current medium is the one with the
+//! highest priority, the most recently entered among equal priorities.
+//!
+//! This handles overlapping volumes, which modelers create to avoid gaps
+//! between touching objects, e.g. the liquid of a glass modeled slightly
+//! larger than the glass's inside. Crossing the boundary of a volume
+//! which isn't the current medium doesn't change the medium the ray is
+//! in, and the crossing is reported as a false boundary which a renderer
+//! should pass through without scattering or refracting.
+//!
+//! The hits along a ray can be collected with a filter writing to a
+//! `PerRayOutput`, then sorted by distance:
+//!
+//! ```no_run
+//! # extern crate cgmath;
+//! # extern crate embree;
+//! # use cgmath::Vector3;
+//! # use embree::{Hit, MediumTracker};
+//! # let dir = Vector3::new(0.0, 0.0, -1.0);
+//! # let mut hits: Vec<(f32, Hit)> = Vec::new();
+//! # let (glass_id, water_id) = (0, 1);
+//! let mut tracker = MediumTracker::new(1.0);
+//! tracker.set_medium(glass_id, 1.5, 1);
+//! tracker.set_medium(water_id, 1.33, 2);
+//! hits.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+//! for &(_, ref hit) in &hits {
+//!     if let Some(c) = tracker.cross(dir, hit) {
+//!         if c.is_boundary() {
+//!             // Refract from the index c.from to c.to
+//!         }
+//!     }
+//! }
+//! ```
+//!
+//! A ray enters a volume through a front face, the side its geometric
+//! normal points to, so the meshes must be closed with their normals
+//! facing outwards. A ray exiting a volume it isn't known to be in, e.g.
+//! one starting inside it, is ignored.
+
+use std::collections::HashMap;
+
+use cgmath::{InnerSpace, Vector3};
+
+use ray::Hit;
+
+/// The crossing of a surface bounding a medium, returned by
+/// `MediumTracker::cross`
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Crossing<M> {
+    /// The geometry whose surface was crossed
+    pub geom_id: u32,
+    /// Whether the ray entered the volume bounded by the geometry
+    pub entering: bool,
+    /// The medium the ray was in before the crossing
+    pub from: M,
+    /// The medium the ray is in after the crossing
+    pub to: M,
+    /// Whether the crossing changed the current medium, false for the
+    /// boundaries of volumes overlapped by a higher priority medium
+    pub changed: bool,
+}
+
+impl<M> Crossing<M> {
+    /// Whether the crossing is a boundary between two media which the
+    /// renderer should scatter or refract at, i.e. it changed the medium
+    pub fn is_boundary(&self) -> bool {
+        self.changed
+    }
+}
+
+/// Tracks the media a ray is inside as it crosses the surfaces bounding
+/// them, see the `medium` module
+#[derive(Debug, Clone)]
+pub struct MediumTracker<M> {
+    outside: M,
+    /// The medium and priority of each tagged geometry
+    media: HashMap<u32, (M, u32)>,
+    /// The geometries whose volumes the ray is inside, in the order
+    /// they were entered
+    stack: Vec<u32>,
+}
+
+impl<M: Copy + PartialEq> MediumTracker<M> {
+    /// Create a tracker for rays starting in the `outside` medium, which
+    /// the ray is in when it's not inside any tagged volume
+    pub fn new(outside: M) -> MediumTracker<M> {
+        MediumTracker {
+            outside,
+            media: HashMap::new(),
+            stack: Vec::new(),
+        }
+    }
+    /// Tag the geometry as bounding a volume of the medium. Higher
+    /// priority media take precedence where volumes overlap.
+    pub fn set_medium(&mut self, geom_id: u32, medium: M, priority: u32) {
+        self.media.insert(geom_id, (medium, priority));
+    }
+    /// Get the medium and priority the geometry is tagged with
+    pub fn medium(&self, geom_id: u32) -> Option<(M, u32)> {
+        self.media.get(&geom_id).cloned()
+    }
+    /// Get the medium the ray is currently in
+    pub fn current(&self) -> M {
+        self.current_entry().map_or(self.outside, |(_, m, _)| m)
+    }
+    /// Get the geometries whose volumes the ray is inside, in the order
+    /// they were entered
+    pub fn inside(&self) -> &[u32] {
+        &self.stack
+    }
+    /// Start tracking a new ray from the outside medium, keeping the tags
+    pub fn reset(&mut self) {
+        self.stack.clear();
+    }
+    /// Start tracking a new ray from inside the volumes of the geometries,
+    /// e.g. for a ray continuing a path from a point inside them
+    pub fn reset_inside(&mut self, inside: &[u32]) {
+        self.stack.clear();
+        self.stack.extend_from_slice(inside);
+    }
+    /// Cross the surface of the hit, for a ray travelling in direction
+    /// `dir`. Hits must be passed in order along the ray. Returns `None`
+    /// if the geometry isn't tagged with a medium, or if the ray exits a
+    /// volume it isn't known to be in.
+    pub fn cross(&mut self, dir: Vector3<f32>, hit: &Hit) -> Option<Crossing<M>> {
+        let ng = Vector3::new(hit.Ng_x, hit.Ng_y, hit.Ng_z);
+        self.cross_surface(hit.geomID, ng.dot(dir) < 0.0)
+    }
+    /// Cross the surface of the geometry, entering or exiting its volume,
+    /// see `cross`
+    pub fn cross_surface(&mut self, geom_id: u32, entering: bool) -> Option<Crossing<M>> {
+        if !self.media.contains_key(&geom_id) {
+            return None;
+        }
+        let before = self.current_entry().map(|(i, _, _)| self.stack[i]);
+        let from = self.current();
+        if entering {
+            self.stack.push(geom_id);
+        } else {
+            // The volume may not be the most recently entered one when
+            // volumes overlap
+            let i = self.stack.iter().rposition(|g| *g == geom_id)?;
+            self.stack.remove(i);
+        }
+        let after = self.current_entry().map(|(i, _, _)| self.stack[i]);
+        Some(Crossing {
+            geom_id,
+            entering,
+            from,
+            to: self.current(),
+            changed: before != after,
+        })
+    }
+    /// Find the stack index, medium and priority of the current medium,
+    /// the highest priority one the ray is inside
+    fn current_entry(&self) -> Option<(usize, M, u32)> {
+        let mut current: Option<(usize, M, u32)> = None;
+        for (i, g) in self.stack.iter().enumerate() {
+            let (m, p) = self.media[g];
+            if current.is_none_or(|(_, _, cp)| p >= cp) {
+                current = Some((i, m, p));
+            }
+        }
+        current
+    }
+}
+
+#[test]
+fn test_nested_media() {
+    // A glass holding water modeled overlapping the glass, with an ice
+    // cube floating in the water
+    let (glass, water, ice, opaque) = (0, 1, 2, 3);
+    let mut tracker = MediumTracker::new("air");
+    tracker.set_medium(glass, "glass", 2);
+    tracker.set_medium(water, "water", 1);
+    tracker.set_medium(ice, "ice", 3);
+
+    let c = tracker.cross_surface(glass, true).unwrap();
+    assert_eq!((c.from, c.to, c.is_boundary()), ("air", "glass", true));
+    // The water overlaps the glass, which has priority
+    let c = tracker.cross_surface(water, true).unwrap();
+    assert_eq!((c.from, c.to, c.is_boundary()), ("glass", "glass", false));
+    let c = tracker.cross_surface(glass, false).unwrap();
+    assert_eq!((c.from, c.to, c.is_boundary()), ("glass", "water", true));
+    assert!(tracker.cross_surface(opaque, true).is_none());
+    let c = tracker.cross_surface(ice, true).unwrap();
+    assert_eq!((c.from, c.to), ("water", "ice"));
+    let c = tracker.cross_surface(ice, false).unwrap();
+    assert_eq!((c.from, c.to), ("ice", "water"));
+    assert_eq!(tracker.inside(), &[water]);
+    // Exiting a volume the ray isn't in is ignored
+    assert!(tracker.cross_surface(ice, false).is_none());
+    assert_eq!(tracker.current(), "water");
+
+    // Equal priorities go to the most recently entered volume
+    tracker.reset_inside(&[glass]);
+    tracker.set_medium(water, "water", 2);
+    let c = tracker.cross_surface(water, true).unwrap();
+    assert_eq!((c.from, c.to, c.is_boundary()), ("glass", "water", true));
+    tracker.reset();
+    assert_eq!(tracker.current(), "air");
+}
+
+#[test]
+fn test_cross_hit() {
+    let mut tracker = MediumTracker::new(1.0);
+    tracker.set_medium(4, 1.5, 0);
+    let mut hit = Hit::new();
+    hit.geomID = 4;
+    hit.Ng_z = 1.0;
+    let dir = Vector3::new(0.0, 0.0, -1.0);
+    // The ray enters through the front face, then exits through a back face
+    assert!(tracker.cross(dir, &hit).unwrap().entering);
+    hit.Ng_z = -1.0;
+    let c = tracker.cross(dir, &hit).unwrap();
+    assert!(!c.entering);
+    assert_eq!((c.from, c.to), (1.5, 1.0));
+}
@@ -0,0 +1,338 @@
+//! Estimates of the memory a scene takes on the device, e.g. to decide
+//! whether a scene fits in memory with the default build or needs to be
+//! committed with `SceneFlags::COMPACT`, before building its BVH.
+//!
+//! The memory taken by the geometry buffers is known exactly from their
+//! sizes, while the size of the BVH Embree builds over the geometry depends
+//! on the build quality, the ISA and how the primitives are distributed in
+//! space. The BVH is estimated with a per primitive model, `MemoryModel`,
+//! whose defaults are rough figures for BVH4 builds on AVX2 and can be off
+//! by up to half in either direction. `MemoryModel::calibrate` fits the
+//! model to the device it's run on by building test scenes and measuring
+//! their BVHs with the memory monitor, narrowing the margin.
+//!
+//! Shared buffers are owned by the application and aren't counted, nor are
+//! the scenes referenced by instances, which should be estimated on their
+//! own.
+
+use std::mem::size_of;
+use std::sync::atomic::{AtomicIsize, Ordering};
+use std::sync::Arc;
+
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+use buffer::Buffer;
+use device::Device;
+use geometry::Geometry;
+use scene::Scene;
+use triangle_mesh::TriangleMesh;
+use SceneFlags;
+
+/// The estimated memory use of a scene, in bytes
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct MemoryEstimate {
+    /// The bytes taken by the geometry buffers
+    pub buffers: usize,
+    /// The estimated bytes taken by the BVH
+    pub bvh: usize,
+    /// The relative error of the BVH estimate, e.g. 0.5 if it may be off by
+    /// up to half of its value
+    pub margin: f32,
+}
+
+impl MemoryEstimate {
+    /// The estimated total bytes used by the scene
+    pub fn total(&self) -> usize {
+        self.buffers + self.bvh
+    }
+    /// The most bytes the scene is expected to use
+    pub fn upper_bound(&self) -> usize {
+        self.buffers + (self.bvh as f32 * (1.0 + self.margin)) as usize
+    }
+    /// The fewest bytes the scene is expected to use
+    pub fn lower_bound(&self) -> usize {
+        self.buffers + (self.bvh as f32 * (1.0 - self.margin).max(0.0)) as usize
+    }
+}
+
+/// The bytes of BVH built per primitive of each kind of geometry, used to
+/// estimate the memory a scene will take
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct MemoryModel {
+    /// Bytes per triangle in the default build
+    pub triangle: f32,
+    /// Bytes per triangle in a `COMPACT` build
+    pub triangle_compact: f32,
+    /// Bytes per quad in the default build
+    pub quad: f32,
+    /// Bytes per quad in a `COMPACT` build
+    pub quad_compact: f32,
+    /// Bytes per curve segment
+    pub curve: f32,
+    /// Bytes per point of a point geometry
+    pub point: f32,
+    /// Bytes per instance
+    pub instance: f32,
+    /// Bytes per user geometry primitive
+    pub user: f32,
+    /// Bytes per subdivision face, covering the cached patch data
+    pub subdivision_face: f32,
+    /// The relative error of estimates made with the model
+    pub margin: f32,
+}
+
+impl Default for MemoryModel {
+    fn default() -> MemoryModel {
+        MemoryModel {
+            triangle: 64.0,
+            triangle_compact: 32.0,
+            quad: 72.0,
+            quad_compact: 36.0,
+            curve: 96.0,
+            point: 48.0,
+            instance: 128.0,
+            user: 48.0,
+            subdivision_face: 1024.0,
+            margin: 0.5,
+        }
+    }
+}
+
+/// The number of quads along each side of the grids built to calibrate
+const CALIBRATION_GRID: usize = 128;
+
+impl MemoryModel {
+    /// Fit the model to the device by building a triangle mesh scene with
+    /// the default and compact flags and measuring the memory retained by
+    /// their BVHs. The other kinds of geometry are scaled by the same
+    /// factor as the default triangle build. This replaces any memory
+    /// monitor function set on the device, and removes it when done.
+    pub fn calibrate(device: &mut Device) -> MemoryModel {
+        let allocated = Arc::new(AtomicIsize::new(0));
+        {
+            let allocated = allocated.clone();
+            device.set_memory_monitor_function(move |bytes, _| {
+                allocated.fetch_add(bytes, Ordering::SeqCst);
+                true
+            });
+        }
+        let (positions, indices) = grid(CALIBRATION_GRID);
+        let device_ref: &Device = device;
+        let measure = |flags: SceneFlags| {
+            let device = device_ref;
+            let mut scene = Scene::new(device);
+            scene.set_flags(flags);
+            let mut mesh = Geometry::Triangle(
+                TriangleMesh::try_from_slices(device, &positions, &indices).unwrap(),
+            );
+            mesh.commit();
+            scene.attach_geometry(mesh);
+            let before = allocated.load(Ordering::SeqCst);
+            let _ = scene.commit();
+            (allocated.load(Ordering::SeqCst) - before).max(0) as f32 / indices.len() as f32
+        };
+        let default = measure(SceneFlags::NONE);
+        let compact = measure(SceneFlags::COMPACT);
+        device.clear_memory_monitor_function();
+        MemoryModel::default().fit(default, compact)
+    }
+    /// Scale the model to the measured bytes per triangle of the default
+    /// and compact builds
+    fn fit(&self, default: f32, compact: f32) -> MemoryModel {
+        if default <= 0.0 || compact <= 0.0 {
+            return *self;
+        }
+        let scale = default / self.triangle;
+        let compact_scale = compact / self.triangle_compact;
+        MemoryModel {
+            triangle: default,
+            triangle_compact: compact,
+            quad: self.quad * scale,
+            quad_compact: self.quad_compact * compact_scale,
+            curve: self.curve * scale,
+            point: self.point * scale,
+            instance: self.instance * scale,
+            user: self.user * scale,
+            subdivision_face: self.subdivision_face * scale,
+            margin: 0.25,
+        }
+    }
+    /// Estimate the memory used by the scene when committed with or without
+    /// `SceneFlags::COMPACT`. A scene with shadow proxies builds a second
+    /// BVH over the shadow geometry, which is included.
+    pub fn estimate(&self, scene: &Scene, compact: bool) -> MemoryEstimate {
+        let mut buffers = 0;
+        let mut bvh = 0.0;
+        let mut shadow_bvh = 0.0;
+        let mut has_shadow = false;
+        for (id, geom) in scene.iter() {
+            buffers += buffer_bytes(geom);
+            bvh += self.bvh_bytes(geom, compact);
+            match scene.get_shadow_proxy(*id) {
+                Some(proxy) => {
+                    has_shadow = true;
+                    buffers += buffer_bytes(proxy);
+                    shadow_bvh += self.bvh_bytes(proxy, compact);
+                }
+                None => shadow_bvh += self.bvh_bytes(geom, compact),
+            }
+        }
+        if has_shadow {
+            bvh += shadow_bvh;
+        }
+        MemoryEstimate {
+            buffers,
+            bvh: bvh as usize,
+            margin: self.margin,
+        }
+    }
+    /// Estimate the bytes of BVH built over the geometry
+    fn bvh_bytes(&self, geom: &Geometry, compact: bool) -> f32 {
+        let per_primitive = match *geom {
+            Geometry::Triangle(_) if compact => self.triangle_compact,
+            Geometry::Triangle(_) => self.triangle,
+            Geometry::Quad(_) if compact => self.quad_compact,
+            Geometry::Quad(_) => self.quad,
+            // Grids are stored in the BVH as compressed blocks of quads
+            // reading their vertices from the vertex buffer
+            Geometry::Grid(ref g) => return self.quad_compact * g.quad_count() as f32,
+            Geometry::Point(_) => self.point,
+            Geometry::Instance(_) => self.instance,
+            #[cfg(feature = "curves")]
+            Geometry::LinearCurve(_)
+            | Geometry::BsplineCurve(_)
+            | Geometry::BezierCurve(_)
+            | Geometry::HermiteCurve(_)
+            | Geometry::CatmullRomCurve(_) => self.curve,
+            #[cfg(feature = "subdivision")]
+            Geometry::Subdivision(_) => self.subdivision_face,
+            Geometry::User(_) => self.user,
+        };
+        per_primitive * geom.primitive_count() as f32
+    }
+}
+
+impl<'a> Scene<'a> {
+    /// Estimate the memory the scene uses once committed with its current
+    /// flags, using the default `MemoryModel`. See the `memory_estimate`
+    /// module for the accuracy of the estimate.
+    pub fn estimate_memory_usage(&self) -> MemoryEstimate {
+        let compact = self.flags().0 & SceneFlags::COMPACT.0 != 0;
+        MemoryModel::default().estimate(self, compact)
+    }
+}
+
+fn bytes<T>(buf: &Buffer<T>) -> usize {
+    buf.len() * size_of::<T>()
+}
+
+fn optional_bytes<T>(buf: &Option<Buffer<T>>) -> usize {
+    buf.as_ref().map_or(0, bytes)
+}
+
+/// The bytes of the buffers allocated on the device for the geometry
+fn buffer_bytes(geom: &Geometry) -> usize {
+    match *geom {
+        Geometry::Triangle(ref m) => {
+            bytes(&m.vertex_buffer)
+                + bytes(&m.index_buffer)
+                + m.motion_vertex_buffers.iter().map(bytes).sum::<usize>()
+                + m.vertex_attribute_buffers.iter().map(bytes).sum::<usize>()
+        }
+        Geometry::Quad(ref m) => bytes(&m.vertex_buffer) + bytes(&m.index_buffer),
+        Geometry::Grid(ref g) => bytes(&g.vertex_buffer) + bytes(&g.grid_buffer),
+        Geometry::Point(ref p) => bytes(&p.vertex_buffer) + optional_bytes(&p.normal_buffer),
+        Geometry::Instance(_) | Geometry::User(_) => 0,
+        #[cfg(feature = "curves")]
+        Geometry::LinearCurve(ref c) => {
+            bytes(&c.vertex_buffer)
+                + bytes(&c.index_buffer)
+                + bytes(&c.flag_buffer)
+                + optional_bytes(&c.normal_buffer)
+        }
+        #[cfg(feature = "curves")]
+        Geometry::BsplineCurve(ref c) => {
+            bytes(&c.vertex_buffer) + bytes(&c.index_buffer) + optional_bytes(&c.normal_buffer)
+        }
+        #[cfg(feature = "curves")]
+        Geometry::BezierCurve(ref c) => {
+            bytes(&c.vertex_buffer) + bytes(&c.index_buffer) + optional_bytes(&c.normal_buffer)
+        }
+        #[cfg(feature = "curves")]
+        Geometry::HermiteCurve(ref c) => {
+            bytes(&c.vertex_buffer)
+                + bytes(&c.index_buffer)
+                + bytes(&c.tangent_buffer)
+                + optional_bytes(&c.normal_derivative_buffer)
+                + optional_bytes(&c.normal_buffer)
+        }
+        #[cfg(feature = "curves")]
+        Geometry::CatmullRomCurve(ref c) => {
+            bytes(&c.vertex_buffer) + bytes(&c.index_buffer) + optional_bytes(&c.normal_buffer)
+        }
+        #[cfg(feature = "subdivision")]
+        Geometry::Subdivision(ref m) => {
+            bytes(&m.vertex_buffer)
+                + bytes(&m.face_buffer)
+                + m.index_buffers.iter().map(bytes).sum::<usize>()
+                + m.vertex_attribute_buffers.iter().map(bytes).sum::<usize>()
+        }
+    }
+}
+
+/// Build a flat grid of `n` by `n` quads split into triangles
+fn grid(n: usize) -> (Vec<[f32; 3]>, Vec<[u32; 3]>) {
+    let mut positions = Vec::with_capacity((n + 1) * (n + 1));
+    for y in 0..n + 1 {
+        for x in 0..n + 1 {
+            positions.push([x as f32, y as f32, 0.0]);
+        }
+    }
+    let mut indices = Vec::with_capacity(2 * n * n);
+    for y in 0..n {
+        for x in 0..n {
+            let i = (y * (n + 1) + x) as u32;
+            let j = i + n as u32 + 1;
+            indices.push([i, i + 1, j]);
+            indices.push([i + 1, j + 1, j]);
+        }
+    }
+    (positions, indices)
+}
+
+#[test]
+fn test_estimate_bounds() {
+    let estimate = MemoryEstimate {
+        buffers: 1000,
+        bvh: 400,
+        margin: 0.5,
+    };
+    assert_eq!(estimate.total(), 1400);
+    assert_eq!(estimate.upper_bound(), 1600);
+    assert_eq!(estimate.lower_bound(), 1200);
+}
+
+#[test]
+fn test_fit_model() {
+    let model = MemoryModel::default();
+    let fit = model.fit(128.0, 40.0);
+    assert_eq!((fit.triangle, fit.triangle_compact), (128.0, 40.0));
+    assert_eq!(fit.quad, model.quad * 2.0);
+    assert_eq!(fit.curve, model.curve * 2.0);
+    assert!(fit.margin < model.margin);
+    // A failed measurement leaves the model unchanged
+    assert_eq!(model.fit(0.0, 40.0), model);
+}
+
+#[test]
+fn test_calibration_grid() {
+    let (positions, indices) = grid(4);
+    assert_eq!(positions.len(), 25);
+    assert_eq!(indices.len(), 32);
+    assert!(indices
+        .iter()
+        .flatten()
+        .all(|i| (*i as usize) < positions.len()));
+}
@@ -0,0 +1,464 @@
+//! Mesh processing utilities for building proxy geometry, enabled with the
+//! `simplify` feature. `decimate` reduces a triangle mesh to a fraction of
+//! its triangles, e.g. to make a cheap occlusion or shadow proxy for a
+//! detailed mesh with `Scene::set_shadow_proxy`. The input and output use
+//! the same position and index slices as `TriangleMesh::try_from_slices`,
+//! so a proxy can be built directly from the decimated mesh.
+
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+
+/// Error quadric of a vertex: the sum of the squared distances to a set
+/// of planes, stored as the upper triangle of the symmetric 4x4 matrix
+#[derive(Debug, Copy, Clone, Default)]
+struct Quadric([f64; 10]);
+
+impl Quadric {
+    /// Quadric of the plane n . p + d = 0, scaled by the weight
+    fn from_plane(n: [f64; 3], d: f64, weight: f64) -> Quadric {
+        let [a, b, c] = n;
+        let w = weight;
+        Quadric([
+            w * a * a,
+            w * a * b,
+            w * a * c,
+            w * a * d,
+            w * b * b,
+            w * b * c,
+            w * b * d,
+            w * c * c,
+            w * c * d,
+            w * d * d,
+        ])
+    }
+    fn add(&mut self, other: &Quadric) {
+        for (a, b) in self.0.iter_mut().zip(other.0.iter()) {
+            *a += *b;
+        }
+    }
+    /// Evaluate the error of moving the vertex to `p`
+    fn error(&self, p: [f64; 3]) -> f64 {
+        let q = &self.0;
+        let [x, y, z] = p;
+        q[0] * x * x
+            + 2.0 * q[1] * x * y
+            + 2.0 * q[2] * x * z
+            + 2.0 * q[3] * x
+            + q[4] * y * y
+            + 2.0 * q[5] * y * z
+            + 2.0 * q[6] * y
+            + q[7] * z * z
+            + 2.0 * q[8] * z
+            + q[9]
+    }
+    /// Find the position minimizing the error, if the system is well
+    /// conditioned enough to solve
+    fn optimal(&self) -> Option<[f64; 3]> {
+        let q = &self.0;
+        let m = [[q[0], q[1], q[2]], [q[1], q[4], q[5]], [q[2], q[5], q[7]]];
+        let rhs = [-q[3], -q[6], -q[8]];
+        let det = determinant(m);
+        let scale = q[0].abs() + q[4].abs() + q[7].abs();
+        if det.abs() <= 1e-12 * scale * scale * scale {
+            return None;
+        }
+        // Solve by Cramer's rule
+        let mut p = [0.0; 3];
+        for (i, v) in p.iter_mut().enumerate() {
+            let mut mi = m;
+            for r in 0..3 {
+                mi[r][i] = rhs[r];
+            }
+            *v = determinant(mi) / det;
+        }
+        Some(p)
+    }
+}
+
+fn determinant(m: [[f64; 3]; 3]) -> f64 {
+    m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+        - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+        + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+}
+
+fn sub(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn cross(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn dot(a: [f64; 3], b: [f64; 3]) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn normalize(a: [f64; 3]) -> Option<[f64; 3]> {
+    let len = dot(a, a).sqrt();
+    if len > 0.0 {
+        Some([a[0] / len, a[1] / len, a[2] / len])
+    } else {
+        None
+    }
+}
+
+/// A candidate collapse of the edge (v0, v1) into v0 at `pos`, valid while
+/// neither vertex has changed since it was computed
+struct Collapse {
+    cost: f64,
+    v0: u32,
+    v1: u32,
+    versions: (u32, u32),
+    pos: [f64; 3],
+}
+
+impl PartialEq for Collapse {
+    fn eq(&self, other: &Collapse) -> bool {
+        self.cost == other.cost
+    }
+}
+
+impl Eq for Collapse {}
+
+impl PartialOrd for Collapse {
+    fn partial_cmp(&self, other: &Collapse) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Collapse {
+    // Reversed so the heap pops the cheapest collapse first
+    fn cmp(&self, other: &Collapse) -> Ordering {
+        other
+            .cost
+            .partial_cmp(&self.cost)
+            .unwrap_or(Ordering::Equal)
+    }
+}
+
+/// The weight of the planes constraining boundary edges, relative to the
+/// planes of the faces, which keeps open boundaries from shrinking
+const BOUNDARY_WEIGHT: f64 = 100.0;
+
+struct Decimator {
+    pos: Vec<[f64; 3]>,
+    quadrics: Vec<Quadric>,
+    versions: Vec<u32>,
+    removed: Vec<bool>,
+    faces: Vec<[u32; 3]>,
+    face_alive: Vec<bool>,
+    vert_faces: Vec<Vec<u32>>,
+    heap: BinaryHeap<Collapse>,
+}
+
+impl Decimator {
+    fn new(positions: &[[f32; 3]], indices: &[[u32; 3]]) -> Decimator {
+        let n = positions.len();
+        let mut d = Decimator {
+            pos: positions
+                .iter()
+                .map(|p| [p[0] as f64, p[1] as f64, p[2] as f64])
+                .collect(),
+            quadrics: vec![Quadric::default(); n],
+            versions: vec![0; n],
+            removed: vec![false; n],
+            faces: indices.to_vec(),
+            face_alive: vec![true; indices.len()],
+            vert_faces: vec![Vec::new(); n],
+            heap: BinaryHeap::new(),
+        };
+        let mut edge_faces: HashMap<(u32, u32), u32> = HashMap::new();
+        for (f, tri) in indices.iter().enumerate() {
+            let [a, b, c] = *tri;
+            if a == b || b == c || a == c {
+                d.face_alive[f] = false;
+                continue;
+            }
+            for v in tri.iter() {
+                d.vert_faces[*v as usize].push(f as u32);
+            }
+            for i in 0..3 {
+                let (u, v) = (tri[i], tri[(i + 1) % 3]);
+                *edge_faces.entry((u.min(v), u.max(v))).or_insert(0) += 1;
+            }
+            let (p0, p1, p2) = (d.pos[a as usize], d.pos[b as usize], d.pos[c as usize]);
+            let area_normal = cross(sub(p1, p0), sub(p2, p0));
+            if let Some(n) = normalize(area_normal) {
+                // Weight the plane by the face area so small slivers
+                // don't dominate the error
+                let area = 0.5 * dot(area_normal, area_normal).sqrt();
+                let q = Quadric::from_plane(n, -dot(n, p0), area);
+                for v in tri.iter() {
+                    d.quadrics[*v as usize].add(&q);
+                }
+            }
+        }
+        // Constrain boundary edges with a plane through the edge which is
+        // perpendicular to its face
+        for (f, tri) in indices.iter().enumerate() {
+            if !d.face_alive[f] {
+                continue;
+            }
+            let (p0, p1, p2) = (
+                d.pos[tri[0] as usize],
+                d.pos[tri[1] as usize],
+                d.pos[tri[2] as usize],
+            );
+            let face_normal = match normalize(cross(sub(p1, p0), sub(p2, p0))) {
+                Some(n) => n,
+                None => continue,
+            };
+            for i in 0..3 {
+                let (u, v) = (tri[i], tri[(i + 1) % 3]);
+                if edge_faces[&(u.min(v), u.max(v))] != 1 {
+                    continue;
+                }
+                let (pu, pv) = (d.pos[u as usize], d.pos[v as usize]);
+                let edge = sub(pv, pu);
+                if let Some(n) = normalize(cross(edge, face_normal)) {
+                    let weight = BOUNDARY_WEIGHT * dot(edge, edge);
+                    let q = Quadric::from_plane(n, -dot(n, pu), weight);
+                    d.quadrics[u as usize].add(&q);
+                    d.quadrics[v as usize].add(&q);
+                }
+            }
+        }
+        for &(u, v) in edge_faces.keys() {
+            d.push_collapse(u, v);
+        }
+        d
+    }
+    fn push_collapse(&mut self, v0: u32, v1: u32) {
+        let mut q = self.quadrics[v0 as usize];
+        q.add(&self.quadrics[v1 as usize]);
+        let (p0, p1) = (self.pos[v0 as usize], self.pos[v1 as usize]);
+        let mid = [
+            0.5 * (p0[0] + p1[0]),
+            0.5 * (p0[1] + p1[1]),
+            0.5 * (p0[2] + p1[2]),
+        ];
+        let mut candidates = vec![p0, p1, mid];
+        if let Some(p) = q.optimal() {
+            candidates.push(p);
+        }
+        let (cost, pos) = candidates
+            .into_iter()
+            .map(|p| (q.error(p), p))
+            .min_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal))
+            .unwrap();
+        self.heap.push(Collapse {
+            cost,
+            v0,
+            v1,
+            versions: (self.versions[v0 as usize], self.versions[v1 as usize]),
+            pos,
+        });
+    }
+    /// Check that moving the faces around the collapsed edge to `pos`
+    /// doesn't flip any of them
+    fn flips(&self, v0: u32, v1: u32, pos: [f64; 3]) -> bool {
+        for &v in &[v0, v1] {
+            for &f in self.vert_faces[v as usize].iter() {
+                if !self.face_alive[f as usize] {
+                    continue;
+                }
+                let tri = self.faces[f as usize];
+                if tri.contains(&v0) && tri.contains(&v1) {
+                    continue;
+                }
+                let p: Vec<[f64; 3]> = tri.iter().map(|i| self.pos[*i as usize]).collect();
+                let before = cross(sub(p[1], p[0]), sub(p[2], p[0]));
+                let moved: Vec<[f64; 3]> = tri
+                    .iter()
+                    .zip(p.iter())
+                    .map(|(i, p)| if *i == v { pos } else { *p })
+                    .collect();
+                let after = cross(sub(moved[1], moved[0]), sub(moved[2], moved[0]));
+                if dot(before, after) <= 0.0 {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+    /// Collapse v1 into v0, returning the number of faces removed
+    fn collapse(&mut self, v0: u32, v1: u32, pos: [f64; 3]) -> usize {
+        let (i0, i1) = (v0 as usize, v1 as usize);
+        self.pos[i0] = pos;
+        let q = self.quadrics[i1];
+        self.quadrics[i0].add(&q);
+        self.removed[i1] = true;
+        self.versions[i0] += 1;
+
+        let mut removed = 0;
+        let moved = std::mem::take(&mut self.vert_faces[i1]);
+        for f in moved {
+            if !self.face_alive[f as usize] {
+                continue;
+            }
+            let tri = &mut self.faces[f as usize];
+            if tri.contains(&v0) {
+                self.face_alive[f as usize] = false;
+                removed += 1;
+            } else {
+                for v in tri.iter_mut() {
+                    if *v == v1 {
+                        *v = v0;
+                    }
+                }
+                self.vert_faces[i0].push(f);
+            }
+        }
+        let alive = &self.face_alive;
+        self.vert_faces[i0].retain(|f| alive[*f as usize]);
+
+        let mut neighbors: Vec<u32> = self.vert_faces[i0]
+            .iter()
+            .flat_map(|f| self.faces[*f as usize].to_vec())
+            .filter(|v| *v != v0)
+            .collect();
+        neighbors.sort_unstable();
+        neighbors.dedup();
+        for n in neighbors {
+            self.push_collapse(v0, n);
+        }
+        removed
+    }
+}
+
+/// Simplify a triangle mesh to about `target_ratio` of its triangles by
+/// collapsing edges in order of the quadric error metric, the sum of the
+/// squared distances of the new vertex to the planes of the original
+/// faces around it. Open boundaries are preserved and collapses which
+/// would flip a face are skipped, so the result may keep more triangles
+/// than the target if no more edges can be collapsed. Returns the
+/// positions and indices of the simplified mesh, with unused vertices
+/// removed.
+///
+/// Panics if the ratio isn't in [0, 1] or an index is out of bounds.
+pub fn decimate(
+    positions: &[[f32; 3]],
+    indices: &[[u32; 3]],
+    target_ratio: f32,
+) -> (Vec<[f32; 3]>, Vec<[u32; 3]>) {
+    assert!(
+        (0.0..=1.0).contains(&target_ratio),
+        "Target ratio {} must be in [0, 1]",
+        target_ratio
+    );
+    for tri in indices {
+        for &v in tri {
+            assert!(
+                (v as usize) < positions.len(),
+                "Index {} is out of bounds of the {} positions",
+                v,
+                positions.len()
+            );
+        }
+    }
+    let mut d = Decimator::new(positions, indices);
+    let target = (indices.len() as f64 * target_ratio as f64).ceil() as usize;
+    let mut live = d.face_alive.iter().filter(|a| **a).count();
+    while live > target {
+        let c = match d.heap.pop() {
+            Some(c) => c,
+            None => break,
+        };
+        let (i0, i1) = (c.v0 as usize, c.v1 as usize);
+        if d.removed[i0]
+            || d.removed[i1]
+            || d.versions[i0] != c.versions.0
+            || d.versions[i1] != c.versions.1
+            || d.flips(c.v0, c.v1, c.pos)
+        {
+            continue;
+        }
+        live -= d.collapse(c.v0, c.v1, c.pos);
+    }
+
+    let mut remap = vec![u32::MAX; positions.len()];
+    let mut out_positions = Vec::new();
+    let mut out_indices = Vec::with_capacity(live);
+    for (f, tri) in d.faces.iter().enumerate() {
+        if !d.face_alive[f] {
+            continue;
+        }
+        let mut out = [0; 3];
+        for (o, v) in out.iter_mut().zip(tri.iter()) {
+            let v = *v as usize;
+            if remap[v] == u32::MAX {
+                remap[v] = out_positions.len() as u32;
+                let p = d.pos[v];
+                out_positions.push([p[0] as f32, p[1] as f32, p[2] as f32]);
+            }
+            *o = remap[v];
+        }
+        out_indices.push(out);
+    }
+    (out_positions, out_indices)
+}
+
+#[cfg(test)]
+fn grid(n: usize, height: impl Fn(f32, f32) -> f32) -> (Vec<[f32; 3]>, Vec<[u32; 3]>) {
+    let mut positions = Vec::new();
+    for j in 0..=n {
+        for i in 0..=n {
+            let (x, y) = (i as f32 / n as f32, j as f32 / n as f32);
+            positions.push([x, y, height(x, y)]);
+        }
+    }
+    let mut indices = Vec::new();
+    let row = n as u32 + 1;
+    for j in 0..n as u32 {
+        for i in 0..n as u32 {
+            let v = j * row + i;
+            indices.push([v, v + 1, v + row + 1]);
+            indices.push([v, v + row + 1, v + row]);
+        }
+    }
+    (positions, indices)
+}
+
+#[test]
+fn test_decimate_plane() {
+    // A flat grid can be collapsed to a few triangles without error, and
+    // its boundary must stay on the unit square
+    let (positions, indices) = grid(16, |_, _| 0.0);
+    let (p, i) = decimate(&positions, &indices, 0.1);
+    assert!(i.len() <= (indices.len() as f32 * 0.1).ceil() as usize);
+    assert!(!i.is_empty());
+    let area: f32 = i
+        .iter()
+        .map(|t| {
+            let (a, b, c) = (p[t[0] as usize], p[t[1] as usize], p[t[2] as usize]);
+            0.5 * ((b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0]))
+        })
+        .sum();
+    assert!((area - 1.0).abs() < 1e-4, "area {}", area);
+    for v in p.iter() {
+        assert!(v[2].abs() < 1e-6);
+        assert!(v[0] >= -1e-6 && v[0] <= 1.0 + 1e-6);
+        assert!(v[1] >= -1e-6 && v[1] <= 1.0 + 1e-6);
+    }
+    for t in i.iter() {
+        assert!(t.iter().all(|v| (*v as usize) < p.len()));
+    }
+}
+
+#[test]
+fn test_decimate_curved() {
+    // A bumpy surface keeps vertices close to the original surface
+    let height = |x: f32, y: f32| 0.1 * (x * 6.0).sin() * (y * 6.0).cos();
+    let (positions, indices) = grid(24, height);
+    let (p, i) = decimate(&positions, &indices, 0.25);
+    assert!(i.len() <= indices.len() / 4 + 1);
+    for v in p.iter() {
+        assert!((v[2] - height(v[0], v[1])).abs() < 0.05);
+    }
+    let (_, same) = decimate(&positions, &indices, 1.0);
+    assert_eq!(same.len(), indices.len());
+}
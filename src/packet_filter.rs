@@ -0,0 +1,285 @@
+//! Filter functions called with a whole packet of candidate hits of a
+//! fixed SIMD width, for filters written with SIMD intrinsics. They're set
+//! with `Geometry::set_intersect_filter_function4`, `8` or `16` and the
+//! matching occlusion variants, and receive a `FilterPacket4`, `8` or `16`
+//! holding the packet's valid mask, rays and hits as Embree's SoA packet
+//! types, e.g. `Ray4` and `Hit4` or `sys::RTCRay8` and `sys::RTCHit8`.
+//!
+//! # Width Contract
+//!
+//! Embree calls filters with packets as wide as the query which found the
+//! hits: one ray for `intersect_ray`, four for `intersect4` and so on,
+//! while ray stream queries may use any width Embree was built with. A
+//! width specific filter is always passed packets of its width, whatever
+//! the query:
+//!
+//! - When the query's width matches, the filter is passed Embree's packet
+//!   directly without copying it.
+//! - Otherwise the hits are copied into packets of the filter's width,
+//!   with the lanes past the end of the query's packet marked invalid,
+//!   and the valid mask the filter leaves is copied back to Embree.
+//!
+//! The ray, hit and valid mask of a packet are always aligned to the
+//! width of the packet in bytes, e.g. 32 bytes for `FilterPacket8`, so
+//! they can be loaded with aligned SIMD loads. Whether Embree was built
+//! with native support for a packet width, and so whether queries of that
+//! width take the copy free path, can be checked with
+//! `Device::supports_packet_width`.
+//!
+//! Filters must follow the thread safety rules of the `filter` module, and
+//! like raw filter functions, they aren't budgeted by budgeted queries.
+//! Only the valid mask can be modified, a lane set to 0 rejects its hit.
+
+use std::mem;
+
+use filter::{self, PacketDispatch};
+use geometry::{self, Geometry};
+use ray_packet::{Hit4, Ray4};
+use sys::{self, RTCHit16, RTCHit8, RTCRay16, RTCRay8};
+
+/// The number of 4 byte fields of a ray and a hit in a SoA packet
+const RAY_FIELDS: usize = 12;
+const HIT_FIELDS: usize = 8;
+
+/// Copy lane `src_i` of the SoA packet of `src_n` lanes at `src` to lane
+/// `dst_i` of the one of `dst_n` lanes at `dst`
+unsafe fn copy_lane(
+    src: *const u32,
+    src_n: usize,
+    src_i: usize,
+    dst: *mut u32,
+    dst_n: usize,
+    dst_i: usize,
+    fields: usize,
+) {
+    for f in 0..fields {
+        *dst.add(f * dst_n + dst_i) = *src.add(f * src_n + src_i);
+    }
+}
+
+macro_rules! filter_packet {
+    ($packet:ident, $valid:ident, $ray:ty, $hit:ty, $width:expr, $align:expr, $dispatch:ident) => {
+        /// A packet of candidate hits passed to a width specific filter,
+        /// see the module documentation
+        pub struct $packet<'p> {
+            /// The valid mask of the packet, -1 for lanes with a candidate
+            /// hit and 0 for empty lanes. Setting a lane to 0 rejects its
+            /// hit.
+            pub valid: &'p mut [i32; $width],
+            pub ray: &'p $ray,
+            pub hit: &'p $hit,
+        }
+
+        impl<'p> $packet<'p> {
+            /// Whether lane `i` holds a candidate hit
+            pub fn is_valid(&self, i: usize) -> bool {
+                self.valid[i] != 0
+            }
+            /// Reject the hit in lane `i`
+            pub fn reject(&mut self, i: usize) {
+                self.valid[i] = 0;
+            }
+        }
+
+        #[repr(C, align($align))]
+        struct $valid([i32; $width]);
+
+        pub(crate) fn $dispatch<'a, F>(filter: F) -> Box<PacketDispatch<'a>>
+        where
+            F: Fn(&mut $packet) + Send + Sync + 'a,
+        {
+            Box::new(move |args: &sys::RTCFilterFunctionNArguments| unsafe {
+                let n = args.N as usize;
+                let aligned = [args.valid as usize, args.ray as usize, args.hit as usize]
+                    .iter()
+                    .all(|p| p % $align == 0);
+                if n == $width && aligned {
+                    filter(&mut $packet {
+                        valid: &mut *(args.valid as *mut [i32; $width]),
+                        ray: &*(args.ray as *const $ray),
+                        hit: &*(args.hit as *const $hit),
+                    });
+                    return;
+                }
+                for start in (0..n).step_by($width) {
+                    let mut valid = $valid([0; $width]);
+                    let mut ray: $ray = mem::zeroed();
+                    let mut hit: $hit = mem::zeroed();
+                    let lanes = (n - start).min($width);
+                    for j in 0..lanes {
+                        let i = start + j;
+                        valid.0[j] = *args.valid.add(i);
+                        let ray_ptr = &mut ray as *mut $ray as *mut u32;
+                        let hit_ptr = &mut hit as *mut $hit as *mut u32;
+                        copy_lane(args.ray as *const u32, n, i, ray_ptr, $width, j, RAY_FIELDS);
+                        copy_lane(args.hit as *const u32, n, i, hit_ptr, $width, j, HIT_FIELDS);
+                    }
+                    if valid.0.iter().all(|v| *v == 0) {
+                        continue;
+                    }
+                    filter(&mut $packet {
+                        valid: &mut valid.0,
+                        ray: &ray,
+                        hit: &hit,
+                    });
+                    for j in 0..lanes {
+                        *args.valid.add(start + j) = valid.0[j];
+                    }
+                }
+            })
+        }
+    };
+}
+
+filter_packet!(FilterPacket4, Valid4, Ray4, Hit4, 4, 16, dispatch4);
+filter_packet!(FilterPacket8, Valid8, RTCRay8, RTCHit8, 8, 32, dispatch8);
+filter_packet!(
+    FilterPacket16,
+    Valid16,
+    RTCRay16,
+    RTCHit16,
+    16,
+    64,
+    dispatch16
+);
+
+impl<'a> Geometry<'a> {
+    /// Set a filter function called with packets of 4 candidate hits found
+    /// on the geometry by intersection queries, replacing any filter set.
+    /// See the `packet_filter` module for the width and alignment
+    /// guarantees. The geometry must be committed for the filter to take
+    /// effect.
+    pub fn set_intersect_filter_function4<F>(&mut self, filter: F)
+    where
+        F: Fn(&mut FilterPacket4) + Send + Sync + 'a,
+    {
+        self.set_intersect_packet_filter(dispatch4(filter));
+    }
+    /// Set a filter function called with packets of 8 candidate hits, see
+    /// `set_intersect_filter_function4`
+    pub fn set_intersect_filter_function8<F>(&mut self, filter: F)
+    where
+        F: Fn(&mut FilterPacket8) + Send + Sync + 'a,
+    {
+        self.set_intersect_packet_filter(dispatch8(filter));
+    }
+    /// Set a filter function called with packets of 16 candidate hits, see
+    /// `set_intersect_filter_function4`
+    pub fn set_intersect_filter_function16<F>(&mut self, filter: F)
+    where
+        F: Fn(&mut FilterPacket16) + Send + Sync + 'a,
+    {
+        self.set_intersect_packet_filter(dispatch16(filter));
+    }
+    /// Set a filter function called with packets of 4 candidate hits found
+    /// on the geometry by occlusion queries, replacing any filter set. See
+    /// `set_intersect_filter_function4`.
+    pub fn set_occluded_filter_function4<F>(&mut self, filter: F)
+    where
+        F: Fn(&mut FilterPacket4) + Send + Sync + 'a,
+    {
+        self.set_occluded_packet_filter(dispatch4(filter));
+    }
+    /// Set a filter function called with packets of 8 candidate hits, see
+    /// `set_occluded_filter_function4`
+    pub fn set_occluded_filter_function8<F>(&mut self, filter: F)
+    where
+        F: Fn(&mut FilterPacket8) + Send + Sync + 'a,
+    {
+        self.set_occluded_packet_filter(dispatch8(filter));
+    }
+    /// Set a filter function called with packets of 16 candidate hits, see
+    /// `set_occluded_filter_function4`
+    pub fn set_occluded_filter_function16<F>(&mut self, filter: F)
+    where
+        F: Fn(&mut FilterPacket16) + Send + Sync + 'a,
+    {
+        self.set_occluded_packet_filter(dispatch16(filter));
+    }
+    fn set_intersect_packet_filter(&mut self, filter: Box<PacketDispatch<'a>>) {
+        geometry::mark_dirty(self.handle());
+        let data = self.data();
+        data.intersect_filter = None;
+        data.intersect_packet_filter = Some(filter);
+        data.raw_intersect_filter = false;
+        unsafe {
+            sys::rtcSetGeometryIntersectFilterFunction(
+                self.handle(),
+                Some(filter::intersect_filter),
+            );
+        }
+    }
+    fn set_occluded_packet_filter(&mut self, filter: Box<PacketDispatch<'a>>) {
+        geometry::mark_dirty(self.handle());
+        let data = self.data();
+        data.occluded_filter = None;
+        data.occluded_packet_filter = Some(filter);
+        data.raw_occluded_filter = false;
+        unsafe {
+            sys::rtcSetGeometryOccludedFilterFunction(self.handle(), Some(filter::occluded_filter));
+        }
+    }
+}
+
+#[test]
+fn test_dispatch_narrow_packet() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    // A single ray query's packet is copied into lane 0 of a packet of 8
+    let calls = AtomicUsize::new(0);
+    let dispatch = dispatch8(|packet| {
+        calls.fetch_add(1, Ordering::Relaxed);
+        assert_eq!(packet.valid as *const _ as usize % 32, 0);
+        assert!(packet.is_valid(0));
+        assert!((1..8).all(|i| !packet.is_valid(i)));
+        assert_eq!(packet.ray.tfar[0], 2.0);
+        assert_eq!(packet.hit.primID[0], 7);
+        packet.reject(0);
+    });
+    let mut valid = -1i32;
+    let mut ray: sys::RTCRay = unsafe { mem::zeroed() };
+    ray.tfar = 2.0;
+    let mut hit: sys::RTCHit = unsafe { mem::zeroed() };
+    hit.primID = 7;
+    let mut args: sys::RTCFilterFunctionNArguments = unsafe { mem::zeroed() };
+    args.valid = &mut valid;
+    args.ray = &mut ray as *mut sys::RTCRay as *mut sys::RTCRayN;
+    args.hit = &mut hit as *mut sys::RTCHit as *mut sys::RTCHitN;
+    args.N = 1;
+    dispatch(&args);
+    assert_eq!(calls.load(Ordering::Relaxed), 1);
+    assert_eq!(valid, 0);
+}
+
+#[test]
+fn test_dispatch_wide_packet() {
+    // A packet of 16 is split into four packets of 4, skipping the ones
+    // without valid lanes
+    let dispatch = dispatch4(|packet| {
+        for i in 0..4 {
+            if packet.is_valid(i) && packet.hit.primID[i] % 2 == 1 {
+                packet.reject(i);
+            }
+        }
+    });
+    let mut valid = [0i32; 16];
+    for v in valid.iter_mut().take(10) {
+        *v = -1;
+    }
+    let mut ray: RTCRay16 = unsafe { mem::zeroed() };
+    let mut hit: RTCHit16 = unsafe { mem::zeroed() };
+    for i in 0..16 {
+        hit.primID[i] = i as u32;
+        ray.tfar[i] = 1.0;
+    }
+    let mut args: sys::RTCFilterFunctionNArguments = unsafe { mem::zeroed() };
+    args.valid = valid.as_mut_ptr();
+    args.ray = &mut ray as *mut RTCRay16 as *mut sys::RTCRayN;
+    args.hit = &mut hit as *mut RTCHit16 as *mut sys::RTCHitN;
+    args.N = 16;
+    dispatch(&args);
+    let expected: Vec<i32> = (0..16)
+        .map(|i| if i < 10 && i % 2 == 0 { -1 } else { 0 })
+        .collect();
+    assert_eq!(valid.to_vec(), expected);
+}
@@ -0,0 +1,126 @@
+//! Partitioning of ray stream results into batches of rays which share a
+//! key, e.g. the geometry or material they hit, for shading each batch
+//! together in a wavefront style loop. The partition keeps its buffers
+//! between calls, so re-partitioning the results of each bounce doesn't
+//! allocate once the buffers have grown to the stream size.
+
+use ray_stream::HitN;
+use soa_ray::SoAHit;
+
+/// The indices of the rays in a stream grouped by a key. Within each group
+/// the indices are in increasing order, and the groups are sorted by key.
+#[derive(Debug, Default, Clone)]
+pub struct HitPartition {
+    /// Pairs of (key, ray index) sorted to form the groups
+    sorted: Vec<(u32, u32)>,
+    /// The ray indices in group order
+    indices: Vec<u32>,
+    /// The key of each group and the range of its indices
+    groups: Vec<(u32, usize, usize)>,
+    /// The rays which weren't assigned a key
+    misses: Vec<u32>,
+}
+
+impl HitPartition {
+    pub fn new() -> HitPartition {
+        HitPartition::default()
+    }
+    /// Create a partition with buffers preallocated to hold `n` rays
+    pub fn with_capacity(n: usize) -> HitPartition {
+        HitPartition {
+            sorted: Vec::with_capacity(n),
+            indices: Vec::with_capacity(n),
+            groups: Vec::new(),
+            misses: Vec::with_capacity(n),
+        }
+    }
+    /// Group the rays in the stream by the ID of the geometry they hit.
+    /// Rays which missed are collected in `misses`.
+    pub fn partition_by_geom_id(&mut self, hits: &HitN) {
+        self.partition_by(hits.len(), |i| {
+            let g = hits.geom_id(i);
+            if g != u32::MAX {
+                Some(g)
+            } else {
+                None
+            }
+        });
+    }
+    /// Group the `n` rays of a stream by the key returned by `key` for
+    /// each ray index, e.g. the material ID of the geometry hit. Rays for
+    /// which `key` returns `None` are collected in `misses`.
+    pub fn partition_by<F>(&mut self, n: usize, mut key: F)
+    where
+        F: FnMut(usize) -> Option<u32>,
+    {
+        self.clear();
+        for i in 0..n {
+            match key(i) {
+                Some(k) => self.sorted.push((k, i as u32)),
+                None => self.misses.push(i as u32),
+            }
+        }
+        // The pairs are unique, so an unstable sort keeps the indices
+        // of each group in order
+        self.sorted.sort_unstable();
+        for (i, &(k, idx)) in self.sorted.iter().enumerate() {
+            match self.groups.last_mut() {
+                Some(g) if g.0 == k => g.2 = i + 1,
+                _ => self.groups.push((k, i, i + 1)),
+            }
+            self.indices.push(idx);
+        }
+    }
+    /// Empty the partition, keeping its buffers for reuse
+    pub fn clear(&mut self) {
+        self.sorted.clear();
+        self.indices.clear();
+        self.groups.clear();
+        self.misses.clear();
+    }
+    /// The number of groups in the partition
+    pub fn num_groups(&self) -> usize {
+        self.groups.len()
+    }
+    /// Iterate over the groups in key order, yielding each key and the
+    /// indices of the rays assigned it
+    pub fn groups<'a>(&'a self) -> impl Iterator<Item = (u32, &'a [u32])> + 'a {
+        self.groups
+            .iter()
+            .map(move |&(k, start, end)| (k, &self.indices[start..end]))
+    }
+    /// Get the indices of the rays assigned the key, if any were
+    pub fn group(&self, key: u32) -> Option<&[u32]> {
+        self.groups
+            .binary_search_by_key(&key, |g| g.0)
+            .ok()
+            .map(|g| &self.indices[self.groups[g].1..self.groups[g].2])
+    }
+    /// The indices of all rays which were assigned a key, in group order
+    pub fn indices(&self) -> &[u32] {
+        &self.indices
+    }
+    /// The indices of the rays which weren't assigned a key
+    pub fn misses(&self) -> &[u32] {
+        &self.misses
+    }
+}
+
+#[test]
+fn test_partition_by_key() {
+    let keys = [Some(3), None, Some(1), Some(3), Some(1), None, Some(7)];
+    let mut p = HitPartition::new();
+    p.partition_by(keys.len(), |i| keys[i]);
+    let groups: Vec<_> = p.groups().map(|(k, g)| (k, g.to_vec())).collect();
+    assert_eq!(groups, vec![(1, vec![2, 4]), (3, vec![0, 3]), (7, vec![6])]);
+    assert_eq!(p.misses(), &[1, 5]);
+    assert_eq!(p.indices(), &[2, 4, 0, 3, 6]);
+    assert_eq!(p.group(3), Some(&[0u32, 3][..]));
+    assert_eq!(p.group(2), None);
+
+    // Repartitioning replaces the previous groups
+    p.partition_by(3, |i| Some(i as u32 % 2));
+    assert_eq!(p.num_groups(), 2);
+    assert_eq!(p.group(0), Some(&[0u32, 2][..]));
+    assert!(p.misses().is_empty());
+}
@@ -0,0 +1,148 @@
+//! Output written by filter functions, kept separate for each ray. Embree
+//! calls filters from whichever threads are tracing rays, so a filter
+//! pushing to a single shared list, e.g. to collect all the hits along the
+//! rays of an image, interleaves the hits of different rays in an order
+//! that changes from run to run, and needs a lock every thread contends on.
+//!
+//! Instead, give each ray a slot of its own: set the ray's `id` to its
+//! index, e.g. its pixel, and write to the ray's slot in a `PerRayOutput`
+//! from the filter. Only the threads tracing the same ray touch a slot,
+//! so locking it is uncontended, and the output of each ray is
+//! independent of how the rays were distributed over the threads:
+//!
+//! ```no_run
+//! # extern crate embree;
+//! # extern crate cgmath;
+//! # use cgmath::Vector3;
+//! # use embree::{Device, Geometry, PerRayOutput, Ray, Scene, TriangleMesh};
+//! # let device = Device::new();
+//! let hits = PerRayOutput::<Vec<u32>>::new(64);
+//! let mut geom = Geometry::Triangle(TriangleMesh::unanimated(&device, 1, 3));
+//! geom.set_intersect_filter_function(|ray, hit| {
+//!     hits.with(ray, |prims| prims.push(hit.primID));
+//!     // Reject the hit to continue traversal and collect all the hits
+//!     false
+//! });
+//! geom.commit();
+//! # let mut scene = Scene::new(&device);
+//! # scene.attach_geometry(geom);
+//! # let rtscene = scene.commit();
+//! for i in 0..64 {
+//!     let mut ray = Ray::new(Vector3::new(i as f32, 0.0, 1.0), Vector3::new(0.0, 0.0, -1.0));
+//!     ray.id = i;
+//!     rtscene.intersect_ray(&ray);
+//! }
+//! ```
+//!
+//! Hits on a ray are reported in traversal order, which depends on the BVH
+//! Embree builds, so output which must be identical between runs should be
+//! sorted, e.g. by hit distance, once tracing is done.
+
+use std::sync::Mutex;
+
+use ray::Ray;
+
+/// A slot of output of type `T` for each ray, indexed by the ray's `id`
+#[derive(Debug)]
+pub struct PerRayOutput<T> {
+    slots: Vec<Mutex<T>>,
+}
+
+impl<T: Default> PerRayOutput<T> {
+    /// Create output for `n` rays, with ids in [0, n), with each ray's
+    /// slot set to the default value
+    pub fn new(n: usize) -> PerRayOutput<T> {
+        PerRayOutput {
+            slots: (0..n).map(|_| Mutex::new(T::default())).collect(),
+        }
+    }
+    /// Reset each slot to the default value, e.g. before tracing a new
+    /// frame
+    pub fn reset(&mut self) {
+        for s in self.slots.iter_mut() {
+            *s.get_mut().unwrap() = T::default();
+        }
+    }
+}
+
+impl<T> PerRayOutput<T> {
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+    /// Run `f` on the slot of the ray, e.g. from a filter function. Calls
+    /// for different rays can run concurrently.
+    ///
+    /// Panics if the ray's id is out of bounds.
+    pub fn with<R, F>(&self, ray: &Ray, f: F) -> R
+    where
+        F: FnOnce(&mut T) -> R,
+    {
+        self.with_id(ray.id, f)
+    }
+    /// Run `f` on the slot for the ray id
+    ///
+    /// Panics if the id is out of bounds.
+    pub fn with_id<R, F>(&self, id: u32, f: F) -> R
+    where
+        F: FnOnce(&mut T) -> R,
+    {
+        let slot = self.slots.get(id as usize).unwrap_or_else(|| {
+            panic!(
+                "Ray id {} is out of bounds of the output for {} rays",
+                id,
+                self.slots.len()
+            )
+        });
+        f(&mut slot.lock().unwrap())
+    }
+    /// Get the slot for the ray id once tracing is done
+    pub fn get_mut(&mut self, id: u32) -> &mut T {
+        self.slots[id as usize].get_mut().unwrap()
+    }
+    /// Iterate over the slots in ray id order once tracing is done
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.slots.iter_mut().map(|s| s.get_mut().unwrap())
+    }
+    /// Take the output of each ray, in ray id order
+    pub fn into_vec(self) -> Vec<T> {
+        self.slots
+            .into_iter()
+            .map(|s| s.into_inner().unwrap())
+            .collect()
+    }
+}
+
+#[test]
+fn test_per_ray_output() {
+    use cgmath::Vector3;
+    use std::thread;
+
+    let out = PerRayOutput::<Vec<u32>>::new(4);
+    thread::scope(|s| {
+        for t in 0..4 {
+            let out = &out;
+            s.spawn(move || {
+                for i in 0..16 {
+                    out.with_id((i + t) % 4, |v| v.push(t));
+                }
+            });
+        }
+    });
+    let mut ray = Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 1.0));
+    ray.id = 2;
+    assert_eq!(out.with(&ray, |v| v.len()), 16);
+
+    let mut out = out;
+    for v in out.iter_mut() {
+        v.sort();
+    }
+    assert_eq!(
+        *out.get_mut(0),
+        vec![0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3]
+    );
+    out.reset();
+    assert!(out.into_vec().iter().all(|v| v.is_empty()));
+}
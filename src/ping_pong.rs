@@ -0,0 +1,126 @@
+//! Double buffered ray streams for overlapping tracing and shading. A
+//! renderer tracing a stream of rays and then shading the hits leaves the
+//! BVH traversal and the shading code each idle while the other runs.
+//! With two streams, batch N can be shaded while batch N + 1 is traced on
+//! another thread, so the shading cost is hidden behind the tracing one.
+//!
+//! `PingPongStreams` owns the two streams: the back stream is filled with
+//! the next batch of rays and traced, while the front stream holds the
+//! last batch traced, ready to shade. Once both are done `swap` makes the
+//! newly traced batch the front. `trace_and_shade` runs one step of this
+//! with `rayon::join`, or the streams can be split with `split_mut` to
+//! trace with a different context or run the two halves some other way:
+//!
+//! ```ignore
+//! for tile in tiles {
+//!     streams.back_mut().fill_primary(&camera, &tile, 0.0, u32::MAX);
+//!     let (front, back) = streams.split_mut();
+//!     rayon::join(
+//!         || scene.intersect_stream_soa(&mut IntersectContext::coherent(), back),
+//!         || shade(front),
+//!     );
+//!     streams.swap();
+//! }
+//! shade(streams.front_mut());
+//! ```
+//!
+//! The front stream is empty before the first swap, so the first step only
+//! traces, and the last batch traced must be shaded once the loop ends.
+//! See the `ping_pong` example for a comparison with tracing and shading
+//! each batch in turn.
+
+use rayon;
+
+use ray::IntersectContext;
+use ray_stream::{RayHitN, RayN};
+use scene::CommittedScene;
+
+/// Two ray streams, one being traced and one being shaded
+pub struct PingPongStreams {
+    streams: [RayHitN; 2],
+    front: usize,
+}
+
+impl PingPongStreams {
+    /// Create the streams, each holding `n` rays. The front stream starts
+    /// out empty.
+    pub fn new(n: usize) -> PingPongStreams {
+        let mut front = RayHitN::new(RayN::new(n));
+        front.resize(0);
+        PingPongStreams {
+            streams: [front, RayHitN::new(RayN::new(n))],
+            front: 0,
+        }
+    }
+    /// Get the stream which was traced last, ready to shade
+    pub fn front(&self) -> &RayHitN {
+        &self.streams[self.front]
+    }
+    pub fn front_mut(&mut self) -> &mut RayHitN {
+        &mut self.streams[self.front]
+    }
+    /// Get the stream to fill with the next batch of rays and trace
+    pub fn back_mut(&mut self) -> &mut RayHitN {
+        &mut self.streams[1 - self.front]
+    }
+    /// Get the front and back streams at once, to shade the front while
+    /// the back is traced
+    pub fn split_mut(&mut self) -> (&mut RayHitN, &mut RayHitN) {
+        let (a, b) = self.streams.split_at_mut(1);
+        if self.front == 0 {
+            (&mut a[0], &mut b[0])
+        } else {
+            (&mut b[0], &mut a[0])
+        }
+    }
+    /// Make the back stream, which was just traced, the front stream to
+    /// shade, and the front the back to fill with the next batch. The new
+    /// back stream is restored to its full size if it was compacted.
+    pub fn swap(&mut self) {
+        self.front = 1 - self.front;
+        let back = self.back_mut();
+        let n = back.capacity();
+        back.resize(n);
+    }
+    /// Trace the back stream against the scene while running `shade` on
+    /// the front stream with `rayon::join`, then swap the streams. Returns
+    /// the result of `shade`.
+    pub fn trace_and_shade<S, R>(&mut self, scene: &CommittedScene, shade: S) -> R
+    where
+        S: FnOnce(&mut RayHitN) -> R + Send,
+        R: Send,
+    {
+        let (front, back) = self.split_mut();
+        let (_, result) = rayon::join(
+            || scene.intersect_stream_soa(&mut IntersectContext::coherent(), back),
+            || shade(front),
+        );
+        self.swap();
+        result
+    }
+}
+
+#[test]
+fn test_ping_pong_swap() {
+    use soa_ray::SoARay;
+
+    let mut streams = PingPongStreams::new(8);
+    assert_eq!(streams.front().len(), 0);
+    assert_eq!(streams.back_mut().len(), 8);
+    streams.back_mut().ray.set_id(0, 42);
+    streams.swap();
+    assert_eq!(streams.front().ray.id(0), 42);
+    assert_eq!(streams.back_mut().len(), 8);
+    {
+        let (front, back) = streams.split_mut();
+        assert_eq!(front.ray.id(0), 42);
+        back.ray.set_id(0, 7);
+        // Compacting a stream doesn't shrink it for the next batch
+        front.compact(&[]);
+    }
+    streams.swap();
+    assert_eq!(streams.front().ray.id(0), 7);
+    // The rays restored by the resize are reset
+    assert_eq!(streams.back_mut().len(), 8);
+    assert_eq!(streams.back_mut().ray.id(0), 0);
+}
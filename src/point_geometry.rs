@@ -0,0 +1,182 @@
+use cgmath::{Vector3, Vector4};
+
+use buffer::Buffer;
+use device::Device;
+use geometry;
+use linear_bounds;
+use sys::*;
+use {Bounds, BufferType, Format, GeometryType};
+
+/// How each point of a `PointGeometry` is rendered
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum PointType {
+    /// A sphere of the point's radius
+    Sphere,
+    /// A disc of the point's radius facing the ray
+    Disc,
+    /// A disc of the point's radius facing along the point's normal
+    OrientedDisc,
+}
+
+impl PointType {
+    /// Get the buffers which must be set before committing points of the
+    /// type, oriented discs need normals
+    pub fn required_buffers(&self) -> &'static [BufferType] {
+        match *self {
+            PointType::OrientedDisc => &[BufferType::VERTEX, BufferType::NORMAL],
+            _ => &[BufferType::VERTEX],
+        }
+    }
+}
+
+/// A set of points rendered as spheres or discs, e.g. particles. Each
+/// point is a primitive, stored in the vertex buffer as its position in
+/// xyz and radius in w.
+pub struct PointGeometry<'a> {
+    pub(crate) handle: RTCGeometry,
+    pub(crate) point_type: PointType,
+    pub vertex_buffer: Buffer<'a, Vector4<f32>>,
+    /// The normal of each point, only set for oriented discs
+    pub normal_buffer: Option<Buffer<'a, Vector3<f32>>>,
+}
+
+impl<'a> PointGeometry<'a> {
+    /// The buffers which must be set before committing the geometry, see
+    /// `PointType::required_buffers` for the buffers each type needs
+    pub const REQUIRED: &'static [BufferType] = &[BufferType::VERTEX];
+    /// The buffers the geometry can optionally use
+    pub const OPTIONAL: &'static [BufferType] = &[BufferType::NORMAL, BufferType::VERTEX_ATTRIBUTE];
+    pub fn unanimated(
+        device: &'a Device,
+        point_type: PointType,
+        num_points: usize,
+    ) -> PointGeometry<'a> {
+        let geom_type = match point_type {
+            PointType::Sphere => GeometryType::SPHERE_POINT,
+            PointType::Disc => GeometryType::DISC_POINT,
+            PointType::OrientedDisc => GeometryType::ORIENTED_DISC_POINT,
+        };
+        let h = unsafe { geometry::new_handle(device, geom_type) };
+        let mut vertex_buffer = Buffer::new(device, num_points);
+        let mut normal_buffer = None;
+        unsafe {
+            rtcSetGeometryBuffer(
+                h,
+                BufferType::VERTEX,
+                0,
+                Format::FLOAT4,
+                vertex_buffer.handle,
+                0,
+                16,
+                num_points,
+            );
+            vertex_buffer.set_attachment(h, BufferType::VERTEX, 0);
+
+            if point_type == PointType::OrientedDisc {
+                let mut normals = Buffer::new(device, num_points);
+                rtcSetGeometryBuffer(
+                    h,
+                    BufferType::NORMAL,
+                    0,
+                    Format::FLOAT3,
+                    normals.handle,
+                    0,
+                    12,
+                    num_points,
+                );
+                normals.set_attachment(h, BufferType::NORMAL, 0);
+                normal_buffer = Some(normals);
+            }
+        }
+        PointGeometry {
+            handle: h,
+            point_type,
+            vertex_buffer,
+            normal_buffer,
+        }
+    }
+    /// Create and commit spheres from the position and radius of each
+    /// point, given as `[x, y, z, radius]`
+    pub fn spheres(device: &'a Device, points: &[[f32; 4]]) -> PointGeometry<'a> {
+        PointGeometry::unanimated(device, PointType::Sphere, points.len())
+            .with_points(points)
+            .committed()
+    }
+    /// Create and commit ray facing discs from the position and radius of
+    /// each point, given as `[x, y, z, radius]`
+    pub fn discs(device: &'a Device, points: &[[f32; 4]]) -> PointGeometry<'a> {
+        PointGeometry::unanimated(device, PointType::Disc, points.len())
+            .with_points(points)
+            .committed()
+    }
+    /// Create and commit oriented discs from the position and radius of
+    /// each point, given as `[x, y, z, radius]`, and the normal of each
+    /// disc.
+    ///
+    /// Panics if there isn't a normal for each point.
+    pub fn oriented_discs(
+        device: &'a Device,
+        points: &[[f32; 4]],
+        normals: &[[f32; 3]],
+    ) -> PointGeometry<'a> {
+        PointGeometry::unanimated(device, PointType::OrientedDisc, points.len())
+            .with_points(points)
+            .with_normals(normals)
+            .committed()
+    }
+    /// Copy the position and radius of each point, given as
+    /// `[x, y, z, radius]`, into the vertex buffer.
+    ///
+    /// Panics if the number of points doesn't match the vertex buffer.
+    pub fn with_points(mut self, points: &[[f32; 4]]) -> PointGeometry<'a> {
+        assert_eq!(
+            points.len(),
+            self.vertex_buffer.len(),
+            "There must be a position and radius for each point"
+        );
+        {
+            let mut verts = self.vertex_buffer.map();
+            for (i, p) in points.iter().enumerate() {
+                verts[i] = Vector4::from(*p);
+            }
+        }
+        self
+    }
+    /// Copy the normal of each point into the normal buffer.
+    ///
+    /// Panics if the points aren't oriented discs or the number of
+    /// normals doesn't match the number of points.
+    pub fn with_normals(mut self, normals: &[[f32; 3]]) -> PointGeometry<'a> {
+        {
+            let buf = self
+                .normal_buffer
+                .as_mut()
+                .expect("Only oriented discs have normals");
+            assert_eq!(
+                normals.len(),
+                buf.len(),
+                "There must be a normal for each point"
+            );
+            let mut mapped = buf.map();
+            for (i, n) in normals.iter().enumerate() {
+                mapped[i] = Vector3::from(*n);
+            }
+        }
+        self
+    }
+    fn committed(self) -> PointGeometry<'a> {
+        geometry::commit_handle(self.handle);
+        self
+    }
+    pub fn point_type(&self) -> PointType {
+        self.point_type
+    }
+    /// Get the bounds of the points, padded by their radius. Discs are
+    /// bounded as if they were spheres.
+    pub fn bounds(&self) -> Bounds {
+        linear_bounds::fit_vertices(&[self.vertex_buffer.as_slice()]).bounds0
+    }
+}
+
+unsafe impl<'a> Sync for PointGeometry<'a> {}
+unsafe impl<'a> Send for PointGeometry<'a> {}
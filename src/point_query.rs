@@ -0,0 +1,182 @@
+use std::os::raw;
+
+use cgmath::Vector3;
+use rayon::prelude::*;
+
+use filter;
+use scene::CommittedScene;
+use sys;
+
+/// A point query for finding the primitives within `radius` of a point,
+/// e.g. to perform closest point lookups. See `CommittedScene::point_query`.
+pub type PointQuery = sys::RTCPointQuery;
+pub type PointQueryContext = sys::RTCPointQueryContext;
+
+impl PointQuery {
+    /// Create a query for the primitives within `radius` of `p`
+    pub fn new(p: Vector3<f32>, radius: f32) -> PointQuery {
+        sys::RTCPointQuery {
+            x: p.x,
+            y: p.y,
+            z: p.z,
+            time: 0.0,
+            radius,
+        }
+    }
+    /// Create a query for the closest primitive to `p`, the radius should
+    /// be shrunk as closer primitives are found to cull the search
+    pub fn closest(p: Vector3<f32>) -> PointQuery {
+        PointQuery::new(p, f32::INFINITY)
+    }
+    pub fn point(&self) -> Vector3<f32> {
+        Vector3::new(self.x, self.y, self.z)
+    }
+}
+
+impl PointQueryContext {
+    pub fn new() -> PointQueryContext {
+        sys::RTCPointQueryContext {
+            world2inst: [[0.0; 16]; 1],
+            inst2world: [[0.0; 16]; 1],
+            instID: [u32::MAX; 1],
+            instStackSize: 0,
+        }
+    }
+}
+
+impl Default for PointQueryContext {
+    fn default() -> PointQueryContext {
+        PointQueryContext::new()
+    }
+}
+
+/// A primitive found within the radius of a point query
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PointQueryPrimitive {
+    pub geom_id: u32,
+    pub prim_id: u32,
+    /// The ID of the instance the primitive was found through, or
+    /// `u32::MAX` if it is not instanced
+    pub inst_id: u32,
+    /// The scale of the instance transform if it is a similarity
+    /// transform, distances computed in the instance's space must be
+    /// multiplied by it. Zero if the transform is not a similarity.
+    pub similarity_scale: f32,
+}
+
+/// The number of queries of a batch run together as a task, amortizing the
+/// cost of scheduling it
+const BATCH_CHUNK: usize = 64;
+
+/// Call the Rust closure passed through the user pointer for the primitive.
+/// A panic in the closure is held until the query returns, and the query's
+/// remaining primitives are skipped.
+unsafe extern "C" fn point_query_callback<F>(args: *mut sys::RTCPointQueryFunctionArguments) -> bool
+where
+    F: FnMut(&mut PointQuery, &PointQueryPrimitive) -> bool,
+{
+    filter::catch_panic(|| call_point_query::<F>(&mut *args)).unwrap_or(false)
+}
+
+unsafe fn call_point_query<F>(args: &mut sys::RTCPointQueryFunctionArguments) -> bool
+where
+    F: FnMut(&mut PointQuery, &PointQueryPrimitive) -> bool,
+{
+    let callback = &mut *(args.userPtr as *mut F);
+    let ctx = &*args.context;
+    let prim = PointQueryPrimitive {
+        geom_id: args.geomID,
+        prim_id: args.primID,
+        inst_id: if ctx.instStackSize > 0 {
+            ctx.instID[0]
+        } else {
+            u32::MAX
+        },
+        similarity_scale: args.similarityScale,
+    };
+    callback(&mut *args.query, &prim)
+}
+
+/// Get the point query function which calls the closure type passed
+fn callback_for<F>(_: &F) -> sys::RTCPointQueryFunction
+where
+    F: FnMut(&mut PointQuery, &PointQueryPrimitive) -> bool,
+{
+    Some(point_query_callback::<F>)
+}
+
+fn user_ptr<F>(callback: &mut F) -> *mut raw::c_void {
+    callback as *mut F as *mut raw::c_void
+}
+
+impl<'a> CommittedScene<'a> {
+    /// Find the primitives within the radius of the point query. The
+    /// callback is called for each primitive which may be within the
+    /// radius, and should compute the distance to it. When searching for
+    /// the closest primitive the callback can shrink the query radius to
+    /// cull the rest of the search, in which case it must return true.
+    /// Returns true if the query radius was changed by any callback. A
+    /// panic in the callback is resumed once the query returns.
+    pub fn point_query<F>(&self, query: &mut PointQuery, mut callback: F) -> bool
+    where
+        F: FnMut(&mut PointQuery, &PointQueryPrimitive) -> bool,
+    {
+        let mut ctx = PointQueryContext::new();
+        let changed = unsafe {
+            sys::rtcPointQuery(
+                self.handle,
+                query as *mut sys::RTCPointQuery,
+                &mut ctx as *mut sys::RTCPointQueryContext,
+                callback_for(&callback),
+                user_ptr(&mut callback),
+            )
+        };
+        filter::resume_held_panic();
+        changed
+    }
+    /// Run a batch of point queries on rayon's thread pool. The callback is
+    /// run as in `point_query`, and is passed the entry in `results`
+    /// corresponding to the query being run, so results can be accumulated
+    /// per query, e.g. the closest primitive found so far. A panic in the
+    /// callback is resumed on the calling thread.
+    ///
+    /// Panics if `queries` and `results` are not the same length.
+    pub fn point_query_batch<T, F>(
+        &self,
+        queries: &mut [PointQuery],
+        results: &mut [T],
+        callback: F,
+    ) where
+        T: Send,
+        F: Fn(&mut PointQuery, &mut T, &PointQueryPrimitive) -> bool + Sync,
+    {
+        assert_eq!(
+            queries.len(),
+            results.len(),
+            "A result is required for each point query"
+        );
+        let callback = &callback;
+        queries
+            .par_chunks_mut(BATCH_CHUNK)
+            .zip(results.par_chunks_mut(BATCH_CHUNK))
+            .for_each(|(qs, rs)| {
+                let mut ctx = PointQueryContext::new();
+                for (q, r) in qs.iter_mut().zip(rs.iter_mut()) {
+                    let mut f =
+                        |q: &mut PointQuery, prim: &PointQueryPrimitive| callback(q, r, prim);
+                    ctx.instStackSize = 0;
+                    unsafe {
+                        sys::rtcPointQuery(
+                            self.handle,
+                            q as *mut sys::RTCPointQuery,
+                            &mut ctx as *mut sys::RTCPointQueryContext,
+                            callback_for(&f),
+                            user_ptr(&mut f),
+                        );
+                    }
+                    // Rayon resumes the panic on the calling thread
+                    filter::resume_held_panic();
+                }
+            });
+    }
+}
@@ -2,7 +2,7 @@ use cgmath::Vector4;
 
 use buffer::Buffer;
 use device::Device;
-use geometry::Geometry;
+use geometry::{self, MeshError};
 use sys::*;
 use {BufferType, Format, GeometryType};
 
@@ -14,8 +14,12 @@ pub struct QuadMesh<'a> {
 }
 
 impl<'a> QuadMesh<'a> {
+    /// The buffers which must be set before committing the geometry
+    pub const REQUIRED: &'static [BufferType] = &[BufferType::VERTEX, BufferType::INDEX];
+    /// The buffers the geometry can optionally use
+    pub const OPTIONAL: &'static [BufferType] = &[BufferType::VERTEX_ATTRIBUTE];
     pub fn unanimated(device: &'a Device, num_quads: usize, num_verts: usize) -> QuadMesh<'a> {
-        let h = unsafe { rtcNewGeometry(device.handle, GeometryType::QUAD) };
+        let h = unsafe { geometry::new_handle(device, GeometryType::QUAD) };
         let mut vertex_buffer = Buffer::new(device, num_verts);
         let mut index_buffer = Buffer::new(device, num_quads);
         unsafe {
@@ -50,6 +54,30 @@ impl<'a> QuadMesh<'a> {
             index_buffer: index_buffer,
         }
     }
+    /// Create and commit a quad mesh from slices of vertex positions and
+    /// quad indices, copying the data into the mesh buffers. See
+    /// `TriangleMesh::try_from_slices`.
+    pub fn try_from_slices(
+        device: &'a Device,
+        positions: &[[f32; 3]],
+        indices: &[[u32; 4]],
+    ) -> Result<QuadMesh<'a>, MeshError> {
+        geometry::validate_indices(indices, positions.len())?;
+        let mut mesh = QuadMesh::unanimated(device, indices.len(), positions.len());
+        {
+            let mut verts = mesh.vertex_buffer.map();
+            for (i, p) in positions.iter().enumerate() {
+                verts[i] = Vector4::new(p[0], p[1], p[2], 0.0);
+            }
+            let mut quads = mesh.index_buffer.map();
+            for (i, q) in indices.iter().enumerate() {
+                quads[i] = Vector4::from(*q);
+            }
+        }
+        geometry::commit_handle(mesh.handle);
+        Ok(mesh)
+    }
 }
 
 unsafe impl<'a> Sync for QuadMesh<'a> {}
+unsafe impl<'a> Send for QuadMesh<'a> {}
@@ -1,6 +1,10 @@
 use cgmath::Vector3;
 use std::{f32, u32};
 
+use geometry::Geometry;
+use light_group;
+use ray_mask::RayMask;
+use scene::{GeomId, Scene};
 use sys;
 
 pub type Ray = sys::RTCRay;
@@ -29,6 +33,42 @@ impl Ray {
             flags: 0,
         }
     }
+    /// Set the ray's mask, the ray only intersects geometry whose mask
+    /// shares a set bit with it. See the `ray_mask` module.
+    pub fn with_mask(mut self, mask: RayMask) -> Ray {
+        self.mask = mask.bits();
+        self
+    }
+    /// Get the ray's mask
+    pub fn ray_mask(&self) -> RayMask {
+        RayMask(self.mask)
+    }
+    /// Set the ray's mask to only intersect geometry in the light group,
+    /// e.g. for a shadow ray towards a light in the group. See the
+    /// `light_group` module.
+    ///
+    /// Panics if the group is out of range.
+    pub fn for_light_group(mut self, group: u8) -> Ray {
+        self.mask = light_group::light_group_mask(group);
+        self
+    }
+    /// Get the origin of the ray
+    pub fn origin(&self) -> Vector3<f32> {
+        Vector3::new(self.org_x, self.org_y, self.org_z)
+    }
+    /// Get the direction of the ray
+    pub fn dir(&self) -> Vector3<f32> {
+        Vector3::new(self.dir_x, self.dir_y, self.dir_z)
+    }
+    /// Convert from Embree's ray type, which has the same layout, see
+    /// the `ray_layout` module
+    pub fn from_sys(ray: sys::RTCRay) -> Ray {
+        ray
+    }
+    /// Convert to Embree's ray type, e.g. to pass it to native code
+    pub fn into_sys(self) -> sys::RTCRay {
+        self
+    }
 }
 
 impl Hit {
@@ -47,6 +87,73 @@ impl Hit {
     pub fn hit(&self) -> bool {
         self.geomID != u32::MAX
     }
+    /// Get the unnormalized geometric normal of the hit
+    pub fn normal(&self) -> Vector3<f32> {
+        Vector3::new(self.Ng_x, self.Ng_y, self.Ng_z)
+    }
+    /// Get the hit's parametric coordinates on the primitive
+    pub fn uv(&self) -> (f32, f32) {
+        (self.u, self.v)
+    }
+    /// Resolve the hit geometry against the scene it was attached to, or
+    /// `None` if there was no hit or the scene has no geometry with the ID.
+    /// For hits on instanced geometry pass the instanced scene.
+    pub fn geom_id(&self, scene: &Scene) -> Option<GeomId> {
+        if self.hit() {
+            scene.geom_id(self.geomID)
+        } else {
+            None
+        }
+    }
+    /// Resolve the hit instance against the top level scene it was attached
+    /// to, or `None` if the hit wasn't on instanced geometry
+    pub fn instance_id(&self, scene: &Scene) -> Option<GeomId> {
+        if self.instID[0] != u32::MAX {
+            scene.geom_id(self.instID[0])
+        } else {
+            None
+        }
+    }
+    /// Get the hit geometry from the scene it was attached to, see `geom_id`
+    pub fn geometry<'s, 'a>(&self, scene: &'s Scene<'a>) -> Option<&'s Geometry<'a>> {
+        self.geom_id(scene).and_then(|id| scene.geometry(id))
+    }
+    /// Convert from Embree's hit type, which has the same layout
+    pub fn from_sys(hit: sys::RTCHit) -> Hit {
+        hit
+    }
+    /// Convert to Embree's hit type
+    pub fn into_sys(self) -> sys::RTCHit {
+        self
+    }
+}
+
+/// The result of an occlusion query, see `CommittedScene::occlusion`
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Occlusion {
+    /// Geometry blocks the ray between its `tnear` and `tfar`
+    Occluded,
+    /// Nothing blocks the ray
+    Visible,
+}
+
+impl Occlusion {
+    /// Read the result of an occlusion query run in place on the ray by
+    /// `CommittedScene::occluded`, which marks occluded rays by setting
+    /// their `tfar` to -inf
+    pub fn of(ray: &Ray) -> Occlusion {
+        if ray.tfar == f32::NEG_INFINITY {
+            Occlusion::Occluded
+        } else {
+            Occlusion::Visible
+        }
+    }
+    pub fn is_occluded(&self) -> bool {
+        *self == Occlusion::Occluded
+    }
+    pub fn is_visible(&self) -> bool {
+        *self == Occlusion::Visible
+    }
 }
 
 impl RayHit {
@@ -56,6 +163,14 @@ impl RayHit {
             hit: Hit::new(),
         }
     }
+    /// Convert from Embree's ray and hit type, which has the same layout
+    pub fn from_sys(ray_hit: sys::RTCRayHit) -> RayHit {
+        ray_hit
+    }
+    /// Convert to Embree's ray and hit type
+    pub fn into_sys(self) -> sys::RTCRayHit {
+        self
+    }
 }
 
 impl IntersectContext {
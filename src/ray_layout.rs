@@ -0,0 +1,179 @@
+//! Layout compatibility of the single ray types with Embree's `RTCRay`,
+//! `RTCHit` and `RTCRayHit`. `Ray`, `Hit` and `RayHit` are the types
+//! generated from Embree's headers, and their size, alignment and field
+//! offsets are checked against the Embree 3 ABI at compile time, so a
+//! build against bindings which drifted from it fails instead of passing
+//! rays Embree would misread.
+//!
+//! Rays can be passed to native code expecting the Embree types as is,
+//! `Ray::into_sys` and `Ray::from_sys` (and the same on `Hit` and
+//! `RayHit`) are free. Rays of other crates binding Embree, or of a
+//! renderer's own `#[repr(C)]` types with the same layout, can be viewed
+//! as this crate's rays without copying with `cast_slice`:
+//!
+//! ```no_run
+//! # extern crate embree;
+//! # use embree::{ray_layout, RayHit};
+//! #[repr(C, align(16))]
+//! #[derive(Copy, Clone)]
+//! struct MyRayHit {
+//!     ray: [f32; 12],
+//!     hit: [f32; 8],
+//! }
+//! # let mut my_rays: Vec<MyRayHit> = Vec::new();
+//! let rays: &mut [RayHit] = unsafe { ray_layout::cast_slice_mut(&mut my_rays) };
+//! ```
+
+use std::mem::{align_of, size_of};
+use std::slice;
+
+use ray::{Hit, Ray, RayHit};
+
+/// Assert at compile time that the field of the type is at the offset
+/// Embree expects
+macro_rules! assert_offset {
+    ($t:ty, $field:ident, $offset:expr) => {
+        const _: () = assert!(
+            ::std::mem::offset_of!($t, $field) == $offset,
+            concat!(stringify!($t), "::", stringify!($field), " is misplaced")
+        );
+    };
+}
+
+/// Assert at compile time that the type has the size and alignment
+/// Embree expects
+macro_rules! assert_layout {
+    ($t:ty, $size:expr, $align:expr) => {
+        const _: () = assert!(
+            size_of::<$t>() == $size && align_of::<$t>() == $align,
+            concat!(stringify!($t), " doesn't match Embree's layout")
+        );
+    };
+}
+
+assert_layout!(Ray, 48, 16);
+assert_offset!(Ray, org_x, 0);
+assert_offset!(Ray, org_y, 4);
+assert_offset!(Ray, org_z, 8);
+assert_offset!(Ray, tnear, 12);
+assert_offset!(Ray, dir_x, 16);
+assert_offset!(Ray, dir_y, 20);
+assert_offset!(Ray, dir_z, 24);
+assert_offset!(Ray, time, 28);
+assert_offset!(Ray, tfar, 32);
+assert_offset!(Ray, mask, 36);
+assert_offset!(Ray, id, 40);
+assert_offset!(Ray, flags, 44);
+
+assert_layout!(Hit, 32, 16);
+assert_offset!(Hit, Ng_x, 0);
+assert_offset!(Hit, Ng_y, 4);
+assert_offset!(Hit, Ng_z, 8);
+assert_offset!(Hit, u, 12);
+assert_offset!(Hit, v, 16);
+assert_offset!(Hit, primID, 20);
+assert_offset!(Hit, geomID, 24);
+assert_offset!(Hit, instID, 28);
+
+assert_layout!(RayHit, 80, 16);
+assert_offset!(RayHit, ray, 0);
+assert_offset!(RayHit, hit, 48);
+
+/// The ray types with the layout of an Embree type, which slices can be
+/// cast to with `cast_slice`
+pub trait EmbreeLayout: Copy + private::Sealed {}
+
+impl EmbreeLayout for Ray {}
+impl EmbreeLayout for Hit {}
+impl EmbreeLayout for RayHit {}
+
+mod private {
+    use ray::{Hit, Ray, RayHit};
+
+    pub trait Sealed {}
+    impl Sealed for Ray {}
+    impl Sealed for Hit {}
+    impl Sealed for RayHit {}
+}
+
+/// Check the foreign type has the size and alignment of the ray type
+fn check_layout<T, U>() {
+    assert!(
+        size_of::<T>() == size_of::<U>() && align_of::<T>() >= align_of::<U>(),
+        "The type cast from must have the size and at least the alignment of the type cast to"
+    );
+}
+
+/// View a slice of another crate's rays as rays of this crate without
+/// copying. Panics if `T` doesn't have the size and at least the
+/// alignment of `U`.
+///
+/// # Safety
+/// `T` must be `#[repr(C)]` with fields of the same types and order as
+/// the Embree type `U` has, or an equivalent layout such as an array.
+pub unsafe fn cast_slice<T, U: EmbreeLayout>(rays: &[T]) -> &[U] {
+    check_layout::<T, U>();
+    slice::from_raw_parts(rays.as_ptr() as *const U, rays.len())
+}
+
+/// View a mutable slice of another crate's rays as rays of this crate,
+/// e.g. to trace them in place. See `cast_slice`.
+///
+/// # Safety
+/// See `cast_slice`.
+pub unsafe fn cast_slice_mut<T, U: EmbreeLayout>(rays: &mut [T]) -> &mut [U] {
+    check_layout::<T, U>();
+    slice::from_raw_parts_mut(rays.as_mut_ptr() as *mut U, rays.len())
+}
+
+#[cfg(test)]
+#[repr(C, align(16))]
+#[derive(Copy, Clone)]
+struct ForeignRay {
+    org: [f32; 3],
+    tnear: f32,
+    dir: [f32; 3],
+    time: f32,
+    tfar: f32,
+    mask: u32,
+    id: u32,
+    flags: u32,
+}
+
+#[test]
+fn test_cast_slice() {
+    let mut foreign = vec![
+        ForeignRay {
+            org: [1.0, 2.0, 3.0],
+            tnear: 0.5,
+            dir: [0.0, 0.0, -1.0],
+            time: 0.25,
+            tfar: 10.0,
+            mask: 3,
+            id: 7,
+            flags: 0,
+        };
+        2
+    ];
+    {
+        let rays: &mut [Ray] = unsafe { cast_slice_mut(&mut foreign) };
+        assert_eq!(rays.len(), 2);
+        assert_eq!(rays[1].org_z, 3.0);
+        assert_eq!(
+            (rays[1].tnear, rays[1].time, rays[1].tfar),
+            (0.5, 0.25, 10.0)
+        );
+        assert_eq!((rays[1].mask, rays[1].id), (3, 7));
+        rays[0].tfar = 2.0;
+    }
+    assert_eq!(foreign[0].tfar, 2.0);
+    let ray = Ray::from_sys(unsafe { cast_slice::<_, Ray>(&foreign) }[0]);
+    assert_eq!(ray.into_sys().tfar, 2.0);
+}
+
+#[test]
+#[should_panic]
+fn test_cast_slice_size_mismatch() {
+    let foreign = [[0.0f32; 8]; 2];
+    let _: &[Ray] = unsafe { cast_slice(&foreign) };
+}
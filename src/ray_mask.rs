@@ -0,0 +1,101 @@
+//! Typed ray and geometry masks. A ray only intersects geometry whose mask
+//! shares a set bit with the ray's mask, letting rays skip whole classes of
+//! geometry, e.g. camera rays skipping shadow-only proxies or shadow rays
+//! skipping glass. `RayMask` wraps the 32 bit masks so they aren't mixed
+//! up with other integers, and can be combined with `|`, `&` and `!`.
+//!
+//! Embree must be built with `EMBREE_RAY_MASK` enabled for masks to have an
+//! effect, otherwise all rays intersect all geometry, which can be checked
+//! with `Device::supports_ray_masks`. See the `light_group` module for a
+//! scheme splitting the mask bits between the application and light
+//! linking.
+
+use std::ops::{BitAnd, BitOr, Not};
+
+/// The mask of a ray or geometry
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct RayMask(pub u32);
+
+impl RayMask {
+    /// The mask with all bits set, the default for rays and geometry
+    pub const ALL: RayMask = RayMask(u32::MAX);
+    /// The mask with no bits set, a ray with it intersects nothing
+    pub const NONE: RayMask = RayMask(0);
+
+    /// Get the mask with only bit `bit` set.
+    ///
+    /// Panics if the bit is 32 or larger.
+    pub fn bit(bit: u32) -> RayMask {
+        assert!(
+            bit < 32,
+            "Ray masks have 32 bits, bit {} is out of range",
+            bit
+        );
+        RayMask(1 << bit)
+    }
+    pub fn bits(&self) -> u32 {
+        self.0
+    }
+    /// Whether a ray with one of the masks intersects geometry with the
+    /// other, i.e. they share a set bit
+    pub fn intersects(&self, other: RayMask) -> bool {
+        self.0 & other.0 != 0
+    }
+}
+
+impl Default for RayMask {
+    fn default() -> RayMask {
+        RayMask::ALL
+    }
+}
+
+impl From<u32> for RayMask {
+    fn from(bits: u32) -> RayMask {
+        RayMask(bits)
+    }
+}
+
+impl From<RayMask> for u32 {
+    fn from(mask: RayMask) -> u32 {
+        mask.0
+    }
+}
+
+impl BitOr for RayMask {
+    type Output = RayMask;
+    fn bitor(self, rhs: RayMask) -> RayMask {
+        RayMask(self.0 | rhs.0)
+    }
+}
+
+impl BitAnd for RayMask {
+    type Output = RayMask;
+    fn bitand(self, rhs: RayMask) -> RayMask {
+        RayMask(self.0 & rhs.0)
+    }
+}
+
+impl Not for RayMask {
+    type Output = RayMask;
+    fn not(self) -> RayMask {
+        RayMask(!self.0)
+    }
+}
+
+#[test]
+fn test_ray_mask_ops() {
+    let camera = RayMask::bit(0);
+    let shadow = RayMask::bit(1);
+    assert_eq!((camera | shadow).bits(), 0b11);
+    assert!(RayMask::ALL.intersects(shadow));
+    assert!(!camera.intersects(shadow));
+    assert!(!(!camera).intersects(camera));
+    assert_eq!(RayMask::ALL & shadow, shadow);
+    assert!(!RayMask::NONE.intersects(RayMask::ALL));
+}
+
+#[test]
+#[should_panic]
+fn test_ray_mask_bit_range() {
+    RayMask::bit(32);
+}
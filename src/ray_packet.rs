@@ -2,15 +2,25 @@ use cgmath::Vector3;
 use std::marker::PhantomData;
 use std::{f32, u32};
 
+use filter;
+use ray::IntersectContext;
+use scene::CommittedScene;
 use soa_ray::{
     SoAHit, SoAHitIter, SoAHitIterMut, SoAHitRef, SoARay, SoARayIter, SoARayIterMut, SoARayRef,
     SoARayRefMut,
 };
+use statistics;
 use sys;
 
 pub type Ray4 = sys::RTCRay4;
 pub type Hit4 = sys::RTCHit4;
 pub type RayHit4 = sys::RTCRayHit4;
+pub type Ray8 = sys::RTCRay8;
+pub type Hit8 = sys::RTCHit8;
+pub type RayHit8 = sys::RTCRayHit8;
+pub type Ray16 = sys::RTCRay16;
+pub type Hit16 = sys::RTCHit16;
+pub type RayHit16 = sys::RTCRayHit16;
 
 impl Ray4 {
     pub fn empty() -> Ray4 {
@@ -194,4 +204,149 @@ impl RayHit4 {
     pub fn iter(&self) -> std::iter::Zip<SoARayIter<Ray4>, SoAHitIter<Hit4>> {
         self.ray.iter().zip(self.hit.iter())
     }
+    /// Iterate over the rays and hits of the lanes which hit something
+    pub fn valid_lanes<'a>(
+        &'a self,
+    ) -> impl Iterator<Item = (SoARayRef<'a, Ray4>, SoAHitRef<'a, Hit4>)> + 'a {
+        self.iter().filter(|(_, h)| h.hit())
+    }
+}
+
+/// The valid masks of the packets, which Embree requires to be aligned
+/// like the packets
+#[repr(C, align(16))]
+struct ValidMask4([i32; 4]);
+#[repr(C, align(32))]
+struct ValidMask8([i32; 8]);
+#[repr(C, align(64))]
+struct ValidMask16([i32; 16]);
+
+/// Check the packet has the alignment Embree requires of it. The packet
+/// types are declared with it, so this only fails for packets made from
+/// misaligned memory with pointer casts.
+fn debug_check_aligned<T>(packet: &T, align: usize) {
+    debug_assert!(
+        (packet as *const T as usize).is_multiple_of(align),
+        "Ray packets must be aligned to {} bytes",
+        align
+    );
+}
+
+impl<'a> CommittedScene<'a> {
+    /// Intersect a packet of 4 rays with the scene, for the lanes whose
+    /// `valid` entry is -1, lanes with 0 are left unchanged. The mask is
+    /// copied to meet Embree's alignment requirement for it.
+    pub fn intersect4(&self, ctx: &mut IntersectContext, ray: &mut RayHit4, valid: &[i32; 4]) {
+        debug_check_aligned(ray, 16);
+        let valid = ValidMask4(*valid);
+        self.scene
+            .ray_counters
+            .count_intersect(statistics::active_lanes(&valid.0));
+        unsafe {
+            sys::rtcIntersect4(
+                valid.0.as_ptr(),
+                self.handle,
+                ctx as *mut sys::RTCIntersectContext,
+                ray as *mut sys::RTCRayHit4,
+            );
+        }
+        filter::resume_held_panic();
+    }
+    /// Test the rays of a packet of 4 for occlusion, for the lanes whose
+    /// `valid` entry is -1. Occluded rays have their `tfar` set to -inf.
+    pub fn occluded4(&self, ctx: &mut IntersectContext, ray: &mut Ray4, valid: &[i32; 4]) {
+        debug_check_aligned(ray, 16);
+        let valid = ValidMask4(*valid);
+        self.scene
+            .ray_counters
+            .count_occluded(statistics::active_lanes(&valid.0));
+        unsafe {
+            sys::rtcOccluded4(
+                valid.0.as_ptr(),
+                self.handle,
+                ctx as *mut sys::RTCIntersectContext,
+                ray as *mut sys::RTCRay4,
+            );
+        }
+        filter::resume_held_panic();
+    }
+    /// Intersect a packet of 8 rays with the scene, for the lanes whose
+    /// `valid` entry is -1. The mask is copied to meet Embree's alignment
+    /// requirement for it.
+    pub fn intersect8(&self, ctx: &mut IntersectContext, ray: &mut RayHit8, valid: &[i32; 8]) {
+        debug_check_aligned(ray, 32);
+        let valid = ValidMask8(*valid);
+        self.scene
+            .ray_counters
+            .count_intersect(statistics::active_lanes(&valid.0));
+        unsafe {
+            sys::rtcIntersect8(
+                valid.0.as_ptr(),
+                self.handle,
+                ctx as *mut sys::RTCIntersectContext,
+                ray as *mut sys::RTCRayHit8,
+            );
+        }
+        filter::resume_held_panic();
+    }
+    /// Test the rays of a packet of 8 for occlusion, see `occluded4`
+    pub fn occluded8(&self, ctx: &mut IntersectContext, ray: &mut Ray8, valid: &[i32; 8]) {
+        debug_check_aligned(ray, 32);
+        let valid = ValidMask8(*valid);
+        self.scene
+            .ray_counters
+            .count_occluded(statistics::active_lanes(&valid.0));
+        unsafe {
+            sys::rtcOccluded8(
+                valid.0.as_ptr(),
+                self.handle,
+                ctx as *mut sys::RTCIntersectContext,
+                ray as *mut sys::RTCRay8,
+            );
+        }
+        filter::resume_held_panic();
+    }
+    /// Intersect a packet of 16 rays with the scene, see `intersect8`
+    pub fn intersect16(&self, ctx: &mut IntersectContext, ray: &mut RayHit16, valid: &[i32; 16]) {
+        debug_check_aligned(ray, 64);
+        let valid = ValidMask16(*valid);
+        self.scene
+            .ray_counters
+            .count_intersect(statistics::active_lanes(&valid.0));
+        unsafe {
+            sys::rtcIntersect16(
+                valid.0.as_ptr(),
+                self.handle,
+                ctx as *mut sys::RTCIntersectContext,
+                ray as *mut sys::RTCRayHit16,
+            );
+        }
+        filter::resume_held_panic();
+    }
+    /// Test the rays of a packet of 16 for occlusion, see `occluded4`
+    pub fn occluded16(&self, ctx: &mut IntersectContext, ray: &mut Ray16, valid: &[i32; 16]) {
+        debug_check_aligned(ray, 64);
+        let valid = ValidMask16(*valid);
+        self.scene
+            .ray_counters
+            .count_occluded(statistics::active_lanes(&valid.0));
+        unsafe {
+            sys::rtcOccluded16(
+                valid.0.as_ptr(),
+                self.handle,
+                ctx as *mut sys::RTCIntersectContext,
+                ray as *mut sys::RTCRay16,
+            );
+        }
+        filter::resume_held_panic();
+    }
+}
+
+#[test]
+fn test_packet_alignment() {
+    use std::mem::align_of;
+    assert_eq!(align_of::<ValidMask4>(), align_of::<RayHit4>());
+    assert_eq!(align_of::<ValidMask8>(), align_of::<RayHit8>());
+    assert_eq!(align_of::<ValidMask16>(), align_of::<RayHit16>());
+    assert_eq!(align_of::<Ray16>(), 64);
 }
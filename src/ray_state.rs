@@ -0,0 +1,138 @@
+//! Per-ray state for progressive and wavefront tracers, where the paths
+//! in a ray stream terminate at different bounces and are compacted out
+//! of the stream. `RayStateVec` holds the payload of each ray, e.g. its
+//! pixel and throughput, and is compacted together with the stream so the
+//! payload at index `i` always belongs to ray `i`. Partitioning the stream
+//! with `HitPartition` only produces lists of indices without moving the
+//! rays, so the state can be indexed with the partition's indices as well.
+
+use std::ops::{Deref, DerefMut};
+
+use ray_stream::{check_keep, Compact};
+
+/// The state of each ray in a stream, kept index-synchronized with the
+/// stream by compacting it along with the stream through `compact_with`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RayStateVec<T> {
+    state: Vec<T>,
+}
+
+impl<T> RayStateVec<T> {
+    pub fn new() -> RayStateVec<T> {
+        RayStateVec { state: Vec::new() }
+    }
+    /// Create an empty state vector with room for the state of `n` rays
+    pub fn with_capacity(n: usize) -> RayStateVec<T> {
+        RayStateVec {
+            state: Vec::with_capacity(n),
+        }
+    }
+    /// Replace the state with the state of `n` new rays, computed by `f`
+    /// from each ray's index, e.g. after filling the stream with primary
+    /// rays. The storage is reused once it's grown to the stream size.
+    pub fn fill_with<F>(&mut self, n: usize, f: F)
+    where
+        F: FnMut(usize) -> T,
+    {
+        self.state.clear();
+        self.state.extend((0..n).map(f));
+    }
+    pub fn push(&mut self, value: T) {
+        self.state.push(value);
+    }
+    pub fn clear(&mut self) {
+        self.state.clear();
+    }
+    /// Compact the state in place to hold only the state of the rays at
+    /// the indices in `keep`, see `RayN::compact`. The state of the other
+    /// rays is dropped.
+    ///
+    /// Panics if the indices in `keep` aren't strictly increasing or are
+    /// out of bounds.
+    pub fn compact(&mut self, keep: &[u32]) {
+        check_keep(keep, self.state.len());
+        // Each kept element moves forward, so swapping it with the slot
+        // it moves to only moves dead or already moved elements back
+        for (i, k) in keep.iter().enumerate() {
+            self.state.swap(i, *k as usize);
+        }
+        self.state.truncate(keep.len());
+    }
+    /// Compact the stream and the state together with the same indices,
+    /// keeping the state of each ray with the ray.
+    ///
+    /// Panics if the stream and state have different lengths, or if the
+    /// indices in `keep` aren't strictly increasing or are out of bounds.
+    pub fn compact_with<S>(&mut self, stream: &mut S, keep: &[u32])
+    where
+        S: Compact + ?Sized,
+    {
+        assert_eq!(
+            stream.len(),
+            self.state.len(),
+            "Ray stream and state vector are out of sync"
+        );
+        stream.compact(keep);
+        self.compact(keep);
+    }
+    pub fn into_vec(self) -> Vec<T> {
+        self.state
+    }
+}
+
+impl<T> Default for RayStateVec<T> {
+    fn default() -> RayStateVec<T> {
+        RayStateVec::new()
+    }
+}
+
+impl<T> From<Vec<T>> for RayStateVec<T> {
+    fn from(state: Vec<T>) -> RayStateVec<T> {
+        RayStateVec { state }
+    }
+}
+
+impl<T> Deref for RayStateVec<T> {
+    type Target = [T];
+    fn deref(&self) -> &[T] {
+        &self.state
+    }
+}
+
+impl<T> DerefMut for RayStateVec<T> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        &mut self.state
+    }
+}
+
+impl<T> Compact for RayStateVec<T> {
+    fn len(&self) -> usize {
+        self.state.len()
+    }
+    fn compact(&mut self, keep: &[u32]) {
+        RayStateVec::compact(self, keep)
+    }
+}
+
+#[test]
+fn test_compact_with_stream() {
+    use ray_stream::RayN;
+    use soa_ray::SoARay;
+
+    let mut rays = RayN::new(5);
+    let mut state = RayStateVec::with_capacity(5);
+    state.fill_with(5, |i| format!("ray {}", i));
+    for i in 0..5 {
+        rays.set_id(i, i as u32);
+    }
+    state.compact_with(&mut rays, &[0, 3, 4]);
+    assert_eq!(state.len(), 3);
+    assert_eq!(rays.len(), 3);
+    for (i, s) in state.iter().enumerate() {
+        assert_eq!(*s, format!("ray {}", rays.id(i)));
+    }
+
+    state.compact_with(&mut rays, &[1]);
+    assert_eq!(&*state, &["ray 3".to_string()][..]);
+    assert_eq!(rays.id(0), 3);
+}
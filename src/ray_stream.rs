@@ -1,14 +1,51 @@
 use cgmath::Vector3;
+use std::any::Any;
 use std::iter::Iterator;
 use std::marker::PhantomData;
+use std::mem;
+use std::panic::{self, AssertUnwindSafe};
 use std::{f32, u32};
 
+use filter;
+use ray::{Hit, IntersectContext, Occlusion, Ray, RayHit};
+use scene::CommittedScene;
 use soa_ray::{
     SoAHit, SoAHitIter, SoAHitIterMut, SoAHitRef, SoARay, SoARayIter, SoARayIterMut, SoARayRef,
     SoARayRefMut,
 };
 use sys;
-use {aligned_vector, aligned_vector_init};
+use {aligned_vector, aligned_vector_init, SceneFlags};
+
+/// A rectangular region of pixels in an image, e.g. the region covered
+/// by a ray stream of primary rays
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Tile {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Tile {
+    pub fn new(x: u32, y: u32, width: u32, height: u32) -> Tile {
+        Tile {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+    /// The number of pixels in the tile
+    pub fn pixel_count(&self) -> usize {
+        (self.width * self.height) as usize
+    }
+    /// Iterate over the image space coordinates of the pixels in the tile
+    /// in row-major order
+    pub fn pixels(&self) -> impl Iterator<Item = (u32, u32)> {
+        let (x, y, w, h) = (self.x, self.y, self.width, self.height);
+        (y..y + h).flat_map(move |j| (x..x + w).map(move |i| (i, j)))
+    }
+}
 
 /// A ray stream stored in SoA format
 pub struct RayN {
@@ -54,6 +91,95 @@ impl RayN {
     pub fn len(&self) -> usize {
         self.org_x.len()
     }
+    /// The number of rays the stream was allocated to hold
+    pub fn capacity(&self) -> usize {
+        self.org_x.capacity()
+    }
+    /// Resize the stream to hold `n` rays, e.g. to restore a compacted
+    /// stream to its full size. Rays added to the stream are initialized
+    /// as by `RayN::new`. The stream's storage is never reallocated, so it
+    /// can only be resized up to its capacity.
+    ///
+    /// Panics if `n` is larger than the capacity of the stream.
+    pub fn resize(&mut self, n: usize) {
+        check_capacity(n, self.capacity());
+        self.org_x.resize(n, 0.0);
+        self.org_y.resize(n, 0.0);
+        self.org_z.resize(n, 0.0);
+        self.tnear.resize(n, 0.0);
+        self.dir_x.resize(n, 0.0);
+        self.dir_y.resize(n, 0.0);
+        self.dir_z.resize(n, 0.0);
+        self.time.resize(n, 0.0);
+        self.tfar.resize(n, f32::INFINITY);
+        self.mask.resize(n, u32::MAX);
+        self.id.resize(n, 0);
+        self.flags.resize(n, 0);
+    }
+    /// Compact the stream in place to hold only the rays at the indices
+    /// in `keep`, e.g. the paths still alive after a bounce. The ray at
+    /// `keep[i]` is moved to index `i` and the stream is shrunk to
+    /// `keep.len()` rays, keeping its capacity.
+    ///
+    /// Panics if the indices in `keep` aren't strictly increasing or are
+    /// out of bounds.
+    pub fn compact(&mut self, keep: &[u32]) {
+        check_keep(keep, self.len());
+        compact_vec(&mut self.org_x, keep);
+        compact_vec(&mut self.org_y, keep);
+        compact_vec(&mut self.org_z, keep);
+        compact_vec(&mut self.tnear, keep);
+        compact_vec(&mut self.dir_x, keep);
+        compact_vec(&mut self.dir_y, keep);
+        compact_vec(&mut self.dir_z, keep);
+        compact_vec(&mut self.time, keep);
+        compact_vec(&mut self.tfar, keep);
+        compact_vec(&mut self.mask, keep);
+        compact_vec(&mut self.id, keep);
+        compact_vec(&mut self.flags, keep);
+    }
+    /// Fill the stream with the primary rays for the pixels in the tile, in
+    /// row-major order. `camera` is called with the image space coordinates
+    /// of each pixel's center and returns the origin and direction of the
+    /// ray through it. The id of each ray is set to the index of its pixel
+    /// in the tile, so results can be mapped back to the image. If the
+    /// stream holds more rays than pixels in the tile, the rays past the
+    /// end of the tile are made inactive by giving them an empty range.
+    ///
+    /// Panics if the stream is too small to hold the rays for the tile.
+    pub fn fill_primary<F>(&mut self, camera: F, tile: &Tile, time: f32, mask: u32)
+    where
+        F: Fn(f32, f32) -> (Vector3<f32>, Vector3<f32>),
+    {
+        let n = tile.pixel_count();
+        assert!(
+            n <= self.len(),
+            "Ray stream of {} rays is too small for a tile with {} pixels",
+            self.len(),
+            n
+        );
+        for (r, (i, j)) in tile.pixels().enumerate() {
+            let (o, d) = camera(i as f32 + 0.5, j as f32 + 0.5);
+            self.org_x[r] = o.x;
+            self.org_y[r] = o.y;
+            self.org_z[r] = o.z;
+            self.dir_x[r] = d.x;
+            self.dir_y[r] = d.y;
+            self.dir_z[r] = d.z;
+        }
+        // The remaining attributes are uniform over the stream
+        self.tnear[..n].fill(0.0);
+        self.tfar[..n].fill(f32::INFINITY);
+        self.time.fill(time);
+        self.mask.fill(mask);
+        self.flags.fill(0);
+        for (r, id) in self.id.iter_mut().enumerate() {
+            *id = r as u32;
+        }
+        // Embree skips rays with tnear > tfar
+        self.tnear[n..].fill(f32::INFINITY);
+        self.tfar[n..].fill(f32::NEG_INFINITY);
+    }
     pub unsafe fn as_raynp(&mut self) -> sys::RTCRayNp {
         sys::RTCRayNp {
             org_x: self.org_x.as_mut_ptr(),
@@ -173,6 +299,48 @@ impl HitN {
     pub fn len(&self) -> usize {
         self.ng_x.len()
     }
+    /// The number of hits the stream was allocated to hold
+    pub fn capacity(&self) -> usize {
+        self.ng_x.capacity()
+    }
+    /// Resize the stream to hold `n` hits, up to its capacity, see
+    /// `RayN::resize`. Hits added to the stream are misses.
+    ///
+    /// Panics if `n` is larger than the capacity of the stream.
+    pub fn resize(&mut self, n: usize) {
+        check_capacity(n, self.capacity());
+        self.ng_x.resize(n, 0.0);
+        self.ng_y.resize(n, 0.0);
+        self.ng_z.resize(n, 0.0);
+        self.u.resize(n, 0.0);
+        self.v.resize(n, 0.0);
+        self.prim_id.resize(n, u32::MAX);
+        self.geom_id.resize(n, u32::MAX);
+        self.inst_id.resize(n, u32::MAX);
+    }
+    /// Compact the stream in place to hold only the hits at the indices
+    /// in `keep`, see `RayN::compact`.
+    ///
+    /// Panics if the indices in `keep` aren't strictly increasing or are
+    /// out of bounds.
+    pub fn compact(&mut self, keep: &[u32]) {
+        check_keep(keep, self.len());
+        compact_vec(&mut self.ng_x, keep);
+        compact_vec(&mut self.ng_y, keep);
+        compact_vec(&mut self.ng_z, keep);
+        compact_vec(&mut self.u, keep);
+        compact_vec(&mut self.v, keep);
+        compact_vec(&mut self.prim_id, keep);
+        compact_vec(&mut self.geom_id, keep);
+        compact_vec(&mut self.inst_id, keep);
+    }
+    /// Mark every hit in the stream as a miss, to reuse the stream for
+    /// tracing another set of rays.
+    pub fn reset(&mut self) {
+        self.prim_id.fill(u32::MAX);
+        self.geom_id.fill(u32::MAX);
+        self.inst_id.fill(u32::MAX);
+    }
     pub unsafe fn as_hitnp(&mut self) -> sys::RTCHitNp {
         sys::RTCHitNp {
             Ng_x: self.ng_x.as_mut_ptr(),
@@ -245,9 +413,39 @@ impl RayHitN {
     pub fn iter(&self) -> std::iter::Zip<SoARayIter<RayN>, SoAHitIter<HitN>> {
         self.ray.iter().zip(self.hit.iter())
     }
+    /// Iterate over the rays and hits of the lanes which hit something
+    pub fn valid_lanes<'a>(
+        &'a self,
+    ) -> impl Iterator<Item = (SoARayRef<'a, RayN>, SoAHitRef<'a, HitN>)> + 'a {
+        self.iter().filter(|(_, h)| h.hit())
+    }
     pub fn len(&self) -> usize {
         self.ray.len()
     }
+    pub fn capacity(&self) -> usize {
+        self.ray.capacity()
+    }
+    /// Resize the rays and hits of the stream, see `RayN::resize`
+    pub fn resize(&mut self, n: usize) {
+        self.ray.resize(n);
+        self.hit.resize(n);
+    }
+    /// Compact the rays and hits of the stream in place to hold only the
+    /// ones at the indices in `keep`, see `RayN::compact`.
+    pub fn compact(&mut self, keep: &[u32]) {
+        self.ray.compact(keep);
+        self.hit.compact(keep);
+    }
+    /// Fill the ray stream with the primary rays for the pixels in the tile,
+    /// see `RayN::fill_primary`, and reset the hits so the stream can be
+    /// reused for tracing another tile.
+    pub fn fill_primary<F>(&mut self, camera: F, tile: &Tile, time: f32, mask: u32)
+    where
+        F: Fn(f32, f32) -> (Vector3<f32>, Vector3<f32>),
+    {
+        self.ray.fill_primary(camera, tile, time, mask);
+        self.hit.reset();
+    }
     pub unsafe fn as_rayhitnp(&mut self) -> sys::RTCRayHitNp {
         sys::RTCRayHitNp {
             ray: self.ray.as_raynp(),
@@ -255,3 +453,494 @@ impl RayHitN {
         }
     }
 }
+
+/// A stream of per-ray data which can be compacted in place, e.g. the
+/// rays and hits of a stream or the per-ray state carried along with them
+/// in a `RayStateVec`. Streams which are compacted with the same indices
+/// stay index-synchronized.
+pub trait Compact {
+    /// The number of elements in the stream
+    fn len(&self) -> usize;
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// Compact the stream in place to hold only the elements at the
+    /// indices in `keep`, which must be strictly increasing
+    fn compact(&mut self, keep: &[u32]);
+}
+
+impl Compact for RayN {
+    fn len(&self) -> usize {
+        RayN::len(self)
+    }
+    fn compact(&mut self, keep: &[u32]) {
+        RayN::compact(self, keep)
+    }
+}
+
+impl Compact for HitN {
+    fn len(&self) -> usize {
+        HitN::len(self)
+    }
+    fn compact(&mut self, keep: &[u32]) {
+        HitN::compact(self, keep)
+    }
+}
+
+impl Compact for RayHitN {
+    fn len(&self) -> usize {
+        RayHitN::len(self)
+    }
+    fn compact(&mut self, keep: &[u32]) {
+        RayHitN::compact(self, keep)
+    }
+}
+
+fn check_capacity(n: usize, capacity: usize) {
+    assert!(
+        n <= capacity,
+        "Can't resize a stream with capacity {} to {} elements",
+        capacity,
+        n
+    );
+}
+
+/// Check the indices to keep when compacting are strictly increasing and
+/// in bounds, so compaction can move each element forward in place.
+pub(crate) fn check_keep(keep: &[u32], len: usize) {
+    for w in keep.windows(2) {
+        assert!(
+            w[0] < w[1],
+            "Compaction indices must be strictly increasing"
+        );
+    }
+    if let Some(last) = keep.last() {
+        assert!(
+            (*last as usize) < len,
+            "Compaction index {} out of bounds for a stream of {}",
+            last,
+            len
+        );
+    }
+}
+
+fn compact_vec<T: Copy>(v: &mut Vec<T>, keep: &[u32]) {
+    for (i, k) in keep.iter().enumerate() {
+        v[i] = v[*k as usize];
+    }
+    v.truncate(keep.len());
+}
+
+/// An intersection context carrying the payloads and filter of a stream
+/// query with payloads, found from the context Embree passes to the
+/// context filter function
+#[repr(C)]
+struct PayloadContext<T, F> {
+    ctx: IntersectContext,
+    payloads: *mut T,
+    filter: *mut F,
+    /// A panic in the filter, held until the query returns as unwinding
+    /// into Embree would abort
+    panic: Option<Box<dyn Any + Send>>,
+}
+
+/// The context filter of a stream query with payloads, calling the query's
+/// filter with each candidate hit and the payload of its ray
+unsafe extern "C" fn payload_filter<T, F>(args: *const sys::RTCFilterFunctionNArguments)
+where
+    F: FnMut(&Ray, &Hit, &mut T) -> bool,
+{
+    let args = &*args;
+    let ctx = &mut *(args.context as *mut PayloadContext<T, F>);
+    if ctx.panic.is_some() {
+        return;
+    }
+    let n = args.N as usize;
+    for i in 0..n {
+        let valid = args.valid.add(i);
+        if *valid == 0 {
+            continue;
+        }
+        let ray = filter::ray_n(args.ray, n, i);
+        let hit = filter::hit_n(args.hit, n, i);
+        // The ray ids were checked against the payloads before the query
+        let payload = &mut *ctx.payloads.add(ray.id as usize);
+        let filter = ctx.filter;
+        match panic::catch_unwind(AssertUnwindSafe(|| (*filter)(&ray, &hit, payload))) {
+            Ok(true) => {}
+            Ok(false) => *valid = 0,
+            Err(p) => {
+                ctx.panic = Some(p);
+                return;
+            }
+        }
+    }
+}
+
+/// Check the scene calls context filters and each ray's id indexes a payload
+fn check_payloads<I: Iterator<Item = u32>>(scene: &CommittedScene, ids: I, payloads: usize) {
+    assert!(
+        scene.scene.flags().0 & SceneFlags::CONTEXT_FILTER_FUNCTION.0 != 0,
+        "Stream queries with payloads require a scene built with \
+         SceneFlags::CONTEXT_FILTER_FUNCTION"
+    );
+    for id in ids {
+        assert!(
+            (id as usize) < payloads,
+            "Ray id {} out of bounds for {} payloads",
+            id,
+            payloads
+        );
+    }
+}
+
+/// The most rays traced by each stream query of `trace_stream` and the
+/// other iterator based queries, keeping the rays and hits of a query
+/// within the caches while amortizing the cost of the call
+const TRACE_CHUNK: usize = 1024;
+
+impl<'a> CommittedScene<'a> {
+    pub fn intersect_stream_aos(&self, ctx: &mut IntersectContext, rays: &mut [RayHit]) {
+        let m = rays.len();
+        self.scene.ray_counters.count_intersect(m);
+        unsafe {
+            sys::rtcIntersect1M(
+                self.handle,
+                ctx as *mut sys::RTCIntersectContext,
+                rays.as_mut_ptr(),
+                m as u32,
+                mem::size_of::<RayHit>(),
+            );
+        }
+        filter::resume_held_panic();
+        if let Some(capture) = self.scene.ray_capture() {
+            capture.record_aos(rays);
+        }
+    }
+    pub fn occluded_stream_aos(&self, ctx: &mut IntersectContext, rays: &mut [Ray]) {
+        let m = rays.len();
+        self.scene.ray_counters.count_occluded(m);
+        unsafe {
+            sys::rtcOccluded1M(
+                self.handle,
+                ctx as *mut sys::RTCIntersectContext,
+                rays.as_mut_ptr(),
+                m as u32,
+                mem::size_of::<Ray>(),
+            );
+        }
+        filter::resume_held_panic();
+        if let Some(capture) = self.scene.ray_capture() {
+            capture.record_aos_occluded(rays);
+        }
+    }
+    pub fn intersect_stream_soa(&self, ctx: &mut IntersectContext, rays: &mut RayHitN) {
+        let n = rays.len();
+        self.scene.ray_counters.count_intersect(n);
+        unsafe {
+            let mut rayhit = rays.as_rayhitnp();
+            sys::rtcIntersectNp(
+                self.handle,
+                ctx as *mut sys::RTCIntersectContext,
+                &mut rayhit as *mut sys::RTCRayHitNp,
+                n as u32,
+            );
+        }
+        filter::resume_held_panic();
+        if let Some(capture) = self.scene.ray_capture() {
+            capture.record_soa(rays);
+        }
+    }
+    pub fn occluded_stream_soa(&self, ctx: &mut IntersectContext, rays: &mut RayN) {
+        let n = rays.len();
+        self.scene.ray_counters.count_occluded(n);
+        unsafe {
+            let mut r = rays.as_raynp();
+            sys::rtcOccludedNp(
+                self.handle,
+                ctx as *mut sys::RTCIntersectContext,
+                &mut r as *mut sys::RTCRayNp,
+                n as u32,
+            );
+        }
+        filter::resume_held_panic();
+        if let Some(capture) = self.scene.ray_capture() {
+            capture.record_soa_occluded(rays);
+        }
+    }
+    /// Trace the rays through the scene, returning the closest hit of each
+    /// ray in order with the ray's `tfar` set to the hit distance. The rays
+    /// are traced as streams without the caller building them, in chunks
+    /// of up to `TRACE_CHUNK` rays. Embree gathers the rays of a stream
+    /// into packets internally, so the AoS layout is used as it needs no
+    /// conversion. Use `intersect_stream_aos` or `intersect_stream_soa`
+    /// to trace with a coherent context or reuse the stream buffers.
+    pub fn trace_stream<I>(&self, rays: I) -> Vec<RayHit>
+    where
+        I: IntoIterator<Item = Ray>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        let mut hits: Vec<RayHit> = rays.into_iter().map(RayHit::new).collect();
+        self.trace_stream_in_place(&mut hits);
+        hits
+    }
+    /// Trace the rays through the scene in place as in `trace_stream`,
+    /// setting the hit and `tfar` of each ray that hits the scene
+    pub fn trace_stream_in_place(&self, rays: &mut [RayHit]) {
+        let mut ctx = IntersectContext::incoherent();
+        for chunk in rays.chunks_mut(TRACE_CHUNK) {
+            self.intersect_stream_aos(&mut ctx, chunk);
+        }
+    }
+    /// Test each ray for occlusion, returning the results in order. The
+    /// rays are traced as streams, see `trace_stream`.
+    pub fn occlusion_stream<I>(&self, rays: I) -> Vec<Occlusion>
+    where
+        I: IntoIterator<Item = Ray>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        let mut rays: Vec<Ray> = rays.into_iter().collect();
+        let mut ctx = IntersectContext::incoherent();
+        for chunk in rays.chunks_mut(TRACE_CHUNK) {
+            self.occluded_stream_aos(&mut ctx, chunk);
+        }
+        rays.iter().map(Occlusion::of).collect()
+    }
+    /// Intersect the stream of rays with the scene as in
+    /// `intersect_stream_aos`, calling `filter` with each candidate hit
+    /// found and the payload of its ray, `payloads[ray.id]`. The filter
+    /// returns whether to accept the hit and can update the payload, e.g.
+    /// to accumulate transmittance through alpha tested surfaces or count
+    /// the surfaces crossed. It's called after any filter functions set on
+    /// the geometry, for the candidates they accept.
+    ///
+    /// The filter is called through Embree's context filter function, so
+    /// the scene must be built with `SceneFlags::CONTEXT_FILTER_FUNCTION`.
+    /// The query runs with a copy of `ctx` whose context filter is replaced.
+    ///
+    /// Panics if the scene wasn't built with the flag or a ray's id is out
+    /// of bounds of the payloads. A panic in `filter` is resumed once the
+    /// query returns.
+    pub fn intersect_stream_with<T, F>(
+        &self,
+        ctx: &IntersectContext,
+        rays: &mut [RayHit],
+        payloads: &mut [T],
+        mut filter: F,
+    ) where
+        F: FnMut(&Ray, &Hit, &mut T) -> bool,
+    {
+        check_payloads(self, rays.iter().map(|r| r.ray.id), payloads.len());
+        let mut payload_ctx = PayloadContext {
+            ctx: *ctx,
+            payloads: payloads.as_mut_ptr(),
+            filter: &mut filter as *mut F,
+            panic: None,
+        };
+        payload_ctx.ctx.filter = Some(payload_filter::<T, F>);
+        self.scene.ray_counters.count_intersect(rays.len());
+        unsafe {
+            sys::rtcIntersect1M(
+                self.handle,
+                &mut payload_ctx as *mut PayloadContext<T, F> as *mut sys::RTCIntersectContext,
+                rays.as_mut_ptr(),
+                rays.len() as u32,
+                mem::size_of::<RayHit>(),
+            );
+        }
+        filter::resume_held_panic();
+        if let Some(p) = payload_ctx.panic.take() {
+            panic::resume_unwind(p);
+        }
+        if let Some(capture) = self.scene.ray_capture() {
+            capture.record_aos(rays);
+        }
+    }
+    /// Test the stream of rays for occlusion as in `occluded_stream_aos`,
+    /// calling `filter` with each candidate hit found and the payload of
+    /// its ray, `payloads[ray.id]`. See `intersect_stream_with`.
+    pub fn occluded_stream_with<T, F>(
+        &self,
+        ctx: &IntersectContext,
+        rays: &mut [Ray],
+        payloads: &mut [T],
+        mut filter: F,
+    ) where
+        F: FnMut(&Ray, &Hit, &mut T) -> bool,
+    {
+        check_payloads(self, rays.iter().map(|r| r.id), payloads.len());
+        let mut payload_ctx = PayloadContext {
+            ctx: *ctx,
+            payloads: payloads.as_mut_ptr(),
+            filter: &mut filter as *mut F,
+            panic: None,
+        };
+        payload_ctx.ctx.filter = Some(payload_filter::<T, F>);
+        self.scene.ray_counters.count_occluded(rays.len());
+        unsafe {
+            sys::rtcOccluded1M(
+                self.handle,
+                &mut payload_ctx as *mut PayloadContext<T, F> as *mut sys::RTCIntersectContext,
+                rays.as_mut_ptr(),
+                rays.len() as u32,
+                mem::size_of::<Ray>(),
+            );
+        }
+        filter::resume_held_panic();
+        if let Some(p) = payload_ctx.panic.take() {
+            panic::resume_unwind(p);
+        }
+        if let Some(capture) = self.scene.ray_capture() {
+            capture.record_aos_occluded(rays);
+        }
+    }
+}
+
+#[test]
+fn test_payload_filter() {
+    // A packet of 4 rays with ids 3, 0, 2, 1 where the third is invalid
+    let n = 4;
+    let mut ray = vec![0u32; 12 * n];
+    ray[10 * n..11 * n].copy_from_slice(&[3, 0, 2, 1]);
+    let mut hit = vec![0u32; 8 * n];
+    hit[5 * n..6 * n].copy_from_slice(&[10, 11, 12, 13]);
+    let mut valid = [-1, -1, 0, -1];
+
+    let mut payloads = vec![Vec::new(); 4];
+    let mut filter = |r: &Ray, h: &Hit, p: &mut Vec<u32>| {
+        p.push(h.primID);
+        r.id < 2
+    };
+    let mut ctx = PayloadContext {
+        ctx: IntersectContext::incoherent(),
+        payloads: payloads.as_mut_ptr(),
+        filter: &mut filter as *mut _,
+        panic: None,
+    };
+    let args = sys::RTCFilterFunctionNArguments {
+        valid: valid.as_mut_ptr(),
+        geometryUserPtr: std::ptr::null_mut(),
+        context: &mut ctx as *mut _ as *mut sys::RTCIntersectContext,
+        ray: ray.as_mut_ptr() as *mut sys::RTCRayN,
+        hit: hit.as_mut_ptr() as *mut sys::RTCHitN,
+        N: n as u32,
+    };
+    fn filter_of<T, F>(
+        _: &PayloadContext<T, F>,
+    ) -> unsafe extern "C" fn(*const sys::RTCFilterFunctionNArguments)
+    where
+        F: FnMut(&Ray, &Hit, &mut T) -> bool,
+    {
+        payload_filter::<T, F>
+    }
+    unsafe {
+        filter_of(&ctx)(&args);
+    }
+    assert_eq!(payloads, vec![vec![11u32], vec![13], vec![], vec![10]]);
+    assert_eq!(valid, [0, -1, 0, -1]);
+    assert!(ctx.panic.is_none());
+
+    // A panic is held and later candidates aren't filtered
+    let mut calls = 0;
+    let mut filter = |_: &Ray, _: &Hit, _: &mut ()| -> bool {
+        calls += 1;
+        panic!("filter panicked")
+    };
+    let mut units = vec![(); 4];
+    let mut ctx = PayloadContext {
+        ctx: IntersectContext::incoherent(),
+        payloads: units.as_mut_ptr(),
+        filter: &mut filter as *mut _,
+        panic: None,
+    };
+    let mut valid = [-1, -1, 0, -1];
+    let args = sys::RTCFilterFunctionNArguments {
+        valid: valid.as_mut_ptr(),
+        context: &mut ctx as *mut _ as *mut sys::RTCIntersectContext,
+        ..args
+    };
+    unsafe {
+        filter_of(&ctx)(&args);
+        filter_of(&ctx)(&args);
+    }
+    assert!(ctx.panic.is_some());
+    assert_eq!(valid, [-1, -1, 0, -1]);
+    drop(ctx);
+    assert_eq!(calls, 1);
+}
+
+#[test]
+fn test_fill_primary() {
+    let tile = Tile::new(4, 2, 3, 2);
+    let mut rays = RayN::new(8);
+    rays.fill_primary(
+        |x, y| (Vector3::new(x, y, 0.0), Vector3::new(0.0, 0.0, -1.0)),
+        &tile,
+        0.5,
+        1,
+    );
+    let pixels: Vec<_> = tile.pixels().collect();
+    assert_eq!(pixels.len(), 6);
+    for r in 0..rays.len() {
+        assert_eq!(rays.time(r), 0.5);
+        assert_eq!(rays.mask(r), 1);
+        assert_eq!(rays.id(r), r as u32);
+        if r < pixels.len() {
+            let (i, j) = pixels[r];
+            assert_eq!(
+                rays.org(r),
+                Vector3::new(i as f32 + 0.5, j as f32 + 0.5, 0.0)
+            );
+            assert!(rays.tnear(r) <= rays.tfar(r));
+        } else {
+            assert!(rays.tnear(r) > rays.tfar(r));
+        }
+    }
+}
+
+#[test]
+fn test_compact_resize() {
+    let mut rays = RayHitN::new(RayN::new(6));
+    for i in 0..6 {
+        rays.ray.set_id(i, i as u32);
+        rays.ray.set_tfar(i, i as f32);
+        rays.hit.set_geom_id(i, 10 + i as u32);
+    }
+    rays.compact(&[1, 2, 5]);
+    assert_eq!(rays.len(), 3);
+    assert_eq!(rays.capacity(), 6);
+    for (i, k) in [1, 2, 5].iter().enumerate() {
+        assert_eq!(rays.ray.id(i), *k);
+        assert_eq!(rays.ray.tfar(i), *k as f32);
+        assert_eq!(rays.hit.geom_id(i), 10 + *k);
+    }
+
+    rays.resize(6);
+    assert_eq!(rays.len(), 6);
+    assert_eq!(rays.ray.id(2), 5);
+    assert_eq!(rays.ray.tfar(4), f32::INFINITY);
+    assert!(!rays.hit.hit(4));
+    rays.hit.reset();
+    assert!(!rays.hit.any_hit());
+}
+
+#[test]
+fn test_valid_lanes() {
+    let mut rays = RayHitN::new(RayN::new(4));
+    for i in 0..4 {
+        rays.ray.set_id(i, i as u32);
+    }
+    rays.hit.set_geom_id(1, 7);
+    rays.hit.set_geom_id(3, 8);
+    assert!(rays.hit.lane(0).is_none());
+    assert_eq!(rays.hit.lane(1).unwrap().geom_id(), 7);
+    let lanes: Vec<_> = rays
+        .valid_lanes()
+        .map(|(r, h)| (r.id(), h.lane(), h.geom_id()))
+        .collect();
+    assert_eq!(lanes, vec![(1, 1, 7), (3, 3, 8)]);
+
+    rays.hit.lane_mut(3).unwrap().set_prim_id(2);
+    assert_eq!(rays.hit.prim_id(3), 2);
+    assert!(rays.hit.lane_mut(2).is_none());
+}
@@ -0,0 +1,469 @@
+//! A slow, brute force reference intersector for debugging, enabled by
+//! the `reference` feature. `ReferenceScene` copies the triangle and quad
+//! meshes of a committed scene and intersects rays by testing every
+//! primitive in double precision, without a BVH. When a renderer shows
+//! holes, self intersections or hits on the wrong surface, tracing the
+//! same rays against the reference tells whether the problem is in
+//! Embree's traversal and precision or in how the scene was set up.
+//!
+//! The reference follows Embree's conventions for the hits it reports:
+//! the unnormalized geometric normal, the barycentric `u` and `v` of
+//! triangles, the bilinear `u` and `v` of quads, the primitive and
+//! geometry IDs, and marking occluded rays by setting `tfar` to -inf.
+//! Motion blurred triangle meshes are interpolated at the ray's time.
+//! Disabled geometry is skipped and shadow proxies made of triangles or
+//! quads are used for occlusion queries, as Embree does. Other kinds of
+//! geometry, instances, buffers shared from user memory, ray masks and
+//! filter functions aren't supported, the geometry which was left out is
+//! listed by `ReferenceScene::skipped`.
+//!
+//! `Tracer` switches between Embree and the reference at runtime, e.g.
+//! from a command line flag or with `Backend::from_env`, so a renderer can
+//! be pointed at the reference without changing its query code:
+//!
+//! ```no_run
+//! # extern crate cgmath;
+//! # extern crate embree;
+//! # use cgmath::Vector3;
+//! # use embree::{Device, Scene, SceneQuery};
+//! # use embree::reference::{Backend, Tracer};
+//! # let device = Device::new();
+//! # let scene = Scene::new(&device);
+//! let rtscene = scene.commit();
+//! let tracer = Tracer::new(&rtscene, Backend::from_env());
+//! let t = tracer.hit_distance(Vector3::new(0.0, 0.0, 1.0), Vector3::new(0.0, 0.0, -1.0));
+//! ```
+
+use std::{env, f32};
+
+use cgmath::{InnerSpace, Vector3, Vector4};
+
+use geometry::Geometry;
+use ray::{IntersectContext, Occlusion, Ray, RayHit};
+use scene::CommittedScene;
+use scene_query::SceneQuery;
+
+/// The primitives of a mesh, quads are split into two triangles the way
+/// Embree splits them
+#[derive(Debug, Clone)]
+enum Primitives {
+    Triangles(Vec<[u32; 3]>),
+    Quads(Vec<[u32; 4]>),
+}
+
+/// A copy of a triangle or quad mesh to intersect rays with
+#[derive(Debug, Clone)]
+struct RefMesh {
+    geom_id: u32,
+    /// The vertices of each time step
+    time_steps: Vec<Vec<Vector3<f64>>>,
+    prims: Primitives,
+}
+
+/// A hit on a triangle in double precision
+#[derive(Debug, Copy, Clone)]
+struct TriangleHit {
+    t: f64,
+    u: f64,
+    v: f64,
+    ng: Vector3<f64>,
+}
+
+/// Intersect the ray with the triangle, giving the barycentric `u` and `v`
+/// of the hit point `(1 - u - v) * v0 + u * v1 + v * v2`. Hits on the
+/// edges count.
+fn intersect_triangle(
+    org: Vector3<f64>,
+    dir: Vector3<f64>,
+    v0: Vector3<f64>,
+    v1: Vector3<f64>,
+    v2: Vector3<f64>,
+) -> Option<TriangleHit> {
+    let e1 = v1 - v0;
+    let e2 = v2 - v0;
+    let p = dir.cross(e2);
+    let det = e1.dot(p);
+    if det == 0.0 {
+        return None;
+    }
+    let s = org - v0;
+    let u = s.dot(p) / det;
+    let q = s.cross(e1);
+    let v = dir.dot(q) / det;
+    if u < 0.0 || v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+    Some(TriangleHit {
+        t: e2.dot(q) / det,
+        u,
+        v,
+        ng: e1.cross(e2),
+    })
+}
+
+impl RefMesh {
+    fn new(geom_id: u32, time_steps: Vec<Vec<Vector3<f64>>>, prims: Primitives) -> RefMesh {
+        RefMesh {
+            geom_id,
+            time_steps,
+            prims,
+        }
+    }
+    /// Copy a triangle or quad mesh, `None` for other kinds of geometry
+    fn from_geometry(geom_id: u32, geom: &Geometry) -> Option<RefMesh> {
+        let to_f64 = |v: &[Vector4<f32>]| -> Vec<Vector3<f64>> {
+            v.iter()
+                .map(|p| Vector3::new(p.x as f64, p.y as f64, p.z as f64))
+                .collect()
+        };
+        if !geom.owns_bound_buffers() {
+            return None;
+        }
+        match *geom {
+            Geometry::Triangle(ref m) => {
+                let time_steps = Some(&m.vertex_buffer)
+                    .into_iter()
+                    .chain(m.motion_vertex_buffers.iter())
+                    .map(|b| to_f64(b.as_slice()))
+                    .collect();
+                let tris = m
+                    .index_buffer
+                    .as_slice()
+                    .iter()
+                    .map(|t| [t.x, t.y, t.z])
+                    .collect();
+                Some(RefMesh::new(
+                    geom_id,
+                    time_steps,
+                    Primitives::Triangles(tris),
+                ))
+            }
+            Geometry::Quad(ref m) => {
+                let quads = m
+                    .index_buffer
+                    .as_slice()
+                    .iter()
+                    .map(|q| [q.x, q.y, q.z, q.w])
+                    .collect();
+                Some(RefMesh::new(
+                    geom_id,
+                    vec![to_f64(m.vertex_buffer.as_slice())],
+                    Primitives::Quads(quads),
+                ))
+            }
+            _ => None,
+        }
+    }
+    /// Get the vertex at the time, linearly interpolating between the
+    /// time steps of motion blurred meshes
+    fn vertex(&self, i: u32, time: f32) -> Vector3<f64> {
+        let steps = self.time_steps.len();
+        if steps == 1 {
+            return self.time_steps[0][i as usize];
+        }
+        let f = (time.clamp(0.0, 1.0) as f64) * (steps - 1) as f64;
+        let step = (f.floor() as usize).min(steps - 2);
+        let s = f - step as f64;
+        let a = self.time_steps[step][i as usize];
+        let b = self.time_steps[step + 1][i as usize];
+        a * (1.0 - s) + b * s
+    }
+    /// Intersect the ray with each primitive, updating it with the closest
+    /// hit. Returns true if the ray hit any primitive, stopping at the
+    /// first if `any_hit` is set.
+    fn intersect(&self, ray_hit: &mut RayHit, any_hit: bool) -> bool {
+        let ray = ray_hit.ray;
+        let org = ray.origin().cast::<f64>().unwrap();
+        let dir = ray.dir().cast::<f64>().unwrap();
+        let mut found = false;
+        let report = |ray_hit: &mut RayHit, prim: usize, hit: TriangleHit| {
+            if hit.t < ray.tnear as f64 || hit.t > ray_hit.ray.tfar as f64 {
+                return false;
+            }
+            ray_hit.ray.tfar = hit.t as f32;
+            ray_hit.hit.u = hit.u as f32;
+            ray_hit.hit.v = hit.v as f32;
+            ray_hit.hit.Ng_x = hit.ng.x as f32;
+            ray_hit.hit.Ng_y = hit.ng.y as f32;
+            ray_hit.hit.Ng_z = hit.ng.z as f32;
+            ray_hit.hit.primID = prim as u32;
+            ray_hit.hit.geomID = self.geom_id;
+            true
+        };
+        match self.prims {
+            Primitives::Triangles(ref tris) => {
+                for (i, t) in tris.iter().enumerate() {
+                    let v = [
+                        self.vertex(t[0], ray.time),
+                        self.vertex(t[1], ray.time),
+                        self.vertex(t[2], ray.time),
+                    ];
+                    if let Some(hit) = intersect_triangle(org, dir, v[0], v[1], v[2]) {
+                        found |= report(ray_hit, i, hit);
+                        if found && any_hit {
+                            return true;
+                        }
+                    }
+                }
+            }
+            Primitives::Quads(ref quads) => {
+                for (i, q) in quads.iter().enumerate() {
+                    let v = [
+                        self.vertex(q[0], ray.time),
+                        self.vertex(q[1], ray.time),
+                        self.vertex(q[2], ray.time),
+                        self.vertex(q[3], ray.time),
+                    ];
+                    // Embree splits the quad into the triangles (v0, v1, v3)
+                    // and (v2, v3, v1), the second's barycentrics flipped
+                    // to give the bilinear u and v over the quad
+                    let first = intersect_triangle(org, dir, v[0], v[1], v[3]);
+                    let second =
+                        intersect_triangle(org, dir, v[2], v[3], v[1]).map(|h| TriangleHit {
+                            u: 1.0 - h.u,
+                            v: 1.0 - h.v,
+                            ..h
+                        });
+                    for hit in first.into_iter().chain(second) {
+                        found |= report(ray_hit, i, hit);
+                        if found && any_hit {
+                            return true;
+                        }
+                    }
+                }
+            }
+        }
+        found
+    }
+}
+
+/// A brute force reference intersector built from a committed scene, see
+/// the `reference` module
+#[derive(Debug, Clone)]
+pub struct ReferenceScene {
+    meshes: Vec<RefMesh>,
+    /// The meshes occlusion queries are run against, where shadow proxies
+    /// replace the geometry they were set for
+    shadow_meshes: Option<Vec<RefMesh>>,
+    skipped: Vec<u32>,
+}
+
+impl ReferenceScene {
+    /// Copy the enabled triangle and quad meshes of the committed scene
+    pub fn new(scene: &CommittedScene) -> ReferenceScene {
+        let scene = scene.scene;
+        let mut meshes = Vec::new();
+        let mut shadow_meshes = Vec::new();
+        let mut has_proxies = false;
+        let mut skipped = Vec::new();
+        for (id, geom) in scene.iter_ordered() {
+            if !geom.is_enabled() {
+                continue;
+            }
+            let mesh = RefMesh::from_geometry(id, geom);
+            match mesh {
+                Some(ref m) => meshes.push(m.clone()),
+                None => skipped.push(id),
+            }
+            match scene.get_shadow_proxy(id) {
+                Some(proxy) => {
+                    has_proxies = true;
+                    shadow_meshes.extend(RefMesh::from_geometry(id, proxy));
+                }
+                None => shadow_meshes.extend(mesh),
+            }
+        }
+        ReferenceScene {
+            meshes,
+            shadow_meshes: if has_proxies {
+                Some(shadow_meshes)
+            } else {
+                None
+            },
+            skipped,
+        }
+    }
+    /// Get the IDs of the enabled geometry which isn't a triangle or quad
+    /// mesh, and so can't be hit by rays traced against the reference
+    pub fn skipped(&self) -> &[u32] {
+        &self.skipped
+    }
+    /// Find the closest hit along the ray, as `CommittedScene::intersect`.
+    /// The context is unused, as the reference doesn't call filter
+    /// functions.
+    pub fn intersect(&self, _ctx: &mut IntersectContext, ray: &mut RayHit) {
+        for m in self.meshes.iter() {
+            m.intersect(ray, false);
+        }
+    }
+    /// Test if the ray is occluded, setting its `tfar` to -inf if it is, as
+    /// `CommittedScene::occluded`
+    pub fn occluded(&self, _ctx: &mut IntersectContext, ray: &mut Ray) {
+        let meshes = self.shadow_meshes.as_ref().unwrap_or(&self.meshes);
+        let mut ray_hit = RayHit::new(*ray);
+        if meshes.iter().any(|m| m.intersect(&mut ray_hit, true)) {
+            ray.tfar = -f32::INFINITY;
+        }
+    }
+    /// Intersect a single ray with the scene, returning the closest hit if
+    /// any, as `CommittedScene::intersect_ray`
+    pub fn intersect_ray(&self, ray: &Ray) -> Option<RayHit> {
+        let mut ray_hit = RayHit::new(*ray);
+        self.intersect(&mut IntersectContext::incoherent(), &mut ray_hit);
+        if ray_hit.hit.hit() {
+            Some(ray_hit)
+        } else {
+            None
+        }
+    }
+    /// Test if the ray is occluded by any geometry in the scene, as
+    /// `CommittedScene::is_occluded`
+    pub fn is_occluded(&self, ray: &Ray) -> bool {
+        let mut r = *ray;
+        self.occluded(&mut IntersectContext::incoherent(), &mut r);
+        Occlusion::of(&r).is_occluded()
+    }
+}
+
+/// Which intersector a `Tracer` runs queries with
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Backend {
+    Embree,
+    Reference,
+}
+
+impl Backend {
+    /// Read the backend from the `EMBREE_RS_BACKEND` environment variable,
+    /// which selects the reference when set to `reference`. Defaults to
+    /// Embree.
+    pub fn from_env() -> Backend {
+        match env::var("EMBREE_RS_BACKEND") {
+            Ok(ref b) if b.eq_ignore_ascii_case("reference") => Backend::Reference,
+            _ => Backend::Embree,
+        }
+    }
+}
+
+/// Runs queries on a committed scene with Embree or the reference
+/// intersector, chosen at runtime
+pub enum Tracer<'a> {
+    Embree(&'a CommittedScene<'a>),
+    Reference(&'a CommittedScene<'a>, ReferenceScene),
+}
+
+impl<'a> Tracer<'a> {
+    /// Create a tracer for the scene, copying its meshes if the reference
+    /// backend is used
+    pub fn new(scene: &'a CommittedScene<'a>, backend: Backend) -> Tracer<'a> {
+        match backend {
+            Backend::Embree => Tracer::Embree(scene),
+            Backend::Reference => Tracer::Reference(scene, ReferenceScene::new(scene)),
+        }
+    }
+    pub fn backend(&self) -> Backend {
+        match *self {
+            Tracer::Embree(_) => Backend::Embree,
+            Tracer::Reference(..) => Backend::Reference,
+        }
+    }
+    /// Get the committed scene being traced
+    pub fn scene(&self) -> &'a CommittedScene<'a> {
+        match *self {
+            Tracer::Embree(s) | Tracer::Reference(s, _) => s,
+        }
+    }
+    pub fn intersect(&self, ctx: &mut IntersectContext, ray: &mut RayHit) {
+        match *self {
+            Tracer::Embree(s) => s.intersect(ctx, ray),
+            Tracer::Reference(_, ref r) => r.intersect(ctx, ray),
+        }
+    }
+    pub fn occluded(&self, ctx: &mut IntersectContext, ray: &mut Ray) {
+        match *self {
+            Tracer::Embree(s) => s.occluded(ctx, ray),
+            Tracer::Reference(_, ref r) => r.occluded(ctx, ray),
+        }
+    }
+    pub fn intersect_ray(&self, ray: &Ray) -> Option<RayHit> {
+        match *self {
+            Tracer::Embree(s) => s.intersect_ray(ray),
+            Tracer::Reference(_, ref r) => r.intersect_ray(ray),
+        }
+    }
+    pub fn is_occluded(&self, ray: &Ray) -> bool {
+        match *self {
+            Tracer::Embree(s) => s.is_occluded(ray),
+            Tracer::Reference(_, ref r) => r.is_occluded(ray),
+        }
+    }
+}
+
+impl<'a> SceneQuery for Tracer<'a> {
+    fn closest_hit(&self, origin: Vector3<f32>, dir: Vector3<f32>) -> Option<RayHit> {
+        self.intersect_ray(&Ray::new(origin, dir))
+    }
+    fn hit_distance(&self, origin: Vector3<f32>, dir: Vector3<f32>) -> Option<f32> {
+        self.closest_hit(origin, dir).map(|h| h.ray.tfar)
+    }
+    fn hit_point(&self, origin: Vector3<f32>, dir: Vector3<f32>) -> Option<Vector3<f32>> {
+        self.hit_distance(origin, dir).map(|t| origin + dir * t)
+    }
+    fn visible(&self, from: Vector3<f32>, to: Vector3<f32>) -> bool {
+        let ray = self.scene().traversal_settings().spawn_ray_to(from, to);
+        !self.is_occluded(&ray)
+    }
+}
+
+#[cfg(test)]
+fn unit_quad(geom_id: u32, z: f64) -> RefMesh {
+    let verts = vec![
+        Vector3::new(0.0, 0.0, z),
+        Vector3::new(1.0, 0.0, z),
+        Vector3::new(1.0, 1.0, z),
+        Vector3::new(0.0, 1.0, z),
+    ];
+    RefMesh::new(geom_id, vec![verts], Primitives::Quads(vec![[0, 1, 2, 3]]))
+}
+
+#[test]
+fn test_quad_bilinear_uv() {
+    let quad = unit_quad(3, 0.0);
+    for &(x, y) in &[(0.25, 0.1), (0.9, 0.8), (0.3, 0.6)] {
+        let ray = Ray::new(Vector3::new(x, y, 1.0), Vector3::new(0.0, 0.0, -1.0));
+        let mut ray_hit = RayHit::new(ray);
+        assert!(quad.intersect(&mut ray_hit, false));
+        assert_eq!((ray_hit.hit.geomID, ray_hit.hit.primID), (3, 0));
+        assert!((ray_hit.ray.tfar - 1.0).abs() < 1e-6);
+        assert!((ray_hit.hit.u - x).abs() < 1e-6 && (ray_hit.hit.v - y).abs() < 1e-6);
+        assert!(ray_hit.hit.normal().normalize().z > 0.999);
+    }
+    let ray = Ray::new(Vector3::new(1.5, 0.5, 1.0), Vector3::new(0.0, 0.0, -1.0));
+    assert!(!quad.intersect(&mut RayHit::new(ray), false));
+}
+
+#[test]
+fn test_closest_of_meshes() {
+    let scene = ReferenceScene {
+        meshes: vec![unit_quad(0, 0.0), unit_quad(1, 0.5)],
+        shadow_meshes: Some(vec![unit_quad(0, 0.0)]),
+        skipped: Vec::new(),
+    };
+    let ray = Ray::new(Vector3::new(0.5, 0.5, 1.0), Vector3::new(0.0, 0.0, -1.0));
+    let hit = scene.intersect_ray(&ray).unwrap();
+    assert_eq!(hit.hit.geomID, 1);
+    assert!((hit.ray.tfar - 0.5).abs() < 1e-6);
+    // Occlusion runs against the shadow meshes, which only have the
+    // farther quad
+    let blocked = Ray::segment(ray.origin(), ray.dir(), 0.0, 0.75);
+    assert!(!scene.is_occluded(&blocked));
+    assert!(scene.is_occluded(&ray));
+}
+
+#[test]
+fn test_motion_blur_vertex() {
+    let start = vec![Vector3::new(0.0, 0.0, 0.0)];
+    let end = vec![Vector3::new(2.0, 0.0, 0.0)];
+    let mesh = RefMesh::new(0, vec![start, end], Primitives::Triangles(Vec::new()));
+    assert_eq!(mesh.vertex(0, 0.25), Vector3::new(0.5, 0.0, 0.0));
+    assert_eq!(mesh.vertex(0, 1.0), Vector3::new(2.0, 0.0, 0.0));
+}
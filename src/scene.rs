@@ -1,13 +1,95 @@
+use std::any::Any;
 use std::collections::HashMap;
 use std::marker::PhantomData;
-use std::mem;
+use std::os::raw;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+#[cfg(feature = "streams")]
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Instant;
 
+use budget::{BudgetContext, BudgetedHit, QueryBudget};
+use bvh::empty_bounds;
+use collide::{self, Collision};
+#[cfg(feature = "streams")]
+use debug::RayCapture;
 use device::Device;
-use geometry::Geometry;
-use ray::{IntersectContext, Ray, RayHit};
-use ray_packet::{Ray4, RayHit4};
-use ray_stream::{RayHitN, RayN};
+use filter;
+use geometry::{self, Geometry, GeometryKind};
+use leak_check::{self, ObjectKind};
+use linear_bounds::{self, LinearBounds};
+use ray::{IntersectContext, Occlusion, Ray, RayHit};
+use ray_mask::RayMask;
+use statistics::RayCounters;
 use sys::*;
+use traversal::TraversalSettings;
+use validation::{self, ValidationError};
+use {Bounds, BuildQuality, Error, SceneFlags};
+
+/// Source of the commit tokens, shared by all scenes so tokens from
+/// different scenes are never equal
+static NEXT_COMMIT_TOKEN: AtomicU64 = AtomicU64::new(1);
+
+/// Source of the serials stamped on each attachment of a geometry, shared
+/// by all scenes so a `GeomId` only matches the attachment it came from
+static NEXT_ATTACH_SERIAL: AtomicU64 = AtomicU64::new(1);
+
+/// The ID of a geometry attached to a scene, returned by `Scene::attach`.
+/// The `u32` IDs Embree assigns are only unique within a scene, and are
+/// reused once geometry is detached. A `GeomId` also remembers which
+/// attachment it refers to, so looking it up in a different scene, or
+/// after the geometry was detached, returns `None` instead of some
+/// unrelated geometry.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct GeomId {
+    id: u32,
+    serial: u64,
+}
+
+impl GeomId {
+    /// Get the ID Embree assigned the geometry, as found in `Hit::geomID`
+    pub fn raw(&self) -> u32 {
+        self.id
+    }
+}
+
+/// Identifies a commit of a scene. Tokens increase monotonically with
+/// each commit, so results computed against a scene can be checked to
+/// come from its current BVH and not one from an earlier commit.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CommitToken(u64);
+
+/// A query result stamped with the commit of the scene it was computed
+/// against. The stamp is only stored in debug builds, where `get` asserts
+/// that the result is used with the latest commit of the scene. In release
+/// builds the check is compiled out.
+#[derive(Debug, Copy, Clone)]
+pub struct Stamped<T> {
+    value: T,
+    #[cfg(debug_assertions)]
+    token: CommitToken,
+}
+
+impl<T> Stamped<T> {
+    /// Get the result, asserting in debug builds that it was computed
+    /// against the latest commit of the scene passed.
+    pub fn get(&self, scene: &CommittedScene) -> &T {
+        #[cfg(debug_assertions)]
+        assert_eq!(
+            Some(self.token),
+            scene.scene.commit_token(),
+            "Result was computed against a different commit of the scene"
+        );
+        let _ = scene;
+        &self.value
+    }
+    /// Get the result without checking which commit it came from
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
 
 /// A scene containing various geometry for rendering. Geometry
 /// can be added and removed by attaching and detaching it, after
@@ -19,14 +101,104 @@ pub struct Scene<'a> {
     /// we just need to track its lifetime for correctness
     device: PhantomData<&'a Device>,
     geometry: HashMap<u32, Geometry<'a>>,
+    /// The IDs of the attached geometry in the order it was attached
+    attach_order: Vec<u32>,
+    /// The serial of each attached geometry's attachment, see `GeomId`
+    attach_serials: HashMap<u32, u64>,
+    /// The token of the last commit, 0 if the scene hasn't been committed
+    commit_token: AtomicU64,
+    /// Shadow proxies replacing geometry for occlusion queries, by the ID
+    /// of the geometry they replace
+    shadow_proxies: HashMap<u32, Geometry<'a>>,
+    /// Scene used for occlusion queries against the shadow proxies,
+    /// created when the first proxy is set. It has the same geometry as
+    /// the scene, attached by the same IDs, with the proxies replacing
+    /// the geometry they stand in for.
+    shadow_handle: Option<RTCScene>,
+    /// The build quality set on the scene, Embree doesn't provide a getter
+    build_quality: BuildQuality,
+    /// The traversal settings, the robust flag is read from the scene flags
+    traversal: TraversalSettings,
+    progress_monitor: Option<Box<ProgressMonitor>>,
+    /// Whether changed geometry is committed when the scene is
+    auto_commit_geometry: bool,
+    /// Records a sample of the rays traced by stream queries
+    #[cfg(feature = "streams")]
+    ray_capture: Option<Arc<RayCapture>>,
+    /// Counts of the rays traced against the scene, see `statistics`
+    pub(crate) ray_counters: RayCounters,
+    /// The time the last commit took in nanoseconds, 0 if the scene
+    /// hasn't been committed
+    pub(crate) build_time: AtomicU64,
+    /// The threads taking part in the current `join_commit`
+    join: Mutex<JoinState>,
+    /// The number of `CommittedScene`s viewing the scene, which a new
+    /// commit would rebuild the BVH under
+    live_views: AtomicUsize,
+}
+
+/// The threads in a `join_commit` and the token they share, a thread
+/// arriving while others are still building joins their commit
+#[derive(Debug, Default)]
+struct JoinState {
+    threads: usize,
+    token: Option<CommitToken>,
+}
+
+/// Reject committing a scene while views of a previous commit are alive
+fn live_views_panic() -> ! {
+    panic!("The scene can't be committed while a CommittedScene from a previous commit is alive");
+}
+
+/// Closure called by Embree with the progress of building a scene's BVH
+pub type ProgressMonitorFunction = dyn Fn(f64) -> bool + Send + Sync;
+
+/// The progress monitor closure set on a scene and the first panic it
+/// raised, which is held until the commit returns as unwinding into Embree
+/// would abort
+struct ProgressMonitor {
+    monitor: Box<ProgressMonitorFunction>,
+    panic: Mutex<Option<Box<dyn Any + Send>>>,
+}
+
+/// Call the Rust closure passed through the user pointer with the
+/// progress, cancelling the build if the closure panics
+unsafe extern "C" fn progress_monitor(ptr: *mut raw::c_void, n: f64) -> bool {
+    let state = &*(ptr as *const ProgressMonitor);
+    match panic::catch_unwind(AssertUnwindSafe(|| (state.monitor)(n))) {
+        Ok(proceed) => proceed,
+        Err(p) => {
+            let mut held = state.panic.lock().unwrap_or_else(|e| e.into_inner());
+            if held.is_none() {
+                *held = Some(p);
+            }
+            false
+        }
+    }
 }
 
 impl<'a> Scene<'a> {
     pub fn new(device: &'a Device) -> Scene {
+        leak_check::created(ObjectKind::Scene);
         Scene {
             handle: unsafe { rtcNewScene(device.handle) },
             device: PhantomData,
             geometry: HashMap::new(),
+            attach_order: Vec::new(),
+            attach_serials: HashMap::new(),
+            commit_token: AtomicU64::new(0),
+            shadow_proxies: HashMap::new(),
+            shadow_handle: None,
+            build_quality: BuildQuality::MEDIUM,
+            traversal: TraversalSettings::default(),
+            progress_monitor: None,
+            auto_commit_geometry: false,
+            #[cfg(feature = "streams")]
+            ray_capture: None,
+            ray_counters: RayCounters::default(),
+            build_time: AtomicU64::new(0),
+            join: Mutex::new(JoinState::default()),
+            live_views: AtomicUsize::new(0),
         }
     }
     /// Attach a new geometry to the scene. Returns the scene local ID which
@@ -34,33 +206,401 @@ impl<'a> Scene<'a> {
     /// A geometry can only be attached to one Scene at a time, per the Embree
     /// documentation. The geometry can be detached from the scene to move
     /// it to another one.
+    ///
+    /// Panics if the scene already has as many geometries as Embree has
+    /// IDs for, see `try_attach_geometry`.
     pub fn attach_geometry(&mut self, mesh: Geometry<'a>) -> u32 {
+        match self.try_attach_geometry(mesh) {
+            Ok(id) => id,
+            Err(e) => panic!("Failed to attach geometry: {}", e),
+        }
+    }
+    /// Attach a new geometry to the scene, returning an error instead of
+    /// the ID if the scene already has `validation::MAX_GEOMETRIES`
+    /// geometries. Embree would give the geometry the invalid ID.
+    pub fn try_attach_geometry(&mut self, mesh: Geometry<'a>) -> Result<u32, ValidationError> {
+        let count = self.geometry.len() + 1;
+        if count > validation::MAX_GEOMETRIES {
+            return Err(ValidationError::TooManyGeometries {
+                count,
+                max: validation::MAX_GEOMETRIES,
+            });
+        }
         let id = unsafe { rtcAttachGeometry(self.handle, mesh.handle()) };
+        self.insert_attached(mesh, id);
+        Ok(id)
+    }
+    /// Attach the geometry with a specific ID, e.g. to recreate a saved
+    /// scene with the same IDs. The caller must check the ID is below
+    /// `validation::MAX_GEOMETRIES` and not already in use.
+    pub(crate) fn attach_geometry_by_id(&mut self, mesh: Geometry<'a>, id: u32) {
+        debug_assert!(!self.geometry.contains_key(&id));
+        unsafe {
+            rtcAttachGeometryByID(self.handle, mesh.handle(), id);
+        }
+        self.insert_attached(mesh, id);
+    }
+    /// Track geometry attached to the scene handle with the ID
+    fn insert_attached(&mut self, mesh: Geometry<'a>, id: u32) {
+        if let Some(shadow) = self.shadow_handle {
+            unsafe {
+                rtcAttachGeometryByID(shadow, mesh.handle(), id);
+            }
+        }
         self.geometry.insert(id, mesh);
-        id
+        self.attach_order.push(id);
+        let serial = NEXT_ATTACH_SERIAL.fetch_add(1, Ordering::Relaxed);
+        self.attach_serials.insert(id, serial);
     }
-    /// Detach the geometry from the scene
+    /// Attach a new geometry to the scene like `attach_geometry`, returning
+    /// a `GeomId` tied to the scene instead of the raw ID.
+    ///
+    /// Panics if the scene already has as many geometries as Embree has
+    /// IDs for.
+    pub fn attach(&mut self, mesh: Geometry<'a>) -> GeomId {
+        let id = self.attach_geometry(mesh);
+        GeomId {
+            id,
+            serial: self.attach_serials[&id],
+        }
+    }
+    /// Get the `GeomId` of the geometry attached with the raw ID, e.g. the
+    /// `geomID` of a hit, or `None` if no geometry has the ID
+    pub fn geom_id(&self, id: u32) -> Option<GeomId> {
+        self.attach_serials
+            .get(&id)
+            .map(|&serial| GeomId { id, serial })
+    }
+    /// Check the ID refers to geometry currently attached to the scene
+    pub fn contains(&self, id: GeomId) -> bool {
+        self.attach_serials.get(&id.id) == Some(&id.serial)
+    }
+    /// Look up a geometry by the ID returned from `attach`, `None` if the
+    /// ID is from another scene or the geometry was detached
+    pub fn geometry(&self, id: GeomId) -> Option<&Geometry<'a>> {
+        if self.contains(id) {
+            self.geometry.get(&id.id)
+        } else {
+            None
+        }
+    }
+    /// Look up a geometry by the ID returned from `attach`, `None` if the
+    /// ID is from another scene or the geometry was detached
+    pub fn geometry_mut(&mut self, id: GeomId) -> Option<&mut Geometry<'a>> {
+        if self.contains(id) {
+            self.geometry.get_mut(&id.id)
+        } else {
+            None
+        }
+    }
+    /// Detach the geometry with the ID returned from `attach`, along with
+    /// its shadow proxy. Returns `None` without detaching anything if the
+    /// ID is from another scene or the geometry was already detached.
+    pub fn detach(&mut self, id: GeomId) -> Option<Geometry<'a>> {
+        if self.contains(id) {
+            self.deattach_geometry(id.id)
+        } else {
+            None
+        }
+    }
+    /// Check the scene is within Embree's limits on the number of
+    /// geometries and on how deeply instances are nested, which Embree
+    /// doesn't report errors for. The buffers of each geometry are checked
+    /// by `Geometry::validate`.
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        validation::validate_scene(self)
+    }
+    /// Detach the geometry from the scene, along with its shadow proxy
     pub fn deattach_geometry(&mut self, id: u32) -> Option<Geometry<'a>> {
-        self.geometry.remove(&id)
+        let geom = self.geometry.remove(&id)?;
+        unsafe {
+            rtcDetachGeometry(self.handle, id);
+            if let Some(shadow) = self.shadow_handle {
+                rtcDetachGeometry(shadow, id);
+            }
+        }
+        self.shadow_proxies.remove(&id);
+        self.attach_order.retain(|&g| g != id);
+        self.attach_serials.remove(&id);
+        Some(geom)
+    }
+    /// Enable or disable each attached geometry the predicate returns true
+    /// for, e.g. to hide all the curves in the scene with
+    /// `scene.set_enabled_where(|_, g| g.kind().is_curve(), false)`. The
+    /// predicate is passed the ID and the geometry. Returns the number of
+    /// geometries whose state changed. The scene must be committed for the
+    /// change to take effect.
+    pub fn set_enabled_where<F>(&mut self, mut predicate: F, enabled: bool) -> usize
+    where
+        F: FnMut(u32, &Geometry<'a>) -> bool,
+    {
+        let mut changed = 0;
+        for id in self.attach_order.iter() {
+            let geom = self.geometry.get_mut(id).unwrap();
+            if geom.is_enabled() != enabled && predicate(*id, geom) {
+                geom.set_enabled(enabled);
+                changed += 1;
+            }
+        }
+        changed
+    }
+    /// Set the mask of each attached geometry the predicate returns true
+    /// for, e.g. to hide the curves in the scene from rays with only the
+    /// `camera` bit set with
+    /// `scene.set_mask_where(|_, g| g.kind().is_curve(), !camera)`. The
+    /// predicate is passed the ID and the geometry. Returns the number of
+    /// geometries whose mask was set. The geometry and scene must be
+    /// committed for the change to take effect. See the `ray_mask` module.
+    pub fn set_mask_where<F>(&mut self, mut predicate: F, mask: RayMask) -> usize
+    where
+        F: FnMut(u32, &Geometry<'a>) -> bool,
+    {
+        let mut changed = 0;
+        for id in self.attach_order.iter() {
+            let geom = self.geometry.get_mut(id).unwrap();
+            if predicate(*id, geom) {
+                geom.set_ray_mask(mask);
+                changed += 1;
+            }
+        }
+        changed
+    }
+    /// Set a shadow proxy to replace the geometry `id` in occlusion queries
+    /// run through `CommittedScene::shadow_proxies`, e.g. a flat ribbon
+    /// version of hair curves made by `shadow_proxy::flat_curve_proxy`.
+    /// The proxy must be committed. Returns the previous proxy for the
+    /// geometry, if any.
+    ///
+    /// Panics if no geometry with the ID is attached to the scene.
+    pub fn set_shadow_proxy(&mut self, id: u32, proxy: Geometry<'a>) -> Option<Geometry<'a>> {
+        assert!(
+            self.geometry.contains_key(&id),
+            "No geometry with ID {} to set a shadow proxy for",
+            id
+        );
+        let shadow = match self.shadow_handle {
+            Some(shadow) => shadow,
+            None => unsafe {
+                let device = rtcGetSceneDevice(self.handle);
+                let shadow = rtcNewScene(device);
+                leak_check::created(ObjectKind::Scene);
+                rtcReleaseDevice(device);
+                rtcSetSceneFlags(shadow, rtcGetSceneFlags(self.handle));
+                rtcSetSceneBuildQuality(shadow, self.build_quality);
+                for (gid, g) in self.geometry.iter() {
+                    rtcAttachGeometryByID(shadow, g.handle(), *gid);
+                }
+                self.shadow_handle = Some(shadow);
+                shadow
+            },
+        };
+        unsafe {
+            rtcDetachGeometry(shadow, id);
+            rtcAttachGeometryByID(shadow, proxy.handle(), id);
+        }
+        self.shadow_proxies.insert(id, proxy)
+    }
+    /// Remove the shadow proxy for the geometry `id`, restoring the
+    /// geometry itself for occlusion queries
+    pub fn remove_shadow_proxy(&mut self, id: u32) -> Option<Geometry<'a>> {
+        let proxy = self.shadow_proxies.remove(&id)?;
+        if let Some(shadow) = self.shadow_handle {
+            unsafe {
+                rtcDetachGeometry(shadow, id);
+                rtcAttachGeometryByID(shadow, self.geometry[&id].handle(), id);
+            }
+        }
+        Some(proxy)
+    }
+    /// Set the flags used when building the scene. They are applied to the
+    /// scene of shadow proxies as well, and take effect on the next commit.
+    pub fn set_flags(&mut self, flags: SceneFlags) {
+        unsafe {
+            rtcSetSceneFlags(self.handle, flags);
+            if let Some(shadow) = self.shadow_handle {
+                rtcSetSceneFlags(shadow, flags);
+            }
+        }
+    }
+    pub fn flags(&self) -> SceneFlags {
+        unsafe { rtcGetSceneFlags(self.handle) }
+    }
+    /// Enable or disable `SceneFlags::ROBUST`, leaving the other flags
+    /// unchanged. Robust mode avoids optimizations which reduce the
+    /// arithmetic accuracy of traversal and intersection, so rays are not
+    /// missed when passing through edges or vertices shared by neighboring
+    /// primitives, at some cost in performance. This applies to both
+    /// intersection and occlusion queries. Note that a ray through a shared
+    /// edge may then report a candidate hit on each primitive sharing it,
+    /// so filter functions can be called more than once for the crossing.
+    pub fn set_robust(&mut self, robust: bool) {
+        let flags = self.flags();
+        let flags = if robust {
+            flags | SceneFlags::ROBUST
+        } else {
+            SceneFlags(flags.0 & !SceneFlags::ROBUST.0)
+        };
+        self.set_flags(flags);
+    }
+    /// Set a closure to monitor the progress of building the scene's BVH
+    /// when it's committed. The closure is passed the fraction of the build
+    /// completed in [0, 1], and returning false cancels the build, causing
+    /// the commit to fail with a cancelled error. The closure may be called
+    /// concurrently from Embree's threads. If the closure panics the build
+    /// is cancelled, and the panic is resumed once the commit returns.
+    pub fn set_progress_monitor_function<F>(&mut self, monitor: F)
+    where
+        F: Fn(f64) -> bool + Send + Sync + 'static,
+    {
+        let state = Box::new(ProgressMonitor {
+            monitor: Box::new(monitor),
+            panic: Mutex::new(None),
+        });
+        unsafe {
+            rtcSetSceneProgressMonitorFunction(
+                self.handle,
+                Some(progress_monitor),
+                &*state as *const ProgressMonitor as *mut raw::c_void,
+            );
+        }
+        self.progress_monitor = Some(state);
+    }
+    /// Remove the progress monitor function set on the scene, if any
+    pub fn clear_progress_monitor_function(&mut self) {
+        unsafe {
+            rtcSetSceneProgressMonitorFunction(self.handle, None, ptr::null_mut());
+        }
+        self.progress_monitor = None;
+    }
+    /// Enable or disable committing the attached geometry and shadow
+    /// proxies which have changed since they were last committed, see
+    /// `Geometry::needs_commit`, when the scene is committed. This is off
+    /// by default, leaving the geometry to be committed by the
+    /// application.
+    pub fn set_auto_commit_geometry(&mut self, enabled: bool) {
+        self.auto_commit_geometry = enabled;
+    }
+    pub fn auto_commit_geometry(&self) -> bool {
+        self.auto_commit_geometry
+    }
+    /// Set a capture to record a sample of the rays traced by the stream
+    /// queries on the scene, or clear it with `None`. See the `debug`
+    /// module.
+    #[cfg(feature = "streams")]
+    pub fn set_ray_capture(&mut self, capture: Option<Arc<RayCapture>>) {
+        self.ray_capture = capture;
+    }
+    #[cfg(feature = "streams")]
+    pub fn ray_capture(&self) -> Option<&Arc<RayCapture>> {
+        self.ray_capture.as_ref()
+    }
+    /// Apply the traversal settings to the scene, see the `traversal`
+    /// module. The robust flag takes effect on the next commit.
+    pub fn set_traversal_settings(&mut self, settings: TraversalSettings) {
+        self.set_robust(settings.robust);
+        self.traversal = settings;
+    }
+    pub fn traversal_settings(&self) -> TraversalSettings {
+        TraversalSettings {
+            robust: self.flags().0 & SceneFlags::ROBUST.0 != 0,
+            ..self.traversal
+        }
+    }
+    /// Set the quality of the BVH built over the scene, taking effect on the
+    /// next commit. Higher quality BVHs are faster to trace rays against
+    /// but slower to build. The default is `BuildQuality::MEDIUM`.
+    pub fn set_build_quality(&mut self, quality: BuildQuality) {
+        self.build_quality = quality;
+        unsafe {
+            rtcSetSceneBuildQuality(self.handle, quality);
+            if let Some(shadow) = self.shadow_handle {
+                rtcSetSceneBuildQuality(shadow, quality);
+            }
+        }
+    }
+    pub fn build_quality(&self) -> BuildQuality {
+        self.build_quality
+    }
+    /// Enable or disable spatial splits when building the scene's BVH.
+    /// Embree only uses spatial splits in high quality builds, so this
+    /// selects between `BuildQuality::HIGH` and the default `MEDIUM`.
+    ///
+    /// Spatial splits reference primitives from multiple leaves to get
+    /// tighter node bounds, which speeds up ray tracing in scenes with
+    /// long, thin or diagonal triangles and quads, e.g. architectural
+    /// models, by up to 2x. The price is a several times slower build and
+    /// more BVH memory, bounded by the device's
+    /// `DeviceConfig::max_spatial_split_replications`, so it's best suited
+    /// to static scenes which are traced many times after committing.
+    pub fn set_spatial_splits(&mut self, enabled: bool) {
+        self.set_build_quality(if enabled {
+            BuildQuality::HIGH
+        } else {
+            BuildQuality::MEDIUM
+        });
+    }
+    /// Get the shadow proxy set for the geometry `id`, if any
+    pub fn get_shadow_proxy(&self, id: u32) -> Option<&Geometry<'a>> {
+        self.shadow_proxies.get(&id)
     }
     /// Look up a geometry in the scene by the ID returned from `attach_geometry`
+    #[deprecated(note = "use `geometry` with the `GeomId` from `attach` or `geom_id`")]
     pub fn get_geometry(&self, id: u32) -> Option<&Geometry<'a>> {
-        match self.geometry.get(&id) {
-            Some(g) => Some(g),
-            None => None,
-        }
+        self.geometry_by_raw_id(id)
     }
     /// Look up a geometry in the scene by the ID returned from `attach_geometry`
+    #[deprecated(note = "use `geometry_mut` with the `GeomId` from `attach` or `geom_id`")]
     pub fn get_geometry_mut(&mut self, id: u32) -> Option<&mut Geometry<'a>> {
-        match self.geometry.get_mut(&id) {
-            Some(g) => Some(g),
-            None => None,
-        }
+        self.geometry_by_raw_id_mut(id)
+    }
+    /// Look up a geometry by the raw ID Embree assigned it, e.g. the
+    /// `geomID` of a hit
+    pub(crate) fn geometry_by_raw_id(&self, id: u32) -> Option<&Geometry<'a>> {
+        self.geometry.get(&id)
+    }
+    pub(crate) fn geometry_by_raw_id_mut(&mut self, id: u32) -> Option<&mut Geometry<'a>> {
+        self.geometry.get_mut(&id)
     }
     /// Get an iterator over the geometry map
     pub fn iter(&self) -> std::collections::hash_map::Iter<u32, Geometry<'a>> {
         self.geometry.iter()
     }
+    /// Get an iterator over the geometry in the order it was attached to
+    /// the scene. Unlike `iter`, the order is deterministic, making it
+    /// suitable for serializing or printing the scene.
+    pub fn iter_ordered<'s>(&'s self) -> impl Iterator<Item = (u32, &'s Geometry<'a>)> + 's {
+        self.attach_order
+            .iter()
+            .map(move |id| (*id, &self.geometry[id]))
+    }
+    /// Get the IDs of the attached geometry in the order it was attached
+    pub fn geometry_ids(&self) -> &[u32] {
+        &self.attach_order
+    }
+    /// Get the bounds of the enabled geometry in the scene, computed from
+    /// the geometry's buffers without committing the scene, e.g. to fit a
+    /// camera to a scene while it's being built. See
+    /// `Geometry::linear_bounds` for how each kind of geometry is bounded.
+    /// The bounds are empty if no geometry is enabled, and `None` if the
+    /// bounds of any enabled geometry can't be computed.
+    pub fn bounds(&self) -> Option<Bounds> {
+        self.linear_bounds().map(|lb| lb.shutter_bounds())
+    }
+    /// Get the linear bounds of the enabled geometry in the scene over the
+    /// shutter interval, computed from the geometry's buffers without
+    /// committing the scene. These can be looser than the bounds Embree
+    /// computes for the committed scene. Returns `None` if the bounds of
+    /// any enabled geometry can't be computed, see `bounds`.
+    pub fn linear_bounds(&self) -> Option<LinearBounds> {
+        let empty = empty_bounds();
+        self.geometry
+            .values()
+            .filter(|g| g.is_enabled())
+            .map(|g| g.linear_bounds())
+            .try_fold(LinearBounds::new(empty, empty), |a, b| {
+                b.map(|b| linear_bounds::union_linear_bounds(&a, &b))
+            })
+    }
     /// Get an iterator over the geometry map
     pub fn iter_mut(&mut self) -> std::collections::hash_map::IterMut<u32, Geometry<'a>> {
         self.geometry.iter_mut()
@@ -69,11 +609,148 @@ impl<'a> Scene<'a> {
     /// for ray tracing the scene. The returned `CommittedScene` can be
     /// used for intersection and occlusion tests. The `Scene` can't
     /// be modified while the `CommittedScene` is active.
+    ///
+    /// Each commit is given a new `CommitToken`, which can be used to
+    /// detect results computed against a previous commit of the scene.
+    ///
+    /// With `set_auto_commit_geometry` enabled, geometry which changed
+    /// since it was last committed is committed first.
+    ///
+    /// Panics if a `CommittedScene` from a previous commit is still alive,
+    /// as rebuilding the BVH would race with queries run through it. Drop
+    /// the views of the scene, including those borrowed by instances,
+    /// before committing it again.
     pub fn commit(&'a self) -> CommittedScene<'a> {
+        if self.has_live_views() {
+            live_views_panic();
+        }
+        self.commit_changed_geometry();
+        let start = Instant::now();
         unsafe {
             rtcCommitScene(self.handle);
+            if let Some(shadow) = self.shadow_handle {
+                rtcCommitScene(shadow);
+            }
+        }
+        self.resume_build_panic();
+        let token = CommitToken(NEXT_COMMIT_TOKEN.fetch_add(1, Ordering::Relaxed));
+        self.finish_commit(start, token)
+    }
+    /// Commit the scene together with the other threads calling
+    /// `join_commit` on it, which all take part in building the BVH and
+    /// return once it's built. This lets the threads of an application's
+    /// task system help with the build instead of blocking on a commit
+    /// run by one of them. A thread which calls `join_commit` while a
+    /// join is in progress joins that build, and all threads in it get the
+    /// same `CommitToken`.
+    ///
+    /// Joining threads should not be Embree's own build threads, and the
+    /// scene must not be committed with `commit` during the join. As with
+    /// `commit`, the first thread to join panics if a `CommittedScene`
+    /// from a previous commit is still alive. With
+    /// `set_auto_commit_geometry` enabled, changed geometry is committed
+    /// by the first thread to join before the build starts. See the
+    /// Embree documentation of `rtcJoinCommitScene` for the requirements
+    /// on the tasking system Embree is built with.
+    pub fn join_commit(&'a self) -> CommittedScene<'a> {
+        let token = {
+            let mut join = self.join.lock().unwrap();
+            if join.token.is_none() && self.has_live_views() {
+                // Release the lock so the panic doesn't poison it
+                drop(join);
+                live_views_panic();
+            }
+            join.threads += 1;
+            if join.token.is_none() {
+                self.commit_changed_geometry();
+                join.token = Some(CommitToken(
+                    NEXT_COMMIT_TOKEN.fetch_add(1, Ordering::Relaxed),
+                ));
+            }
+            join.token.unwrap()
+        };
+        let start = Instant::now();
+        unsafe {
+            rtcJoinCommitScene(self.handle);
+            if let Some(shadow) = self.shadow_handle {
+                rtcJoinCommitScene(shadow);
+            }
+        }
+        {
+            let mut join = self.join.lock().unwrap();
+            join.threads -= 1;
+            if join.threads == 0 {
+                join.token = None;
+            }
+        }
+        self.resume_build_panic();
+        self.finish_commit(start, token)
+    }
+    /// Whether a `CommittedScene` of the scene is alive, which a new
+    /// commit would rebuild the BVH under
+    fn has_live_views(&self) -> bool {
+        self.live_views.load(Ordering::Acquire) != 0
+    }
+    /// Resume a panic held from a callback Embree made while building the
+    /// scene, as unwinding into Embree would abort
+    fn resume_build_panic(&self) {
+        if let Some(ref state) = self.progress_monitor {
+            let held = state.panic.lock().unwrap_or_else(|e| e.into_inner()).take();
+            if let Some(p) = held {
+                panic::resume_unwind(p);
+            }
+        }
+        for g in self.geometry.values().chain(self.shadow_proxies.values()) {
+            if let Some(p) = geometry::take_build_panic(g.handle()) {
+                panic::resume_unwind(p);
+            }
+        }
+    }
+    /// Commit geometry which changed since it was last committed, if
+    /// enabled with `set_auto_commit_geometry`
+    fn commit_changed_geometry(&self) {
+        if self.auto_commit_geometry {
+            for g in self.geometry.values().chain(self.shadow_proxies.values()) {
+                if g.needs_commit() {
+                    geometry::commit_handle(g.handle());
+                }
+            }
+        }
+    }
+    /// Record the build time and token of a commit started at `start`
+    fn finish_commit(&'a self, start: Instant, token: CommitToken) -> CommittedScene<'a> {
+        let build_time = start.elapsed().as_nanos().max(1) as u64;
+        self.build_time.store(build_time, Ordering::Relaxed);
+        self.commit_token.store(token.0, Ordering::Release);
+        CommittedScene::view(self, self.handle, token)
+    }
+    /// Make changes to the scene in the `edit` closure, then commit it.
+    /// The scene stays mutably borrowed by the returned `CommittedScene`,
+    /// so it can't be changed through the `Scene` again until that's
+    /// dropped. Changes to data the scene doesn't own, see the
+    /// `scene_query` module, still need a new commit to be seen.
+    pub fn edit<'s, F>(&'s mut self, edit: F) -> CommittedScene<'s>
+    where
+        F: FnOnce(&mut Scene<'a>),
+    {
+        edit(self);
+        let scene: &'s Scene<'s> = self;
+        scene.commit()
+    }
+    /// Get a view of the scene as of its last commit without committing it
+    /// again, if it has been committed. The caller must make sure the scene
+    /// wasn't changed since, as `commit` borrowing the scene would.
+    #[cfg(feature = "capi")]
+    pub(crate) fn last_commit(&'a self) -> Option<CommittedScene<'a>> {
+        self.commit_token()
+            .map(|token| CommittedScene::view(self, self.handle, token))
+    }
+    /// Get the token of the last commit of the scene, if it has been committed
+    pub fn commit_token(&self) -> Option<CommitToken> {
+        match self.commit_token.load(Ordering::Acquire) {
+            0 => None,
+            t => Some(CommitToken(t)),
         }
-        CommittedScene { scene: &self }
     }
     /// Get the underlying handle to the scene, e.g. for passing it to
     /// native code or ISPC kernels.
@@ -86,104 +763,196 @@ impl<'a> Drop for Scene<'a> {
     fn drop(&mut self) {
         unsafe {
             rtcReleaseScene(self.handle);
+            leak_check::released(ObjectKind::Scene);
+            if let Some(shadow) = self.shadow_handle {
+                rtcReleaseScene(shadow);
+                leak_check::released(ObjectKind::Scene);
+            }
         }
     }
 }
 
 unsafe impl<'a> Sync for Scene<'a> {}
+// The scene can be moved to another thread, e.g. to commit it on a worker
+// with `commit_async`:
+// - Embree scene handles aren't tied to the thread creating them, and
+//   releasing them is thread safe.
+// - The attached geometry and shadow proxies are only moved when the
+//   geometry types are `Send`. Their filter functions and user shapes
+//   must be `Send + Sync` to be set.
+// - The ray capture is an `Arc` of atomics and a `Mutex`, and the
+//   progress monitor must be `Send + Sync` to be set.
+// - The remaining fields are plain data, atomics or a `Mutex`.
+unsafe impl<'a> Send for Scene<'a> where Geometry<'a>: Send {}
 
 /// A committed scene with a BVH built over the geometry
 /// which can be used for ray queries.
 pub struct CommittedScene<'a> {
     pub(crate) scene: &'a Scene<'a>,
+    /// The Embree scene queries are run against, either the scene itself
+    /// or the scene of its shadow proxies
+    pub(crate) handle: RTCScene,
+    token: CommitToken,
 }
 
 impl<'a> CommittedScene<'a> {
+    /// Create a view of the scene querying `handle`, counted by the scene
+    /// until it's dropped
+    fn view(scene: &'a Scene<'a>, handle: RTCScene, token: CommitToken) -> CommittedScene<'a> {
+        scene.live_views.fetch_add(1, Ordering::AcqRel);
+        CommittedScene {
+            scene,
+            handle,
+            token,
+        }
+    }
+    /// Get a view of the scene as of the commit with the token, which must
+    /// be its last commit
+    #[cfg(feature = "async")]
+    pub(crate) fn at_commit(scene: &'a Scene<'a>, token: CommitToken) -> CommittedScene<'a> {
+        CommittedScene::view(scene, scene.handle, token)
+    }
     pub fn intersect(&self, ctx: &mut IntersectContext, ray: &mut RayHit) {
+        self.scene.ray_counters.count_intersect(1);
         unsafe {
             rtcIntersect1(
-                self.scene.handle,
+                self.handle,
                 ctx as *mut RTCIntersectContext,
                 ray as *mut RTCRayHit,
             );
         }
+        filter::resume_held_panic();
     }
+    /// Test if the ray is occluded in place, Embree marks occluded rays
+    /// by setting their `tfar` to -inf and leaves other rays unchanged.
+    /// Read the result with `Occlusion::of`, or use `occlusion` to test
+    /// without modifying the ray.
     pub fn occluded(&self, ctx: &mut IntersectContext, ray: &mut Ray) {
+        self.scene.ray_counters.count_occluded(1);
         unsafe {
             rtcOccluded1(
-                self.scene.handle,
+                self.handle,
                 ctx as *mut RTCIntersectContext,
                 ray as *mut RTCRay,
             );
         }
+        filter::resume_held_panic();
     }
-    pub fn intersect4(&self, ctx: &mut IntersectContext, ray: &mut RayHit4, valid: &[i32; 4]) {
-        unsafe {
-            rtcIntersect4(
-                valid.as_ptr(),
-                self.scene.handle,
-                ctx as *mut RTCIntersectContext,
-                ray as *mut RTCRayHit4,
-            );
+    /// Intersect a single ray with the scene, returning the closest hit if
+    /// any. The returned `RayHit` holds a copy of the ray with `tfar` set to
+    /// the hit distance. Use `intersect` to trace in place when tracing
+    /// rays in performance critical code.
+    pub fn intersect_ray(&self, ray: &Ray) -> Option<RayHit> {
+        let mut ctx = IntersectContext::incoherent();
+        let mut ray_hit = RayHit::new(*ray);
+        self.intersect(&mut ctx, &mut ray_hit);
+        if ray_hit.hit.hit() {
+            Some(ray_hit)
+        } else {
+            None
         }
     }
-    pub fn occluded4(&self, ctx: &mut IntersectContext, ray: &mut Ray4, valid: &[i32; 4]) {
+    /// Intersect a single ray with the scene, limiting the candidate hits
+    /// passed to filter functions or the time spent filtering them to the
+    /// budget. The result is flagged as approximate if the budget was
+    /// spent. This is experimental, see the `budget` module for how the
+    /// budget is applied.
+    pub fn intersect_with_budget(&self, ray: &Ray, budget: QueryBudget) -> BudgetedHit {
+        let mut ctx = BudgetContext::new(budget);
+        let mut ray_hit = RayHit::new(*ray);
+        self.scene.ray_counters.count_intersect(1);
         unsafe {
-            rtcOccluded4(
-                valid.as_ptr(),
-                self.scene.handle,
-                ctx as *mut RTCIntersectContext,
-                ray as *mut RTCRay4,
+            rtcIntersect1(
+                self.handle,
+                &mut ctx as *mut BudgetContext as *mut RTCIntersectContext,
+                &mut ray_hit as *mut RTCRayHit,
             );
         }
+        filter::resume_held_panic();
+        ctx.result(ray_hit)
     }
-    pub fn intersect_stream_aos(&self, ctx: &mut IntersectContext, rays: &mut Vec<RayHit>) {
-        let m = rays.len();
-        unsafe {
-            rtcIntersect1M(
-                self.scene.handle,
-                ctx as *mut RTCIntersectContext,
-                rays.as_mut_ptr(),
-                m as u32,
-                mem::size_of::<RayHit>(),
-            );
-        }
+    /// Intersect a single ray with the scene as in `intersect_ray`, stamping
+    /// the result with the commit of the scene it was computed against
+    pub fn intersect_stamped(&self, ray: &Ray) -> Stamped<Option<RayHit>> {
+        self.stamp(self.intersect_ray(ray))
     }
-    pub fn occluded_stream_aos(&self, ctx: &mut IntersectContext, rays: &mut Vec<Ray>) {
-        let m = rays.len();
-        unsafe {
-            rtcOccluded1M(
-                self.scene.handle,
-                ctx as *mut RTCIntersectContext,
-                rays.as_mut_ptr(),
-                m as u32,
-                mem::size_of::<Ray>(),
-            );
+    /// Stamp a result computed against the scene with its commit token
+    pub fn stamp<T>(&self, value: T) -> Stamped<T> {
+        Stamped {
+            value,
+            #[cfg(debug_assertions)]
+            token: self.token,
         }
     }
-    pub fn intersect_stream_soa(&self, ctx: &mut IntersectContext, rays: &mut RayHitN) {
-        let n = rays.len();
-        unsafe {
-            let mut rayhit = rays.as_rayhitnp();
-            rtcIntersectNp(
-                self.scene.handle,
-                ctx as *mut RTCIntersectContext,
-                &mut rayhit as *mut RTCRayHitNp,
-                n as u32,
-            );
+    /// Find the pairs of potentially colliding primitives between this scene
+    /// and `other`, which may be the same scene to find self collisions.
+    /// The callback is passed batches of colliding primitive pairs, and may
+    /// be called concurrently from Embree's build threads.
+    ///
+    /// Embree only supports collision detection between scenes containing
+    /// user geometry, where the primitive bounds are tested for overlap.
+    /// The callback should perform the exact intersection test between the
+    /// primitives. Returns `Error::INVALID_OPERATION` without querying if
+    /// either scene holds other kinds of geometry.
+    ///
+    /// A panic in the callback is held until the query returns and resumed
+    /// then, and the callback isn't called again during the query.
+    pub fn collide<F>(&self, other: &CommittedScene, callback: F) -> Result<(), Error>
+    where
+        F: Fn(&[Collision]) + Sync,
+    {
+        let user_only = |s: &CommittedScene| {
+            s.scene
+                .geometry
+                .values()
+                .all(|g| g.kind() == GeometryKind::User)
+        };
+        if !user_only(self) || !user_only(other) {
+            return Err(Error::INVALID_OPERATION);
         }
-    }
-    pub fn occluded_stream_soa(&self, ctx: &mut IntersectContext, rays: &mut RayN) {
-        let n = rays.len();
         unsafe {
-            let mut r = rays.as_raynp();
-            rtcOccludedNp(
-                self.scene.handle,
-                ctx as *mut RTCIntersectContext,
-                &mut r as *mut RTCRayNp,
-                n as u32,
-            );
+            collide::collide(self.handle, other.handle, &callback);
         }
+        Ok(())
+    }
+    /// Get a view of the scene with the geometry replaced by the shadow
+    /// proxies set for it, for running occlusion queries. Geometry without
+    /// a proxy is unchanged. If no proxies are set this is the same as the
+    /// scene itself.
+    pub fn shadow_proxies(&self) -> CommittedScene<'a> {
+        let handle = self.scene.shadow_handle.unwrap_or(self.scene.handle);
+        CommittedScene::view(self.scene, handle, self.token)
+    }
+    /// Get the token of the commit this is a view of
+    pub fn token(&self) -> CommitToken {
+        self.token
+    }
+    /// Get the traversal settings of the scene, e.g. to spawn secondary
+    /// rays from hits
+    pub fn traversal_settings(&self) -> TraversalSettings {
+        self.scene.traversal_settings()
+    }
+    /// Check if this is a view of the latest commit of the scene
+    pub fn is_current(&self) -> bool {
+        self.scene.commit_token() == Some(self.token)
+    }
+    /// Test if the ray is occluded by any geometry in the scene. The ray
+    /// passed is not modified, use `occluded` to test in place.
+    pub fn is_occluded(&self, ray: &Ray) -> bool {
+        self.occlusion(ray).is_occluded()
+    }
+    /// Test if the ray is occluded by any geometry in the scene, without
+    /// modifying the ray
+    pub fn occlusion(&self, ray: &Ray) -> Occlusion {
+        self.occlusion_with_context(&mut IntersectContext::incoherent(), ray)
+    }
+    /// Test if the ray is occluded using the context passed, e.g. a
+    /// coherent context for a batch of shadow rays toward a light, without
+    /// modifying the ray
+    pub fn occlusion_with_context(&self, ctx: &mut IntersectContext, ray: &Ray) -> Occlusion {
+        let mut r = *ray;
+        self.occluded(ctx, &mut r);
+        Occlusion::of(&r)
     }
     pub fn bounds(&self) -> RTCBounds {
         let mut bounds = RTCBounds {
@@ -201,11 +970,49 @@ impl<'a> CommittedScene<'a> {
         }
         bounds
     }
+    /// Get the linear bounds of the scene over the shutter interval, which
+    /// contain the motion blurred geometry at each time
+    pub fn linear_bounds(&self) -> LinearBounds {
+        let empty = empty_bounds();
+        let mut bounds = LinearBounds::new(empty, empty);
+        unsafe {
+            rtcGetSceneLinearBounds(self.handle(), &mut bounds as *mut RTCLinearBounds);
+        }
+        bounds
+    }
     /// Get the underlying handle to the scene, e.g. for passing it to
     /// native code or ISPC kernels.
     pub unsafe fn handle(&self) -> RTCScene {
-        self.scene.handle
+        self.handle
+    }
+}
+
+impl<'a> Drop for CommittedScene<'a> {
+    fn drop(&mut self) {
+        self.scene.live_views.fetch_sub(1, Ordering::AcqRel);
     }
 }
 
 unsafe impl<'a> Sync for CommittedScene<'a> {}
+
+#[test]
+fn test_progress_monitor_panic() {
+    let state = ProgressMonitor {
+        monitor: Box::new(|n| {
+            if n > 0.5 {
+                panic!("monitor panicked")
+            }
+            true
+        }),
+        panic: Mutex::new(None),
+    };
+    let ptr = &state as *const ProgressMonitor as *mut raw::c_void;
+    unsafe {
+        assert!(progress_monitor(ptr, 0.25));
+        // The build is cancelled and the first panic is held
+        assert!(!progress_monitor(ptr, 0.75));
+        assert!(!progress_monitor(ptr, 1.0));
+    }
+    let p = state.panic.lock().unwrap().take().unwrap();
+    assert_eq!(p.downcast_ref::<&str>(), Some(&"monitor panicked"));
+}
@@ -0,0 +1,195 @@
+//! An owned container for a device and its scenes, for applications which
+//! need to keep them together in one struct, e.g. a renderer's state or a
+//! scene cached by a UI. `Scene` and the geometry borrow the `Device`
+//! they're created on, so a struct holding both a device and its scenes
+//! would have to borrow from itself. `SceneBundle` owns the device and the
+//! scenes built on it and hands out borrows tied to the bundle instead:
+//!
+//! ```no_run
+//! # extern crate cgmath;
+//! # extern crate embree;
+//! # use cgmath::Vector3;
+//! # use embree::{Device, Geometry, SceneBundle, SceneQuery, TriangleMesh};
+//! struct Renderer {
+//!     bundle: SceneBundle,
+//! }
+//!
+//! let mut bundle = SceneBundle::new(Device::new());
+//! let id = bundle.add_scene();
+//! bundle.edit(id, |device, scene| {
+//!     let mesh = TriangleMesh::try_from_slices(
+//!         device,
+//!         &[[-1.0, -1.0, 0.0], [1.0, -1.0, 0.0], [0.0, 1.0, 0.0]],
+//!         &[[0, 1, 2]],
+//!     )
+//!     .unwrap();
+//!     let mut geom = Geometry::Triangle(mesh);
+//!     geom.commit();
+//!     scene.attach_geometry(geom);
+//! });
+//! let renderer = Renderer { bundle };
+//! let rtscene = renderer.bundle.commit(id);
+//! let t = rtscene.hit_distance(Vector3::new(0.0, 0.0, 1.0), Vector3::new(0.0, 0.0, -1.0));
+//! ```
+//!
+//! Geometry is created and attached in `edit`, whose closure is passed
+//! the bundle's device, so it can only attach geometry built on that
+//! device and can't keep any of it after returning. Buffers shared with
+//! `Geometry::set_shared_buffer_from_slice` must then be `'static`.
+//! Geometry created on another device doesn't compile:
+//!
+//! ```compile_fail
+//! # extern crate embree;
+//! # use embree::{Device, Geometry, SceneBundle, TriangleMesh};
+//! let other = Device::new();
+//! let mut bundle = SceneBundle::new(Device::new());
+//! let id = bundle.add_scene();
+//! bundle.edit(id, |_, scene| {
+//!     let mesh = Geometry::Triangle(TriangleMesh::unanimated(&other, 1, 3));
+//!     scene.attach_geometry(mesh);
+//! });
+//! ```
+//!
+//! Scenes in a bundle can't instance each other, as an `Instance` borrows
+//! the committed scene it places.
+//!
+//! The bundle doesn't change how `Scene` and `Geometry` own their data,
+//! they still borrow the device. It only stores the scenes with the
+//! device's lifetime erased, and shortens it again to a borrow of the
+//! bundle whenever a scene or the device is handed out. None of those
+//! borrows can outlive the bundle, so the device can't be dropped while
+//! they're in use. The device passed to `edit` can't be kept after the
+//! closure returns:
+//!
+//! ```compile_fail
+//! # extern crate embree;
+//! # use embree::{Device, SceneBundle};
+//! let mut bundle = SceneBundle::new(Device::new());
+//! let id = bundle.add_scene();
+//! let mut kept = None;
+//! bundle.edit(id, |device, _| kept = Some(device));
+//! ```
+//!
+//! Nor can a scene or a commit of it outlive the bundle:
+//!
+//! ```compile_fail
+//! # extern crate embree;
+//! # use embree::{Device, SceneBundle};
+//! let rtscene = {
+//!     let mut bundle = SceneBundle::new(Device::new());
+//!     let id = bundle.add_scene();
+//!     bundle.commit(id)
+//! };
+//! ```
+//!
+//! A scene can't be edited while it's committed:
+//!
+//! ```compile_fail
+//! # extern crate embree;
+//! # use embree::{Device, SceneBundle};
+//! let mut bundle = SceneBundle::new(Device::new());
+//! let id = bundle.add_scene();
+//! let rtscene = bundle.commit(id);
+//! bundle.edit(id, |_, scene| scene.set_build_quality(embree::BuildQuality::HIGH));
+//! let bounds = rtscene.bounds();
+//! ```
+//!
+//! And geometry sharing a buffer which doesn't outlive the bundle can't
+//! be attached:
+//!
+//! ```compile_fail
+//! # extern crate embree;
+//! # use embree::{BufferType, Device, Format, Geometry, SceneBundle, TriangleMesh};
+//! let mut bundle = SceneBundle::new(Device::new());
+//! let id = bundle.add_scene();
+//! let vertices = vec![[0.0f32; 3]; 4];
+//! bundle.edit(id, |device, scene| {
+//!     let mut geom = Geometry::Triangle(TriangleMesh::unanimated(device, 1, 0));
+//!     geom.set_shared_buffer_from_slice(BufferType::VERTEX, 0, Format::FLOAT3, &vertices, 3);
+//!     scene.attach_geometry(geom);
+//! });
+//! ```
+
+use device::Device;
+use scene::{CommittedScene, Scene};
+
+/// Identifies a scene in a `SceneBundle`
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct SceneId(usize);
+
+impl SceneId {
+    pub fn index(&self) -> usize {
+        self.0
+    }
+}
+
+/// A device and the scenes built on it, see the `scene_bundle` module
+pub struct SceneBundle {
+    /// The scenes borrow the device, which is boxed so it stays at the
+    /// same address when the bundle is moved. The `'static` lifetime is
+    /// never handed out, borrows of the scenes are shortened to the
+    /// borrow of the bundle.
+    scenes: Vec<Scene<'static>>,
+    device: Box<Device>,
+}
+
+impl SceneBundle {
+    /// Create a bundle owning the device
+    pub fn new(device: Device) -> SceneBundle {
+        SceneBundle {
+            scenes: Vec::new(),
+            device: Box::new(device),
+        }
+    }
+    pub fn device(&self) -> &Device {
+        &self.device
+    }
+    /// Add an empty scene to the bundle
+    pub fn add_scene(&mut self) -> SceneId {
+        // The device is never moved or dropped while the scene is alive:
+        // it's boxed, and dropped after the scenes
+        let device: &'static Device = unsafe { &*(&*self.device as *const Device) };
+        self.scenes.push(Scene::new(device));
+        SceneId(self.scenes.len() - 1)
+    }
+    /// Get the number of scenes in the bundle
+    pub fn len(&self) -> usize {
+        self.scenes.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.scenes.is_empty()
+    }
+    /// Get the IDs of the scenes in the order they were added
+    pub fn scene_ids(&self) -> impl Iterator<Item = SceneId> {
+        (0..self.scenes.len()).map(SceneId)
+    }
+    /// Get the scene with the ID. Panics if it isn't from this bundle.
+    pub fn scene(&self, id: SceneId) -> &Scene<'_> {
+        &self.scenes[id.0]
+    }
+    /// Modify the scene with the ID, e.g. to attach geometry created on the
+    /// device passed to `edit`. Panics if the ID isn't from this bundle.
+    pub fn edit<F, R>(&mut self, id: SceneId, edit: F) -> R
+    where
+        F: for<'b> FnOnce(&'b Device, &mut Scene<'b>) -> R,
+    {
+        // Shorten the scene's lifetime to the borrow of the bundle, the
+        // closure works for any lifetime so it can only attach geometry
+        // borrowing the bundle's device or 'static data
+        let scene: *mut Scene<'static> = &mut self.scenes[id.0];
+        edit(&self.device, unsafe { &mut *scene.cast() })
+    }
+    /// Commit the scene with the ID for querying. Panics if the ID isn't
+    /// from this bundle.
+    pub fn commit(&self, id: SceneId) -> CommittedScene<'_> {
+        self.scene(id).commit()
+    }
+}
+
+impl Drop for SceneBundle {
+    fn drop(&mut self) {
+        // Release the scenes and their geometry before the device, which
+        // the field order also ensures
+        self.scenes.clear();
+    }
+}
@@ -0,0 +1,311 @@
+//! Caching of scene inputs for static scenes. Embree can't serialize the
+//! BVHs it builds, so a scene always has to be committed after loading,
+//! but the work done by an application to produce the geometry, e.g.
+//! parsing a model format, welding vertices or tessellating, can be
+//! skipped by saving the final meshes and loading them back directly.
+//!
+//! A cache file stores the triangle and quad meshes of a scene in attach
+//! order along with its build quality, a key identifying the source data
+//! the scene was built from (e.g. a hash of the model file) and a hash of
+//! the cached content to detect corrupt files. Vertex attributes are not
+//! stored, and meshes with buffers shared from user memory can't be
+//! cached. `SceneCache::load_or_build` loads the scene from the cache when
+//! the key matches, and otherwise builds it and updates the cache. Loading
+//! is a sequential read and copy into the geometry buffers, so how much it
+//! saves over building the scene from its source depends on the
+//! application's preprocessing; the `scene_cache` example prints both
+//! times for comparison.
+
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+use cgmath::{Vector3, Vector4};
+
+use device::Device;
+use geometry::Geometry;
+use quad_mesh::QuadMesh;
+use scene::Scene;
+use triangle_mesh::TriangleMesh;
+use BuildQuality;
+
+const MAGIC: &[u8; 8] = b"EMBRSCN\0";
+const VERSION: u32 = 1;
+const TRIANGLE: u32 = 0;
+const QUAD: u32 = 1;
+
+/// Hash the bytes with 64-bit FNV-1a
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut h = 0xcbf2_9ce4_8422_2325u64;
+    for b in bytes {
+        h ^= u64::from(*b);
+        h = h.wrapping_mul(0x0100_0000_01b3);
+    }
+    h
+}
+
+pub(crate) fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+pub(crate) fn put_u32(out: &mut Vec<u8>, x: u32) {
+    out.extend_from_slice(&x.to_le_bytes());
+}
+
+pub(crate) fn put_vertices(out: &mut Vec<u8>, verts: &[Vector4<f32>]) {
+    for v in verts {
+        for x in &[v.x, v.y, v.z, v.w] {
+            out.extend_from_slice(&x.to_le_bytes());
+        }
+    }
+}
+
+/// Decode a build quality saved as its `u32` value
+pub(crate) fn build_quality(x: u32) -> io::Result<BuildQuality> {
+    match x {
+        0 => Ok(BuildQuality::LOW),
+        1 => Ok(BuildQuality::MEDIUM),
+        2 => Ok(BuildQuality::HIGH),
+        3 => Ok(BuildQuality::REFIT),
+        _ => Err(invalid_data("invalid build quality")),
+    }
+}
+
+/// Reads little endian values from the cached content, shared with the
+/// `snapshot` module
+pub(crate) struct Cursor<'b> {
+    pub(crate) bytes: &'b [u8],
+}
+
+impl<'b> Cursor<'b> {
+    pub(crate) fn take(&mut self, n: usize) -> io::Result<&'b [u8]> {
+        if self.bytes.len() < n {
+            return Err(invalid_data("scene data is truncated"));
+        }
+        let (head, tail) = self.bytes.split_at(n);
+        self.bytes = tail;
+        Ok(head)
+    }
+    pub(crate) fn u32(&mut self) -> io::Result<u32> {
+        let mut b = [0; 4];
+        b.copy_from_slice(self.take(4)?);
+        Ok(u32::from_le_bytes(b))
+    }
+    pub(crate) fn u64(&mut self) -> io::Result<u64> {
+        let mut b = [0; 8];
+        b.copy_from_slice(self.take(8)?);
+        Ok(u64::from_le_bytes(b))
+    }
+    pub(crate) fn f32(&mut self) -> io::Result<f32> {
+        Ok(f32::from_bits(self.u32()?))
+    }
+    pub(crate) fn vertex(&mut self) -> io::Result<Vector4<f32>> {
+        Ok(Vector4::new(
+            self.f32()?,
+            self.f32()?,
+            self.f32()?,
+            self.f32()?,
+        ))
+    }
+}
+
+/// Serialize the meshes of the scene, returning the content to be hashed
+fn serialize_geometry(scene: &Scene) -> io::Result<Vec<u8>> {
+    let mut out = Vec::new();
+    put_u32(&mut out, scene.geometry_ids().len() as u32);
+    for (id, geom) in scene.iter_ordered() {
+        if !geom.owns_bound_buffers() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "geometry {} can't be cached, its buffers were replaced by shared data",
+                    id
+                ),
+            ));
+        }
+        match *geom {
+            Geometry::Triangle(ref m) => {
+                put_u32(&mut out, TRIANGLE);
+                put_u32(&mut out, 1 + m.motion_vertex_buffers.len() as u32);
+                put_u32(&mut out, m.vertex_buffer.len() as u32);
+                put_u32(&mut out, m.index_buffer.len() as u32);
+                put_vertices(&mut out, m.vertex_buffer.as_slice());
+                for b in m.motion_vertex_buffers.iter() {
+                    put_vertices(&mut out, b.as_slice());
+                }
+                for t in m.index_buffer.as_slice() {
+                    for i in &[t.x, t.y, t.z] {
+                        put_u32(&mut out, *i);
+                    }
+                }
+            }
+            Geometry::Quad(ref m) => {
+                put_u32(&mut out, QUAD);
+                put_u32(&mut out, 1);
+                put_u32(&mut out, m.vertex_buffer.len() as u32);
+                put_u32(&mut out, m.index_buffer.len() as u32);
+                put_vertices(&mut out, m.vertex_buffer.as_slice());
+                for q in m.index_buffer.as_slice() {
+                    for i in &[q.x, q.y, q.z, q.w] {
+                        put_u32(&mut out, *i);
+                    }
+                }
+            }
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!(
+                        "geometry {} can't be cached, only triangle and quad meshes are supported",
+                        id
+                    ),
+                ))
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// Compute a hash of the meshes in the scene, which changes when any of
+/// their vertices or indices change. Returns an error if the scene holds
+/// geometry other than triangle and quad meshes.
+pub fn content_hash(scene: &Scene) -> io::Result<u64> {
+    serialize_geometry(scene).map(|c| fnv1a(&c))
+}
+
+/// Write the meshes and build quality of the scene to `writer`, tagged
+/// with `key` to identify the source data the scene was built from.
+/// Returns an error if the scene holds geometry other than triangle and
+/// quad meshes.
+pub fn save_scene<W: Write>(scene: &Scene, key: u64, mut writer: W) -> io::Result<()> {
+    let content = serialize_geometry(scene)?;
+    writer.write_all(MAGIC)?;
+    writer.write_all(&VERSION.to_le_bytes())?;
+    writer.write_all(&key.to_le_bytes())?;
+    writer.write_all(&fnv1a(&content).to_le_bytes())?;
+    writer.write_all(&(scene.build_quality() as u32).to_le_bytes())?;
+    writer.write_all(&content)?;
+    writer.flush()
+}
+
+/// Load a scene written by `save_scene`. The meshes are committed and
+/// attached in the order they were saved, so a scene whose geometry IDs
+/// were assigned without detaching geometry gets the same IDs back. The
+/// scene is ready to be committed. Returns `None` if the cache was saved
+/// with a different key, and an error if the cache is corrupt.
+pub fn load_scene<'a, R: Read>(
+    device: &'a Device,
+    key: u64,
+    mut reader: R,
+) -> io::Result<Option<Scene<'a>>> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+    let mut c = Cursor { bytes: &bytes };
+    if c.take(MAGIC.len())? != MAGIC {
+        return Err(invalid_data("not a scene cache"));
+    }
+    if c.u32()? != VERSION {
+        return Err(invalid_data("unsupported scene cache version"));
+    }
+    if c.u64()? != key {
+        return Ok(None);
+    }
+    let hash = c.u64()?;
+    let quality = build_quality(c.u32()?)?;
+    if fnv1a(c.bytes) != hash {
+        return Err(invalid_data("scene cache content hash mismatch"));
+    }
+
+    let mut scene = Scene::new(device);
+    scene.set_build_quality(quality);
+    for _ in 0..c.u32()? {
+        let kind = c.u32()?;
+        let time_steps = c.u32()?;
+        let num_verts = c.u32()? as usize;
+        let num_prims = c.u32()? as usize;
+        let mut geom = match kind {
+            TRIANGLE if time_steps > 0 => {
+                let mut mesh = TriangleMesh::animated(device, num_prims, num_verts, time_steps);
+                for t in 0..time_steps {
+                    let mut verts = mesh.time_step(t).map();
+                    for i in 0..num_verts {
+                        verts[i] = c.vertex()?;
+                    }
+                }
+                {
+                    let mut tris = mesh.index_buffer.map();
+                    for i in 0..num_prims {
+                        tris[i] = Vector3::new(c.u32()?, c.u32()?, c.u32()?);
+                    }
+                }
+                Geometry::Triangle(mesh)
+            }
+            QUAD if time_steps == 1 => {
+                let mut mesh = QuadMesh::unanimated(device, num_prims, num_verts);
+                {
+                    let mut verts = mesh.vertex_buffer.map();
+                    for i in 0..num_verts {
+                        verts[i] = c.vertex()?;
+                    }
+                }
+                {
+                    let mut quads = mesh.index_buffer.map();
+                    for i in 0..num_prims {
+                        quads[i] = Vector4::new(c.u32()?, c.u32()?, c.u32()?, c.u32()?);
+                    }
+                }
+                Geometry::Quad(mesh)
+            }
+            _ => return Err(invalid_data("invalid geometry in scene cache")),
+        };
+        geom.commit();
+        scene.attach_geometry(geom);
+    }
+    Ok(Some(scene))
+}
+
+/// A cache file for a static scene, see the module documentation
+#[derive(Debug, Clone)]
+pub struct SceneCache {
+    path: PathBuf,
+}
+
+impl SceneCache {
+    pub fn new<P: AsRef<Path>>(path: P) -> SceneCache {
+        SceneCache {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+    /// Load the scene from the cache if it was saved with the same `key`,
+    /// otherwise build it with `build` and save it to the cache. A missing,
+    /// stale or corrupt cache file is rebuilt. Returns the scene and
+    /// whether it was loaded from the cache.
+    pub fn load_or_build<'a, F>(
+        &self,
+        device: &'a Device,
+        key: u64,
+        build: F,
+    ) -> io::Result<(Scene<'a>, bool)>
+    where
+        F: FnOnce(&'a Device) -> Scene<'a>,
+    {
+        if let Ok(file) = File::open(&self.path) {
+            if let Ok(Some(scene)) = load_scene(device, key, BufReader::new(file)) {
+                return Ok((scene, true));
+            }
+        }
+        let scene = build(device);
+        save_scene(&scene, key, BufWriter::new(File::create(&self.path)?))?;
+        Ok((scene, false))
+    }
+}
+
+#[test]
+fn test_fnv1a() {
+    // Reference values for 64-bit FNV-1a
+    assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+    assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+    assert_eq!(fnv1a(b"foobar"), 0x8594_4171_f739_67e8);
+}
@@ -0,0 +1,312 @@
+//! Incremental updates of a scene from an external scene description, e.g.
+//! the meshes sent each frame by a bridge from a content creation tool.
+//! Rebuilding the scene from scratch each frame throws away the geometry
+//! and BVHs of meshes which didn't change, so instead the meshes of the
+//! previous and current frame are diffed by a stable key and only the
+//! difference is applied to the scene:
+//!
+//! - Meshes which are no longer in the description are detached.
+//! - Meshes with the same number of vertices and triangles whose positions
+//!   or indices changed are updated in place in their existing buffers.
+//! - Meshes whose vertex or triangle count changed are replaced, as the
+//!   size of Embree's buffers is fixed when they're created.
+//! - New meshes are attached.
+//!
+//! `SceneSync` tracks the geometry ID of each key to apply the diff. The
+//! changed geometry is committed when the diff is applied, so only the
+//! scene has to be committed afterwards, which can be skipped if
+//! `SceneChanges::is_empty`.
+//!
+//! ```ignore
+//! let mut sync = SceneSync::new();
+//! let mut previous = Vec::new();
+//! loop {
+//!     let current = bridge.receive_meshes();
+//!     let changes = sync.apply(&device, &mut scene, &previous, &current)?;
+//!     if !changes.is_empty() {
+//!         let rtscene = scene.commit();
+//!         // ...
+//!     }
+//!     previous = current;
+//! }
+//! ```
+
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use cgmath::{Vector3, Vector4};
+
+use device::Device;
+use geometry::{self, Geometry, MeshError};
+use scene::Scene;
+use triangle_mesh::TriangleMesh;
+
+/// A triangle mesh from an external scene description, identified by a
+/// key which is stable across frames, e.g. the mesh's object path
+#[derive(Debug, Copy, Clone)]
+pub struct MeshDescriptor<'d, K> {
+    pub key: K,
+    pub positions: &'d [[f32; 3]],
+    pub indices: &'d [[u32; 3]],
+}
+
+/// How a mesh changed between the previous and current frame
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MeshChange {
+    /// The mesh is new in the current frame
+    Added,
+    /// The mesh is no longer in the current frame
+    Removed,
+    /// The mesh's positions or indices changed, but not their counts
+    Updated {
+        positions: bool,
+        indices: bool,
+    },
+    /// The mesh's vertex or triangle count changed
+    Replaced,
+    Unchanged,
+}
+
+/// Diff the meshes of the previous and current frame by their keys. The
+/// changes are returned in the order of `current`, followed by the meshes
+/// removed from `previous` in its order.
+///
+/// Panics if a key appears more than once in either frame.
+pub fn diff_meshes<'p, 'c, K: Hash + Eq + Clone>(
+    previous: &[MeshDescriptor<'p, K>],
+    current: &[MeshDescriptor<'c, K>],
+) -> Vec<(K, MeshChange)> {
+    let mut prev = HashMap::with_capacity(previous.len());
+    for (i, m) in previous.iter().enumerate() {
+        assert!(
+            prev.insert(&m.key, i).is_none(),
+            "Duplicate mesh key in the previous frame"
+        );
+    }
+    let mut seen = HashMap::with_capacity(current.len());
+    let mut changes = Vec::with_capacity(current.len());
+    for m in current {
+        assert!(
+            seen.insert(&m.key, ()).is_none(),
+            "Duplicate mesh key in the current frame"
+        );
+        let change = match prev.get(&m.key) {
+            None => MeshChange::Added,
+            Some(&i) => {
+                let p = &previous[i];
+                if p.positions.len() != m.positions.len() || p.indices.len() != m.indices.len() {
+                    MeshChange::Replaced
+                } else {
+                    let positions = p.positions != m.positions;
+                    let indices = p.indices != m.indices;
+                    if positions || indices {
+                        MeshChange::Updated { positions, indices }
+                    } else {
+                        MeshChange::Unchanged
+                    }
+                }
+            }
+        };
+        changes.push((m.key.clone(), change));
+    }
+    for m in previous {
+        if !seen.contains_key(&m.key) {
+            changes.push((m.key.clone(), MeshChange::Removed));
+        }
+    }
+    changes
+}
+
+/// The changes made to a scene by `SceneSync::apply`, by mesh key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SceneChanges<K> {
+    pub added: Vec<K>,
+    pub removed: Vec<K>,
+    pub updated: Vec<K>,
+    pub replaced: Vec<K>,
+    /// The number of meshes left as they were
+    pub unchanged: usize,
+}
+
+impl<K> SceneChanges<K> {
+    /// Whether the scene wasn't changed and doesn't need to be committed
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.updated.is_empty()
+            && self.replaced.is_empty()
+    }
+}
+
+/// Tracks the geometry a scene holds for each mesh of an external scene
+/// description, to apply the changes between frames to it. See the module
+/// documentation.
+pub struct SceneSync<K> {
+    ids: HashMap<K, u32>,
+}
+
+impl<K: Hash + Eq + Clone> SceneSync<K> {
+    pub fn new() -> SceneSync<K> {
+        SceneSync {
+            ids: HashMap::new(),
+        }
+    }
+    /// Get the ID of the geometry in the scene for the mesh
+    pub fn id(&self, key: &K) -> Option<u32> {
+        self.ids.get(key).cloned()
+    }
+    /// Apply the changes between the meshes of the previous and current
+    /// frame to the scene, whose meshes must have been attached by the
+    /// previous calls to `apply`, with `previous` the meshes passed as
+    /// `current` to the last call. The changed geometry is committed, but
+    /// the scene must be committed for the changes to take effect.
+    ///
+    /// The scene is left unchanged if an added or changed mesh has an
+    /// index out of bounds.
+    pub fn apply<'a, 'p, 'c>(
+        &mut self,
+        device: &'a Device,
+        scene: &mut Scene<'a>,
+        previous: &[MeshDescriptor<'p, K>],
+        current: &[MeshDescriptor<'c, K>],
+    ) -> Result<SceneChanges<K>, MeshError> {
+        let diff = diff_meshes(previous, current);
+        for (m, &(_, change)) in current.iter().zip(diff.iter()) {
+            if change != MeshChange::Unchanged && change != MeshChange::Removed {
+                geometry::validate_indices(m.indices, m.positions.len())?;
+            }
+        }
+
+        let mut changes = SceneChanges {
+            added: Vec::new(),
+            removed: Vec::new(),
+            updated: Vec::new(),
+            replaced: Vec::new(),
+            unchanged: 0,
+        };
+        // Detach the removed and replaced meshes first so their IDs can be
+        // reused by the meshes attached
+        for &(ref key, change) in diff.iter() {
+            if change == MeshChange::Removed || change == MeshChange::Replaced {
+                if let Some(id) = self.ids.remove(key) {
+                    scene.deattach_geometry(id);
+                }
+            }
+        }
+        for (m, (key, change)) in current.iter().zip(diff.iter().cloned()) {
+            match change {
+                MeshChange::Updated { positions, indices } => {
+                    let id = self.ids[&key];
+                    if let Some(&mut Geometry::Triangle(ref mut mesh)) =
+                        scene.geometry_by_raw_id_mut(id)
+                    {
+                        if positions {
+                            let mut verts = mesh.vertex_buffer.map();
+                            for (i, p) in m.positions.iter().enumerate() {
+                                verts[i] = Vector4::new(p[0], p[1], p[2], 0.0);
+                            }
+                        }
+                        if indices {
+                            let mut tris = mesh.index_buffer.map();
+                            for (i, t) in m.indices.iter().enumerate() {
+                                tris[i] = Vector3::new(t[0], t[1], t[2]);
+                            }
+                        }
+                    }
+                    scene.geometry_by_raw_id_mut(id).unwrap().commit();
+                    changes.updated.push(key);
+                }
+                MeshChange::Added | MeshChange::Replaced => {
+                    let mesh = TriangleMesh::try_from_slices(device, m.positions, m.indices)?;
+                    let id = scene.attach_geometry(Geometry::Triangle(mesh));
+                    self.ids.insert(key.clone(), id);
+                    if change == MeshChange::Added {
+                        changes.added.push(key);
+                    } else {
+                        changes.replaced.push(key);
+                    }
+                }
+                MeshChange::Unchanged => changes.unchanged += 1,
+                MeshChange::Removed => {}
+            }
+        }
+        changes.removed = diff
+            .into_iter()
+            .filter(|&(_, change)| change == MeshChange::Removed)
+            .map(|(key, _)| key)
+            .collect();
+        Ok(changes)
+    }
+}
+
+impl<K: Hash + Eq + Clone> Default for SceneSync<K> {
+    fn default() -> SceneSync<K> {
+        SceneSync::new()
+    }
+}
+
+#[test]
+fn test_diff_meshes() {
+    let tri = [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]];
+    let moved = [[0.0, 0.0, 1.0], [1.0, 0.0, 1.0], [0.0, 1.0, 1.0]];
+    let quad = [
+        [0.0, 0.0, 0.0],
+        [1.0, 0.0, 0.0],
+        [1.0, 1.0, 0.0],
+        [0.0, 1.0, 0.0],
+    ];
+    let mesh = |key, positions, indices| MeshDescriptor {
+        key,
+        positions,
+        indices,
+    };
+    let previous = [
+        mesh("kept", &tri[..], &[[0, 1, 2]][..]),
+        mesh("moved", &tri[..], &[[0, 1, 2]][..]),
+        mesh("flipped", &tri[..], &[[0, 1, 2]][..]),
+        mesh("grown", &tri[..], &[[0, 1, 2]][..]),
+        mesh("gone", &tri[..], &[[0, 1, 2]][..]),
+    ];
+    let current = [
+        mesh("new", &tri[..], &[[0, 1, 2]][..]),
+        mesh("grown", &quad[..], &[[0, 1, 2], [0, 2, 3]][..]),
+        mesh("flipped", &tri[..], &[[0, 2, 1]][..]),
+        mesh("moved", &moved[..], &[[0, 1, 2]][..]),
+        mesh("kept", &tri[..], &[[0, 1, 2]][..]),
+    ];
+    assert_eq!(
+        diff_meshes(&previous, &current),
+        vec![
+            ("new", MeshChange::Added),
+            ("grown", MeshChange::Replaced),
+            (
+                "flipped",
+                MeshChange::Updated {
+                    positions: false,
+                    indices: true
+                }
+            ),
+            (
+                "moved",
+                MeshChange::Updated {
+                    positions: true,
+                    indices: false
+                }
+            ),
+            ("kept", MeshChange::Unchanged),
+            ("gone", MeshChange::Removed),
+        ]
+    );
+}
+
+#[test]
+#[should_panic]
+fn test_diff_duplicate_keys() {
+    let tri = [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]];
+    let m = MeshDescriptor {
+        key: 0,
+        positions: &tri[..],
+        indices: &[[0, 1, 2]][..],
+    };
+    diff_meshes(&[], &[m, m]);
+}
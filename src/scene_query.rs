@@ -0,0 +1,98 @@
+//! Convenience queries for tools and simple renderers which trace single
+//! rays from a point in a direction or between two points, without setting
+//! up `Ray`s and intersect contexts.
+//!
+//! The queries are provided by the `SceneQuery` extension trait, which is
+//! only implemented for `CommittedScene`, and for the `reference::Tracer`
+//! built from one with the `reference` feature. A scene can only be queried once
+//! it has been committed, and committing borrows the `Scene`, so its
+//! geometry can't be attached, detached or changed through the `Scene`
+//! while a `CommittedScene` is alive. This doesn't cover data the scene
+//! doesn't own, such as buffers attached with `Buffer::attach_vertices`,
+//! slices shared with `Geometry::set_shared_buffer_from_slice` or scenes
+//! it instances, whose changes queries only see after the scene is
+//! committed again. The scene can only be committed again once the
+//! `CommittedScene`s of its previous commit are dropped, `commit` panics
+//! otherwise. Querying a scene which wasn't committed doesn't compile:
+//!
+//! ```compile_fail
+//! # extern crate cgmath;
+//! # extern crate embree;
+//! # use cgmath::Vector3;
+//! # use embree::{Device, Scene, SceneQuery};
+//! # let device = Device::new();
+//! let scene = Scene::new(&device);
+//! let t = scene.hit_distance(Vector3::new(0.0, 0.0, 1.0), Vector3::new(0.0, 0.0, -1.0));
+//! ```
+//!
+//! Nor does modifying it while it's committed:
+//!
+//! ```compile_fail
+//! # extern crate cgmath;
+//! # extern crate embree;
+//! # use cgmath::Vector3;
+//! # use embree::{Device, Geometry, Scene, SceneQuery, TriangleMesh};
+//! # let device = Device::new();
+//! # let mesh = Geometry::Triangle(TriangleMesh::unanimated(&device, 1, 3));
+//! let mut scene = Scene::new(&device);
+//! let rtscene = scene.commit();
+//! scene.attach_geometry(mesh);
+//! let t = rtscene.hit_distance(Vector3::new(0.0, 0.0, 1.0), Vector3::new(0.0, 0.0, -1.0));
+//! ```
+//!
+//! Changes to a committed scene are made by dropping the `CommittedScene`,
+//! or in an `edit` scope on the scene which commits it once the changes
+//! are made:
+//!
+//! ```no_run
+//! # extern crate cgmath;
+//! # extern crate embree;
+//! # use cgmath::Vector3;
+//! # use embree::{Device, Geometry, Scene, SceneQuery, TriangleMesh};
+//! # let device = Device::new();
+//! # let mesh = Geometry::Triangle(TriangleMesh::unanimated(&device, 1, 3));
+//! let mut scene = Scene::new(&device);
+//! let rtscene = scene.edit(|scene| {
+//!     scene.attach_geometry(mesh);
+//! });
+//! let lit = rtscene.visible(Vector3::new(0.0, 0.0, 1.0), Vector3::new(0.0, 5.0, 1.0));
+//! ```
+
+use cgmath::Vector3;
+
+use ray::{Ray, RayHit};
+use scene::CommittedScene;
+
+/// Queries from a point in a direction or between two points, see the
+/// `scene_query` module
+pub trait SceneQuery {
+    /// Find the closest hit along the ray from `origin` in direction `dir`
+    fn closest_hit(&self, origin: Vector3<f32>, dir: Vector3<f32>) -> Option<RayHit>;
+    /// Find the distance to the closest hit along the ray from `origin` in
+    /// direction `dir`, in units of `dir`'s length
+    fn hit_distance(&self, origin: Vector3<f32>, dir: Vector3<f32>) -> Option<f32>;
+    /// Find the point of the closest hit along the ray from `origin` in
+    /// direction `dir`
+    fn hit_point(&self, origin: Vector3<f32>, dir: Vector3<f32>) -> Option<Vector3<f32>>;
+    /// Test if nothing blocks the segment between the points `from` and
+    /// `to`, e.g. a point on a surface and a light sample. The ends of the
+    /// segment are offset by the scene's traversal settings to avoid
+    /// hitting the surfaces the points lie on.
+    fn visible(&self, from: Vector3<f32>, to: Vector3<f32>) -> bool;
+}
+
+impl<'a> SceneQuery for CommittedScene<'a> {
+    fn closest_hit(&self, origin: Vector3<f32>, dir: Vector3<f32>) -> Option<RayHit> {
+        self.intersect_ray(&Ray::new(origin, dir))
+    }
+    fn hit_distance(&self, origin: Vector3<f32>, dir: Vector3<f32>) -> Option<f32> {
+        self.closest_hit(origin, dir).map(|h| h.ray.tfar)
+    }
+    fn hit_point(&self, origin: Vector3<f32>, dir: Vector3<f32>) -> Option<Vector3<f32>> {
+        self.hit_distance(origin, dir).map(|t| origin + dir * t)
+    }
+    fn visible(&self, from: Vector3<f32>, to: Vector3<f32>) -> bool {
+        let ray = self.traversal_settings().spawn_ray_to(from, to);
+        !self.is_occluded(&ray)
+    }
+}
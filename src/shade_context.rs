@@ -0,0 +1,168 @@
+//! A context for shading a ray hit, bundling the scene, the hit and the
+//! lookups shaders commonly make for it: the geometry hit, resolved through
+//! an instance if the hit was on an instanced scene, its transform to world
+//! space, the world space normal and whether the hit is on the front face.
+//! These are computed on first use and cached, so shading code can call the
+//! accessors freely without repeating the work.
+
+use std::cell::Cell;
+use std::os::raw;
+
+use cgmath::{InnerSpace, Matrix4, SquareMatrix, Vector3};
+
+use geometry::Geometry;
+use instance::transform_normal;
+use ray::{Hit, Ray, RayHit};
+use scene::CommittedScene;
+use sys::*;
+use triangle_mesh::AttributeValue;
+use Format;
+
+/// A ray hit along with the scene it was found in, for shading it. See the
+/// module documentation.
+pub struct ShadeContext<'s> {
+    scene: &'s CommittedScene<'s>,
+    ray_hit: RayHit,
+    geometry: Cell<Option<&'s Geometry<'s>>>,
+    transform: Cell<Option<Matrix4<f32>>>,
+    normal: Cell<Option<Vector3<f32>>>,
+}
+
+impl<'s> ShadeContext<'s> {
+    /// Create a context for shading the hit, which must have been found by
+    /// a query on `scene`.
+    ///
+    /// Panics if the ray didn't hit anything.
+    pub fn new(scene: &'s CommittedScene<'s>, ray_hit: RayHit) -> ShadeContext<'s> {
+        assert!(
+            ray_hit.hit.hit(),
+            "Can't shade a ray which missed the scene"
+        );
+        ShadeContext {
+            scene,
+            ray_hit,
+            geometry: Cell::new(None),
+            transform: Cell::new(None),
+            normal: Cell::new(None),
+        }
+    }
+    pub fn ray_hit(&self) -> &RayHit {
+        &self.ray_hit
+    }
+    pub fn ray(&self) -> &Ray {
+        &self.ray_hit.ray
+    }
+    pub fn hit(&self) -> &Hit {
+        &self.ray_hit.hit
+    }
+    /// Get the world space position of the hit
+    pub fn position(&self) -> Vector3<f32> {
+        let ray = self.ray();
+        ray.origin() + ray.dir() * ray.tfar
+    }
+    /// Get the hit's parametric coordinates on the primitive
+    pub fn uv(&self) -> (f32, f32) {
+        self.hit().uv()
+    }
+    /// Get the instance the hit geometry was found through, if the hit is
+    /// on an instanced scene
+    pub fn instance(&self) -> Option<&'s Geometry<'s>> {
+        let inst_id = self.hit().instID[0];
+        if inst_id == u32::MAX {
+            None
+        } else {
+            self.scene.scene.geometry_by_raw_id(inst_id)
+        }
+    }
+    /// Get the geometry which was hit, in the instanced scene if the hit is
+    /// on an instance
+    pub fn geometry(&self) -> &'s Geometry<'s> {
+        if let Some(g) = self.geometry.get() {
+            return g;
+        }
+        let geom_id = self.hit().geomID;
+        let geometry = match self.instance() {
+            Some(Geometry::Instance(i)) => i.scene.scene.geometry_by_raw_id(geom_id),
+            Some(_) => panic!("Hit instance {} isn't an instance", self.hit().instID[0]),
+            None => self.scene.scene.geometry_by_raw_id(geom_id),
+        };
+        let geometry = geometry
+            .unwrap_or_else(|| panic!("No geometry {} is attached to the hit scene", geom_id));
+        self.geometry.set(Some(geometry));
+        geometry
+    }
+    /// Get the transform from the hit geometry's space to world space at
+    /// the ray's time, which is the instance's transform for instanced
+    /// hits and the identity otherwise
+    pub fn world_transform(&self) -> Matrix4<f32> {
+        if let Some(t) = self.transform.get() {
+            return t;
+        }
+        let transform = match self.instance() {
+            Some(inst) => {
+                let mut mat = [[0.0f32; 4]; 4];
+                unsafe {
+                    rtcGetGeometryTransform(
+                        inst.handle(),
+                        self.ray().time,
+                        Format::FLOAT4X4_COLUMN_MAJOR,
+                        mat.as_mut_ptr() as *mut raw::c_void,
+                    );
+                }
+                Matrix4::from(mat)
+            }
+            None => Matrix4::identity(),
+        };
+        self.transform.set(Some(transform));
+        transform
+    }
+    /// Get the normalized world space geometric normal of the hit
+    pub fn normal(&self) -> Vector3<f32> {
+        if let Some(n) = self.normal.get() {
+            return n;
+        }
+        let ng = self.hit().normal();
+        let n = if self.instance().is_some() {
+            transform_normal(&self.world_transform(), ng)
+        } else {
+            ng
+        };
+        let n = n.normalize();
+        self.normal.set(Some(n));
+        n
+    }
+    /// Whether the ray hit the front face of the geometry, the side its
+    /// geometric normal points to
+    pub fn front_facing(&self) -> bool {
+        self.normal().dot(self.ray().dir()) < 0.0
+    }
+    /// Get the world space geometric normal flipped to face the ray, e.g.
+    /// for offsetting secondary rays or shading two sided surfaces
+    pub fn facing_normal(&self) -> Vector3<f32> {
+        if self.front_facing() {
+            self.normal()
+        } else {
+            -self.normal()
+        }
+    }
+    /// Interpolate the vertex attribute in `slot` of the hit geometry at
+    /// the hit. Returns `None` if the geometry isn't a triangle or
+    /// subdivision mesh, the geometry types which own their attribute
+    /// buffers, or has no attribute in the slot. For triangle meshes it's
+    /// also `None` if their buffers were replaced by shared data, see
+    /// `TriangleMesh::interpolate_attribute_cpu`.
+    pub fn attribute<T: AttributeValue>(&self, slot: u32) -> Option<T> {
+        match *self.geometry() {
+            Geometry::Triangle(ref m) => m.interpolate_attribute_cpu(self.hit(), slot),
+            #[cfg(feature = "subdivision")]
+            Geometry::Subdivision(ref s) => {
+                if (slot as usize) < s.vertex_attribute_buffers.len() {
+                    Some(s.interpolate_attribute(self.hit(), slot))
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        }
+    }
+}
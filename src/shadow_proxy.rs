@@ -0,0 +1,56 @@
+//! Shadow proxies are cheaper stand-in geometry which replace a geometry
+//! in a scene for occlusion queries only, e.g. tracing hair as flat
+//! ribbons or using a decimated mesh for shadow rays while primary rays
+//! still see the full geometry. Proxies are registered on the scene with
+//! `Scene::set_shadow_proxy`, and occlusion queries are run against them
+//! through the view returned by `CommittedScene::shadow_proxies`.
+
+use buffer::Buffer;
+use device::Device;
+use geometry::Geometry;
+use {BezierCurve, BsplineCurve, CatmullRomCurve, HermiteCurve, LinearCurve};
+
+fn copy_buffer<'a, T: Copy + 'a>(src: &Buffer<T>, dst: &mut Buffer<'a, T>) {
+    let mut mapped = dst.map();
+    for (i, x) in src.as_slice().iter().enumerate() {
+        mapped[i] = *x;
+    }
+}
+
+/// Build a flat ribbon version of the curve geometry passed, sharing its
+/// basis, control points and segments, for use as a shadow proxy. Flat
+/// curves are much cheaper to intersect than round or normal oriented
+/// curves, while casting nearly the same shadow for thin hair and fur.
+/// The proxy is committed and ready to be passed to
+/// `Scene::set_shadow_proxy`. Returns `None` if the geometry isn't a curve.
+pub fn flat_curve_proxy<'a>(device: &'a Device, geom: &Geometry) -> Option<Geometry<'a>> {
+    macro_rules! flat_curve {
+        ($ty:ident, $c:expr) => {{
+            let c = $c;
+            let mut proxy = $ty::flat(device, c.index_buffer.len(), c.vertex_buffer.len(), false);
+            copy_buffer(&c.vertex_buffer, &mut proxy.vertex_buffer);
+            copy_buffer(&c.index_buffer, &mut proxy.index_buffer);
+            proxy
+        }};
+    }
+    let mut proxy = match *geom {
+        Geometry::LinearCurve(ref c) => {
+            let mut proxy = flat_curve!(LinearCurve, c);
+            copy_buffer(&c.flag_buffer, &mut proxy.flag_buffer);
+            Geometry::LinearCurve(proxy)
+        }
+        Geometry::BezierCurve(ref c) => Geometry::BezierCurve(flat_curve!(BezierCurve, c)),
+        Geometry::BsplineCurve(ref c) => Geometry::BsplineCurve(flat_curve!(BsplineCurve, c)),
+        Geometry::CatmullRomCurve(ref c) => {
+            Geometry::CatmullRomCurve(flat_curve!(CatmullRomCurve, c))
+        }
+        Geometry::HermiteCurve(ref c) => {
+            let mut proxy = flat_curve!(HermiteCurve, c);
+            copy_buffer(&c.tangent_buffer, &mut proxy.tangent_buffer);
+            Geometry::HermiteCurve(proxy)
+        }
+        _ => return None,
+    };
+    proxy.commit();
+    Some(proxy)
+}
@@ -0,0 +1,194 @@
+//! Linear blend skinning of animated characters. A `Skin` holds a mesh's
+//! bind pose vertices and the bones influencing each vertex. Each frame,
+//! `Skin::apply` transforms the bind pose by the frame's bone matrices and
+//! writes the skinned positions straight into the mesh's vertex buffer,
+//! splitting the vertices over the available threads. The buffer is marked
+//! as updated on the geometry, which then needs to be committed.
+//!
+//! Skinning moves the vertices but keeps the mesh's topology, so the BVH
+//! can be refit to the new positions instead of rebuilt. Set
+//! `BuildQuality::REFIT` on the geometry once when creating it:
+//!
+//! ```no_run
+//! # extern crate cgmath;
+//! # extern crate embree;
+//! # use cgmath::{Matrix4, SquareMatrix, Vector3};
+//! # use embree::{BuildQuality, Device, Geometry, Skin, TriangleMesh, VertexInfluence};
+//! # let device = Device::new();
+//! # let positions = vec![Vector3::new(0.0, 0.0, 0.0); 3];
+//! # let influences = vec![VertexInfluence::single(0); 3];
+//! let mesh = TriangleMesh::unanimated(&device, 1, 3);
+//! let skin = Skin::new(positions, influences);
+//! let mut geom = Geometry::Triangle(mesh);
+//! geom.set_build_quality(BuildQuality::REFIT);
+//! // Each frame
+//! # let bones = vec![Matrix4::identity()];
+//! if let Geometry::Triangle(ref mut mesh) = geom {
+//!     skin.apply(&bones, &mut mesh.vertex_buffer);
+//! }
+//! geom.commit();
+//! ```
+//!
+//! The scene holding the geometry must be committed again as well. Only
+//! positions are skinned, normals computed from the skinned positions
+//! should be used for shading.
+
+use std::thread;
+
+use cgmath::{Matrix4, Vector3, Vector4};
+
+use buffer::{Buffer, VertexElement};
+
+/// The bones influencing a vertex and their weights. A vertex is
+/// influenced by up to 4 bones, unused influences have a weight of 0. The
+/// weights should sum to 1.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct VertexInfluence {
+    pub bones: [u32; 4],
+    pub weights: [f32; 4],
+}
+
+impl VertexInfluence {
+    /// A vertex moved rigidly by a single bone
+    pub fn single(bone: u32) -> VertexInfluence {
+        VertexInfluence {
+            bones: [bone, 0, 0, 0],
+            weights: [1.0, 0.0, 0.0, 0.0],
+        }
+    }
+    fn skin(&self, bones: &[Matrix4<f32>], p: Vector4<f32>) -> Vector3<f32> {
+        let mut skinned = Vector4::new(0.0, 0.0, 0.0, 0.0);
+        for (b, w) in self.bones.iter().zip(self.weights.iter()) {
+            if *w != 0.0 {
+                skinned += bones[*b as usize] * p * *w;
+            }
+        }
+        skinned.truncate()
+    }
+}
+
+/// The bind pose of a skinned mesh and the bones influencing each of its
+/// vertices
+pub struct Skin {
+    bind_pose: Vec<Vector3<f32>>,
+    influences: Vec<VertexInfluence>,
+    /// The number of bone matrices the influences index
+    bone_count: usize,
+}
+
+impl Skin {
+    /// Create a skin from the bind pose positions of the mesh's vertices
+    /// and the influences on each vertex.
+    ///
+    /// Panics if there isn't an influence for each vertex.
+    pub fn new(bind_pose: Vec<Vector3<f32>>, influences: Vec<VertexInfluence>) -> Skin {
+        assert_eq!(
+            bind_pose.len(),
+            influences.len(),
+            "A skin needs an influence for each vertex"
+        );
+        let bone_count = influences
+            .iter()
+            .flat_map(|inf| inf.bones.iter().zip(inf.weights.iter()))
+            .filter(|&(_, w)| *w != 0.0)
+            .map(|(b, _)| *b as usize + 1)
+            .max()
+            .unwrap_or(0);
+        Skin {
+            bind_pose,
+            influences,
+            bone_count,
+        }
+    }
+    pub fn len(&self) -> usize {
+        self.bind_pose.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.bind_pose.is_empty()
+    }
+    /// Get the number of bone matrices needed to skin the mesh
+    pub fn bone_count(&self) -> usize {
+        self.bone_count
+    }
+    /// Skin the bind pose by the bone matrices, writing the skinned
+    /// positions to `out`. Each bone matrix transforms from the bind pose
+    /// to the bone's pose in the frame, i.e. the bone's world transform
+    /// times its inverse bind transform.
+    ///
+    /// Panics if there are fewer bone matrices than `bone_count` or `out`
+    /// is shorter than the bind pose.
+    pub fn skin_into<V: VertexElement + Send>(&self, bones: &[Matrix4<f32>], out: &mut [V]) {
+        assert!(
+            bones.len() >= self.bone_count,
+            "The skin needs {} bone matrices, got {}",
+            self.bone_count,
+            bones.len()
+        );
+        assert!(
+            out.len() >= self.len(),
+            "Output of {} vertices is too short for a skin of {} vertices",
+            out.len(),
+            self.len()
+        );
+        let out = &mut out[..self.len()];
+        let threads = thread::available_parallelism().map_or(1, |n| n.get());
+        let chunk_size = self.len().div_ceil(threads).max(1024);
+        thread::scope(|s| {
+            for ((ps, infs), vs) in self
+                .bind_pose
+                .chunks(chunk_size)
+                .zip(self.influences.chunks(chunk_size))
+                .zip(out.chunks_mut(chunk_size))
+            {
+                s.spawn(move || {
+                    for ((p, inf), v) in ps.iter().zip(infs.iter()).zip(vs.iter_mut()) {
+                        *v = V::from_position(inf.skin(bones, p.extend(1.0)).into());
+                    }
+                });
+            }
+        });
+    }
+    /// Skin the bind pose by the bone matrices as in `skin_into`, writing
+    /// the skinned positions into the vertex buffer and marking it as
+    /// updated on the geometry it's attached to.
+    pub fn apply<'a, V>(&self, bones: &[Matrix4<f32>], vertices: &mut Buffer<'a, V>)
+    where
+        V: VertexElement + Send + 'a,
+    {
+        vertices.mapped_scope(|v| self.skin_into(bones, v));
+    }
+}
+
+#[test]
+fn test_skin_into() {
+    use cgmath::SquareMatrix;
+
+    let bind_pose = vec![
+        Vector3::new(0.0, 0.0, 0.0),
+        Vector3::new(1.0, 0.0, 0.0),
+        Vector3::new(2.0, 0.0, 0.0),
+    ];
+    let halfway = VertexInfluence {
+        bones: [0, 1, 0, 0],
+        weights: [0.5, 0.5, 0.0, 0.0],
+    };
+    let influences = vec![
+        VertexInfluence::single(0),
+        halfway,
+        VertexInfluence::single(1),
+    ];
+    let skin = Skin::new(bind_pose, influences);
+    assert_eq!(skin.bone_count(), 2);
+
+    let bones = [
+        Matrix4::identity(),
+        Matrix4::from_translation(Vector3::new(0.0, 2.0, 0.0)),
+    ];
+    let mut out = vec![[0.0; 4]; 4];
+    skin.skin_into(&bones, &mut out);
+    assert_eq!(out[0], [0.0, 0.0, 0.0, 0.0]);
+    assert_eq!(out[1], [1.0, 1.0, 0.0, 0.0]);
+    assert_eq!(out[2], [2.0, 2.0, 0.0, 0.0]);
+    // Vertices past the end of the skin are left as they were
+    assert_eq!(out[3], [0.0; 4]);
+}
@@ -0,0 +1,683 @@
+//! Snapshots of a scene's geometry, e.g. to ship a scene which renders
+//! incorrectly as a repro case. A snapshot stores the input Embree builds
+//! the scene from: the scene flags and build quality, and each attached
+//! geometry with its ID, kind, enabled state, mask, hit face mode and
+//! buffers. `load_snapshot` rebuilds an identical scene from it on any
+//! machine, though the BVH isn't stored and the scene must be committed.
+//!
+//! Triangle meshes keep their motion blur time steps and vertex attributes,
+//! points their normals, curves their type, normals, flags and tangents,
+//! and subdivision meshes each topology's indices and boundary mode.
+//! Filter functions, shadow proxies, tessellation rates and the vertex
+//! attributes of subdivision meshes aren't stored. Instances and user
+//! geometries can't be stored, as their scenes and shapes live outside the
+//! scene's buffers, so saving a scene holding them returns an error. The
+//! same goes for geometry with buffers shared from user memory, e.g. with
+//! `Geometry::set_shared_buffer_from_slice`, as only the geometry's own
+//! buffers can be read.
+//!
+//! The format is little endian and versioned, with a hash of the content
+//! to detect corrupt files. Curves and subdivision meshes can only be
+//! loaded with the `curves` and `subdivision` features enabled.
+
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use cgmath::{Vector3, Vector4};
+
+use buffer::Buffer;
+use device::Device;
+use filter::HitFaceMode;
+use geometry::Geometry;
+use grid_mesh::{Grid, GridMesh};
+use point_geometry::{PointGeometry, PointType};
+use quad_mesh::QuadMesh;
+use scene::Scene;
+use scene_cache::{self, fnv1a, invalid_data, put_u32, Cursor};
+use triangle_mesh::TriangleMesh;
+use validation;
+use SceneFlags;
+
+#[cfg(feature = "curves")]
+use bezier_curve::BezierCurve;
+#[cfg(feature = "curves")]
+use bspline_curve::BsplineCurve;
+#[cfg(feature = "curves")]
+use catmull_rom_curve::CatmullRomCurve;
+#[cfg(feature = "curves")]
+use hermite_curve::HermiteCurve;
+#[cfg(feature = "curves")]
+use linear_curve::LinearCurve;
+#[cfg(feature = "subdivision")]
+use subdivision_mesh::SubdivisionMesh;
+#[cfg(feature = "curves")]
+use CurveType;
+#[cfg(feature = "subdivision")]
+use SubdivisionMode;
+
+const MAGIC: &[u8; 8] = b"EMBRSNP\0";
+const VERSION: u32 = 1;
+
+const TRIANGLE: u32 = 0;
+const QUAD: u32 = 1;
+const GRID: u32 = 2;
+const POINT: u32 = 3;
+const LINEAR_CURVE: u32 = 4;
+const BEZIER_CURVE: u32 = 5;
+const BSPLINE_CURVE: u32 = 6;
+const HERMITE_CURVE: u32 = 7;
+const CATMULL_ROM_CURVE: u32 = 8;
+const SUBDIVISION: u32 = 9;
+
+/// A buffer element which can be written to and read from a snapshot
+trait Element: Copy {
+    /// The size of the element in the snapshot in bytes
+    const SIZE: usize;
+    fn put(&self, out: &mut Vec<u8>);
+    fn read(c: &mut Cursor) -> io::Result<Self>;
+}
+
+impl Element for u32 {
+    const SIZE: usize = 4;
+    fn put(&self, out: &mut Vec<u8>) {
+        put_u32(out, *self);
+    }
+    fn read(c: &mut Cursor) -> io::Result<u32> {
+        c.u32()
+    }
+}
+
+impl Element for f32 {
+    const SIZE: usize = 4;
+    fn put(&self, out: &mut Vec<u8>) {
+        put_u32(out, self.to_bits());
+    }
+    fn read(c: &mut Cursor) -> io::Result<f32> {
+        c.f32()
+    }
+}
+
+impl<T: Element> Element for Vector3<T> {
+    const SIZE: usize = 3 * T::SIZE;
+    fn put(&self, out: &mut Vec<u8>) {
+        self.x.put(out);
+        self.y.put(out);
+        self.z.put(out);
+    }
+    fn read(c: &mut Cursor) -> io::Result<Vector3<T>> {
+        Ok(Vector3::new(T::read(c)?, T::read(c)?, T::read(c)?))
+    }
+}
+
+impl<T: Element> Element for Vector4<T> {
+    const SIZE: usize = 4 * T::SIZE;
+    fn put(&self, out: &mut Vec<u8>) {
+        self.x.put(out);
+        self.y.put(out);
+        self.z.put(out);
+        self.w.put(out);
+    }
+    fn read(c: &mut Cursor) -> io::Result<Vector4<T>> {
+        Ok(Vector4::new(
+            T::read(c)?,
+            T::read(c)?,
+            T::read(c)?,
+            T::read(c)?,
+        ))
+    }
+}
+
+impl Element for Grid {
+    const SIZE: usize = 16;
+    fn put(&self, out: &mut Vec<u8>) {
+        put_u32(out, self.startVertexID);
+        put_u32(out, self.stride);
+        put_u32(out, u32::from(self.width));
+        put_u32(out, u32::from(self.height));
+    }
+    fn read(c: &mut Cursor) -> io::Result<Grid> {
+        let start = c.u32()?;
+        let stride = c.u32()?;
+        let (width, height) = (c.u32()?, c.u32()?);
+        if width > u32::from(u16::MAX) || height > u32::from(u16::MAX) {
+            return Err(invalid_data("invalid grid size in snapshot"));
+        }
+        Ok(Grid::new(start, stride, width as u16, height as u16))
+    }
+}
+
+fn put_buffer<T: Element>(out: &mut Vec<u8>, buf: &Buffer<T>) {
+    for x in buf.as_slice() {
+        x.put(out);
+    }
+}
+
+/// Write whether the buffer is set, followed by its elements if it is
+fn put_optional<T: Element>(out: &mut Vec<u8>, buf: &Option<Buffer<T>>) {
+    put_u32(out, buf.is_some() as u32);
+    if let Some(ref b) = *buf {
+        put_buffer(out, b);
+    }
+}
+
+/// Fill the buffer with as many elements as it holds
+fn read_buffer<'a, T: Element + 'a>(c: &mut Cursor, buf: &mut Buffer<'a, T>) -> io::Result<()> {
+    let mut mapped = buf.map();
+    for x in mapped.as_mut_slice() {
+        *x = T::read(c)?;
+    }
+    Ok(())
+}
+
+/// Fill a buffer written by `put_optional`, which must be set on the
+/// geometry if and only if it was set on the saved one
+fn read_optional<'a, T: Element + 'a>(
+    c: &mut Cursor,
+    buf: &mut Option<Buffer<'a, T>>,
+) -> io::Result<()> {
+    match (c.u32()?, buf.as_mut()) {
+        (0, None) => Ok(()),
+        (1, Some(b)) => read_buffer(c, b),
+        _ => Err(invalid_data(
+            "optional buffer doesn't match the geometry in snapshot",
+        )),
+    }
+}
+
+/// The size in the snapshot of `count` elements of `T`, saturating so an
+/// overflowing count is caught by `check_sizes`
+fn elements<T: Element>(count: usize) -> usize {
+    count.saturating_mul(T::SIZE)
+}
+
+/// Check the rest of the snapshot holds the buffers of the given sizes in
+/// bytes, before allocating them for a geometry
+fn check_sizes(c: &Cursor, sizes: &[usize]) -> io::Result<()> {
+    let total = sizes.iter().fold(0usize, |a, b| a.saturating_add(*b));
+    if total > c.bytes.len() {
+        return Err(invalid_data("geometry buffers exceed the snapshot data"));
+    }
+    Ok(())
+}
+
+fn unsupported(id: u32, what: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("geometry {} can't be saved in a snapshot, {}", id, what),
+    )
+}
+
+#[cfg(feature = "curves")]
+fn curve_type_code(curve_type: CurveType) -> u32 {
+    match curve_type {
+        CurveType::Flat => 0,
+        CurveType::NormalOriented => 1,
+        CurveType::Round => 2,
+        CurveType::Cone => 3,
+    }
+}
+
+#[cfg(feature = "curves")]
+fn curve_type(code: u32) -> io::Result<CurveType> {
+    match code {
+        0 => Ok(CurveType::Flat),
+        1 => Ok(CurveType::NormalOriented),
+        2 => Ok(CurveType::Round),
+        3 => Ok(CurveType::Cone),
+        _ => Err(invalid_data("invalid curve type in snapshot")),
+    }
+}
+
+#[cfg(feature = "subdivision")]
+fn subdivision_mode(code: u32) -> io::Result<SubdivisionMode> {
+    match code {
+        0 => Ok(SubdivisionMode::NO_BOUNDARY),
+        1 => Ok(SubdivisionMode::SMOOTH_BOUNDARY),
+        2 => Ok(SubdivisionMode::PIN_CORNERS),
+        3 => Ok(SubdivisionMode::PIN_BOUNDARY),
+        4 => Ok(SubdivisionMode::PIN_ALL),
+        _ => Err(invalid_data("invalid subdivision mode in snapshot")),
+    }
+}
+
+/// Write the curve type, counts and the buffers shared by all curves
+#[cfg(feature = "curves")]
+macro_rules! put_curve {
+    ($out:expr, $code:expr, $c:expr) => {{
+        let (out, c) = (&mut *$out, $c);
+        put_u32(out, $code);
+        put_u32(out, curve_type_code(c.curve_type));
+        put_u32(out, c.index_buffer.len() as u32);
+        put_u32(out, c.vertex_buffer.len() as u32);
+        put_u32(out, c.normal_buffer.is_some() as u32);
+        put_buffer(out, &c.vertex_buffer);
+        put_buffer(out, &c.index_buffer);
+        put_optional(out, &c.normal_buffer);
+    }};
+}
+
+/// Create the curve wrapper for the saved type and fill the buffers
+/// shared by all curves
+#[cfg(feature = "curves")]
+macro_rules! read_curve {
+    ($ty:ident, $device:expr, $c:expr, $vertex_buffers:expr) => {{
+        let c = &mut *$c;
+        let curve_type = curve_type(c.u32()?)?;
+        let s = c.u32()? as usize;
+        let v = c.u32()? as usize;
+        let normals = c.u32()? != 0;
+        let per_vertex = v.saturating_mul($vertex_buffers);
+        check_sizes(
+            c,
+            &[
+                elements::<Vector4<f32>>(per_vertex),
+                elements::<u32>(s),
+                elements::<Vector3<f32>>(if normals { per_vertex } else { 0 }),
+            ],
+        )?;
+        let mut curve = match curve_type {
+            CurveType::Flat => $ty::flat($device, s, v, normals),
+            CurveType::Round => $ty::round($device, s, v, normals),
+            CurveType::NormalOriented => $ty::normal_oriented($device, s, v),
+            CurveType::Cone => return Err(invalid_data("cone curves must be linear")),
+        };
+        read_buffer(c, &mut curve.vertex_buffer)?;
+        read_buffer(c, &mut curve.index_buffer)?;
+        read_optional(c, &mut curve.normal_buffer)?;
+        curve
+    }};
+}
+
+/// Serialize the scene's settings and geometry, returning the content
+/// to be hashed
+fn serialize_scene(scene: &Scene) -> io::Result<Vec<u8>> {
+    let mut out = Vec::new();
+    put_u32(&mut out, scene.flags().0);
+    put_u32(&mut out, scene.build_quality() as u32);
+    put_u32(&mut out, scene.geometry_ids().len() as u32);
+    for (id, geom) in scene.iter_ordered() {
+        if !geom.owns_bound_buffers() {
+            return Err(unsupported(id, "its buffers were replaced by shared data"));
+        }
+        put_u32(&mut out, id);
+        put_u32(&mut out, geom.is_enabled() as u32);
+        put_u32(&mut out, geom.mask());
+        put_u32(
+            &mut out,
+            match geom.hit_face_mode() {
+                HitFaceMode::Both => 0,
+                HitFaceMode::FrontOnly => 1,
+                HitFaceMode::BackOnly => 2,
+            },
+        );
+        let out = &mut out;
+        match *geom {
+            Geometry::Triangle(ref m) => {
+                put_u32(out, TRIANGLE);
+                put_u32(out, m.time_step_count());
+                put_u32(out, m.vertex_buffer.len() as u32);
+                put_u32(out, m.index_buffer.len() as u32);
+                put_u32(out, m.vertex_attribute_buffers.len() as u32);
+                put_buffer(out, &m.vertex_buffer);
+                for b in m.motion_vertex_buffers.iter() {
+                    put_buffer(out, b);
+                }
+                put_buffer(out, &m.index_buffer);
+                for b in m.vertex_attribute_buffers.iter() {
+                    put_buffer(out, b);
+                }
+            }
+            Geometry::Quad(ref m) => {
+                put_u32(out, QUAD);
+                put_u32(out, m.vertex_buffer.len() as u32);
+                put_u32(out, m.index_buffer.len() as u32);
+                put_buffer(out, &m.vertex_buffer);
+                put_buffer(out, &m.index_buffer);
+            }
+            Geometry::Grid(ref m) => {
+                put_u32(out, GRID);
+                put_u32(out, m.vertex_buffer.len() as u32);
+                put_u32(out, m.grid_buffer.len() as u32);
+                put_buffer(out, &m.vertex_buffer);
+                put_buffer(out, &m.grid_buffer);
+            }
+            Geometry::Point(ref p) => {
+                put_u32(out, POINT);
+                put_u32(
+                    out,
+                    match p.point_type() {
+                        PointType::Sphere => 0,
+                        PointType::Disc => 1,
+                        PointType::OrientedDisc => 2,
+                    },
+                );
+                put_u32(out, p.vertex_buffer.len() as u32);
+                put_buffer(out, &p.vertex_buffer);
+                put_optional(out, &p.normal_buffer);
+            }
+            #[cfg(feature = "curves")]
+            Geometry::LinearCurve(ref c) => {
+                put_curve!(out, LINEAR_CURVE, c);
+                put_buffer(out, &c.flag_buffer);
+            }
+            #[cfg(feature = "curves")]
+            Geometry::BezierCurve(ref c) => put_curve!(out, BEZIER_CURVE, c),
+            #[cfg(feature = "curves")]
+            Geometry::BsplineCurve(ref c) => put_curve!(out, BSPLINE_CURVE, c),
+            #[cfg(feature = "curves")]
+            Geometry::HermiteCurve(ref c) => {
+                put_curve!(out, HERMITE_CURVE, c);
+                put_buffer(out, &c.tangent_buffer);
+                put_optional(out, &c.normal_derivative_buffer);
+            }
+            #[cfg(feature = "curves")]
+            Geometry::CatmullRomCurve(ref c) => put_curve!(out, CATMULL_ROM_CURVE, c),
+            #[cfg(feature = "subdivision")]
+            Geometry::Subdivision(ref m) => {
+                put_u32(out, SUBDIVISION);
+                put_u32(out, m.face_buffer.len() as u32);
+                put_u32(out, m.index_buffer(0).len() as u32);
+                put_u32(out, m.vertex_buffer.len() as u32);
+                put_u32(out, m.num_topologies() as u32);
+                put_buffer(out, &m.vertex_buffer);
+                put_buffer(out, &m.face_buffer);
+                for t in 0..m.num_topologies() {
+                    put_u32(out, m.modes[t] as u32);
+                    put_buffer(out, m.index_buffer(t as u32));
+                }
+            }
+            Geometry::Instance(_) => {
+                return Err(unsupported(id, "instances reference another scene"))
+            }
+            Geometry::User(_) => {
+                return Err(unsupported(id, "user geometry shapes can't be stored"))
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// Read the buffers of a geometry of the kind, returning it uncommitted
+fn read_geometry<'a>(device: &'a Device, kind: u32, c: &mut Cursor) -> io::Result<Geometry<'a>> {
+    let geom = match kind {
+        TRIANGLE => {
+            let time_steps = c.u32()?;
+            let num_verts = c.u32()? as usize;
+            let num_tris = c.u32()? as usize;
+            let num_attribs = c.u32()?;
+            if time_steps == 0 {
+                return Err(invalid_data("triangle mesh without time steps in snapshot"));
+            }
+            let vertex_buffers = (time_steps as usize).saturating_add(num_attribs as usize);
+            check_sizes(
+                c,
+                &[
+                    elements::<Vector4<f32>>(num_verts.saturating_mul(vertex_buffers)),
+                    elements::<Vector3<u32>>(num_tris),
+                ],
+            )?;
+            let mut mesh = TriangleMesh::animated(device, num_tris, num_verts, time_steps);
+            for t in 0..time_steps {
+                read_buffer(c, mesh.time_step(t))?;
+            }
+            read_buffer(c, &mut mesh.index_buffer)?;
+            for _ in 0..num_attribs {
+                let slot = mesh.add_vertex_attribute();
+                read_buffer(c, &mut mesh.vertex_attribute_buffers[slot as usize])?;
+            }
+            Geometry::Triangle(mesh)
+        }
+        QUAD => {
+            let num_verts = c.u32()? as usize;
+            let num_quads = c.u32()? as usize;
+            check_sizes(
+                c,
+                &[
+                    elements::<Vector4<f32>>(num_verts),
+                    elements::<Vector4<u32>>(num_quads),
+                ],
+            )?;
+            let mut mesh = QuadMesh::unanimated(device, num_quads, num_verts);
+            read_buffer(c, &mut mesh.vertex_buffer)?;
+            read_buffer(c, &mut mesh.index_buffer)?;
+            Geometry::Quad(mesh)
+        }
+        GRID => {
+            let num_verts = c.u32()? as usize;
+            let num_grids = c.u32()? as usize;
+            check_sizes(
+                c,
+                &[
+                    elements::<Vector4<f32>>(num_verts),
+                    elements::<Grid>(num_grids),
+                ],
+            )?;
+            let mut mesh = GridMesh::unanimated(device, num_grids, num_verts);
+            read_buffer(c, &mut mesh.vertex_buffer)?;
+            read_buffer(c, &mut mesh.grid_buffer)?;
+            Geometry::Grid(mesh)
+        }
+        POINT => {
+            let point_type = match c.u32()? {
+                0 => PointType::Sphere,
+                1 => PointType::Disc,
+                2 => PointType::OrientedDisc,
+                _ => return Err(invalid_data("invalid point type in snapshot")),
+            };
+            let num_points = c.u32()? as usize;
+            let num_normals = match point_type {
+                PointType::OrientedDisc => num_points,
+                _ => 0,
+            };
+            check_sizes(
+                c,
+                &[
+                    elements::<Vector4<f32>>(num_points),
+                    elements::<Vector3<f32>>(num_normals),
+                ],
+            )?;
+            let mut points = PointGeometry::unanimated(device, point_type, num_points);
+            read_buffer(c, &mut points.vertex_buffer)?;
+            read_optional(c, &mut points.normal_buffer)?;
+            Geometry::Point(points)
+        }
+        #[cfg(feature = "curves")]
+        LINEAR_CURVE => {
+            let curve_type = curve_type(c.u32()?)?;
+            let s = c.u32()? as usize;
+            let v = c.u32()? as usize;
+            let normals = c.u32()? != 0;
+            check_sizes(
+                c,
+                &[
+                    elements::<Vector4<f32>>(v),
+                    elements::<u32>(s.saturating_mul(2)),
+                    elements::<Vector3<f32>>(if normals { v } else { 0 }),
+                ],
+            )?;
+            let mut curve = match curve_type {
+                CurveType::Flat => LinearCurve::flat(device, s, v, normals),
+                CurveType::Round => LinearCurve::round(device, s, v, normals),
+                CurveType::Cone => LinearCurve::cone(device, s, v, normals),
+                CurveType::NormalOriented => {
+                    return Err(invalid_data("linear curves can't be normal oriented"))
+                }
+            };
+            read_buffer(c, &mut curve.vertex_buffer)?;
+            read_buffer(c, &mut curve.index_buffer)?;
+            read_optional(c, &mut curve.normal_buffer)?;
+            read_buffer(c, &mut curve.flag_buffer)?;
+            Geometry::LinearCurve(curve)
+        }
+        #[cfg(feature = "curves")]
+        BEZIER_CURVE => Geometry::BezierCurve(read_curve!(BezierCurve, device, c, 1)),
+        #[cfg(feature = "curves")]
+        BSPLINE_CURVE => Geometry::BsplineCurve(read_curve!(BsplineCurve, device, c, 1)),
+        #[cfg(feature = "curves")]
+        HERMITE_CURVE => {
+            // The tangents and normal derivatives are per vertex as well
+            let mut curve = read_curve!(HermiteCurve, device, c, 2);
+            read_buffer(c, &mut curve.tangent_buffer)?;
+            read_optional(c, &mut curve.normal_derivative_buffer)?;
+            Geometry::HermiteCurve(curve)
+        }
+        #[cfg(feature = "curves")]
+        CATMULL_ROM_CURVE => Geometry::CatmullRomCurve(read_curve!(CatmullRomCurve, device, c, 1)),
+        #[cfg(feature = "subdivision")]
+        SUBDIVISION => {
+            let num_faces = c.u32()? as usize;
+            let num_indices = c.u32()? as usize;
+            let num_verts = c.u32()? as usize;
+            let num_topologies = c.u32()?;
+            check_sizes(
+                c,
+                &[
+                    elements::<Vector4<f32>>(num_verts),
+                    elements::<u32>(num_faces),
+                    elements::<u32>(num_indices),
+                ],
+            )?;
+            let mut mesh = SubdivisionMesh::unanimated(device, num_faces, num_indices, num_verts);
+            read_buffer(c, &mut mesh.vertex_buffer)?;
+            read_buffer(c, &mut mesh.face_buffer)?;
+            for t in 0..num_topologies {
+                let mode = subdivision_mode(c.u32()?)?;
+                if t > 0 {
+                    check_sizes(c, &[elements::<u32>(num_indices)])?;
+                }
+                let mut topology = if t == 0 {
+                    mesh.base_topology()
+                } else {
+                    mesh.add_topology()
+                };
+                topology.set_mode(mode);
+                read_buffer(c, topology.index_buffer())?;
+            }
+            Geometry::Subdivision(mesh)
+        }
+        #[cfg(not(feature = "curves"))]
+        LINEAR_CURVE | BEZIER_CURVE | BSPLINE_CURVE | HERMITE_CURVE | CATMULL_ROM_CURVE => {
+            return Err(invalid_data(
+                "snapshot has curves, which need the `curves` feature",
+            ))
+        }
+        #[cfg(not(feature = "subdivision"))]
+        SUBDIVISION => {
+            return Err(invalid_data(
+                "snapshot has subdivision meshes, which need the `subdivision` feature",
+            ))
+        }
+        _ => return Err(invalid_data("invalid geometry kind in snapshot")),
+    };
+    Ok(geom)
+}
+
+/// Write a snapshot of the scene's settings and geometry to `writer`.
+/// Returns an error if the scene holds instances or user geometry, which
+/// can't be stored.
+pub fn save_snapshot<W: Write>(scene: &Scene, mut writer: W) -> io::Result<()> {
+    let content = serialize_scene(scene)?;
+    writer.write_all(MAGIC)?;
+    writer.write_all(&VERSION.to_le_bytes())?;
+    writer.write_all(&fnv1a(&content).to_le_bytes())?;
+    writer.write_all(&content)?;
+    writer.flush()
+}
+
+/// Write a snapshot of the scene to the file at `path`, see `save_snapshot`
+pub fn save_snapshot_file<P: AsRef<Path>>(scene: &Scene, path: P) -> io::Result<()> {
+    save_snapshot(scene, BufWriter::new(File::create(path)?))
+}
+
+/// Load a scene from a snapshot written by `save_snapshot`. Each geometry
+/// is committed and attached with the ID it had in the saved scene, in
+/// the same order. The scene is ready to be committed. Returns an error if
+/// the snapshot is corrupt or needs a feature which isn't enabled.
+pub fn load_snapshot<'a, R: Read>(device: &'a Device, mut reader: R) -> io::Result<Scene<'a>> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+    let mut c = Cursor { bytes: &bytes };
+    if c.take(MAGIC.len())? != MAGIC {
+        return Err(invalid_data("not a scene snapshot"));
+    }
+    if c.u32()? != VERSION {
+        return Err(invalid_data("unsupported scene snapshot version"));
+    }
+    let hash = c.u64()?;
+    if fnv1a(c.bytes) != hash {
+        return Err(invalid_data("scene snapshot content hash mismatch"));
+    }
+
+    let mut scene = Scene::new(device);
+    scene.set_flags(SceneFlags(c.u32()?));
+    scene.set_build_quality(scene_cache::build_quality(c.u32()?)?);
+    for _ in 0..c.u32()? {
+        let id = c.u32()?;
+        let enabled = c.u32()? != 0;
+        let mask = c.u32()?;
+        let face_mode = match c.u32()? {
+            0 => HitFaceMode::Both,
+            1 => HitFaceMode::FrontOnly,
+            2 => HitFaceMode::BackOnly,
+            _ => return Err(invalid_data("invalid hit face mode in snapshot")),
+        };
+        let kind = c.u32()?;
+        if id as usize >= validation::MAX_GEOMETRIES || scene.geometry_by_raw_id(id).is_some() {
+            return Err(invalid_data("invalid geometry ID in snapshot"));
+        }
+        let mut geom = read_geometry(device, kind, &mut c)?;
+        geom.set_enabled(enabled);
+        geom.set_mask(mask);
+        if face_mode != HitFaceMode::Both {
+            geom.set_hit_face_mode(face_mode);
+        }
+        geom.commit();
+        scene.attach_geometry_by_id(geom, id);
+    }
+    if !c.bytes.is_empty() {
+        return Err(invalid_data("trailing data in scene snapshot"));
+    }
+    Ok(scene)
+}
+
+/// Load a scene from the snapshot file at `path`, see `load_snapshot`
+pub fn load_snapshot_file<'a, P: AsRef<Path>>(
+    device: &'a Device,
+    path: P,
+) -> io::Result<Scene<'a>> {
+    load_snapshot(device, BufReader::new(File::open(path)?))
+}
+
+#[test]
+fn test_element_round_trip() {
+    let mut out = Vec::new();
+    let grid = Grid::new(3, 16, 4, 2);
+    let v = Vector4::new(1.0f32, -2.5, 0.0, 8.0);
+    grid.put(&mut out);
+    v.put(&mut out);
+    Vector3::new(1u32, 2, 3).put(&mut out);
+    assert_eq!(
+        out.len(),
+        Grid::SIZE + Vector4::<f32>::SIZE + Vector3::<u32>::SIZE
+    );
+
+    let mut c = Cursor { bytes: &out };
+    let g = Grid::read(&mut c).unwrap();
+    assert_eq!(
+        (g.startVertexID, g.stride, g.width, g.height),
+        (3, 16, 4, 2)
+    );
+    assert_eq!(Vector4::<f32>::read(&mut c).unwrap(), v);
+    assert_eq!(Vector3::<u32>::read(&mut c).unwrap(), Vector3::new(1, 2, 3));
+    assert!(u32::read(&mut c).is_err());
+}
+
+#[test]
+fn test_check_sizes() {
+    let bytes = [0u8; 64];
+    let c = Cursor { bytes: &bytes };
+    assert!(check_sizes(&c, &[elements::<Vector4<f32>>(2), elements::<Grid>(2)]).is_ok());
+    assert!(check_sizes(&c, &[elements::<Vector4<f32>>(4), elements::<u32>(1)]).is_err());
+    assert!(check_sizes(&c, &[elements::<Vector4<f32>>(usize::MAX), 1]).is_err());
+}
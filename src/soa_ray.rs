@@ -30,6 +30,11 @@ pub trait SoARay {
     fn set_flags(&mut self, i: usize, flags: u32);
 }
 
+/// Access to the lanes of a packet or stream of hits. The attributes of
+/// a lane are only meaningful if it hit something; lanes which missed keep
+/// whatever values they held before traversal. `lane` and `lane_mut` only
+/// return lanes which hit, while the indexed accessors read any lane
+/// without checking, for hot code which already knows which lanes are valid.
 pub trait SoAHit {
     fn normal(&self, i: usize) -> Vector3<f32>;
     fn set_normal(&mut self, i: usize, n: Vector3<f32>);
@@ -50,6 +55,34 @@ pub trait SoAHit {
     fn hit(&self, i: usize) -> bool {
         self.geom_id(i) != u32::MAX
     }
+
+    /// Get the hit in lane `i`, or `None` if the lane didn't hit anything
+    fn lane(&self, i: usize) -> Option<SoAHitRef<'_, Self>>
+    where
+        Self: Sized,
+    {
+        if self.hit(i) {
+            Some(SoAHitRef { hit: self, idx: i })
+        } else {
+            None
+        }
+    }
+    /// Get the hit in lane `i` to modify, or `None` if the lane didn't
+    /// hit anything
+    fn lane_mut(&mut self, i: usize) -> Option<SoAHitRefMut<'_, Self>>
+    where
+        Self: Sized,
+    {
+        if self.hit(i) {
+            Some(SoAHitRefMut {
+                hit: self as *mut Self,
+                idx: i,
+                marker: PhantomData,
+            })
+        } else {
+            None
+        }
+    }
 }
 
 pub struct SoARayRef<'a, T> {
@@ -232,6 +265,10 @@ pub struct SoAHitRef<'a, T> {
 }
 
 impl<'a, T: SoAHit + 'a> SoAHitRef<'a, T> {
+    /// The lane of the hit in its packet or stream
+    pub fn lane(&self) -> usize {
+        self.idx
+    }
     pub fn normal(&self) -> Vector3<f32> {
         self.hit.normal(self.idx)
     }
@@ -298,6 +335,10 @@ pub struct SoAHitRefMut<'a, T> {
 }
 
 impl<'a, T: SoAHit + 'a> SoAHitRefMut<'a, T> {
+    /// The lane of the hit in its packet or stream
+    pub fn lane(&self) -> usize {
+        self.idx
+    }
     pub fn normal(&self) -> Vector3<f32> {
         let hit = unsafe { self.hit.as_ref().expect("should never be null!") };
         hit.normal(self.idx)
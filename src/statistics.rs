@@ -0,0 +1,220 @@
+//! Per scene statistics for logging and render farm dashboards, collected
+//! into one report by `Scene::statistics_report`: the number of geometries
+//! and primitives of each kind, the time the last commit took to build
+//! the BVH, the estimated memory use (see the `memory_estimate` module)
+//! and the number of rays traced since the counts were last reset.
+//!
+//! Counting rays costs an atomic add on a counter shared by all threads
+//! per query, so it's off by default and enabled with
+//! `Scene::set_ray_counting`. Packet queries count their active lanes and
+//! stream queries the rays in the stream. A renderer reporting per frame
+//! statistics resets the counts with `Scene::reset_ray_counts` at the
+//! start of each frame.
+//!
+//! With the `serde` feature the report implements `Serialize`, so it can
+//! be written as JSON with `serde_json` or any other serde format:
+//!
+//! ```ignore
+//! let report = scene.statistics_report();
+//! println!("{}", serde_json::to_string(&report).unwrap());
+//! ```
+
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::Duration;
+
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+use geometry::GeometryKind;
+use memory_estimate::MemoryEstimate;
+use scene::Scene;
+
+/// The number of rays traced against a scene
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct RayCounts {
+    /// Rays traced by closest hit queries
+    pub intersect: u64,
+    /// Rays traced by occlusion queries
+    pub occluded: u64,
+}
+
+impl RayCounts {
+    pub fn total(&self) -> u64 {
+        self.intersect + self.occluded
+    }
+}
+
+/// The geometries of one kind in a scene
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct KindCount {
+    pub kind: GeometryKind,
+    /// The number of geometries of the kind
+    pub geometries: usize,
+    /// The number of primitives in the geometries of the kind
+    pub primitives: usize,
+}
+
+/// A snapshot of a scene's statistics, see the `statistics` module
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct SceneStatistics {
+    /// The geometries and primitives of each kind in the scene, in the
+    /// order the first geometry of each kind was attached
+    pub kinds: Vec<KindCount>,
+    /// The number of geometries attached to the scene
+    pub geometries: usize,
+    /// The number of enabled geometries
+    pub enabled_geometries: usize,
+    /// The number of primitives of all geometries
+    pub primitives: usize,
+    /// The number of geometries with a shadow proxy
+    pub shadow_proxies: usize,
+    /// The time the last commit took to build the BVH in seconds, if the
+    /// scene was committed
+    pub build_time_seconds: Option<f64>,
+    /// The estimated memory use of the scene
+    pub memory: MemoryEstimate,
+    /// The rays traced since the counts were last reset, zero unless ray
+    /// counting is enabled
+    pub rays: RayCounts,
+}
+
+/// The ray counters of a scene, only updated when counting is enabled
+#[derive(Debug, Default)]
+pub(crate) struct RayCounters {
+    enabled: AtomicBool,
+    intersect: AtomicU64,
+    occluded: AtomicU64,
+}
+
+impl RayCounters {
+    pub(crate) fn count_intersect(&self, rays: usize) {
+        if self.enabled.load(Ordering::Relaxed) {
+            self.intersect.fetch_add(rays as u64, Ordering::Relaxed);
+        }
+    }
+    pub(crate) fn count_occluded(&self, rays: usize) {
+        if self.enabled.load(Ordering::Relaxed) {
+            self.occluded.fetch_add(rays as u64, Ordering::Relaxed);
+        }
+    }
+    fn counts(&self) -> RayCounts {
+        RayCounts {
+            intersect: self.intersect.load(Ordering::Relaxed),
+            occluded: self.occluded.load(Ordering::Relaxed),
+        }
+    }
+    fn reset(&self) {
+        self.intersect.store(0, Ordering::Relaxed);
+        self.occluded.store(0, Ordering::Relaxed);
+    }
+}
+
+/// Count the lanes of a packet's valid mask which are traced
+#[cfg(any(feature = "packets", test))]
+pub(crate) fn active_lanes(valid: &[i32]) -> usize {
+    valid.iter().filter(|v| **v == -1).count()
+}
+
+/// Add the geometry to the count of its kind, keeping the kinds in the
+/// order they're first seen
+fn add_to_kind(kinds: &mut Vec<KindCount>, kind: GeometryKind, primitives: usize) {
+    match kinds.iter_mut().find(|k| k.kind == kind) {
+        Some(k) => {
+            k.geometries += 1;
+            k.primitives += primitives;
+        }
+        None => kinds.push(KindCount {
+            kind,
+            geometries: 1,
+            primitives,
+        }),
+    }
+}
+
+impl<'a> Scene<'a> {
+    /// Enable or disable counting the rays traced against the scene, see
+    /// the `statistics` module. Counting is disabled by default.
+    pub fn set_ray_counting(&mut self, enabled: bool) {
+        self.ray_counters.enabled.store(enabled, Ordering::Relaxed);
+    }
+    /// Get the number of rays traced since the counts were last reset
+    pub fn ray_counts(&self) -> RayCounts {
+        self.ray_counters.counts()
+    }
+    /// Reset the ray counts to zero, e.g. at the start of a frame
+    pub fn reset_ray_counts(&self) {
+        self.ray_counters.reset();
+    }
+    /// Get the time the last commit took to build the scene's BVH, if the
+    /// scene was committed
+    pub fn last_build_time(&self) -> Option<Duration> {
+        match self.build_time.load(Ordering::Relaxed) {
+            0 => None,
+            t => Some(Duration::from_nanos(t)),
+        }
+    }
+    /// Collect the scene's statistics into a report, see the `statistics`
+    /// module
+    pub fn statistics_report(&self) -> SceneStatistics {
+        let mut kinds = Vec::new();
+        let mut enabled_geometries = 0;
+        let mut primitives = 0;
+        let mut shadow_proxies = 0;
+        for (id, geom) in self.iter_ordered() {
+            let n = geom.primitive_count();
+            add_to_kind(&mut kinds, geom.kind(), n);
+            primitives += n;
+            if geom.is_enabled() {
+                enabled_geometries += 1;
+            }
+            if self.get_shadow_proxy(id).is_some() {
+                shadow_proxies += 1;
+            }
+        }
+        SceneStatistics {
+            kinds,
+            geometries: self.geometry_ids().len(),
+            enabled_geometries,
+            primitives,
+            shadow_proxies,
+            build_time_seconds: self.last_build_time().map(|t| t.as_secs_f64()),
+            memory: self.estimate_memory_usage(),
+            rays: self.ray_counts(),
+        }
+    }
+}
+
+#[test]
+fn test_ray_counters() {
+    let counters = RayCounters::default();
+    counters.count_intersect(4);
+    assert_eq!(counters.counts(), RayCounts::default());
+    counters.enabled.store(true, Ordering::Relaxed);
+    counters.count_intersect(4);
+    counters.count_occluded(active_lanes(&[-1, 0, -1, -1]));
+    assert_eq!(
+        counters.counts(),
+        RayCounts {
+            intersect: 4,
+            occluded: 3,
+        }
+    );
+    assert_eq!(counters.counts().total(), 7);
+    counters.reset();
+    assert_eq!(counters.counts().total(), 0);
+}
+
+#[test]
+fn test_kind_counts() {
+    let mut kinds = Vec::new();
+    add_to_kind(&mut kinds, GeometryKind::Quad, 6);
+    add_to_kind(&mut kinds, GeometryKind::Triangle, 12);
+    add_to_kind(&mut kinds, GeometryKind::Quad, 2);
+    assert_eq!(kinds.len(), 2);
+    assert_eq!(kinds[0].kind, GeometryKind::Quad);
+    assert_eq!((kinds[0].geometries, kinds[0].primitives), (2, 8));
+    assert_eq!((kinds[1].geometries, kinds[1].primitives), (1, 12));
+}
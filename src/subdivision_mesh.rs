@@ -0,0 +1,268 @@
+use std::ptr;
+
+use cgmath::Vector4;
+
+use buffer::Buffer;
+use device::Device;
+use geometry;
+use ray::Hit;
+use sys::*;
+use triangle_mesh::AttributeValue;
+use {BufferType, Format, GeometryType, SubdivisionMode};
+
+/// Identifies a topology of a subdivision mesh. The base topology, used
+/// for the vertex positions, is created with the mesh and additional
+/// topologies are added with `SubdivisionMesh::add_topology`, so IDs can
+/// only come from the mesh and can't be confused with buffer slots.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct TopologyId(u32);
+
+impl TopologyId {
+    pub fn index(&self) -> u32 {
+        self.0
+    }
+}
+
+pub struct SubdivisionMesh<'a> {
+    device: &'a Device,
+    pub(crate) handle: RTCGeometry,
+    pub vertex_buffer: Buffer<'a, Vector4<f32>>,
+    /// The number of vertices of each face
+    pub face_buffer: Buffer<'a, u32>,
+    /// Vertex attribute buffers, indexed by the attribute slot returned
+    /// by `Topology::add_vertex_attribute`
+    pub vertex_attribute_buffers: Vec<Buffer<'a, Vector4<f32>>>,
+    pub(crate) index_buffers: Vec<Buffer<'a, u32>>,
+    pub(crate) modes: Vec<SubdivisionMode>,
+    num_indices: usize,
+}
+
+impl<'a> SubdivisionMesh<'a> {
+    /// The buffers which must be set before committing the geometry
+    pub const REQUIRED: &'static [BufferType] =
+        &[BufferType::VERTEX, BufferType::INDEX, BufferType::FACE];
+    /// The buffers the geometry can optionally use
+    pub const OPTIONAL: &'static [BufferType] = &[
+        BufferType::VERTEX_ATTRIBUTE,
+        BufferType::LEVEL,
+        BufferType::EDGE_CREASE_INDEX,
+        BufferType::EDGE_CREASE_WEIGHT,
+        BufferType::VERTEX_CREASE_INDEX,
+        BufferType::VERTEX_CREASE_WEIGHT,
+        BufferType::HOLE,
+    ];
+    /// Create a subdivision mesh with `num_faces` faces, whose vertices are
+    /// given by `num_indices` indices into the `num_verts` vertices. The
+    /// base topology uses the `SMOOTH_BOUNDARY` mode, as in Embree.
+    pub fn unanimated(
+        device: &'a Device,
+        num_faces: usize,
+        num_indices: usize,
+        num_verts: usize,
+    ) -> SubdivisionMesh<'a> {
+        let h = unsafe { geometry::new_handle(device, GeometryType::SUBDIVISION) };
+        let mut vertex_buffer = Buffer::new(device, num_verts);
+        let mut face_buffer = Buffer::new(device, num_faces);
+        unsafe {
+            rtcSetGeometryBuffer(
+                h,
+                BufferType::VERTEX,
+                0,
+                Format::FLOAT3,
+                vertex_buffer.handle,
+                0,
+                16,
+                num_verts,
+            );
+            vertex_buffer.set_attachment(h, BufferType::VERTEX, 0);
+
+            rtcSetGeometryBuffer(
+                h,
+                BufferType::FACE,
+                0,
+                Format::UINT,
+                face_buffer.handle,
+                0,
+                4,
+                num_faces,
+            );
+            face_buffer.set_attachment(h, BufferType::FACE, 0);
+        }
+        let mut mesh = SubdivisionMesh {
+            device,
+            handle: h,
+            vertex_buffer,
+            face_buffer,
+            vertex_attribute_buffers: Vec::new(),
+            index_buffers: Vec::new(),
+            modes: Vec::new(),
+            num_indices,
+        };
+        mesh.add_topology();
+        mesh
+    }
+    /// Get the base topology, which indexes the vertex buffer
+    pub fn base_topology(&mut self) -> Topology<'_, 'a> {
+        self.topology(TopologyId(0))
+    }
+    /// Get the topology with the ID, panics if the mesh doesn't have it
+    pub fn topology(&mut self, id: TopologyId) -> Topology<'_, 'a> {
+        assert!(
+            (id.0 as usize) < self.index_buffers.len(),
+            "Topology {} does not exist in the mesh",
+            id.0
+        );
+        Topology { mesh: self, id }
+    }
+    /// Add a topology with its own index buffer, e.g. to give texture
+    /// coordinates seams which the vertex positions don't have. The face
+    /// structure is shared with the base topology, so the index buffer has
+    /// the same number of indices.
+    pub fn add_topology(&mut self) -> Topology<'_, 'a> {
+        let id = TopologyId(self.index_buffers.len() as u32);
+        let mut index_buffer = Buffer::new(self.device, self.num_indices);
+        unsafe {
+            rtcSetGeometryTopologyCount(self.handle, id.0 + 1);
+            rtcSetGeometryBuffer(
+                self.handle,
+                BufferType::INDEX,
+                id.0,
+                Format::UINT,
+                index_buffer.handle,
+                0,
+                4,
+                self.num_indices,
+            );
+            index_buffer.set_attachment(self.handle, BufferType::INDEX, id.0);
+        }
+        geometry::mark_dirty(self.handle);
+        self.index_buffers.push(index_buffer);
+        self.modes.push(SubdivisionMode::SMOOTH_BOUNDARY);
+        Topology { mesh: self, id }
+    }
+    pub fn num_topologies(&self) -> usize {
+        self.index_buffers.len()
+    }
+    pub(crate) fn index_buffer(&self, topology: u32) -> &Buffer<'a, u32> {
+        &self.index_buffers[topology as usize]
+    }
+    /// Set the number of segments each edge is tessellated into
+    pub fn set_tessellation_rate(&mut self, rate: f32) {
+        unsafe {
+            rtcSetGeometryTessellationRate(self.handle, rate);
+        }
+        geometry::mark_dirty(self.handle);
+    }
+    /// Evaluate the vertex attribute in the slot on the limit surface at
+    /// the hit, using `rtcInterpolate`. `T` selects how many components
+    /// of the attribute are read, see `TriangleMesh::interpolate_attribute_cpu`.
+    ///
+    /// Panics if the slot has no attribute buffer.
+    pub fn interpolate_attribute<T: AttributeValue>(&self, hit: &Hit, slot: u32) -> T {
+        assert!(
+            (slot as usize) < self.vertex_attribute_buffers.len(),
+            "No vertex attribute in slot {}",
+            slot
+        );
+        let mut value = [0.0f32; 4];
+        let args = RTCInterpolateArguments {
+            geometry: self.handle,
+            primID: hit.primID,
+            u: hit.u,
+            v: hit.v,
+            bufferType: BufferType::VERTEX_ATTRIBUTE,
+            bufferSlot: slot,
+            P: value.as_mut_ptr(),
+            dPdu: ptr::null_mut(),
+            dPdv: ptr::null_mut(),
+            ddPdudu: ptr::null_mut(),
+            ddPdvdv: ptr::null_mut(),
+            ddPdudv: ptr::null_mut(),
+            valueCount: 4,
+        };
+        unsafe {
+            rtcInterpolate(&args);
+        }
+        T::from_vector4(Vector4::from(value))
+    }
+}
+
+unsafe impl<'a> Sync for SubdivisionMesh<'a> {}
+unsafe impl<'a> Send for SubdivisionMesh<'a> {}
+
+/// A topology of a subdivision mesh, through which its index buffer,
+/// boundary mode and the vertex attributes using it are configured.
+pub struct Topology<'m, 'a: 'm> {
+    mesh: &'m mut SubdivisionMesh<'a>,
+    id: TopologyId,
+}
+
+impl<'m, 'a: 'm> Topology<'m, 'a> {
+    pub fn id(&self) -> TopologyId {
+        self.id
+    }
+    pub fn mode(&self) -> SubdivisionMode {
+        self.mesh.modes[self.id.0 as usize]
+    }
+    /// Set how the boundary of the topology is subdivided
+    pub fn set_mode(&mut self, mode: SubdivisionMode) {
+        self.mesh.modes[self.id.0 as usize] = mode;
+        unsafe {
+            rtcSetGeometrySubdivisionMode(self.mesh.handle, self.id.0, mode);
+        }
+        geometry::mark_dirty(self.mesh.handle);
+    }
+    pub fn index_buffer(&mut self) -> &mut Buffer<'a, u32> {
+        &mut self.mesh.index_buffers[self.id.0 as usize]
+    }
+    /// Replace the index buffer of the topology, which must have the same
+    /// number of indices as the mesh was created with.
+    pub fn set_index_buffer(&mut self, mut buffer: Buffer<'a, u32>) {
+        assert_eq!(
+            buffer.len(),
+            self.mesh.num_indices,
+            "Topology index buffer must have one index per face vertex"
+        );
+        unsafe {
+            rtcSetGeometryBuffer(
+                self.mesh.handle,
+                BufferType::INDEX,
+                self.id.0,
+                Format::UINT,
+                buffer.handle,
+                0,
+                4,
+                buffer.len(),
+            );
+        }
+        buffer.set_attachment(self.mesh.handle, BufferType::INDEX, self.id.0);
+        geometry::mark_dirty(self.mesh.handle);
+        self.mesh.index_buffers[self.id.0 as usize] = buffer;
+    }
+    /// Add a vertex attribute with `num_verts` values indexed by this
+    /// topology, returning the slot of its buffer in
+    /// `SubdivisionMesh::vertex_attribute_buffers`.
+    pub fn add_vertex_attribute(&mut self, num_verts: usize) -> u32 {
+        let slot = self.mesh.vertex_attribute_buffers.len() as u32;
+        let h = self.mesh.handle;
+        let mut buffer = Buffer::new(self.mesh.device, num_verts);
+        unsafe {
+            rtcSetGeometryVertexAttributeCount(h, slot + 1);
+            rtcSetGeometryBuffer(
+                h,
+                BufferType::VERTEX_ATTRIBUTE,
+                slot,
+                Format::FLOAT4,
+                buffer.handle,
+                0,
+                16,
+                num_verts,
+            );
+            rtcSetGeometryVertexAttributeTopology(h, slot, self.id.0);
+        }
+        buffer.set_attachment(h, BufferType::VERTEX_ATTRIBUTE, slot);
+        geometry::mark_dirty(h);
+        self.mesh.vertex_attribute_buffers.push(buffer);
+        slot
+    }
+}
@@ -117,7 +117,7 @@ fn bindgen_test_layout_RTCBounds() {
         concat!("Alignment of ", stringify!(RTCBounds))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCBounds>())).lower_x as *const _ as usize },
+        ::std::mem::offset_of!(RTCBounds, lower_x),
         0usize,
         concat!(
             "Offset of field: ",
@@ -127,7 +127,7 @@ fn bindgen_test_layout_RTCBounds() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCBounds>())).lower_y as *const _ as usize },
+        ::std::mem::offset_of!(RTCBounds, lower_y),
         4usize,
         concat!(
             "Offset of field: ",
@@ -137,7 +137,7 @@ fn bindgen_test_layout_RTCBounds() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCBounds>())).lower_z as *const _ as usize },
+        ::std::mem::offset_of!(RTCBounds, lower_z),
         8usize,
         concat!(
             "Offset of field: ",
@@ -147,7 +147,7 @@ fn bindgen_test_layout_RTCBounds() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCBounds>())).align0 as *const _ as usize },
+        ::std::mem::offset_of!(RTCBounds, align0),
         12usize,
         concat!(
             "Offset of field: ",
@@ -157,7 +157,7 @@ fn bindgen_test_layout_RTCBounds() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCBounds>())).upper_x as *const _ as usize },
+        ::std::mem::offset_of!(RTCBounds, upper_x),
         16usize,
         concat!(
             "Offset of field: ",
@@ -167,7 +167,7 @@ fn bindgen_test_layout_RTCBounds() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCBounds>())).upper_y as *const _ as usize },
+        ::std::mem::offset_of!(RTCBounds, upper_y),
         20usize,
         concat!(
             "Offset of field: ",
@@ -177,7 +177,7 @@ fn bindgen_test_layout_RTCBounds() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCBounds>())).upper_z as *const _ as usize },
+        ::std::mem::offset_of!(RTCBounds, upper_z),
         24usize,
         concat!(
             "Offset of field: ",
@@ -187,7 +187,7 @@ fn bindgen_test_layout_RTCBounds() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCBounds>())).align1 as *const _ as usize },
+        ::std::mem::offset_of!(RTCBounds, align1),
         28usize,
         concat!(
             "Offset of field: ",
@@ -217,7 +217,7 @@ fn bindgen_test_layout_RTCLinearBounds() {
         concat!("Alignment of ", stringify!(RTCLinearBounds))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCLinearBounds>())).bounds0 as *const _ as usize },
+        ::std::mem::offset_of!(RTCLinearBounds, bounds0),
         0usize,
         concat!(
             "Offset of field: ",
@@ -227,7 +227,7 @@ fn bindgen_test_layout_RTCLinearBounds() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCLinearBounds>())).bounds1 as *const _ as usize },
+        ::std::mem::offset_of!(RTCLinearBounds, bounds1),
         32usize,
         concat!(
             "Offset of field: ",
@@ -298,9 +298,7 @@ fn bindgen_test_layout_RTCFilterFunctionNArguments() {
         concat!("Alignment of ", stringify!(RTCFilterFunctionNArguments))
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<RTCFilterFunctionNArguments>())).valid as *const _ as usize
-        },
+        ::std::mem::offset_of!(RTCFilterFunctionNArguments, valid),
         0usize,
         concat!(
             "Offset of field: ",
@@ -310,10 +308,7 @@ fn bindgen_test_layout_RTCFilterFunctionNArguments() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<RTCFilterFunctionNArguments>())).geometryUserPtr as *const _
-                as usize
-        },
+        ::std::mem::offset_of!(RTCFilterFunctionNArguments, geometryUserPtr),
         8usize,
         concat!(
             "Offset of field: ",
@@ -323,9 +318,7 @@ fn bindgen_test_layout_RTCFilterFunctionNArguments() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<RTCFilterFunctionNArguments>())).context as *const _ as usize
-        },
+        ::std::mem::offset_of!(RTCFilterFunctionNArguments, context),
         16usize,
         concat!(
             "Offset of field: ",
@@ -335,7 +328,7 @@ fn bindgen_test_layout_RTCFilterFunctionNArguments() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCFilterFunctionNArguments>())).ray as *const _ as usize },
+        ::std::mem::offset_of!(RTCFilterFunctionNArguments, ray),
         24usize,
         concat!(
             "Offset of field: ",
@@ -345,7 +338,7 @@ fn bindgen_test_layout_RTCFilterFunctionNArguments() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCFilterFunctionNArguments>())).hit as *const _ as usize },
+        ::std::mem::offset_of!(RTCFilterFunctionNArguments, hit),
         32usize,
         concat!(
             "Offset of field: ",
@@ -355,7 +348,7 @@ fn bindgen_test_layout_RTCFilterFunctionNArguments() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCFilterFunctionNArguments>())).N as *const _ as usize },
+        ::std::mem::offset_of!(RTCFilterFunctionNArguments, N),
         40usize,
         concat!(
             "Offset of field: ",
@@ -387,7 +380,7 @@ fn bindgen_test_layout_RTCIntersectContext() {
         concat!("Alignment of ", stringify!(RTCIntersectContext))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCIntersectContext>())).flags as *const _ as usize },
+        ::std::mem::offset_of!(RTCIntersectContext, flags),
         0usize,
         concat!(
             "Offset of field: ",
@@ -397,7 +390,7 @@ fn bindgen_test_layout_RTCIntersectContext() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCIntersectContext>())).filter as *const _ as usize },
+        ::std::mem::offset_of!(RTCIntersectContext, filter),
         8usize,
         concat!(
             "Offset of field: ",
@@ -407,7 +400,7 @@ fn bindgen_test_layout_RTCIntersectContext() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCIntersectContext>())).instID as *const _ as usize },
+        ::std::mem::offset_of!(RTCIntersectContext, instID),
         16usize,
         concat!(
             "Offset of field: ",
@@ -440,7 +433,7 @@ fn bindgen_test_layout_RTCPointQuery() {
         concat!("Alignment of ", stringify!(RTCPointQuery))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCPointQuery>())).x as *const _ as usize },
+        ::std::mem::offset_of!(RTCPointQuery, x),
         0usize,
         concat!(
             "Offset of field: ",
@@ -450,7 +443,7 @@ fn bindgen_test_layout_RTCPointQuery() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCPointQuery>())).y as *const _ as usize },
+        ::std::mem::offset_of!(RTCPointQuery, y),
         4usize,
         concat!(
             "Offset of field: ",
@@ -460,7 +453,7 @@ fn bindgen_test_layout_RTCPointQuery() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCPointQuery>())).z as *const _ as usize },
+        ::std::mem::offset_of!(RTCPointQuery, z),
         8usize,
         concat!(
             "Offset of field: ",
@@ -470,7 +463,7 @@ fn bindgen_test_layout_RTCPointQuery() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCPointQuery>())).time as *const _ as usize },
+        ::std::mem::offset_of!(RTCPointQuery, time),
         12usize,
         concat!(
             "Offset of field: ",
@@ -480,7 +473,7 @@ fn bindgen_test_layout_RTCPointQuery() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCPointQuery>())).radius as *const _ as usize },
+        ::std::mem::offset_of!(RTCPointQuery, radius),
         16usize,
         concat!(
             "Offset of field: ",
@@ -513,7 +506,7 @@ fn bindgen_test_layout_RTCPointQuery4() {
         concat!("Alignment of ", stringify!(RTCPointQuery4))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCPointQuery4>())).x as *const _ as usize },
+        ::std::mem::offset_of!(RTCPointQuery4, x),
         0usize,
         concat!(
             "Offset of field: ",
@@ -523,7 +516,7 @@ fn bindgen_test_layout_RTCPointQuery4() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCPointQuery4>())).y as *const _ as usize },
+        ::std::mem::offset_of!(RTCPointQuery4, y),
         16usize,
         concat!(
             "Offset of field: ",
@@ -533,7 +526,7 @@ fn bindgen_test_layout_RTCPointQuery4() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCPointQuery4>())).z as *const _ as usize },
+        ::std::mem::offset_of!(RTCPointQuery4, z),
         32usize,
         concat!(
             "Offset of field: ",
@@ -543,7 +536,7 @@ fn bindgen_test_layout_RTCPointQuery4() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCPointQuery4>())).time as *const _ as usize },
+        ::std::mem::offset_of!(RTCPointQuery4, time),
         48usize,
         concat!(
             "Offset of field: ",
@@ -553,7 +546,7 @@ fn bindgen_test_layout_RTCPointQuery4() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCPointQuery4>())).radius as *const _ as usize },
+        ::std::mem::offset_of!(RTCPointQuery4, radius),
         64usize,
         concat!(
             "Offset of field: ",
@@ -586,7 +579,7 @@ fn bindgen_test_layout_RTCPointQuery8() {
         concat!("Alignment of ", stringify!(RTCPointQuery8))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCPointQuery8>())).x as *const _ as usize },
+        ::std::mem::offset_of!(RTCPointQuery8, x),
         0usize,
         concat!(
             "Offset of field: ",
@@ -596,7 +589,7 @@ fn bindgen_test_layout_RTCPointQuery8() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCPointQuery8>())).y as *const _ as usize },
+        ::std::mem::offset_of!(RTCPointQuery8, y),
         32usize,
         concat!(
             "Offset of field: ",
@@ -606,7 +599,7 @@ fn bindgen_test_layout_RTCPointQuery8() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCPointQuery8>())).z as *const _ as usize },
+        ::std::mem::offset_of!(RTCPointQuery8, z),
         64usize,
         concat!(
             "Offset of field: ",
@@ -616,7 +609,7 @@ fn bindgen_test_layout_RTCPointQuery8() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCPointQuery8>())).time as *const _ as usize },
+        ::std::mem::offset_of!(RTCPointQuery8, time),
         96usize,
         concat!(
             "Offset of field: ",
@@ -626,7 +619,7 @@ fn bindgen_test_layout_RTCPointQuery8() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCPointQuery8>())).radius as *const _ as usize },
+        ::std::mem::offset_of!(RTCPointQuery8, radius),
         128usize,
         concat!(
             "Offset of field: ",
@@ -659,7 +652,7 @@ fn bindgen_test_layout_RTCPointQuery16() {
         concat!("Alignment of ", stringify!(RTCPointQuery16))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCPointQuery16>())).x as *const _ as usize },
+        ::std::mem::offset_of!(RTCPointQuery16, x),
         0usize,
         concat!(
             "Offset of field: ",
@@ -669,7 +662,7 @@ fn bindgen_test_layout_RTCPointQuery16() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCPointQuery16>())).y as *const _ as usize },
+        ::std::mem::offset_of!(RTCPointQuery16, y),
         64usize,
         concat!(
             "Offset of field: ",
@@ -679,7 +672,7 @@ fn bindgen_test_layout_RTCPointQuery16() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCPointQuery16>())).z as *const _ as usize },
+        ::std::mem::offset_of!(RTCPointQuery16, z),
         128usize,
         concat!(
             "Offset of field: ",
@@ -689,7 +682,7 @@ fn bindgen_test_layout_RTCPointQuery16() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCPointQuery16>())).time as *const _ as usize },
+        ::std::mem::offset_of!(RTCPointQuery16, time),
         192usize,
         concat!(
             "Offset of field: ",
@@ -699,7 +692,7 @@ fn bindgen_test_layout_RTCPointQuery16() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCPointQuery16>())).radius as *const _ as usize },
+        ::std::mem::offset_of!(RTCPointQuery16, radius),
         256usize,
         concat!(
             "Offset of field: ",
@@ -736,7 +729,7 @@ fn bindgen_test_layout_RTCPointQueryContext() {
         concat!("Alignment of ", stringify!(RTCPointQueryContext))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCPointQueryContext>())).world2inst as *const _ as usize },
+        ::std::mem::offset_of!(RTCPointQueryContext, world2inst),
         0usize,
         concat!(
             "Offset of field: ",
@@ -746,7 +739,7 @@ fn bindgen_test_layout_RTCPointQueryContext() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCPointQueryContext>())).inst2world as *const _ as usize },
+        ::std::mem::offset_of!(RTCPointQueryContext, inst2world),
         64usize,
         concat!(
             "Offset of field: ",
@@ -756,7 +749,7 @@ fn bindgen_test_layout_RTCPointQueryContext() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCPointQueryContext>())).instID as *const _ as usize },
+        ::std::mem::offset_of!(RTCPointQueryContext, instID),
         128usize,
         concat!(
             "Offset of field: ",
@@ -766,9 +759,7 @@ fn bindgen_test_layout_RTCPointQueryContext() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<RTCPointQueryContext>())).instStackSize as *const _ as usize
-        },
+        ::std::mem::offset_of!(RTCPointQueryContext, instStackSize),
         132usize,
         concat!(
             "Offset of field: ",
@@ -802,9 +793,7 @@ fn bindgen_test_layout_RTCPointQueryFunctionArguments() {
         concat!("Alignment of ", stringify!(RTCPointQueryFunctionArguments))
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<RTCPointQueryFunctionArguments>())).query as *const _ as usize
-        },
+        ::std::mem::offset_of!(RTCPointQueryFunctionArguments, query),
         0usize,
         concat!(
             "Offset of field: ",
@@ -814,9 +803,7 @@ fn bindgen_test_layout_RTCPointQueryFunctionArguments() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<RTCPointQueryFunctionArguments>())).userPtr as *const _ as usize
-        },
+        ::std::mem::offset_of!(RTCPointQueryFunctionArguments, userPtr),
         8usize,
         concat!(
             "Offset of field: ",
@@ -826,9 +813,7 @@ fn bindgen_test_layout_RTCPointQueryFunctionArguments() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<RTCPointQueryFunctionArguments>())).primID as *const _ as usize
-        },
+        ::std::mem::offset_of!(RTCPointQueryFunctionArguments, primID),
         16usize,
         concat!(
             "Offset of field: ",
@@ -838,9 +823,7 @@ fn bindgen_test_layout_RTCPointQueryFunctionArguments() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<RTCPointQueryFunctionArguments>())).geomID as *const _ as usize
-        },
+        ::std::mem::offset_of!(RTCPointQueryFunctionArguments, geomID),
         20usize,
         concat!(
             "Offset of field: ",
@@ -850,9 +833,7 @@ fn bindgen_test_layout_RTCPointQueryFunctionArguments() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<RTCPointQueryFunctionArguments>())).context as *const _ as usize
-        },
+        ::std::mem::offset_of!(RTCPointQueryFunctionArguments, context),
         24usize,
         concat!(
             "Offset of field: ",
@@ -862,10 +843,7 @@ fn bindgen_test_layout_RTCPointQueryFunctionArguments() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<RTCPointQueryFunctionArguments>())).similarityScale as *const _
-                as usize
-        },
+        ::std::mem::offset_of!(RTCPointQueryFunctionArguments, similarityScale),
         32usize,
         concat!(
             "Offset of field: ",
@@ -1037,7 +1015,7 @@ fn bindgen_test_layout_RTCRay() {
         concat!("Alignment of ", stringify!(RTCRay))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCRay>())).org_x as *const _ as usize },
+        ::std::mem::offset_of!(RTCRay, org_x),
         0usize,
         concat!(
             "Offset of field: ",
@@ -1047,7 +1025,7 @@ fn bindgen_test_layout_RTCRay() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCRay>())).org_y as *const _ as usize },
+        ::std::mem::offset_of!(RTCRay, org_y),
         4usize,
         concat!(
             "Offset of field: ",
@@ -1057,7 +1035,7 @@ fn bindgen_test_layout_RTCRay() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCRay>())).org_z as *const _ as usize },
+        ::std::mem::offset_of!(RTCRay, org_z),
         8usize,
         concat!(
             "Offset of field: ",
@@ -1067,7 +1045,7 @@ fn bindgen_test_layout_RTCRay() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCRay>())).tnear as *const _ as usize },
+        ::std::mem::offset_of!(RTCRay, tnear),
         12usize,
         concat!(
             "Offset of field: ",
@@ -1077,7 +1055,7 @@ fn bindgen_test_layout_RTCRay() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCRay>())).dir_x as *const _ as usize },
+        ::std::mem::offset_of!(RTCRay, dir_x),
         16usize,
         concat!(
             "Offset of field: ",
@@ -1087,7 +1065,7 @@ fn bindgen_test_layout_RTCRay() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCRay>())).dir_y as *const _ as usize },
+        ::std::mem::offset_of!(RTCRay, dir_y),
         20usize,
         concat!(
             "Offset of field: ",
@@ -1097,7 +1075,7 @@ fn bindgen_test_layout_RTCRay() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCRay>())).dir_z as *const _ as usize },
+        ::std::mem::offset_of!(RTCRay, dir_z),
         24usize,
         concat!(
             "Offset of field: ",
@@ -1107,7 +1085,7 @@ fn bindgen_test_layout_RTCRay() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCRay>())).time as *const _ as usize },
+        ::std::mem::offset_of!(RTCRay, time),
         28usize,
         concat!(
             "Offset of field: ",
@@ -1117,7 +1095,7 @@ fn bindgen_test_layout_RTCRay() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCRay>())).tfar as *const _ as usize },
+        ::std::mem::offset_of!(RTCRay, tfar),
         32usize,
         concat!(
             "Offset of field: ",
@@ -1127,7 +1105,7 @@ fn bindgen_test_layout_RTCRay() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCRay>())).mask as *const _ as usize },
+        ::std::mem::offset_of!(RTCRay, mask),
         36usize,
         concat!(
             "Offset of field: ",
@@ -1137,7 +1115,7 @@ fn bindgen_test_layout_RTCRay() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCRay>())).id as *const _ as usize },
+        ::std::mem::offset_of!(RTCRay, id),
         40usize,
         concat!(
             "Offset of field: ",
@@ -1147,7 +1125,7 @@ fn bindgen_test_layout_RTCRay() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCRay>())).flags as *const _ as usize },
+        ::std::mem::offset_of!(RTCRay, flags),
         44usize,
         concat!(
             "Offset of field: ",
@@ -1183,7 +1161,7 @@ fn bindgen_test_layout_RTCHit() {
         concat!("Alignment of ", stringify!(RTCHit))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCHit>())).Ng_x as *const _ as usize },
+        ::std::mem::offset_of!(RTCHit, Ng_x),
         0usize,
         concat!(
             "Offset of field: ",
@@ -1193,7 +1171,7 @@ fn bindgen_test_layout_RTCHit() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCHit>())).Ng_y as *const _ as usize },
+        ::std::mem::offset_of!(RTCHit, Ng_y),
         4usize,
         concat!(
             "Offset of field: ",
@@ -1203,7 +1181,7 @@ fn bindgen_test_layout_RTCHit() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCHit>())).Ng_z as *const _ as usize },
+        ::std::mem::offset_of!(RTCHit, Ng_z),
         8usize,
         concat!(
             "Offset of field: ",
@@ -1213,17 +1191,17 @@ fn bindgen_test_layout_RTCHit() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCHit>())).u as *const _ as usize },
+        ::std::mem::offset_of!(RTCHit, u),
         12usize,
         concat!("Offset of field: ", stringify!(RTCHit), "::", stringify!(u))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCHit>())).v as *const _ as usize },
+        ::std::mem::offset_of!(RTCHit, v),
         16usize,
         concat!("Offset of field: ", stringify!(RTCHit), "::", stringify!(v))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCHit>())).primID as *const _ as usize },
+        ::std::mem::offset_of!(RTCHit, primID),
         20usize,
         concat!(
             "Offset of field: ",
@@ -1233,7 +1211,7 @@ fn bindgen_test_layout_RTCHit() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCHit>())).geomID as *const _ as usize },
+        ::std::mem::offset_of!(RTCHit, geomID),
         24usize,
         concat!(
             "Offset of field: ",
@@ -1243,7 +1221,7 @@ fn bindgen_test_layout_RTCHit() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCHit>())).instID as *const _ as usize },
+        ::std::mem::offset_of!(RTCHit, instID),
         28usize,
         concat!(
             "Offset of field: ",
@@ -1273,7 +1251,7 @@ fn bindgen_test_layout_RTCRayHit() {
         concat!("Alignment of ", stringify!(RTCRayHit))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCRayHit>())).ray as *const _ as usize },
+        ::std::mem::offset_of!(RTCRayHit, ray),
         0usize,
         concat!(
             "Offset of field: ",
@@ -1283,7 +1261,7 @@ fn bindgen_test_layout_RTCRayHit() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCRayHit>())).hit as *const _ as usize },
+        ::std::mem::offset_of!(RTCRayHit, hit),
         48usize,
         concat!(
             "Offset of field: ",
@@ -1323,7 +1301,7 @@ fn bindgen_test_layout_RTCRay4() {
         concat!("Alignment of ", stringify!(RTCRay4))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCRay4>())).org_x as *const _ as usize },
+        ::std::mem::offset_of!(RTCRay4, org_x),
         0usize,
         concat!(
             "Offset of field: ",
@@ -1333,7 +1311,7 @@ fn bindgen_test_layout_RTCRay4() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCRay4>())).org_y as *const _ as usize },
+        ::std::mem::offset_of!(RTCRay4, org_y),
         16usize,
         concat!(
             "Offset of field: ",
@@ -1343,7 +1321,7 @@ fn bindgen_test_layout_RTCRay4() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCRay4>())).org_z as *const _ as usize },
+        ::std::mem::offset_of!(RTCRay4, org_z),
         32usize,
         concat!(
             "Offset of field: ",
@@ -1353,7 +1331,7 @@ fn bindgen_test_layout_RTCRay4() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCRay4>())).tnear as *const _ as usize },
+        ::std::mem::offset_of!(RTCRay4, tnear),
         48usize,
         concat!(
             "Offset of field: ",
@@ -1363,7 +1341,7 @@ fn bindgen_test_layout_RTCRay4() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCRay4>())).dir_x as *const _ as usize },
+        ::std::mem::offset_of!(RTCRay4, dir_x),
         64usize,
         concat!(
             "Offset of field: ",
@@ -1373,7 +1351,7 @@ fn bindgen_test_layout_RTCRay4() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCRay4>())).dir_y as *const _ as usize },
+        ::std::mem::offset_of!(RTCRay4, dir_y),
         80usize,
         concat!(
             "Offset of field: ",
@@ -1383,7 +1361,7 @@ fn bindgen_test_layout_RTCRay4() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCRay4>())).dir_z as *const _ as usize },
+        ::std::mem::offset_of!(RTCRay4, dir_z),
         96usize,
         concat!(
             "Offset of field: ",
@@ -1393,7 +1371,7 @@ fn bindgen_test_layout_RTCRay4() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCRay4>())).time as *const _ as usize },
+        ::std::mem::offset_of!(RTCRay4, time),
         112usize,
         concat!(
             "Offset of field: ",
@@ -1403,7 +1381,7 @@ fn bindgen_test_layout_RTCRay4() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCRay4>())).tfar as *const _ as usize },
+        ::std::mem::offset_of!(RTCRay4, tfar),
         128usize,
         concat!(
             "Offset of field: ",
@@ -1413,7 +1391,7 @@ fn bindgen_test_layout_RTCRay4() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCRay4>())).mask as *const _ as usize },
+        ::std::mem::offset_of!(RTCRay4, mask),
         144usize,
         concat!(
             "Offset of field: ",
@@ -1423,7 +1401,7 @@ fn bindgen_test_layout_RTCRay4() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCRay4>())).id as *const _ as usize },
+        ::std::mem::offset_of!(RTCRay4, id),
         160usize,
         concat!(
             "Offset of field: ",
@@ -1433,7 +1411,7 @@ fn bindgen_test_layout_RTCRay4() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCRay4>())).flags as *const _ as usize },
+        ::std::mem::offset_of!(RTCRay4, flags),
         176usize,
         concat!(
             "Offset of field: ",
@@ -1469,7 +1447,7 @@ fn bindgen_test_layout_RTCHit4() {
         concat!("Alignment of ", stringify!(RTCHit4))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCHit4>())).Ng_x as *const _ as usize },
+        ::std::mem::offset_of!(RTCHit4, Ng_x),
         0usize,
         concat!(
             "Offset of field: ",
@@ -1479,7 +1457,7 @@ fn bindgen_test_layout_RTCHit4() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCHit4>())).Ng_y as *const _ as usize },
+        ::std::mem::offset_of!(RTCHit4, Ng_y),
         16usize,
         concat!(
             "Offset of field: ",
@@ -1489,7 +1467,7 @@ fn bindgen_test_layout_RTCHit4() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCHit4>())).Ng_z as *const _ as usize },
+        ::std::mem::offset_of!(RTCHit4, Ng_z),
         32usize,
         concat!(
             "Offset of field: ",
@@ -1499,7 +1477,7 @@ fn bindgen_test_layout_RTCHit4() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCHit4>())).u as *const _ as usize },
+        ::std::mem::offset_of!(RTCHit4, u),
         48usize,
         concat!(
             "Offset of field: ",
@@ -1509,7 +1487,7 @@ fn bindgen_test_layout_RTCHit4() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCHit4>())).v as *const _ as usize },
+        ::std::mem::offset_of!(RTCHit4, v),
         64usize,
         concat!(
             "Offset of field: ",
@@ -1519,7 +1497,7 @@ fn bindgen_test_layout_RTCHit4() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCHit4>())).primID as *const _ as usize },
+        ::std::mem::offset_of!(RTCHit4, primID),
         80usize,
         concat!(
             "Offset of field: ",
@@ -1529,7 +1507,7 @@ fn bindgen_test_layout_RTCHit4() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCHit4>())).geomID as *const _ as usize },
+        ::std::mem::offset_of!(RTCHit4, geomID),
         96usize,
         concat!(
             "Offset of field: ",
@@ -1539,7 +1517,7 @@ fn bindgen_test_layout_RTCHit4() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCHit4>())).instID as *const _ as usize },
+        ::std::mem::offset_of!(RTCHit4, instID),
         112usize,
         concat!(
             "Offset of field: ",
@@ -1569,7 +1547,7 @@ fn bindgen_test_layout_RTCRayHit4() {
         concat!("Alignment of ", stringify!(RTCRayHit4))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCRayHit4>())).ray as *const _ as usize },
+        ::std::mem::offset_of!(RTCRayHit4, ray),
         0usize,
         concat!(
             "Offset of field: ",
@@ -1579,7 +1557,7 @@ fn bindgen_test_layout_RTCRayHit4() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCRayHit4>())).hit as *const _ as usize },
+        ::std::mem::offset_of!(RTCRayHit4, hit),
         192usize,
         concat!(
             "Offset of field: ",
@@ -1619,7 +1597,7 @@ fn bindgen_test_layout_RTCRay8() {
         concat!("Alignment of ", stringify!(RTCRay8))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCRay8>())).org_x as *const _ as usize },
+        ::std::mem::offset_of!(RTCRay8, org_x),
         0usize,
         concat!(
             "Offset of field: ",
@@ -1629,7 +1607,7 @@ fn bindgen_test_layout_RTCRay8() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCRay8>())).org_y as *const _ as usize },
+        ::std::mem::offset_of!(RTCRay8, org_y),
         32usize,
         concat!(
             "Offset of field: ",
@@ -1639,7 +1617,7 @@ fn bindgen_test_layout_RTCRay8() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCRay8>())).org_z as *const _ as usize },
+        ::std::mem::offset_of!(RTCRay8, org_z),
         64usize,
         concat!(
             "Offset of field: ",
@@ -1649,7 +1627,7 @@ fn bindgen_test_layout_RTCRay8() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCRay8>())).tnear as *const _ as usize },
+        ::std::mem::offset_of!(RTCRay8, tnear),
         96usize,
         concat!(
             "Offset of field: ",
@@ -1659,7 +1637,7 @@ fn bindgen_test_layout_RTCRay8() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCRay8>())).dir_x as *const _ as usize },
+        ::std::mem::offset_of!(RTCRay8, dir_x),
         128usize,
         concat!(
             "Offset of field: ",
@@ -1669,7 +1647,7 @@ fn bindgen_test_layout_RTCRay8() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCRay8>())).dir_y as *const _ as usize },
+        ::std::mem::offset_of!(RTCRay8, dir_y),
         160usize,
         concat!(
             "Offset of field: ",
@@ -1679,7 +1657,7 @@ fn bindgen_test_layout_RTCRay8() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCRay8>())).dir_z as *const _ as usize },
+        ::std::mem::offset_of!(RTCRay8, dir_z),
         192usize,
         concat!(
             "Offset of field: ",
@@ -1689,7 +1667,7 @@ fn bindgen_test_layout_RTCRay8() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCRay8>())).time as *const _ as usize },
+        ::std::mem::offset_of!(RTCRay8, time),
         224usize,
         concat!(
             "Offset of field: ",
@@ -1699,7 +1677,7 @@ fn bindgen_test_layout_RTCRay8() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCRay8>())).tfar as *const _ as usize },
+        ::std::mem::offset_of!(RTCRay8, tfar),
         256usize,
         concat!(
             "Offset of field: ",
@@ -1709,7 +1687,7 @@ fn bindgen_test_layout_RTCRay8() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCRay8>())).mask as *const _ as usize },
+        ::std::mem::offset_of!(RTCRay8, mask),
         288usize,
         concat!(
             "Offset of field: ",
@@ -1719,7 +1697,7 @@ fn bindgen_test_layout_RTCRay8() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCRay8>())).id as *const _ as usize },
+        ::std::mem::offset_of!(RTCRay8, id),
         320usize,
         concat!(
             "Offset of field: ",
@@ -1729,7 +1707,7 @@ fn bindgen_test_layout_RTCRay8() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCRay8>())).flags as *const _ as usize },
+        ::std::mem::offset_of!(RTCRay8, flags),
         352usize,
         concat!(
             "Offset of field: ",
@@ -1765,7 +1743,7 @@ fn bindgen_test_layout_RTCHit8() {
         concat!("Alignment of ", stringify!(RTCHit8))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCHit8>())).Ng_x as *const _ as usize },
+        ::std::mem::offset_of!(RTCHit8, Ng_x),
         0usize,
         concat!(
             "Offset of field: ",
@@ -1775,7 +1753,7 @@ fn bindgen_test_layout_RTCHit8() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCHit8>())).Ng_y as *const _ as usize },
+        ::std::mem::offset_of!(RTCHit8, Ng_y),
         32usize,
         concat!(
             "Offset of field: ",
@@ -1785,7 +1763,7 @@ fn bindgen_test_layout_RTCHit8() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCHit8>())).Ng_z as *const _ as usize },
+        ::std::mem::offset_of!(RTCHit8, Ng_z),
         64usize,
         concat!(
             "Offset of field: ",
@@ -1795,7 +1773,7 @@ fn bindgen_test_layout_RTCHit8() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCHit8>())).u as *const _ as usize },
+        ::std::mem::offset_of!(RTCHit8, u),
         96usize,
         concat!(
             "Offset of field: ",
@@ -1805,7 +1783,7 @@ fn bindgen_test_layout_RTCHit8() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCHit8>())).v as *const _ as usize },
+        ::std::mem::offset_of!(RTCHit8, v),
         128usize,
         concat!(
             "Offset of field: ",
@@ -1815,7 +1793,7 @@ fn bindgen_test_layout_RTCHit8() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCHit8>())).primID as *const _ as usize },
+        ::std::mem::offset_of!(RTCHit8, primID),
         160usize,
         concat!(
             "Offset of field: ",
@@ -1825,7 +1803,7 @@ fn bindgen_test_layout_RTCHit8() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCHit8>())).geomID as *const _ as usize },
+        ::std::mem::offset_of!(RTCHit8, geomID),
         192usize,
         concat!(
             "Offset of field: ",
@@ -1835,7 +1813,7 @@ fn bindgen_test_layout_RTCHit8() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCHit8>())).instID as *const _ as usize },
+        ::std::mem::offset_of!(RTCHit8, instID),
         224usize,
         concat!(
             "Offset of field: ",
@@ -1865,7 +1843,7 @@ fn bindgen_test_layout_RTCRayHit8() {
         concat!("Alignment of ", stringify!(RTCRayHit8))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCRayHit8>())).ray as *const _ as usize },
+        ::std::mem::offset_of!(RTCRayHit8, ray),
         0usize,
         concat!(
             "Offset of field: ",
@@ -1875,7 +1853,7 @@ fn bindgen_test_layout_RTCRayHit8() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCRayHit8>())).hit as *const _ as usize },
+        ::std::mem::offset_of!(RTCRayHit8, hit),
         384usize,
         concat!(
             "Offset of field: ",
@@ -1915,7 +1893,7 @@ fn bindgen_test_layout_RTCRay16() {
         concat!("Alignment of ", stringify!(RTCRay16))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCRay16>())).org_x as *const _ as usize },
+        ::std::mem::offset_of!(RTCRay16, org_x),
         0usize,
         concat!(
             "Offset of field: ",
@@ -1925,7 +1903,7 @@ fn bindgen_test_layout_RTCRay16() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCRay16>())).org_y as *const _ as usize },
+        ::std::mem::offset_of!(RTCRay16, org_y),
         64usize,
         concat!(
             "Offset of field: ",
@@ -1935,7 +1913,7 @@ fn bindgen_test_layout_RTCRay16() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCRay16>())).org_z as *const _ as usize },
+        ::std::mem::offset_of!(RTCRay16, org_z),
         128usize,
         concat!(
             "Offset of field: ",
@@ -1945,7 +1923,7 @@ fn bindgen_test_layout_RTCRay16() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCRay16>())).tnear as *const _ as usize },
+        ::std::mem::offset_of!(RTCRay16, tnear),
         192usize,
         concat!(
             "Offset of field: ",
@@ -1955,7 +1933,7 @@ fn bindgen_test_layout_RTCRay16() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCRay16>())).dir_x as *const _ as usize },
+        ::std::mem::offset_of!(RTCRay16, dir_x),
         256usize,
         concat!(
             "Offset of field: ",
@@ -1965,7 +1943,7 @@ fn bindgen_test_layout_RTCRay16() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCRay16>())).dir_y as *const _ as usize },
+        ::std::mem::offset_of!(RTCRay16, dir_y),
         320usize,
         concat!(
             "Offset of field: ",
@@ -1975,7 +1953,7 @@ fn bindgen_test_layout_RTCRay16() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCRay16>())).dir_z as *const _ as usize },
+        ::std::mem::offset_of!(RTCRay16, dir_z),
         384usize,
         concat!(
             "Offset of field: ",
@@ -1985,7 +1963,7 @@ fn bindgen_test_layout_RTCRay16() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCRay16>())).time as *const _ as usize },
+        ::std::mem::offset_of!(RTCRay16, time),
         448usize,
         concat!(
             "Offset of field: ",
@@ -1995,7 +1973,7 @@ fn bindgen_test_layout_RTCRay16() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCRay16>())).tfar as *const _ as usize },
+        ::std::mem::offset_of!(RTCRay16, tfar),
         512usize,
         concat!(
             "Offset of field: ",
@@ -2005,7 +1983,7 @@ fn bindgen_test_layout_RTCRay16() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCRay16>())).mask as *const _ as usize },
+        ::std::mem::offset_of!(RTCRay16, mask),
         576usize,
         concat!(
             "Offset of field: ",
@@ -2015,7 +1993,7 @@ fn bindgen_test_layout_RTCRay16() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCRay16>())).id as *const _ as usize },
+        ::std::mem::offset_of!(RTCRay16, id),
         640usize,
         concat!(
             "Offset of field: ",
@@ -2025,7 +2003,7 @@ fn bindgen_test_layout_RTCRay16() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCRay16>())).flags as *const _ as usize },
+        ::std::mem::offset_of!(RTCRay16, flags),
         704usize,
         concat!(
             "Offset of field: ",
@@ -2061,7 +2039,7 @@ fn bindgen_test_layout_RTCHit16() {
         concat!("Alignment of ", stringify!(RTCHit16))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCHit16>())).Ng_x as *const _ as usize },
+        ::std::mem::offset_of!(RTCHit16, Ng_x),
         0usize,
         concat!(
             "Offset of field: ",
@@ -2071,7 +2049,7 @@ fn bindgen_test_layout_RTCHit16() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCHit16>())).Ng_y as *const _ as usize },
+        ::std::mem::offset_of!(RTCHit16, Ng_y),
         64usize,
         concat!(
             "Offset of field: ",
@@ -2081,7 +2059,7 @@ fn bindgen_test_layout_RTCHit16() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCHit16>())).Ng_z as *const _ as usize },
+        ::std::mem::offset_of!(RTCHit16, Ng_z),
         128usize,
         concat!(
             "Offset of field: ",
@@ -2091,7 +2069,7 @@ fn bindgen_test_layout_RTCHit16() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCHit16>())).u as *const _ as usize },
+        ::std::mem::offset_of!(RTCHit16, u),
         192usize,
         concat!(
             "Offset of field: ",
@@ -2101,7 +2079,7 @@ fn bindgen_test_layout_RTCHit16() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCHit16>())).v as *const _ as usize },
+        ::std::mem::offset_of!(RTCHit16, v),
         256usize,
         concat!(
             "Offset of field: ",
@@ -2111,7 +2089,7 @@ fn bindgen_test_layout_RTCHit16() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCHit16>())).primID as *const _ as usize },
+        ::std::mem::offset_of!(RTCHit16, primID),
         320usize,
         concat!(
             "Offset of field: ",
@@ -2121,7 +2099,7 @@ fn bindgen_test_layout_RTCHit16() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCHit16>())).geomID as *const _ as usize },
+        ::std::mem::offset_of!(RTCHit16, geomID),
         384usize,
         concat!(
             "Offset of field: ",
@@ -2131,7 +2109,7 @@ fn bindgen_test_layout_RTCHit16() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCHit16>())).instID as *const _ as usize },
+        ::std::mem::offset_of!(RTCHit16, instID),
         448usize,
         concat!(
             "Offset of field: ",
@@ -2161,7 +2139,7 @@ fn bindgen_test_layout_RTCRayHit16() {
         concat!("Alignment of ", stringify!(RTCRayHit16))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCRayHit16>())).ray as *const _ as usize },
+        ::std::mem::offset_of!(RTCRayHit16, ray),
         0usize,
         concat!(
             "Offset of field: ",
@@ -2171,7 +2149,7 @@ fn bindgen_test_layout_RTCRayHit16() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCRayHit16>())).hit as *const _ as usize },
+        ::std::mem::offset_of!(RTCRayHit16, hit),
         768usize,
         concat!(
             "Offset of field: ",
@@ -2210,7 +2188,7 @@ fn bindgen_test_layout_RTCRayNp() {
         concat!("Alignment of ", stringify!(RTCRayNp))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCRayNp>())).org_x as *const _ as usize },
+        ::std::mem::offset_of!(RTCRayNp, org_x),
         0usize,
         concat!(
             "Offset of field: ",
@@ -2220,7 +2198,7 @@ fn bindgen_test_layout_RTCRayNp() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCRayNp>())).org_y as *const _ as usize },
+        ::std::mem::offset_of!(RTCRayNp, org_y),
         8usize,
         concat!(
             "Offset of field: ",
@@ -2230,7 +2208,7 @@ fn bindgen_test_layout_RTCRayNp() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCRayNp>())).org_z as *const _ as usize },
+        ::std::mem::offset_of!(RTCRayNp, org_z),
         16usize,
         concat!(
             "Offset of field: ",
@@ -2240,7 +2218,7 @@ fn bindgen_test_layout_RTCRayNp() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCRayNp>())).tnear as *const _ as usize },
+        ::std::mem::offset_of!(RTCRayNp, tnear),
         24usize,
         concat!(
             "Offset of field: ",
@@ -2250,7 +2228,7 @@ fn bindgen_test_layout_RTCRayNp() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCRayNp>())).dir_x as *const _ as usize },
+        ::std::mem::offset_of!(RTCRayNp, dir_x),
         32usize,
         concat!(
             "Offset of field: ",
@@ -2260,7 +2238,7 @@ fn bindgen_test_layout_RTCRayNp() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCRayNp>())).dir_y as *const _ as usize },
+        ::std::mem::offset_of!(RTCRayNp, dir_y),
         40usize,
         concat!(
             "Offset of field: ",
@@ -2270,7 +2248,7 @@ fn bindgen_test_layout_RTCRayNp() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCRayNp>())).dir_z as *const _ as usize },
+        ::std::mem::offset_of!(RTCRayNp, dir_z),
         48usize,
         concat!(
             "Offset of field: ",
@@ -2280,7 +2258,7 @@ fn bindgen_test_layout_RTCRayNp() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCRayNp>())).time as *const _ as usize },
+        ::std::mem::offset_of!(RTCRayNp, time),
         56usize,
         concat!(
             "Offset of field: ",
@@ -2290,7 +2268,7 @@ fn bindgen_test_layout_RTCRayNp() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCRayNp>())).tfar as *const _ as usize },
+        ::std::mem::offset_of!(RTCRayNp, tfar),
         64usize,
         concat!(
             "Offset of field: ",
@@ -2300,7 +2278,7 @@ fn bindgen_test_layout_RTCRayNp() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCRayNp>())).mask as *const _ as usize },
+        ::std::mem::offset_of!(RTCRayNp, mask),
         72usize,
         concat!(
             "Offset of field: ",
@@ -2310,7 +2288,7 @@ fn bindgen_test_layout_RTCRayNp() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCRayNp>())).id as *const _ as usize },
+        ::std::mem::offset_of!(RTCRayNp, id),
         80usize,
         concat!(
             "Offset of field: ",
@@ -2320,7 +2298,7 @@ fn bindgen_test_layout_RTCRayNp() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCRayNp>())).flags as *const _ as usize },
+        ::std::mem::offset_of!(RTCRayNp, flags),
         88usize,
         concat!(
             "Offset of field: ",
@@ -2355,7 +2333,7 @@ fn bindgen_test_layout_RTCHitNp() {
         concat!("Alignment of ", stringify!(RTCHitNp))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCHitNp>())).Ng_x as *const _ as usize },
+        ::std::mem::offset_of!(RTCHitNp, Ng_x),
         0usize,
         concat!(
             "Offset of field: ",
@@ -2365,7 +2343,7 @@ fn bindgen_test_layout_RTCHitNp() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCHitNp>())).Ng_y as *const _ as usize },
+        ::std::mem::offset_of!(RTCHitNp, Ng_y),
         8usize,
         concat!(
             "Offset of field: ",
@@ -2375,7 +2353,7 @@ fn bindgen_test_layout_RTCHitNp() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCHitNp>())).Ng_z as *const _ as usize },
+        ::std::mem::offset_of!(RTCHitNp, Ng_z),
         16usize,
         concat!(
             "Offset of field: ",
@@ -2385,7 +2363,7 @@ fn bindgen_test_layout_RTCHitNp() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCHitNp>())).u as *const _ as usize },
+        ::std::mem::offset_of!(RTCHitNp, u),
         24usize,
         concat!(
             "Offset of field: ",
@@ -2395,7 +2373,7 @@ fn bindgen_test_layout_RTCHitNp() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCHitNp>())).v as *const _ as usize },
+        ::std::mem::offset_of!(RTCHitNp, v),
         32usize,
         concat!(
             "Offset of field: ",
@@ -2405,7 +2383,7 @@ fn bindgen_test_layout_RTCHitNp() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCHitNp>())).primID as *const _ as usize },
+        ::std::mem::offset_of!(RTCHitNp, primID),
         40usize,
         concat!(
             "Offset of field: ",
@@ -2415,7 +2393,7 @@ fn bindgen_test_layout_RTCHitNp() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCHitNp>())).geomID as *const _ as usize },
+        ::std::mem::offset_of!(RTCHitNp, geomID),
         48usize,
         concat!(
             "Offset of field: ",
@@ -2425,7 +2403,7 @@ fn bindgen_test_layout_RTCHitNp() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCHitNp>())).instID as *const _ as usize },
+        ::std::mem::offset_of!(RTCHitNp, instID),
         56usize,
         concat!(
             "Offset of field: ",
@@ -2454,7 +2432,7 @@ fn bindgen_test_layout_RTCRayHitNp() {
         concat!("Alignment of ", stringify!(RTCRayHitNp))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCRayHitNp>())).ray as *const _ as usize },
+        ::std::mem::offset_of!(RTCRayHitNp, ray),
         0usize,
         concat!(
             "Offset of field: ",
@@ -2464,7 +2442,7 @@ fn bindgen_test_layout_RTCRayHitNp() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCRayHitNp>())).hit as *const _ as usize },
+        ::std::mem::offset_of!(RTCRayHitNp, hit),
         96usize,
         concat!(
             "Offset of field: ",
@@ -2523,9 +2501,7 @@ fn bindgen_test_layout_RTCQuaternionDecomposition() {
         concat!("Alignment of ", stringify!(RTCQuaternionDecomposition))
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<RTCQuaternionDecomposition>())).scale_x as *const _ as usize
-        },
+        ::std::mem::offset_of!(RTCQuaternionDecomposition, scale_x),
         0usize,
         concat!(
             "Offset of field: ",
@@ -2535,9 +2511,7 @@ fn bindgen_test_layout_RTCQuaternionDecomposition() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<RTCQuaternionDecomposition>())).scale_y as *const _ as usize
-        },
+        ::std::mem::offset_of!(RTCQuaternionDecomposition, scale_y),
         4usize,
         concat!(
             "Offset of field: ",
@@ -2547,9 +2521,7 @@ fn bindgen_test_layout_RTCQuaternionDecomposition() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<RTCQuaternionDecomposition>())).scale_z as *const _ as usize
-        },
+        ::std::mem::offset_of!(RTCQuaternionDecomposition, scale_z),
         8usize,
         concat!(
             "Offset of field: ",
@@ -2559,9 +2531,7 @@ fn bindgen_test_layout_RTCQuaternionDecomposition() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<RTCQuaternionDecomposition>())).skew_xy as *const _ as usize
-        },
+        ::std::mem::offset_of!(RTCQuaternionDecomposition, skew_xy),
         12usize,
         concat!(
             "Offset of field: ",
@@ -2571,9 +2541,7 @@ fn bindgen_test_layout_RTCQuaternionDecomposition() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<RTCQuaternionDecomposition>())).skew_xz as *const _ as usize
-        },
+        ::std::mem::offset_of!(RTCQuaternionDecomposition, skew_xz),
         16usize,
         concat!(
             "Offset of field: ",
@@ -2583,9 +2551,7 @@ fn bindgen_test_layout_RTCQuaternionDecomposition() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<RTCQuaternionDecomposition>())).skew_yz as *const _ as usize
-        },
+        ::std::mem::offset_of!(RTCQuaternionDecomposition, skew_yz),
         20usize,
         concat!(
             "Offset of field: ",
@@ -2595,9 +2561,7 @@ fn bindgen_test_layout_RTCQuaternionDecomposition() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<RTCQuaternionDecomposition>())).shift_x as *const _ as usize
-        },
+        ::std::mem::offset_of!(RTCQuaternionDecomposition, shift_x),
         24usize,
         concat!(
             "Offset of field: ",
@@ -2607,9 +2571,7 @@ fn bindgen_test_layout_RTCQuaternionDecomposition() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<RTCQuaternionDecomposition>())).shift_y as *const _ as usize
-        },
+        ::std::mem::offset_of!(RTCQuaternionDecomposition, shift_y),
         28usize,
         concat!(
             "Offset of field: ",
@@ -2619,9 +2581,7 @@ fn bindgen_test_layout_RTCQuaternionDecomposition() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<RTCQuaternionDecomposition>())).shift_z as *const _ as usize
-        },
+        ::std::mem::offset_of!(RTCQuaternionDecomposition, shift_z),
         32usize,
         concat!(
             "Offset of field: ",
@@ -2631,9 +2591,7 @@ fn bindgen_test_layout_RTCQuaternionDecomposition() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<RTCQuaternionDecomposition>())).quaternion_r as *const _ as usize
-        },
+        ::std::mem::offset_of!(RTCQuaternionDecomposition, quaternion_r),
         36usize,
         concat!(
             "Offset of field: ",
@@ -2643,9 +2601,7 @@ fn bindgen_test_layout_RTCQuaternionDecomposition() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<RTCQuaternionDecomposition>())).quaternion_i as *const _ as usize
-        },
+        ::std::mem::offset_of!(RTCQuaternionDecomposition, quaternion_i),
         40usize,
         concat!(
             "Offset of field: ",
@@ -2655,9 +2611,7 @@ fn bindgen_test_layout_RTCQuaternionDecomposition() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<RTCQuaternionDecomposition>())).quaternion_j as *const _ as usize
-        },
+        ::std::mem::offset_of!(RTCQuaternionDecomposition, quaternion_j),
         44usize,
         concat!(
             "Offset of field: ",
@@ -2667,9 +2621,7 @@ fn bindgen_test_layout_RTCQuaternionDecomposition() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<RTCQuaternionDecomposition>())).quaternion_k as *const _ as usize
-        },
+        ::std::mem::offset_of!(RTCQuaternionDecomposition, quaternion_k),
         48usize,
         concat!(
             "Offset of field: ",
@@ -2679,10 +2631,7 @@ fn bindgen_test_layout_RTCQuaternionDecomposition() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<RTCQuaternionDecomposition>())).translation_x as *const _
-                as usize
-        },
+        ::std::mem::offset_of!(RTCQuaternionDecomposition, translation_x),
         52usize,
         concat!(
             "Offset of field: ",
@@ -2692,10 +2641,7 @@ fn bindgen_test_layout_RTCQuaternionDecomposition() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<RTCQuaternionDecomposition>())).translation_y as *const _
-                as usize
-        },
+        ::std::mem::offset_of!(RTCQuaternionDecomposition, translation_y),
         56usize,
         concat!(
             "Offset of field: ",
@@ -2705,10 +2651,7 @@ fn bindgen_test_layout_RTCQuaternionDecomposition() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<RTCQuaternionDecomposition>())).translation_z as *const _
-                as usize
-        },
+        ::std::mem::offset_of!(RTCQuaternionDecomposition, translation_z),
         60usize,
         concat!(
             "Offset of field: ",
@@ -2823,10 +2766,7 @@ fn bindgen_test_layout_RTCBoundsFunctionArguments() {
         concat!("Alignment of ", stringify!(RTCBoundsFunctionArguments))
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<RTCBoundsFunctionArguments>())).geometryUserPtr as *const _
-                as usize
-        },
+        ::std::mem::offset_of!(RTCBoundsFunctionArguments, geometryUserPtr),
         0usize,
         concat!(
             "Offset of field: ",
@@ -2836,9 +2776,7 @@ fn bindgen_test_layout_RTCBoundsFunctionArguments() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<RTCBoundsFunctionArguments>())).primID as *const _ as usize
-        },
+        ::std::mem::offset_of!(RTCBoundsFunctionArguments, primID),
         8usize,
         concat!(
             "Offset of field: ",
@@ -2848,9 +2786,7 @@ fn bindgen_test_layout_RTCBoundsFunctionArguments() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<RTCBoundsFunctionArguments>())).timeStep as *const _ as usize
-        },
+        ::std::mem::offset_of!(RTCBoundsFunctionArguments, timeStep),
         12usize,
         concat!(
             "Offset of field: ",
@@ -2860,9 +2796,7 @@ fn bindgen_test_layout_RTCBoundsFunctionArguments() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<RTCBoundsFunctionArguments>())).bounds_o as *const _ as usize
-        },
+        ::std::mem::offset_of!(RTCBoundsFunctionArguments, bounds_o),
         16usize,
         concat!(
             "Offset of field: ",
@@ -2898,9 +2832,7 @@ fn bindgen_test_layout_RTCIntersectFunctionNArguments() {
         concat!("Alignment of ", stringify!(RTCIntersectFunctionNArguments))
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<RTCIntersectFunctionNArguments>())).valid as *const _ as usize
-        },
+        ::std::mem::offset_of!(RTCIntersectFunctionNArguments, valid),
         0usize,
         concat!(
             "Offset of field: ",
@@ -2910,10 +2842,7 @@ fn bindgen_test_layout_RTCIntersectFunctionNArguments() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<RTCIntersectFunctionNArguments>())).geometryUserPtr as *const _
-                as usize
-        },
+        ::std::mem::offset_of!(RTCIntersectFunctionNArguments, geometryUserPtr),
         8usize,
         concat!(
             "Offset of field: ",
@@ -2923,9 +2852,7 @@ fn bindgen_test_layout_RTCIntersectFunctionNArguments() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<RTCIntersectFunctionNArguments>())).primID as *const _ as usize
-        },
+        ::std::mem::offset_of!(RTCIntersectFunctionNArguments, primID),
         16usize,
         concat!(
             "Offset of field: ",
@@ -2935,9 +2862,7 @@ fn bindgen_test_layout_RTCIntersectFunctionNArguments() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<RTCIntersectFunctionNArguments>())).context as *const _ as usize
-        },
+        ::std::mem::offset_of!(RTCIntersectFunctionNArguments, context),
         24usize,
         concat!(
             "Offset of field: ",
@@ -2947,9 +2872,7 @@ fn bindgen_test_layout_RTCIntersectFunctionNArguments() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<RTCIntersectFunctionNArguments>())).rayhit as *const _ as usize
-        },
+        ::std::mem::offset_of!(RTCIntersectFunctionNArguments, rayhit),
         32usize,
         concat!(
             "Offset of field: ",
@@ -2959,9 +2882,7 @@ fn bindgen_test_layout_RTCIntersectFunctionNArguments() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<RTCIntersectFunctionNArguments>())).N as *const _ as usize
-        },
+        ::std::mem::offset_of!(RTCIntersectFunctionNArguments, N),
         40usize,
         concat!(
             "Offset of field: ",
@@ -2971,9 +2892,7 @@ fn bindgen_test_layout_RTCIntersectFunctionNArguments() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<RTCIntersectFunctionNArguments>())).geomID as *const _ as usize
-        },
+        ::std::mem::offset_of!(RTCIntersectFunctionNArguments, geomID),
         44usize,
         concat!(
             "Offset of field: ",
@@ -3009,9 +2928,7 @@ fn bindgen_test_layout_RTCOccludedFunctionNArguments() {
         concat!("Alignment of ", stringify!(RTCOccludedFunctionNArguments))
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<RTCOccludedFunctionNArguments>())).valid as *const _ as usize
-        },
+        ::std::mem::offset_of!(RTCOccludedFunctionNArguments, valid),
         0usize,
         concat!(
             "Offset of field: ",
@@ -3021,10 +2938,7 @@ fn bindgen_test_layout_RTCOccludedFunctionNArguments() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<RTCOccludedFunctionNArguments>())).geometryUserPtr as *const _
-                as usize
-        },
+        ::std::mem::offset_of!(RTCOccludedFunctionNArguments, geometryUserPtr),
         8usize,
         concat!(
             "Offset of field: ",
@@ -3034,9 +2948,7 @@ fn bindgen_test_layout_RTCOccludedFunctionNArguments() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<RTCOccludedFunctionNArguments>())).primID as *const _ as usize
-        },
+        ::std::mem::offset_of!(RTCOccludedFunctionNArguments, primID),
         16usize,
         concat!(
             "Offset of field: ",
@@ -3046,9 +2958,7 @@ fn bindgen_test_layout_RTCOccludedFunctionNArguments() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<RTCOccludedFunctionNArguments>())).context as *const _ as usize
-        },
+        ::std::mem::offset_of!(RTCOccludedFunctionNArguments, context),
         24usize,
         concat!(
             "Offset of field: ",
@@ -3058,9 +2968,7 @@ fn bindgen_test_layout_RTCOccludedFunctionNArguments() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<RTCOccludedFunctionNArguments>())).ray as *const _ as usize
-        },
+        ::std::mem::offset_of!(RTCOccludedFunctionNArguments, ray),
         32usize,
         concat!(
             "Offset of field: ",
@@ -3070,7 +2978,7 @@ fn bindgen_test_layout_RTCOccludedFunctionNArguments() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCOccludedFunctionNArguments>())).N as *const _ as usize },
+        ::std::mem::offset_of!(RTCOccludedFunctionNArguments, N),
         40usize,
         concat!(
             "Offset of field: ",
@@ -3080,9 +2988,7 @@ fn bindgen_test_layout_RTCOccludedFunctionNArguments() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<RTCOccludedFunctionNArguments>())).geomID as *const _ as usize
-        },
+        ::std::mem::offset_of!(RTCOccludedFunctionNArguments, geomID),
         44usize,
         concat!(
             "Offset of field: ",
@@ -3127,10 +3033,7 @@ fn bindgen_test_layout_RTCDisplacementFunctionNArguments() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<RTCDisplacementFunctionNArguments>())).geometryUserPtr
-                as *const _ as usize
-        },
+        ::std::mem::offset_of!(RTCDisplacementFunctionNArguments, geometryUserPtr),
         0usize,
         concat!(
             "Offset of field: ",
@@ -3140,10 +3043,7 @@ fn bindgen_test_layout_RTCDisplacementFunctionNArguments() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<RTCDisplacementFunctionNArguments>())).geometry as *const _
-                as usize
-        },
+        ::std::mem::offset_of!(RTCDisplacementFunctionNArguments, geometry),
         8usize,
         concat!(
             "Offset of field: ",
@@ -3153,10 +3053,7 @@ fn bindgen_test_layout_RTCDisplacementFunctionNArguments() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<RTCDisplacementFunctionNArguments>())).primID as *const _
-                as usize
-        },
+        ::std::mem::offset_of!(RTCDisplacementFunctionNArguments, primID),
         16usize,
         concat!(
             "Offset of field: ",
@@ -3166,10 +3063,7 @@ fn bindgen_test_layout_RTCDisplacementFunctionNArguments() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<RTCDisplacementFunctionNArguments>())).timeStep as *const _
-                as usize
-        },
+        ::std::mem::offset_of!(RTCDisplacementFunctionNArguments, timeStep),
         20usize,
         concat!(
             "Offset of field: ",
@@ -3179,9 +3073,7 @@ fn bindgen_test_layout_RTCDisplacementFunctionNArguments() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<RTCDisplacementFunctionNArguments>())).u as *const _ as usize
-        },
+        ::std::mem::offset_of!(RTCDisplacementFunctionNArguments, u),
         24usize,
         concat!(
             "Offset of field: ",
@@ -3191,9 +3083,7 @@ fn bindgen_test_layout_RTCDisplacementFunctionNArguments() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<RTCDisplacementFunctionNArguments>())).v as *const _ as usize
-        },
+        ::std::mem::offset_of!(RTCDisplacementFunctionNArguments, v),
         32usize,
         concat!(
             "Offset of field: ",
@@ -3203,9 +3093,7 @@ fn bindgen_test_layout_RTCDisplacementFunctionNArguments() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<RTCDisplacementFunctionNArguments>())).Ng_x as *const _ as usize
-        },
+        ::std::mem::offset_of!(RTCDisplacementFunctionNArguments, Ng_x),
         40usize,
         concat!(
             "Offset of field: ",
@@ -3215,9 +3103,7 @@ fn bindgen_test_layout_RTCDisplacementFunctionNArguments() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<RTCDisplacementFunctionNArguments>())).Ng_y as *const _ as usize
-        },
+        ::std::mem::offset_of!(RTCDisplacementFunctionNArguments, Ng_y),
         48usize,
         concat!(
             "Offset of field: ",
@@ -3227,9 +3113,7 @@ fn bindgen_test_layout_RTCDisplacementFunctionNArguments() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<RTCDisplacementFunctionNArguments>())).Ng_z as *const _ as usize
-        },
+        ::std::mem::offset_of!(RTCDisplacementFunctionNArguments, Ng_z),
         56usize,
         concat!(
             "Offset of field: ",
@@ -3239,9 +3123,7 @@ fn bindgen_test_layout_RTCDisplacementFunctionNArguments() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<RTCDisplacementFunctionNArguments>())).P_x as *const _ as usize
-        },
+        ::std::mem::offset_of!(RTCDisplacementFunctionNArguments, P_x),
         64usize,
         concat!(
             "Offset of field: ",
@@ -3251,9 +3133,7 @@ fn bindgen_test_layout_RTCDisplacementFunctionNArguments() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<RTCDisplacementFunctionNArguments>())).P_y as *const _ as usize
-        },
+        ::std::mem::offset_of!(RTCDisplacementFunctionNArguments, P_y),
         72usize,
         concat!(
             "Offset of field: ",
@@ -3263,9 +3143,7 @@ fn bindgen_test_layout_RTCDisplacementFunctionNArguments() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<RTCDisplacementFunctionNArguments>())).P_z as *const _ as usize
-        },
+        ::std::mem::offset_of!(RTCDisplacementFunctionNArguments, P_z),
         80usize,
         concat!(
             "Offset of field: ",
@@ -3275,9 +3153,7 @@ fn bindgen_test_layout_RTCDisplacementFunctionNArguments() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<RTCDisplacementFunctionNArguments>())).N as *const _ as usize
-        },
+        ::std::mem::offset_of!(RTCDisplacementFunctionNArguments, N),
         88usize,
         concat!(
             "Offset of field: ",
@@ -3544,9 +3420,7 @@ fn bindgen_test_layout_RTCInterpolateArguments() {
         concat!("Alignment of ", stringify!(RTCInterpolateArguments))
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<RTCInterpolateArguments>())).geometry as *const _ as usize
-        },
+        ::std::mem::offset_of!(RTCInterpolateArguments, geometry),
         0usize,
         concat!(
             "Offset of field: ",
@@ -3556,7 +3430,7 @@ fn bindgen_test_layout_RTCInterpolateArguments() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCInterpolateArguments>())).primID as *const _ as usize },
+        ::std::mem::offset_of!(RTCInterpolateArguments, primID),
         8usize,
         concat!(
             "Offset of field: ",
@@ -3566,7 +3440,7 @@ fn bindgen_test_layout_RTCInterpolateArguments() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCInterpolateArguments>())).u as *const _ as usize },
+        ::std::mem::offset_of!(RTCInterpolateArguments, u),
         12usize,
         concat!(
             "Offset of field: ",
@@ -3576,7 +3450,7 @@ fn bindgen_test_layout_RTCInterpolateArguments() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCInterpolateArguments>())).v as *const _ as usize },
+        ::std::mem::offset_of!(RTCInterpolateArguments, v),
         16usize,
         concat!(
             "Offset of field: ",
@@ -3586,9 +3460,7 @@ fn bindgen_test_layout_RTCInterpolateArguments() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<RTCInterpolateArguments>())).bufferType as *const _ as usize
-        },
+        ::std::mem::offset_of!(RTCInterpolateArguments, bufferType),
         20usize,
         concat!(
             "Offset of field: ",
@@ -3598,9 +3470,7 @@ fn bindgen_test_layout_RTCInterpolateArguments() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<RTCInterpolateArguments>())).bufferSlot as *const _ as usize
-        },
+        ::std::mem::offset_of!(RTCInterpolateArguments, bufferSlot),
         24usize,
         concat!(
             "Offset of field: ",
@@ -3610,7 +3480,7 @@ fn bindgen_test_layout_RTCInterpolateArguments() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCInterpolateArguments>())).P as *const _ as usize },
+        ::std::mem::offset_of!(RTCInterpolateArguments, P),
         32usize,
         concat!(
             "Offset of field: ",
@@ -3620,7 +3490,7 @@ fn bindgen_test_layout_RTCInterpolateArguments() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCInterpolateArguments>())).dPdu as *const _ as usize },
+        ::std::mem::offset_of!(RTCInterpolateArguments, dPdu),
         40usize,
         concat!(
             "Offset of field: ",
@@ -3630,7 +3500,7 @@ fn bindgen_test_layout_RTCInterpolateArguments() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCInterpolateArguments>())).dPdv as *const _ as usize },
+        ::std::mem::offset_of!(RTCInterpolateArguments, dPdv),
         48usize,
         concat!(
             "Offset of field: ",
@@ -3640,7 +3510,7 @@ fn bindgen_test_layout_RTCInterpolateArguments() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCInterpolateArguments>())).ddPdudu as *const _ as usize },
+        ::std::mem::offset_of!(RTCInterpolateArguments, ddPdudu),
         56usize,
         concat!(
             "Offset of field: ",
@@ -3650,7 +3520,7 @@ fn bindgen_test_layout_RTCInterpolateArguments() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCInterpolateArguments>())).ddPdvdv as *const _ as usize },
+        ::std::mem::offset_of!(RTCInterpolateArguments, ddPdvdv),
         64usize,
         concat!(
             "Offset of field: ",
@@ -3660,7 +3530,7 @@ fn bindgen_test_layout_RTCInterpolateArguments() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCInterpolateArguments>())).ddPdudv as *const _ as usize },
+        ::std::mem::offset_of!(RTCInterpolateArguments, ddPdudv),
         72usize,
         concat!(
             "Offset of field: ",
@@ -3670,9 +3540,7 @@ fn bindgen_test_layout_RTCInterpolateArguments() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<RTCInterpolateArguments>())).valueCount as *const _ as usize
-        },
+        ::std::mem::offset_of!(RTCInterpolateArguments, valueCount),
         80usize,
         concat!(
             "Offset of field: ",
@@ -3717,9 +3585,7 @@ fn bindgen_test_layout_RTCInterpolateNArguments() {
         concat!("Alignment of ", stringify!(RTCInterpolateNArguments))
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<RTCInterpolateNArguments>())).geometry as *const _ as usize
-        },
+        ::std::mem::offset_of!(RTCInterpolateNArguments, geometry),
         0usize,
         concat!(
             "Offset of field: ",
@@ -3729,7 +3595,7 @@ fn bindgen_test_layout_RTCInterpolateNArguments() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCInterpolateNArguments>())).valid as *const _ as usize },
+        ::std::mem::offset_of!(RTCInterpolateNArguments, valid),
         8usize,
         concat!(
             "Offset of field: ",
@@ -3739,9 +3605,7 @@ fn bindgen_test_layout_RTCInterpolateNArguments() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<RTCInterpolateNArguments>())).primIDs as *const _ as usize
-        },
+        ::std::mem::offset_of!(RTCInterpolateNArguments, primIDs),
         16usize,
         concat!(
             "Offset of field: ",
@@ -3751,7 +3615,7 @@ fn bindgen_test_layout_RTCInterpolateNArguments() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCInterpolateNArguments>())).u as *const _ as usize },
+        ::std::mem::offset_of!(RTCInterpolateNArguments, u),
         24usize,
         concat!(
             "Offset of field: ",
@@ -3761,7 +3625,7 @@ fn bindgen_test_layout_RTCInterpolateNArguments() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCInterpolateNArguments>())).v as *const _ as usize },
+        ::std::mem::offset_of!(RTCInterpolateNArguments, v),
         32usize,
         concat!(
             "Offset of field: ",
@@ -3771,7 +3635,7 @@ fn bindgen_test_layout_RTCInterpolateNArguments() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCInterpolateNArguments>())).N as *const _ as usize },
+        ::std::mem::offset_of!(RTCInterpolateNArguments, N),
         40usize,
         concat!(
             "Offset of field: ",
@@ -3781,9 +3645,7 @@ fn bindgen_test_layout_RTCInterpolateNArguments() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<RTCInterpolateNArguments>())).bufferType as *const _ as usize
-        },
+        ::std::mem::offset_of!(RTCInterpolateNArguments, bufferType),
         44usize,
         concat!(
             "Offset of field: ",
@@ -3793,9 +3655,7 @@ fn bindgen_test_layout_RTCInterpolateNArguments() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<RTCInterpolateNArguments>())).bufferSlot as *const _ as usize
-        },
+        ::std::mem::offset_of!(RTCInterpolateNArguments, bufferSlot),
         48usize,
         concat!(
             "Offset of field: ",
@@ -3805,7 +3665,7 @@ fn bindgen_test_layout_RTCInterpolateNArguments() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCInterpolateNArguments>())).P as *const _ as usize },
+        ::std::mem::offset_of!(RTCInterpolateNArguments, P),
         56usize,
         concat!(
             "Offset of field: ",
@@ -3815,7 +3675,7 @@ fn bindgen_test_layout_RTCInterpolateNArguments() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCInterpolateNArguments>())).dPdu as *const _ as usize },
+        ::std::mem::offset_of!(RTCInterpolateNArguments, dPdu),
         64usize,
         concat!(
             "Offset of field: ",
@@ -3825,7 +3685,7 @@ fn bindgen_test_layout_RTCInterpolateNArguments() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCInterpolateNArguments>())).dPdv as *const _ as usize },
+        ::std::mem::offset_of!(RTCInterpolateNArguments, dPdv),
         72usize,
         concat!(
             "Offset of field: ",
@@ -3835,9 +3695,7 @@ fn bindgen_test_layout_RTCInterpolateNArguments() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<RTCInterpolateNArguments>())).ddPdudu as *const _ as usize
-        },
+        ::std::mem::offset_of!(RTCInterpolateNArguments, ddPdudu),
         80usize,
         concat!(
             "Offset of field: ",
@@ -3847,9 +3705,7 @@ fn bindgen_test_layout_RTCInterpolateNArguments() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<RTCInterpolateNArguments>())).ddPdvdv as *const _ as usize
-        },
+        ::std::mem::offset_of!(RTCInterpolateNArguments, ddPdvdv),
         88usize,
         concat!(
             "Offset of field: ",
@@ -3859,9 +3715,7 @@ fn bindgen_test_layout_RTCInterpolateNArguments() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<RTCInterpolateNArguments>())).ddPdudv as *const _ as usize
-        },
+        ::std::mem::offset_of!(RTCInterpolateNArguments, ddPdudv),
         96usize,
         concat!(
             "Offset of field: ",
@@ -3871,9 +3725,7 @@ fn bindgen_test_layout_RTCInterpolateNArguments() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<RTCInterpolateNArguments>())).valueCount as *const _ as usize
-        },
+        ::std::mem::offset_of!(RTCInterpolateNArguments, valueCount),
         104usize,
         concat!(
             "Offset of field: ",
@@ -3907,7 +3759,7 @@ fn bindgen_test_layout_RTCGrid() {
         concat!("Alignment of ", stringify!(RTCGrid))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCGrid>())).startVertexID as *const _ as usize },
+        ::std::mem::offset_of!(RTCGrid, startVertexID),
         0usize,
         concat!(
             "Offset of field: ",
@@ -3917,7 +3769,7 @@ fn bindgen_test_layout_RTCGrid() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCGrid>())).stride as *const _ as usize },
+        ::std::mem::offset_of!(RTCGrid, stride),
         4usize,
         concat!(
             "Offset of field: ",
@@ -3927,7 +3779,7 @@ fn bindgen_test_layout_RTCGrid() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCGrid>())).width as *const _ as usize },
+        ::std::mem::offset_of!(RTCGrid, width),
         8usize,
         concat!(
             "Offset of field: ",
@@ -3937,7 +3789,7 @@ fn bindgen_test_layout_RTCGrid() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCGrid>())).height as *const _ as usize },
+        ::std::mem::offset_of!(RTCGrid, height),
         10usize,
         concat!(
             "Offset of field: ",
@@ -4237,7 +4089,7 @@ fn bindgen_test_layout_RTCCollision() {
         concat!("Alignment of ", stringify!(RTCCollision))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCCollision>())).geomID0 as *const _ as usize },
+        ::std::mem::offset_of!(RTCCollision, geomID0),
         0usize,
         concat!(
             "Offset of field: ",
@@ -4247,7 +4099,7 @@ fn bindgen_test_layout_RTCCollision() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCCollision>())).primID0 as *const _ as usize },
+        ::std::mem::offset_of!(RTCCollision, primID0),
         4usize,
         concat!(
             "Offset of field: ",
@@ -4257,7 +4109,7 @@ fn bindgen_test_layout_RTCCollision() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCCollision>())).geomID1 as *const _ as usize },
+        ::std::mem::offset_of!(RTCCollision, geomID1),
         8usize,
         concat!(
             "Offset of field: ",
@@ -4267,7 +4119,7 @@ fn bindgen_test_layout_RTCCollision() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCCollision>())).primID1 as *const _ as usize },
+        ::std::mem::offset_of!(RTCCollision, primID1),
         12usize,
         concat!(
             "Offset of field: ",
@@ -4324,7 +4176,7 @@ fn bindgen_test_layout_RTCBuildPrimitive() {
         concat!("Alignment of ", stringify!(RTCBuildPrimitive))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCBuildPrimitive>())).lower_x as *const _ as usize },
+        ::std::mem::offset_of!(RTCBuildPrimitive, lower_x),
         0usize,
         concat!(
             "Offset of field: ",
@@ -4334,7 +4186,7 @@ fn bindgen_test_layout_RTCBuildPrimitive() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCBuildPrimitive>())).lower_y as *const _ as usize },
+        ::std::mem::offset_of!(RTCBuildPrimitive, lower_y),
         4usize,
         concat!(
             "Offset of field: ",
@@ -4344,7 +4196,7 @@ fn bindgen_test_layout_RTCBuildPrimitive() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCBuildPrimitive>())).lower_z as *const _ as usize },
+        ::std::mem::offset_of!(RTCBuildPrimitive, lower_z),
         8usize,
         concat!(
             "Offset of field: ",
@@ -4354,7 +4206,7 @@ fn bindgen_test_layout_RTCBuildPrimitive() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCBuildPrimitive>())).geomID as *const _ as usize },
+        ::std::mem::offset_of!(RTCBuildPrimitive, geomID),
         12usize,
         concat!(
             "Offset of field: ",
@@ -4364,7 +4216,7 @@ fn bindgen_test_layout_RTCBuildPrimitive() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCBuildPrimitive>())).upper_x as *const _ as usize },
+        ::std::mem::offset_of!(RTCBuildPrimitive, upper_x),
         16usize,
         concat!(
             "Offset of field: ",
@@ -4374,7 +4226,7 @@ fn bindgen_test_layout_RTCBuildPrimitive() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCBuildPrimitive>())).upper_y as *const _ as usize },
+        ::std::mem::offset_of!(RTCBuildPrimitive, upper_y),
         20usize,
         concat!(
             "Offset of field: ",
@@ -4384,7 +4236,7 @@ fn bindgen_test_layout_RTCBuildPrimitive() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCBuildPrimitive>())).upper_z as *const _ as usize },
+        ::std::mem::offset_of!(RTCBuildPrimitive, upper_z),
         24usize,
         concat!(
             "Offset of field: ",
@@ -4394,7 +4246,7 @@ fn bindgen_test_layout_RTCBuildPrimitive() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCBuildPrimitive>())).primID as *const _ as usize },
+        ::std::mem::offset_of!(RTCBuildPrimitive, primID),
         28usize,
         concat!(
             "Offset of field: ",
@@ -4526,7 +4378,7 @@ fn bindgen_test_layout_RTCBuildArguments() {
         concat!("Alignment of ", stringify!(RTCBuildArguments))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCBuildArguments>())).byteSize as *const _ as usize },
+        ::std::mem::offset_of!(RTCBuildArguments, byteSize),
         0usize,
         concat!(
             "Offset of field: ",
@@ -4536,7 +4388,7 @@ fn bindgen_test_layout_RTCBuildArguments() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCBuildArguments>())).buildQuality as *const _ as usize },
+        ::std::mem::offset_of!(RTCBuildArguments, buildQuality),
         8usize,
         concat!(
             "Offset of field: ",
@@ -4546,7 +4398,7 @@ fn bindgen_test_layout_RTCBuildArguments() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCBuildArguments>())).buildFlags as *const _ as usize },
+        ::std::mem::offset_of!(RTCBuildArguments, buildFlags),
         12usize,
         concat!(
             "Offset of field: ",
@@ -4556,9 +4408,7 @@ fn bindgen_test_layout_RTCBuildArguments() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<RTCBuildArguments>())).maxBranchingFactor as *const _ as usize
-        },
+        ::std::mem::offset_of!(RTCBuildArguments, maxBranchingFactor),
         16usize,
         concat!(
             "Offset of field: ",
@@ -4568,7 +4418,7 @@ fn bindgen_test_layout_RTCBuildArguments() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCBuildArguments>())).maxDepth as *const _ as usize },
+        ::std::mem::offset_of!(RTCBuildArguments, maxDepth),
         20usize,
         concat!(
             "Offset of field: ",
@@ -4578,7 +4428,7 @@ fn bindgen_test_layout_RTCBuildArguments() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCBuildArguments>())).sahBlockSize as *const _ as usize },
+        ::std::mem::offset_of!(RTCBuildArguments, sahBlockSize),
         24usize,
         concat!(
             "Offset of field: ",
@@ -4588,7 +4438,7 @@ fn bindgen_test_layout_RTCBuildArguments() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCBuildArguments>())).minLeafSize as *const _ as usize },
+        ::std::mem::offset_of!(RTCBuildArguments, minLeafSize),
         28usize,
         concat!(
             "Offset of field: ",
@@ -4598,7 +4448,7 @@ fn bindgen_test_layout_RTCBuildArguments() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCBuildArguments>())).maxLeafSize as *const _ as usize },
+        ::std::mem::offset_of!(RTCBuildArguments, maxLeafSize),
         32usize,
         concat!(
             "Offset of field: ",
@@ -4608,7 +4458,7 @@ fn bindgen_test_layout_RTCBuildArguments() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCBuildArguments>())).traversalCost as *const _ as usize },
+        ::std::mem::offset_of!(RTCBuildArguments, traversalCost),
         36usize,
         concat!(
             "Offset of field: ",
@@ -4618,9 +4468,7 @@ fn bindgen_test_layout_RTCBuildArguments() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<RTCBuildArguments>())).intersectionCost as *const _ as usize
-        },
+        ::std::mem::offset_of!(RTCBuildArguments, intersectionCost),
         40usize,
         concat!(
             "Offset of field: ",
@@ -4630,7 +4478,7 @@ fn bindgen_test_layout_RTCBuildArguments() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCBuildArguments>())).bvh as *const _ as usize },
+        ::std::mem::offset_of!(RTCBuildArguments, bvh),
         48usize,
         concat!(
             "Offset of field: ",
@@ -4640,7 +4488,7 @@ fn bindgen_test_layout_RTCBuildArguments() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCBuildArguments>())).primitives as *const _ as usize },
+        ::std::mem::offset_of!(RTCBuildArguments, primitives),
         56usize,
         concat!(
             "Offset of field: ",
@@ -4650,9 +4498,7 @@ fn bindgen_test_layout_RTCBuildArguments() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<RTCBuildArguments>())).primitiveCount as *const _ as usize
-        },
+        ::std::mem::offset_of!(RTCBuildArguments, primitiveCount),
         64usize,
         concat!(
             "Offset of field: ",
@@ -4662,10 +4508,7 @@ fn bindgen_test_layout_RTCBuildArguments() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<RTCBuildArguments>())).primitiveArrayCapacity as *const _
-                as usize
-        },
+        ::std::mem::offset_of!(RTCBuildArguments, primitiveArrayCapacity),
         72usize,
         concat!(
             "Offset of field: ",
@@ -4675,7 +4518,7 @@ fn bindgen_test_layout_RTCBuildArguments() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCBuildArguments>())).createNode as *const _ as usize },
+        ::std::mem::offset_of!(RTCBuildArguments, createNode),
         80usize,
         concat!(
             "Offset of field: ",
@@ -4685,9 +4528,7 @@ fn bindgen_test_layout_RTCBuildArguments() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<RTCBuildArguments>())).setNodeChildren as *const _ as usize
-        },
+        ::std::mem::offset_of!(RTCBuildArguments, setNodeChildren),
         88usize,
         concat!(
             "Offset of field: ",
@@ -4697,7 +4538,7 @@ fn bindgen_test_layout_RTCBuildArguments() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCBuildArguments>())).setNodeBounds as *const _ as usize },
+        ::std::mem::offset_of!(RTCBuildArguments, setNodeBounds),
         96usize,
         concat!(
             "Offset of field: ",
@@ -4707,7 +4548,7 @@ fn bindgen_test_layout_RTCBuildArguments() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCBuildArguments>())).createLeaf as *const _ as usize },
+        ::std::mem::offset_of!(RTCBuildArguments, createLeaf),
         104usize,
         concat!(
             "Offset of field: ",
@@ -4717,9 +4558,7 @@ fn bindgen_test_layout_RTCBuildArguments() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<RTCBuildArguments>())).splitPrimitive as *const _ as usize
-        },
+        ::std::mem::offset_of!(RTCBuildArguments, splitPrimitive),
         112usize,
         concat!(
             "Offset of field: ",
@@ -4729,7 +4568,7 @@ fn bindgen_test_layout_RTCBuildArguments() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCBuildArguments>())).buildProgress as *const _ as usize },
+        ::std::mem::offset_of!(RTCBuildArguments, buildProgress),
         120usize,
         concat!(
             "Offset of field: ",
@@ -4739,7 +4578,7 @@ fn bindgen_test_layout_RTCBuildArguments() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<RTCBuildArguments>())).userPtr as *const _ as usize },
+        ::std::mem::offset_of!(RTCBuildArguments, userPtr),
         128usize,
         concat!(
             "Offset of field: ",
@@ -0,0 +1,278 @@
+//! Deterministic procedural scene generation for benchmarks and tests.
+//! Scenes are generated from a `SceneConfig` and a seed, so performance
+//! comparisons and bug reports can refer to a reproducible scene by its
+//! configuration instead of shipping large assets. The same configuration
+//! always produces the same geometry, attached in the same order.
+
+use std::f32;
+
+use cgmath::{InnerSpace, Matrix4, Rad, Vector3};
+
+use device::Device;
+use geometry::Geometry;
+use instance::Instance;
+use scene::{CommittedScene, Scene};
+use triangle_mesh::TriangleMesh;
+
+/// Small PCG32 random number generator, producing the same sequence for a
+/// given seed on every platform.
+pub struct Pcg32 {
+    state: u64,
+    inc: u64,
+}
+
+impl Pcg32 {
+    pub fn new(seed: u64) -> Pcg32 {
+        let mut rng = Pcg32 {
+            state: 0,
+            inc: (seed << 1) | 1,
+        };
+        rng.next_u32();
+        rng.state = rng.state.wrapping_add(0x853c_49e6_748f_ea9b ^ seed);
+        rng.next_u32();
+        rng
+    }
+    pub fn next_u32(&mut self) -> u32 {
+        let old = self.state;
+        self.state = old
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(self.inc);
+        let xorshifted = (((old >> 18) ^ old) >> 27) as u32;
+        let rot = (old >> 59) as u32;
+        xorshifted.rotate_right(rot)
+    }
+    /// Get a random float in [0, 1)
+    pub fn next_f32(&mut self) -> f32 {
+        // Take the upper 24 bits to get a float in [0, 1)
+        (self.next_u32() >> 8) as f32 * (1.0 / (1u32 << 24) as f32)
+    }
+    /// Get a random float in [lo, hi)
+    pub fn range(&mut self, lo: f32, hi: f32) -> f32 {
+        lo + (hi - lo) * self.next_f32()
+    }
+    /// Get a random point in the cube [-extent, extent]^3
+    pub fn point_in_cube(&mut self, extent: f32) -> Vector3<f32> {
+        Vector3::new(
+            self.range(-extent, extent),
+            self.range(-extent, extent),
+            self.range(-extent, extent),
+        )
+    }
+}
+
+/// Configuration of a procedurally generated scene
+#[derive(Debug, Clone, PartialEq)]
+pub struct SceneConfig {
+    spheres: usize,
+    meshes: usize,
+    instances: usize,
+    motion_blur: bool,
+    resolution: u32,
+    extent: f32,
+    seed: u64,
+}
+
+impl Default for SceneConfig {
+    fn default() -> SceneConfig {
+        SceneConfig {
+            spheres: 16,
+            meshes: 4,
+            instances: 0,
+            motion_blur: false,
+            resolution: 16,
+            extent: 10.0,
+            seed: 0,
+        }
+    }
+}
+
+impl SceneConfig {
+    pub fn new() -> SceneConfig {
+        SceneConfig::default()
+    }
+    /// Set the number of tessellated spheres to generate
+    pub fn spheres(mut self, spheres: usize) -> SceneConfig {
+        self.spheres = spheres;
+        self
+    }
+    /// Set the number of bumpy heightfield meshes to generate
+    pub fn meshes(mut self, meshes: usize) -> SceneConfig {
+        self.meshes = meshes;
+        self
+    }
+    /// Set the number of instances of the prototype scene to place
+    pub fn instances(mut self, instances: usize) -> SceneConfig {
+        self.instances = instances;
+        self
+    }
+    /// Give the spheres and meshes a random linear motion over the shutter
+    /// interval, stored as two time steps
+    pub fn motion_blur(mut self, motion_blur: bool) -> SceneConfig {
+        self.motion_blur = motion_blur;
+        self
+    }
+    /// Set the tessellation resolution of the spheres and meshes
+    pub fn resolution(mut self, resolution: u32) -> SceneConfig {
+        self.resolution = resolution;
+        self
+    }
+    /// Set the half-width of the cube the objects are placed in
+    pub fn extent(mut self, extent: f32) -> SceneConfig {
+        self.extent = extent;
+        self
+    }
+    pub fn seed(mut self, seed: u64) -> SceneConfig {
+        self.seed = seed;
+        self
+    }
+    /// The number of triangles in the spheres and meshes of the scene,
+    /// excluding the ones in instances
+    pub fn triangle_count(&self) -> usize {
+        let r = self.resolution.max(2) as usize;
+        (self.spheres + self.meshes) * 4 * r * r
+    }
+}
+
+/// Build a triangle mesh on a (2 * res) x res vertex grid, with the
+/// vertex positions at each time step computed by `vertex` from the
+/// grid coordinates in [0, 1]^2 and the time step index.
+fn grid_mesh<'a, F>(device: &'a Device, res: u32, time_steps: u32, vertex: F) -> Geometry<'a>
+where
+    F: Fn(f32, f32, u32) -> Vector3<f32>,
+{
+    let nu = 2 * res;
+    let nv = res;
+    let num_verts = ((nu + 1) * (nv + 1)) as usize;
+    let num_tris = (2 * nu * nv) as usize;
+    let mut mesh = TriangleMesh::animated(device, num_tris, num_verts, time_steps);
+    for t in 0..time_steps {
+        let mut verts = mesh.time_step(t).map();
+        for j in 0..nv + 1 {
+            for i in 0..nu + 1 {
+                let p = vertex(i as f32 / nu as f32, j as f32 / nv as f32, t);
+                verts[(j * (nu + 1) + i) as usize] = p.extend(0.0);
+            }
+        }
+    }
+    {
+        let mut tris = mesh.index_buffer.map();
+        for j in 0..nv {
+            for i in 0..nu {
+                let v0 = j * (nu + 1) + i;
+                let v1 = v0 + 1;
+                let v2 = v0 + nu + 1;
+                let v3 = v2 + 1;
+                let q = (2 * (j * nu + i)) as usize;
+                tris[q] = Vector3::new(v0, v1, v3);
+                tris[q + 1] = Vector3::new(v0, v3, v2);
+            }
+        }
+    }
+    let mut geom = Geometry::Triangle(mesh);
+    geom.commit();
+    geom
+}
+
+fn sphere_point(center: Vector3<f32>, radius: f32, u: f32, v: f32) -> Vector3<f32> {
+    let phi = u * 2.0 * f32::consts::PI;
+    let theta = v * f32::consts::PI;
+    center
+        + radius
+            * Vector3::new(
+                theta.sin() * phi.cos(),
+                theta.cos(),
+                theta.sin() * phi.sin(),
+            )
+}
+
+/// Build a tessellated unit sphere centered at the origin, e.g. to use as
+/// the prototype scene which is instanced by `generate_scene`.
+pub fn prototype_scene<'a>(device: &'a Device, resolution: u32) -> Scene<'a> {
+    let mut scene = Scene::new(device);
+    scene.attach_geometry(grid_mesh(device, resolution, 1, |u, v, _| {
+        sphere_point(Vector3::new(0.0, 0.0, 0.0), 1.0, u, v)
+    }));
+    scene
+}
+
+/// Generate the scene described by the configuration, the geometry is
+/// committed and attached to the returned scene, which is ready to be
+/// committed. Spheres are attached first, followed by the meshes and
+/// then the instances, so geometry IDs are stable for a configuration.
+///
+/// Instances place randomly rotated and scaled copies of `prototype`,
+/// e.g. the scene returned by `prototype_scene`, and are not motion
+/// blurred. Panics if the configuration requests instances but no
+/// prototype is passed.
+pub fn generate_scene<'a>(
+    device: &'a Device,
+    config: &SceneConfig,
+    prototype: Option<&'a CommittedScene<'a>>,
+) -> Scene<'a> {
+    assert!(
+        config.instances == 0 || prototype.is_some(),
+        "a prototype scene is required to generate instances"
+    );
+    let mut rng = Pcg32::new(config.seed);
+    let mut scene = Scene::new(device);
+    let time_steps = if config.motion_blur { 2 } else { 1 };
+    let extent = config.extent;
+    let res = config.resolution.max(2);
+
+    for _ in 0..config.spheres {
+        let center = rng.point_in_cube(extent);
+        let radius = rng.range(0.02, 0.1) * extent;
+        let velocity = rng.point_in_cube(0.05 * extent);
+        scene.attach_geometry(grid_mesh(device, res, time_steps, |u, v, t| {
+            sphere_point(center + velocity * t as f32, radius, u, v)
+        }));
+    }
+
+    for _ in 0..config.meshes {
+        let corner = rng.point_in_cube(extent);
+        let size = rng.range(0.1, 0.4) * extent;
+        let height = rng.range(0.01, 0.1) * extent;
+        let freq = [rng.range(1.0, 6.0), rng.range(1.0, 6.0)];
+        let two_pi = 2.0 * f32::consts::PI;
+        let phase = [rng.range(0.0, two_pi), rng.range(0.0, two_pi)];
+        let velocity = rng.point_in_cube(0.05 * extent);
+        scene.attach_geometry(grid_mesh(device, res, time_steps, |u, v, t| {
+            let h = (u * freq[0] + phase[0]).sin() * (v * freq[1] + phase[1]).cos();
+            corner + Vector3::new(u * size, h * height, v * size) + velocity * t as f32
+        }));
+    }
+
+    if let Some(prototype) = prototype {
+        for _ in 0..config.instances {
+            let translation = rng.point_in_cube(extent);
+            let axis = Vector3::new(rng.range(-1.0, 1.0), rng.range(-1.0, 1.0), 1.0).normalize();
+            let angle = rng.range(0.0, 2.0 * f32::consts::PI);
+            let scale = rng.range(0.02, 0.1) * extent;
+            let transform = Matrix4::from_translation(translation)
+                * Matrix4::from_axis_angle(axis, Rad(angle))
+                * Matrix4::from_scale(scale);
+            let mut instance = Instance::unanimated(device, prototype);
+            instance.set_transform(&transform);
+            let mut geom = Geometry::Instance(instance);
+            geom.commit();
+            scene.attach_geometry(geom);
+        }
+    }
+    scene
+}
+
+#[test]
+fn test_pcg32_deterministic() {
+    let mut a = Pcg32::new(42);
+    let mut b = Pcg32::new(42);
+    let mut c = Pcg32::new(43);
+    let sa: Vec<u32> = (0..16).map(|_| a.next_u32()).collect();
+    let sb: Vec<u32> = (0..16).map(|_| b.next_u32()).collect();
+    let sc: Vec<u32> = (0..16).map(|_| c.next_u32()).collect();
+    assert_eq!(sa, sb);
+    assert_ne!(sa, sc);
+    for _ in 0..1000 {
+        let x = a.next_f32();
+        assert!((0.0..1.0).contains(&x));
+    }
+}
@@ -0,0 +1,188 @@
+//! Transform hierarchies for driving instances from an animation system.
+//! Each node of the hierarchy has a transform relative to its parent, and
+//! nodes can be bound to instance geometries in a scene. After changing
+//! the local transforms for a frame, `TransformHierarchy::update` composes
+//! the world transform of each node and updates and recommits only the
+//! instances whose world transform changed since the last update.
+
+use cgmath::Matrix4;
+
+use geometry::Geometry;
+use scene::Scene;
+
+/// Identifies a node of a `TransformHierarchy`. IDs are only returned by
+/// `TransformHierarchy::add_node`, so a node's parent always comes before
+/// it in the hierarchy.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct NodeId(u32);
+
+impl NodeId {
+    pub fn index(&self) -> u32 {
+        self.0
+    }
+}
+
+struct Node {
+    parent: Option<NodeId>,
+    local: Matrix4<f32>,
+    world: Matrix4<f32>,
+    /// The ID of the instance geometry driven by the node
+    instance: Option<u32>,
+    /// The world transform last set on the instance
+    applied: Option<Matrix4<f32>>,
+}
+
+/// A hierarchy of transforms, whose nodes can be bound to the instance
+/// geometries in a scene.
+#[derive(Default)]
+pub struct TransformHierarchy {
+    nodes: Vec<Node>,
+}
+
+impl TransformHierarchy {
+    pub fn new() -> TransformHierarchy {
+        TransformHierarchy::default()
+    }
+    /// Add a node with the transform `local` relative to its parent, or to
+    /// world space if it has no parent.
+    pub fn add_node(&mut self, parent: Option<NodeId>, local: Matrix4<f32>) -> NodeId {
+        let world = match parent {
+            Some(p) => self.node(p).world * local,
+            None => local,
+        };
+        self.nodes.push(Node {
+            parent,
+            local,
+            world,
+            instance: None,
+            applied: None,
+        });
+        NodeId(self.nodes.len() as u32 - 1)
+    }
+    /// Bind the node to the instance geometry with the ID `geom_id`, whose
+    /// transform will be set to the node's world transform by `update`.
+    /// Returns the instance previously bound to the node, if any.
+    pub fn bind_instance(&mut self, node: NodeId, geom_id: u32) -> Option<u32> {
+        let n = self.node_mut(node);
+        n.applied = None;
+        n.instance.replace(geom_id)
+    }
+    /// Unbind the node from its instance, returning the instance's ID
+    pub fn unbind_instance(&mut self, node: NodeId) -> Option<u32> {
+        let n = self.node_mut(node);
+        n.applied = None;
+        n.instance.take()
+    }
+    pub fn instance(&self, node: NodeId) -> Option<u32> {
+        self.node(node).instance
+    }
+    pub fn parent(&self, node: NodeId) -> Option<NodeId> {
+        self.node(node).parent
+    }
+    pub fn set_local_transform(&mut self, node: NodeId, local: Matrix4<f32>) {
+        self.node_mut(node).local = local;
+    }
+    pub fn local_transform(&self, node: NodeId) -> Matrix4<f32> {
+        self.node(node).local
+    }
+    /// Get the world transform of the node as of the last call to
+    /// `propagate` or `update`, or when the node was added
+    pub fn world_transform(&self, node: NodeId) -> Matrix4<f32> {
+        self.node(node).world
+    }
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+    /// Compose the world transforms of the nodes from their local
+    /// transforms, without updating the instances
+    pub fn propagate(&mut self) {
+        // Parents are always added before their children, so each
+        // parent's world transform is up to date when its children are
+        for i in 0..self.nodes.len() {
+            let world = match self.nodes[i].parent {
+                Some(p) => self.nodes[p.0 as usize].world * self.nodes[i].local,
+                None => self.nodes[i].local,
+            };
+            self.nodes[i].world = world;
+        }
+    }
+    /// Compose the world transforms of the nodes and set them on the
+    /// instances bound to nodes whose world transform changed since it was
+    /// last set. The changed instances are committed, after which the scene
+    /// must be committed to see the new transforms. Returns the number of
+    /// instances updated.
+    ///
+    /// Panics if a node is bound to a geometry which isn't an instance
+    /// attached to the scene.
+    pub fn update(&mut self, scene: &mut Scene) -> usize {
+        self.propagate();
+        let mut updated = 0;
+        for n in self.nodes.iter_mut() {
+            let id = match n.instance {
+                Some(id) if n.applied != Some(n.world) => id,
+                _ => continue,
+            };
+            let geom = scene
+                .geometry_by_raw_id_mut(id)
+                .unwrap_or_else(|| panic!("No geometry {} is attached to the scene", id));
+            match *geom {
+                Geometry::Instance(ref mut instance) => instance.set_transform(&n.world),
+                _ => panic!("Geometry {} is not an instance", id),
+            }
+            geom.commit();
+            n.applied = Some(n.world);
+            updated += 1;
+        }
+        updated
+    }
+    /// Get the node which is bound to the instance, if any
+    pub fn find_instance(&self, geom_id: u32) -> Option<NodeId> {
+        self.nodes
+            .iter()
+            .position(|n| n.instance == Some(geom_id))
+            .map(|i| NodeId(i as u32))
+    }
+    fn node(&self, id: NodeId) -> &Node {
+        &self.nodes[id.0 as usize]
+    }
+    fn node_mut(&mut self, id: NodeId) -> &mut Node {
+        &mut self.nodes[id.0 as usize]
+    }
+}
+
+#[test]
+fn test_propagate_world_transforms() {
+    use cgmath::{Rad, Vector3};
+
+    let mut h = TransformHierarchy::new();
+    let root = h.add_node(None, Matrix4::from_translation(Vector3::new(1.0, 0.0, 0.0)));
+    let arm = h.add_node(Some(root), Matrix4::from_angle_z(Rad(0.5)));
+    let hand = h.add_node(
+        Some(arm),
+        Matrix4::from_translation(Vector3::new(0.0, 2.0, 0.0)),
+    );
+    assert_eq!(h.parent(hand), Some(arm));
+    assert_eq!(
+        h.world_transform(hand),
+        h.local_transform(root) * h.local_transform(arm) * h.local_transform(hand)
+    );
+
+    // Moving the root moves its descendants once propagated
+    let moved = Matrix4::from_translation(Vector3::new(0.0, 0.0, 3.0));
+    h.set_local_transform(root, moved);
+    assert_ne!(h.world_transform(hand).w.z, 3.0);
+    h.propagate();
+    assert_eq!(
+        h.world_transform(hand),
+        moved * h.local_transform(arm) * h.local_transform(hand)
+    );
+    assert_eq!(h.world_transform(root), moved);
+
+    assert_eq!(h.bind_instance(hand, 4), None);
+    assert_eq!(h.find_instance(4), Some(hand));
+    assert_eq!(h.bind_instance(hand, 5), Some(4));
+    assert_eq!(h.find_instance(4), None);
+}
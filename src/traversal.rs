@@ -0,0 +1,103 @@
+//! The settings affecting how robustly rays traverse a scene, collected in
+//! one place. Embree doesn't have a configurable intersection epsilon, the
+//! knobs which affect whether rays are missed or hit the surface they
+//! start on are:
+//!
+//! - `robust`: enables `SceneFlags::ROBUST`, which keeps Embree from using
+//!   optimizations that reduce arithmetic accuracy, so rays through edges
+//!   and vertices shared by neighboring primitives aren't missed. Without
+//!   it, rays hitting exactly on a shared edge, e.g. grazing rays or rays
+//!   cast through mesh vertices, may fall through the mesh. Note that a
+//!   ray through a shared edge may then report a candidate hit on each
+//!   primitive sharing it, so filter functions can be called more than
+//!   once for the same surface crossing and must not count calls to find
+//!   the number of surfaces crossed.
+//! - `tnear_epsilon`: the start distance used for rays spawned from a
+//!   surface by `spawn_ray`, relative to the magnitude of the ray origin.
+//!   The hit point computed from a hit is only accurate to a few ulps of
+//!   its coordinates, so a secondary ray started at `tnear = 0` can hit
+//!   the surface it leaves. A fixed epsilon is either too small for
+//!   geometry far from the origin or too large for small geometry near
+//!   it, scaling it by the origin's magnitude avoids both.
+//!
+//! The settings are applied to a scene with `Scene::set_traversal_settings`.
+
+use cgmath::{InnerSpace, Vector3};
+use std::f32;
+
+use ray::Ray;
+
+/// Settings for the robustness of ray traversal in a scene, see the
+/// module documentation for their effects.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TraversalSettings {
+    pub robust: bool,
+    pub tnear_epsilon: f32,
+}
+
+impl Default for TraversalSettings {
+    fn default() -> TraversalSettings {
+        TraversalSettings {
+            robust: false,
+            tnear_epsilon: 1e-4,
+        }
+    }
+}
+
+impl TraversalSettings {
+    pub fn new() -> TraversalSettings {
+        TraversalSettings::default()
+    }
+    pub fn robust(mut self, robust: bool) -> TraversalSettings {
+        self.robust = robust;
+        self
+    }
+    /// Set the start distance of spawned rays relative to the magnitude of
+    /// their origin. Panics if the epsilon is negative.
+    pub fn tnear_epsilon(mut self, epsilon: f32) -> TraversalSettings {
+        assert!(epsilon >= 0.0, "tnear epsilon must not be negative");
+        self.tnear_epsilon = epsilon;
+        self
+    }
+    /// Get the tnear for a ray spawned at `origin` with direction `dir`,
+    /// the epsilon scaled by the largest coordinate of the origin, or 1 if
+    /// it's closer than that to the world origin, in units of `dir`'s length
+    pub fn spawn_tnear(&self, origin: Vector3<f32>, dir: Vector3<f32>) -> f32 {
+        let scale = origin
+            .x
+            .abs()
+            .max(origin.y.abs())
+            .max(origin.z.abs())
+            .max(1.0);
+        self.tnear_epsilon * scale / dir.magnitude()
+    }
+    /// Create a ray leaving a surface at `origin`, e.g. a shadow or
+    /// reflection ray from a hit point, which starts past the error in
+    /// the origin to avoid hitting the surface it leaves
+    pub fn spawn_ray(&self, origin: Vector3<f32>, dir: Vector3<f32>) -> Ray {
+        Ray::segment(origin, dir, self.spawn_tnear(origin, dir), f32::INFINITY)
+    }
+    /// Create a ray leaving a surface at `origin` towards the point
+    /// `target`, e.g. a light sample, ending just before the target
+    pub fn spawn_ray_to(&self, origin: Vector3<f32>, target: Vector3<f32>) -> Ray {
+        let dir = target - origin;
+        let tnear = self.spawn_tnear(origin, dir);
+        Ray::segment(origin, dir, tnear, 1.0 - self.spawn_tnear(target, dir))
+    }
+}
+
+#[test]
+fn test_spawn_tnear() {
+    let settings = TraversalSettings::new().tnear_epsilon(1e-3);
+    let dir = Vector3::new(0.0, 2.0, 0.0);
+    // Origins near the world origin use the epsilon as an absolute distance
+    let t = settings.spawn_tnear(Vector3::new(0.1, 0.5, -0.2), dir);
+    assert!((t * 2.0 - 1e-3).abs() < 1e-9);
+    // Origins far from it scale the epsilon by their largest coordinate
+    let t = settings.spawn_tnear(Vector3::new(10.0, -500.0, 3.0), dir);
+    assert!((t * 2.0 - 0.5).abs() < 1e-6);
+
+    let ray = settings.spawn_ray_to(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 4.0));
+    assert!((ray.tnear - 2.5e-4).abs() < 1e-9);
+    assert!((ray.tfar - (1.0 - 1e-3)).abs() < 1e-6);
+}
@@ -1,36 +1,79 @@
-use cgmath::{Vector3, Vector4};
+use cgmath::{Vector2, Vector3, Vector4};
 
-use buffer::Buffer;
+use buffer::{Buffer, VertexElement};
 use device::Device;
-use geometry::Geometry;
+use geometry::{self, MeshError};
+use ray::Hit;
 use sys::*;
+use validation::ValidationError;
 use {BufferType, Format, GeometryType};
 
 pub struct TriangleMesh<'a> {
     device: &'a Device,
     pub(crate) handle: RTCGeometry,
     pub vertex_buffer: Buffer<'a, Vector4<f32>>,
+    /// Vertex buffers for the time steps after the first of an animated
+    /// mesh, `vertex_buffer` holds the vertices for the first time step.
+    pub motion_vertex_buffers: Vec<Buffer<'a, Vector4<f32>>>,
     pub index_buffer: Buffer<'a, Vector3<u32>>,
+    /// Vertex attribute buffers, indexed by the attribute slot returned
+    /// by `add_vertex_attribute`
+    pub vertex_attribute_buffers: Vec<Buffer<'a, Vector4<f32>>>,
+}
+
+/// A vertex attribute value which can be read from the first components
+/// of the `Vector4<f32>` attribute buffers of a mesh
+pub trait AttributeValue: Copy {
+    fn from_vector4(v: Vector4<f32>) -> Self;
+}
+
+impl AttributeValue for f32 {
+    fn from_vector4(v: Vector4<f32>) -> f32 {
+        v.x
+    }
+}
+
+impl AttributeValue for Vector2<f32> {
+    fn from_vector4(v: Vector4<f32>) -> Vector2<f32> {
+        v.truncate().truncate()
+    }
+}
+
+impl AttributeValue for Vector3<f32> {
+    fn from_vector4(v: Vector4<f32>) -> Vector3<f32> {
+        v.truncate()
+    }
+}
+
+impl AttributeValue for Vector4<f32> {
+    fn from_vector4(v: Vector4<f32>) -> Vector4<f32> {
+        v
+    }
 }
 
 impl<'a> TriangleMesh<'a> {
+    /// The buffers which must be set before committing the geometry
+    pub const REQUIRED: &'static [BufferType] = &[BufferType::VERTEX, BufferType::INDEX];
+    /// The buffers the geometry can optionally use
+    pub const OPTIONAL: &'static [BufferType] = &[BufferType::VERTEX_ATTRIBUTE];
     pub fn unanimated(device: &'a Device, num_tris: usize, num_verts: usize) -> TriangleMesh<'a> {
-        let h = unsafe { rtcNewGeometry(device.handle, GeometryType::TRIANGLE) };
-        let mut vertex_buffer = Buffer::new(device, num_verts);
+        TriangleMesh::animated(device, num_tris, num_verts, 1)
+    }
+    /// Create a triangle mesh with `time_steps` vertex buffers for motion
+    /// blur, with the time steps spread uniformly over the [0, 1] shutter
+    /// interval. The mesh topology is shared between the time steps.
+    pub fn animated(
+        device: &'a Device,
+        num_tris: usize,
+        num_verts: usize,
+        time_steps: u32,
+    ) -> TriangleMesh<'a> {
+        assert!(time_steps > 0, "a mesh must have at least one time step");
+        let h = unsafe { geometry::new_handle(device, GeometryType::TRIANGLE) };
         let mut index_buffer = Buffer::new(device, num_tris);
+        let (vertex_buffer, motion_vertex_buffers) =
+            vertex_time_steps(device, h, num_verts, time_steps);
         unsafe {
-            rtcSetGeometryBuffer(
-                h,
-                BufferType::VERTEX,
-                0,
-                Format::FLOAT3,
-                vertex_buffer.handle,
-                0,
-                16,
-                num_verts,
-            );
-            vertex_buffer.set_attachment(h, BufferType::VERTEX, 0);
-
             rtcSetGeometryBuffer(
                 h,
                 BufferType::INDEX,
@@ -44,12 +87,183 @@ impl<'a> TriangleMesh<'a> {
             index_buffer.set_attachment(h, BufferType::INDEX, 0);
         }
         TriangleMesh {
-            device: device,
+            device,
             handle: h,
             vertex_buffer: vertex_buffer,
+            motion_vertex_buffers,
             index_buffer: index_buffer,
+            vertex_attribute_buffers: Vec::new(),
+        }
+    }
+    /// Create and commit a triangle mesh from slices of vertex positions
+    /// and triangle indices, copying the data into the mesh buffers. The
+    /// positions are padded out to the 16 byte stride the vertex buffer
+    /// uses, which also satisfies Embree's requirement that the last
+    /// vertex can be read with a 16 byte load. Returns an error if any
+    /// triangle references a vertex past the end of `positions`.
+    pub fn try_from_slices(
+        device: &'a Device,
+        positions: &[[f32; 3]],
+        indices: &[[u32; 3]],
+    ) -> Result<TriangleMesh<'a>, MeshError> {
+        geometry::validate_indices(indices, positions.len())?;
+        let mut mesh = TriangleMesh::unanimated(device, indices.len(), positions.len());
+        {
+            let mut verts = mesh.vertex_buffer.map();
+            for (i, p) in positions.iter().enumerate() {
+                verts[i] = Vector4::new(p[0], p[1], p[2], 0.0);
+            }
+            let mut tris = mesh.index_buffer.map();
+            for (i, t) in indices.iter().enumerate() {
+                tris[i] = Vector3::from(*t);
+            }
+        }
+        geometry::commit_handle(mesh.handle);
+        Ok(mesh)
+    }
+    /// Get the number of motion blur time steps of the mesh, 1 if it isn't
+    /// animated
+    pub fn time_step_count(&self) -> u32 {
+        1 + self.motion_vertex_buffers.len() as u32
+    }
+    /// Get the vertex buffer of time step `t`, which is `vertex_buffer` for
+    /// the first time step, e.g. to fill it through `time_step(t).map()`.
+    ///
+    /// Panics if `t` is not less than `time_step_count`.
+    pub fn time_step(&mut self, t: u32) -> &mut Buffer<'a, Vector4<f32>> {
+        assert!(
+            t < self.time_step_count(),
+            "Time step {} is out of range for a mesh with {} time steps",
+            t,
+            self.time_step_count()
+        );
+        match t {
+            0 => &mut self.vertex_buffer,
+            _ => &mut self.motion_vertex_buffers[t as usize - 1],
+        }
+    }
+    /// Copy the vertex positions of each motion blur time step into the
+    /// mesh, one slice per time step with the time steps spread uniformly
+    /// over the [0, 1] shutter interval. The vertex buffers are reallocated
+    /// if the number of time steps or vertices changed. The geometry must
+    /// be committed for the change to take effect.
+    ///
+    /// Returns `ValidationError::BufferSize` and leaves the mesh unchanged
+    /// if the time steps don't all have the same number of vertices.
+    /// Panics if there are no time steps.
+    pub fn set_time_steps<V: VertexElement>(
+        &mut self,
+        steps: &[&[V]],
+    ) -> Result<(), ValidationError> {
+        assert!(!steps.is_empty(), "a mesh must have at least one time step");
+        let num_verts = steps[0].len();
+        for (t, s) in steps.iter().enumerate() {
+            if s.len() != num_verts {
+                return Err(ValidationError::BufferSize {
+                    buf_type: BufferType::VERTEX,
+                    slot: t as u32,
+                    expected: num_verts,
+                    actual: s.len(),
+                });
+            }
+        }
+        let time_steps = steps.len() as u32;
+        if time_steps != self.time_step_count() || num_verts != self.vertex_buffer.len() {
+            let (first, rest) = vertex_time_steps(self.device, self.handle, num_verts, time_steps);
+            self.vertex_buffer = first;
+            self.motion_vertex_buffers = rest;
+        }
+        for (t, s) in steps.iter().enumerate() {
+            let mut verts = self.time_step(t as u32).map();
+            for (i, v) in s.iter().enumerate() {
+                verts[i] = Vector3::from(v.position()).extend(0.0);
+            }
+        }
+        geometry::mark_dirty(self.handle);
+        Ok(())
+    }
+    /// Add a vertex attribute with a value for each vertex of the mesh,
+    /// returning the slot of its buffer in `vertex_attribute_buffers`.
+    /// The geometry must be committed for the attribute to be used.
+    pub fn add_vertex_attribute(&mut self) -> u32 {
+        let slot = self.vertex_attribute_buffers.len() as u32;
+        let num_verts = self.vertex_buffer.len();
+        let mut buffer = Buffer::new(self.device, num_verts);
+        unsafe {
+            rtcSetGeometryVertexAttributeCount(self.handle, slot + 1);
+            rtcSetGeometryBuffer(
+                self.handle,
+                BufferType::VERTEX_ATTRIBUTE,
+                slot,
+                Format::FLOAT4,
+                buffer.handle,
+                0,
+                16,
+                num_verts,
+            );
+        }
+        buffer.set_attachment(self.handle, BufferType::VERTEX_ATTRIBUTE, slot);
+        geometry::mark_dirty(self.handle);
+        self.vertex_attribute_buffers.push(buffer);
+        slot
+    }
+    /// Interpolate the vertex attribute in `slot` at the hit point on the
+    /// mesh, computing the barycentric weighted sum of the attribute at the
+    /// triangle's vertices directly from the buffers. This is faster than
+    /// calling `rtcInterpolate` for each hit, but doesn't compute
+    /// derivatives. `T` selects how many components of the attribute are
+    /// interpolated, e.g. `Vector2<f32>` for texture coordinates.
+    ///
+    /// Returns `None` if the slot has no attribute buffer, or if shared
+    /// data was bound in place of the attribute or index buffer, e.g. with
+    /// `Geometry::set_shared_buffer_from_slice`, as the mesh's buffers then
+    /// no longer hold the data Embree uses. `rtcInterpolate` works for any
+    /// buffer. Panics if the hit's primitive isn't in the mesh.
+    pub fn interpolate_attribute_cpu<T: AttributeValue>(&self, hit: &Hit, slot: u32) -> Option<T> {
+        let attribs = self.vertex_attribute_buffers.get(slot as usize)?;
+        if !attribs.is_bound() || !self.index_buffer.is_bound() {
+            return None;
+        }
+        let attribs = attribs.as_slice();
+        let tri = self.index_buffer.as_slice()[hit.primID as usize];
+        let v = attribs[tri.x as usize] * (1.0 - hit.u - hit.v)
+            + attribs[tri.y as usize] * hit.u
+            + attribs[tri.z as usize] * hit.v;
+        Some(T::from_vector4(v))
+    }
+}
+
+/// Allocate a vertex buffer of `num_verts` vertices for each of the
+/// `time_steps` time steps of the geometry and bind them to its vertex
+/// slots, returning the first time step's buffer and those after it
+fn vertex_time_steps<'a>(
+    device: &'a Device,
+    h: RTCGeometry,
+    num_verts: usize,
+    time_steps: u32,
+) -> (Buffer<'a, Vector4<f32>>, Vec<Buffer<'a, Vector4<f32>>>) {
+    let mut buffers = Vec::new();
+    unsafe {
+        rtcSetGeometryTimeStepCount(h, time_steps);
+        for t in 0..time_steps {
+            let mut buf = Buffer::new(device, num_verts);
+            rtcSetGeometryBuffer(
+                h,
+                BufferType::VERTEX,
+                t,
+                Format::FLOAT3,
+                buf.handle,
+                0,
+                16,
+                num_verts,
+            );
+            buf.set_attachment(h, BufferType::VERTEX, t);
+            buffers.push(buf);
         }
     }
+    let first = buffers.remove(0);
+    (first, buffers)
 }
 
 unsafe impl<'a> Sync for TriangleMesh<'a> {}
+unsafe impl<'a> Send for TriangleMesh<'a> {}
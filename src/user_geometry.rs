@@ -0,0 +1,544 @@
+//! User geometries made of analytic shapes, intersected exactly by Rust
+//! code instead of being tessellated. Each primitive of a `UserGeometry`
+//! is a shape implementing `AnalyticShape`, which provides its bounds for
+//! Embree's BVH build and the points where a ray crosses its surface. The
+//! `analytic_shapes` module provides cones and capsules, common proxy
+//! shapes for collision and visibility queries.
+//!
+//! Intersection and occlusion queries are both supported, and filter
+//! functions set on the geometry are called for each candidate hit as for
+//! the built in geometry types. Hits report the shape's surface normal as
+//! `Ng` and `u` and `v` are 0.
+//!
+//! Primitives which aren't convex shapes, or which compute their own `u`
+//! and `v` or have a cheaper occlusion test, implement `UserPrimitive`
+//! instead and are put in a geometry with `UserGeometry::from_primitives`.
+//! The wrapper installs the bounds, intersection and occlusion callbacks
+//! and owns the primitives, so no unsafe code is needed:
+//!
+//! ```no_run
+//! # extern crate cgmath;
+//! # extern crate embree;
+//! # use cgmath::{InnerSpace, Vector3};
+//! # use embree::{Bounds, Device, Geometry, PrimitiveHit, Ray, UserGeometry, UserPrimitive};
+//! struct Sphere {
+//!     center: Vector3<f32>,
+//!     radius: f32,
+//! }
+//!
+//! impl UserPrimitive for Sphere {
+//!     fn bounds(&self) -> Bounds {
+//!         let (c, r) = (self.center, self.radius);
+//!         Bounds {
+//!             lower_x: c.x - r,
+//!             lower_y: c.y - r,
+//!             lower_z: c.z - r,
+//!             align0: 0.0,
+//!             upper_x: c.x + r,
+//!             upper_y: c.y + r,
+//!             upper_z: c.z + r,
+//!             align1: 0.0,
+//!         }
+//!     }
+//!     fn intersect(&self, ray: &Ray) -> Option<PrimitiveHit> {
+//!         let oc = ray.origin() - self.center;
+//!         let (a, b) = (ray.dir().magnitude2(), oc.dot(ray.dir()));
+//!         let disc = b * b - a * (oc.magnitude2() - self.radius * self.radius);
+//!         if disc < 0.0 {
+//!             return None;
+//!         }
+//!         let t = (-b - disc.sqrt()) / a;
+//!         Some(PrimitiveHit::new(t, oc + ray.dir() * t))
+//!     }
+//! }
+//!
+//! # let device = Device::new();
+//! let spheres = vec![Sphere { center: Vector3::new(0.0, 0.0, 0.0), radius: 1.0 }];
+//! let mut geom = Geometry::User(UserGeometry::from_primitives(&device, spheres));
+//! geom.commit();
+//! ```
+//!
+//! A panic in a primitive's `bounds` is held until the scene's commit
+//! returns and resumed there, and the primitive is left out of the BVH. A
+//! panic while intersecting or testing occlusion is held until the query
+//! returns as for filter functions, see the `filter` module.
+
+use std::marker::PhantomData;
+use std::os::raw;
+use std::panic::{self, AssertUnwindSafe};
+
+use cgmath::{InnerSpace, Vector3};
+
+use bvh::empty_bounds;
+use device::Device;
+use filter;
+use geometry;
+use ray::{Hit, Ray};
+use sys::*;
+use {Bounds, BufferType, GeometryType};
+
+/// A point where a ray crosses the surface of a shape, at distance `t`
+/// along the ray where the shape's outward facing normal is `normal`
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ShapeHit {
+    pub t: f32,
+    pub normal: Vector3<f32>,
+}
+
+/// A convex shape which can be intersected analytically. As the shape is
+/// convex, a line enters and leaves it at most once.
+pub trait AnalyticShape {
+    /// Get the bounds of the shape
+    fn bounds(&self) -> Bounds;
+    /// Find where the line through `org` in direction `dir` enters and
+    /// leaves the shape, returning the points ordered by distance along
+    /// the line. The distances are in units of `dir`'s length and can be
+    /// negative. Returns `None` if the line misses the shape. A line
+    /// touching the shape at a single point enters and leaves it there.
+    fn intersect_line(&self, org: Vector3<f32>, dir: Vector3<f32>) -> Option<[ShapeHit; 2]>;
+}
+
+/// A hit found by a `UserPrimitive`, at distance `t` along the ray where
+/// the primitive's surface normal is `normal` and its surface coordinates
+/// are `u` and `v`
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PrimitiveHit {
+    pub t: f32,
+    pub normal: Vector3<f32>,
+    pub u: f32,
+    pub v: f32,
+}
+
+impl PrimitiveHit {
+    /// Create a hit at distance `t` with the surface normal, whose `u` and
+    /// `v` are 0. The normal doesn't need to be normalized.
+    pub fn new(t: f32, normal: Vector3<f32>) -> PrimitiveHit {
+        PrimitiveHit {
+            t,
+            normal,
+            u: 0.0,
+            v: 0.0,
+        }
+    }
+    pub fn with_uv(mut self, u: f32, v: f32) -> PrimitiveHit {
+        self.u = u;
+        self.v = v;
+        self
+    }
+}
+
+/// A primitive of a user geometry intersected by Rust code, see
+/// `UserGeometry::from_primitives`. Unlike `AnalyticShape` the primitive
+/// can have any shape, and it finds the hit itself.
+pub trait UserPrimitive {
+    /// Get the bounds of the primitive
+    fn bounds(&self) -> Bounds;
+    /// Find the nearest hit of the ray on the primitive, returning `None`
+    /// if it misses. Hits outside the ray's `[tnear, tfar]` interval are
+    /// discarded by the wrapper. Filter functions set on the geometry are
+    /// called with the hit, and if they reject it the ray misses the
+    /// primitive.
+    fn intersect(&self, ray: &Ray) -> Option<PrimitiveHit>;
+    /// Whether the ray hits the primitive within its `[tnear, tfar]`
+    /// interval. By default this checks for a hit with `intersect`, and
+    /// can be overridden with a cheaper test. Occlusion filter functions
+    /// aren't called for user primitives.
+    fn occluded(&self, ray: &Ray) -> bool {
+        match self.intersect(ray) {
+            Some(h) => h.t >= ray.tnear && h.t <= ray.tfar,
+            None => false,
+        }
+    }
+}
+
+/// The shapes of a user geometry with their type erased, stored in the
+/// geometry's `GeometryData`
+pub(crate) trait ShapeSet: Send + Sync {
+    fn len(&self) -> usize;
+    fn bounds(&self, prim: usize) -> Bounds;
+    /// Find the candidate hits of the ray on the primitive which lie
+    /// within the ray's `[tnear, tfar]` interval, nearest first
+    fn candidates(&self, prim: usize, ray: &Ray) -> Vec<PrimitiveHit>;
+    /// Test whether the ray is occluded by the primitive without calling
+    /// filter functions, or return `None` to test each candidate hit
+    /// through the filters
+    fn occluded(&self, _prim: usize, _ray: &Ray) -> Option<bool> {
+        None
+    }
+}
+
+impl<S: AnalyticShape + Send + Sync> ShapeSet for Vec<S> {
+    fn len(&self) -> usize {
+        Vec::len(self)
+    }
+    fn bounds(&self, prim: usize) -> Bounds {
+        self[prim].bounds()
+    }
+    fn candidates(&self, prim: usize, ray: &Ray) -> Vec<PrimitiveHit> {
+        match self[prim].intersect_line(ray.origin(), ray.dir()) {
+            Some(hits) => {
+                let count = if hits[0].t == hits[1].t { 1 } else { 2 };
+                hits[..count]
+                    .iter()
+                    .filter(|h| h.t >= ray.tnear && h.t <= ray.tfar)
+                    .map(|h| PrimitiveHit::new(h.t, h.normal))
+                    .collect()
+            }
+            None => Vec::new(),
+        }
+    }
+}
+
+/// The primitives of a geometry made with `UserGeometry::from_primitives`,
+/// wrapped to keep their `ShapeSet` impl apart from the one for shapes
+struct Primitives<P>(Vec<P>);
+
+impl<P: UserPrimitive + Send + Sync> ShapeSet for Primitives<P> {
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+    fn bounds(&self, prim: usize) -> Bounds {
+        self.0[prim].bounds()
+    }
+    fn candidates(&self, prim: usize, ray: &Ray) -> Vec<PrimitiveHit> {
+        self.0[prim]
+            .intersect(ray)
+            .into_iter()
+            .filter(|h| h.t >= ray.tnear && h.t <= ray.tfar)
+            .collect()
+    }
+    fn occluded(&self, prim: usize, ray: &Ray) -> Option<bool> {
+        Some(self.0[prim].occluded(ray))
+    }
+}
+
+pub struct UserGeometry<'a> {
+    pub(crate) handle: RTCGeometry,
+    device: PhantomData<&'a Device>,
+}
+
+impl<'a> UserGeometry<'a> {
+    /// The buffers which must be set before committing the geometry
+    pub const REQUIRED: &'static [BufferType] = &[];
+    /// The buffers the geometry can optionally use
+    pub const OPTIONAL: &'static [BufferType] = &[];
+    /// Create a user geometry with a primitive for each shape. The
+    /// geometry must be committed before attaching it to a scene.
+    pub fn new<S>(device: &'a Device, shapes: Vec<S>) -> UserGeometry<'a>
+    where
+        S: AnalyticShape + Send + Sync + 'a,
+    {
+        let mut geom = UserGeometry::with_callbacks(device);
+        geom.set_shapes(shapes);
+        geom
+    }
+    /// Create a user geometry with a primitive for each `UserPrimitive`,
+    /// which the geometry owns. The geometry must be committed before
+    /// attaching it to a scene.
+    pub fn from_primitives<P>(device: &'a Device, primitives: Vec<P>) -> UserGeometry<'a>
+    where
+        P: UserPrimitive + Send + Sync + 'a,
+    {
+        let mut geom = UserGeometry::with_callbacks(device);
+        geom.set_primitives(primitives);
+        geom
+    }
+    fn with_callbacks(device: &'a Device) -> UserGeometry<'a> {
+        let h = unsafe { geometry::new_handle(device, GeometryType::USER) };
+        unsafe {
+            let data = geometry::data_ptr(h);
+            rtcSetGeometryBoundsFunction(h, Some(shape_bounds), data as *mut raw::c_void);
+            rtcSetGeometryIntersectFunction(h, Some(intersect_shapes));
+            rtcSetGeometryOccludedFunction(h, Some(occluded_shapes));
+        }
+        UserGeometry {
+            handle: h,
+            device: PhantomData,
+        }
+    }
+    /// Replace the shapes of the geometry, which must be committed again
+    /// for the change to take effect
+    pub fn set_shapes<S>(&mut self, shapes: Vec<S>)
+    where
+        S: AnalyticShape + Send + Sync + 'a,
+    {
+        self.set_shape_set(shapes.len(), Box::new(shapes));
+    }
+    /// Replace the primitives of the geometry, which must be committed
+    /// again for the change to take effect
+    pub fn set_primitives<P>(&mut self, primitives: Vec<P>)
+    where
+        P: UserPrimitive + Send + Sync + 'a,
+    {
+        self.set_shape_set(primitives.len(), Box::new(Primitives(primitives)));
+    }
+    fn set_shape_set(&mut self, len: usize, shapes: Box<dyn ShapeSet + 'a>) {
+        geometry::mark_dirty(self.handle);
+        unsafe {
+            rtcSetGeometryUserPrimitiveCount(self.handle, len as u32);
+            (*geometry::data_ptr(self.handle)).user_shapes = Some(shapes);
+        }
+    }
+    /// Get the number of shapes or primitives in the geometry
+    pub fn len(&self) -> usize {
+        self.shapes().len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// Get the bounds of the shape or primitive `prim`
+    pub fn bounds(&self, prim: u32) -> Bounds {
+        self.shapes().bounds(prim as usize)
+    }
+    fn shapes(&self) -> &dyn ShapeSet {
+        unsafe {
+            (*geometry::data_ptr(self.handle))
+                .user_shapes
+                .as_ref()
+                .unwrap()
+                .as_ref()
+        }
+    }
+}
+
+// The shapes are `ShapeSet`s, which are `Send + Sync`
+unsafe impl<'a> Sync for UserGeometry<'a> {}
+unsafe impl<'a> Send for UserGeometry<'a> {}
+
+/// The most lanes Embree passes to a user geometry's functions at once
+const MAX_LANES: usize = 16;
+
+unsafe fn shapes_of<'s>(user_ptr: *mut raw::c_void) -> &'s dyn ShapeSet {
+    (*(user_ptr as *const filter::GeometryData))
+        .user_shapes
+        .as_ref()
+        .unwrap()
+        .as_ref()
+}
+
+/// Write the bounds of the primitive, or empty bounds leaving it out of
+/// the BVH if its bounds panic. The panic is held on the geometry until
+/// the commit building the BVH returns, which may run this on Embree's
+/// build threads.
+unsafe extern "C" fn shape_bounds(args: *const RTCBoundsFunctionArguments) {
+    let args = &*args;
+    let data = &*(args.geometryUserPtr as *const filter::GeometryData);
+    let shapes = shapes_of(args.geometryUserPtr);
+    let bounds = panic::catch_unwind(AssertUnwindSafe(|| shapes.bounds(args.primID as usize)));
+    *args.bounds_o = match bounds {
+        Ok(b) => b,
+        Err(p) => {
+            data.build_panic.lock().unwrap().get_or_insert(p);
+            empty_bounds()
+        }
+    };
+}
+
+/// Write `hit` to lane `i` of the N wide SoA hit packet
+unsafe fn set_hit_n(hit_n: *mut RTCHitN, n: usize, i: usize, hit: &Hit) {
+    let f = hit_n as *mut f32;
+    let u = hit_n as *mut u32;
+    *f.add(i) = hit.Ng_x;
+    *f.add(n + i) = hit.Ng_y;
+    *f.add(2 * n + i) = hit.Ng_z;
+    *f.add(3 * n + i) = hit.u;
+    *f.add(4 * n + i) = hit.v;
+    *u.add(5 * n + i) = hit.primID;
+    *u.add(6 * n + i) = hit.geomID;
+    *u.add(7 * n + i) = hit.instID[0];
+}
+
+fn shape_hit(prim: u32, geom_id: u32, ctx: *const RTCIntersectContext, h: &PrimitiveHit) -> Hit {
+    let n = h.normal.normalize();
+    RTCHit {
+        Ng_x: n.x,
+        Ng_y: n.y,
+        Ng_z: n.z,
+        u: h.u,
+        v: h.v,
+        primID: prim,
+        geomID: geom_id,
+        instID: unsafe { (*ctx).instID },
+    }
+}
+
+/// The `tfar` of lane `i` of the N wide SoA ray packet
+unsafe fn tfar_n(ray_n: *mut RTCRayN, n: usize, i: usize) -> *mut f32 {
+    (ray_n as *mut f32).add(8 * n + i)
+}
+
+unsafe extern "C" fn intersect_shapes(args: *const RTCIntersectFunctionNArguments) {
+    filter::catch_panic(|| intersect_shapes_n(&*args));
+}
+
+unsafe fn intersect_shapes_n(args: &RTCIntersectFunctionNArguments) {
+    let n = args.N as usize;
+    if n > MAX_LANES {
+        return;
+    }
+    let shapes = shapes_of(args.geometryUserPtr);
+    let ray_n = args.rayhit as *mut RTCRayN;
+    // The hits of the rays follow their 12 word rays in the packet
+    let hit_n = (args.rayhit as *mut u32).add(12 * n) as *mut RTCHitN;
+    for i in 0..n {
+        if *args.valid.add(i) == 0 {
+            continue;
+        }
+        let ray = filter::ray_n(ray_n, n, i);
+        for c in shapes.candidates(args.primID as usize, &ray) {
+            let hit = shape_hit(args.primID, args.geomID, args.context, &c);
+            // Filters are passed the candidate in a packet of the same
+            // width as the rays, with only this ray's lane valid
+            let mut valid = [0; MAX_LANES];
+            valid[i] = -1;
+            let mut candidate = [0u32; 8 * MAX_LANES];
+            let candidate_n = candidate.as_mut_ptr() as *mut RTCHitN;
+            set_hit_n(candidate_n, n, i, &hit);
+            // The candidate's distance is passed as the ray's tfar
+            let tfar = tfar_n(ray_n, n, i);
+            let prev_tfar = *tfar;
+            *tfar = c.t;
+            let fargs = RTCFilterFunctionNArguments {
+                valid: valid.as_mut_ptr(),
+                geometryUserPtr: args.geometryUserPtr,
+                context: args.context,
+                ray: ray_n,
+                hit: candidate_n,
+                N: args.N,
+            };
+            rtcFilterIntersection(args, &fargs);
+            if valid[i] != 0 {
+                set_hit_n(hit_n, n, i, &filter::hit_n(candidate_n, n, i));
+                break;
+            }
+            *tfar = prev_tfar;
+        }
+    }
+}
+
+unsafe extern "C" fn occluded_shapes(args: *const RTCOccludedFunctionNArguments) {
+    filter::catch_panic(|| occluded_shapes_n(&*args));
+}
+
+unsafe fn occluded_shapes_n(args: &RTCOccludedFunctionNArguments) {
+    let n = args.N as usize;
+    if n > MAX_LANES {
+        return;
+    }
+    let shapes = shapes_of(args.geometryUserPtr);
+    for i in 0..n {
+        if *args.valid.add(i) == 0 {
+            continue;
+        }
+        let ray = filter::ray_n(args.ray, n, i);
+        if let Some(occluded) = shapes.occluded(args.primID as usize, &ray) {
+            if occluded {
+                // Occluded rays are marked by setting tfar to -inf
+                *tfar_n(args.ray, n, i) = f32::NEG_INFINITY;
+            }
+            continue;
+        }
+        for c in shapes.candidates(args.primID as usize, &ray) {
+            let hit = shape_hit(args.primID, args.geomID, args.context, &c);
+            let mut valid = [0; MAX_LANES];
+            valid[i] = -1;
+            let mut candidate = [0u32; 8 * MAX_LANES];
+            let candidate_n = candidate.as_mut_ptr() as *mut RTCHitN;
+            set_hit_n(candidate_n, n, i, &hit);
+            let tfar = tfar_n(args.ray, n, i);
+            let prev_tfar = *tfar;
+            *tfar = c.t;
+            let fargs = RTCFilterFunctionNArguments {
+                valid: valid.as_mut_ptr(),
+                geometryUserPtr: args.geometryUserPtr,
+                context: args.context,
+                ray: args.ray,
+                hit: candidate_n,
+                N: args.N,
+            };
+            rtcFilterOcclusion(args, &fargs);
+            if valid[i] != 0 {
+                // Occluded rays are marked by setting tfar to -inf
+                *tfar = f32::NEG_INFINITY;
+                break;
+            }
+            *tfar = prev_tfar;
+        }
+    }
+}
+
+#[test]
+fn test_primitive_candidates_in_interval() {
+    struct Plane;
+    impl UserPrimitive for Plane {
+        fn bounds(&self) -> Bounds {
+            Bounds {
+                lower_x: -1.0,
+                lower_y: -1.0,
+                lower_z: 0.0,
+                align0: 0.0,
+                upper_x: 1.0,
+                upper_y: 1.0,
+                upper_z: 0.0,
+                align1: 0.0,
+            }
+        }
+        fn intersect(&self, ray: &Ray) -> Option<PrimitiveHit> {
+            let t = -ray.org_z / ray.dir_z;
+            Some(PrimitiveHit::new(t, Vector3::new(0.0, 0.0, 1.0)).with_uv(0.25, 0.5))
+        }
+    }
+    let prims = Primitives(vec![Plane]);
+    let dir = Vector3::new(0.0, 0.0, -1.0);
+    let ray = Ray::new(Vector3::new(0.0, 0.0, 2.0), dir);
+    let hits = prims.candidates(0, &ray);
+    assert_eq!(
+        hits,
+        vec![PrimitiveHit::new(2.0, dir * -1.0).with_uv(0.25, 0.5)]
+    );
+    assert_eq!(prims.occluded(0, &ray), Some(true));
+    // Hits past tfar are discarded, also by the default occlusion test
+    let short = Ray::segment(Vector3::new(0.0, 0.0, 2.0), dir, 0.0, 1.0);
+    assert!(prims.candidates(0, &short).is_empty());
+    assert_eq!(prims.occluded(0, &short), Some(false));
+}
+
+#[test]
+fn test_bounds_panic_is_held() {
+    struct Broken;
+    impl UserPrimitive for Broken {
+        fn bounds(&self) -> Bounds {
+            panic!("bounds panicked")
+        }
+        fn intersect(&self, _: &Ray) -> Option<PrimitiveHit> {
+            None
+        }
+    }
+    let mut data = filter::GeometryData {
+        user_shapes: Some(Box::new(Primitives(vec![Broken]))),
+        ..Default::default()
+    };
+    let mut bounds = Bounds {
+        lower_x: 0.0,
+        lower_y: 0.0,
+        lower_z: 0.0,
+        align0: 0.0,
+        upper_x: 0.0,
+        upper_y: 0.0,
+        upper_z: 0.0,
+        align1: 0.0,
+    };
+    let args = RTCBoundsFunctionArguments {
+        geometryUserPtr: &mut data as *mut filter::GeometryData as *mut raw::c_void,
+        primID: 0,
+        timeStep: 0,
+        bounds_o: &mut bounds,
+    };
+    unsafe {
+        shape_bounds(&args);
+    }
+    // The primitive is left out of the BVH and the panic held for commit
+    assert!(bounds.lower_x > bounds.upper_x);
+    let p = data.build_panic.lock().unwrap().take().unwrap();
+    assert_eq!(p.downcast_ref::<&str>(), Some(&"bounds panicked"));
+}
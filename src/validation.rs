@@ -0,0 +1,381 @@
+//! Checks of a geometry's buffers before committing it, reporting exactly
+//! which buffer is missing or inconsistent instead of the generic errors
+//! Embree gives on commit or the crashes from out of bounds indices.
+//! Each geometry kind lists the buffers it requires and can optionally
+//! take in its `REQUIRED` and `OPTIONAL` associated constants.
+//!
+//! `Scene::validate` checks the scene against Embree's limits on the number
+//! of geometries and how deeply instances are nested, which Embree doesn't
+//! report but silently misbehaves past, e.g. by wrapping primitive and
+//! geometry IDs around to the invalid ID.
+
+use std::{error, fmt};
+
+#[cfg(feature = "curves")]
+use cgmath::Vector4;
+
+use buffer::Buffer;
+use geometry::{self, Geometry, MeshError};
+use scene::Scene;
+use sys::*;
+use BufferType;
+
+/// The most primitives a geometry can have, the primitive IDs go up to
+/// but don't include `u32::MAX`, which is Embree's invalid ID
+pub const MAX_PRIMITIVES: usize = u32::MAX as usize - 1;
+/// The most geometries a scene can have, the geometry IDs go up to but
+/// don't include `u32::MAX`, which is Embree's invalid ID
+pub const MAX_GEOMETRIES: usize = u32::MAX as usize - 1;
+/// The most levels of instances Embree was built to trace through, an
+/// instance of a scene without instances is one level
+pub const MAX_INSTANCE_DEPTH: usize = RTC_MAX_INSTANCE_LEVEL_COUNT as usize;
+
+/// A problem found by `Geometry::validate` or `Scene::validate`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ValidationError {
+    /// A buffer required by the geometry kind isn't set
+    MissingBuffer { buf_type: BufferType, slot: u32 },
+    /// A buffer doesn't have the number of elements it must have to match
+    /// the other buffers of the geometry
+    BufferSize {
+        buf_type: BufferType,
+        slot: u32,
+        expected: usize,
+        actual: usize,
+    },
+    /// A primitive references a vertex past the end of the vertex buffer
+    IndexOutOfBounds {
+        primitive: usize,
+        index: u32,
+        num_verts: usize,
+    },
+    /// A subdivision mesh face has fewer than three vertices
+    DegenerateFace { face: usize, num_verts: u32 },
+    /// A grid of a grid mesh is narrower than two vertices on a side
+    DegenerateGrid {
+        grid: usize,
+        width: u16,
+        height: u16,
+    },
+    /// A geometry has more primitives than Embree has IDs for
+    TooManyPrimitives { count: usize, max: usize },
+    /// A scene has more geometries than Embree has IDs for
+    TooManyGeometries { count: usize, max: usize },
+    /// Instances are nested deeper than the levels Embree was built with
+    InstanceDepth { depth: usize, max: usize },
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ValidationError::MissingBuffer { buf_type, slot } => {
+                write!(
+                    f,
+                    "required {:?} buffer in slot {} is not set",
+                    buf_type, slot
+                )
+            }
+            ValidationError::BufferSize {
+                buf_type,
+                slot,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "{:?} buffer in slot {} has {} elements but {} are required",
+                buf_type, slot, actual, expected
+            ),
+            ValidationError::IndexOutOfBounds {
+                primitive,
+                index,
+                num_verts,
+            } => write!(
+                f,
+                "primitive {} references vertex {} but the geometry has {} vertices",
+                primitive, index, num_verts
+            ),
+            ValidationError::DegenerateFace { face, num_verts } => write!(
+                f,
+                "face {} has {} vertices, faces need at least 3",
+                face, num_verts
+            ),
+            ValidationError::DegenerateGrid {
+                grid,
+                width,
+                height,
+            } => write!(
+                f,
+                "grid {} is {}x{} vertices, grids need at least 2x2",
+                grid, width, height
+            ),
+            ValidationError::TooManyPrimitives { count, max } => write!(
+                f,
+                "geometry has {} primitives but Embree supports at most {}, \
+                 split it into several geometries",
+                count, max
+            ),
+            ValidationError::TooManyGeometries { count, max } => write!(
+                f,
+                "scene has {} geometries but Embree supports at most {}, \
+                 group them into scenes placed with instances",
+                count, max
+            ),
+            ValidationError::InstanceDepth { depth, max } => write!(
+                f,
+                "instances are nested {} levels deep but Embree was built to \
+                 trace through {}, flatten the nested instances or build Embree \
+                 with a larger EMBREE_MAX_INSTANCE_LEVEL_COUNT",
+                depth, max
+            ),
+        }
+    }
+}
+
+impl error::Error for ValidationError {}
+
+impl From<MeshError> for ValidationError {
+    fn from(e: MeshError) -> ValidationError {
+        match e {
+            MeshError::IndexOutOfBounds {
+                primitive,
+                index,
+                num_verts,
+            } => ValidationError::IndexOutOfBounds {
+                primitive,
+                index,
+                num_verts,
+            },
+        }
+    }
+}
+
+fn check_size<T>(
+    buf: &Buffer<T>,
+    buf_type: BufferType,
+    slot: u32,
+    expected: usize,
+) -> Result<(), ValidationError> {
+    if buf.is_bound() && buf.len() != expected {
+        Err(ValidationError::BufferSize {
+            buf_type,
+            slot,
+            expected,
+            actual: buf.len(),
+        })
+    } else {
+        Ok(())
+    }
+}
+
+/// Check the curve segments starting at each index, which use `n` control
+/// points, are in bounds of the vertex buffer
+#[cfg(feature = "curves")]
+fn check_segments(
+    verts: &Buffer<Vector4<f32>>,
+    indices: &Buffer<u32>,
+    n: u32,
+) -> Result<(), ValidationError> {
+    if !verts.is_bound() || !indices.is_bound() {
+        return Ok(());
+    }
+    let num_verts = verts.len();
+    for (primitive, start) in indices.as_slice().iter().enumerate() {
+        let last = *start as usize + n as usize - 1;
+        if last >= num_verts {
+            return Err(ValidationError::IndexOutOfBounds {
+                primitive,
+                index: last as u32,
+                num_verts,
+            });
+        }
+    }
+    Ok(())
+}
+
+fn check_optional<T>(
+    buf: &Option<Buffer<T>>,
+    buf_type: BufferType,
+    expected: usize,
+) -> Result<(), ValidationError> {
+    match *buf {
+        Some(ref b) => check_size(b, buf_type, 0, expected),
+        None => Ok(()),
+    }
+}
+
+/// Get how many levels of instances the scene contains, 0 if it has no
+/// instances
+pub(crate) fn instance_depth(scene: &Scene) -> usize {
+    scene
+        .iter()
+        .filter_map(|(_, g)| match *g {
+            Geometry::Instance(ref i) => Some(1 + instance_depth(i.scene.scene)),
+            _ => None,
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+pub(crate) fn validate_scene(scene: &Scene) -> Result<(), ValidationError> {
+    let count = scene.iter().len();
+    if count > MAX_GEOMETRIES {
+        return Err(ValidationError::TooManyGeometries {
+            count,
+            max: MAX_GEOMETRIES,
+        });
+    }
+    let depth = instance_depth(scene);
+    if depth > MAX_INSTANCE_DEPTH {
+        return Err(ValidationError::InstanceDepth {
+            depth,
+            max: MAX_INSTANCE_DEPTH,
+        });
+    }
+    Ok(())
+}
+
+pub(crate) fn validate(geom: &Geometry) -> Result<(), ValidationError> {
+    let count = geom.primitive_count();
+    if count > MAX_PRIMITIVES {
+        return Err(ValidationError::TooManyPrimitives {
+            count,
+            max: MAX_PRIMITIVES,
+        });
+    }
+    let h = geom.handle();
+    for &buf_type in geom.required_buffers() {
+        if unsafe { rtcGetGeometryBufferData(h, buf_type, 0) }.is_null() {
+            return Err(ValidationError::MissingBuffer { buf_type, slot: 0 });
+        }
+    }
+    match *geom {
+        Geometry::Triangle(ref m) => {
+            let num_verts = m.vertex_buffer.len();
+            for (i, b) in m.motion_vertex_buffers.iter().enumerate() {
+                check_size(b, BufferType::VERTEX, i as u32 + 1, num_verts)?;
+            }
+            if m.vertex_buffer.is_bound() && m.index_buffer.is_bound() {
+                let tris: Vec<[u32; 3]> = m
+                    .index_buffer
+                    .as_slice()
+                    .iter()
+                    .map(|t| [t.x, t.y, t.z])
+                    .collect();
+                geometry::validate_indices(&tris, num_verts)?;
+            }
+        }
+        Geometry::Quad(ref m) => {
+            if m.vertex_buffer.is_bound() && m.index_buffer.is_bound() {
+                let quads: Vec<[u32; 4]> = m
+                    .index_buffer
+                    .as_slice()
+                    .iter()
+                    .map(|q| [q.x, q.y, q.z, q.w])
+                    .collect();
+                geometry::validate_indices(&quads, m.vertex_buffer.len())?;
+            }
+        }
+        Geometry::Grid(ref m) => {
+            if m.vertex_buffer.is_bound() && m.grid_buffer.is_bound() {
+                let num_verts = m.vertex_buffer.len();
+                for (primitive, g) in m.grid_buffer.as_slice().iter().enumerate() {
+                    if g.width < 2 || g.height < 2 {
+                        return Err(ValidationError::DegenerateGrid {
+                            grid: primitive,
+                            width: g.width,
+                            height: g.height,
+                        });
+                    }
+                    let index = g.last_vertex_id();
+                    if index as usize >= num_verts {
+                        return Err(ValidationError::IndexOutOfBounds {
+                            primitive,
+                            index,
+                            num_verts,
+                        });
+                    }
+                }
+            }
+        }
+        Geometry::Point(ref p) => {
+            check_optional(&p.normal_buffer, BufferType::NORMAL, p.vertex_buffer.len())?;
+        }
+        #[cfg(feature = "subdivision")]
+        Geometry::Subdivision(ref m) => {
+            if m.face_buffer.is_bound() {
+                let mut num_indices = 0;
+                for (face, &n) in m.face_buffer.as_slice().iter().enumerate() {
+                    if n < 3 {
+                        return Err(ValidationError::DegenerateFace { face, num_verts: n });
+                    }
+                    num_indices += n as usize;
+                }
+                for t in 0..m.num_topologies() as u32 {
+                    let indices = m.index_buffer(t);
+                    check_size(indices, BufferType::INDEX, t, num_indices)?;
+                }
+            }
+            if m.vertex_buffer.is_bound() && m.index_buffer(0).is_bound() {
+                let num_verts = m.vertex_buffer.len();
+                for (primitive, &index) in m.index_buffer(0).as_slice().iter().enumerate() {
+                    if index as usize >= num_verts {
+                        return Err(ValidationError::IndexOutOfBounds {
+                            primitive,
+                            index,
+                            num_verts,
+                        });
+                    }
+                }
+            }
+        }
+        #[cfg(feature = "curves")]
+        Geometry::LinearCurve(ref c) => {
+            check_size(&c.flag_buffer, BufferType::FLAGS, 0, c.index_buffer.len())?;
+            check_optional(&c.normal_buffer, BufferType::NORMAL, c.vertex_buffer.len())?;
+            check_segments(&c.vertex_buffer, &c.index_buffer, 2)?;
+        }
+        #[cfg(feature = "curves")]
+        Geometry::BezierCurve(ref c) => {
+            check_optional(&c.normal_buffer, BufferType::NORMAL, c.vertex_buffer.len())?;
+            check_segments(&c.vertex_buffer, &c.index_buffer, 4)?;
+        }
+        #[cfg(feature = "curves")]
+        Geometry::BsplineCurve(ref c) => {
+            check_optional(&c.normal_buffer, BufferType::NORMAL, c.vertex_buffer.len())?;
+            check_segments(&c.vertex_buffer, &c.index_buffer, 4)?;
+        }
+        #[cfg(feature = "curves")]
+        Geometry::CatmullRomCurve(ref c) => {
+            check_optional(&c.normal_buffer, BufferType::NORMAL, c.vertex_buffer.len())?;
+            check_segments(&c.vertex_buffer, &c.index_buffer, 4)?;
+        }
+        #[cfg(feature = "curves")]
+        Geometry::HermiteCurve(ref c) => {
+            let num_verts = c.vertex_buffer.len();
+            check_size(&c.tangent_buffer, BufferType::TANGENT, 0, num_verts)?;
+            check_optional(&c.normal_buffer, BufferType::NORMAL, num_verts)?;
+            check_optional(
+                &c.normal_derivative_buffer,
+                BufferType::NORMAL_DERIVATIVE,
+                num_verts,
+            )?;
+            check_segments(&c.vertex_buffer, &c.index_buffer, 2)?;
+        }
+        Geometry::Instance(_) | Geometry::User(_) => {}
+    }
+    Ok(())
+}
+
+#[test]
+fn test_limit_messages() {
+    let err = ValidationError::TooManyPrimitives {
+        count: MAX_PRIMITIVES + 1,
+        max: MAX_PRIMITIVES,
+    };
+    assert!(err.to_string().contains("split it into several geometries"));
+    let err = ValidationError::InstanceDepth {
+        depth: MAX_INSTANCE_DEPTH + 1,
+        max: MAX_INSTANCE_DEPTH,
+    };
+    assert!(err.to_string().contains("EMBREE_MAX_INSTANCE_LEVEL_COUNT"));
+}
@@ -0,0 +1,48 @@
+#![cfg(feature = "async")]
+
+extern crate cgmath;
+extern crate embree;
+extern crate tokio;
+
+mod common;
+
+use cgmath::Vector3;
+use embree::{Device, Ray, Scene};
+use tokio::runtime::Runtime;
+
+#[test]
+fn commit_and_query_async() {
+    let device: &'static Device = Box::leak(Box::new(Device::new()));
+    let runtime = Runtime::new().unwrap();
+    let mut scene = Scene::new(device);
+    let id = scene.attach_geometry(common::committed_triangle(
+        device,
+        common::centered_triangle(0.0),
+    ));
+
+    let scene = runtime.block_on(scene.commit_async());
+    assert_eq!(scene.scene().commit_token(), Some(scene.token()));
+
+    let dir = Vector3::new(0.0, 0.0, -1.0);
+    let rays = vec![
+        Ray::new(Vector3::new(0.0, 0.0, 1.0), dir),
+        Ray::new(Vector3::new(5.0, 0.0, 1.0), dir),
+    ];
+    let hits = runtime.block_on(scene.intersect_async(rays.clone()));
+    assert_eq!(hits.len(), 2);
+    assert_eq!(hits[0].hit.geomID, id);
+    assert_eq!(hits[1].hit.geomID, u32::MAX);
+    let occluded = runtime.block_on(scene.occluded_async(rays));
+    assert_eq!(occluded, vec![true, false]);
+
+    // The scene can only be taken back once the other handles are dropped
+    let other = scene.clone();
+    let scene = scene.into_scene().err().unwrap();
+    drop(other);
+    let mut scene = scene.into_scene().ok().unwrap();
+    scene.deattach_geometry(id);
+    let scene = runtime.block_on(scene.commit_async());
+    let hits =
+        runtime.block_on(scene.intersect_async(vec![Ray::new(Vector3::new(0.0, 0.0, 1.0), dir)]));
+    assert_eq!(hits[0].hit.geomID, u32::MAX);
+}
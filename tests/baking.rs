@@ -0,0 +1,65 @@
+extern crate cgmath;
+extern crate embree;
+
+use cgmath::{Vector3, Vector4};
+use embree::{AoBaker, Device, Geometry, Scene, TriangleMesh};
+
+#[test]
+fn bake_occlusion_under_overhang() {
+    let device = Device::new();
+    // A unit square on the ground, with UVs matching its xy coordinates
+    let mut ground = TriangleMesh::unanimated(&device, 2, 4);
+    let uv = ground.add_vertex_attribute();
+    {
+        let corners = [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)];
+        let mut verts = ground.vertex_buffer.map();
+        let mut uvs = ground.vertex_attribute_buffers[uv as usize].map();
+        for (i, &(x, y)) in corners.iter().enumerate() {
+            verts[i] = Vector4::new(x, y, 0.0, 0.0);
+            uvs[i] = Vector4::new(x, y, 0.0, 0.0);
+        }
+        let mut tris = ground.index_buffer.map();
+        tris[0] = Vector3::new(0, 1, 2);
+        tris[1] = Vector3::new(0, 2, 3);
+    }
+    let mut ground = Geometry::Triangle(ground);
+    ground.commit();
+    // A large overhang just above the right half of the square
+    let overhang = TriangleMesh::try_from_slices(
+        &device,
+        &[
+            [0.5, -100.0, 0.05],
+            [100.0, -100.0, 0.05],
+            [100.0, 100.0, 0.05],
+            [0.5, 100.0, 0.05],
+        ],
+        &[[0, 1, 2], [0, 2, 3]],
+    )
+    .unwrap();
+
+    let mut scene = Scene::new(&device);
+    let ground_id = scene.attach_geometry(ground);
+    let overhang_id = scene.attach_geometry(Geometry::Triangle(overhang));
+    let rtscene = scene.commit();
+
+    let baker = AoBaker::new(4, 4).samples(256).seed(5);
+    let ao = baker.bake(&rtscene, ground_id, uv).unwrap();
+    assert_eq!((ao.width, ao.height), (4, 4));
+    assert!(ao.coverage.iter().all(|c| *c));
+    for y in 0..4 {
+        assert!(ao.get(0, y).unwrap() > 0.95);
+        assert!(ao.get(3, y).unwrap() < 0.05);
+    }
+    // The same seed bakes the same texture
+    assert_eq!(baker.bake(&rtscene, ground_id, uv).unwrap(), ao);
+
+    // Only occlusion within the distance is baked
+    let local = baker
+        .max_distance(0.01)
+        .bake(&rtscene, ground_id, uv)
+        .unwrap();
+    assert!(local.texels.iter().all(|t| *t == 1.0));
+
+    // Only geometry in the scene can be baked
+    assert!(baker.bake(&rtscene, overhang_id + 1, uv).is_none());
+}
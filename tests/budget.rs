@@ -0,0 +1,51 @@
+extern crate cgmath;
+extern crate embree;
+
+use cgmath::{Vector3, Vector4};
+use embree::{Device, Geometry, QueryBudget, Ray, Scene, TriangleMesh};
+
+/// Build a stack of `n` quads in front of each other along z, like layers
+/// of alpha tested leaves, with a filter rejecting every hit
+fn make_layers(device: &Device, n: usize) -> Geometry<'_> {
+    let mut mesh = TriangleMesh::unanimated(device, 2 * n, 4 * n);
+    {
+        let mut verts = mesh.vertex_buffer.map();
+        let mut tris = mesh.index_buffer.map();
+        for i in 0..n {
+            let z = -(i as f32);
+            verts[4 * i] = Vector4::new(-1.0, -1.0, z, 0.0);
+            verts[4 * i + 1] = Vector4::new(1.0, -1.0, z, 0.0);
+            verts[4 * i + 2] = Vector4::new(1.0, 1.0, z, 0.0);
+            verts[4 * i + 3] = Vector4::new(-1.0, 1.0, z, 0.0);
+            let v = 4 * i as u32;
+            tris[2 * i] = Vector3::new(v, v + 1, v + 2);
+            tris[2 * i + 1] = Vector3::new(v, v + 2, v + 3);
+        }
+    }
+    let mut geom = Geometry::Triangle(mesh);
+    geom.set_intersect_filter_function(|_, _| false);
+    geom.commit();
+    geom
+}
+
+#[test]
+fn budget_bounds_filtered_candidates() {
+    let device = Device::new();
+    let mut scene = Scene::new(&device);
+    scene.attach_geometry(make_layers(&device, 64));
+    let rtscene = scene.commit();
+    let ray = Ray::new(Vector3::new(0.1, 0.2, 1.0), Vector3::new(0.0, 0.0, -1.0));
+
+    // Without a limit every layer is filtered and rejected
+    let result = rtscene.intersect_with_budget(&ray, QueryBudget::new());
+    assert!(result.ray_hit.is_none());
+    assert!(!result.approximate);
+    assert_eq!(result.candidates, 64);
+    assert!(rtscene.intersect_ray(&ray).is_none());
+
+    // Once the budget is spent a candidate is accepted unfiltered
+    let result = rtscene.intersect_with_budget(&ray, QueryBudget::new().candidates(8));
+    assert!(result.approximate);
+    assert_eq!(result.candidates, 8);
+    assert!(result.ray_hit.is_some());
+}
@@ -0,0 +1,44 @@
+extern crate cgmath;
+extern crate embree;
+
+use cgmath::{Vector3, Vector4};
+use embree::{Device, Geometry, Ray, Scene, TriangleMesh};
+
+#[test]
+fn mapped_scope_updates_geometry() {
+    let device = Device::new();
+    let mut tris = TriangleMesh::unanimated(&device, 1, 3);
+    let num_verts = tris.vertex_buffer.mapped_scope(|verts| {
+        verts[0] = Vector4::new(0.0, 0.0, 0.0, 0.0);
+        verts[1] = Vector4::new(1.0, 0.0, 0.0, 0.0);
+        verts[2] = Vector4::new(0.0, 1.0, 0.0, 0.0);
+        verts.len()
+    });
+    assert_eq!(num_verts, 3);
+    tris.index_buffer.mapped_scope(|indices| {
+        indices[0] = Vector3::new(0, 1, 2);
+    });
+    let mut geom = Geometry::Triangle(tris);
+    geom.commit();
+
+    let mut scene = Scene::new(&device);
+    let id = scene.attach(geom);
+    {
+        let rtscene = scene.commit();
+        let ray = Ray::new(Vector3::new(0.25, 0.25, 1.0), Vector3::new(0.0, 0.0, -1.0));
+        assert!(rtscene.intersect_ray(&ray).is_some());
+    }
+
+    // Move the triangle away through a scoped mapping and recommit
+    if let Some(&mut Geometry::Triangle(ref mut tris)) = scene.geometry_mut(id) {
+        tris.vertex_buffer.mapped_scope(|verts| {
+            for v in verts.iter_mut() {
+                v.x += 10.0;
+            }
+        });
+    }
+    scene.geometry_mut(id).unwrap().commit();
+    let rtscene = scene.commit();
+    let ray = Ray::new(Vector3::new(0.25, 0.25, 1.0), Vector3::new(0.0, 0.0, -1.0));
+    assert!(rtscene.intersect_ray(&ray).is_none());
+}
@@ -0,0 +1,59 @@
+extern crate embree;
+
+use embree::{Bounds, BuildPrimitive, BuildSettings, Bvh, BvhLeaf, BvhTree, Device};
+
+fn unit_box(x: f32) -> Bounds {
+    Bounds {
+        lower_x: x,
+        lower_y: 0.0,
+        lower_z: 0.0,
+        align0: 0.0,
+        upper_x: x + 1.0,
+        upper_y: 1.0,
+        upper_z: 1.0,
+        align1: 0.0,
+    }
+}
+
+/// Leaves storing only the primitive IDs
+struct PrimIds(Vec<u32>);
+
+impl BvhLeaf for PrimIds {
+    fn create(prims: &[BuildPrimitive]) -> PrimIds {
+        PrimIds(prims.iter().map(|p| p.primID).collect())
+    }
+}
+
+#[test]
+fn build_wide_bvh() {
+    let device = Device::new();
+    let prims: Vec<BuildPrimitive> = (0..256)
+        .map(|i| BuildPrimitive::new(&unit_box(i as f32 * 2.0), 0, i))
+        .collect();
+    let settings = BuildSettings::new().branching_factor(4).leaf_size(1, 4);
+    let bvh: Bvh<(), PrimIds> = Bvh::build(&device, &prims, &settings);
+    assert!(bvh.depth() > 1);
+    assert_eq!(bvh.bounds().upper_x, 511.0);
+    if let Some(BvhTree::Node { children, .. }) = bvh.root() {
+        assert!(children.len() <= 4);
+    }
+
+    let mut count = 0;
+    bvh.traverse(|_| true, |leaf| count += leaf.0.len());
+    assert_eq!(count, prims.len());
+
+    let mut hit = Vec::new();
+    bvh.traverse(
+        |b| b.lower_x <= 10.5 && 10.5 <= b.upper_x,
+        |leaf| hit.extend(leaf.0.iter().cloned()),
+    );
+    assert!(hit.contains(&5));
+}
+
+#[test]
+fn build_empty_bvh() {
+    let device = Device::new();
+    let bvh: Bvh<(), ()> = Bvh::build(&device, &[], &BuildSettings::new());
+    assert!(bvh.root().is_none());
+    assert_eq!(bvh.depth(), 0);
+}
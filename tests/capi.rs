@@ -0,0 +1,77 @@
+#![cfg(feature = "capi")]
+
+extern crate embree;
+
+use std::ptr;
+
+use embree::capi::*;
+
+#[test]
+fn intersect1() {
+    unsafe {
+        let device = embree_rs_device_new();
+        assert!(!device.is_null());
+        let scene = embree_rs_scene_new(device);
+        assert!(!scene.is_null());
+
+        let positions = [-1.0, -1.0, 0.0, 1.0, -1.0, 0.0, 0.0, 1.0, 0.0];
+        let indices = [0, 1, 2];
+        let id =
+            embree_rs_scene_add_triangle_mesh(scene, positions.as_ptr(), 3, indices.as_ptr(), 1);
+        assert_eq!(id, 0);
+
+        let ray = EmbreeRsRay {
+            org: [0.0, 0.0, 1.0],
+            dir: [0.0, 0.0, -1.0],
+            tnear: 0.0,
+            tfar: f32::INFINITY,
+        };
+        let mut hit = EmbreeRsHit {
+            t: 0.0,
+            u: 0.0,
+            v: 0.0,
+            ng: [0.0; 3],
+            geom_id: EMBREE_RS_INVALID_ID,
+            prim_id: EMBREE_RS_INVALID_ID,
+            inst_id: EMBREE_RS_INVALID_ID,
+        };
+        // The scene must be committed before it can be queried
+        assert!(!embree_rs_scene_intersect1(scene, &ray, &mut hit));
+        assert!(embree_rs_scene_commit(scene));
+        assert!(embree_rs_scene_intersect1(scene, &ray, &mut hit));
+        assert_eq!(hit.geom_id, id);
+        assert_eq!(hit.prim_id, 0);
+        assert_eq!(hit.inst_id, EMBREE_RS_INVALID_ID);
+        assert!((hit.t - 1.0).abs() < 1e-5);
+        assert!(hit.ng[2] > 0.0);
+
+        let miss = EmbreeRsRay {
+            org: [5.0, 0.0, 1.0],
+            ..ray
+        };
+        assert!(!embree_rs_scene_intersect1(scene, &miss, &mut hit));
+        assert!(!embree_rs_scene_intersect1(scene, &ray, ptr::null_mut()));
+
+        // Adding geometry requires committing again
+        let bad_indices = [0, 1, 3];
+        let bad = embree_rs_scene_add_triangle_mesh(
+            scene,
+            positions.as_ptr(),
+            3,
+            bad_indices.as_ptr(),
+            1,
+        );
+        assert_eq!(bad, EMBREE_RS_INVALID_ID);
+        let second =
+            embree_rs_scene_add_triangle_mesh(scene, positions.as_ptr(), 3, indices.as_ptr(), 1);
+        assert_eq!(second, 1);
+        assert!(!embree_rs_scene_intersect1(scene, &ray, &mut hit));
+        assert!(embree_rs_scene_commit(scene));
+        assert!(embree_rs_scene_intersect1(scene, &ray, &mut hit));
+
+        embree_rs_scene_free(scene);
+        embree_rs_device_free(device);
+        assert!(embree_rs_scene_new(ptr::null()).is_null());
+        assert!(!embree_rs_scene_commit(ptr::null_mut()));
+    }
+}
@@ -0,0 +1,65 @@
+extern crate cgmath;
+extern crate embree;
+
+use std::sync::Mutex;
+
+use cgmath::Vector3;
+use embree::{Capsule, Device, Error, Geometry, Scene, TriangleMesh, UserGeometry};
+
+fn capsules<'a>(device: &'a Device, xs: &[f32]) -> Scene<'a> {
+    let shapes = xs
+        .iter()
+        .map(|&x| Capsule::new(Vector3::new(x, 0.0, 0.0), Vector3::new(x, 1.0, 0.0), 0.5))
+        .collect();
+    let mut geom = Geometry::User(UserGeometry::new(device, shapes));
+    geom.commit();
+    let mut scene = Scene::new(device);
+    scene.attach_geometry(geom);
+    scene
+}
+
+#[test]
+fn overlapping_capsules_collide() {
+    let device = Device::new();
+    let scene = capsules(&device, &[0.0, 0.75, 5.0]);
+    let rtscene = scene.commit();
+    let pairs = Mutex::new(Vec::new());
+    rtscene
+        .collide(&rtscene, |c| {
+            let mut pairs = pairs.lock().unwrap();
+            pairs.extend(c.iter().map(|c| (c.primID0, c.primID1)));
+        })
+        .unwrap();
+    let pairs = pairs.into_inner().unwrap();
+    assert!(pairs.contains(&(0, 1)) || pairs.contains(&(1, 0)));
+    assert!(pairs.iter().all(|&(a, b)| a != 2 && b != 2));
+}
+
+#[test]
+fn collide_rejects_meshes() {
+    let device = Device::new();
+    let user = capsules(&device, &[0.0]);
+    let rtuser = user.commit();
+    let mut mesh = Scene::new(&device);
+    let mut tri = Geometry::Triangle(TriangleMesh::unanimated(&device, 1, 3));
+    tri.commit();
+    mesh.attach_geometry(tri);
+    let rtmesh = mesh.commit();
+    assert_eq!(
+        rtuser.collide(&rtmesh, |_| {}),
+        Err(Error::INVALID_OPERATION)
+    );
+    assert_eq!(
+        rtmesh.collide(&rtuser, |_| {}),
+        Err(Error::INVALID_OPERATION)
+    );
+}
+
+#[test]
+#[should_panic(expected = "callback panicked")]
+fn collide_resumes_callback_panic() {
+    let device = Device::new();
+    let scene = capsules(&device, &[0.0, 0.75]);
+    let rtscene = scene.commit();
+    let _ = rtscene.collide(&rtscene, |_| panic!("callback panicked"));
+}
@@ -0,0 +1,68 @@
+extern crate cgmath;
+extern crate embree;
+
+use cgmath::Vector3;
+use embree::{Device, Ray, Scene};
+
+#[test]
+fn commit_tokens_increase() {
+    let device = Device::new();
+    let scene = Scene::new(&device);
+    assert_eq!(scene.commit_token(), None);
+    let first = {
+        let first = scene.commit();
+        assert!(first.is_current());
+        first.token()
+    };
+    let second = scene.commit();
+    assert!(second.token() > first);
+    assert!(second.is_current());
+    assert_eq!(scene.commit_token(), Some(second.token()));
+
+    let ray = Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 1.0));
+    let hit = second.intersect_stamped(&ray);
+    assert!(hit.get(&second).is_none());
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "different commit")]
+fn stale_result_is_detected() {
+    let device = Device::new();
+    let scene = Scene::new(&device);
+    let first = scene.commit();
+    let ray = Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 1.0));
+    let hit = first.intersect_stamped(&ray);
+    drop(first);
+    let second = scene.commit();
+    hit.get(&second);
+}
+
+#[test]
+#[should_panic(expected = "previous commit is alive")]
+fn commit_while_viewed_is_rejected() {
+    let device = Device::new();
+    let scene = Scene::new(&device);
+    let _first = scene.commit();
+    scene.commit();
+}
+
+#[test]
+#[should_panic(expected = "previous commit is alive")]
+fn join_commit_while_viewed_is_rejected() {
+    let device = Device::new();
+    let scene = Scene::new(&device);
+    let _shadow = scene.commit().shadow_proxies();
+    scene.join_commit();
+}
+
+#[test]
+fn commit_after_views_are_dropped() {
+    let device = Device::new();
+    let scene = Scene::new(&device);
+    {
+        let first = scene.commit();
+        let _shadow = first.shadow_proxies();
+    }
+    assert!(scene.commit().is_current());
+}
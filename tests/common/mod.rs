@@ -0,0 +1,60 @@
+//! Fixtures shared by the integration tests
+
+// Each test crate only uses some of the fixtures
+#![allow(dead_code)]
+
+use cgmath::{Vector3, Vector4};
+use embree::{Device, Geometry, TriangleMesh};
+
+/// The corners of the unit right triangle at the origin in the z = 0 plane
+pub const UNIT_TRIANGLE: [[f32; 3]; 3] = [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]];
+
+/// The corners of a triangle around the z axis in the plane `z`
+pub fn centered_triangle(z: f32) -> [[f32; 3]; 3] {
+    [[-1.0, -1.0, z], [1.0, -1.0, z], [0.0, 1.0, z]]
+}
+
+/// Create a mesh of one triangle with the corners, facing +z if they're
+/// counter-clockwise seen from above
+pub fn triangle_mesh(device: &Device, corners: [[f32; 3]; 3]) -> TriangleMesh<'_> {
+    let mut mesh = TriangleMesh::unanimated(device, 1, 3);
+    {
+        let mut verts = mesh.vertex_buffer.map();
+        for (v, c) in verts.as_mut_slice().iter_mut().zip(corners.iter()) {
+            *v = Vector4::new(c[0], c[1], c[2], 0.0);
+        }
+        mesh.index_buffer.map()[0] = Vector3::new(0, 1, 2);
+    }
+    mesh
+}
+
+/// Create an uncommitted geometry of one triangle with the corners
+pub fn triangle(device: &Device, corners: [[f32; 3]; 3]) -> Geometry<'_> {
+    Geometry::Triangle(triangle_mesh(device, corners))
+}
+
+/// Create a committed geometry of one triangle with the corners
+pub fn committed_triangle(device: &Device, corners: [[f32; 3]; 3]) -> Geometry<'_> {
+    let mut geom = triangle(device, corners);
+    geom.commit();
+    geom
+}
+
+/// Create two overlapping triangles covering [-1, 1]^2, the odd one at
+/// z = 1 in front of the even one at z = 0
+pub fn make_layers(device: &Device) -> Geometry<'_> {
+    let mut mesh = TriangleMesh::unanimated(device, 2, 6);
+    {
+        let mut verts = mesh.vertex_buffer.map();
+        for i in 0..2 {
+            let z = i as f32;
+            verts[i * 3] = Vector4::new(-1.0, -1.0, z, 0.0);
+            verts[i * 3 + 1] = Vector4::new(3.0, -1.0, z, 0.0);
+            verts[i * 3 + 2] = Vector4::new(-1.0, 3.0, z, 0.0);
+        }
+        let mut tris = mesh.index_buffer.map();
+        tris[0] = Vector3::new(0, 1, 2);
+        tris[1] = Vector3::new(3, 4, 5);
+    }
+    Geometry::Triangle(mesh)
+}
@@ -0,0 +1,49 @@
+#![cfg(feature = "curves")]
+
+extern crate cgmath;
+extern crate embree;
+
+use cgmath::{Vector3, Vector4};
+use embree::{
+    BufferType, CurveBasis, CurveType, Device, Error, Geometry, GeometryKind, Scene, SceneQuery,
+};
+
+#[test]
+fn curve_basis_and_type() {
+    let device = Device::new();
+    let geom = Geometry::curve(
+        &device,
+        CurveBasis::Bspline,
+        CurveType::NormalOriented,
+        1,
+        4,
+    )
+    .unwrap();
+    assert_eq!(geom.kind(), GeometryKind::BsplineCurve);
+    assert_eq!(geom.curve_basis(), Some(CurveBasis::Bspline));
+    assert_eq!(geom.curve_type(), Some(CurveType::NormalOriented));
+    assert!(geom.required_buffers().contains(&BufferType::NORMAL));
+
+    let err = Geometry::curve(&device, CurveBasis::Linear, CurveType::NormalOriented, 1, 2);
+    assert_eq!(err.err(), Some(Error::INVALID_ARGUMENT));
+    let err = Geometry::curve(&device, CurveBasis::Hermite, CurveType::Cone, 1, 2);
+    assert_eq!(err.err(), Some(Error::INVALID_ARGUMENT));
+}
+
+#[test]
+fn try_commit_curves() {
+    let device = Device::new();
+    let mut geom = Geometry::curve(&device, CurveBasis::Linear, CurveType::Round, 1, 2).unwrap();
+    if let Geometry::LinearCurve(ref mut c) = geom {
+        let mut verts = c.vertex_buffer.map();
+        verts[0] = Vector4::new(-1.0, 0.0, 0.0, 0.1);
+        verts[1] = Vector4::new(1.0, 0.0, 0.0, 0.1);
+        c.index_buffer.map()[0] = 0;
+    }
+    geom.try_commit().unwrap();
+    let mut scene = Scene::new(&device);
+    scene.attach_geometry(geom);
+    let rtscene = scene.commit();
+    let t = rtscene.hit_distance(Vector3::new(0.0, 0.0, 1.0), Vector3::new(0.0, 0.0, -1.0));
+    assert!(t.is_some());
+}
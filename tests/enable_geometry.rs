@@ -0,0 +1,62 @@
+#![cfg(feature = "curves")]
+
+extern crate cgmath;
+extern crate embree;
+
+mod common;
+
+use cgmath::{Vector3, Vector4};
+use embree::{CommittedScene, Device, Geometry, LinearCurve, Ray, Scene};
+
+fn make_curve(device: &Device) -> Geometry<'_> {
+    let mut curve = LinearCurve::round(device, 1, 2, false);
+    {
+        let mut verts = curve.vertex_buffer.map();
+        verts[0] = Vector4::new(-1.0, 0.0, 1.0, 0.1);
+        verts[1] = Vector4::new(1.0, 0.0, 1.0, 0.1);
+        curve.index_buffer.map()[0] = 0;
+    }
+    Geometry::LinearCurve(curve)
+}
+
+fn trace(scene: &CommittedScene) -> Option<u32> {
+    let ray = Ray::new(Vector3::new(0.0, 0.0, 2.0), Vector3::new(0.0, 0.0, -1.0));
+    scene.intersect_ray(&ray).map(|h| h.hit.geomID)
+}
+
+#[test]
+fn set_enabled_where() {
+    let device = Device::new();
+    let mut scene = Scene::new(&device);
+    let mut tri = common::triangle(&device, common::centered_triangle(0.0));
+    tri.commit();
+    let tri_id = scene.attach(tri);
+    let mut curve = make_curve(&device);
+    curve.commit();
+    let curve_id = scene.attach(curve);
+
+    // Hiding the curves only changes the curve, and doing it again changes
+    // nothing
+    assert_eq!(
+        scene.set_enabled_where(|_, g| g.kind().is_curve(), false),
+        1
+    );
+    assert!(!scene.geometry(curve_id).unwrap().is_enabled());
+    assert!(scene.geometry(tri_id).unwrap().is_enabled());
+    assert_eq!(
+        scene.set_enabled_where(|_, g| g.kind().is_curve(), false),
+        0
+    );
+
+    // The ray passes through the curve before the triangle
+    assert_eq!(trace(&scene.commit()), Some(tri_id.raw()));
+
+    assert_eq!(scene.set_enabled_where(|_, _| true, false), 1);
+    assert_eq!(trace(&scene.commit()), None);
+
+    assert_eq!(
+        scene.set_enabled_where(|id, _| id == curve_id.raw(), true),
+        1
+    );
+    assert_eq!(trace(&scene.commit()), Some(curve_id.raw()));
+}
@@ -0,0 +1,81 @@
+extern crate cgmath;
+extern crate embree;
+
+use cgmath::Vector4;
+use embree::form_factor::estimate_form_factor;
+use embree::{Device, Geometry, QuadMesh, Scene};
+
+/// A unit square in the plane `z`, whose front face points along +z if
+/// `up` or -z otherwise
+fn make_square(device: &Device, z: f32, up: bool) -> Geometry<'_> {
+    let mut quads = QuadMesh::unanimated(device, 1, 4);
+    {
+        let mut verts = quads.vertex_buffer.map();
+        verts[0] = Vector4::new(0.0, 0.0, z, 0.0);
+        verts[1] = Vector4::new(1.0, 0.0, z, 0.0);
+        verts[2] = Vector4::new(1.0, 1.0, z, 0.0);
+        verts[3] = Vector4::new(0.0, 1.0, z, 0.0);
+    }
+    quads.index_buffer.map()[0] = if up {
+        Vector4::new(0, 1, 2, 3)
+    } else {
+        Vector4::new(0, 3, 2, 1)
+    };
+    let mut geom = Geometry::Quad(quads);
+    geom.commit();
+    geom
+}
+
+#[test]
+fn parallel_unit_squares() {
+    let device = Device::new();
+    let mut scene = Scene::new(&device);
+    let from = scene.attach_geometry(make_square(&device, 0.0, true));
+    let to = scene.attach_geometry(make_square(&device, 1.0, false));
+    let rtscene = scene.commit();
+
+    // The form factor between directly opposed parallel unit squares a
+    // unit distance apart, from the closed form for parallel rectangles
+    let expected = 0.19982;
+    let estimate = estimate_form_factor(&rtscene, from, to, 200_000, 7).unwrap();
+    assert_eq!(estimate.samples, 200_000);
+    assert!(
+        (estimate.value - expected).abs() < 0.01,
+        "expected {} got {}",
+        expected,
+        estimate.value
+    );
+    assert!((estimate.value - expected).abs() < 5.0 * estimate.std_error());
+
+    // The squares face away from each other
+    let mut scene = Scene::new(&device);
+    let from = scene.attach_geometry(make_square(&device, 0.0, false));
+    let to = scene.attach_geometry(make_square(&device, 1.0, true));
+    let rtscene = scene.commit();
+    let estimate = estimate_form_factor(&rtscene, from, to, 1000, 7).unwrap();
+    assert_eq!(estimate.value, 0.0);
+}
+
+#[test]
+fn occluded_unit_squares() {
+    let device = Device::new();
+    let mut scene = Scene::new(&device);
+    let from = scene.attach_geometry(make_square(&device, 0.0, true));
+    let to = scene.attach_geometry(make_square(&device, 1.0, false));
+    // A larger blocker half way between the squares
+    let mut blocker = QuadMesh::unanimated(&device, 1, 4);
+    {
+        let mut verts = blocker.vertex_buffer.map();
+        verts[0] = Vector4::new(-10.0, -10.0, 0.5, 0.0);
+        verts[1] = Vector4::new(10.0, -10.0, 0.5, 0.0);
+        verts[2] = Vector4::new(10.0, 10.0, 0.5, 0.0);
+        verts[3] = Vector4::new(-10.0, 10.0, 0.5, 0.0);
+    }
+    blocker.index_buffer.map()[0] = Vector4::new(0, 1, 2, 3);
+    let mut blocker = Geometry::Quad(blocker);
+    blocker.commit();
+    scene.attach_geometry(blocker);
+    let rtscene = scene.commit();
+    let estimate = estimate_form_factor(&rtscene, from, to, 1000, 7).unwrap();
+    assert_eq!(estimate.value, 0.0);
+}
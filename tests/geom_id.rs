@@ -0,0 +1,70 @@
+extern crate cgmath;
+extern crate embree;
+
+mod common;
+
+use cgmath::Vector3;
+use embree::{Device, Ray, Scene};
+
+#[test]
+fn ids_are_tied_to_their_scene() {
+    let device = Device::new();
+    let mut a = Scene::new(&device);
+    let mut b = Scene::new(&device);
+    let id_a = a.attach(common::committed_triangle(
+        &device,
+        common::centered_triangle(0.0),
+    ));
+    let id_b = b.attach(common::committed_triangle(
+        &device,
+        common::centered_triangle(0.0),
+    ));
+
+    // Both scenes give their first geometry the same raw ID
+    assert_eq!(id_a.raw(), id_b.raw());
+    assert!(a.geometry(id_a).is_some());
+    assert!(a.geometry(id_b).is_none());
+    assert!(b.detach(id_a).is_none());
+    assert!(b.contains(id_b));
+}
+
+#[test]
+fn stale_ids_are_rejected() {
+    let device = Device::new();
+    let mut scene = Scene::new(&device);
+    let old = scene.attach(common::committed_triangle(
+        &device,
+        common::centered_triangle(0.0),
+    ));
+    assert!(scene.detach(old).is_some());
+
+    // Embree reuses the detached ID for the next geometry
+    let new = scene.attach(common::committed_triangle(
+        &device,
+        common::centered_triangle(0.0),
+    ));
+    assert_eq!(old.raw(), new.raw());
+    assert!(scene.geometry(old).is_none());
+    assert!(scene.geometry(new).is_some());
+}
+
+#[test]
+fn hit_resolves_against_scene() {
+    let device = Device::new();
+    let mut scene = Scene::new(&device);
+    let near = scene.attach(common::committed_triangle(
+        &device,
+        common::centered_triangle(0.0),
+    ));
+    scene.attach(common::committed_triangle(
+        &device,
+        common::centered_triangle(-1.0),
+    ));
+    let rtscene = scene.commit();
+
+    let ray = Ray::new(Vector3::new(0.0, 0.0, 1.0), Vector3::new(0.0, 0.0, -1.0));
+    let hit = rtscene.intersect_ray(&ray).unwrap().hit;
+    assert_eq!(hit.geom_id(&scene), Some(near));
+    assert!(hit.geometry(&scene).is_some());
+    assert_eq!(hit.instance_id(&scene), None);
+}
@@ -0,0 +1,61 @@
+extern crate cgmath;
+extern crate embree;
+
+use cgmath::Vector4;
+#[cfg(feature = "subdivision")]
+use embree::SubdivisionMesh;
+use embree::{Device, Error, Geometry, GeometryKind, KindMismatch, QuadMesh, Scene, TriangleMesh};
+
+#[test]
+fn recover_typed_geometry() {
+    let device = Device::new();
+    let mut scene = Scene::new(&device);
+    let mut tris = Geometry::Triangle(TriangleMesh::unanimated(&device, 2, 4));
+    tris.commit();
+    let mut quads = Geometry::Quad(QuadMesh::unanimated(&device, 1, 4));
+    quads.commit();
+    let tri_id = scene.attach(tris);
+    let quad_id = scene.attach(quads);
+
+    let geom = scene.geometry(tri_id).unwrap();
+    assert_eq!(geom.kind(), GeometryKind::Triangle);
+    assert!(geom.is_kind::<TriangleMesh>());
+    let mesh = geom.try_into_kind::<TriangleMesh>().unwrap();
+    assert_eq!(mesh.vertex_buffer.len(), 4);
+    assert_eq!(
+        geom.try_into_kind::<QuadMesh>().err(),
+        Some(KindMismatch {
+            expected: GeometryKind::Quad,
+            found: GeometryKind::Triangle,
+        })
+    );
+
+    let geom = scene.geometry_mut(quad_id).unwrap();
+    assert!(geom.as_kind::<TriangleMesh>().is_none());
+    let quads = geom.as_kind_mut::<QuadMesh>().unwrap();
+    quads.index_buffer.map()[0] = Vector4::new(0, 1, 2, 3);
+    geom.commit();
+}
+
+#[test]
+fn try_set_tessellation_rate() {
+    let device = Device::new();
+    let mut tris = Geometry::Triangle(TriangleMesh::unanimated(&device, 1, 3));
+    assert_eq!(
+        tris.try_set_tessellation_rate(4.0),
+        Err(Error::INVALID_OPERATION)
+    );
+    #[cfg(feature = "subdivision")]
+    {
+        let mut subdiv = Geometry::Subdivision(SubdivisionMesh::unanimated(&device, 1, 4, 4));
+        assert_eq!(subdiv.try_set_tessellation_rate(4.0), Ok(()));
+    }
+}
+
+#[test]
+#[should_panic]
+fn set_tessellation_rate_wrong_kind() {
+    let device = Device::new();
+    let mut quads = Geometry::Quad(QuadMesh::unanimated(&device, 1, 4));
+    quads.set_tessellation_rate(4.0);
+}
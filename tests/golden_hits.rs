@@ -0,0 +1,511 @@
+//! Golden hit tests for each geometry kind: every geometry is built from
+//! small canonical data, a deterministic ray is traced against it and the
+//! hit distance, parametric coordinates and geometric normal are compared
+//! against analytically derived values. These catch regressions in the
+//! buffer layouts and formats the wrappers set up for Embree.
+
+extern crate cgmath;
+extern crate embree;
+
+mod common;
+
+use cgmath::{InnerSpace, Matrix4, Vector3, Vector4};
+#[cfg(feature = "subdivision")]
+use embree::SubdivisionMesh;
+#[cfg(feature = "curves")]
+use embree::{BezierCurve, BsplineCurve, CatmullRomCurve, HermiteCurve, LinearCurve};
+use embree::{
+    Bounds, Device, Geometry, Grid, GridMesh, Instance, IntersectContext, PointGeometry,
+    PrimitiveHit, QuadMesh, Ray, RayHit, Scene, UserGeometry, UserPrimitive,
+};
+
+const EPS: f32 = 1e-3;
+
+/// The expected result of tracing a golden ray
+#[derive(Copy, Clone)]
+struct Golden {
+    t: f32,
+    u: f32,
+    /// The expected v coordinate, if it is well defined for the geometry kind
+    v: Option<f32>,
+    /// The expected direction of the geometric normal, if it is well
+    /// defined for the geometry kind
+    ng: Option<Vector3<f32>>,
+}
+
+fn assert_close(name: &str, what: &str, expected: f32, actual: f32) {
+    assert!(
+        (expected - actual).abs() < EPS,
+        "{}: {} expected {} got {}",
+        name,
+        what,
+        expected,
+        actual
+    );
+}
+
+/// Trace the ray from `origin` along -Z against a scene containing just
+/// `geom` and compare the result with the golden values.
+fn check_golden<'a>(
+    name: &str,
+    device: &'a Device,
+    geom: Geometry<'a>,
+    origin: Vector3<f32>,
+    golden: Golden,
+) {
+    let mut scene = Scene::new(device);
+    let id = scene.attach_geometry(geom);
+    let rtscene = scene.commit();
+
+    let mut ctx = IntersectContext::coherent();
+    let mut ray_hit = RayHit::new(Ray::new(origin, Vector3::new(0.0, 0.0, -1.0)));
+    rtscene.intersect(&mut ctx, &mut ray_hit);
+
+    assert!(ray_hit.hit.hit(), "{}: expected a hit", name);
+    assert_eq!(ray_hit.hit.geomID, id, "{}: geomID", name);
+    assert_eq!(ray_hit.hit.primID, 0, "{}: primID", name);
+    assert_close(name, "t", golden.t, ray_hit.ray.tfar);
+    assert_close(name, "u", golden.u, ray_hit.hit.u);
+    if let Some(v) = golden.v {
+        assert_close(name, "v", v, ray_hit.hit.v);
+    }
+    if let Some(ng) = golden.ng {
+        let hit_ng = Vector3::new(ray_hit.hit.Ng_x, ray_hit.hit.Ng_y, ray_hit.hit.Ng_z).normalize();
+        assert_close(name, "Ng.x", ng.x, hit_ng.x);
+        assert_close(name, "Ng.y", ng.y, hit_ng.y);
+        assert_close(name, "Ng.z", ng.z, hit_ng.z);
+    }
+}
+
+/// Curve control points for a straight segment along the x axis from -1 to 1,
+/// laid out such that the curve is parameterized linearly for each basis.
+#[cfg(feature = "curves")]
+fn straight_curve_points(basis_span: [f32; 4]) -> [Vector4<f32>; 4] {
+    [
+        Vector4::new(basis_span[0], 0.0, 0.0, 0.1),
+        Vector4::new(basis_span[1], 0.0, 0.0, 0.1),
+        Vector4::new(basis_span[2], 0.0, 0.0, 0.1),
+        Vector4::new(basis_span[3], 0.0, 0.0, 0.1),
+    ]
+}
+
+#[test]
+fn golden_triangle() {
+    let device = Device::new();
+    check_golden(
+        "triangle",
+        &device,
+        common::committed_triangle(&device, common::UNIT_TRIANGLE),
+        Vector3::new(0.25, 0.25, 1.0),
+        Golden {
+            t: 1.0,
+            u: 0.25,
+            v: Some(0.25),
+            ng: Some(Vector3::new(0.0, 0.0, 1.0)),
+        },
+    );
+}
+
+#[test]
+fn golden_quad() {
+    let device = Device::new();
+    let mut quads = QuadMesh::unanimated(&device, 1, 4);
+    {
+        let mut verts = quads.vertex_buffer.map();
+        verts[0] = Vector4::new(0.0, 0.0, 0.0, 0.0);
+        verts[1] = Vector4::new(1.0, 0.0, 0.0, 0.0);
+        verts[2] = Vector4::new(1.0, 1.0, 0.0, 0.0);
+        verts[3] = Vector4::new(0.0, 1.0, 0.0, 0.0);
+    }
+    quads.index_buffer.map()[0] = Vector4::new(0, 1, 2, 3);
+    let mut geom = Geometry::Quad(quads);
+    geom.commit();
+    check_golden(
+        "quad",
+        &device,
+        geom,
+        Vector3::new(0.25, 0.25, 1.0),
+        Golden {
+            t: 1.0,
+            u: 0.25,
+            v: Some(0.25),
+            ng: Some(Vector3::new(0.0, 0.0, 1.0)),
+        },
+    );
+}
+
+#[test]
+fn golden_grid() {
+    let device = Device::new();
+    let mut grids = GridMesh::unanimated(&device, 1, 4);
+    {
+        let mut verts = grids.vertex_buffer.map();
+        verts[0] = Vector4::new(0.0, 0.0, 0.0, 0.0);
+        verts[1] = Vector4::new(1.0, 0.0, 0.0, 0.0);
+        verts[2] = Vector4::new(0.0, 1.0, 0.0, 0.0);
+        verts[3] = Vector4::new(1.0, 1.0, 0.0, 0.0);
+    }
+    grids.grid_buffer.map()[0] = Grid::new(0, 2, 2, 2);
+    let mut geom = Geometry::Grid(grids);
+    geom.commit();
+    check_golden(
+        "grid",
+        &device,
+        geom,
+        Vector3::new(0.25, 0.75, 1.0),
+        Golden {
+            t: 1.0,
+            u: 0.25,
+            v: Some(0.75),
+            ng: Some(Vector3::new(0.0, 0.0, 1.0)),
+        },
+    );
+}
+
+#[test]
+#[cfg(feature = "subdivision")]
+fn golden_subdivision() {
+    let device = Device::new();
+    let mut mesh = SubdivisionMesh::unanimated(&device, 1, 4, 4);
+    {
+        let mut verts = mesh.vertex_buffer.map();
+        verts[0] = Vector4::new(-1.0, -1.0, 0.0, 0.0);
+        verts[1] = Vector4::new(1.0, -1.0, 0.0, 0.0);
+        verts[2] = Vector4::new(1.0, 1.0, 0.0, 0.0);
+        verts[3] = Vector4::new(-1.0, 1.0, 0.0, 0.0);
+        mesh.face_buffer.map()[0] = 4;
+    }
+    {
+        let mut topology = mesh.base_topology();
+        let mut indices = topology.index_buffer().map();
+        for i in 0..4 {
+            indices[i] = i as u32;
+        }
+    }
+    let mut geom = Geometry::Subdivision(mesh);
+    geom.commit();
+    // The limit surface of a flat face stays in its plane, and the face's
+    // center is at its parametric center by symmetry
+    check_golden(
+        "subdivision",
+        &device,
+        geom,
+        Vector3::new(0.0, 0.0, 1.0),
+        Golden {
+            t: 1.0,
+            u: 0.5,
+            v: Some(0.5),
+            ng: Some(Vector3::new(0.0, 0.0, 1.0)),
+        },
+    );
+}
+
+#[test]
+fn golden_points() {
+    let device = Device::new();
+    let origin = Vector3::new(0.0, 0.0, 1.0);
+    let point = [[0.0, 0.0, 0.0, 0.5]];
+    // Embree doesn't parameterize points, their u and v are 0
+    check_golden(
+        "sphere",
+        &device,
+        Geometry::Point(PointGeometry::spheres(&device, &point)),
+        origin,
+        Golden {
+            t: 0.5,
+            u: 0.0,
+            v: Some(0.0),
+            ng: Some(Vector3::new(0.0, 0.0, 1.0)),
+        },
+    );
+    check_golden(
+        "disc",
+        &device,
+        Geometry::Point(PointGeometry::discs(&device, &point)),
+        origin,
+        Golden {
+            t: 1.0,
+            u: 0.0,
+            v: Some(0.0),
+            ng: Some(Vector3::new(0.0, 0.0, 1.0)),
+        },
+    );
+    // The disc is tilted about the x axis, so the ray meets it at the
+    // center with the disc's normal
+    let n = Vector3::new(0.0, 1.0, 1.0).normalize();
+    check_golden(
+        "oriented disc",
+        &device,
+        Geometry::Point(PointGeometry::oriented_discs(&device, &point, &[n.into()])),
+        origin,
+        Golden {
+            t: 1.0,
+            u: 0.0,
+            v: Some(0.0),
+            ng: Some(n),
+        },
+    );
+}
+
+/// A unit square in the z = 0 plane, whose u and v are the hit's
+/// position across it
+struct UnitSquare;
+
+impl UserPrimitive for UnitSquare {
+    fn bounds(&self) -> Bounds {
+        Bounds {
+            lower_x: 0.0,
+            lower_y: 0.0,
+            lower_z: 0.0,
+            align0: 0.0,
+            upper_x: 1.0,
+            upper_y: 1.0,
+            upper_z: 0.0,
+            align1: 0.0,
+        }
+    }
+    fn intersect(&self, ray: &Ray) -> Option<PrimitiveHit> {
+        let t = -ray.org_z / ray.dir_z;
+        let p = ray.origin() + ray.dir() * t;
+        if (0.0..=1.0).contains(&p.x) && (0.0..=1.0).contains(&p.y) {
+            Some(PrimitiveHit::new(t, Vector3::new(0.0, 0.0, 2.0)).with_uv(p.x, p.y))
+        } else {
+            None
+        }
+    }
+}
+
+#[test]
+fn golden_user_geometry() {
+    let device = Device::new();
+    let mut geom = Geometry::User(UserGeometry::from_primitives(&device, vec![UnitSquare]));
+    geom.commit();
+    check_golden(
+        "user geometry",
+        &device,
+        geom,
+        Vector3::new(0.25, 0.75, 1.0),
+        Golden {
+            t: 1.0,
+            u: 0.25,
+            v: Some(0.75),
+            ng: Some(Vector3::new(0.0, 0.0, 1.0)),
+        },
+    );
+}
+
+#[test]
+#[cfg(feature = "curves")]
+fn golden_linear_curves() {
+    let device = Device::new();
+    let round = Golden {
+        t: 0.9,
+        u: 0.5,
+        v: None,
+        ng: None,
+    };
+    fn make(mut curve: LinearCurve) -> Geometry {
+        {
+            let mut verts = curve.vertex_buffer.map();
+            verts[0] = Vector4::new(-1.0, 0.0, 0.0, 0.1);
+            verts[1] = Vector4::new(1.0, 0.0, 0.0, 0.1);
+        }
+        curve.index_buffer.map()[0] = 0;
+        curve.flag_buffer.map()[0] = 0;
+        let mut geom = Geometry::LinearCurve(curve);
+        geom.commit();
+        geom
+    }
+    let origin = Vector3::new(0.0, 0.0, 1.0);
+    check_golden(
+        "round linear curve",
+        &device,
+        make(LinearCurve::round(&device, 1, 2, false)),
+        origin,
+        round,
+    );
+    check_golden(
+        "cone linear curve",
+        &device,
+        make(LinearCurve::cone(&device, 1, 2, false)),
+        origin,
+        round,
+    );
+    check_golden(
+        "flat linear curve",
+        &device,
+        make(LinearCurve::flat(&device, 1, 2, false)),
+        origin,
+        Golden {
+            t: 1.0,
+            u: 0.5,
+            v: None,
+            ng: None,
+        },
+    );
+}
+
+#[test]
+#[cfg(feature = "curves")]
+fn golden_cubic_curves() {
+    let device = Device::new();
+    let origin = Vector3::new(0.0, 0.0, 1.0);
+    let round = Golden {
+        t: 0.9,
+        u: 0.5,
+        v: None,
+        ng: None,
+    };
+    let flat = Golden {
+        t: 1.0,
+        u: 0.5,
+        v: None,
+        ng: None,
+    };
+
+    // Bezier control points evenly spaced over the segment give a
+    // linearly parameterized curve
+    let bezier_pts = straight_curve_points([-1.0, -1.0 / 3.0, 1.0 / 3.0, 1.0]);
+    // For the B-spline and Catmull-Rom bases the segment spans the inner
+    // two control points
+    let spline_pts = straight_curve_points([-3.0, -1.0, 1.0, 3.0]);
+
+    macro_rules! cubic_curve {
+        ($ty:ident, $variant:ident, $ctor:ident, $pts:expr) => {{
+            let mut curve = $ty::$ctor(&device, 1, 4, false);
+            {
+                let mut verts = curve.vertex_buffer.map();
+                for (i, p) in $pts.iter().enumerate() {
+                    verts[i] = *p;
+                }
+            }
+            curve.index_buffer.map()[0] = 0;
+            let mut geom = Geometry::$variant(curve);
+            geom.commit();
+            geom
+        }};
+    }
+
+    check_golden(
+        "round bezier curve",
+        &device,
+        cubic_curve!(BezierCurve, BezierCurve, round, bezier_pts),
+        origin,
+        round,
+    );
+    check_golden(
+        "flat bezier curve",
+        &device,
+        cubic_curve!(BezierCurve, BezierCurve, flat, bezier_pts),
+        origin,
+        flat,
+    );
+    check_golden(
+        "round bspline curve",
+        &device,
+        cubic_curve!(BsplineCurve, BsplineCurve, round, spline_pts),
+        origin,
+        round,
+    );
+    check_golden(
+        "flat bspline curve",
+        &device,
+        cubic_curve!(BsplineCurve, BsplineCurve, flat, spline_pts),
+        origin,
+        flat,
+    );
+    check_golden(
+        "round catmull-rom curve",
+        &device,
+        cubic_curve!(CatmullRomCurve, CatmullRomCurve, round, spline_pts),
+        origin,
+        round,
+    );
+    check_golden(
+        "flat catmull-rom curve",
+        &device,
+        cubic_curve!(CatmullRomCurve, CatmullRomCurve, flat, spline_pts),
+        origin,
+        flat,
+    );
+}
+
+#[test]
+#[cfg(feature = "curves")]
+fn golden_hermite_curves() {
+    let device = Device::new();
+    fn make(mut curve: HermiteCurve) -> Geometry {
+        {
+            let mut verts = curve.vertex_buffer.map();
+            verts[0] = Vector4::new(-1.0, 0.0, 0.0, 0.1);
+            verts[1] = Vector4::new(1.0, 0.0, 0.0, 0.1);
+        }
+        {
+            // Tangents matching the segment give a linear parameterization
+            let mut tangents = curve.tangent_buffer.map();
+            tangents[0] = Vector4::new(2.0, 0.0, 0.0, 0.0);
+            tangents[1] = Vector4::new(2.0, 0.0, 0.0, 0.0);
+        }
+        curve.index_buffer.map()[0] = 0;
+        let mut geom = Geometry::HermiteCurve(curve);
+        geom.commit();
+        geom
+    }
+    let origin = Vector3::new(0.0, 0.0, 1.0);
+    check_golden(
+        "round hermite curve",
+        &device,
+        make(HermiteCurve::round(&device, 1, 2, false)),
+        origin,
+        Golden {
+            t: 0.9,
+            u: 0.5,
+            v: None,
+            ng: None,
+        },
+    );
+    check_golden(
+        "flat hermite curve",
+        &device,
+        make(HermiteCurve::flat(&device, 1, 2, false)),
+        origin,
+        Golden {
+            t: 1.0,
+            u: 0.5,
+            v: None,
+            ng: None,
+        },
+    );
+}
+
+#[test]
+fn golden_instance() {
+    let device = Device::new();
+    let mut child = Scene::new(&device);
+    let tri_id = child.attach_geometry(common::committed_triangle(&device, common::UNIT_TRIANGLE));
+    let child = child.commit();
+
+    let mut instance = Instance::unanimated(&device, &child);
+    instance.set_transform(&Matrix4::from_translation(Vector3::new(2.0, 0.0, -1.0)));
+    let mut geom = Geometry::Instance(instance);
+    geom.commit();
+
+    let mut scene = Scene::new(&device);
+    let inst_id = scene.attach_geometry(geom);
+    let rtscene = scene.commit();
+
+    let mut ctx = IntersectContext::coherent();
+    let mut ray_hit = RayHit::new(Ray::new(
+        Vector3::new(2.25, 0.25, 1.0),
+        Vector3::new(0.0, 0.0, -1.0),
+    ));
+    rtscene.intersect(&mut ctx, &mut ray_hit);
+
+    assert!(ray_hit.hit.hit());
+    assert_eq!(ray_hit.hit.instID[0], inst_id);
+    assert_eq!(ray_hit.hit.geomID, tri_id);
+    assert_eq!(ray_hit.hit.primID, 0);
+    assert_close("instance", "t", 2.0, ray_hit.ray.tfar);
+    assert_close("instance", "u", 0.25, ray_hit.hit.u);
+    assert_close("instance", "v", 0.25, ray_hit.hit.v);
+}
@@ -0,0 +1,40 @@
+extern crate cgmath;
+extern crate embree;
+
+use cgmath::{Vector2, Vector3};
+use embree::{Device, Geometry, GeometryKind, Grid, GridMesh, Scene, SceneQuery};
+
+#[test]
+fn heightfield_hit() {
+    let device = Device::new();
+    let heights = vec![0.5; 9];
+    let mesh = GridMesh::from_heightfield(
+        &device,
+        &heights,
+        3,
+        3,
+        Vector3::new(-1.0, 0.0, -1.0),
+        Vector2::new(1.0, 1.0),
+    );
+    assert_eq!(mesh.grid_buffer.len(), 1);
+    assert_eq!(mesh.quad_count(), 4);
+
+    let geom = Geometry::Grid(mesh);
+    assert_eq!(geom.kind(), GeometryKind::Grid);
+    assert_eq!(geom.primitive_count(), 1);
+    assert!(geom.validate().is_ok());
+
+    let mut scene = Scene::new(&device);
+    scene.attach_geometry(geom);
+    let rtscene = scene.commit();
+    let t = rtscene.hit_distance(Vector3::new(0.0, 2.0, 0.0), Vector3::new(0.0, -1.0, 0.0));
+    assert!((t.unwrap() - 1.5).abs() < 1e-5);
+}
+
+#[test]
+fn grid_out_of_bounds() {
+    let device = Device::new();
+    let mut mesh = GridMesh::unanimated(&device, 1, 4);
+    mesh.grid_buffer.map()[0] = Grid::new(0, 2, 2, 3);
+    assert!(Geometry::Grid(mesh).validate().is_err());
+}
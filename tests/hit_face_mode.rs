@@ -0,0 +1,114 @@
+extern crate cgmath;
+extern crate embree;
+
+use std::os::raw;
+use std::ptr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use cgmath::Vector3;
+use embree::{sys, Device, Error, Geometry, HitFaceMode, Ray, Scene, TriangleMesh};
+
+#[test]
+fn cull_front_and_back_faces() {
+    let device = Device::new();
+    let mesh = TriangleMesh::try_from_slices(
+        &device,
+        &[[-1.0, -1.0, 0.0], [1.0, -1.0, 0.0], [0.0, 1.0, 0.0]],
+        &[[0, 1, 2]],
+    )
+    .unwrap();
+    let filtered = AtomicUsize::new(0);
+    let mut geom = Geometry::Triangle(mesh);
+    geom.set_intersect_filter_function(|_, _| {
+        filtered.fetch_add(1, Ordering::Relaxed);
+        true
+    });
+    geom.commit();
+    let mut scene = Scene::new(&device);
+    let id = scene.attach(geom);
+
+    let down = Ray::new(Vector3::new(0.0, 0.0, 1.0), Vector3::new(0.0, 0.0, -1.0));
+    let up = Ray::new(Vector3::new(0.0, 0.0, -1.0), Vector3::new(0.0, 0.0, 1.0));
+    // Find which of the rays hits the front face, the side Ng points to
+    let (front, back) = {
+        let rtscene = scene.commit();
+        let hit = rtscene.intersect_ray(&down).unwrap();
+        assert!(rtscene.intersect_ray(&up).is_some());
+        if hit.hit.Ng_z > 0.0 {
+            (down, up)
+        } else {
+            (up, down)
+        }
+    };
+    assert_eq!(filtered.swap(0, Ordering::Relaxed), 2);
+
+    for &(mode, hits_front, hits_back) in &[
+        (HitFaceMode::FrontOnly, true, false),
+        (HitFaceMode::BackOnly, false, true),
+        (HitFaceMode::Both, true, true),
+    ] {
+        {
+            let geom = scene.geometry_mut(id).unwrap();
+            geom.set_hit_face_mode(mode);
+            assert_eq!(geom.hit_face_mode(), mode);
+            geom.commit();
+        }
+        let rtscene = scene.commit();
+        assert_eq!(rtscene.intersect_ray(&front).is_some(), hits_front);
+        assert_eq!(rtscene.is_occluded(&front), hits_front);
+        assert_eq!(rtscene.intersect_ray(&back).is_some(), hits_back);
+        assert_eq!(rtscene.is_occluded(&back), hits_back);
+        // Culled hits are skipped before calling the filter
+        assert_eq!(
+            filtered.swap(0, Ordering::Relaxed),
+            hits_front as usize + hits_back as usize
+        );
+    }
+
+    // Clearing the filters keeps the face mode
+    let geom = scene.geometry_mut(id).unwrap();
+    geom.set_hit_face_mode(HitFaceMode::FrontOnly);
+    geom.clear_filter_functions();
+    geom.commit();
+    let rtscene = scene.commit();
+    assert!(rtscene.intersect_ray(&front).is_some());
+    assert!(rtscene.intersect_ray(&back).is_none());
+}
+
+unsafe extern "C" fn accept_all(_: *const sys::RTCFilterFunctionNArguments) {}
+
+#[test]
+fn raw_filter_resets_face_mode() {
+    let device = Device::new();
+    let mesh = TriangleMesh::try_from_slices(
+        &device,
+        &[[-1.0, -1.0, 0.0], [1.0, -1.0, 0.0], [0.0, 1.0, 0.0]],
+        &[[0, 1, 2]],
+    )
+    .unwrap();
+    let mut geom = Geometry::Triangle(mesh);
+    geom.set_hit_face_mode(HitFaceMode::BackOnly);
+    unsafe {
+        geom.set_intersect_filter_function_raw(Some(accept_all), ptr::null_mut::<raw::c_void>());
+    }
+    assert_eq!(geom.hit_face_mode(), HitFaceMode::Both);
+    // Culling would replace the raw filter, so it's rejected until the
+    // filters are cleared
+    assert_eq!(
+        geom.try_set_hit_face_mode(HitFaceMode::FrontOnly),
+        Err(Error::INVALID_OPERATION)
+    );
+    assert_eq!(geom.hit_face_mode(), HitFaceMode::Both);
+    assert_eq!(geom.try_set_hit_face_mode(HitFaceMode::Both), Ok(()));
+    geom.commit();
+    let mut scene = Scene::new(&device);
+    scene.attach_geometry(geom);
+    let rtscene = scene.commit();
+
+    // Neither query culls either face once the mode is reset
+    for &(origin, dir) in &[(1.0, -1.0), (-1.0, 1.0)] {
+        let ray = Ray::new(Vector3::new(0.0, 0.0, origin), Vector3::new(0.0, 0.0, dir));
+        assert!(rtscene.intersect_ray(&ray).is_some());
+        assert!(rtscene.is_occluded(&ray));
+    }
+}
@@ -0,0 +1,122 @@
+extern crate cgmath;
+extern crate embree;
+
+mod common;
+
+use std::mem;
+
+use cgmath::Vector3;
+use embree::{BufferType, Device, Error, Format, Ray, Scene};
+
+#[repr(C)]
+#[derive(Copy, Clone, Default)]
+struct Vertex {
+    pos: [f32; 3],
+    n: [f32; 3],
+    uv: [f32; 2],
+}
+
+fn vertex(x: f32, y: f32) -> Vertex {
+    Vertex {
+        pos: [x, y, 0.0],
+        n: [0.0, 0.0, 1.0],
+        uv: [x, y],
+    }
+}
+
+#[test]
+fn interleaved_vertex_attributes() {
+    let device = Device::new();
+    // The uv member ends the struct, so a padding vertex is needed for
+    // Embree's 16 byte read of the last uv
+    let vertices = vec![
+        vertex(0.0, 0.0),
+        vertex(1.0, 0.0),
+        vertex(0.0, 1.0),
+        Vertex::default(),
+    ];
+    let mut geom = common::triangle(&device, common::UNIT_TRIANGLE);
+    geom.bind_interleaved(&vertices, 3)
+        .bind(
+            BufferType::VERTEX,
+            0,
+            Format::FLOAT3,
+            mem::offset_of!(Vertex, pos),
+        )
+        .bind(
+            BufferType::VERTEX_ATTRIBUTE,
+            0,
+            Format::FLOAT3,
+            mem::offset_of!(Vertex, n),
+        )
+        .bind(
+            BufferType::VERTEX_ATTRIBUTE,
+            1,
+            Format::FLOAT2,
+            mem::offset_of!(Vertex, uv),
+        );
+    geom.commit();
+
+    let mut scene = Scene::new(&device);
+    scene.attach_geometry(geom);
+    let rtscene = scene.commit();
+    let ray = Ray::new(Vector3::new(0.25, 0.25, 1.0), Vector3::new(0.0, 0.0, -1.0));
+    let ray_hit = rtscene.intersect_ray(&ray).expect("expected a hit");
+    assert!((ray_hit.ray.tfar - 1.0).abs() < 1e-4);
+}
+
+#[test]
+#[should_panic]
+fn interleaved_missing_padding() {
+    let device = Device::new();
+    let vertices = vec![vertex(0.0, 0.0), vertex(1.0, 0.0), vertex(0.0, 1.0)];
+    let mut geom = common::triangle(&device, common::UNIT_TRIANGLE);
+    geom.bind_interleaved(&vertices, 3).bind(
+        BufferType::VERTEX_ATTRIBUTE,
+        0,
+        Format::FLOAT2,
+        mem::offset_of!(Vertex, uv),
+    );
+}
+
+#[test]
+#[should_panic]
+fn interleaved_member_out_of_bounds() {
+    let device = Device::new();
+    let vertices = vec![vertex(0.0, 0.0); 4];
+    let mut geom = common::triangle(&device, common::UNIT_TRIANGLE);
+    geom.bind_interleaved(&vertices, 3).bind(
+        BufferType::VERTEX,
+        0,
+        Format::FLOAT3,
+        mem::offset_of!(Vertex, uv),
+    );
+}
+
+#[test]
+#[should_panic]
+fn interleaved_invalid_format() {
+    let device = Device::new();
+    let vertices = vec![vertex(0.0, 0.0); 4];
+    let mut geom = common::triangle(&device, common::UNIT_TRIANGLE);
+    geom.bind_interleaved(&vertices, 3).bind(
+        BufferType::VERTEX,
+        0,
+        Format::FLOAT4,
+        mem::offset_of!(Vertex, pos),
+    );
+}
+
+#[test]
+fn interleaved_try_bind_invalid_format() {
+    let device = Device::new();
+    let vertices = vec![vertex(0.0, 0.0); 4];
+    let mut geom = common::triangle(&device, common::UNIT_TRIANGLE);
+    let binding = geom.bind_interleaved(&vertices, 3).try_bind(
+        BufferType::VERTEX,
+        0,
+        Format::FLOAT4,
+        mem::offset_of!(Vertex, pos),
+    );
+    assert_eq!(binding.err(), Some(Error::INVALID_OPERATION));
+}
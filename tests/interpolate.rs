@@ -0,0 +1,108 @@
+extern crate cgmath;
+extern crate embree;
+
+use std::ptr;
+
+use cgmath::{Vector2, Vector3, Vector4};
+use embree::{sys, BufferType, Device, Format, Geometry, Hit, Ray, Scene, TriangleMesh};
+
+#[test]
+fn cpu_matches_embree() {
+    let device = Device::new();
+    let mut mesh = TriangleMesh::unanimated(&device, 2, 4);
+    let color = mesh.add_vertex_attribute();
+    {
+        let mut verts = mesh.vertex_buffer.map();
+        verts[0] = Vector4::new(-1.0, -1.0, 0.0, 0.0);
+        verts[1] = Vector4::new(1.0, -1.0, 0.0, 0.0);
+        verts[2] = Vector4::new(1.0, 1.0, 0.0, 0.0);
+        verts[3] = Vector4::new(-1.0, 1.0, 0.0, 0.0);
+        let mut colors = mesh.vertex_attribute_buffers[color as usize].map();
+        colors[0] = Vector4::new(1.0, 0.0, 0.0, 1.0);
+        colors[1] = Vector4::new(0.0, 1.0, 0.0, 1.0);
+        colors[2] = Vector4::new(0.0, 0.0, 1.0, 0.5);
+        colors[3] = Vector4::new(1.0, 1.0, 1.0, 0.0);
+        let mut tris = mesh.index_buffer.map();
+        tris[0] = Vector3::new(0, 1, 2);
+        tris[1] = Vector3::new(0, 2, 3);
+    }
+    let mut geom = Geometry::Triangle(mesh);
+    geom.commit();
+    let handle = geom.handle();
+    let mut scene = Scene::new(&device);
+    let id = scene.attach(geom);
+    let rtscene = scene.commit();
+    let mesh = match *scene.geometry(id).unwrap() {
+        Geometry::Triangle(ref m) => m,
+        _ => unreachable!(),
+    };
+
+    for &(x, y) in &[(0.5, -0.5), (-0.5, 0.5), (0.1, 0.05), (-0.9, -0.8)] {
+        let ray = Ray::new(Vector3::new(x, y, 1.0), Vector3::new(0.0, 0.0, -1.0));
+        let hit = rtscene.intersect_ray(&ray).unwrap().hit;
+
+        let mut expected = [0.0f32; 4];
+        let args = sys::RTCInterpolateArguments {
+            geometry: handle,
+            primID: hit.primID,
+            u: hit.u,
+            v: hit.v,
+            bufferType: sys::RTCBufferType::VERTEX_ATTRIBUTE,
+            bufferSlot: color,
+            P: expected.as_mut_ptr(),
+            dPdu: ptr::null_mut(),
+            dPdv: ptr::null_mut(),
+            ddPdudu: ptr::null_mut(),
+            ddPdvdv: ptr::null_mut(),
+            ddPdudv: ptr::null_mut(),
+            valueCount: 4,
+        };
+        unsafe {
+            sys::rtcInterpolate(&args);
+        }
+
+        let c = mesh
+            .interpolate_attribute_cpu::<Vector4<f32>>(&hit, color)
+            .unwrap();
+        for i in 0..4 {
+            assert!(
+                (c[i] - expected[i]).abs() < 1e-5,
+                "{:?} != {:?}",
+                c,
+                expected
+            );
+        }
+        let rg = mesh
+            .interpolate_attribute_cpu::<Vector2<f32>>(&hit, color)
+            .unwrap();
+        assert_eq!(rg, c.truncate().truncate());
+        let r = mesh.interpolate_attribute_cpu::<f32>(&hit, color).unwrap();
+        assert_eq!(r, c.x);
+    }
+}
+
+#[test]
+fn cpu_skips_shared_attributes() {
+    let device = Device::new();
+    let colors = vec![[1.0f32, 0.0, 0.0, 1.0]; 3];
+    let mut mesh = TriangleMesh::unanimated(&device, 1, 3);
+    let color = mesh.add_vertex_attribute();
+    let mut geom = Geometry::Triangle(mesh);
+    // The mesh's own attribute buffer no longer holds the colors
+    geom.set_shared_buffer_from_slice(
+        BufferType::VERTEX_ATTRIBUTE,
+        color,
+        Format::FLOAT4,
+        &colors,
+        3,
+    );
+    let hit = Hit::new();
+    if let Geometry::Triangle(ref m) = geom {
+        assert!(m
+            .interpolate_attribute_cpu::<Vector4<f32>>(&hit, color)
+            .is_none());
+        assert!(m
+            .interpolate_attribute_cpu::<Vector4<f32>>(&hit, color + 1)
+            .is_none());
+    }
+}
@@ -0,0 +1,47 @@
+extern crate cgmath;
+extern crate embree;
+
+mod common;
+
+use embree::{Device, Scene};
+
+#[test]
+fn iterates_in_attach_order() {
+    let device = Device::new();
+    let mut scene = Scene::new(&device);
+    let ids: Vec<u32> = (0..3)
+        .map(|i| {
+            scene.attach_geometry(common::committed_triangle(
+                &device,
+                common::centered_triangle(-(i as f32)),
+            ))
+        })
+        .collect();
+    let handles: Vec<_> = scene.iter_ordered().map(|(_, g)| g.handle()).collect();
+    assert_eq!(
+        scene.iter_ordered().map(|(id, _)| id).collect::<Vec<_>>(),
+        ids
+    );
+
+    // Re-attaching the middle geometry reuses its ID but moves it last
+    let middle = scene.deattach_geometry(ids[1]).unwrap();
+    assert_eq!(
+        scene.iter_ordered().map(|(id, _)| id).collect::<Vec<_>>(),
+        [ids[0], ids[2]]
+    );
+    assert_eq!(scene.attach_geometry(middle), ids[1]);
+
+    let order: Vec<_> = scene
+        .iter_ordered()
+        .map(|(id, g)| (id, g.handle()))
+        .collect();
+    assert_eq!(
+        order,
+        [
+            (ids[0], handles[0]),
+            (ids[2], handles[2]),
+            (ids[1], handles[1])
+        ]
+    );
+    assert_eq!(scene.geometry_ids(), &[ids[0], ids[2], ids[1]]);
+}
@@ -0,0 +1,52 @@
+extern crate cgmath;
+extern crate embree;
+extern crate rayon;
+
+use cgmath::Vector3;
+use embree::{Device, Geometry, Scene, SceneQuery, TriangleMesh};
+use rayon::prelude::*;
+
+/// A grid of n by n quads in the xy plane, split into triangles
+fn grid_mesh(device: &Device, n: u32) -> Geometry<'_> {
+    let mut positions = Vec::new();
+    for y in 0..=n {
+        for x in 0..=n {
+            positions.push([x as f32 / n as f32, y as f32 / n as f32, 0.0]);
+        }
+    }
+    let mut indices = Vec::new();
+    for y in 0..n {
+        for x in 0..n {
+            let i = y * (n + 1) + x;
+            indices.push([i, i + 1, i + n + 1]);
+            indices.push([i + 1, i + n + 2, i + n + 1]);
+        }
+    }
+    Geometry::Triangle(TriangleMesh::try_from_slices(device, &positions, &indices).unwrap())
+}
+
+#[test]
+fn join_commit_from_threads() {
+    let device = Device::new();
+    let mut scene = Scene::new(&device);
+    for _ in 0..8 {
+        scene.attach_geometry(grid_mesh(&device, 256));
+    }
+    scene.set_auto_commit_geometry(true);
+
+    let scene = &scene;
+    let tokens: Vec<_> = (0..4)
+        .into_par_iter()
+        .map(|_| {
+            let rtscene = scene.join_commit();
+            let t = rtscene.hit_distance(Vector3::new(0.5, 0.5, 1.0), Vector3::new(0.0, 0.0, -1.0));
+            assert!((t.unwrap() - 1.0).abs() < 1e-5);
+            rtscene.token()
+        })
+        .collect();
+    assert!(scene.last_build_time().is_some());
+    // Threads in the same join share its token, and later commits get
+    // newer tokens
+    let latest = *tokens.iter().max().unwrap();
+    assert!(scene.commit().token() > latest);
+}
@@ -0,0 +1,37 @@
+#![cfg(feature = "leak-check")]
+
+extern crate embree;
+
+use embree::leak_check::{live_objects, LiveObjects};
+use embree::{Device, Geometry, Scene, TriangleMesh};
+
+// The counts are global, so everything is checked in a single test to
+// keep other tests from creating objects concurrently
+#[test]
+fn objects_are_released() {
+    let base = live_objects();
+    {
+        let device = Device::new();
+        let mut scene = Scene::new(&device);
+        let mut mesh = TriangleMesh::unanimated(&device, 1, 3);
+        {
+            let mut verts = mesh.vertex_buffer.map();
+            verts[1].x = 1.0;
+            verts[2].y = 1.0;
+        }
+        let mut geom = Geometry::Triangle(mesh);
+        geom.commit();
+        scene.attach_geometry(geom);
+        assert_eq!(
+            live_objects(),
+            LiveObjects {
+                devices: base.devices + 1,
+                scenes: base.scenes + 1,
+                geometries: base.geometries + 1,
+                // The vertex and index buffers
+                buffers: base.buffers + 2,
+            }
+        );
+    }
+    assert_eq!(live_objects(), base);
+}
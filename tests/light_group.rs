@@ -0,0 +1,71 @@
+extern crate cgmath;
+extern crate embree;
+
+use cgmath::{Vector3, Vector4};
+use embree::light_group::USER_MASK_BITS;
+use embree::{Device, DeviceProperty, Geometry, Ray, Scene, TriangleMesh};
+
+/// Build a quad covering [-1, 1]^2 at height z, facing +z
+fn make_quad(device: &Device, z: f32) -> Geometry<'_> {
+    let mut mesh = TriangleMesh::unanimated(device, 2, 4);
+    {
+        let mut verts = mesh.vertex_buffer.map();
+        verts[0] = Vector4::new(-1.0, -1.0, z, 0.0);
+        verts[1] = Vector4::new(1.0, -1.0, z, 0.0);
+        verts[2] = Vector4::new(1.0, 1.0, z, 0.0);
+        verts[3] = Vector4::new(-1.0, 1.0, z, 0.0);
+        let mut tris = mesh.index_buffer.map();
+        tris[0] = Vector3::new(0, 1, 2);
+        tris[1] = Vector3::new(0, 2, 3);
+    }
+    Geometry::Triangle(mesh)
+}
+
+#[test]
+fn shadow_rays_only_see_linked_geometry() {
+    let device = Device::new();
+    let mut scene = Scene::new(&device);
+    let mut blocker = make_quad(&device, 1.0);
+    blocker.set_light_group(0);
+    blocker.commit();
+    let blocker = scene.attach_geometry(blocker);
+    let mut linked = make_quad(&device, 2.0);
+    linked.set_light_groups(&[1, 2]);
+    linked.commit();
+    let linked = scene.attach_geometry(linked);
+    let rtscene = scene.commit();
+
+    let org = Vector3::new(0.2, 0.3, 0.0);
+    let up = Vector3::new(0.0, 0.0, 1.0);
+
+    // Camera rays see all the geometry, using either the user bits or the
+    // default mask
+    let mut camera = Ray::new(org, up);
+    camera.mask = USER_MASK_BITS;
+    let hit = rtscene.intersect_ray(&camera).unwrap();
+    assert_eq!(hit.hit.geomID, blocker);
+    assert_eq!(
+        rtscene
+            .intersect_ray(&Ray::new(org, up))
+            .unwrap()
+            .hit
+            .geomID,
+        blocker
+    );
+
+    // Rays for a group always see the geometry in it
+    let ray = Ray::new(org, up).for_light_group(0);
+    assert_eq!(rtscene.intersect_ray(&ray).unwrap().hit.geomID, blocker);
+
+    if device.property(DeviceProperty::RAY_MASK_SUPPORTED) == 0 {
+        return;
+    }
+    // Rays for other groups pass through it
+    for &group in &[1, 2] {
+        let ray = Ray::new(org, up).for_light_group(group);
+        assert_eq!(rtscene.intersect_ray(&ray).unwrap().hit.geomID, linked);
+    }
+    let ray = Ray::new(org, up).for_light_group(3);
+    assert!(rtscene.intersect_ray(&ray).is_none());
+    assert!(!rtscene.is_occluded(&ray));
+}
@@ -0,0 +1,143 @@
+extern crate cgmath;
+extern crate embree;
+
+use cgmath::{Matrix4, Vector3, Vector4};
+use embree::{
+    Bounds, BufferType, Capsule, Device, Format, Geometry, Instance, Scene, TriangleMesh,
+    UserGeometry,
+};
+
+fn contains(outer: &Bounds, inner: &Bounds) -> bool {
+    let eps = 1e-4;
+    outer.lower_x <= inner.lower_x + eps
+        && outer.lower_y <= inner.lower_y + eps
+        && outer.lower_z <= inner.lower_z + eps
+        && outer.upper_x >= inner.upper_x - eps
+        && outer.upper_y >= inner.upper_y - eps
+        && outer.upper_z >= inner.upper_z - eps
+}
+
+#[test]
+fn motion_blurred_triangle() {
+    let device = Device::new();
+    // A triangle moving up in y over three time steps, with the middle
+    // step off to the side in x
+    let offsets = [
+        Vector3::new(0.0, 0.0, 0.0),
+        Vector3::new(2.0, 1.0, 0.0),
+        Vector3::new(0.0, 2.0, 0.0),
+    ];
+    let mut mesh = TriangleMesh::animated(&device, 1, 3, 3);
+    for (t, o) in offsets.iter().enumerate() {
+        let buf = if t == 0 {
+            &mut mesh.vertex_buffer
+        } else {
+            &mut mesh.motion_vertex_buffers[t - 1]
+        };
+        let mut verts = buf.map();
+        verts[0] = Vector4::new(o.x, o.y, o.z, 0.0);
+        verts[1] = Vector4::new(o.x + 1.0, o.y, o.z, 0.0);
+        verts[2] = Vector4::new(o.x, o.y + 1.0, o.z, 0.0);
+    }
+    mesh.index_buffer.map()[0] = Vector3::new(0, 1, 2);
+    let mut geom = Geometry::Triangle(mesh);
+    geom.commit();
+
+    let steps: Vec<Bounds> = offsets
+        .iter()
+        .map(|o| Bounds {
+            lower_x: o.x,
+            lower_y: o.y,
+            lower_z: o.z,
+            align0: 0.0,
+            upper_x: o.x + 1.0,
+            upper_y: o.y + 1.0,
+            upper_z: o.z,
+            align1: 0.0,
+        })
+        .collect();
+
+    let lb = geom.linear_bounds().unwrap();
+    let mut scene = Scene::new(&device);
+    scene.attach_geometry(geom);
+    let rtscene = scene.commit();
+    let scene_lb = rtscene.linear_bounds();
+    for (t, step) in steps.iter().enumerate() {
+        let time = t as f32 / 2.0;
+        assert!(contains(&lb.at(time), step), "step {} not contained", t);
+        assert!(
+            contains(&scene_lb.at(time), step),
+            "step {} not in scene",
+            t
+        );
+    }
+    assert!(contains(&scene_lb.shutter_bounds(), &rtscene.bounds()));
+}
+
+fn unit_triangle<'a>(device: &'a Device) -> Geometry<'a> {
+    let mesh = TriangleMesh::try_from_slices(
+        device,
+        &[[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]],
+        &[[0, 1, 2]],
+    )
+    .unwrap();
+    let mut geom = Geometry::Triangle(mesh);
+    geom.commit();
+    geom
+}
+
+#[test]
+fn instance_and_user_geometry_bounds() {
+    let device = Device::new();
+    let mut inner = Scene::new(&device);
+    inner.attach_geometry(unit_triangle(&device));
+    let rtinner = inner.commit();
+
+    let mut instance = Instance::unanimated(&device, &rtinner);
+    instance.set_transform(&Matrix4::from_translation(Vector3::new(10.0, 0.0, 0.0)));
+    let mut instance = Geometry::Instance(instance);
+    instance.commit();
+    let b = instance.bounds().unwrap();
+    assert!((b.lower_x - 10.0).abs() < 1e-4 && (b.upper_x - 11.0).abs() < 1e-4);
+
+    let capsule = Capsule::new(
+        Vector3::new(0.0, 0.0, 0.0),
+        Vector3::new(0.0, 2.0, 0.0),
+        0.5,
+    );
+    let mut user = Geometry::User(UserGeometry::new(&device, vec![capsule]));
+    user.commit();
+    let b = user.bounds().unwrap();
+    assert!(b.lower_y <= -0.5 && b.upper_y >= 2.5 && b.upper_x >= 0.5);
+
+    // The scene's bounds cover the enabled geometry, without committing
+    let mut scene = Scene::new(&device);
+    scene.attach_geometry(instance);
+    let user_id = scene.attach(user);
+    assert!(contains(&scene.bounds().unwrap(), &b));
+    assert!(scene.bounds().unwrap().upper_x >= 11.0 - 1e-4);
+    scene.geometry_mut(user_id).unwrap().set_enabled(false);
+    assert!(scene.bounds().unwrap().lower_x >= 10.0 - 1e-4);
+    let rtscene = scene.commit();
+    assert!(contains(&scene.bounds().unwrap(), &rtscene.bounds()));
+}
+
+#[test]
+fn shared_buffers_have_no_bounds() {
+    let device = Device::new();
+    let mut vertices = vec![[0.0f32, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]];
+    vertices.push([0.0; 3]);
+    let mut geom = unit_triangle(&device);
+    // The mesh's own vertices no longer bound the geometry once replaced
+    geom.set_shared_buffer_from_slice(BufferType::VERTEX, 0, Format::FLOAT3, &vertices, 3);
+    geom.commit();
+    assert!(geom.linear_bounds().is_none());
+    assert!(geom.bounds().is_none());
+
+    let mut scene = Scene::new(&device);
+    scene.attach_geometry(unit_triangle(&device));
+    let id = scene.attach(geom);
+    assert!(scene.bounds().is_none());
+    scene.geometry_mut(id).unwrap().set_enabled(false);
+    assert!(scene.bounds().is_some());
+}
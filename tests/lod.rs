@@ -0,0 +1,55 @@
+#![cfg(feature = "subdivision")]
+
+extern crate cgmath;
+extern crate embree;
+
+use cgmath::{Vector3, Vector4};
+use embree::{Device, Geometry, LodController, Ray, Scene, SubdivisionMesh};
+
+/// Build a single quad face on [-1, 1]^2 in the z = 0 plane
+fn make_quad(device: &Device) -> Geometry<'_> {
+    let mut mesh = SubdivisionMesh::unanimated(device, 1, 4, 4);
+    {
+        let mut verts = mesh.vertex_buffer.map();
+        verts[0] = Vector4::new(-1.0, -1.0, 0.0, 0.0);
+        verts[1] = Vector4::new(1.0, -1.0, 0.0, 0.0);
+        verts[2] = Vector4::new(1.0, 1.0, 0.0, 0.0);
+        verts[3] = Vector4::new(-1.0, 1.0, 0.0, 0.0);
+        mesh.face_buffer.map()[0] = 4;
+    }
+    {
+        let mut topology = mesh.base_topology();
+        let mut indices = topology.index_buffer().map();
+        for i in 0..4 {
+            indices[i] = i as u32;
+        }
+    }
+    let mut geom = Geometry::Subdivision(mesh);
+    geom.commit();
+    geom
+}
+
+#[test]
+fn update_on_threshold_crossing() {
+    let device = Device::new();
+    let mut scene = Scene::new(&device);
+    let id = scene.attach_geometry(make_quad(&device));
+
+    let mut lod = LodController::new().level(5.0, 16.0).level(50.0, 2.0);
+    lod.track(&scene, id);
+    assert_eq!(lod.current_level(id), None);
+
+    assert_eq!(lod.update(&mut scene, Vector3::new(0.0, 0.0, 3.0)), 1);
+    assert_eq!(lod.current_level(id), Some(0));
+    // Moving within the level doesn't recommit the geometry
+    assert_eq!(lod.update(&mut scene, Vector3::new(0.0, 1.0, 4.0)), 0);
+    assert_eq!(lod.update(&mut scene, Vector3::new(0.0, 0.0, 20.0)), 1);
+    assert_eq!(lod.current_level(id), Some(1));
+
+    let rtscene = scene.commit();
+    let hit = rtscene.intersect_ray(&Ray::new(
+        Vector3::new(0.1, 0.1, 1.0),
+        Vector3::new(0.0, 0.0, -1.0),
+    ));
+    assert_eq!(hit.map(|h| h.hit.geomID), Some(id));
+}
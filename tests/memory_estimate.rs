@@ -0,0 +1,28 @@
+extern crate embree;
+
+use embree::{Device, Geometry, MemoryModel, Scene, SceneFlags, TriangleMesh};
+
+#[test]
+fn estimate_compact_scene() {
+    let mut device = Device::new();
+    let model = MemoryModel::calibrate(&mut device);
+    let mut scene = Scene::new(&device);
+    let mesh = TriangleMesh::try_from_slices(
+        &device,
+        &[[-1.0, -1.0, 0.0], [1.0, -1.0, 0.0], [0.0, 1.0, 0.0]],
+        &[[0, 1, 2]],
+    )
+    .unwrap();
+    scene.attach_geometry(Geometry::Triangle(mesh));
+
+    let estimate = scene.estimate_memory_usage();
+    assert!(estimate.buffers > 0);
+    assert!(estimate.lower_bound() <= estimate.total());
+    assert!(estimate.total() <= estimate.upper_bound());
+
+    let compact = model.estimate(&scene, true);
+    assert!(compact.bvh <= model.estimate(&scene, false).bvh);
+    scene.set_flags(SceneFlags::COMPACT);
+    let rtscene = scene.commit();
+    assert!(rtscene.is_current());
+}
@@ -0,0 +1,121 @@
+extern crate cgmath;
+extern crate embree;
+
+use std::sync::atomic::{AtomicIsize, Ordering};
+use std::sync::Arc;
+
+use embree::{testing, Device};
+
+#[test]
+fn tracks_scene_allocations() {
+    let mut device = Device::new();
+    let current = Arc::new(AtomicIsize::new(0));
+    let peak = Arc::new(AtomicIsize::new(0));
+    {
+        let current = current.clone();
+        let peak = peak.clone();
+        // Allocations are reported before they happen and frees after, as
+        // negative deltas, so every call is counted
+        device.set_memory_monitor_function(move |bytes, _post| {
+            let now = current.fetch_add(bytes, Ordering::Relaxed) + bytes;
+            peak.fetch_max(now, Ordering::Relaxed);
+            true
+        });
+    }
+    let start = current.load(Ordering::Relaxed);
+    {
+        let config = testing::SceneConfig::new().spheres(8).meshes(2);
+        let scene = testing::generate_scene(&device, &config, None);
+        let rtscene = scene.commit();
+        assert!(rtscene.bounds().upper_x > rtscene.bounds().lower_x);
+    }
+    assert!(peak.load(Ordering::Relaxed) > start);
+    // Dropping the scene releases everything it allocated
+    assert_eq!(current.load(Ordering::Relaxed), start);
+
+    device.clear_memory_monitor_function();
+}
+
+#[test]
+fn holds_memory_monitor_panics() {
+    let mut device = Device::new();
+    device.set_memory_monitor_function(|bytes, post| {
+        if bytes > 0 && !post {
+            panic!("monitor panic");
+        }
+        true
+    });
+    {
+        let config = testing::SceneConfig::new().spheres(8).meshes(2);
+        let scene = testing::generate_scene(&device, &config, None);
+        let _ = scene.commit();
+    }
+    let held = device
+        .take_memory_monitor_panic()
+        .expect("panic was not held");
+    assert_eq!(held.downcast_ref::<&str>(), Some(&"monitor panic"));
+    assert!(device.take_memory_monitor_panic().is_none());
+
+    device.clear_memory_monitor_function();
+}
+
+#[test]
+fn refuses_allocations_past_budget() {
+    let mut device = Device::new();
+    let budget = device.set_memory_budget(1024);
+    {
+        let config = testing::SceneConfig::new().spheres(8).meshes(2);
+        let scene = testing::generate_scene(&device, &config, None);
+        let _ = scene.commit();
+    }
+    assert!(budget.refused() > 0);
+    assert!(budget.peak() <= budget.limit());
+
+    device.clear_memory_monitor_function();
+}
+
+#[test]
+fn budget_counts_memory_freed_by_dropped_scenes() {
+    let mut device = Device::new();
+    let budget = device.set_memory_budget(256 << 20);
+    let config = testing::SceneConfig::new().spheres(8).meshes(2);
+    let mut after_drop = Vec::new();
+    for _ in 0..2 {
+        {
+            let scene = testing::generate_scene(&device, &config, None);
+            let rtscene = scene.commit();
+            assert!(rtscene.bounds().upper_x > rtscene.bounds().lower_x);
+        }
+        after_drop.push(budget.used());
+    }
+    // Rebuilding the same scene after dropping the first one reuses the
+    // budget freed by the drop instead of counting both
+    assert_eq!(after_drop[0], after_drop[1]);
+    assert!(budget.peak() > budget.used());
+    assert_eq!(budget.refused(), 0);
+
+    device.clear_memory_monitor_function();
+}
+
+#[test]
+fn reports_build_progress() {
+    let device = Device::new();
+    let config = testing::SceneConfig::new().spheres(8).meshes(2);
+    let mut scene = testing::generate_scene(&device, &config, None);
+    let calls = Arc::new(AtomicIsize::new(0));
+    {
+        let calls = calls.clone();
+        scene.set_progress_monitor_function(move |progress| {
+            assert!((0.0..=1.0).contains(&progress));
+            calls.fetch_add(1, Ordering::Relaxed);
+            true
+        });
+    }
+    {
+        let rtscene = scene.commit();
+        assert!(rtscene.bounds().upper_x > rtscene.bounds().lower_x);
+    }
+    assert!(calls.load(Ordering::Relaxed) > 0);
+
+    scene.clear_progress_monitor_function();
+}
@@ -0,0 +1,68 @@
+extern crate cgmath;
+extern crate embree;
+
+use cgmath::Vector3;
+use embree::{
+    Device, Geometry, IntersectContext, MeshError, QuadMesh, Ray, RayHit, Scene, TriangleMesh,
+};
+
+const POSITIONS: [[f32; 3]; 4] = [
+    [-1.0, -1.0, 0.0],
+    [1.0, -1.0, 0.0],
+    [1.0, 1.0, 0.0],
+    [-1.0, 1.0, 0.0],
+];
+
+/// Trace a ray down -Z through (x, y) and return the hit primitive, if any
+fn trace<'a>(device: &'a Device, geom: Geometry<'a>, x: f32, y: f32) -> Option<u32> {
+    let mut scene = Scene::new(device);
+    scene.attach_geometry(geom);
+    let rtscene = scene.commit();
+    let mut ctx = IntersectContext::coherent();
+    let mut ray_hit = RayHit::new(Ray::new(
+        Vector3::new(x, y, 1.0),
+        Vector3::new(0.0, 0.0, -1.0),
+    ));
+    rtscene.intersect(&mut ctx, &mut ray_hit);
+    if ray_hit.hit.hit() {
+        Some(ray_hit.hit.primID)
+    } else {
+        None
+    }
+}
+
+#[test]
+fn triangle_mesh_from_slices() {
+    let device = Device::new();
+    let tris = TriangleMesh::try_from_slices(&device, &POSITIONS, &[[0, 1, 2], [0, 2, 3]]).unwrap();
+    assert_eq!(tris.vertex_buffer.len(), 4);
+    assert_eq!(tris.index_buffer.len(), 2);
+    assert_eq!(trace(&device, Geometry::Triangle(tris), 0.5, -0.5), Some(0));
+
+    let tris = TriangleMesh::try_from_slices(&device, &POSITIONS, &[[0, 1, 2], [0, 2, 3]]).unwrap();
+    assert_eq!(trace(&device, Geometry::Triangle(tris), -0.5, 0.5), Some(1));
+}
+
+#[test]
+fn quad_mesh_from_slices() {
+    let device = Device::new();
+    let quads = QuadMesh::try_from_slices(&device, &POSITIONS, &[[0, 1, 2, 3]]).unwrap();
+    assert_eq!(trace(&device, Geometry::Quad(quads), 0.25, 0.25), Some(0));
+}
+
+#[test]
+fn mesh_from_slices_bad_index() {
+    let device = Device::new();
+    let result = TriangleMesh::try_from_slices(&device, &POSITIONS, &[[0, 1, 7]]);
+    match result {
+        Err(e) => assert_eq!(
+            e,
+            MeshError::IndexOutOfBounds {
+                primitive: 0,
+                index: 7,
+                num_verts: 4,
+            }
+        ),
+        Ok(_) => panic!("expected an out of bounds index error"),
+    }
+}
@@ -0,0 +1,109 @@
+extern crate cgmath;
+extern crate embree;
+
+mod common;
+
+use cgmath::{InnerSpace, Matrix4, Vector3, Vector4};
+use embree::{CommittedScene, Device, Geometry, Instance, Ray, Scene, ShadeContext};
+
+/// Build a triangle in the z = 0 plane facing +z, off center in x so
+/// mirroring it across x = 0 moves it
+fn make_triangle(device: &Device) -> Geometry<'_> {
+    common::committed_triangle(
+        device,
+        [[0.0, -1.0, 0.0], [2.0, -1.0, 0.0], [1.0, 1.0, 0.0]],
+    )
+}
+
+/// Render the z component of the world space normal seen by rays looking
+/// down -z over the square [-2, 2] in x and y, 0 where the rays miss
+fn render(scene: &CommittedScene, size: usize) -> Vec<f32> {
+    let mut image = Vec::with_capacity(size * size);
+    for j in 0..size {
+        for i in 0..size {
+            let x = -2.0 + 4.0 * (i as f32 + 0.5) / size as f32;
+            let y = -2.0 + 4.0 * (j as f32 + 0.5) / size as f32;
+            let ray = Ray::new(Vector3::new(x, y, 1.0), Vector3::new(0.0, 0.0, -1.0));
+            image.push(match scene.intersect_ray(&ray) {
+                Some(hit) => ShadeContext::new(scene, hit).normal().z,
+                None => 0.0,
+            });
+        }
+    }
+    image
+}
+
+#[test]
+fn mirrored_instance() {
+    let device = Device::new();
+    let mut inner = Scene::new(&device);
+    inner.attach_geometry(make_triangle(&device));
+    let rtinner = inner.commit();
+
+    let mirror = Matrix4::from_nonuniform_scale(-1.0, 1.0, 1.0);
+    let mut instance = Instance::unanimated(&device, &rtinner);
+    assert!(!instance.flips_orientation());
+    instance.set_transform(&mirror);
+    assert!(instance.flips_orientation());
+    assert!(instance.is_invertible());
+
+    // Mirroring reverses the winding of the triangle in world space, so the
+    // normal of the transformed vertices faces -z
+    let v = [
+        mirror * Vector4::new(0.0, -1.0, 0.0, 1.0),
+        mirror * Vector4::new(2.0, -1.0, 0.0, 1.0),
+        mirror * Vector4::new(1.0, 1.0, 0.0, 1.0),
+    ];
+    let winding_normal = (v[1] - v[0]).truncate().cross((v[2] - v[0]).truncate());
+    assert!(winding_normal.z < 0.0);
+
+    let mut geom = Geometry::Instance(instance);
+    geom.commit();
+    let mut scene = Scene::new(&device);
+    let inst_id = scene.attach(geom);
+    let rtscene = scene.commit();
+
+    // Embree reports the object space normal, which still faces +z, and
+    // transforming it keeps it on the same side of the surface
+    let ray = Ray::new(Vector3::new(-1.0, 0.0, 1.0), Vector3::new(0.0, 0.0, -1.0));
+    let hit = rtscene.intersect_ray(&ray).unwrap();
+    assert!(hit.hit.normal().z > 0.0);
+    if let Geometry::Instance(ref inst) = *scene.geometry(inst_id).unwrap() {
+        let n = inst.normal_to_world(hit.hit.normal());
+        assert!((n - Vector3::new(0.0, 0.0, 1.0)).magnitude() < 1e-5);
+    }
+    let ctx = ShadeContext::new(&rtscene, hit);
+    assert!(ctx.front_facing());
+
+    // The mirrored render is the direct one flipped horizontally, with the
+    // same normals
+    let size = 16;
+    let direct = render(&rtinner, size);
+    let mirrored = render(&rtscene, size);
+    assert!(direct.iter().any(|n| *n > 0.99));
+    for j in 0..size {
+        for i in 0..size {
+            let m = mirrored[j * size + i];
+            let d = direct[j * size + size - 1 - i];
+            assert!((m - d).abs() < 1e-5);
+        }
+    }
+}
+
+#[test]
+fn flattened_instance() {
+    let device = Device::new();
+    let mut inner = Scene::new(&device);
+    inner.attach_geometry(make_triangle(&device));
+    let rtinner = inner.commit();
+
+    let mut instance = Instance::unanimated(&device, &rtinner);
+    instance.set_transform(&Matrix4::from_nonuniform_scale(1.0, 0.0, 1.0));
+    assert!(!instance.is_invertible());
+    assert!(!instance.flips_orientation());
+    instance.set_transform(&Matrix4::from_nonuniform_scale(-1.0, -1.0, -1.0));
+    assert!(instance.is_invertible());
+    assert!(instance.flips_orientation());
+    instance.set_transform(&Matrix4::from_nonuniform_scale(-1.0, -1.0, 1.0));
+    assert!(!instance.flips_orientation());
+}
@@ -0,0 +1,87 @@
+extern crate cgmath;
+extern crate embree;
+
+use cgmath::{Vector3, Vector4};
+use embree::{
+    BufferType, Device, Error, Geometry, QuadMesh, Ray, Scene, TriangleMesh, ValidationError,
+};
+
+fn triangle(x: f32) -> [[f32; 3]; 3] {
+    [[x - 1.0, -1.0, 0.0], [x + 1.0, -1.0, 0.0], [x, 1.0, 0.0]]
+}
+
+#[test]
+fn triangle_moves_between_time_steps() {
+    let device = Device::new();
+    let mut mesh = TriangleMesh::unanimated(&device, 1, 3);
+    mesh.index_buffer.map()[0] = Vector3::new(0, 1, 2);
+    let mut geom = Geometry::Triangle(mesh);
+    let (start, end) = (triangle(0.0), triangle(4.0));
+    geom.set_motion_vertex_buffers(&[&start[..], &end[..]]);
+    geom.commit();
+
+    let mut scene = Scene::new(&device);
+    scene.attach_geometry(geom);
+    let rtscene = scene.commit();
+
+    let mut ray = Ray::new(Vector3::new(4.0, 0.0, 1.0), Vector3::new(0.0, 0.0, -1.0));
+    assert!(rtscene.intersect_ray(&ray).is_none());
+    ray.time = 1.0;
+    assert!(rtscene.intersect_ray(&ray).is_some());
+}
+
+#[test]
+fn time_steps_are_mapped_separately() {
+    let device = Device::new();
+    let mut mesh = TriangleMesh::animated(&device, 1, 3, 3);
+    assert_eq!(mesh.time_step_count(), 3);
+    for t in 0..3 {
+        let mut verts = mesh.time_step(t).map();
+        verts[0] = Vector4::new(t as f32, 0.0, 0.0, 0.0);
+    }
+    assert_eq!(mesh.motion_vertex_buffers[1].map()[0].x, 2.0);
+}
+
+#[test]
+fn mismatched_time_steps_are_rejected() {
+    let device = Device::new();
+    let mut mesh = TriangleMesh::unanimated(&device, 1, 3);
+    let start = triangle(0.0);
+    let end = &triangle(1.0)[..2];
+    assert_eq!(
+        mesh.set_time_steps(&[&start[..], end]),
+        Err(ValidationError::BufferSize {
+            buf_type: BufferType::VERTEX,
+            slot: 1,
+            expected: 3,
+            actual: 2,
+        })
+    );
+    assert_eq!(mesh.time_step_count(), 1);
+}
+
+#[test]
+fn try_set_motion_vertex_buffers_reports_errors() {
+    let device = Device::new();
+    let start = triangle(0.0);
+    let end = &triangle(1.0)[..2];
+    let mut geom = Geometry::Triangle(TriangleMesh::unanimated(&device, 1, 3));
+    assert_eq!(
+        geom.try_set_motion_vertex_buffers(&[&start[..], end]),
+        Err(Error::INVALID_ARGUMENT)
+    );
+    assert_eq!(
+        geom.try_set_motion_vertex_buffers::<[f32; 3]>(&[]),
+        Err(Error::INVALID_ARGUMENT)
+    );
+    assert_eq!(
+        geom.try_set_motion_vertex_buffers(&[&start[..], &start[..]]),
+        Ok(())
+    );
+
+    let mut quads = Geometry::Quad(QuadMesh::unanimated(&device, 1, 4));
+    assert_eq!(
+        quads.try_set_motion_vertex_buffers(&[&start[..]]),
+        Err(Error::INVALID_OPERATION)
+    );
+}
@@ -0,0 +1,49 @@
+extern crate cgmath;
+extern crate embree;
+
+mod common;
+
+use cgmath::{Vector3, Vector4};
+use embree::{Device, Geometry, Ray, Scene, TriangleMesh};
+
+#[test]
+fn tracks_changes() {
+    let device = Device::new();
+    let mut geom = common::triangle(&device, common::centered_triangle(0.0));
+    assert!(geom.needs_commit());
+    geom.commit();
+    assert!(!geom.needs_commit());
+
+    if let Geometry::Triangle(ref mut mesh) = geom {
+        mesh.vertex_buffer.map()[2] = Vector4::new(0.0, 2.0, 0.0, 0.0);
+    }
+    assert!(geom.needs_commit());
+    geom.commit();
+    geom.set_mask(1);
+    assert!(geom.needs_commit());
+    geom.commit();
+    geom.set_intersect_filter_function(|_, _| true);
+    assert!(geom.needs_commit());
+
+    let mesh = TriangleMesh::try_from_slices(
+        &device,
+        &[[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]],
+        &[[0, 1, 2]],
+    )
+    .unwrap();
+    assert!(!Geometry::Triangle(mesh).needs_commit());
+}
+
+#[test]
+fn auto_commit_geometry() {
+    let device = Device::new();
+    let mut scene = Scene::new(&device);
+    scene.set_auto_commit_geometry(true);
+    let id = scene.attach(common::triangle(&device, common::centered_triangle(0.0)));
+    assert!(scene.geometry(id).unwrap().needs_commit());
+
+    let rtscene = scene.commit();
+    assert!(!scene.geometry(id).unwrap().needs_commit());
+    let ray = Ray::new(Vector3::new(0.0, 0.0, 1.0), Vector3::new(0.0, 0.0, -1.0));
+    assert!(rtscene.intersect_ray(&ray).is_some());
+}
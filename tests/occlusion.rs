@@ -0,0 +1,34 @@
+extern crate cgmath;
+extern crate embree;
+
+use cgmath::Vector3;
+use embree::{Device, Geometry, IntersectContext, Occlusion, Ray, Scene, TriangleMesh};
+
+#[test]
+fn occlusion_leaves_ray_unchanged() {
+    let device = Device::new();
+    let mesh = TriangleMesh::try_from_slices(
+        &device,
+        &[[-1.0, -1.0, 0.0], [1.0, -1.0, 0.0], [0.0, 1.0, 0.0]],
+        &[[0, 1, 2]],
+    )
+    .unwrap();
+    let mut scene = Scene::new(&device);
+    scene.attach_geometry(Geometry::Triangle(mesh));
+    let rtscene = scene.commit();
+
+    let blocked = Ray::new(Vector3::new(0.0, 0.0, 1.0), Vector3::new(0.0, 0.0, -1.0));
+    assert_eq!(rtscene.occlusion(&blocked), Occlusion::Occluded);
+    assert_eq!(blocked.tfar, f32::INFINITY);
+
+    let clear = Ray::new(Vector3::new(4.0, 0.0, 1.0), Vector3::new(0.0, 0.0, -1.0));
+    let mut ctx = IntersectContext::coherent();
+    assert!(rtscene
+        .occlusion_with_context(&mut ctx, &clear)
+        .is_visible());
+
+    // The in place query marks the ray, which Occlusion::of reads back
+    let mut ray = blocked;
+    rtscene.occluded(&mut ctx, &mut ray);
+    assert!(Occlusion::of(&ray).is_occluded());
+}
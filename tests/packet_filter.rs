@@ -0,0 +1,49 @@
+#![cfg(feature = "packets")]
+
+extern crate cgmath;
+extern crate embree;
+
+mod common;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use cgmath::Vector3;
+use embree::{Device, IntersectContext, Ray, Ray4, RayHit4, Scene};
+
+#[test]
+fn packet_filters() {
+    let device = Device::new();
+    let calls = AtomicUsize::new(0);
+    let mut geom = common::make_layers(&device);
+    // Reject hits on the odd triangle
+    geom.set_intersect_filter_function4(|packet| {
+        calls.fetch_add(1, Ordering::Relaxed);
+        assert_eq!(packet.ray as *const Ray4 as usize % 16, 0);
+        for i in 0..4 {
+            if packet.is_valid(i) && packet.hit.primID[i] % 2 == 1 {
+                packet.reject(i);
+            }
+        }
+    });
+    geom.commit();
+    let mut scene = Scene::new(&device);
+    scene.attach_geometry(geom);
+    let rtscene = scene.commit();
+
+    // Single ray queries are passed to the filter in a packet of 4
+    let ray = Ray::new(Vector3::new(0.0, 0.0, 2.0), Vector3::new(0.0, 0.0, -1.0));
+    let hit = rtscene.intersect_ray(&ray).unwrap();
+    assert_eq!(hit.hit.primID, 0);
+    assert!(calls.load(Ordering::Relaxed) > 0);
+
+    let origin = Vector3::new(0.0, 0.0, 2.0);
+    let dir = Vector3::new(0.0, 0.0, -1.0);
+    let mut ray_hit = RayHit4::new(Ray4::new([origin; 4], [dir; 4]));
+    let mut ctx = IntersectContext::coherent();
+    rtscene.intersect4(&mut ctx, &mut ray_hit, &[-1, -1, 0, -1]);
+    for i in [0, 1, 3] {
+        assert_eq!(ray_hit.hit.primID[i], 0);
+        assert!((ray_hit.ray.tfar[i] - 2.0).abs() < 1e-4);
+    }
+    assert_eq!(ray_hit.hit.geomID[2], u32::MAX);
+}
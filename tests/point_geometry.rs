@@ -0,0 +1,45 @@
+extern crate cgmath;
+extern crate embree;
+
+use cgmath::Vector3;
+use embree::{
+    BufferType, Device, Geometry, GeometryKind, PointGeometry, PointType, Scene, SceneQuery,
+};
+
+#[test]
+fn sphere_points() {
+    let device = Device::new();
+    let points = PointGeometry::spheres(&device, &[[0.0, 0.0, 0.0, 0.5], [2.0, 0.0, 0.0, 1.0]]);
+    assert_eq!(points.point_type(), PointType::Sphere);
+    assert!(points.normal_buffer.is_none());
+    let b = points.bounds();
+    assert_eq!((b.lower_x, b.upper_x), (-0.5, 3.0));
+    assert_eq!((b.lower_y, b.upper_y), (-1.0, 1.0));
+
+    let geom = Geometry::Point(points);
+    assert_eq!(geom.kind(), GeometryKind::Point);
+    assert_eq!(geom.primitive_count(), 2);
+    assert!(geom.validate().is_ok());
+
+    let mut scene = Scene::new(&device);
+    scene.attach_geometry(geom);
+    let rtscene = scene.commit();
+    let t = rtscene.hit_distance(Vector3::new(0.0, 0.0, 2.0), Vector3::new(0.0, 0.0, -1.0));
+    assert!((t.unwrap() - 1.5).abs() < 1e-5);
+}
+
+#[test]
+fn oriented_discs_need_normals() {
+    let device = Device::new();
+    let discs = PointGeometry::oriented_discs(&device, &[[0.0, 0.0, 0.0, 1.0]], &[[0.0, 0.0, 1.0]]);
+    let geom = Geometry::Point(discs);
+    assert!(geom.required_buffers().contains(&BufferType::NORMAL));
+    assert!(geom.validate().is_ok());
+}
+
+#[test]
+#[should_panic]
+fn discs_have_no_normals() {
+    let device = Device::new();
+    let _ = PointGeometry::unanimated(&device, PointType::Disc, 1).with_normals(&[[0.0, 0.0, 1.0]]);
+}
@@ -0,0 +1,73 @@
+#![cfg(feature = "point-query")]
+
+extern crate cgmath;
+extern crate embree;
+
+mod common;
+
+use cgmath::Vector3;
+use embree::{Device, Geometry, PointQuery, Scene};
+
+fn make_triangle(device: &Device, offset: Vector3<f32>) -> Geometry<'_> {
+    let mut corners = common::UNIT_TRIANGLE;
+    for c in corners.iter_mut() {
+        *c = (offset + Vector3::from(*c)).into();
+    }
+    common::committed_triangle(device, corners)
+}
+
+#[test]
+fn point_query_batch_finds_nearby_geometry() {
+    let device = Device::new();
+    let mut scene = Scene::new(&device);
+    let left = scene.attach_geometry(make_triangle(&device, Vector3::new(-10.0, 0.0, 0.0)));
+    let right = scene.attach_geometry(make_triangle(&device, Vector3::new(10.0, 0.0, 0.0)));
+    let rtscene = scene.commit();
+
+    // Single query near the left triangle
+    let mut query = PointQuery::new(Vector3::new(-9.75, 0.25, 0.5), 1.0);
+    let mut found = Vec::new();
+    rtscene.point_query(&mut query, |_, prim| {
+        found.push(prim.geom_id);
+        false
+    });
+    assert_eq!(found, vec![left]);
+
+    // A batch alternating between the two triangles, with a far away
+    // query which shouldn't find anything
+    let mut queries: Vec<PointQuery> = (0..1000)
+        .map(|i| {
+            let x = if i % 2 == 0 { -9.75 } else { 10.25 };
+            PointQuery::new(Vector3::new(x, 0.25, 0.5), 1.0)
+        })
+        .collect();
+    queries.push(PointQuery::new(Vector3::new(0.0, 100.0, 0.0), 1.0));
+    let mut results = vec![Vec::new(); queries.len()];
+    rtscene.point_query_batch(&mut queries, &mut results, |_, found, prim| {
+        found.push(prim.geom_id);
+        false
+    });
+    for (i, r) in results.iter().enumerate() {
+        if i == 1000 {
+            assert!(r.is_empty());
+        } else if i % 2 == 0 {
+            assert_eq!(*r, vec![left]);
+        } else {
+            assert_eq!(*r, vec![right]);
+        }
+    }
+}
+
+#[test]
+#[should_panic(expected = "callback panicked")]
+fn point_query_batch_resumes_callback_panic() {
+    let device = Device::new();
+    let mut scene = Scene::new(&device);
+    scene.attach_geometry(make_triangle(&device, Vector3::new(0.0, 0.0, 0.0)));
+    let rtscene = scene.commit();
+    let mut queries = vec![PointQuery::new(Vector3::new(0.25, 0.25, 0.5), 1.0); 256];
+    let mut results = vec![0; queries.len()];
+    rtscene.point_query_batch(&mut queries, &mut results, |_, _, _| {
+        panic!("callback panicked")
+    });
+}
@@ -0,0 +1,67 @@
+//! Checks that procedurally generated scenes are reproducible from their
+//! configuration, so they can be referenced by benchmarks and bug reports.
+
+extern crate cgmath;
+extern crate embree;
+
+use cgmath::InnerSpace;
+use embree::testing::{self, Pcg32, SceneConfig};
+use embree::{CommittedScene, Device, Ray};
+
+/// Trace a fixed set of random rays through the scene, returning the
+/// hit geometry and distance of each
+fn trace_rays(scene: &CommittedScene, time: f32) -> Vec<(u32, u32, f32)> {
+    let mut rng = Pcg32::new(7);
+    (0..256)
+        .map(|_| {
+            let org = rng.point_in_cube(10.0);
+            let dir = rng.point_in_cube(1.0).normalize();
+            let mut ray = Ray::new(org, dir);
+            ray.time = time;
+            match scene.intersect_ray(&ray) {
+                Some(h) => (h.hit.geomID, h.hit.primID, h.ray.tfar),
+                None => (u32::MAX, u32::MAX, 0.0),
+            }
+        })
+        .collect()
+}
+
+#[test]
+fn same_seed_same_scene() {
+    let device = Device::new();
+    let config = SceneConfig::new().spheres(32).meshes(8).seed(1234);
+    let a = testing::generate_scene(&device, &config, None);
+    let b = testing::generate_scene(&device, &config, None);
+    assert_eq!(a.geometry_ids(), b.geometry_ids());
+    let a = a.commit();
+    let b = b.commit();
+    let hits = trace_rays(&a, 0.0);
+    assert!(hits.iter().any(|h| h.0 != u32::MAX));
+    assert_eq!(hits, trace_rays(&b, 0.0));
+
+    let c = testing::generate_scene(&device, &config.clone().seed(4321), None);
+    assert_ne!(hits, trace_rays(&c.commit(), 0.0));
+}
+
+#[test]
+fn motion_blur_and_instances() {
+    let device = Device::new();
+    let prototype = testing::prototype_scene(&device, 8);
+    let prototype = prototype.commit();
+    let config = SceneConfig::new()
+        .spheres(32)
+        .meshes(8)
+        .instances(16)
+        .motion_blur(true)
+        .seed(99);
+    let a = testing::generate_scene(&device, &config, Some(&prototype));
+    assert_eq!(a.geometry_ids().len(), 32 + 8 + 16);
+    let b = testing::generate_scene(&device, &config, Some(&prototype));
+    let a = a.commit();
+    let b = b.commit();
+    for &time in &[0.0, 0.5, 1.0] {
+        assert_eq!(trace_rays(&a, time), trace_rays(&b, time));
+    }
+    // The moving geometry is in a different place at the end of the shutter
+    assert_ne!(trace_rays(&a, 0.0), trace_rays(&a, 1.0));
+}
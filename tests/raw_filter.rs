@@ -0,0 +1,57 @@
+extern crate cgmath;
+extern crate embree;
+
+mod common;
+
+use std::os::raw;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use cgmath::Vector3;
+use embree::{filter, sys, Device, Ray, Scene};
+
+/// Reject hits on odd primitives, counting the calls in the AtomicUsize
+/// passed as user data
+unsafe extern "C" fn skip_odd(args: *const sys::RTCFilterFunctionNArguments) {
+    let calls = &*(filter::user_data(args) as *const AtomicUsize);
+    calls.fetch_add(1, Ordering::Relaxed);
+    let args = &*args;
+    let n = args.N as usize;
+    let prim_ids = (args.hit as *const u32).add(5 * n);
+    for i in 0..n {
+        if *prim_ids.add(i) % 2 == 1 {
+            *args.valid.add(i) = 0;
+        }
+    }
+}
+
+#[test]
+fn raw_filters() {
+    let device = Device::new();
+    let calls = AtomicUsize::new(0);
+    let mut geom = common::make_layers(&device);
+    unsafe {
+        let user_data = &calls as *const AtomicUsize as *mut raw::c_void;
+        geom.set_intersect_filter_function_raw(Some(skip_odd), user_data);
+        geom.set_occluded_filter_function_raw(Some(skip_odd), user_data);
+    }
+    geom.commit();
+    let mut scene = Scene::new(&device);
+    scene.attach_geometry(geom);
+    let rtscene = scene.commit();
+
+    let ray = Ray::new(Vector3::new(0.0, 0.0, 2.0), Vector3::new(0.0, 0.0, -1.0));
+    let hit = rtscene.intersect_ray(&ray).unwrap();
+    assert_eq!(hit.hit.primID, 0);
+    assert!(calls.load(Ordering::Relaxed) > 0);
+
+    // Occlusion queries only see the odd triangle in front of the origin
+    let calls_before = calls.load(Ordering::Relaxed);
+    let ray = Ray::segment(
+        Vector3::new(0.0, 0.0, 2.0),
+        Vector3::new(0.0, 0.0, -1.0),
+        0.0,
+        1.5,
+    );
+    assert!(!rtscene.is_occluded(&ray));
+    assert!(calls.load(Ordering::Relaxed) > calls_before);
+}
@@ -0,0 +1,50 @@
+#![cfg(feature = "streams")]
+
+extern crate cgmath;
+extern crate embree;
+
+use std::sync::Arc;
+
+use cgmath::Vector3;
+use embree::{Device, Geometry, IntersectContext, Ray, RayCapture, RayHit, Scene, TriangleMesh};
+
+#[test]
+fn capture_stream_rays() {
+    let device = Device::new();
+    let mesh = TriangleMesh::try_from_slices(
+        &device,
+        &[[-1.0, -1.0, 0.0], [1.0, -1.0, 0.0], [0.0, 1.0, 0.0]],
+        &[[0, 1, 2]],
+    )
+    .unwrap();
+    let mut scene = Scene::new(&device);
+    scene.attach_geometry(Geometry::Triangle(mesh));
+    let capture = Arc::new(RayCapture::every(2));
+    scene.set_ray_capture(Some(capture.clone()));
+    let rtscene = scene.commit();
+
+    // Alternate rays hitting and missing the triangle
+    let mut rays: Vec<RayHit> = (0..8)
+        .map(|i| {
+            let x = if i % 4 < 2 { 0.0 } else { 5.0 };
+            RayHit::new(Ray::new(
+                Vector3::new(x, 0.0, 1.0),
+                Vector3::new(0.0, 0.0, -1.0),
+            ))
+        })
+        .collect();
+    let mut ctx = IntersectContext::coherent();
+    rtscene.intersect_stream_aos(&mut ctx, &mut rays);
+
+    let (captured, hits) = capture.take();
+    assert_eq!(captured.len(), 4);
+    let hit: Vec<bool> = hits.iter().map(|h| h.hit()).collect();
+    assert_eq!(hit, vec![true, false, true, false]);
+    assert!((captured[0].tfar - 1.0).abs() < 1e-5);
+
+    let path = std::env::temp_dir().join("embree_ray_capture.obj");
+    embree::debug::dump_rays_as_lines(&captured, &hits, &path).unwrap();
+    let obj = std::fs::read_to_string(&path).unwrap();
+    assert_eq!(obj.lines().filter(|l| l.starts_with("l ")).count(), 4);
+    std::fs::remove_file(&path).unwrap();
+}
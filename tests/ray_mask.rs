@@ -0,0 +1,52 @@
+extern crate cgmath;
+extern crate embree;
+
+mod common;
+
+use cgmath::Vector3;
+use embree::{Device, GeometryKind, Ray, RayMask, Scene};
+
+#[test]
+fn masked_rays_skip_geometry() {
+    let device = Device::new();
+    let camera = RayMask::bit(0);
+    let shadow = RayMask::bit(1);
+
+    let mut scene = Scene::new(&device);
+    let shadow_only = scene.attach(common::committed_triangle(
+        &device,
+        common::centered_triangle(1.0),
+    ));
+    let visible = scene.attach(common::committed_triangle(
+        &device,
+        common::centered_triangle(2.0),
+    ));
+    let masked = scene.set_mask_where(|id, _| id == shadow_only.raw(), shadow);
+    assert_eq!(masked, 1);
+    let all = scene.set_mask_where(|_, g| g.kind() == GeometryKind::Triangle, RayMask::ALL);
+    assert_eq!(all, 2);
+    scene.set_mask_where(|id, _| id == shadow_only.raw(), shadow);
+    scene.geometry_mut(shadow_only).unwrap().commit();
+    scene.geometry_mut(visible).unwrap().commit();
+    let rtscene = scene.commit();
+
+    let org = Vector3::new(0.0, 0.0, 0.0);
+    let up = Vector3::new(0.0, 0.0, 1.0);
+    let ray = Ray::new(org, up).with_mask(shadow);
+    assert_eq!(ray.ray_mask(), shadow);
+    assert_eq!(
+        rtscene.intersect_ray(&ray).unwrap().hit.geomID,
+        shadow_only.raw()
+    );
+
+    if !device.supports_ray_masks() {
+        return;
+    }
+    let ray = Ray::new(org, up).with_mask(camera);
+    assert_eq!(
+        rtscene.intersect_ray(&ray).unwrap().hit.geomID,
+        visible.raw()
+    );
+    let ray = Ray::new(org, up).with_mask(RayMask::NONE);
+    assert!(rtscene.intersect_ray(&ray).is_none());
+}
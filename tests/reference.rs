@@ -0,0 +1,38 @@
+#![cfg(feature = "reference")]
+
+extern crate cgmath;
+extern crate embree;
+
+use cgmath::InnerSpace;
+use embree::reference::{Backend, Tracer};
+use embree::testing::{self, Pcg32, SceneConfig};
+use embree::{Device, Ray, ReferenceScene};
+
+#[test]
+fn reference_matches_embree() {
+    let device = Device::new();
+    let config = SceneConfig::new()
+        .spheres(8)
+        .meshes(2)
+        .motion_blur(true)
+        .seed(5);
+    let scene = testing::generate_scene(&device, &config, None);
+    let rtscene = scene.commit();
+    let reference = ReferenceScene::new(&rtscene);
+    assert!(reference.skipped().is_empty());
+
+    let embree = Tracer::new(&rtscene, Backend::Embree);
+    let mut rng = Pcg32::new(3);
+    for _ in 0..128 {
+        let mut ray = Ray::new(rng.point_in_cube(10.0), rng.point_in_cube(1.0).normalize());
+        ray.time = rng.next_f32();
+        let expected = embree.intersect_ray(&ray);
+        let actual = reference.intersect_ray(&ray);
+        assert_eq!(expected.is_some(), actual.is_some());
+        if let (Some(e), Some(a)) = (expected, actual) {
+            assert_eq!((e.hit.geomID, e.hit.primID), (a.hit.geomID, a.hit.primID));
+            assert!((e.ray.tfar - a.ray.tfar).abs() < 1e-3 * e.ray.tfar.max(1.0));
+        }
+        assert_eq!(embree.is_occluded(&ray), reference.is_occluded(&ray));
+    }
+}
@@ -0,0 +1,173 @@
+//! Watertightness corner cases: rays passing exactly through edges and
+//! vertices shared by neighboring triangles, traced with axis-aligned and
+//! grazing directions, with and without `SceneFlags::ROBUST`. In robust
+//! mode every ray must hit the mesh, without it a miss is tolerated but
+//! any hit reported must still be at the right distance. These also serve
+//! as a template for validating the watertightness of other scenes.
+
+extern crate cgmath;
+extern crate embree;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use cgmath::{Vector3, Vector4};
+use embree::{Device, Geometry, Ray, Scene, SceneFlags, TriangleMesh};
+
+/// Build a mesh of 2x2 quads on [-1, 1]^2 in the z = 0 plane, each split
+/// into two triangles along its diagonal. The center vertex is shared by
+/// six triangles.
+fn make_grid(device: &Device) -> Geometry<'_> {
+    let mut mesh = TriangleMesh::unanimated(device, 8, 9);
+    {
+        let mut verts = mesh.vertex_buffer.map();
+        for j in 0..3 {
+            for i in 0..3 {
+                verts[j * 3 + i] = Vector4::new(i as f32 - 1.0, j as f32 - 1.0, 0.0, 0.0);
+            }
+        }
+        let mut tris = mesh.index_buffer.map();
+        for j in 0..2 {
+            for i in 0..2 {
+                let v0 = j * 3 + i;
+                let q = 2 * (j * 2 + i) as usize;
+                tris[q] = Vector3::new(v0, v0 + 1, v0 + 4);
+                tris[q + 1] = Vector3::new(v0, v0 + 4, v0 + 3);
+            }
+        }
+    }
+    let mut geom = Geometry::Triangle(mesh);
+    geom.commit();
+    geom
+}
+
+/// Rays through the shared edges and vertices of the grid, along with the
+/// distance they should hit it at
+fn corner_case_rays() -> Vec<(&'static str, Ray, f32)> {
+    let down = Vector3::new(0.0, 0.0, -1.0);
+    let mut rays = vec![
+        (
+            "center vertex",
+            Ray::new(Vector3::new(0.0, 0.0, 1.0), down),
+            1.0,
+        ),
+        (
+            "edge vertex",
+            Ray::new(Vector3::new(0.0, -1.0, 1.0), down),
+            1.0,
+        ),
+        (
+            "x axis edge",
+            Ray::new(Vector3::new(0.5, 0.0, 1.0), down),
+            1.0,
+        ),
+        (
+            "y axis edge",
+            Ray::new(Vector3::new(0.0, -0.5, 1.0), down),
+            1.0,
+        ),
+        (
+            "diagonal edge",
+            Ray::new(Vector3::new(0.5, 0.5, 1.0), down),
+            1.0,
+        ),
+        (
+            "diagonal edge",
+            Ray::new(Vector3::new(-0.25, -0.25, 1.0), down),
+            1.0,
+        ),
+    ];
+    // Grazing rays nearly parallel to the grid, crossing it on shared edges
+    // and at the center vertex
+    for &(x, y) in &[(0.5, 0.0), (0.0, 0.5), (0.0, 0.0)] {
+        let slope = 1e-3;
+        let dir = Vector3::new(1.0, 0.0, -slope);
+        let org = Vector3::new(x - 2.0, y, 2.0 * slope);
+        rays.push(("grazing", Ray::new(org, dir), 2.0));
+    }
+    rays
+}
+
+fn check_scene(robust: bool) {
+    let device = Device::new();
+    let mut scene = Scene::new(&device);
+    scene.attach_geometry(make_grid(&device));
+    scene.set_robust(robust);
+    assert_eq!(scene.flags().0 & SceneFlags::ROBUST.0 != 0, robust);
+    let rtscene = scene.commit();
+
+    for (name, ray, t) in corner_case_rays() {
+        match rtscene.intersect_ray(&ray) {
+            Some(ray_hit) => {
+                let t_hit = ray_hit.ray.tfar;
+                assert!(
+                    (t_hit - t).abs() < 1e-3,
+                    "{} (robust = {}): expected t = {} got {}",
+                    name,
+                    robust,
+                    t,
+                    t_hit
+                );
+            }
+            None => assert!(!robust, "{}: robust mode missed the mesh", name),
+        }
+        if robust {
+            assert!(rtscene.is_occluded(&ray), "{}: not occluded", name);
+        }
+    }
+}
+
+#[test]
+fn shared_edges_and_vertices_robust() {
+    check_scene(true);
+}
+
+#[test]
+fn shared_edges_and_vertices_default() {
+    check_scene(false);
+}
+
+#[test]
+fn robust_flag_preserves_other_flags() {
+    let device = Device::new();
+    let mut scene = Scene::new(&device);
+    scene.set_flags(SceneFlags::COMPACT);
+    scene.set_robust(true);
+    assert_eq!(
+        scene.flags().0,
+        (SceneFlags::COMPACT | SceneFlags::ROBUST).0
+    );
+    scene.set_robust(false);
+    assert_eq!(scene.flags().0, SceneFlags::COMPACT.0);
+}
+
+#[test]
+fn occluded_filter_robust() {
+    // Rejecting every candidate hit in the occlusion filter must leave
+    // rays through shared edges unoccluded, even though robust mode may
+    // report the crossing on each triangle sharing the edge
+    let device = Device::new();
+    let calls = AtomicUsize::new(0);
+    let mut geom = make_grid(&device);
+    geom.set_occluded_filter_function(|_, _| {
+        calls.fetch_add(1, Ordering::Relaxed);
+        false
+    });
+    geom.commit();
+    let mut scene = Scene::new(&device);
+    scene.attach_geometry(geom);
+    scene.set_robust(true);
+    let rtscene = scene.commit();
+
+    for (name, ray, _) in corner_case_rays() {
+        let before = calls.load(Ordering::Relaxed);
+        assert!(!rtscene.is_occluded(&ray), "{}: filter was ignored", name);
+        assert!(
+            calls.load(Ordering::Relaxed) > before,
+            "{}: filter was not called",
+            name
+        );
+    }
+    // Intersection queries don't use the occlusion filter
+    let ray = Ray::new(Vector3::new(0.5, -0.5, 1.0), Vector3::new(0.0, 0.0, -1.0));
+    assert!(rtscene.intersect_ray(&ray).is_some());
+}
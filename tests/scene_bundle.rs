@@ -0,0 +1,60 @@
+extern crate cgmath;
+extern crate embree;
+
+use cgmath::Vector3;
+use embree::{Device, Geometry, SceneBundle, SceneQuery, TriangleMesh};
+
+/// Holds the bundle like an application's state would
+struct State {
+    bundle: SceneBundle,
+}
+
+fn make_state() -> State {
+    let mut bundle = SceneBundle::new(Device::new());
+    for z in &[0.0, 1.0] {
+        let id = bundle.add_scene();
+        bundle.edit(id, |device, scene| {
+            let mesh = TriangleMesh::try_from_slices(
+                device,
+                &[[-1.0, -1.0, *z], [1.0, -1.0, *z], [0.0, 1.0, *z]],
+                &[[0, 1, 2]],
+            )
+            .unwrap();
+            let mut geom = Geometry::Triangle(mesh);
+            geom.commit();
+            scene.attach_geometry(geom)
+        });
+    }
+    State { bundle }
+}
+
+#[test]
+fn bundle_owns_scenes() {
+    // The bundle is moved out of the function building it
+    let state = make_state();
+    assert_eq!(state.bundle.len(), 2);
+    let origin = Vector3::new(0.0, 0.0, 2.0);
+    let down = Vector3::new(0.0, 0.0, -1.0);
+    for (i, z) in [0.0, 1.0].iter().enumerate() {
+        let id = state.bundle.scene_ids().nth(i).unwrap();
+        let t = state.bundle.commit(id).hit_distance(origin, down).unwrap();
+        assert!((t - (2.0 - z)).abs() < 1e-5);
+    }
+}
+
+#[test]
+fn bundle_edits_after_moves() {
+    // Moving the bundle doesn't move the boxed device the scenes borrow
+    let state = Box::new(make_state());
+    let mut bundle = Box::new(state.bundle);
+    let first = bundle.scene_ids().next().unwrap();
+    let device_addr = bundle.device() as *const Device;
+    let count = bundle.edit(first, |device, scene| {
+        assert_eq!(device as *const Device, device_addr);
+        scene.iter().count()
+    });
+    assert_eq!(count, 1);
+    let origin = Vector3::new(0.0, 0.0, 2.0);
+    let down = Vector3::new(0.0, 0.0, -1.0);
+    assert!(bundle.commit(first).hit_distance(origin, down).is_some());
+}
@@ -0,0 +1,58 @@
+extern crate cgmath;
+extern crate embree;
+
+use std::io;
+
+use cgmath::Vector3;
+use embree::scene_cache::{content_hash, load_scene, save_scene};
+use embree::testing::{generate_scene, SceneConfig};
+use embree::{BuildQuality, Device, Ray};
+
+#[test]
+fn round_trip() {
+    let device = Device::new();
+    let config = SceneConfig::new().spheres(4).meshes(2).motion_blur(true);
+    let mut scene = generate_scene(&device, &config, None);
+    scene.set_build_quality(BuildQuality::HIGH);
+    let mut cache = Vec::new();
+    save_scene(&scene, 7, &mut cache).unwrap();
+
+    let loaded = load_scene(&device, 7, &cache[..]).unwrap().unwrap();
+    assert_eq!(loaded.geometry_ids(), scene.geometry_ids());
+    assert_eq!(loaded.build_quality(), BuildQuality::HIGH);
+    assert_eq!(
+        content_hash(&loaded).unwrap(),
+        content_hash(&scene).unwrap()
+    );
+
+    // Rays hit the same geometry in both scenes
+    let rtscene = scene.commit();
+    let rtloaded = loaded.commit();
+    for i in 0..16 {
+        let ray = Ray::new(
+            Vector3::new(0.0, 0.0, 20.0),
+            Vector3::new(i as f32 / 16.0 - 0.5, 0.3, -1.0),
+        );
+        let a = rtscene.intersect_ray(&ray).map(|h| h.hit.geomID);
+        let b = rtloaded.intersect_ray(&ray).map(|h| h.hit.geomID);
+        assert_eq!(a, b);
+    }
+}
+
+#[test]
+fn stale_and_corrupt_caches() {
+    let device = Device::new();
+    let scene = generate_scene(&device, &SceneConfig::new().spheres(1).meshes(0), None);
+    let mut cache = Vec::new();
+    save_scene(&scene, 1, &mut cache).unwrap();
+
+    assert!(load_scene(&device, 2, &cache[..]).unwrap().is_none());
+
+    let last = cache.len() - 1;
+    cache[last] ^= 0xff;
+    let err = load_scene(&device, 1, &cache[..]).err().unwrap();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+    cache.truncate(16);
+    assert!(load_scene(&device, 1, &cache[..]).is_err());
+}
@@ -0,0 +1,84 @@
+extern crate cgmath;
+extern crate embree;
+
+use cgmath::Vector3;
+use embree::{Device, MeshDescriptor, Ray, Scene, SceneSync};
+
+const TRI: [[f32; 3]; 3] = [[-1.0, -1.0, 0.0], [1.0, -1.0, 0.0], [0.0, 1.0, 0.0]];
+const FAR_TRI: [[f32; 3]; 3] = [[-1.0, -1.0, -5.0], [1.0, -1.0, -5.0], [0.0, 1.0, -5.0]];
+const QUAD: [[f32; 3]; 4] = [
+    [-1.0, -1.0, -2.0],
+    [1.0, -1.0, -2.0],
+    [1.0, 1.0, -2.0],
+    [-1.0, 1.0, -2.0],
+];
+
+fn mesh<'d>(
+    key: &'static str,
+    positions: &'d [[f32; 3]],
+    indices: &'d [[u32; 3]],
+) -> MeshDescriptor<'d, &'static str> {
+    MeshDescriptor {
+        key,
+        positions,
+        indices,
+    }
+}
+
+fn trace(scene: &Scene) -> Option<(u32, f32)> {
+    let rtscene = scene.commit();
+    let ray = Ray::new(Vector3::new(0.0, 0.0, 1.0), Vector3::new(0.0, 0.0, -1.0));
+    rtscene
+        .intersect_ray(&ray)
+        .map(|h| (h.hit.geomID, h.ray.tfar))
+}
+
+#[test]
+fn apply_changes() {
+    let device = Device::new();
+    let mut scene = Scene::new(&device);
+    let mut sync = SceneSync::new();
+
+    let tri_indices = [[0, 1, 2]];
+    let quad_indices = [[0, 1, 2], [0, 2, 3]];
+    let frame0 = vec![
+        mesh("a", &TRI, &tri_indices),
+        mesh("b", &QUAD, &quad_indices),
+    ];
+    let changes = sync.apply(&device, &mut scene, &[], &frame0).unwrap();
+    assert_eq!(changes.added, vec!["a", "b"]);
+    let a = sync.id(&"a").unwrap();
+    assert_eq!(trace(&scene), Some((a, 1.0)));
+
+    // Nothing changed
+    let changes = sync.apply(&device, &mut scene, &frame0, &frame0).unwrap();
+    assert!(changes.is_empty());
+    assert_eq!(changes.unchanged, 2);
+
+    // Moving "a" behind "b" updates it in place, keeping its ID
+    let frame1 = vec![
+        mesh("a", &FAR_TRI, &tri_indices),
+        mesh("b", &QUAD, &quad_indices),
+    ];
+    let changes = sync.apply(&device, &mut scene, &frame0, &frame1).unwrap();
+    assert_eq!(changes.updated, vec!["a"]);
+    assert_eq!(sync.id(&"a"), Some(a));
+    let b = sync.id(&"b").unwrap();
+    assert_eq!(trace(&scene), Some((b, 2.0)));
+
+    // Removing "b" and turning "a" into a quad replaces it
+    let frame2 = vec![mesh("a", &QUAD, &quad_indices)];
+    let changes = sync.apply(&device, &mut scene, &frame1, &frame2).unwrap();
+    assert_eq!(changes.removed, vec!["b"]);
+    assert_eq!(changes.replaced, vec!["a"]);
+    assert_eq!(sync.id(&"b"), None);
+    assert_eq!(scene.iter().count(), 1);
+    assert_eq!(trace(&scene), Some((sync.id(&"a").unwrap(), 2.0)));
+
+    // A bad mesh leaves the scene as it was
+    let bad_indices = [[0, 1, 7]];
+    let frame3 = vec![mesh("c", &TRI, &bad_indices)];
+    assert!(sync.apply(&device, &mut scene, &frame2, &frame3).is_err());
+    assert_eq!(scene.iter().count(), 1);
+    assert!(sync.id(&"a").is_some());
+}
@@ -0,0 +1,47 @@
+extern crate cgmath;
+extern crate embree;
+
+use cgmath::Vector3;
+use embree::{Device, Geometry, Scene, SceneQuery, TriangleMesh};
+
+#[test]
+fn query_committed_scene() {
+    let device = Device::new();
+    let mut scene = Scene::new(&device);
+    let down = Vector3::new(0.0, 0.0, -1.0);
+    let above = Vector3::new(0.0, 0.0, 2.0);
+    {
+        let rtscene = scene.edit(|_| {});
+        assert!(rtscene.closest_hit(above, down).is_none());
+        assert!(rtscene.visible(above, Vector3::new(0.0, 0.0, -2.0)));
+    }
+
+    // Replace the triangle with one closer to the ray origin
+    let mut id = None;
+    for z in &[0.0, 1.0] {
+        let rtscene = scene.edit(|scene| {
+            if let Some(id) = id {
+                scene.deattach_geometry(id);
+            }
+            let mesh = TriangleMesh::try_from_slices(
+                &device,
+                &[[-1.0, -1.0, *z], [1.0, -1.0, *z], [0.0, 1.0, *z]],
+                &[[0, 1, 2]],
+            )
+            .unwrap();
+            let mut geom = Geometry::Triangle(mesh);
+            geom.commit();
+            id = Some(scene.attach_geometry(geom));
+        });
+        assert!(rtscene.is_current());
+        let hit = rtscene.closest_hit(above, down).unwrap();
+        assert_eq!(Some(hit.hit.geomID), id);
+        let t = rtscene.hit_distance(above, down * 2.0).unwrap();
+        assert!((t - (2.0 - z) / 2.0).abs() < 1e-5);
+        let p = rtscene.hit_point(above, down).unwrap();
+        assert!((p.z - z).abs() < 1e-5);
+        // The segment ends on the triangle, which doesn't block it
+        assert!(rtscene.visible(above, p));
+        assert!(!rtscene.visible(above, Vector3::new(0.0, 0.0, -2.0)));
+    }
+}
@@ -0,0 +1,45 @@
+extern crate cgmath;
+extern crate embree;
+
+use cgmath::{Matrix4, Vector3};
+use embree::{Device, Geometry, Instance, IntersectContext, Ray, RayHit, Scene, TriangleMesh};
+
+#[test]
+fn object_space_secondary_ray() {
+    let device = Device::new();
+    let mesh = TriangleMesh::try_from_slices(
+        &device,
+        &[[-1.0, -1.0, 0.0], [1.0, -1.0, 0.0], [0.0, 1.0, 0.0]],
+        &[[0, 1, 2]],
+    )
+    .unwrap();
+    let mut inner = Scene::new(&device);
+    inner.attach_geometry(Geometry::Triangle(mesh));
+    let rtinner = inner.commit();
+
+    let mut instance = Instance::unanimated(&device, &rtinner);
+    instance.set_transform(&Matrix4::from_translation(Vector3::new(10.0, 0.0, 0.0)));
+    let mut geom = Geometry::Instance(instance);
+    geom.commit();
+    let mut scene = Scene::new(&device);
+    let inst_id = scene.attach_geometry(geom);
+    let rtscene = scene.commit();
+
+    let ray = Ray::new(Vector3::new(10.0, 0.0, 1.0), Vector3::new(0.0, 0.0, -1.0));
+    let hit = rtscene.intersect_ray(&ray).unwrap();
+    assert_eq!(hit.hit.instance_stack().ids(), &[inst_id]);
+
+    // A ray in the instanced scene's space, traced against it directly,
+    // only reports the instance if the context is seeded
+    let bounce = Ray::new(Vector3::new(0.0, 0.0, -1.0), Vector3::new(0.0, 0.0, 1.0));
+    let mut unseeded = RayHit::new(bounce);
+    rtinner.intersect(&mut IntersectContext::incoherent(), &mut unseeded);
+    assert!(unseeded.hit.hit());
+    assert!(unseeded.hit.instance_stack().is_empty());
+
+    let mut seeded = RayHit::new(bounce);
+    let mut ctx = IntersectContext::with_instance_stack(&hit.hit);
+    rtinner.intersect(&mut ctx, &mut seeded);
+    assert!(seeded.hit.hit());
+    assert_eq!(seeded.hit.instance_stack(), hit.hit.instance_stack());
+}
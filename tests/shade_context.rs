@@ -0,0 +1,80 @@
+extern crate cgmath;
+extern crate embree;
+
+mod common;
+
+use cgmath::{InnerSpace, Matrix4, Vector2, Vector3, Vector4};
+use embree::{Device, Geometry, Instance, Ray, Scene, ShadeContext};
+
+/// Build a triangle in the z = 0 plane facing +z, with a texture
+/// coordinate attribute
+fn make_triangle(device: &Device) -> Geometry<'_> {
+    let mut mesh = common::triangle_mesh(device, common::centered_triangle(0.0));
+    let uv = mesh.add_vertex_attribute();
+    {
+        let mut uvs = mesh.vertex_attribute_buffers[uv as usize].map();
+        uvs[0] = Vector4::new(0.0, 0.0, 0.0, 0.0);
+        uvs[1] = Vector4::new(1.0, 0.0, 0.0, 0.0);
+        uvs[2] = Vector4::new(0.5, 1.0, 0.0, 0.0);
+    }
+    let mut geom = Geometry::Triangle(mesh);
+    geom.commit();
+    geom
+}
+
+#[test]
+fn direct_hit() {
+    let device = Device::new();
+    let mut scene = Scene::new(&device);
+    let id = scene.attach(make_triangle(&device));
+    let rtscene = scene.commit();
+
+    let ray = Ray::new(Vector3::new(0.0, 0.0, 1.0), Vector3::new(0.0, 0.0, -1.0));
+    let ctx = ShadeContext::new(&rtscene, rtscene.intersect_ray(&ray).unwrap());
+    assert!(ctx.instance().is_none());
+    assert!(*ctx.geometry() == *scene.geometry(id).unwrap());
+    assert!((ctx.position() - Vector3::new(0.0, 0.0, 0.0)).magnitude() < 1e-5);
+    assert!((ctx.normal() - Vector3::new(0.0, 0.0, 1.0)).magnitude() < 1e-5);
+    assert!(ctx.front_facing());
+    let uv = ctx.attribute::<Vector2<f32>>(0).unwrap();
+    assert!((uv - Vector2::new(0.5, 0.5)).magnitude() < 1e-5);
+    assert!(ctx.attribute::<f32>(1).is_none());
+
+    // From behind the normal is flipped to face the ray
+    let ray = Ray::new(Vector3::new(0.0, 0.0, -1.0), Vector3::new(0.0, 0.0, 1.0));
+    let ctx = ShadeContext::new(&rtscene, rtscene.intersect_ray(&ray).unwrap());
+    assert!(!ctx.front_facing());
+    assert!((ctx.facing_normal() - Vector3::new(0.0, 0.0, -1.0)).magnitude() < 1e-5);
+}
+
+#[test]
+fn instanced_hit() {
+    let device = Device::new();
+    let mut inner = Scene::new(&device);
+    let tri_id = inner.attach(make_triangle(&device));
+    let rtinner = inner.commit();
+
+    // Scale the triangle non-uniformly and turn it to face +x
+    let transform = Matrix4::from_translation(Vector3::new(5.0, 0.0, 0.0))
+        * Matrix4::from_angle_y(cgmath::Deg(90.0))
+        * Matrix4::from_nonuniform_scale(1.0, 3.0, 1.0);
+    let mut instance = Instance::unanimated(&device, &rtinner);
+    instance.set_transform(&transform);
+    let mut geom = Geometry::Instance(instance);
+    geom.commit();
+    let mut scene = Scene::new(&device);
+    let inst_id = scene.attach(geom);
+    let rtscene = scene.commit();
+
+    let ray = Ray::new(Vector3::new(10.0, 0.0, 0.0), Vector3::new(-1.0, 0.0, 0.0));
+    let ctx = ShadeContext::new(&rtscene, rtscene.intersect_ray(&ray).unwrap());
+    assert!(*ctx.instance().unwrap() == *scene.geometry(inst_id).unwrap());
+    assert!(*ctx.geometry() == *inner.geometry(tri_id).unwrap());
+    let diff = ctx.world_transform() - transform;
+    for i in 0..4 {
+        assert!(diff[i].magnitude() < 1e-5);
+    }
+    assert!((ctx.normal() - Vector3::new(1.0, 0.0, 0.0)).magnitude() < 1e-5);
+    assert!(ctx.front_facing());
+    assert!((ctx.position() - Vector3::new(5.0, 0.0, 0.0)).magnitude() < 1e-5);
+}
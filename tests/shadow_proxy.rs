@@ -0,0 +1,71 @@
+extern crate cgmath;
+extern crate embree;
+
+mod common;
+
+use cgmath::Vector3;
+#[cfg(feature = "curves")]
+use cgmath::Vector4;
+#[cfg(feature = "curves")]
+use embree::shadow_proxy::flat_curve_proxy;
+#[cfg(feature = "curves")]
+use embree::LinearCurve;
+use embree::{Device, Geometry, Ray, Scene};
+
+fn make_triangle(device: &Device, x: f32) -> Geometry<'_> {
+    common::committed_triangle(device, [[x, 0.0, 0.0], [x + 1.0, 0.0, 0.0], [x, 1.0, 0.0]])
+}
+
+fn shadow_ray(x: f32) -> Ray {
+    Ray::new(Vector3::new(x, 0.25, 1.0), Vector3::new(0.0, 0.0, -1.0))
+}
+
+#[test]
+fn proxies_replace_geometry_for_occlusion() {
+    let device = Device::new();
+    let mut scene = Scene::new(&device);
+    let id = scene.attach_geometry(make_triangle(&device, 0.0));
+    let other = scene.attach_geometry(make_triangle(&device, 10.0));
+    // The proxy is somewhere else entirely so we can tell which is used
+    assert!(scene
+        .set_shadow_proxy(id, make_triangle(&device, 5.0))
+        .is_none());
+    assert!(scene.get_shadow_proxy(id).is_some());
+    assert!(scene.get_shadow_proxy(other).is_none());
+
+    let rtscene = scene.commit();
+    assert!(rtscene.is_occluded(&shadow_ray(0.25)));
+    assert!(!rtscene.is_occluded(&shadow_ray(5.25)));
+
+    let shadows = rtscene.shadow_proxies();
+    assert!(!shadows.is_occluded(&shadow_ray(0.25)));
+    assert!(shadows.is_occluded(&shadow_ray(5.25)));
+    // Geometry without a proxy is still seen by shadow rays
+    assert!(shadows.is_occluded(&shadow_ray(10.25)));
+}
+
+#[test]
+#[cfg(feature = "curves")]
+fn flat_curve_proxy_occludes() {
+    let device = Device::new();
+    let mut curve = LinearCurve::round(&device, 1, 2, false);
+    {
+        let mut verts = curve.vertex_buffer.map();
+        verts[0] = Vector4::new(-1.0, 0.25, 0.0, 0.1);
+        verts[1] = Vector4::new(1.0, 0.25, 0.0, 0.1);
+    }
+    curve.index_buffer.map()[0] = 0;
+    curve.flag_buffer.map()[0] = 0;
+    let mut geom = Geometry::LinearCurve(curve);
+    geom.commit();
+
+    let proxy = flat_curve_proxy(&device, &geom).unwrap();
+    assert!(flat_curve_proxy(&device, &make_triangle(&device, 0.0)).is_none());
+
+    let mut scene = Scene::new(&device);
+    let id = scene.attach_geometry(geom);
+    scene.set_shadow_proxy(id, proxy);
+    let rtscene = scene.commit();
+    assert!(rtscene.shadow_proxies().is_occluded(&shadow_ray(0.0)));
+    assert!(!rtscene.shadow_proxies().is_occluded(&shadow_ray(1.5)));
+}
@@ -0,0 +1,130 @@
+extern crate cgmath;
+extern crate embree;
+
+use cgmath::Vector3;
+use embree::{
+    buffer, scene_cache, Buffer, BufferType, Device, Format, Geometry, Ray, Scene, TriangleMesh,
+    VertexLayout,
+};
+
+#[test]
+fn shared_vertex_buffer() {
+    let device = Device::new();
+    // The application owned vertices, with padding for Embree's 16 byte
+    // read of the last vertex
+    let mut vertices = vec![[0.0f32, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]];
+    let count = vertices.len();
+    for _ in 0..buffer::vertex_padding::<[f32; 3]>() {
+        vertices.push([0.0; 3]);
+    }
+
+    let mut tris = TriangleMesh::unanimated(&device, 1, count);
+    {
+        let mut indices = tris.index_buffer.map();
+        indices[0] = Vector3::new(0, 1, 2);
+    }
+    let mut geom = Geometry::Triangle(tris);
+    geom.set_shared_buffer_from_slice(BufferType::VERTEX, 0, Format::FLOAT3, &vertices, count);
+    geom.commit();
+
+    let mut scene = Scene::new(&device);
+    scene.attach_geometry(geom);
+    let rtscene = scene.commit();
+    let ray = Ray::new(Vector3::new(0.25, 0.25, 1.0), Vector3::new(0.0, 0.0, -1.0));
+    let ray_hit = rtscene.intersect_ray(&ray).expect("expected a hit");
+    assert!((ray_hit.ray.tfar - 1.0).abs() < 1e-4);
+    let ray = Ray::new(Vector3::new(0.75, 0.75, 1.0), Vector3::new(0.0, 0.0, -1.0));
+    assert!(rtscene.intersect_ray(&ray).is_none());
+}
+
+#[test]
+#[should_panic]
+fn shared_vertex_buffer_without_padding() {
+    let device = Device::new();
+    let vertices = vec![[0.0f32, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]];
+    let mut geom = Geometry::Triangle(TriangleMesh::unanimated(&device, 1, 3));
+    geom.set_shared_buffer_from_slice(BufferType::VERTEX, 0, Format::FLOAT3, &vertices, 3);
+}
+
+#[test]
+fn packed_vertex_buffer() {
+    let device = Device::new();
+    let positions = [[0.0f32, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]];
+    let mut vertices = Buffer::<[f32; 3]>::vertices(&device, &positions);
+    assert_eq!(vertices.layout(), VertexLayout::Packed12);
+    assert_eq!(vertices.positions(), positions.to_vec());
+
+    // The mesh takes its vertices from the attached buffer
+    let mut tris = TriangleMesh::unanimated(&device, 1, 0);
+    {
+        let mut indices = tris.index_buffer.map();
+        indices[0] = Vector3::new(0, 1, 2);
+    }
+    let mut geom = Geometry::Triangle(tris);
+    vertices.attach_vertices(&mut geom, BufferType::VERTEX, 0);
+    geom.commit();
+
+    let mut scene = Scene::new(&device);
+    scene.attach_geometry(geom);
+    let rtscene = scene.commit();
+    let ray = Ray::new(Vector3::new(0.25, 0.25, 1.0), Vector3::new(0.0, 0.0, -1.0));
+    assert!(rtscene.intersect_ray(&ray).is_some());
+}
+
+#[test]
+#[should_panic]
+fn attach_vertices_to_owned_buffer() {
+    let device = Device::new();
+    let positions = [[0.0f32, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]];
+    let mut vertices = Buffer::<[f32; 3]>::vertices(&device, &positions);
+    let mut geom = Geometry::Triangle(TriangleMesh::unanimated(&device, 1, positions.len()));
+    vertices.attach_vertices(&mut geom, BufferType::VERTEX, 0);
+}
+
+#[test]
+fn map_attached_vertices_after_geometry_drop() {
+    let device = Device::new();
+    let positions = [[0.0f32, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]];
+    let mut vertices = Buffer::<[f32; 3]>::vertices(&device, &positions);
+    let mut geom = Geometry::Triangle(TriangleMesh::unanimated(&device, 1, 0));
+    vertices.attach_vertices(&mut geom, BufferType::VERTEX, 0);
+    drop(geom);
+    // The buffer still holds a reference to the geometry handle
+    vertices.mapped_scope(|v| v[0] = [0.5, 0.0, 0.0]);
+    assert_eq!(vertices.positions()[0], [0.5, 0.0, 0.0]);
+}
+
+#[test]
+fn rebound_buffers_are_not_read() {
+    let device = Device::new();
+    let mut vertices = vec![[0.0f32, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]];
+    vertices.push([0.0; 3]);
+    let mut tris = TriangleMesh::unanimated(&device, 1, 3);
+    {
+        let mut indices = tris.index_buffer.map();
+        indices[0] = Vector3::new(0, 1, 2);
+    }
+    // The mesh's own vertex buffer is replaced, and no longer holds the
+    // vertices Embree uses
+    let mut geom = Geometry::Triangle(tris);
+    geom.set_shared_buffer_from_slice(BufferType::VERTEX, 0, Format::FLOAT3, &vertices, 3);
+    geom.commit();
+    assert_eq!(geom.primitive_count(), 1);
+
+    let mut scene = Scene::new(&device);
+    scene.attach_geometry(geom);
+    assert!(scene_cache::content_hash(&scene).is_err());
+}
+
+#[test]
+fn attached_vertices_are_not_read_from_the_mesh() {
+    let device = Device::new();
+    let positions = [[0.0f32, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]];
+    let mut vertices = Buffer::<[f32; 3]>::vertices(&device, &positions);
+    let mut geom = Geometry::Triangle(TriangleMesh::unanimated(&device, 1, 0));
+    vertices.attach_vertices(&mut geom, BufferType::VERTEX, 0);
+    let mut scene = Scene::new(&device);
+    scene.attach_geometry(geom);
+    // The mesh's empty vertex buffer isn't the one Embree reads
+    assert!(scene_cache::content_hash(&scene).is_err());
+}
@@ -0,0 +1,44 @@
+extern crate cgmath;
+extern crate embree;
+
+use cgmath::{Matrix4, SquareMatrix, Vector3};
+use embree::{BuildQuality, Device, Geometry, Ray, Scene, Skin, TriangleMesh, VertexInfluence};
+
+#[test]
+fn refit_skinned_mesh() {
+    let device = Device::new();
+    let bind_pose = vec![
+        Vector3::new(-1.0, -1.0, 0.0),
+        Vector3::new(1.0, -1.0, 0.0),
+        Vector3::new(0.0, 1.0, 0.0),
+    ];
+    let skin = Skin::new(bind_pose, vec![VertexInfluence::single(0); 3]);
+    let mut mesh = TriangleMesh::unanimated(&device, 1, 3);
+    {
+        let mut tris = mesh.index_buffer.map();
+        tris[0] = Vector3::new(0, 1, 2);
+    }
+    skin.apply(&[Matrix4::identity()], &mut mesh.vertex_buffer);
+    let mut geom = Geometry::Triangle(mesh);
+    geom.set_build_quality(BuildQuality::REFIT);
+    geom.commit();
+    let mut scene = Scene::new(&device);
+    let id = scene.attach(geom);
+
+    let ray = Ray::new(Vector3::new(0.0, 0.0, 1.0), Vector3::new(0.0, 0.0, -1.0));
+    let hit = scene.commit().intersect_ray(&ray).unwrap();
+    assert_eq!(hit.ray.tfar, 1.0);
+
+    // Move the bone away from the viewer and refit the BVH
+    let bones = [Matrix4::from_translation(Vector3::new(0.0, 0.0, -2.0))];
+    {
+        let geom = scene.geometry_mut(id).unwrap();
+        if let Geometry::Triangle(ref mut mesh) = *geom {
+            skin.apply(&bones, &mut mesh.vertex_buffer);
+        }
+        assert!(geom.needs_commit());
+        geom.commit();
+    }
+    let hit = scene.commit().intersect_ray(&ray).unwrap();
+    assert_eq!(hit.ray.tfar, 3.0);
+}
@@ -0,0 +1,113 @@
+extern crate cgmath;
+extern crate embree;
+
+use std::io;
+
+use cgmath::{Vector2, Vector3};
+use embree::snapshot::{load_snapshot, save_snapshot};
+use embree::testing::{generate_scene, SceneConfig};
+use embree::{
+    BufferType, BuildQuality, Device, Format, Geometry, GridMesh, HitFaceMode, Instance,
+    PointGeometry, Ray, RayMask, Scene, SceneFlags, TriangleMesh,
+};
+
+#[test]
+fn round_trip() {
+    let device = Device::new();
+    let config = SceneConfig::new().spheres(3).meshes(2).motion_blur(true);
+    let mut scene = generate_scene(&device, &config, None);
+    scene.set_build_quality(BuildQuality::HIGH);
+    scene.set_flags(SceneFlags::ROBUST);
+
+    let heights = [0.0, 1.0, 0.5, 0.25, 0.0, 1.0, 0.5, 0.0, 0.75];
+    let grid = GridMesh::from_heightfield(
+        &device,
+        &heights,
+        3,
+        3,
+        Vector3::new(-1.0, -2.0, -1.0),
+        Vector2::new(1.0, 1.0),
+    );
+    let grid_id = scene.attach_geometry(Geometry::Grid(grid));
+    let mut points = Geometry::Point(PointGeometry::oriented_discs(
+        &device,
+        &[[0.0, 3.0, 0.0, 0.5]],
+        &[[0.0, 0.0, 1.0]],
+    ));
+    points.set_enabled(false);
+    points.set_ray_mask(RayMask::bit(2));
+    points.set_hit_face_mode(HitFaceMode::FrontOnly);
+    let points_id = scene.attach_geometry(points);
+    // Leave a gap in the IDs, which the loaded scene must keep
+    scene.deattach_geometry(1);
+
+    let mut snapshot = Vec::new();
+    save_snapshot(&scene, &mut snapshot).unwrap();
+    let loaded = load_snapshot(&device, &snapshot[..]).unwrap();
+    assert_eq!(loaded.geometry_ids(), scene.geometry_ids());
+    assert_eq!(loaded.build_quality(), BuildQuality::HIGH);
+    assert_eq!(loaded.flags(), SceneFlags::ROBUST);
+    // The loaded scene keeps the raw IDs, which are looked up again
+    assert!(loaded.geom_id(1).is_none());
+    let grid_id = loaded.geom_id(grid_id).unwrap();
+    assert!(loaded.geometry(grid_id).unwrap().is_enabled());
+    let p = loaded.geometry(loaded.geom_id(points_id).unwrap()).unwrap();
+    assert!(!p.is_enabled());
+    assert_eq!(p.ray_mask(), RayMask::bit(2));
+    assert_eq!(p.hit_face_mode(), HitFaceMode::FrontOnly);
+
+    // Saving the loaded scene gives the same snapshot
+    let mut resaved = Vec::new();
+    save_snapshot(&loaded, &mut resaved).unwrap();
+    assert_eq!(resaved, snapshot);
+
+    // Rays hit the same geometry in both scenes
+    let rtscene = scene.commit();
+    let rtloaded = loaded.commit();
+    for i in 0..16 {
+        let ray = Ray::new(
+            Vector3::new(0.0, 0.0, 20.0),
+            Vector3::new(i as f32 / 16.0 - 0.5, 0.3, -1.0),
+        );
+        let a = rtscene.intersect_ray(&ray).map(|h| h.hit.geomID);
+        let b = rtloaded.intersect_ray(&ray).map(|h| h.hit.geomID);
+        assert_eq!(a, b);
+    }
+}
+
+#[test]
+fn unsupported_and_corrupt_snapshots() {
+    let device = Device::new();
+    let inner = generate_scene(&device, &SceneConfig::new().spheres(1).meshes(0), None);
+    let rtinner = inner.commit();
+    let mut scene = generate_scene(&device, &SceneConfig::new().spheres(1).meshes(0), None);
+    let mut snapshot = Vec::new();
+    save_snapshot(&scene, &mut snapshot).unwrap();
+
+    let last = snapshot.len() - 1;
+    snapshot[last] ^= 0xff;
+    let err = load_snapshot(&device, &snapshot[..]).err().unwrap();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    snapshot.truncate(16);
+    assert!(load_snapshot(&device, &snapshot[..]).is_err());
+
+    let mut instance = Geometry::Instance(Instance::unanimated(&device, &rtinner));
+    instance.commit();
+    scene.attach_geometry(instance);
+    let err = save_snapshot(&scene, io::sink()).err().unwrap();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn shared_buffers_are_not_saved() {
+    let device = Device::new();
+    let mut vertices = vec![[0.0f32, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]];
+    vertices.push([0.0; 3]);
+    let mut geom = Geometry::Triangle(TriangleMesh::unanimated(&device, 1, 3));
+    geom.set_shared_buffer_from_slice(BufferType::VERTEX, 0, Format::FLOAT3, &vertices, 3);
+    geom.commit();
+    let mut scene = Scene::new(&device);
+    scene.attach_geometry(geom);
+    let err = save_snapshot(&scene, io::sink()).err().unwrap();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+}
@@ -0,0 +1,51 @@
+extern crate cgmath;
+extern crate embree;
+#[cfg(feature = "serde")]
+extern crate serde_json;
+
+use cgmath::Vector3;
+use embree::{Device, Geometry, GeometryKind, QuadMesh, Ray, Scene, TriangleMesh};
+
+#[test]
+fn statistics_report() {
+    let device = Device::new();
+    let mut scene = Scene::new(&device);
+    let tri = TriangleMesh::try_from_slices(
+        &device,
+        &[[-1.0, -1.0, 0.0], [1.0, -1.0, 0.0], [0.0, 1.0, 0.0]],
+        &[[0, 1, 2]],
+    )
+    .unwrap();
+    let mut tri = Geometry::Triangle(tri);
+    tri.commit();
+    scene.attach_geometry(tri);
+    let mut quad = Geometry::Quad(QuadMesh::unanimated(&device, 2, 6));
+    quad.set_enabled(false);
+    scene.attach_geometry(quad);
+
+    let report = scene.statistics_report();
+    assert_eq!(report.geometries, 2);
+    assert_eq!(report.enabled_geometries, 1);
+    assert_eq!(report.primitives, 3);
+    assert_eq!(report.kinds[0].kind, GeometryKind::Triangle);
+    assert_eq!(report.kinds[1].primitives, 2);
+    assert!(report.build_time_seconds.is_none());
+
+    scene.set_ray_counting(true);
+    let rtscene = scene.commit();
+    let ray = Ray::new(Vector3::new(0.0, 0.0, 1.0), Vector3::new(0.0, 0.0, -1.0));
+    rtscene.intersect_ray(&ray);
+    rtscene.is_occluded(&ray);
+    let report = scene.statistics_report();
+    assert!(report.build_time_seconds.is_some());
+    assert_eq!((report.rays.intersect, report.rays.occluded), (1, 1));
+    scene.reset_ray_counts();
+    assert_eq!(scene.ray_counts().total(), 0);
+
+    #[cfg(feature = "serde")]
+    {
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["geometries"], 2);
+        assert_eq!(json["kinds"][0]["kind"], "Triangle");
+    }
+}
@@ -0,0 +1,85 @@
+#![cfg(feature = "streams")]
+
+extern crate cgmath;
+extern crate embree;
+
+use cgmath::Vector3;
+use embree::{Device, Geometry, IntersectContext, Ray, RayHit, Scene, SceneFlags, TriangleMesh};
+
+/// Build a scene of `layers` parallel triangles at z = 0, -1, -2, ...
+fn layered_scene(device: &Device, layers: usize) -> Scene<'_> {
+    let mut scene = Scene::new(device);
+    scene.set_flags(SceneFlags::CONTEXT_FILTER_FUNCTION);
+    for l in 0..layers {
+        let z = -(l as f32);
+        let mesh = TriangleMesh::try_from_slices(
+            device,
+            &[[-1.0, -1.0, z], [1.0, -1.0, z], [0.0, 1.0, z]],
+            &[[0, 1, 2]],
+        )
+        .unwrap();
+        scene.attach_geometry(Geometry::Triangle(mesh));
+    }
+    scene
+}
+
+#[test]
+fn count_surfaces_crossed() {
+    let device = Device::new();
+    let scene = layered_scene(&device, 4);
+    let rtscene = scene.commit();
+
+    // Rays are given out of order, the payloads follow their ids. The ray
+    // with id 1 misses the triangles
+    let dir = Vector3::new(0.0, 0.0, -1.0);
+    let mut rays: Vec<Ray> = [2, 0, 1]
+        .iter()
+        .map(|&id| {
+            let x = if id == 1 { 5.0 } else { 0.0 };
+            let mut r = Ray::new(Vector3::new(x, 0.0, 1.0), dir);
+            r.id = id;
+            r
+        })
+        .collect();
+    let mut crossed = vec![0; 3];
+    let ctx = IntersectContext::coherent();
+    rtscene.occluded_stream_with(&ctx, &mut rays, &mut crossed, |_, _, n| {
+        *n += 1;
+        false
+    });
+    assert_eq!(crossed, vec![4, 0, 4]);
+
+    // Accept the hit behind the second surface crossed
+    let mut ray_hits: Vec<RayHit> = rays.iter().map(|r| RayHit::new(*r)).collect();
+    let mut crossed = vec![0; 3];
+    rtscene.intersect_stream_with(&ctx, &mut ray_hits, &mut crossed, |_, h, n| {
+        *n += 1;
+        h.geomID >= 1
+    });
+    for rh in &ray_hits {
+        if rh.ray.id == 1 {
+            assert!(!rh.hit.hit());
+        } else {
+            assert_eq!(rh.hit.geomID, 1);
+            assert_eq!(rh.ray.tfar, 2.0);
+        }
+    }
+    assert_eq!(crossed[1], 0);
+}
+
+#[test]
+#[should_panic(expected = "out of bounds")]
+fn ray_id_out_of_bounds() {
+    let device = Device::new();
+    let scene = layered_scene(&device, 1);
+    let rtscene = scene.commit();
+    let mut ray = Ray::new(Vector3::new(0.0, 0.0, 1.0), Vector3::new(0.0, 0.0, -1.0));
+    ray.id = 2;
+    let mut payloads = [0; 2];
+    rtscene.occluded_stream_with(
+        &IntersectContext::incoherent(),
+        &mut [ray],
+        &mut payloads,
+        |_, _, _| true,
+    );
+}
@@ -0,0 +1,113 @@
+#![cfg(feature = "subdivision")]
+
+extern crate cgmath;
+extern crate embree;
+
+use cgmath::{Vector3, Vector4};
+use embree::{
+    Device, Geometry, IntersectContext, Ray, RayHit, Scene, SubdivisionMesh, SubdivisionMode,
+};
+
+/// Build a single quad face on [-1, 1]^2 in the z = 0 plane
+fn make_quad(device: &Device) -> SubdivisionMesh<'_> {
+    let mut mesh = SubdivisionMesh::unanimated(device, 1, 4, 4);
+    {
+        let mut verts = mesh.vertex_buffer.map();
+        verts[0] = Vector4::new(-1.0, -1.0, 0.0, 0.0);
+        verts[1] = Vector4::new(1.0, -1.0, 0.0, 0.0);
+        verts[2] = Vector4::new(1.0, 1.0, 0.0, 0.0);
+        verts[3] = Vector4::new(-1.0, 1.0, 0.0, 0.0);
+        mesh.face_buffer.map()[0] = 4;
+    }
+    {
+        let mut topology = mesh.base_topology();
+        let mut indices = topology.index_buffer().map();
+        for i in 0..4 {
+            indices[i] = i as u32;
+        }
+    }
+    mesh
+}
+
+/// Trace a ray down -Z through the center of the quad
+fn trace<'a>(device: &'a Device, mesh: SubdivisionMesh<'a>) -> RayHit {
+    let mut geom = Geometry::Subdivision(mesh);
+    geom.commit();
+    let mut scene = Scene::new(device);
+    scene.attach_geometry(geom);
+    let rtscene = scene.commit();
+    let mut ctx = IntersectContext::coherent();
+    let mut ray_hit = RayHit::new(Ray::new(
+        Vector3::new(0.1, 0.1, 1.0),
+        Vector3::new(0.0, 0.0, -1.0),
+    ));
+    rtscene.intersect(&mut ctx, &mut ray_hit);
+    ray_hit
+}
+
+#[test]
+fn boundary_modes() {
+    let device = Device::new();
+
+    // Pinning the boundary keeps the limit surface of a single planar
+    // face on the face itself
+    let mut mesh = make_quad(&device);
+    mesh.base_topology().set_mode(SubdivisionMode::PIN_ALL);
+    let ray_hit = trace(&device, mesh);
+    assert!(ray_hit.hit.hit());
+    assert!((ray_hit.ray.tfar - 1.0).abs() < 1e-3);
+
+    // Without boundary faces are not rendered, so the lone face vanishes
+    let mut mesh = make_quad(&device);
+    mesh.base_topology().set_mode(SubdivisionMode::NO_BOUNDARY);
+    let ray_hit = trace(&device, mesh);
+    assert!(!ray_hit.hit.hit());
+}
+
+#[test]
+fn per_topology_configuration() {
+    let device = Device::new();
+    let mut mesh = make_quad(&device);
+    let base = mesh.base_topology().id();
+    assert_eq!(
+        mesh.base_topology().mode(),
+        SubdivisionMode::SMOOTH_BOUNDARY
+    );
+
+    let uv_topology = {
+        let mut uv = mesh.add_topology();
+        uv.set_mode(SubdivisionMode::PIN_CORNERS);
+        {
+            let mut indices = uv.index_buffer().map();
+            for i in 0..4 {
+                indices[i] = 3 - i as u32;
+            }
+        }
+        let slot = uv.add_vertex_attribute(4);
+        assert_eq!(slot, 0);
+        uv.id()
+    };
+    assert_ne!(base, uv_topology);
+    assert_eq!(uv_topology.index(), 1);
+    assert_eq!(mesh.num_topologies(), 2);
+    assert_eq!(mesh.vertex_attribute_buffers.len(), 1);
+
+    // Configuring one topology leaves the others untouched
+    mesh.base_topology().set_mode(SubdivisionMode::PIN_ALL);
+    assert_eq!(
+        mesh.topology(uv_topology).mode(),
+        SubdivisionMode::PIN_CORNERS
+    );
+    assert_eq!(mesh.base_topology().mode(), SubdivisionMode::PIN_ALL);
+    let ray_hit = trace(&device, mesh);
+    assert!(ray_hit.hit.hit());
+}
+
+#[test]
+#[should_panic]
+fn topology_index_buffer_size_mismatch() {
+    let device = Device::new();
+    let mut mesh = make_quad(&device);
+    let buffer = embree::Buffer::new(&device, 3);
+    mesh.add_topology().set_index_buffer(buffer);
+}
@@ -0,0 +1,42 @@
+#![cfg(feature = "streams")]
+
+extern crate cgmath;
+extern crate embree;
+
+use cgmath::Vector3;
+use embree::{Device, Geometry, Occlusion, Ray, Scene, TriangleMesh};
+
+#[test]
+fn trace_stream_matches_single_rays() {
+    let device = Device::new();
+    let mesh = TriangleMesh::try_from_slices(
+        &device,
+        &[[-1.0, -1.0, 0.0], [1.0, -1.0, 0.0], [0.0, 1.0, 0.0]],
+        &[[0, 1, 2]],
+    )
+    .unwrap();
+    let mut scene = Scene::new(&device);
+    scene.attach_geometry(Geometry::Triangle(mesh));
+    let rtscene = scene.commit();
+
+    // More rays than fit in one stream query, alternating hits and misses
+    let rays: Vec<Ray> = (0..3000)
+        .map(|i| {
+            let x = if i % 2 == 0 { 0.0 } else { 4.0 };
+            Ray::new(Vector3::new(x, 0.0, 1.0), Vector3::new(0.0, 0.0, -1.0))
+        })
+        .collect();
+    let hits = rtscene.trace_stream(rays.iter().cloned());
+    assert_eq!(hits.len(), rays.len());
+    for (ray, hit) in rays.iter().zip(hits.iter()) {
+        let single = rtscene.intersect_ray(ray);
+        assert_eq!(hit.hit.hit(), single.is_some());
+        if let Some(s) = single {
+            assert_eq!(hit.ray.tfar, s.ray.tfar);
+        }
+    }
+
+    let occlusion = rtscene.occlusion_stream(rays.iter().cloned());
+    assert_eq!(occlusion[0], Occlusion::Occluded);
+    assert_eq!(occlusion[1], Occlusion::Visible);
+}
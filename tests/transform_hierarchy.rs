@@ -0,0 +1,59 @@
+extern crate cgmath;
+extern crate embree;
+
+use cgmath::{Matrix4, Vector3};
+use embree::testing::prototype_scene;
+use embree::{Device, Geometry, Instance, Ray, Scene, TransformHierarchy};
+
+#[test]
+fn update_only_changed_instances() {
+    let device = Device::new();
+    let prototype = prototype_scene(&device, 8);
+    let rtprototype = prototype.commit();
+
+    let mut scene = Scene::new(&device);
+    let mut hierarchy = TransformHierarchy::new();
+    let root = hierarchy.add_node(None, Matrix4::from_scale(1.0));
+    let mut nodes = Vec::new();
+    for i in 0..4 {
+        let mut instance = Geometry::Instance(Instance::unanimated(&device, &rtprototype));
+        instance.commit();
+        let id = scene.attach_geometry(instance);
+        let offset = Vector3::new(3.0 * i as f32, 0.0, 0.0);
+        let node = hierarchy.add_node(Some(root), Matrix4::from_translation(offset));
+        hierarchy.bind_instance(node, id);
+        nodes.push(node);
+    }
+
+    // All instances are set on the first update, and none on the next
+    assert_eq!(hierarchy.update(&mut scene), 4);
+    assert_eq!(hierarchy.update(&mut scene), 0);
+    {
+        let rtscene = scene.commit();
+        let hit = rtscene.intersect_ray(&Ray::new(
+            Vector3::new(3.0, 0.0, 5.0),
+            Vector3::new(0.0, 0.0, -1.0),
+        ));
+        assert_eq!(hit.map(|h| h.hit.instID[0]), Some(1));
+    }
+
+    // Moving one child only updates its instance
+    hierarchy.set_local_transform(
+        nodes[1],
+        Matrix4::from_translation(Vector3::new(3.0, 10.0, 0.0)),
+    );
+    assert_eq!(hierarchy.update(&mut scene), 1);
+
+    // Moving the root updates all of them
+    hierarchy.set_local_transform(
+        root,
+        Matrix4::from_translation(Vector3::new(0.0, -10.0, 0.0)),
+    );
+    assert_eq!(hierarchy.update(&mut scene), 4);
+    let rtscene = scene.commit();
+    let hit = rtscene.intersect_ray(&Ray::new(
+        Vector3::new(3.0, 0.0, 5.0),
+        Vector3::new(0.0, 0.0, -1.0),
+    ));
+    assert_eq!(hit.map(|h| h.hit.instID[0]), Some(1));
+}
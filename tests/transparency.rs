@@ -0,0 +1,194 @@
+//! Renders a tiny frame of overlapping transparent cubes by accumulating
+//! transmittance in filter functions, and compares it against the
+//! transmittance computed analytically from the cubes crossed by each
+//! ray. This guards the plumbing a transparency filter relies on: finding
+//! the ray's output from its id, passing per-ray payloads through the
+//! intersection context, and dropping repeated hits on the same face.
+
+extern crate cgmath;
+extern crate embree;
+
+use cgmath::Vector3;
+use embree::{Device, Geometry, PerRayOutput, Ray, Scene, TriangleMesh};
+#[cfg(feature = "streams")]
+use embree::{IntersectContext, SceneFlags};
+
+const WIDTH: u32 = 7;
+const HEIGHT: u32 = 7;
+
+struct Cube {
+    center: [f32; 3],
+    half_size: f32,
+    opacity: f32,
+}
+
+/// The cubes seen by the camera, each at a different depth and partially
+/// overlapping the others. None of their edges lie on a pixel center.
+const CUBES: [Cube; 3] = [
+    Cube {
+        center: [-0.5, 0.0, 0.0],
+        half_size: 1.0,
+        opacity: 0.5,
+    },
+    Cube {
+        center: [0.5, 0.0, -3.0],
+        half_size: 1.0,
+        opacity: 0.25,
+    },
+    Cube {
+        center: [0.0, 0.5, -6.0],
+        half_size: 0.75,
+        opacity: 0.75,
+    },
+];
+
+fn cube_mesh<'a>(device: &'a Device, cube: &Cube) -> TriangleMesh<'a> {
+    let [cx, cy, cz] = cube.center;
+    let h = cube.half_size;
+    let verts: Vec<[f32; 3]> = (0..8)
+        .map(|i| {
+            let sx = if i & 1 == 0 { -h } else { h };
+            let sy = if i & 2 == 0 { -h } else { h };
+            let sz = if i & 4 == 0 { -h } else { h };
+            [cx + sx, cy + sy, cz + sz]
+        })
+        .collect();
+    // Two triangles per face, so face f is made of primitives 2f and 2f + 1
+    let tris = [
+        [0, 2, 3],
+        [0, 3, 1],
+        [4, 5, 7],
+        [4, 7, 6],
+        [0, 1, 5],
+        [0, 5, 4],
+        [2, 6, 7],
+        [2, 7, 3],
+        [0, 4, 6],
+        [0, 6, 2],
+        [1, 3, 7],
+        [1, 7, 5],
+    ];
+    TriangleMesh::try_from_slices(device, &verts, &tris).unwrap()
+}
+
+/// An orthographic camera looking down -z over the [-2, 2] square
+fn camera_ray(i: u32, j: u32) -> Ray {
+    let x = -2.0 + (i as f32 + 0.5) * 4.0 / WIDTH as f32;
+    let y = 2.0 - (j as f32 + 0.5) * 4.0 / HEIGHT as f32;
+    let mut ray = Ray::new(Vector3::new(x, y, 10.0), Vector3::new(0.0, 0.0, -1.0));
+    ray.id = j * WIDTH + i;
+    ray
+}
+
+/// The transmittance along the ray computed from the cubes it crosses,
+/// each attenuating it once entering and once leaving the cube
+fn reference_transmittance(ray: &Ray) -> f32 {
+    CUBES
+        .iter()
+        .filter(|c| {
+            (ray.org_x - c.center[0]).abs() < c.half_size
+                && (ray.org_y - c.center[1]).abs() < c.half_size
+        })
+        .map(|c| (1.0 - c.opacity) * (1.0 - c.opacity))
+        .product()
+}
+
+/// The transmittance along the ray through the faces it hit, given as the
+/// geometry and primitive of each hit. Embree can report a hit more than
+/// once, e.g. when a ray passes through the edge shared by the triangles
+/// of a face, so each face only attenuates the ray once.
+fn accumulated_transmittance(hits: &[(u32, u32)]) -> f32 {
+    let mut faces: Vec<(u32, u32)> = hits.iter().map(|&(g, p)| (g, p / 2)).collect();
+    faces.sort();
+    faces.dedup();
+    faces
+        .iter()
+        .map(|&(g, _)| 1.0 - CUBES[g as usize].opacity)
+        .product()
+}
+
+fn check_frame(frame: &[f32]) {
+    let mut crossed_all = false;
+    for j in 0..HEIGHT {
+        for i in 0..WIDTH {
+            let ray = camera_ray(i, j);
+            let expected = reference_transmittance(&ray);
+            let found = frame[ray.id as usize];
+            assert!(
+                (expected - found).abs() < 1e-6,
+                "Pixel ({}, {}) has transmittance {}, expected {}",
+                i,
+                j,
+                found,
+                expected
+            );
+            crossed_all |= expected < 0.01;
+        }
+    }
+    // Make sure the frame covers the pixel where all the cubes overlap
+    assert!(crossed_all);
+}
+
+#[test]
+fn transparency_with_geometry_filters() {
+    let device = Device::new();
+    let hits = PerRayOutput::<Vec<(u32, u32)>>::new((WIDTH * HEIGHT) as usize);
+    let mut scene = Scene::new(&device);
+    for cube in CUBES.iter() {
+        let mut geom = Geometry::Triangle(cube_mesh(&device, cube));
+        geom.set_occluded_filter_function(|ray, hit| {
+            hits.with(ray, |h| h.push((hit.geomID, hit.primID)));
+            false
+        });
+        geom.commit();
+        scene.attach_geometry(geom);
+    }
+    {
+        let rtscene = scene.commit();
+        for j in 0..HEIGHT {
+            for i in 0..WIDTH {
+                // Every hit is rejected, so the ray is never occluded
+                assert!(!rtscene.is_occluded(&camera_ray(i, j)));
+            }
+        }
+    }
+    drop(scene);
+    let frame: Vec<f32> = hits
+        .into_vec()
+        .iter()
+        .map(|h| accumulated_transmittance(h))
+        .collect();
+    check_frame(&frame);
+}
+
+#[cfg(feature = "streams")]
+#[test]
+fn transparency_with_stream_payloads() {
+    let device = Device::new();
+    let mut scene = Scene::new(&device);
+    scene.set_flags(SceneFlags::CONTEXT_FILTER_FUNCTION);
+    for cube in CUBES.iter() {
+        let mut geom = Geometry::Triangle(cube_mesh(&device, cube));
+        geom.commit();
+        scene.attach_geometry(geom);
+    }
+    let rtscene = scene.commit();
+
+    // Trace the rows in reverse, the payloads are found by ray id
+    let mut rays: Vec<Ray> = (0..HEIGHT)
+        .rev()
+        .flat_map(|j| (0..WIDTH).map(move |i| camera_ray(i, j)))
+        .collect();
+    let mut hits = vec![Vec::new(); (WIDTH * HEIGHT) as usize];
+    rtscene.occluded_stream_with(
+        &IntersectContext::coherent(),
+        &mut rays,
+        &mut hits,
+        |_, hit, h: &mut Vec<(u32, u32)>| {
+            h.push((hit.geomID, hit.primID));
+            false
+        },
+    );
+    let frame: Vec<f32> = hits.iter().map(|h| accumulated_transmittance(h)).collect();
+    check_frame(&frame);
+}
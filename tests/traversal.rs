@@ -0,0 +1,84 @@
+//! Grazing and secondary ray cases showing the effect of the traversal
+//! settings: robust traversal keeps rays crossing shared edges at a
+//! shallow angle from falling through a mesh, and spawning rays with the
+//! scaled tnear keeps rays leaving a surface far from the origin from
+//! hitting it again.
+
+extern crate cgmath;
+extern crate embree;
+
+use cgmath::{InnerSpace, Vector3};
+use embree::{Device, Geometry, Ray, Scene, TraversalSettings, TriangleMesh};
+
+/// Build a fan of triangles around `center` in the plane through it with
+/// normal (0, 0, 1), all sharing the center vertex
+fn make_fan<'a>(device: &'a Device, center: Vector3<f32>, size: f32) -> Geometry<'a> {
+    let n = 8;
+    let mut mesh = TriangleMesh::unanimated(device, n, n + 1);
+    {
+        let mut verts = mesh.vertex_buffer.map();
+        verts[0] = center.extend(0.0);
+        for i in 0..n {
+            let a = i as f32 * 2.0 * std::f32::consts::PI / n as f32;
+            let p = center + Vector3::new(a.cos(), a.sin(), 0.0) * size;
+            verts[i + 1] = p.extend(0.0);
+        }
+        let mut tris = mesh.index_buffer.map();
+        for i in 0..n as u32 {
+            tris[i as usize] = Vector3::new(0, i + 1, (i + 1) % n as u32 + 1);
+        }
+    }
+    let mut geom = Geometry::Triangle(mesh);
+    geom.commit();
+    geom
+}
+
+#[test]
+fn robust_grazing_rays() {
+    let device = Device::new();
+    let mut scene = Scene::new(&device);
+    scene.attach_geometry(make_fan(&device, Vector3::new(0.0, 0.0, 0.0), 1.0));
+    scene.set_traversal_settings(TraversalSettings::new().robust(true));
+    assert!(scene.traversal_settings().robust);
+    let rtscene = scene.commit();
+
+    // Rays crossing the plane at a grazing angle through the center vertex
+    // and along the shared edges
+    for i in 0..16 {
+        let a = i as f32 * std::f32::consts::PI / 8.0;
+        let dir = Vector3::new(a.cos(), a.sin(), -1e-3);
+        let org = -dir * 0.5;
+        let hit = rtscene.intersect_ray(&Ray::new(org, dir));
+        assert!(hit.is_some(), "grazing ray {} fell through the mesh", i);
+        assert!(rtscene.is_occluded(&Ray::new(org, dir)));
+    }
+}
+
+#[test]
+fn spawned_rays_leave_the_surface() {
+    let device = Device::new();
+    let center = Vector3::new(3.0e4, -2.0e4, 1.0e4);
+    let mut scene = Scene::new(&device);
+    scene.attach_geometry(make_fan(&device, center, 100.0));
+    let rtscene = scene.commit();
+    let settings = rtscene.traversal_settings();
+    assert!(!settings.robust);
+
+    for i in 0..64 {
+        let x = (i % 8) as f32 * 7.3 - 25.0;
+        let y = (i / 8) as f32 * 5.1 - 20.0;
+        let org = center + Vector3::new(x, y, 10.0);
+        let dir = Vector3::new(0.013 * x, -0.021 * y, -10.0);
+        let hit = rtscene.intersect_ray(&Ray::new(org, dir)).unwrap();
+        let p = hit.ray.origin() + hit.ray.dir() * hit.ray.tfar;
+
+        // A ray reflected back away from the surface must not hit it again
+        let mut reflect = hit.ray.dir().normalize();
+        reflect.z = -reflect.z;
+        let ray = settings.spawn_ray(p, reflect);
+        assert!(rtscene.intersect_ray(&ray).is_none(), "ray {} self hit", i);
+        // A shadow ray to a point on the surface ends before reaching it
+        let target = center + Vector3::new(-x, -y, 0.0);
+        assert!(!rtscene.is_occluded(&settings.spawn_ray_to(p, target)));
+    }
+}
@@ -0,0 +1,150 @@
+extern crate cgmath;
+extern crate embree;
+
+use cgmath::Vector3;
+use embree::{
+    Bounds, Capsule, Cone, Device, Geometry, GeometryKind, IntersectContext, PrimitiveHit, Ray,
+    RayHit, Scene, UserGeometry, UserPrimitive,
+};
+
+#[test]
+fn intersect_cones_and_capsules() {
+    let device = Device::new();
+    let cones = vec![Cone::new(
+        Vector3::new(0.0, 0.0, 0.0),
+        1.0,
+        Vector3::new(0.0, 2.0, 0.0),
+        0.0,
+    )];
+    let capsules = vec![
+        Capsule::new(
+            Vector3::new(3.0, 0.0, 0.0),
+            Vector3::new(3.0, 2.0, 0.0),
+            0.5,
+        ),
+        Capsule::new(
+            Vector3::new(6.0, 1.0, 0.0),
+            Vector3::new(6.0, 1.0, 0.0),
+            0.5,
+        ),
+    ];
+    let mut cone_geom = Geometry::User(UserGeometry::new(&device, cones));
+    cone_geom.commit();
+    let mut capsule_geom = Geometry::User(UserGeometry::new(&device, capsules));
+    assert_eq!(capsule_geom.kind(), GeometryKind::User);
+    assert_eq!(capsule_geom.as_kind::<UserGeometry>().unwrap().len(), 2);
+    // Skip the near side of the capsules, so rays hit their far side
+    capsule_geom.set_intersect_filter_function(|_, hit| hit.Ng_z < 0.0);
+    capsule_geom.commit();
+
+    let mut scene = Scene::new(&device);
+    let cone_id = scene.attach_geometry(cone_geom);
+    let capsule_id = scene.attach_geometry(capsule_geom);
+    let rtscene = scene.commit();
+
+    let dir = Vector3::new(0.0, 0.0, -1.0);
+    let mut ctx = IntersectContext::incoherent();
+    // The cone's radius is 0.5 half way up
+    let mut ray_hit = RayHit::new(Ray::new(Vector3::new(0.0, 1.0, 5.0), dir));
+    rtscene.intersect(&mut ctx, &mut ray_hit);
+    assert_eq!(ray_hit.hit.geomID, cone_id);
+    assert!((ray_hit.ray.tfar - 4.5).abs() < 1e-4);
+    assert!(ray_hit.hit.Ng_z > 0.0);
+
+    // The filter rejects the front of the capsules
+    let mut ray_hit = RayHit::new(Ray::new(Vector3::new(3.0, 1.0, 5.0), dir));
+    rtscene.intersect(&mut ctx, &mut ray_hit);
+    assert_eq!(ray_hit.hit.geomID, capsule_id);
+    assert_eq!(ray_hit.hit.primID, 0);
+    assert!((ray_hit.ray.tfar - 5.5).abs() < 1e-4);
+    assert!(ray_hit.hit.Ng_z < 0.0);
+    let mut ray_hit = RayHit::new(Ray::new(Vector3::new(6.0, 1.0, 5.0), dir));
+    rtscene.intersect(&mut ctx, &mut ray_hit);
+    assert_eq!(ray_hit.hit.primID, 1);
+    assert!((ray_hit.ray.tfar - 5.5).abs() < 1e-4);
+
+    // Occlusion stops at the first hit in the ray's interval
+    let blocked = Ray::new(Vector3::new(0.0, 0.5, 5.0), dir);
+    assert!(rtscene.is_occluded(&blocked));
+    let short = Ray::segment(Vector3::new(0.0, 0.5, 5.0), dir, 0.0, 3.0);
+    assert!(!rtscene.is_occluded(&short));
+    let missed = Ray::new(Vector3::new(4.5, 1.0, 5.0), dir);
+    assert!(!rtscene.is_occluded(&missed));
+    assert!(rtscene.intersect_ray(&missed).is_none());
+}
+
+/// A square in the z = 0 plane facing +z, reporting where it was hit as
+/// its u and v
+struct Square {
+    min: [f32; 2],
+    size: f32,
+}
+
+impl UserPrimitive for Square {
+    fn bounds(&self) -> Bounds {
+        Bounds {
+            lower_x: self.min[0],
+            lower_y: self.min[1],
+            lower_z: 0.0,
+            align0: 0.0,
+            upper_x: self.min[0] + self.size,
+            upper_y: self.min[1] + self.size,
+            upper_z: 0.0,
+            align1: 0.0,
+        }
+    }
+    fn intersect(&self, ray: &Ray) -> Option<PrimitiveHit> {
+        if ray.dir_z == 0.0 {
+            return None;
+        }
+        let t = -ray.org_z / ray.dir_z;
+        let p = ray.origin() + ray.dir() * t;
+        let u = (p.x - self.min[0]) / self.size;
+        let v = (p.y - self.min[1]) / self.size;
+        if !(0.0..=1.0).contains(&u) || !(0.0..=1.0).contains(&v) {
+            return None;
+        }
+        Some(PrimitiveHit::new(t, Vector3::new(0.0, 0.0, 1.0)).with_uv(u, v))
+    }
+}
+
+#[test]
+fn intersect_user_primitives() {
+    let device = Device::new();
+    let squares = vec![
+        Square {
+            min: [0.0, 0.0],
+            size: 1.0,
+        },
+        Square {
+            min: [2.0, 0.0],
+            size: 2.0,
+        },
+    ];
+    let mut geom = Geometry::User(UserGeometry::from_primitives(&device, squares));
+    assert_eq!(geom.as_kind::<UserGeometry>().unwrap().len(), 2);
+    assert_eq!(
+        geom.as_kind::<UserGeometry>().unwrap().bounds(1).upper_x,
+        4.0
+    );
+    geom.commit();
+    let mut scene = Scene::new(&device);
+    let id = scene.attach_geometry(geom);
+    let rtscene = scene.commit();
+
+    let dir = Vector3::new(0.0, 0.0, -1.0);
+    let hit = rtscene
+        .intersect_ray(&Ray::new(Vector3::new(3.0, 0.5, 2.0), dir))
+        .unwrap();
+    assert_eq!((hit.hit.geomID, hit.hit.primID), (id, 1));
+    assert!((hit.ray.tfar - 2.0).abs() < 1e-4);
+    assert!((hit.hit.u - 0.5).abs() < 1e-4 && (hit.hit.v - 0.25).abs() < 1e-4);
+    assert_eq!(hit.hit.Ng_z, 1.0);
+
+    assert!(rtscene.is_occluded(&Ray::new(Vector3::new(0.5, 0.5, 1.0), dir)));
+    let short = Ray::segment(Vector3::new(0.5, 0.5, 1.0), dir, 0.0, 0.5);
+    assert!(!rtscene.is_occluded(&short));
+    assert!(rtscene
+        .intersect_ray(&Ray::new(Vector3::new(1.5, 0.5, 1.0), dir))
+        .is_none());
+}
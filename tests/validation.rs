@@ -0,0 +1,120 @@
+extern crate cgmath;
+extern crate embree;
+
+mod common;
+
+use cgmath::Vector3;
+#[cfg(feature = "curves")]
+use embree::BezierCurve;
+#[cfg(feature = "subdivision")]
+use embree::SubdivisionMesh;
+use embree::{BufferType, Device, Geometry, Instance, Scene, TriangleMesh, ValidationError};
+
+fn make_triangle(device: &Device, index: u32) -> Geometry<'_> {
+    let mut tris = common::triangle_mesh(device, common::UNIT_TRIANGLE);
+    tris.index_buffer.map()[0] = Vector3::new(0, 1, index);
+    Geometry::Triangle(tris)
+}
+
+#[test]
+fn required_buffers() {
+    assert_eq!(
+        TriangleMesh::REQUIRED,
+        &[BufferType::VERTEX, BufferType::INDEX]
+    );
+    let device = Device::new();
+    let geom = make_triangle(&device, 2);
+    assert_eq!(geom.required_buffers(), TriangleMesh::REQUIRED);
+    assert_eq!(geom.optional_buffers(), TriangleMesh::OPTIONAL);
+}
+
+#[test]
+fn triangle_index_out_of_bounds() {
+    let device = Device::new();
+    assert_eq!(make_triangle(&device, 2).validate(), Ok(()));
+    assert_eq!(
+        make_triangle(&device, 3).validate(),
+        Err(ValidationError::IndexOutOfBounds {
+            primitive: 0,
+            index: 3,
+            num_verts: 3,
+        })
+    );
+}
+
+#[test]
+#[cfg(feature = "curves")]
+fn curve_segment_out_of_bounds() {
+    let device = Device::new();
+    let mut curve = BezierCurve::flat(&device, 2, 7, false);
+    {
+        let mut indices = curve.index_buffer.map();
+        indices[0] = 0;
+        indices[1] = 4;
+    }
+    assert_eq!(
+        Geometry::BezierCurve(curve).validate(),
+        Err(ValidationError::IndexOutOfBounds {
+            primitive: 1,
+            index: 7,
+            num_verts: 7,
+        })
+    );
+}
+
+#[test]
+#[cfg(feature = "subdivision")]
+fn subdivision_faces() {
+    let device = Device::new();
+    let mut mesh = SubdivisionMesh::unanimated(&device, 2, 6, 4);
+    {
+        let mut faces = mesh.face_buffer.map();
+        faces[0] = 4;
+        faces[1] = 2;
+    }
+    assert_eq!(
+        Geometry::Subdivision(mesh).validate(),
+        Err(ValidationError::DegenerateFace {
+            face: 1,
+            num_verts: 2,
+        })
+    );
+
+    let mut mesh = SubdivisionMesh::unanimated(&device, 1, 6, 4);
+    mesh.face_buffer.map()[0] = 4;
+    assert_eq!(
+        Geometry::Subdivision(mesh).validate(),
+        Err(ValidationError::BufferSize {
+            buf_type: BufferType::INDEX,
+            slot: 0,
+            expected: 4,
+            actual: 6,
+        })
+    );
+}
+
+#[test]
+fn nested_instance_depth() {
+    let device = Device::new();
+    let mut inner = Scene::new(&device);
+    let mut tri = make_triangle(&device, 2);
+    tri.commit();
+    inner.attach_geometry(tri);
+    let inner = inner.commit();
+
+    let mut middle = Scene::new(&device);
+    let mut instance = Geometry::Instance(Instance::unanimated(&device, &inner));
+    instance.commit();
+    middle.try_attach_geometry(instance).unwrap();
+    assert_eq!(middle.validate(), Ok(()));
+    let middle = middle.commit();
+
+    let mut outer = Scene::new(&device);
+    let mut instance = Geometry::Instance(Instance::unanimated(&device, &middle));
+    instance.commit();
+    outer.attach_geometry(instance);
+    assert_eq!(
+        outer.validate(),
+        Err(ValidationError::InstanceDepth { depth: 2, max: 1 })
+    );
+}